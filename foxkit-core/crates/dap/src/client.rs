@@ -51,7 +51,7 @@ impl DapClient {
             supports_variable_type: true,
             supports_variable_paging: false,
             supports_run_in_terminal_request: true,
-            supports_memory_references: false,
+            supports_memory_references: true,
             supports_progress_reporting: true,
             supports_invalidated_event: true,
         };
@@ -106,23 +106,42 @@ impl DapClient {
     }
 
     /// Step over (next)
-    pub async fn next(&self, thread_id: i64) -> Result<()> {
+    pub async fn next(&self, thread_id: i64, granularity: Option<SteppingGranularity>) -> Result<()> {
+        let _args = self.step_arguments(thread_id, granularity);
+        // TODO: Send next request
         *self.state.write() = DebugState::Running;
         Ok(())
     }
 
     /// Step into
-    pub async fn step_in(&self, thread_id: i64) -> Result<()> {
+    pub async fn step_in(&self, thread_id: i64, granularity: Option<SteppingGranularity>) -> Result<()> {
+        let _args = self.step_arguments(thread_id, granularity);
+        // TODO: Send stepIn request
         *self.state.write() = DebugState::Running;
         Ok(())
     }
 
     /// Step out
-    pub async fn step_out(&self, thread_id: i64) -> Result<()> {
+    pub async fn step_out(&self, thread_id: i64, granularity: Option<SteppingGranularity>) -> Result<()> {
+        let _args = self.step_arguments(thread_id, granularity);
+        // TODO: Send stepOut request
         *self.state.write() = DebugState::Running;
         Ok(())
     }
 
+    /// Build step arguments, dropping the granularity if the adapter can't honour it
+    fn step_arguments(&self, thread_id: i64, granularity: Option<SteppingGranularity>) -> StepArguments {
+        let supported = self.capabilities.read()
+            .as_ref()
+            .is_some_and(|c| c.supports_stepping_granularity);
+
+        StepArguments {
+            thread_id,
+            single_thread: false,
+            granularity: granularity.filter(|_| supported),
+        }
+    }
+
     /// Set breakpoints
     pub async fn set_breakpoints(&self, source: Source, breakpoints: Vec<SourceBreakpoint>) -> Result<Vec<Breakpoint>> {
         // TODO: Send actual request
@@ -167,9 +186,83 @@ impl DapClient {
     }
 
     /// Evaluate expression
-    pub async fn evaluate(&self, expression: &str, frame_id: Option<i64>, context: Option<&str>) -> Result<String> {
+    pub async fn evaluate(&self, expression: &str, frame_id: Option<i64>, context: Option<&str>) -> Result<EvaluateResponseBody> {
+        let _args = EvaluateArguments {
+            expression: expression.to_string(),
+            frame_id,
+            context: context.map(String::from),
+        };
+
+        // TODO: Send actual request
+        Ok(EvaluateResponseBody::default())
+    }
+
+    /// Assign a value to an assignable expression (e.g. a watch)
+    pub async fn set_expression(&self, expression: &str, value: &str, frame_id: Option<i64>) -> Result<SetExpressionResponseBody> {
+        let supported = self.capabilities.read()
+            .as_ref()
+            .is_some_and(|c| c.supports_set_expression);
+        if !supported {
+            anyhow::bail!("Debug adapter does not support setExpression");
+        }
+
+        let _args = SetExpressionArguments {
+            expression: expression.to_string(),
+            value: value.to_string(),
+            frame_id,
+        };
+
+        // TODO: Send actual request
+        Ok(SetExpressionResponseBody {
+            value: value.to_string(),
+            ..Default::default()
+        })
+    }
+
+    /// Disassemble instructions around a memory reference
+    pub async fn disassemble(
+        &self,
+        memory_reference: &str,
+        instruction_offset: i64,
+        instruction_count: i64,
+    ) -> Result<Vec<DisassembledInstruction>> {
+        let _args = DisassembleArguments {
+            memory_reference: memory_reference.to_string(),
+            offset: None,
+            instruction_offset: Some(instruction_offset),
+            instruction_count,
+            resolve_symbols: true,
+        };
+
+        // TODO: Send actual request
+        Ok(vec![])
+    }
+
+    /// Read raw memory
+    pub async fn read_memory(&self, memory_reference: &str, offset: i64, count: i64) -> Result<ReadMemoryResponseBody> {
+        let _args = ReadMemoryArguments {
+            memory_reference: memory_reference.to_string(),
+            offset: Some(offset),
+            count,
+        };
+
+        // TODO: Send actual request
+        Ok(ReadMemoryResponseBody {
+            address: memory_reference.to_string(),
+            unreadable_bytes: Some(count),
+            data: None,
+        })
+    }
+
+    /// Write raw memory
+    pub async fn write_memory(&self, memory_reference: &str, offset: i64, data: &[u8]) -> Result<WriteMemoryResponseBody> {
+        let _args = WriteMemoryArguments::new(memory_reference, Some(offset), data);
+
         // TODO: Send actual request
-        Ok(String::new())
+        Ok(WriteMemoryResponseBody {
+            offset: Some(offset),
+            bytes_written: Some(data.len() as i64),
+        })
     }
 
     /// Get state
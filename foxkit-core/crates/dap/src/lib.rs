@@ -5,6 +5,7 @@
 pub mod adapter;
 pub mod client;
 pub mod protocol;
+pub mod recording;
 pub mod session;
 
 use std::sync::Arc;
@@ -16,6 +17,8 @@ use serde::{Deserialize, Serialize};
 
 pub use adapter::DebugAdapter;
 pub use client::DapClient;
+pub use protocol::{DisassembledInstruction, SteppingGranularity};
+pub use recording::{RecordedStop, Recording, RecordingOptions};
 pub use session::DebugSession;
 
 /// Debug adapter configuration
@@ -167,6 +170,9 @@ pub struct StackFrame {
     pub column: i64,
     /// Module ID
     pub module_id: Option<serde_json::Value>,
+    /// Memory reference of the current instruction pointer
+    #[serde(default)]
+    pub instruction_pointer_reference: Option<String>,
 }
 
 /// Thread
@@ -195,6 +201,9 @@ pub struct Variable {
     pub named_variables: Option<i64>,
     /// Indexed variables count
     pub indexed_variables: Option<i64>,
+    /// Memory reference (for the memory inspector)
+    #[serde(default)]
+    pub memory_reference: Option<String>,
 }
 
 /// Scope
@@ -248,7 +257,8 @@ pub enum DebugEvent {
 }
 
 /// Stop reason
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub enum StopReason {
     Breakpoint,
     Step,
@@ -1,5 +1,7 @@
 //! DAP protocol types
 
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -188,3 +190,198 @@ pub struct BreakpointEventBody {
     pub reason: String,
     pub breakpoint: crate::Breakpoint,
 }
+
+/// Stepping granularity
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SteppingGranularity {
+    #[default]
+    Statement,
+    Line,
+    Instruction,
+}
+
+/// Arguments shared by next, stepIn and stepOut
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StepArguments {
+    pub thread_id: i64,
+    #[serde(default)]
+    pub single_thread: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub granularity: Option<SteppingGranularity>,
+}
+
+/// Disassemble arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisassembleArguments {
+    pub memory_reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub instruction_offset: Option<i64>,
+    pub instruction_count: i64,
+    #[serde(default)]
+    pub resolve_symbols: bool,
+}
+
+/// Disassembled instruction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DisassembledInstruction {
+    pub address: String,
+    #[serde(default)]
+    pub instruction_bytes: Option<String>,
+    pub instruction: String,
+    #[serde(default)]
+    pub symbol: Option<String>,
+    #[serde(default)]
+    pub location: Option<crate::Source>,
+    #[serde(default)]
+    pub line: Option<i64>,
+    #[serde(default)]
+    pub column: Option<i64>,
+    #[serde(default)]
+    pub end_line: Option<i64>,
+    #[serde(default)]
+    pub end_column: Option<i64>,
+}
+
+impl DisassembledInstruction {
+    /// Parse the instruction address (`0x`-prefixed hex or decimal)
+    pub fn address_value(&self) -> Option<u64> {
+        parse_address(&self.address)
+    }
+}
+
+/// Read memory arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadMemoryArguments {
+    pub memory_reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    pub count: i64,
+}
+
+/// Read memory response body
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadMemoryResponseBody {
+    pub address: String,
+    #[serde(default)]
+    pub unreadable_bytes: Option<i64>,
+    /// Base64-encoded bytes
+    #[serde(default)]
+    pub data: Option<String>,
+}
+
+impl ReadMemoryResponseBody {
+    /// Decode the returned bytes
+    pub fn bytes(&self) -> anyhow::Result<Vec<u8>> {
+        match &self.data {
+            Some(data) => Ok(BASE64.decode(data)?),
+            None => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Write memory arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteMemoryArguments {
+    pub memory_reference: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub allow_partial: bool,
+    /// Base64-encoded bytes
+    pub data: String,
+}
+
+impl WriteMemoryArguments {
+    pub fn new(memory_reference: &str, offset: Option<i64>, bytes: &[u8]) -> Self {
+        Self {
+            memory_reference: memory_reference.to_string(),
+            offset,
+            allow_partial: false,
+            data: BASE64.encode(bytes),
+        }
+    }
+}
+
+/// Write memory response body
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WriteMemoryResponseBody {
+    #[serde(default)]
+    pub offset: Option<i64>,
+    #[serde(default)]
+    pub bytes_written: Option<i64>,
+}
+
+/// Evaluate arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateArguments {
+    pub expression: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<i64>,
+    /// "watch", "repl", "hover", "clipboard" or "variables"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub context: Option<String>,
+}
+
+/// Evaluate response body
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EvaluateResponseBody {
+    pub result: String,
+    #[serde(default, rename = "type")]
+    pub result_type: Option<String>,
+    #[serde(default)]
+    pub variables_reference: i64,
+    #[serde(default)]
+    pub named_variables: Option<i64>,
+    #[serde(default)]
+    pub indexed_variables: Option<i64>,
+    #[serde(default)]
+    pub memory_reference: Option<String>,
+}
+
+/// Set expression arguments
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetExpressionArguments {
+    pub expression: String,
+    pub value: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frame_id: Option<i64>,
+}
+
+/// Set expression response body
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetExpressionResponseBody {
+    pub value: String,
+    #[serde(default, rename = "type")]
+    pub value_type: Option<String>,
+    #[serde(default)]
+    pub variables_reference: i64,
+    #[serde(default)]
+    pub named_variables: Option<i64>,
+    #[serde(default)]
+    pub indexed_variables: Option<i64>,
+    #[serde(default)]
+    pub memory_reference: Option<String>,
+}
+
+/// Parse a memory reference or address (`0x`-prefixed hex or decimal)
+pub fn parse_address(address: &str) -> Option<u64> {
+    let address = address.trim();
+    match address.strip_prefix("0x").or_else(|| address.strip_prefix("0X")) {
+        Some(hex) => u64::from_str_radix(hex, 16).ok(),
+        None => address.parse().ok(),
+    }
+}
@@ -0,0 +1,241 @@
+//! Debug session recording
+//!
+//! Captures every stop together with the stack trace, scopes and variables
+//! fetched for it, so stops can be scrubbed through after the process exits.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{DapClient, Scope, StackFrame, StopReason, Variable};
+
+/// Recording options
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingOptions {
+    /// Maximum stack frames captured per stop
+    pub max_frames: i64,
+    /// How deep to expand structured variables
+    pub max_depth: usize,
+    /// Also capture scopes the adapter marks as expensive
+    pub include_expensive: bool,
+    /// Oldest stops are dropped beyond this many
+    pub max_stops: usize,
+}
+
+impl Default for RecordingOptions {
+    fn default() -> Self {
+        Self {
+            max_frames: 20,
+            max_depth: 2,
+            include_expensive: false,
+            max_stops: 1000,
+        }
+    }
+}
+
+/// A recorded stop event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordedStop {
+    /// Sequence number within the recording
+    pub index: usize,
+    /// Milliseconds since the Unix epoch
+    pub timestamp_ms: u64,
+    /// Thread that stopped
+    pub thread_id: i64,
+    /// Stop reason
+    pub reason: StopReason,
+    /// Stack trace, innermost frame first
+    pub frames: Vec<StackFrame>,
+    /// Scopes by frame ID
+    pub scopes: HashMap<i64, Vec<Scope>>,
+    /// Variables by variables reference
+    pub variables: HashMap<i64, Vec<Variable>>,
+}
+
+impl RecordedStop {
+    /// Innermost frame
+    pub fn top_frame(&self) -> Option<&StackFrame> {
+        self.frames.first()
+    }
+
+    /// Scopes captured for a frame
+    pub fn scopes_for(&self, frame_id: i64) -> &[Scope] {
+        self.scopes.get(&frame_id).map(Vec::as_slice).unwrap_or_default()
+    }
+
+    /// Variables captured for a reference
+    pub fn variables_for(&self, reference: i64) -> &[Variable] {
+        self.variables.get(&reference).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+/// A recording of stop events
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Recording {
+    /// Adapter type the recording was made with
+    pub adapter_type: String,
+    /// Recording options
+    pub options: RecordingOptions,
+    /// Recorded stops, oldest first
+    pub stops: VecDeque<RecordedStop>,
+    /// Stops recorded so far, including dropped ones
+    #[serde(default)]
+    next_index: usize,
+}
+
+impl Recording {
+    pub fn new(adapter_type: &str, options: RecordingOptions) -> Self {
+        Self {
+            adapter_type: adapter_type.to_string(),
+            options,
+            stops: VecDeque::new(),
+            next_index: 0,
+        }
+    }
+
+    /// Append a stop, dropping the oldest beyond `max_stops`
+    pub fn push(&mut self, mut stop: RecordedStop) {
+        stop.index = self.next_index;
+        self.next_index += 1;
+
+        if self.stops.len() >= self.options.max_stops.max(1) {
+            self.stops.pop_front();
+        }
+        self.stops.push_back(stop);
+    }
+
+    /// Number of recorded stops
+    pub fn len(&self) -> usize {
+        self.stops.len()
+    }
+
+    /// Is recording empty?
+    pub fn is_empty(&self) -> bool {
+        self.stops.is_empty()
+    }
+
+    /// Get stop by position
+    pub fn get(&self, position: usize) -> Option<&RecordedStop> {
+        self.stops.get(position)
+    }
+
+    /// Serialize to JSON
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Deserialize from JSON
+    pub fn from_json(json: &str) -> Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    /// Export to a file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Import from a file
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+}
+
+/// Fetch the stack trace, scopes and variables for a stop
+pub async fn capture_stop(
+    client: &DapClient,
+    thread_id: i64,
+    reason: StopReason,
+    options: &RecordingOptions,
+) -> Result<RecordedStop> {
+    let frames = client.stack_trace(thread_id, Some(0), Some(options.max_frames)).await?;
+    let mut scopes = HashMap::new();
+    let mut variables = HashMap::new();
+    let mut seen = HashSet::new();
+
+    for frame in &frames {
+        let frame_scopes = client.scopes(frame.id).await?;
+
+        // Breadth-first so `max_depth` limits how far structured values expand
+        let mut queue: VecDeque<(i64, usize)> = frame_scopes.iter()
+            .filter(|s| options.include_expensive || !s.expensive)
+            .map(|s| (s.variables_reference, 0))
+            .collect();
+
+        while let Some((reference, depth)) = queue.pop_front() {
+            if reference <= 0 || !seen.insert(reference) {
+                continue;
+            }
+
+            let children = client.variables(reference, None, None).await?;
+            if depth < options.max_depth {
+                queue.extend(children.iter().map(|v| (v.variables_reference, depth + 1)));
+            }
+            variables.insert(reference, children);
+        }
+
+        scopes.insert(frame.id, frame_scopes);
+    }
+
+    Ok(RecordedStop {
+        index: 0,
+        timestamp_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or(0),
+        thread_id,
+        reason,
+        frames,
+        scopes,
+        variables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stop(thread_id: i64) -> RecordedStop {
+        RecordedStop {
+            index: 0,
+            timestamp_ms: 0,
+            thread_id,
+            reason: StopReason::Breakpoint,
+            frames: vec![],
+            scopes: HashMap::new(),
+            variables: HashMap::from([(7, vec![])]),
+        }
+    }
+
+    #[test]
+    fn test_push_drops_oldest() {
+        let mut recording = Recording::new("lldb", RecordingOptions {
+            max_stops: 2,
+            ..Default::default()
+        });
+        recording.push(stop(1));
+        recording.push(stop(2));
+        recording.push(stop(3));
+
+        assert_eq!(recording.len(), 2);
+        assert_eq!(recording.get(0).unwrap().thread_id, 2);
+        assert_eq!(recording.get(1).unwrap().index, 2);
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let mut recording = Recording::new("node", RecordingOptions::default());
+        recording.push(stop(1));
+
+        let restored = Recording::from_json(&recording.to_json().unwrap()).unwrap();
+        assert_eq!(restored.adapter_type, "node");
+        assert_eq!(restored.len(), 1);
+        assert_eq!(restored.get(0).unwrap().reason, StopReason::Breakpoint);
+        assert!(restored.get(0).unwrap().variables_for(7).is_empty());
+    }
+}
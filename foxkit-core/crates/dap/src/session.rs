@@ -6,8 +6,9 @@ use tokio::sync::mpsc;
 use parking_lot::RwLock;
 use anyhow::Result;
 
-use crate::{AdapterConfig, DapClient, DebugEvent, DebugState, LaunchConfig, Breakpoint, Source};
+use crate::{AdapterConfig, DapClient, DebugEvent, DebugState, LaunchConfig, Breakpoint, Source, SteppingGranularity, StopReason};
 use crate::client::SourceBreakpoint;
+use crate::recording::{self, Recording, RecordingOptions};
 
 /// Debug session
 pub struct DebugSession {
@@ -19,12 +20,19 @@ pub struct DebugSession {
     breakpoints: RwLock<HashMap<String, Vec<Breakpoint>>>,
     /// Event receiver
     event_rx: mpsc::UnboundedReceiver<DebugEvent>,
+    /// Granularity used by the step commands
+    granularity: RwLock<SteppingGranularity>,
+    /// Adapter type, kept for recordings
+    adapter_type: String,
+    /// Active stop recording
+    recording: RwLock<Option<Recording>>,
 }
 
 impl DebugSession {
     /// Create new session
     pub fn new(id: u64, config: AdapterConfig) -> Self {
         let (event_tx, event_rx) = mpsc::unbounded_channel();
+        let adapter_type = config.adapter_type.clone();
         let client = Arc::new(DapClient::new(config, event_tx));
 
         Self {
@@ -32,6 +40,9 @@ impl DebugSession {
             client,
             breakpoints: RwLock::new(HashMap::new()),
             event_rx,
+            granularity: RwLock::new(SteppingGranularity::default()),
+            adapter_type,
+            recording: RwLock::new(None),
         }
     }
 
@@ -132,7 +143,7 @@ impl DebugSession {
     pub async fn step_over(&self) -> Result<()> {
         let threads = self.client.threads().await?;
         if let Some(thread) = threads.first() {
-            self.client.next(thread.id).await?;
+            self.client.next(thread.id, Some(self.granularity())).await?;
         }
         Ok(())
     }
@@ -141,7 +152,7 @@ impl DebugSession {
     pub async fn step_into(&self) -> Result<()> {
         let threads = self.client.threads().await?;
         if let Some(thread) = threads.first() {
-            self.client.step_in(thread.id).await?;
+            self.client.step_in(thread.id, Some(self.granularity())).await?;
         }
         Ok(())
     }
@@ -150,11 +161,30 @@ impl DebugSession {
     pub async fn step_out(&self) -> Result<()> {
         let threads = self.client.threads().await?;
         if let Some(thread) = threads.first() {
-            self.client.step_out(thread.id).await?;
+            self.client.step_out(thread.id, Some(self.granularity())).await?;
         }
         Ok(())
     }
 
+    /// Step by instruction instead of by statement (e.g. while the disassembly view is focused)
+    pub fn set_instruction_stepping(&self, enabled: bool) {
+        *self.granularity.write() = if enabled {
+            SteppingGranularity::Instruction
+        } else {
+            SteppingGranularity::Statement
+        };
+    }
+
+    /// Current stepping granularity
+    pub fn granularity(&self) -> SteppingGranularity {
+        *self.granularity.read()
+    }
+
+    /// DAP client
+    pub fn client(&self) -> &Arc<DapClient> {
+        &self.client
+    }
+
     /// Pause
     pub async fn pause(&self) -> Result<()> {
         let threads = self.client.threads().await?;
@@ -166,7 +196,7 @@ impl DebugSession {
 
     /// Evaluate expression
     pub async fn evaluate(&self, expression: &str) -> Result<String> {
-        self.client.evaluate(expression, None, Some("repl")).await
+        Ok(self.client.evaluate(expression, None, Some("repl")).await?.result)
     }
 
     /// Get state
@@ -174,9 +204,50 @@ impl DebugSession {
         self.client.state()
     }
 
-    /// Poll for events
+    /// Start recording stops
+    pub fn start_recording(&self, options: RecordingOptions) {
+        *self.recording.write() = Some(Recording::new(&self.adapter_type, options));
+    }
+
+    /// Stop recording and return what was recorded
+    pub fn stop_recording(&self) -> Option<Recording> {
+        self.recording.write().take()
+    }
+
+    /// Is a recording active?
+    pub fn is_recording(&self) -> bool {
+        self.recording.read().is_some()
+    }
+
+    /// Snapshot of the active recording
+    pub fn recording(&self) -> Option<Recording> {
+        self.recording.read().clone()
+    }
+
+    /// Capture the current stop into the active recording
+    pub async fn record_stop(&self, thread_id: i64, reason: StopReason) -> Result<()> {
+        let Some(options) = self.recording.read().as_ref().map(|r| r.options.clone()) else {
+            return Ok(());
+        };
+
+        let stop = recording::capture_stop(&self.client, thread_id, reason, &options).await?;
+        if let Some(recording) = self.recording.write().as_mut() {
+            recording.push(stop);
+        }
+        Ok(())
+    }
+
+    /// Poll for events, recording stops while a recording is active
     pub async fn next_event(&mut self) -> Option<DebugEvent> {
-        self.event_rx.recv().await
+        let event = self.event_rx.recv().await;
+
+        if let Some(DebugEvent::Stopped { reason, thread_id, .. }) = &event
+            && let Err(err) = self.record_stop(*thread_id, *reason).await
+        {
+            tracing::warn!("Failed to record stop: {}", err);
+        }
+
+        event
     }
 }
 
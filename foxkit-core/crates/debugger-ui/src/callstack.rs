@@ -164,6 +164,33 @@ impl StackFrame {
     }
 }
 
+impl From<&dap::StackFrame> for StackFrame {
+    fn from(frame: &dap::StackFrame) -> Self {
+        Self {
+            id: frame.id,
+            name: frame.name.clone(),
+            source: frame.source.as_ref().map(|s| Source {
+                name: s.name.clone(),
+                path: s.path.clone(),
+                source_reference: s.source_reference,
+                presentation_hint: None,
+                origin: None,
+            }),
+            line: frame.line as u32,
+            column: frame.column as u32,
+            end_line: None,
+            end_column: None,
+            can_restart: None,
+            instruction_pointer_reference: frame.instruction_pointer_reference.clone(),
+            module_id: frame.module_id.as_ref().map(|m| match m {
+                serde_json::Value::String(s) => s.clone(),
+                other => other.to_string(),
+            }),
+            presentation_hint: None,
+        }
+    }
+}
+
 /// Source info
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Source {
@@ -0,0 +1,308 @@
+//! Disassembly view
+
+use std::collections::HashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::{DebugView, DebugViewId};
+
+/// Disassembly view
+pub struct DisassemblyView {
+    /// Instructions in address order
+    instructions: RwLock<Vec<Instruction>>,
+    /// Source text by path, used to interleave source lines
+    sources: RwLock<HashMap<String, Vec<String>>>,
+    /// Address of the current instruction pointer
+    current_address: RwLock<Option<u64>>,
+    /// Show source lines between instructions
+    show_source: bool,
+    /// Visibility
+    visible: bool,
+}
+
+impl DisassemblyView {
+    pub fn new() -> Self {
+        Self {
+            instructions: RwLock::new(Vec::new()),
+            sources: RwLock::new(HashMap::new()),
+            current_address: RwLock::new(None),
+            show_source: true,
+            visible: false,
+        }
+    }
+
+    /// Replace the disassembled instructions
+    pub fn set_instructions(&self, mut instructions: Vec<Instruction>) {
+        instructions.sort_by_key(|i| i.address);
+        *self.instructions.write() = instructions;
+    }
+
+    /// Merge instructions fetched while scrolling, keeping address order
+    pub fn extend_instructions(&self, more: Vec<Instruction>) {
+        let mut instructions = self.instructions.write();
+        for instruction in more {
+            match instructions.binary_search_by_key(&instruction.address, |i| i.address) {
+                Ok(pos) => instructions[pos] = instruction,
+                Err(pos) => instructions.insert(pos, instruction),
+            }
+        }
+    }
+
+    /// Get instructions
+    pub fn instructions(&self) -> Vec<Instruction> {
+        self.instructions.read().clone()
+    }
+
+    /// Provide source text for a file referenced by the instructions
+    pub fn set_source(&self, path: impl Into<String>, text: &str) {
+        let lines = text.lines().map(String::from).collect();
+        self.sources.write().insert(path.into(), lines);
+    }
+
+    /// Set the instruction pointer
+    pub fn set_current_address(&self, address: Option<u64>) {
+        *self.current_address.write() = address;
+    }
+
+    /// Get the instruction pointer
+    pub fn current_address(&self) -> Option<u64> {
+        *self.current_address.read()
+    }
+
+    /// Toggle interleaved source lines
+    pub fn set_show_source(&mut self, show: bool) {
+        self.show_source = show;
+    }
+
+    /// Lowest and highest loaded addresses
+    pub fn address_range(&self) -> Option<(u64, u64)> {
+        let instructions = self.instructions.read();
+        Some((instructions.first()?.address, instructions.last()?.address))
+    }
+
+    /// Build display lines, interleaving a source line whenever the
+    /// source location changes between instructions
+    pub fn lines(&self) -> Vec<DisassemblyLine> {
+        let instructions = self.instructions.read();
+        let sources = self.sources.read();
+        let current = *self.current_address.read();
+
+        let mut lines = Vec::with_capacity(instructions.len());
+        let mut last_location: Option<(&str, u32)> = None;
+
+        for instruction in instructions.iter() {
+            if let Some((path, line)) = instruction.location().filter(|_| self.show_source)
+                && last_location != Some((path, line))
+            {
+                let text = sources.get(path)
+                    .and_then(|l| l.get(line.saturating_sub(1) as usize))
+                    .cloned();
+                lines.push(DisassemblyLine::Source {
+                    path: path.to_string(),
+                    line,
+                    text,
+                });
+                last_location = Some((path, line));
+            }
+
+            lines.push(DisassemblyLine::Instruction {
+                address: instruction.address,
+                bytes: instruction.bytes.clone(),
+                text: instruction.text.clone(),
+                symbol: instruction.symbol.clone(),
+                is_current: current == Some(instruction.address),
+            });
+        }
+
+        lines
+    }
+
+    /// Index of the current instruction in `lines()`
+    pub fn current_line_index(&self) -> Option<usize> {
+        self.lines().iter().position(|l| matches!(l, DisassemblyLine::Instruction { is_current: true, .. }))
+    }
+
+    /// Clear instructions and cached sources
+    pub fn clear(&self) {
+        self.instructions.write().clear();
+        self.sources.write().clear();
+        *self.current_address.write() = None;
+    }
+}
+
+impl Default for DisassemblyView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugView for DisassemblyView {
+    fn id(&self) -> DebugViewId {
+        DebugViewId::Disassembly
+    }
+
+    fn title(&self) -> &str {
+        "Disassembly"
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn show(&mut self) {
+        self.visible = true;
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    fn refresh(&mut self) {
+        // Refresh from DAP
+    }
+
+    fn clear(&mut self) {
+        DisassemblyView::clear(self);
+    }
+}
+
+/// Disassembled instruction
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instruction {
+    /// Instruction address
+    pub address: u64,
+    /// Raw instruction bytes (hex)
+    pub bytes: Option<String>,
+    /// Instruction text
+    pub text: String,
+    /// Symbol the instruction belongs to
+    pub symbol: Option<String>,
+    /// Source path
+    pub path: Option<String>,
+    /// Source line
+    pub line: Option<u32>,
+}
+
+impl Instruction {
+    /// Source location, if known
+    pub fn location(&self) -> Option<(&str, u32)> {
+        Some((self.path.as_deref()?, self.line?))
+    }
+
+    /// Convert a DAP instruction. Per the protocol, an instruction without a
+    /// location shares the location of the previous one, so pass that along.
+    pub fn from_dap(
+        instruction: &dap::DisassembledInstruction,
+        previous: Option<&Instruction>,
+    ) -> Option<Self> {
+        let path = instruction.location.as_ref()
+            .and_then(|s| s.path.clone())
+            .or_else(|| previous.and_then(|p| p.path.clone()));
+        let line = instruction.line
+            .map(|l| l as u32)
+            .or_else(|| previous.and_then(|p| p.line));
+
+        Some(Self {
+            address: instruction.address_value()?,
+            bytes: instruction.instruction_bytes.clone(),
+            text: instruction.instruction.clone(),
+            symbol: instruction.symbol.clone(),
+            path,
+            line,
+        })
+    }
+}
+
+/// Convert a batch of DAP instructions, skipping ones with unparsable addresses
+pub fn instructions_from_dap(instructions: &[dap::DisassembledInstruction]) -> Vec<Instruction> {
+    let mut result: Vec<Instruction> = Vec::with_capacity(instructions.len());
+    for instruction in instructions {
+        if let Some(converted) = Instruction::from_dap(instruction, result.last()) {
+            result.push(converted);
+        }
+    }
+    result
+}
+
+/// Disassembly display line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisassemblyLine {
+    /// Interleaved source line
+    Source {
+        path: String,
+        line: u32,
+        text: Option<String>,
+    },
+    /// Machine instruction
+    Instruction {
+        address: u64,
+        bytes: Option<String>,
+        text: String,
+        symbol: Option<String>,
+        is_current: bool,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instruction(address: u64, text: &str, line: Option<u32>) -> Instruction {
+        Instruction {
+            address,
+            bytes: None,
+            text: text.to_string(),
+            symbol: Some("main".to_string()),
+            path: line.map(|_| "main.c".to_string()),
+            line,
+        }
+    }
+
+    #[test]
+    fn test_interleaves_source_lines() {
+        let view = DisassemblyView::new();
+        view.set_source("main.c", "int main() {\n  return 0;\n}");
+        view.set_instructions(vec![
+            instruction(0x1008, "ret", Some(2)),
+            instruction(0x1000, "push rbp", Some(1)),
+            instruction(0x1004, "xor eax, eax", Some(2)),
+        ]);
+        view.set_current_address(Some(0x1004));
+
+        let lines = view.lines();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0], DisassemblyLine::Source {
+            path: "main.c".to_string(),
+            line: 1,
+            text: Some("int main() {".to_string()),
+        });
+        assert!(matches!(lines[1], DisassemblyLine::Instruction { address: 0x1000, .. }));
+        assert!(matches!(&lines[2], DisassemblyLine::Source { line: 2, .. }));
+        assert!(matches!(lines[4], DisassemblyLine::Instruction { address: 0x1008, is_current: false, .. }));
+        assert_eq!(view.current_line_index(), Some(3));
+    }
+
+    #[test]
+    fn test_extend_keeps_address_order() {
+        let view = DisassemblyView::new();
+        view.set_instructions(vec![instruction(0x20, "nop", None)]);
+        view.extend_instructions(vec![instruction(0x10, "nop", None), instruction(0x30, "ret", None)]);
+
+        assert_eq!(view.address_range(), Some((0x10, 0x30)));
+        assert_eq!(view.lines().len(), 3);
+    }
+
+    #[test]
+    fn test_from_dap_inherits_location() {
+        let json = serde_json::json!([
+            { "address": "0x400", "instruction": "mov", "location": { "path": "a.rs" }, "line": 7 },
+            { "address": "0x404", "instruction": "add" },
+            { "address": "bogus", "instruction": "??" },
+        ]);
+        let dap: Vec<dap::DisassembledInstruction> = serde_json::from_value(json).unwrap();
+        let converted = instructions_from_dap(&dap);
+
+        assert_eq!(converted.len(), 2);
+        assert_eq!(converted[1].location(), Some(("a.rs", 7)));
+    }
+}
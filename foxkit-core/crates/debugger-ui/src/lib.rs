@@ -9,6 +9,9 @@ pub mod callstack;
 pub mod watch;
 pub mod console;
 pub mod toolbar;
+pub mod disassembly;
+pub mod memory;
+pub mod replay;
 
 use std::sync::Arc;
 use parking_lot::RwLock;
@@ -17,9 +20,12 @@ pub use views::{DebugView, DebugViewId};
 pub use breakpoints::BreakpointsView;
 pub use variables::VariablesView;
 pub use callstack::CallStackView;
-pub use watch::WatchView;
+pub use watch::{WatchListStore, WatchView};
 pub use console::DebugConsole;
 pub use toolbar::DebugToolbar;
+pub use disassembly::DisassemblyView;
+pub use memory::MemoryView;
+pub use replay::ReplayView;
 
 /// Debug UI service
 pub struct DebugUiService {
@@ -37,6 +43,14 @@ pub struct DebugUiService {
     console: DebugConsole,
     /// Toolbar state
     toolbar: DebugToolbar,
+    /// Disassembly view
+    disassembly: DisassemblyView,
+    /// Memory inspector
+    memory: MemoryView,
+    /// Recorded stop replay
+    replay: ReplayView,
+    /// Persisted watch lists
+    watch_store: RwLock<Option<WatchListStore>>,
     /// Event listeners
     listeners: RwLock<Vec<Box<dyn Fn(&DebugUiEvent) + Send + Sync>>>,
 }
@@ -51,12 +65,25 @@ impl DebugUiService {
             watch: WatchView::new(),
             console: DebugConsole::new(),
             toolbar: DebugToolbar::new(),
+            disassembly: DisassemblyView::new(),
+            memory: MemoryView::new(),
+            replay: ReplayView::new(),
+            watch_store: RwLock::new(None),
             listeners: RwLock::new(Vec::new()),
         }
     }
 
     /// Start a debug session
     pub fn start_session(&self, config: DebugConfiguration) -> anyhow::Result<()> {
+        // Without a stored list the watches already added are kept
+        if let Some(store) = self.watch_store.read().as_ref() {
+            match store.load(&config.name) {
+                Ok(Some(watches)) => self.watch.restore(watches),
+                Ok(None) => {}
+                Err(err) => tracing::warn!("Failed to load watch expressions: {}", err),
+            }
+        }
+
         let session = DebugSession::new(config);
         *self.session.write() = Some(session);
         
@@ -68,23 +95,43 @@ impl DebugUiService {
 
     /// Stop the debug session
     pub fn stop_session(&self) {
-        if self.session.write().take().is_some() {
+        if let Some(session) = self.session.write().take() {
+            if let Some(store) = self.watch_store.read().as_ref()
+                && let Err(err) = store.save(&session.config.name, self.watch.persisted())
+            {
+                tracing::warn!("Failed to save watch expressions: {}", err);
+            }
+
             self.toolbar.set_state(ToolbarState::Stopped);
             self.variables.clear();
             self.callstack.clear();
+            self.disassembly.clear();
+            self.memory.clear();
             self.emit(DebugUiEvent::SessionStopped);
         }
     }
 
-    /// Handle debugger stopped event
-    pub fn on_stopped(&self, reason: StopReason, location: Option<SourceLocation>) {
+    /// Handle debugger stopped event, re-evaluating watches in the
+    /// stopped frame
+    pub async fn on_stopped(
+        &self,
+        client: &dap::DapClient,
+        reason: StopReason,
+        location: Option<SourceLocation>,
+        frame_id: Option<i64>,
+    ) {
         self.toolbar.set_state(ToolbarState::Paused);
-        
+        if let Some(session) = self.session.write().as_mut() {
+            session.frame_id = frame_id;
+            session.state = SessionState::Paused;
+        }
+
         if let Some(loc) = &location {
             self.emit(DebugUiEvent::Navigate(loc.clone()));
         }
-        
+
         self.emit(DebugUiEvent::Stopped(reason));
+        self.refresh_watches(client, frame_id).await;
     }
 
     /// Handle debugger continued event
@@ -113,6 +160,17 @@ impl DebugUiService {
         &self.watch
     }
 
+    /// Persist watch lists per launch configuration in the given file
+    pub fn set_watch_store(&self, path: impl Into<std::path::PathBuf>) {
+        *self.watch_store.write() = Some(WatchListStore::new(path));
+    }
+
+    /// Re-evaluate watch expressions; call on every stop
+    pub async fn refresh_watches(&self, client: &dap::DapClient, frame_id: Option<i64>) {
+        self.watch.refresh_from(client, frame_id, false).await;
+        self.emit(DebugUiEvent::WatchesUpdated);
+    }
+
     /// Get console
     pub fn console(&self) -> &DebugConsole {
         &self.console
@@ -123,6 +181,74 @@ impl DebugUiService {
         &self.toolbar
     }
 
+    /// Get disassembly view
+    pub fn disassembly(&self) -> &DisassemblyView {
+        &self.disassembly
+    }
+
+    /// Get memory view
+    pub fn memory(&self) -> &MemoryView {
+        &self.memory
+    }
+
+    /// Get replay view
+    pub fn replay(&self) -> &ReplayView {
+        &self.replay
+    }
+
+    /// Load a recording for replay (works after the session has ended)
+    pub fn load_recording(&self, recording: dap::Recording) {
+        self.replay.load(recording);
+        self.show_recorded_stop(self.replay.position());
+    }
+
+    /// Step back through the recorded stops
+    pub fn replay_back(&self) -> bool {
+        match self.replay.position().checked_sub(1) {
+            Some(position) => self.show_recorded_stop(position),
+            None => false,
+        }
+    }
+
+    /// Step forward through the recorded stops
+    pub fn replay_forward(&self) -> bool {
+        !self.replay.is_at_latest() && self.show_recorded_stop(self.replay.position() + 1)
+    }
+
+    /// Show a recorded stop in the call stack and variables views
+    pub fn show_recorded_stop(&self, position: usize) -> bool {
+        let Some(stop) = self.replay.seek(position) else {
+            return false;
+        };
+
+        self.callstack.set_threads(vec![replay::recorded_thread(&stop)]);
+        self.callstack.select_thread(stop.thread_id);
+        if let Some(frame) = stop.top_frame() {
+            self.callstack.select_frame(frame.id);
+        }
+        self.variables.set_scopes(replay::recorded_scopes(&stop));
+
+        if let Some(location) = replay::recorded_location(&stop) {
+            self.emit(DebugUiEvent::Navigate(location));
+        }
+        self.emit(DebugUiEvent::ReplayPositionChanged(self.replay.position()));
+        self.emit(DebugUiEvent::CallStackUpdated);
+        self.emit(DebugUiEvent::VariablesUpdated);
+        true
+    }
+
+    /// Open the memory inspector for a variable; returns false if the
+    /// variable has no memory reference
+    pub fn inspect_memory(&self, variable: &variables::Variable) -> bool {
+        let Some(reference) = variable.memory_reference.clone() else {
+            return false;
+        };
+
+        self.memory.open(reference.clone());
+        self.emit(DebugUiEvent::MemoryRequested(reference));
+        true
+    }
+
     /// Subscribe to events
     pub fn subscribe<F>(&self, callback: F)
     where
@@ -214,8 +340,13 @@ pub enum DebugUiEvent {
     BreakpointHit(i64),
     Navigate(SourceLocation),
     VariablesUpdated,
+    WatchesUpdated,
     CallStackUpdated,
     OutputReceived(String),
+    /// Memory inspector opened; the memory needs to be read
+    MemoryRequested(String),
+    /// A recorded stop is being shown
+    ReplayPositionChanged(usize),
 }
 
 /// Stop reason
@@ -246,3 +377,46 @@ pub enum ToolbarState {
     Running,
     Paused,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn configuration(name: &str) -> DebugConfiguration {
+        DebugConfiguration {
+            name: name.to_string(),
+            debug_type: "lldb".to_string(),
+            request: RequestType::Launch,
+            program: None,
+            args: Vec::new(),
+            env: Default::default(),
+            cwd: None,
+            extra: serde_json::Value::Null,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_watches_across_sessions() {
+        let path = std::env::temp_dir().join(format!("foxkit-session-watches-{}.json", std::process::id()));
+        let service = DebugUiService::new();
+        service.set_watch_store(&path);
+
+        // Nothing stored yet: watches added before the session stay
+        service.watch().add("count".to_string());
+        service.start_session(configuration("Debug app")).unwrap();
+        assert_eq!(service.watch().expressions().len(), 1);
+
+        let (event_tx, _events) = tokio::sync::mpsc::unbounded_channel();
+        let client = dap::DapClient::new(dap::AdapterConfig::new("lldb", "LLDB", "lldb-dap"), event_tx);
+        service.on_stopped(&client, StopReason::Breakpoint, None, Some(1)).await;
+        assert!(service.watch().expressions()[0].result.is_some());
+        assert_eq!(service.session.read().as_ref().unwrap().frame_id, Some(1));
+
+        service.stop_session();
+        service.watch().clear_all();
+        service.start_session(configuration("Debug app")).unwrap();
+        assert_eq!(service.watch().expressions()[0].expression, "count");
+
+        std::fs::remove_file(path).ok();
+    }
+}
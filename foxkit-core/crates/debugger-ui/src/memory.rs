@@ -0,0 +1,340 @@
+//! Memory inspector (hex viewer)
+
+use std::collections::BTreeMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::{DebugView, DebugViewId};
+
+/// Memory view
+pub struct MemoryView {
+    /// Memory reference being inspected
+    memory_reference: RwLock<Option<String>>,
+    /// Address of the first loaded byte
+    base_address: RwLock<u64>,
+    /// Loaded bytes (`None` for unreadable bytes)
+    data: RwLock<Vec<Option<u8>>>,
+    /// Edited bytes by address, not yet written; kept when the window moves
+    pending: RwLock<BTreeMap<u64, u8>>,
+    /// Bytes shown per row
+    bytes_per_row: usize,
+    /// Visibility
+    visible: bool,
+}
+
+impl MemoryView {
+    pub fn new() -> Self {
+        Self {
+            memory_reference: RwLock::new(None),
+            base_address: RwLock::new(0),
+            data: RwLock::new(Vec::new()),
+            pending: RwLock::new(BTreeMap::new()),
+            bytes_per_row: 16,
+            visible: false,
+        }
+    }
+
+    /// Inspect a new memory reference (e.g. a variable's `memoryReference`)
+    pub fn open(&self, memory_reference: impl Into<String>) {
+        let memory_reference = memory_reference.into();
+        *self.base_address.write() = dap::protocol::parse_address(&memory_reference).unwrap_or(0);
+        *self.memory_reference.write() = Some(memory_reference);
+        self.data.write().clear();
+        self.pending.write().clear();
+    }
+
+    /// Memory reference being inspected
+    pub fn memory_reference(&self) -> Option<String> {
+        self.memory_reference.read().clone()
+    }
+
+    /// Set loaded contents; `unreadable` bytes after `data` are shown as `??`
+    ///
+    /// Pending edits stay at their address, so scrolling the window doesn't
+    /// lose them.
+    pub fn set_contents(&self, address: u64, data: Vec<u8>, unreadable: usize) {
+        *self.base_address.write() = address;
+        let mut bytes: Vec<_> = data.into_iter().map(Some).collect();
+        bytes.extend(std::iter::repeat_n(None, unreadable));
+        *self.data.write() = bytes;
+    }
+
+    /// Set loaded contents from a DAP `readMemory` response
+    pub fn set_read_response(&self, response: &dap::protocol::ReadMemoryResponseBody) -> anyhow::Result<()> {
+        let address = dap::protocol::parse_address(&response.address)
+            .ok_or_else(|| anyhow::anyhow!("Invalid address: {}", response.address))?;
+        let unreadable = response.unreadable_bytes.unwrap_or(0).max(0) as usize;
+        self.set_contents(address, response.bytes()?, unreadable);
+        Ok(())
+    }
+
+    /// Set bytes per row
+    pub fn set_bytes_per_row(&mut self, bytes_per_row: usize) {
+        self.bytes_per_row = bytes_per_row.max(1);
+    }
+
+    /// Address of the first loaded byte
+    pub fn base_address(&self) -> u64 {
+        *self.base_address.read()
+    }
+
+    /// Address of the byte at offset, or `None` past the end of the address
+    /// space
+    fn address(&self, offset: usize) -> Option<u64> {
+        self.base_address.read().checked_add(offset as u64)
+    }
+
+    /// Byte at offset, including pending edits
+    pub fn byte(&self, offset: usize) -> Option<u8> {
+        let address = self.address(offset)?;
+        if let Some(&value) = self.pending.read().get(&address) {
+            return Some(value);
+        }
+        self.data.read().get(offset).copied().flatten()
+    }
+
+    /// Edit a byte; returns false if the byte isn't readable
+    pub fn edit_byte(&self, offset: usize, value: u8) -> bool {
+        let Some(address) = self.address(offset) else {
+            return false;
+        };
+        match self.data.read().get(offset) {
+            Some(Some(original)) => {
+                let mut pending = self.pending.write();
+                if *original == value {
+                    pending.remove(&address);
+                } else {
+                    pending.insert(address, value);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Has unwritten edits?
+    pub fn is_dirty(&self) -> bool {
+        !self.pending.read().is_empty()
+    }
+
+    /// Pending edits coalesced into contiguous `(offset, bytes)` writes
+    ///
+    /// Offsets are from the start of the current window, negative for edits
+    /// made before scrolling past them.
+    pub fn pending_writes(&self) -> Vec<(i64, Vec<u8>)> {
+        let base = *self.base_address.read();
+        let mut writes: Vec<(u64, Vec<u8>)> = Vec::new();
+        for (&address, &value) in self.pending.read().iter() {
+            match writes.last_mut() {
+                Some((start, bytes)) if start.checked_add(bytes.len() as u64) == Some(address) => bytes.push(value),
+                _ => writes.push((address, vec![value])),
+            }
+        }
+        writes.into_iter()
+            .map(|(address, bytes)| (address.wrapping_sub(base) as i64, bytes))
+            .collect()
+    }
+
+    /// Apply pending edits after they were written to the debuggee
+    pub fn commit_writes(&self) {
+        let base = *self.base_address.read();
+        let pending = std::mem::take(&mut *self.pending.write());
+        let mut data = self.data.write();
+        for (address, value) in pending {
+            let Some(offset) = address.checked_sub(base) else {
+                continue;
+            };
+            if let Some(byte) = usize::try_from(offset).ok().and_then(|offset| data.get_mut(offset)) {
+                *byte = Some(value);
+            }
+        }
+    }
+
+    /// Discard pending edits
+    pub fn revert(&self) {
+        self.pending.write().clear();
+    }
+
+    /// Rows for display
+    pub fn rows(&self) -> Vec<MemoryRow> {
+        let base = *self.base_address.read();
+        let data = self.data.read();
+        let pending = self.pending.read();
+
+        data.chunks(self.bytes_per_row)
+            .enumerate()
+            // Rows past the end of the address space can't be shown
+            .map_while(|(row, chunk)| {
+                let start = row * self.bytes_per_row;
+                let address = base.checked_add(start as u64)?;
+                let mut hex = Vec::with_capacity(chunk.len());
+                let mut ascii = String::with_capacity(chunk.len());
+                let mut edited = Vec::new();
+
+                for (i, byte) in chunk.iter().enumerate() {
+                    let value = address.checked_add(i as u64).and_then(|a| pending.get(&a)).copied();
+                    if value.is_some() {
+                        edited.push(i);
+                    }
+                    match value.or(*byte) {
+                        Some(b) => {
+                            hex.push(format!("{:02x}", b));
+                            ascii.push(if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' });
+                        }
+                        None => {
+                            hex.push("??".to_string());
+                            ascii.push('?');
+                        }
+                    }
+                }
+
+                Some(MemoryRow {
+                    address,
+                    hex,
+                    ascii,
+                    edited,
+                })
+            })
+            .collect()
+    }
+
+    /// Clear loaded memory
+    pub fn clear(&self) {
+        *self.memory_reference.write() = None;
+        *self.base_address.write() = 0;
+        self.data.write().clear();
+        self.pending.write().clear();
+    }
+}
+
+impl Default for MemoryView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugView for MemoryView {
+    fn id(&self) -> DebugViewId {
+        DebugViewId::Memory
+    }
+
+    fn title(&self) -> &str {
+        "Memory"
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn show(&mut self) {
+        self.visible = true;
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    fn refresh(&mut self) {
+        // Re-read memory from DAP
+    }
+
+    fn clear(&mut self) {
+        MemoryView::clear(self);
+    }
+}
+
+/// Hex viewer row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryRow {
+    /// Address of the first byte
+    pub address: u64,
+    /// Hex byte cells
+    pub hex: Vec<String>,
+    /// ASCII rendering
+    pub ascii: String,
+    /// Columns with pending edits
+    pub edited: Vec<usize>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows() {
+        let mut view = MemoryView::new();
+        view.set_bytes_per_row(4);
+        view.set_contents(0x1000, b"Hi!\x00abc".to_vec(), 2);
+
+        let rows = view.rows();
+        assert_eq!(rows.len(), 3);
+        assert_eq!(rows[0].address, 0x1000);
+        assert_eq!(rows[0].hex, vec!["48", "69", "21", "00"]);
+        assert_eq!(rows[0].ascii, "Hi!.");
+        assert_eq!(rows[1].hex, vec!["61", "62", "63", "??"]);
+        assert_eq!(rows[2].address, 0x1008);
+        assert_eq!(rows[2].ascii, "?");
+    }
+
+    #[test]
+    fn test_pending_writes_coalesce() {
+        let view = MemoryView::new();
+        view.set_contents(0, vec![0; 8], 1);
+
+        assert!(view.edit_byte(1, 0xaa));
+        assert!(view.edit_byte(2, 0xbb));
+        assert!(view.edit_byte(5, 0xcc));
+        assert!(!view.edit_byte(8, 0xdd));
+        assert_eq!(view.pending_writes(), vec![(1, vec![0xaa, 0xbb]), (5, vec![0xcc])]);
+
+        view.commit_writes();
+        assert!(!view.is_dirty());
+        assert_eq!(view.byte(2), Some(0xbb));
+    }
+
+    #[test]
+    fn test_pending_writes_follow_scrolling() {
+        let view = MemoryView::new();
+        view.set_contents(0x100, vec![0; 8], 0);
+        assert!(view.edit_byte(4, 0xaa));
+
+        // Scrolled down two bytes: the edit is now at offset 2
+        view.set_contents(0x102, vec![0; 8], 0);
+        assert_eq!(view.byte(2), Some(0xaa));
+        assert_eq!(view.rows()[0].edited, vec![2]);
+        assert_eq!(view.pending_writes(), vec![(2, vec![0xaa])]);
+
+        // Scrolled past it
+        view.set_contents(0x108, vec![0; 8], 0);
+        assert_eq!(view.byte(0), Some(0));
+        assert_eq!(view.pending_writes(), vec![(-4, vec![0xaa])]);
+    }
+
+    #[test]
+    fn test_end_of_address_space() {
+        let mut view = MemoryView::new();
+        view.set_bytes_per_row(2);
+        view.set_contents(u64::MAX - 1, vec![0; 4], 0);
+
+        assert_eq!(view.rows().len(), 1);
+        assert_eq!(view.rows()[0].address, u64::MAX - 1);
+        assert!(view.edit_byte(1, 0xaa));
+        assert!(!view.edit_byte(2, 0xaa));
+        assert_eq!(view.pending_writes(), vec![(1, vec![0xaa])]);
+    }
+
+    #[test]
+    fn test_read_response() {
+        let view = MemoryView::new();
+        view.open("0x20");
+        let response = dap::protocol::ReadMemoryResponseBody {
+            address: "0x20".to_string(),
+            unreadable_bytes: None,
+            data: Some("AQID".to_string()),
+        };
+        view.set_read_response(&response).unwrap();
+
+        assert_eq!(view.base_address(), 0x20);
+        assert_eq!(view.rows()[0].hex, vec!["01", "02", "03"]);
+    }
+}
@@ -0,0 +1,226 @@
+//! Recorded session replay view
+
+use std::path::Path;
+use parking_lot::RwLock;
+
+use dap::{RecordedStop, Recording};
+
+use crate::{DebugView, DebugViewId, SourceLocation};
+use crate::callstack::{StackFrame, Thread};
+use crate::variables::{Scope, Variable};
+
+/// Replay view for scrubbing through recorded stops
+pub struct ReplayView {
+    /// Loaded recording
+    recording: RwLock<Option<Recording>>,
+    /// Position of the stop being shown
+    position: RwLock<usize>,
+    /// Visibility
+    visible: bool,
+}
+
+impl ReplayView {
+    pub fn new() -> Self {
+        Self {
+            recording: RwLock::new(None),
+            position: RwLock::new(0),
+            visible: false,
+        }
+    }
+
+    /// Load a recording, positioned at the latest stop
+    pub fn load(&self, recording: Recording) {
+        *self.position.write() = recording.len().saturating_sub(1);
+        *self.recording.write() = Some(recording);
+    }
+
+    /// Import a recording exported by a teammate
+    pub fn import(&self, path: &Path) -> anyhow::Result<()> {
+        self.load(Recording::load(path)?);
+        Ok(())
+    }
+
+    /// Export the loaded recording
+    pub fn export(&self, path: &Path) -> anyhow::Result<()> {
+        match self.recording.read().as_ref() {
+            Some(recording) => recording.save(path),
+            None => anyhow::bail!("No recording loaded"),
+        }
+    }
+
+    /// Is a recording loaded?
+    pub fn is_loaded(&self) -> bool {
+        self.recording.read().is_some()
+    }
+
+    /// Number of recorded stops
+    pub fn len(&self) -> usize {
+        self.recording.read().as_ref().map_or(0, Recording::len)
+    }
+
+    /// Is the recording empty?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Current position
+    pub fn position(&self) -> usize {
+        *self.position.read()
+    }
+
+    /// Stop at the current position
+    pub fn current(&self) -> Option<RecordedStop> {
+        let position = *self.position.read();
+        self.recording.read().as_ref()?.get(position).cloned()
+    }
+
+    /// Move to a position, clamped to the recording
+    pub fn seek(&self, position: usize) -> Option<RecordedStop> {
+        let len = self.len();
+        if len == 0 {
+            return None;
+        }
+        *self.position.write() = position.min(len - 1);
+        self.current()
+    }
+
+    /// Step back one stop
+    pub fn step_back(&self) -> Option<RecordedStop> {
+        let position = *self.position.read();
+        if position == 0 {
+            return None;
+        }
+        self.seek(position - 1)
+    }
+
+    /// Step forward one stop
+    pub fn step_forward(&self) -> Option<RecordedStop> {
+        let position = *self.position.read();
+        if position + 1 >= self.len() {
+            return None;
+        }
+        self.seek(position + 1)
+    }
+
+    /// Is the latest stop shown?
+    pub fn is_at_latest(&self) -> bool {
+        *self.position.read() + 1 >= self.len()
+    }
+
+    /// Recorded variables for a reference at the current stop
+    pub fn variables(&self, reference: i64) -> Vec<Variable> {
+        self.current()
+            .map(|stop| stop.variables_for(reference).iter().map(Variable::from).collect())
+            .unwrap_or_default()
+    }
+
+    /// Unload the recording
+    pub fn clear(&self) {
+        *self.recording.write() = None;
+        *self.position.write() = 0;
+    }
+}
+
+impl Default for ReplayView {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DebugView for ReplayView {
+    fn id(&self) -> DebugViewId {
+        DebugViewId::Replay
+    }
+
+    fn title(&self) -> &str {
+        "Recorded Stops"
+    }
+
+    fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    fn show(&mut self) {
+        self.visible = true;
+    }
+
+    fn hide(&mut self) {
+        self.visible = false;
+    }
+
+    fn refresh(&mut self) {
+        // Recordings don't change once loaded
+    }
+
+    fn clear(&mut self) {
+        ReplayView::clear(self);
+    }
+}
+
+/// Thread snapshot for the call stack view
+pub fn recorded_thread(stop: &RecordedStop) -> Thread {
+    Thread {
+        id: stop.thread_id,
+        name: format!("Thread {}", stop.thread_id),
+        stack_frames: stop.frames.iter().map(StackFrame::from).collect(),
+    }
+}
+
+/// Scopes of the innermost frame for the variables view
+pub fn recorded_scopes(stop: &RecordedStop) -> Vec<Scope> {
+    stop.top_frame()
+        .map(|frame| stop.scopes_for(frame.id).iter().map(Scope::from).collect())
+        .unwrap_or_default()
+}
+
+/// Location of the innermost frame
+pub fn recorded_location(stop: &RecordedStop) -> Option<SourceLocation> {
+    stop.top_frame().and_then(|frame| StackFrame::from(frame).location())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn recording(stops: usize) -> Recording {
+        let mut recording = Recording::new("lldb", Default::default());
+        for thread_id in 0..stops as i64 {
+            recording.push(RecordedStop {
+                index: 0,
+                timestamp_ms: 0,
+                thread_id,
+                reason: dap::StopReason::Step,
+                frames: vec![],
+                scopes: HashMap::new(),
+                variables: HashMap::from([(1, vec![])]),
+            });
+        }
+        recording
+    }
+
+    #[test]
+    fn test_scrubbing() {
+        let view = ReplayView::new();
+        view.load(recording(3));
+
+        assert!(view.is_at_latest());
+        assert_eq!(view.current().unwrap().thread_id, 2);
+        assert_eq!(view.step_back().unwrap().thread_id, 1);
+        assert_eq!(view.step_back().unwrap().thread_id, 0);
+        assert!(view.step_back().is_none());
+        assert_eq!(view.seek(10).unwrap().thread_id, 2);
+        assert!(view.step_forward().is_none());
+    }
+
+    #[test]
+    fn test_empty_recording() {
+        let view = ReplayView::new();
+        view.load(recording(0));
+
+        assert!(view.is_empty());
+        assert!(view.current().is_none());
+        assert!(view.seek(0).is_none());
+        assert!(view.export(Path::new("/nonexistent/recording.json")).is_err());
+    }
+}
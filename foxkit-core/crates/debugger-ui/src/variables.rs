@@ -122,6 +122,23 @@ pub struct Scope {
     pub end_column: Option<u32>,
 }
 
+impl From<&dap::Scope> for Scope {
+    fn from(scope: &dap::Scope) -> Self {
+        Self {
+            name: scope.name.clone(),
+            variables_reference: VariableReference(scope.variables_reference),
+            named_variables: None,
+            indexed_variables: None,
+            expensive: scope.expensive,
+            source: scope.source.as_ref().and_then(|s| s.path.clone()),
+            line: scope.line.map(|l| l as u32),
+            column: None,
+            end_line: None,
+            end_column: None,
+        }
+    }
+}
+
 /// Variable
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Variable {
@@ -157,6 +174,22 @@ impl Variable {
     }
 }
 
+impl From<&dap::Variable> for Variable {
+    fn from(var: &dap::Variable) -> Self {
+        Self {
+            name: var.name.clone(),
+            value: var.value.clone(),
+            var_type: var.var_type.clone(),
+            presentation_hint: None,
+            evaluate_name: None,
+            variables_reference: VariableReference(var.variables_reference),
+            named_variables: var.named_variables,
+            indexed_variables: var.indexed_variables,
+            memory_reference: var.memory_reference.clone(),
+        }
+    }
+}
+
 /// Variable presentation hint
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VariablePresentationHint {
@@ -12,6 +12,9 @@ pub enum DebugViewId {
     Console,
     Loaded,
     Threads,
+    Disassembly,
+    Memory,
+    Replay,
 }
 
 impl DebugViewId {
@@ -24,6 +27,9 @@ impl DebugViewId {
             Self::Console => "debug.console",
             Self::Loaded => "debug.loaded",
             Self::Threads => "debug.threads",
+            Self::Disassembly => "debug.disassembly",
+            Self::Memory => "debug.memory",
+            Self::Replay => "debug.replay",
         }
     }
 
@@ -36,6 +42,9 @@ impl DebugViewId {
             Self::Console => "Debug Console",
             Self::Loaded => "Loaded Scripts",
             Self::Threads => "Threads",
+            Self::Disassembly => "Disassembly",
+            Self::Memory => "Memory",
+            Self::Replay => "Recorded Stops",
         }
     }
 }
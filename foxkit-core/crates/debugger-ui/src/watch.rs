@@ -1,8 +1,12 @@
 //! Watch expressions view
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 
+use dap::DapClient;
+
 use crate::{DebugView, DebugViewId};
 use crate::variables::{Variable, VariableReference};
 
@@ -10,6 +14,8 @@ use crate::variables::{Variable, VariableReference};
 pub struct WatchView {
     /// Watch expressions
     expressions: RwLock<Vec<WatchExpression>>,
+    /// Fetched children of expandable results
+    children: RwLock<HashMap<VariableReference, Vec<Variable>>>,
     /// Selected expression
     selected: RwLock<Option<usize>>,
     /// Visibility
@@ -20,21 +26,34 @@ impl WatchView {
     pub fn new() -> Self {
         Self {
             expressions: RwLock::new(Vec::new()),
+            children: RwLock::new(HashMap::new()),
             selected: RwLock::new(None),
             visible: true,
         }
     }
 
     /// Add watch expression
-    pub fn add(&self, expression: String) {
+    pub fn add(&self, expression: String) -> u64 {
         static COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
-        
+
+        let id = COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
         self.expressions.write().push(WatchExpression {
-            id: COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed),
+            id,
             expression,
             result: None,
             error: None,
+            last_value: None,
+            changed: false,
+            refresh_on_stop: true,
         });
+        id
+    }
+
+    /// Evaluate on every stop, or only on demand (for expressions with side effects)
+    pub fn set_refresh_on_stop(&self, id: u64, refresh_on_stop: bool) {
+        if let Some(expr) = self.expressions.write().iter_mut().find(|e| e.id == id) {
+            expr.refresh_on_stop = refresh_on_stop;
+        }
     }
 
     /// Remove watch expression
@@ -49,6 +68,8 @@ impl WatchView {
             expr.expression = new_expression;
             expr.result = None;
             expr.error = None;
+            expr.last_value = None;
+            expr.changed = false;
         }
     }
 
@@ -63,17 +84,101 @@ impl WatchView {
         if let Some(expr) = exprs.iter_mut().find(|e| e.id == id) {
             match result {
                 EvaluateResult::Value(v) => {
+                    expr.changed = expr.last_value.as_ref().is_some_and(|last| *last != v.value);
+                    expr.last_value = Some(v.value.clone());
                     expr.result = Some(v);
                     expr.error = None;
                 }
                 EvaluateResult::Error(e) => {
                     expr.result = None;
                     expr.error = Some(e);
+                    expr.changed = false;
                 }
             }
         }
     }
 
+    /// Re-evaluate watches in the `watch` context; call on every stop.
+    /// Watches with `refresh_on_stop` off are only evaluated when `force` is set.
+    pub async fn refresh_from(&self, client: &DapClient, frame_id: Option<i64>, force: bool) {
+        let pending: Vec<(u64, String)> = self.expressions.read()
+            .iter()
+            .filter(|e| force || e.refresh_on_stop)
+            .map(|e| (e.id, e.expression.clone()))
+            .collect();
+
+        // Variable references are only valid for a single stop
+        self.children.write().clear();
+
+        for (id, expression) in pending {
+            let result = match client.evaluate(&expression, frame_id, Some("watch")).await {
+                Ok(body) => EvaluateResult::Value(WatchResult::from(body)),
+                Err(e) => EvaluateResult::Error(e.to_string()),
+            };
+            self.set_result(id, result);
+        }
+    }
+
+    /// Fetch the children of an expandable result
+    pub async fn expand(&self, client: &DapClient, reference: VariableReference) -> anyhow::Result<Vec<Variable>> {
+        if let Some(children) = self.children.read().get(&reference) {
+            return Ok(children.clone());
+        }
+
+        let children: Vec<Variable> = client.variables(reference.0, None, None).await?
+            .iter()
+            .map(Variable::from)
+            .collect();
+        self.children.write().insert(reference, children.clone());
+        Ok(children)
+    }
+
+    /// Children fetched for a reference
+    pub fn children(&self, reference: VariableReference) -> Option<Vec<Variable>> {
+        self.children.read().get(&reference).cloned()
+    }
+
+    /// Assign a new value to a watched expression through `setExpression`
+    pub async fn set_value(&self, client: &DapClient, id: u64, value: &str, frame_id: Option<i64>) -> anyhow::Result<()> {
+        let expression = self.expressions.read()
+            .iter()
+            .find(|e| e.id == id)
+            .map(|e| e.expression.clone())
+            .ok_or_else(|| anyhow::anyhow!("Unknown watch: {}", id))?;
+
+        let body = client.set_expression(&expression, value, frame_id).await?;
+        self.children.write().clear();
+        self.set_result(id, EvaluateResult::Value(WatchResult {
+            value: body.value,
+            result_type: body.value_type,
+            variables_reference: VariableReference(body.variables_reference),
+            named_variables: body.named_variables,
+            indexed_variables: body.indexed_variables,
+            memory_reference: body.memory_reference,
+        }));
+        Ok(())
+    }
+
+    /// Expressions to persist
+    pub fn persisted(&self) -> Vec<PersistedWatch> {
+        self.expressions.read()
+            .iter()
+            .map(|e| PersistedWatch {
+                expression: e.expression.clone(),
+                refresh_on_stop: e.refresh_on_stop,
+            })
+            .collect()
+    }
+
+    /// Replace expressions with a persisted list
+    pub fn restore(&self, watches: Vec<PersistedWatch>) {
+        self.clear_all();
+        for watch in watches {
+            let id = self.add(watch.expression);
+            self.set_refresh_on_stop(id, watch.refresh_on_stop);
+        }
+    }
+
     /// Select expression
     pub fn select(&self, index: usize) {
         *self.selected.write() = Some(index);
@@ -82,6 +187,7 @@ impl WatchView {
     /// Clear all expressions
     pub fn clear_all(&self) {
         self.expressions.write().clear();
+        self.children.write().clear();
     }
 }
 
@@ -121,7 +227,9 @@ impl DebugView for WatchView {
         for expr in self.expressions.write().iter_mut() {
             expr.result = None;
             expr.error = None;
+            expr.changed = false;
         }
+        self.children.write().clear();
     }
 }
 
@@ -136,6 +244,19 @@ pub struct WatchExpression {
     pub result: Option<WatchResult>,
     /// Error message
     pub error: Option<String>,
+    /// Value at the previous evaluation
+    #[serde(skip)]
+    pub last_value: Option<String>,
+    /// Value changed since the previous stop
+    #[serde(skip)]
+    pub changed: bool,
+    /// Re-evaluate on every stop
+    #[serde(default = "default_refresh_on_stop")]
+    pub refresh_on_stop: bool,
+}
+
+fn default_refresh_on_stop() -> bool {
+    true
 }
 
 /// Watch result
@@ -155,12 +276,78 @@ pub struct WatchResult {
     pub memory_reference: Option<String>,
 }
 
+impl From<dap::protocol::EvaluateResponseBody> for WatchResult {
+    fn from(body: dap::protocol::EvaluateResponseBody) -> Self {
+        Self {
+            value: body.result,
+            result_type: body.result_type,
+            variables_reference: VariableReference(body.variables_reference),
+            named_variables: body.named_variables,
+            indexed_variables: body.indexed_variables,
+            memory_reference: body.memory_reference,
+        }
+    }
+}
+
 /// Evaluate result
 pub enum EvaluateResult {
     Value(WatchResult),
     Error(String),
 }
 
+/// Watch expression as persisted per launch configuration
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PersistedWatch {
+    pub expression: String,
+    #[serde(default = "default_refresh_on_stop")]
+    pub refresh_on_stop: bool,
+}
+
+/// Watch lists stored in a JSON file, keyed by launch configuration name
+pub struct WatchListStore {
+    path: PathBuf,
+}
+
+impl WatchListStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    /// All stored watch lists; none when the file doesn't exist yet
+    fn read_all(&self) -> anyhow::Result<HashMap<String, Vec<PersistedWatch>>> {
+        let content = match std::fs::read_to_string(&self.path) {
+            Ok(content) => content,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(err) => return Err(err.into()),
+        };
+        serde_json::from_str(&content)
+            .map_err(|err| anyhow::anyhow!("Invalid watch list file {}: {}", self.path.display(), err))
+    }
+
+    /// Load the watch list stored for a launch configuration, if any
+    pub fn load(&self, configuration: &str) -> anyhow::Result<Option<Vec<PersistedWatch>>> {
+        Ok(self.read_all()?.remove(configuration))
+    }
+
+    /// Save the watch list for a launch configuration. A file that can't be
+    /// read is left alone rather than overwritten.
+    pub fn save(&self, configuration: &str, watches: Vec<PersistedWatch>) -> anyhow::Result<()> {
+        let mut all = self.read_all()?;
+        if watches.is_empty() {
+            all.remove(configuration);
+        } else {
+            all.insert(configuration.to_string(), watches);
+        }
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&all)?)?;
+        Ok(())
+    }
+}
+
 /// Inline value
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InlineValue {
@@ -184,3 +371,69 @@ pub struct HoverResult {
     /// Result
     pub result: WatchResult,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn value(value: &str) -> EvaluateResult {
+        EvaluateResult::Value(WatchResult {
+            value: value.to_string(),
+            result_type: None,
+            variables_reference: VariableReference(0),
+            named_variables: None,
+            indexed_variables: None,
+            memory_reference: None,
+        })
+    }
+
+    #[test]
+    fn test_change_highlighting() {
+        let view = WatchView::new();
+        let id = view.add("counter".to_string());
+
+        view.set_result(id, value("1"));
+        assert!(!view.expressions()[0].changed);
+
+        view.set_result(id, value("2"));
+        assert!(view.expressions()[0].changed);
+
+        view.set_result(id, value("2"));
+        assert!(!view.expressions()[0].changed);
+    }
+
+    #[test]
+    fn test_store_per_configuration() {
+        let path = std::env::temp_dir().join(format!("foxkit-watches-{}.json", std::process::id()));
+        let store = WatchListStore::new(&path);
+
+        let view = WatchView::new();
+        let id = view.add("a + b".to_string());
+        view.add("items.len()".to_string());
+        view.set_refresh_on_stop(id, false);
+        store.save("Debug tests", view.persisted()).unwrap();
+        store.save("Debug app", vec![]).unwrap();
+
+        let restored = WatchView::new();
+        restored.restore(store.load("Debug tests").unwrap().unwrap());
+        let expressions = restored.expressions();
+        assert_eq!(expressions.len(), 2);
+        assert!(!expressions[0].refresh_on_stop);
+        assert!(store.load("Debug app").unwrap().is_none());
+
+        std::fs::remove_file(path).ok();
+    }
+
+    #[test]
+    fn test_store_keeps_unreadable_file() {
+        let path = std::env::temp_dir().join(format!("foxkit-watches-broken-{}.json", std::process::id()));
+        std::fs::write(&path, "{ not json").unwrap();
+        let store = WatchListStore::new(&path);
+
+        assert!(store.load("Debug tests").is_err());
+        assert!(store.save("Debug tests", vec![]).is_err());
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "{ not json");
+
+        std::fs::remove_file(path).ok();
+    }
+}
@@ -9,7 +9,7 @@ pub mod patch;
 use serde::{Deserialize, Serialize};
 
 pub use myers::myers_diff;
-pub use merge::{merge3, ConflictStyle};
+pub use merge::{merge3, merge3_with_style, ConflictStyle};
 pub use patch::{Patch, PatchHunk};
 
 /// A single edit operation
@@ -204,6 +204,44 @@ pub fn diff_lines(old: &[&str], new: &[&str]) -> Diff {
     myers_diff(old, new)
 }
 
+/// Calculate diff between two strings word by word
+///
+/// Words, runs of whitespace and single punctuation characters are compared
+/// as units, so ops hold those tokens rather than lines.
+pub fn diff_words(old: &str, new: &str) -> Diff {
+    let old_words = split_words(old);
+    let new_words = split_words(new);
+    myers_diff(&old_words, &new_words)
+}
+
+fn split_words(text: &str) -> Vec<&str> {
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            0
+        } else if c.is_whitespace() {
+            1
+        } else {
+            2
+        }
+    };
+
+    let mut words = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((_, c)) = chars.next() {
+        let kind = class(c);
+        let joins = |next: char| kind != 2 && class(next) == kind;
+        let end = match chars.peek() {
+            Some(&(i, next)) if !joins(next) => i,
+            Some(_) => continue,
+            None => text.len(),
+        };
+        words.push(&text[start..end]);
+        start = end;
+    }
+    words
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,4 +263,13 @@ mod tests {
         let d = diff(text, text);
         assert!(!d.has_changes());
     }
+
+    #[test]
+    fn test_diff_words() {
+        assert_eq!(split_words("let x = foo(a_b);"), vec!["let", " ", "x", " ", "=", " ", "foo", "(", "a_b", ")", ";"]);
+
+        let d = diff_words("let x = 1;", "let y = 1;");
+        assert_eq!(d.deletions(), 1);
+        assert_eq!(d.insertions(), 1);
+    }
 }
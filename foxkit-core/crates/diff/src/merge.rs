@@ -8,6 +8,8 @@ use serde::{Deserialize, Serialize};
 pub enum ConflictStyle {
     /// Standard diff3 style
     Diff3,
+    /// Diff3 with the lines both sides agree on moved out of the conflict
+    ZDiff3,
     /// Git merge style
     Merge,
 }
@@ -32,9 +34,9 @@ pub struct MergeResult {
 /// A conflict region
 #[derive(Debug, Clone)]
 pub struct Conflict {
-    /// Start line in output
+    /// Line of the opening marker in output
     pub start_line: usize,
-    /// End line in output
+    /// Line after the closing marker in output
     pub end_line: usize,
     /// Ours content
     pub ours: Vec<String>,
@@ -50,6 +52,11 @@ pub fn merge3(base: &str, ours: &str, theirs: &str) -> MergeResult {
 }
 
 /// Three-way merge with conflict style
+///
+/// Changes that don't touch the same base lines are applied as they are,
+/// and so are changes both sides made identically. Insertions at the same
+/// place, or right after a change on the other side, conflict since their
+/// order can't be told.
 pub fn merge3_with_style(
     base: &str,
     ours: &str,
@@ -63,24 +70,12 @@ pub fn merge3_with_style(
     let diff_ours = diff_lines(&base_lines, &ours_lines);
     let diff_theirs = diff_lines(&base_lines, &theirs_lines);
 
-    // Simple merge: if changes don't overlap, merge them
-    // If they overlap, create conflict
-    
-    let mut result_lines = Vec::new();
+    let mut result_lines: Vec<String> = Vec::new();
     let mut conflicts = Vec::new();
-    let mut has_conflicts = false;
-
-    let mut base_idx = 0;
-    let mut ours_idx = 0;
-    let mut theirs_idx = 0;
 
     // Get changes from both diffs
-    let ours_changes = extract_changes(&diff_ours);
-    let theirs_changes = extract_changes(&diff_theirs);
-
-    // Sort changes by base position
     let mut all_changes: Vec<ChangeRegion> = Vec::new();
-    for (start, end, lines) in ours_changes {
+    for (start, end, lines) in extract_changes(&diff_ours) {
         all_changes.push(ChangeRegion {
             base_start: start,
             base_end: end,
@@ -88,7 +83,7 @@ pub fn merge3_with_style(
             source: ChangeSource::Ours,
         });
     }
-    for (start, end, lines) in theirs_changes {
+    for (start, end, lines) in extract_changes(&diff_theirs) {
         all_changes.push(ChangeRegion {
             base_start: start,
             base_end: end,
@@ -96,106 +91,140 @@ pub fn merge3_with_style(
             source: ChangeSource::Theirs,
         });
     }
-    
-    all_changes.sort_by_key(|c| c.base_start);
+
+    // Sort changes by base position
+    all_changes.sort_by_key(|c| (c.base_start, c.base_end));
+
+    let base_text = |range: std::ops::Range<usize>| -> Vec<String> {
+        base_lines[range].iter().map(|s| s.to_string()).collect()
+    };
 
     // Process changes
     let mut last_end = 0;
     let mut i = 0;
-    
+
     while i < all_changes.len() {
-        let change = &all_changes[i];
-        
+        let start = all_changes[i].base_start;
+
         // Add unchanged lines before this change
-        for j in last_end..change.base_start {
-            if j < base_lines.len() {
-                result_lines.push(base_lines[j].to_string());
-            }
-        }
+        result_lines.extend(base_text(last_end..start));
 
-        // Check for overlapping changes
-        let mut overlapping: Vec<&ChangeRegion> = vec![change];
-        let mut max_end = change.base_end;
-        
+        // Group changes that overlap, or insert where another one ends
+        let mut end = all_changes[i].base_end;
+        let mut insertion_at_end = all_changes[i].is_insertion();
         let mut j = i + 1;
-        while j < all_changes.len() && all_changes[j].base_start < max_end {
-            overlapping.push(&all_changes[j]);
-            max_end = max_end.max(all_changes[j].base_end);
+        while j < all_changes.len() {
+            let next = &all_changes[j];
+            let touches = next.base_start == end && (next.is_insertion() || insertion_at_end);
+            if next.base_start >= end && !touches {
+                break;
+            }
+            if next.base_end > end {
+                end = next.base_end;
+                insertion_at_end = next.is_insertion();
+            } else if next.base_end == end {
+                insertion_at_end |= next.is_insertion();
+            }
             j += 1;
         }
 
-        if overlapping.len() == 1 {
-            // No conflict, apply change
-            result_lines.extend(change.new_lines.clone());
-            last_end = change.base_end;
-            i += 1;
+        let group = &all_changes[i..j];
+        let base_content = base_text(start..end);
+        let ours_content = apply_changes(&base_lines, group, ChangeSource::Ours, start, end);
+        let theirs_content = apply_changes(&base_lines, group, ChangeSource::Theirs, start, end);
+
+        if theirs_content == base_content || ours_content == theirs_content {
+            result_lines.extend(ours_content);
+        } else if ours_content == base_content {
+            result_lines.extend(theirs_content);
         } else {
-            // Conflict!
-            has_conflicts = true;
-            
-            let ours_content: Vec<String> = overlapping
-                .iter()
-                .filter(|c| c.source == ChangeSource::Ours)
-                .flat_map(|c| c.new_lines.clone())
-                .collect();
-            
-            let theirs_content: Vec<String> = overlapping
-                .iter()
-                .filter(|c| c.source == ChangeSource::Theirs)
-                .flat_map(|c| c.new_lines.clone())
-                .collect();
-            
-            let base_content: Vec<String> = (change.base_start..max_end)
-                .filter_map(|idx| base_lines.get(idx).map(|s| s.to_string()))
-                .collect();
+            let (prefix, suffix) = if style == ConflictStyle::ZDiff3 {
+                common_ends(&ours_content, &theirs_content)
+            } else {
+                (0, 0)
+            };
+            result_lines.extend(ours_content[..prefix].iter().cloned());
 
+            let ours_conflict = ours_content[prefix..ours_content.len() - suffix].to_vec();
+            let theirs_conflict = theirs_content[prefix..theirs_content.len() - suffix].to_vec();
             let conflict_start = result_lines.len();
-            
-            match style {
-                ConflictStyle::Merge => {
-                    result_lines.push("<<<<<<< ours".to_string());
-                    result_lines.extend(ours_content.clone());
-                    result_lines.push("=======".to_string());
-                    result_lines.extend(theirs_content.clone());
-                    result_lines.push(">>>>>>> theirs".to_string());
-                }
-                ConflictStyle::Diff3 => {
-                    result_lines.push("<<<<<<< ours".to_string());
-                    result_lines.extend(ours_content.clone());
-                    result_lines.push("||||||| base".to_string());
-                    result_lines.extend(base_content.clone());
-                    result_lines.push("=======".to_string());
-                    result_lines.extend(theirs_content.clone());
-                    result_lines.push(">>>>>>> theirs".to_string());
-                }
+
+            result_lines.push("<<<<<<< ours".to_string());
+            result_lines.extend(ours_conflict.iter().cloned());
+            if style != ConflictStyle::Merge {
+                result_lines.push("||||||| base".to_string());
+                result_lines.extend(base_content.iter().cloned());
             }
+            result_lines.push("=======".to_string());
+            result_lines.extend(theirs_conflict.iter().cloned());
+            result_lines.push(">>>>>>> theirs".to_string());
 
             conflicts.push(Conflict {
                 start_line: conflict_start,
                 end_line: result_lines.len(),
-                ours: ours_content,
-                theirs: theirs_content,
+                ours: ours_conflict,
+                theirs: theirs_conflict,
                 base: base_content,
             });
 
-            last_end = max_end;
-            i = j;
+            result_lines.extend(ours_content[ours_content.len() - suffix..].iter().cloned());
         }
+
+        last_end = end;
+        i = j;
     }
 
     // Add remaining unchanged lines
-    for j in last_end..base_lines.len() {
-        result_lines.push(base_lines[j].to_string());
+    result_lines.extend(base_text(last_end..base_lines.len()));
+
+    // The final newline merges like any other change: a side that changed it
+    // from the base wins
+    let newline = |text: &str| text.ends_with('\n');
+    let trailing_newline = if newline(ours) == newline(base) { newline(theirs) } else { newline(ours) };
+    let mut content = result_lines.join("\n");
+    if !result_lines.is_empty() && trailing_newline {
+        content.push('\n');
     }
 
     MergeResult {
-        content: result_lines.join("\n"),
-        has_conflicts,
+        content,
+        has_conflicts: !conflicts.is_empty(),
         conflicts,
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// One side's version of the base lines `start..end`
+fn apply_changes(
+    base_lines: &[&str],
+    changes: &[ChangeRegion],
+    source: ChangeSource,
+    start: usize,
+    end: usize,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for change in changes.iter().filter(|c| c.source == source) {
+        lines.extend(base_lines[pos..change.base_start].iter().map(|s| s.to_string()));
+        lines.extend(change.new_lines.iter().cloned());
+        pos = change.base_end;
+    }
+    lines.extend(base_lines[pos..end].iter().map(|s| s.to_string()));
+    lines
+}
+
+/// Number of leading and trailing lines two versions share
+fn common_ends(a: &[String], b: &[String]) -> (usize, usize) {
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    (prefix, suffix)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum ChangeSource {
     Ours,
     Theirs,
@@ -209,6 +238,12 @@ struct ChangeRegion {
     source: ChangeSource,
 }
 
+impl ChangeRegion {
+    fn is_insertion(&self) -> bool {
+        self.base_start == self.base_end
+    }
+}
+
 fn extract_changes(diff: &Diff) -> Vec<(usize, usize, Vec<String>)> {
     let mut changes = Vec::new();
     let mut base_idx = 0;
@@ -270,4 +305,44 @@ mod tests {
         assert!(result.has_conflicts);
         assert_eq!(result.conflicts.len(), 1);
     }
+
+    #[test]
+    fn test_merge_styles() {
+        let base = "fn main() {\n    run();\n}\n";
+        let ours = "fn main() {\n    setup();\n    run(1);\n}\n";
+        let theirs = "fn main() {\n    setup();\n    run(2);\n}\n";
+
+        let result = merge3_with_style(base, ours, theirs, ConflictStyle::Diff3);
+        assert_eq!(result.conflicts[0].ours, vec!["    setup();", "    run(1);"]);
+        assert_eq!(result.conflicts[0].base, vec!["    run();"]);
+
+        // zdiff3 moves the line both sides added out of the conflict
+        let result = merge3_with_style(base, ours, theirs, ConflictStyle::ZDiff3);
+        assert_eq!(
+            result.content,
+            "fn main() {\n    setup();\n<<<<<<< ours\n    run(1);\n||||||| base\n    run();\n\
+             =======\n    run(2);\n>>>>>>> theirs\n}\n"
+        );
+        assert_eq!((result.conflicts[0].start_line, result.conflicts[0].end_line), (2, 9));
+
+        // The same change on both sides merges cleanly
+        let result = merge3(base, ours, ours);
+        assert!(!result.has_conflicts);
+        assert_eq!(result.content, ours);
+
+        // So do insertions at different places, but not at the same place
+        assert!(!merge3("a\nb\n", "x\na\nb\n", "a\nb\ny\n").has_conflicts);
+        assert!(merge3("a\nb\n", "a\nx\nb\n", "a\ny\nb\n").has_conflicts);
+    }
+
+    #[test]
+    fn test_trailing_newline() {
+        // Kept or left out as all sides have it
+        assert_eq!(merge3("a\nb\n", "x\nb\n", "a\nb\n").content, "x\nb\n");
+        assert_eq!(merge3("a\nb", "x\nb", "a\nb").content, "x\nb");
+
+        // A side that removed or added it wins over an unchanged side
+        assert_eq!(merge3("a\nb\n", "x\nb\n", "a\nb").content, "x\nb");
+        assert_eq!(merge3("a\nb", "a\nb", "a\ny\n").content, "a\ny\n");
+    }
 }
@@ -1151,6 +1151,7 @@ impl AppState {
 
         // Build scene (rectangles/backgrounds)
         let scene = self.build_scene();
+        self.renderer.prepare_images(&scene);
 
         // Prepare text renderer with viewport size
         self.text_renderer.prepare(self.config.width, self.config.height);
@@ -0,0 +1,152 @@
+//! Git bisect
+//!
+//! Parses what `git bisect` reports after each step and tracks which commits
+//! are still candidates for the first bad commit.
+
+use serde::{Deserialize, Serialize};
+
+/// Where a bisect stands after a command
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BisectStep {
+    /// Waiting for good or bad commits to be marked
+    Waiting,
+    /// A commit is checked out for testing
+    Testing {
+        commit: String,
+        summary: String,
+        /// Revisions left to test after this one
+        remaining: usize,
+        /// Roughly how many more steps it will take
+        steps: usize,
+    },
+    /// The first bad commit was found
+    FirstBad { commit: String },
+    /// Only skipped commits are left, and any of them may be the first bad one
+    Inconclusive { candidates: Vec<String> },
+}
+
+impl BisectStep {
+    /// Parse the output of `git bisect start/good/bad/skip/next`
+    pub fn parse(output: &str) -> Option<Self> {
+        let lines: Vec<&str> = output.lines().map(str::trim).collect();
+
+        if let Some(commit) = lines.iter().find_map(|l| l.strip_suffix(" is the first bad commit")) {
+            return Some(BisectStep::FirstBad { commit: commit.to_string() });
+        }
+
+        if let Some(start) = lines.iter().position(|l| l.starts_with("The first bad commit could be any of")) {
+            let candidates = lines[start + 1..]
+                .iter()
+                .take_while(|l| is_hash(l))
+                .map(|l| l.to_string())
+                .collect();
+            return Some(BisectStep::Inconclusive { candidates });
+        }
+
+        if let Some(index) = lines.iter().position(|l| l.starts_with("Bisecting:")) {
+            let numbers: Vec<usize> = lines[index]
+                .split(|c: char| !c.is_ascii_digit())
+                .filter_map(|n| n.parse().ok())
+                .collect();
+            let (commit, summary) = lines
+                .get(index + 1)
+                .and_then(|l| l.strip_prefix('['))
+                .and_then(|l| l.split_once(']'))?;
+            return Some(BisectStep::Testing {
+                commit: commit.to_string(),
+                summary: summary.trim().to_string(),
+                remaining: numbers.first().copied().unwrap_or(0),
+                steps: numbers.get(1).copied().unwrap_or(0),
+            });
+        }
+
+        lines
+            .iter()
+            .any(|l| l.starts_with("status: waiting"))
+            .then_some(BisectStep::Waiting)
+    }
+}
+
+fn is_hash(s: &str) -> bool {
+    s.len() >= 7 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Role of a commit in a bisect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BisectMark {
+    Good,
+    Bad,
+    Skipped,
+    /// Checked out for testing
+    Current,
+    /// Still a candidate for the first bad commit
+    Remaining,
+}
+
+/// Commits marked so far and those still in play
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BisectRange {
+    pub bad: Option<String>,
+    pub good: Vec<String>,
+    pub skipped: Vec<String>,
+    /// Candidates for the first bad commit, newest first
+    pub remaining: Vec<String>,
+    /// Commit checked out for testing
+    pub current: Option<String>,
+}
+
+impl BisectRange {
+    /// Role of a commit, if it takes part in the bisect
+    pub fn mark(&self, commit: &str) -> Option<BisectMark> {
+        if self.bad.as_deref() == Some(commit) {
+            Some(BisectMark::Bad)
+        } else if self.good.iter().any(|c| c == commit) {
+            Some(BisectMark::Good)
+        } else if self.skipped.iter().any(|c| c == commit) {
+            Some(BisectMark::Skipped)
+        } else if self.current.as_deref() == Some(commit) {
+            Some(BisectMark::Current)
+        } else if self.remaining.iter().any(|c| c == commit) {
+            Some(BisectMark::Remaining)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bisect_steps() {
+        let step = BisectStep::parse(
+            "Bisecting: 6 revisions left to test after this (roughly 3 steps)\n\
+             [0f3c2a9d1e5b7c8a9f0e1d2c3b4a5f6e7d8c9b0a] Add lexer\n",
+        );
+        assert_eq!(step, Some(BisectStep::Testing {
+            commit: "0f3c2a9d1e5b7c8a9f0e1d2c3b4a5f6e7d8c9b0a".into(),
+            summary: "Add lexer".into(),
+            remaining: 6,
+            steps: 3,
+        }));
+
+        let step = BisectStep::parse(
+            "4a5f6e7d8c9b0a0f3c2a9d1e5b7c8a9f0e1d2c3b is the first bad commit\n\
+             commit 4a5f6e7d8c9b0a0f3c2a9d1e5b7c8a9f0e1d2c3b\n",
+        );
+        assert_eq!(step, Some(BisectStep::FirstBad { commit: "4a5f6e7d8c9b0a0f3c2a9d1e5b7c8a9f0e1d2c3b".into() }));
+
+        let step = BisectStep::parse(
+            "There are only 'skip'ped commits left to test.\n\
+             The first bad commit could be any of:\n\
+             1111111111111111111111111111111111111111\n\
+             2222222222222222222222222222222222222222\n\
+             We cannot bisect more!\n",
+        );
+        assert!(matches!(step, Some(BisectStep::Inconclusive { candidates }) if candidates.len() == 2));
+
+        let step = BisectStep::parse("status: waiting for good commit(s), bad commit known\n");
+        assert_eq!(step, Some(BisectStep::Waiting));
+    }
+}
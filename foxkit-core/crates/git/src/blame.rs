@@ -1,7 +1,102 @@
 //! Git blame
+//!
+//! Runs `git blame --porcelain` with move/copy detection and ignored
+//! revisions, so reformatting commits listed in `.git-blame-ignore-revs`
+//! don't hide who wrote a line.
 
+use std::collections::HashMap;
 use std::path::Path;
 
+/// File listing revisions blame should look past, by convention at the
+/// repository root
+pub const IGNORE_REVS_FILE: &str = ".git-blame-ignore-revs";
+
+/// Commit id git reports for lines that aren't committed yet
+pub const UNCOMMITTED: &str = "0000000000000000000000000000000000000000";
+
+/// How to run blame
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlameOptions {
+    /// Blame the file as of this revision instead of the working tree
+    pub revision: Option<String>,
+    /// Only blame these lines (1-based, inclusive)
+    pub lines: Option<(usize, usize)>,
+    /// Follow lines moved within a file (`-M`)
+    pub detect_moves: bool,
+    /// Follow lines moved or copied from other files (`-C`), looking at
+    /// more commits the higher this is, up to 3
+    pub detect_copies: u8,
+    /// Revisions to look past, in addition to the ignore revs file
+    pub ignore_revs: Vec<String>,
+    /// Look past the revisions in `.git-blame-ignore-revs` when it exists
+    pub use_ignore_revs_file: bool,
+}
+
+impl Default for BlameOptions {
+    fn default() -> Self {
+        Self {
+            revision: None,
+            lines: None,
+            detect_moves: true,
+            detect_copies: 1,
+            ignore_revs: Vec::new(),
+            use_ignore_revs_file: true,
+        }
+    }
+}
+
+impl BlameOptions {
+    /// Blame as of a revision
+    pub fn at(mut self, revision: impl Into<String>) -> Self {
+        self.revision = Some(revision.into());
+        self
+    }
+
+    /// Blame a range of lines (1-based, inclusive)
+    pub fn lines(mut self, start: usize, end: usize) -> Self {
+        self.lines = Some((start, end));
+        self
+    }
+
+    /// Arguments for `git blame` in `repo_path`, up to the `--` before the path
+    fn args(&self, repo_path: &Path) -> Vec<String> {
+        let mut args = vec!["blame".to_string(), "--porcelain".to_string()];
+        if let Some((start, end)) = self.lines {
+            args.push(format!("-L{},{}", start, end));
+        }
+        if self.detect_moves {
+            args.push("-M".to_string());
+        }
+        for _ in 0..self.detect_copies.min(3) {
+            args.push("-C".to_string());
+        }
+        for rev in &self.ignore_revs {
+            args.push(format!("--ignore-rev={}", rev));
+        }
+        if self.use_ignore_revs_file && repo_path.join(IGNORE_REVS_FILE).is_file() {
+            args.push(format!("--ignore-revs-file={}", IGNORE_REVS_FILE));
+        }
+        args.extend(self.revision.clone());
+        args
+    }
+}
+
+/// Blame a file in the repository at `repo_path`
+pub async fn blame_file(repo_path: &Path, path: &Path, options: &BlameOptions) -> anyhow::Result<Blame> {
+    let output = tokio::process::Command::new("git")
+        .args(options.args(repo_path))
+        .arg("--")
+        .arg(path)
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git blame failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_blame(&String::from_utf8_lossy(&output.stdout), &path.to_string_lossy()))
+}
+
 /// Blame information for a file
 #[derive(Debug, Clone)]
 pub struct Blame {
@@ -41,9 +136,37 @@ pub struct BlameLine {
     pub line_number: usize,
     pub original_line: usize,
     pub summary: Option<String>,
+    /// Path of the file in the blamed commit, which differs from the blamed
+    /// path when the line was moved or copied from another file
+    pub original_path: String,
+    /// Parent commit and path to blame next to see the line before this commit
+    pub previous: Option<(String, String)>,
+    /// Text of the line
+    pub content: String,
 }
 
 impl BlameLine {
+    /// Line not committed yet
+    pub fn uncommitted(line_number: usize, content: impl Into<String>) -> Self {
+        Self {
+            commit_id: UNCOMMITTED.to_string(),
+            author: "Not Committed Yet".to_string(),
+            email: String::new(),
+            timestamp: 0,
+            line_number,
+            original_line: line_number,
+            summary: None,
+            original_path: String::new(),
+            previous: None,
+            content: content.into(),
+        }
+    }
+
+    /// Whether the line comes from a commit
+    pub fn is_committed(&self) -> bool {
+        self.commit_id != UNCOMMITTED
+    }
+
     /// Get short commit ID
     pub fn short_id(&self) -> &str {
         if self.commit_id.len() >= 7 {
@@ -86,57 +209,105 @@ impl BlameLine {
 }
 
 /// Parse porcelain blame output
+///
+/// Porcelain output only describes a commit the first time it appears, so
+/// later lines from the same commit reuse what was read then.
 pub fn parse_blame(output: &str, path: &str) -> Blame {
     let mut blame = Blame::new(path);
-    let mut lines = output.lines().peekable();
-    let mut line_number = 1usize;
+    let mut commits: HashMap<String, BlameLine> = HashMap::new();
+    let mut lines = output.lines();
 
     while let Some(header) = lines.next() {
+        // <commit> <original line> <final line> [<lines in group>]
         let parts: Vec<&str> = header.split_whitespace().collect();
-        if parts.is_empty() {
+        if parts.len() < 3 {
             continue;
         }
 
         let commit_id = parts[0].to_string();
-        let original_line = parts.get(1).and_then(|s| s.parse().ok()).unwrap_or(line_number);
-        
-        // Parse metadata lines
-        let mut author = String::new();
-        let mut email = String::new();
-        let mut timestamp = 0i64;
-        let mut summary = None;
-
-        while let Some(line) = lines.peek() {
-            if line.starts_with('\t') {
-                // Content line
-                lines.next();
+        let mut line = commits.get(&commit_id).cloned().unwrap_or_else(|| BlameLine {
+            commit_id: commit_id.clone(),
+            author: String::new(),
+            email: String::new(),
+            timestamp: 0,
+            line_number: 0,
+            original_line: 0,
+            summary: None,
+            original_path: path.to_string(),
+            previous: None,
+            content: String::new(),
+        });
+        line.original_line = parts[1].parse().unwrap_or(0);
+        line.line_number = parts[2].parse().unwrap_or(blame.lines.len() + 1);
+
+        // Metadata lines, up to the tab-prefixed content line
+        for meta in lines.by_ref() {
+            if let Some(content) = meta.strip_prefix('\t') {
+                line.content = content.to_string();
                 break;
             }
-            
-            let line = lines.next().unwrap();
-            if let Some(value) = line.strip_prefix("author ") {
-                author = value.to_string();
-            } else if let Some(value) = line.strip_prefix("author-mail ") {
-                email = value.trim_matches(|c| c == '<' || c == '>').to_string();
-            } else if let Some(value) = line.strip_prefix("author-time ") {
-                timestamp = value.parse().unwrap_or(0);
-            } else if let Some(value) = line.strip_prefix("summary ") {
-                summary = Some(value.to_string());
+
+            if let Some(value) = meta.strip_prefix("author ") {
+                line.author = value.to_string();
+            } else if let Some(value) = meta.strip_prefix("author-mail ") {
+                line.email = value.trim_matches(|c| c == '<' || c == '>').to_string();
+            } else if let Some(value) = meta.strip_prefix("author-time ") {
+                line.timestamp = value.parse().unwrap_or(0);
+            } else if let Some(value) = meta.strip_prefix("summary ") {
+                line.summary = Some(value.to_string());
+            } else if let Some(value) = meta.strip_prefix("filename ") {
+                line.original_path = value.to_string();
+            } else if let Some((commit, file)) = meta.strip_prefix("previous ").and_then(|v| v.split_once(' ')) {
+                line.previous = Some((commit.to_string(), file.to_string()));
             }
         }
 
-        blame.lines.push(BlameLine {
-            commit_id,
-            author,
-            email,
-            timestamp,
-            line_number,
-            original_line,
-            summary,
-        });
-
-        line_number += 1;
+        commits.insert(commit_id, line.clone());
+        blame.lines.push(line);
     }
 
     blame
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git_with_env, test_repo};
+
+    #[tokio::test]
+    async fn test_blame_ignores_revs() {
+        let dir = test_repo("blame");
+        let git = |author: &str, args: &[&str]| {
+            let env = [("GIT_AUTHOR_NAME", author), ("GIT_COMMITTER_NAME", author)];
+            git_with_env(&dir, &env, args).trim().to_string()
+        };
+
+        std::fs::write(dir.join("main.c"), "int a;\nint b;\n").unwrap();
+        git("Ann", &["add", "main.c"]);
+        git("Ann", &["commit", "-q", "-m", "Add main"]);
+        let original = git("Ann", &["rev-parse", "HEAD"]);
+        std::fs::write(dir.join("main.c"), "int  a;\nint  b;\n").unwrap();
+        git("Bob", &["commit", "-q", "-am", "Reformat"]);
+        let reformat = git("Bob", &["rev-parse", "HEAD"]);
+        std::fs::write(dir.join("main.c"), "int  a;\nint  b;\nint c;\n").unwrap();
+
+        let path = Path::new("main.c");
+        let blame = blame_file(&dir, path, &BlameOptions::default()).await.unwrap();
+        assert_eq!(blame.lines.len(), 3);
+        assert_eq!(blame.lines[0].author, "Bob");
+        assert_eq!(blame.lines[1].author, "Bob");
+        assert_eq!(blame.lines[1].previous, Some((original.clone(), "main.c".to_string())));
+        assert!(!blame.lines[2].is_committed());
+
+        std::fs::write(dir.join(IGNORE_REVS_FILE), format!("{}\n", reformat)).unwrap();
+        let blame = blame_file(&dir, path, &BlameOptions::default()).await.unwrap();
+        assert_eq!(blame.commits(), vec![UNCOMMITTED, original.as_str()]);
+        assert_eq!(blame.lines[1].content, "int  b;");
+
+        // Blaming the previous revision shows the line before the reformat
+        let blame = blame_file(&dir, path, &BlameOptions::default().at(&original)).await.unwrap();
+        assert_eq!(blame.lines[0].content, "int a;");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
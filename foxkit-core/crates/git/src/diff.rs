@@ -1,6 +1,7 @@
 //! Git diff
 
 use std::ops::Range;
+use std::path::{Path, PathBuf};
 
 /// A diff between two versions
 #[derive(Debug, Clone)]
@@ -36,6 +37,58 @@ impl Diff {
             .filter(|l| l.kind == DiffLineKind::Deletion)
             .count()
     }
+
+    /// Path of the file after the change, without the `b/` prefix
+    pub fn path(&self) -> Option<&str> {
+        let path = match self.new_path.as_deref() {
+            Some("/dev/null") | None => self.old_path.as_deref()?,
+            Some(path) => path,
+        };
+        Some(path.strip_prefix("b/").or_else(|| path.strip_prefix("a/")).unwrap_or(path))
+    }
+
+    /// Added lines in the new file (1-based)
+    pub fn added_lines(&self) -> Vec<u32> {
+        self.hunks.iter()
+            .flat_map(|h| &h.lines)
+            .filter(|l| l.kind == DiffLineKind::Addition)
+            .filter_map(|l| l.new_line)
+            .collect()
+    }
+
+    /// Hunk touching a line of the new file (1-based)
+    ///
+    /// Pure deletions are matched on the line they were removed after, which
+    /// is where the gutter shows their marker.
+    pub fn hunk_at_line(&self, line: u32) -> Option<&DiffHunk> {
+        self.hunks.iter().find(|h| {
+            if h.new_lines == 0 {
+                h.new_start.max(1) == line
+            } else {
+                h.new_range().contains(&line)
+            }
+        })
+    }
+
+    /// Line of the old file (1-based) that a line of the new file came from
+    ///
+    /// Needs hunks without context (`-U0`). Changed lines map to the start
+    /// of the lines they replaced, added lines to the line before them.
+    pub fn old_line(&self, line: u32) -> u32 {
+        let mut offset: i64 = 0;
+        for hunk in &self.hunks {
+            // A pure deletion sits after `new_start`
+            let first = if hunk.new_lines == 0 { hunk.new_start + 1 } else { hunk.new_start };
+            if line < first {
+                break;
+            }
+            if line < first + hunk.new_lines {
+                return hunk.old_start.max(1);
+            }
+            offset += hunk.old_lines as i64 - hunk.new_lines as i64;
+        }
+        (line as i64 + offset).max(1) as u32
+    }
 }
 
 impl Default for Diff {
@@ -74,6 +127,8 @@ pub struct DiffLine {
     pub content: String,
     pub old_line: Option<u32>,
     pub new_line: Option<u32>,
+    /// Followed by `\ No newline at end of file`
+    pub no_newline: bool,
 }
 
 /// Diff line kind
@@ -119,6 +174,14 @@ pub fn parse_unified_diff(diff_text: &str) -> Diff {
                 current_hunk = Some(hunk);
             }
         } else if let Some(ref mut hunk) = current_hunk {
+            if line.starts_with('\\') {
+                // "\ No newline at end of file" marks the line before it
+                if let Some(last) = hunk.lines.last_mut() {
+                    last.no_newline = true;
+                }
+                continue;
+            }
+
             let (kind, content) = if line.starts_with('+') {
                 (DiffLineKind::Addition, &line[1..])
             } else if line.starts_with('-') {
@@ -146,6 +209,7 @@ pub fn parse_unified_diff(diff_text: &str) -> Diff {
                 } else { 
                     None 
                 },
+                no_newline: false,
             };
             hunk.lines.push(diff_line);
         } else if line.starts_with("---") {
@@ -162,6 +226,120 @@ pub fn parse_unified_diff(diff_text: &str) -> Diff {
     diff
 }
 
+/// Parse the output of `git diff`, one `Diff` per file
+pub fn parse_unified_diffs(diff_text: &str) -> Vec<Diff> {
+    let mut diffs = Vec::new();
+    let mut current = String::new();
+
+    for line in diff_text.lines() {
+        if line.starts_with("diff --git ") && !current.is_empty() {
+            diffs.push(parse_unified_diff(&current));
+            current.clear();
+        }
+        current.push_str(line);
+        current.push('\n');
+    }
+    if !current.is_empty() {
+        diffs.push(parse_unified_diff(&current));
+    }
+
+    diffs.retain(|d| d.path().is_some());
+    diffs
+}
+
+/// Diff a single file, either the working tree against the index or, when
+/// `staged` is set, the index against HEAD
+///
+/// Hunks keep three lines of context so they can be turned back into
+/// patches with [`crate::patch::build_patch`].
+pub async fn diff_file(repo_path: &Path, path: &Path, staged: bool) -> anyhow::Result<Option<Diff>> {
+    let mut args = vec!["diff", "--no-color", "--no-ext-diff", "-U3"];
+    if staged {
+        args.push("--cached");
+    }
+
+    let output = tokio::process::Command::new("git")
+        .args(&args)
+        .arg("--")
+        .arg(path)
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_unified_diffs(&String::from_utf8_lossy(&output.stdout)).into_iter().next())
+}
+
+/// Diff two blobs given as `<revision>:<path>`, without context, or `None`
+/// when they're the same
+pub async fn diff_blobs(repo_path: &Path, old: &str, new: &str) -> anyhow::Result<Option<Diff>> {
+    let output = tokio::process::Command::new("git")
+        .args(["diff", "--no-color", "--no-ext-diff", "-U0", old, new])
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_unified_diffs(&String::from_utf8_lossy(&output.stdout)).into_iter().next())
+}
+
+/// Find the merge base of HEAD and `base`
+pub async fn merge_base(repo_path: &Path, base: &str) -> anyhow::Result<String> {
+    let output = tokio::process::Command::new("git")
+        .args(["merge-base", "HEAD", base])
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git merge-base failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Diff the working tree against the merge base of HEAD and `base`
+pub async fn diff_merge_base(repo_path: &Path, base: &str) -> anyhow::Result<Vec<Diff>> {
+    let merge_base = merge_base(repo_path, base).await?;
+
+    let output = tokio::process::Command::new("git")
+        .args(["diff", "--no-color", "--no-ext-diff", "-U0", &merge_base])
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_unified_diffs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Files changed in the working tree since the merge base of HEAD and
+/// `base`, relative to the repository root
+///
+/// Renames are reported as a deletion and an addition so both paths show up.
+pub async fn changed_files(repo_path: &Path, base: &str) -> anyhow::Result<Vec<PathBuf>> {
+    let merge_base = merge_base(repo_path, base).await?;
+
+    let output = tokio::process::Command::new("git")
+        .args(["diff", "--name-only", "--no-renames", "-z", &merge_base])
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git diff failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
 fn parse_hunk_header(line: &str) -> Option<DiffHunk> {
     // @@ -old_start,old_lines +new_start,new_lines @@ optional header
     let parts: Vec<&str> = line.split_whitespace().collect();
@@ -198,3 +376,42 @@ fn parse_range(s: &str) -> Option<(u32, u32)> {
         Some((s.parse().ok()?, 1))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unified_diffs() {
+        let text = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -3,0 +4,2 @@ fn main() {
++    let x = 1;
++    let y = 2;
+@@ -10 +12 @@
+-    old();
++    new();
+diff --git a/gone.rs b/gone.rs
+deleted file mode 100644
+--- a/gone.rs
++++ /dev/null
+@@ -1 +0,0 @@
+-fn gone() {}
+";
+        let diffs = parse_unified_diffs(text);
+        assert_eq!(diffs.len(), 2);
+
+        assert_eq!(diffs[0].path(), Some("src/lib.rs"));
+        assert_eq!(diffs[0].hunks.len(), 2);
+        assert_eq!(diffs[0].added_lines(), vec![4, 5, 12]);
+        assert_eq!(diffs[0].deletions(), 1);
+        let old: Vec<_> = [3, 4, 5, 6, 12, 13].into_iter().map(|line| diffs[0].old_line(line)).collect();
+        assert_eq!(old, vec![3, 3, 3, 4, 10, 11]);
+
+        assert_eq!(diffs[1].path(), Some("gone.rs"));
+        assert!(diffs[1].added_lines().is_empty());
+    }
+}
@@ -2,18 +2,28 @@
 //!
 //! Git integration for version control.
 
+pub mod bisect;
 pub mod blame;
 pub mod diff;
+pub mod operations;
+pub mod patch;
+pub mod rebase;
 pub mod repository;
 pub mod status;
+#[cfg(any(test, feature = "test-support"))]
+pub mod test_support;
 
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use parking_lot::RwLock;
 
-pub use blame::{Blame, BlameLine};
+pub use bisect::{BisectMark, BisectRange, BisectStep};
+pub use blame::{Blame, BlameLine, BlameOptions};
 pub use diff::{Diff, DiffHunk, DiffLine, DiffLineKind};
-pub use repository::{Repository, Commit, Branch, Remote};
+pub use operations::{ConflictStages, GitOperations, GitError, GitResult, RepositoryState};
+pub use patch::HunkSelection;
+pub use rebase::{FixupMessage, RebaseStop, RebaseTodo, TodoAction, TodoItem};
+pub use repository::{Repository, Commit, Branch, Remote, DiffBase};
 pub use status::{Status, FileStatus, StatusKind};
 
 /// Git manager
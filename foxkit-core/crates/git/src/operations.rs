@@ -8,6 +8,14 @@
 
 use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use std::process::Stdio;
+
+use tokio::io::AsyncWriteExt;
+
+use crate::bisect::{BisectRange, BisectStep};
+use crate::diff::DiffHunk;
+use crate::patch::{build_patch, HunkSelection};
+use crate::rebase::{RebaseStop, RebaseTodo, TodoAction, TodoItem};
 
 /// Result type for git operations.
 pub type GitResult<T> = Result<T, GitError>;
@@ -420,6 +428,11 @@ impl GitOperations {
         }
     }
 
+    /// Repository path.
+    pub fn repo_path(&self) -> &Path {
+        &self.repo_path
+    }
+
     /// Set credentials provider.
     pub fn with_credentials<P: CredentialsProvider + Send + Sync + 'static>(
         mut self,
@@ -473,6 +486,62 @@ impl GitOperations {
         Ok(())
     }
 
+    /// Stage the selected changes of an unstaged hunk.
+    ///
+    /// `hunk` comes from the working tree diff of `path`
+    /// (`diff::diff_file(.., false)`).
+    pub async fn stage_hunk(&self, path: &Path, hunk: &DiffHunk, selection: &HunkSelection) -> GitResult<()> {
+        self.apply_hunk(path, hunk, selection, &["--cached"]).await
+    }
+
+    /// Unstage the selected changes of a staged hunk.
+    ///
+    /// `hunk` comes from the index diff of `path` (`diff::diff_file(.., true)`).
+    pub async fn unstage_hunk(&self, path: &Path, hunk: &DiffHunk, selection: &HunkSelection) -> GitResult<()> {
+        self.apply_hunk(path, hunk, selection, &["--cached", "--reverse"]).await
+    }
+
+    /// Discard the selected changes of an unstaged hunk from the working tree.
+    pub async fn discard_hunk(&self, path: &Path, hunk: &DiffHunk, selection: &HunkSelection) -> GitResult<()> {
+        self.apply_hunk(path, hunk, selection, &["--reverse"]).await
+    }
+
+    /// Build a patch from the selection and feed it to `git apply`.
+    async fn apply_hunk(
+        &self,
+        path: &Path,
+        hunk: &DiffHunk,
+        selection: &HunkSelection,
+        args: &[&str],
+    ) -> GitResult<()> {
+        let reverse = args.contains(&"--reverse");
+        let path = path.to_string_lossy().replace('\\', "/");
+        let Some(patch) = build_patch(&path, hunk, selection, reverse) else {
+            return Ok(());
+        };
+
+        let mut child = tokio::process::Command::new("git")
+            .arg("apply")
+            .args(args)
+            .args(["--whitespace=nowarn", "-"])
+            .current_dir(&self.repo_path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|e| GitError::Other(e.to_string()))?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(patch.as_bytes()).await.map_err(|e| GitError::Other(e.to_string()))?;
+        }
+        let output = child.wait_with_output().await.map_err(|e| GitError::Other(e.to_string()))?;
+
+        if !output.status.success() {
+            return Err(GitError::IndexError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        Ok(())
+    }
+
     /// Create a commit.
     pub async fn commit(&self, options: &CommitOptions) -> GitResult<CommitInfo> {
         if options.message.is_empty() && !options.amend {
@@ -597,40 +666,352 @@ impl GitOperations {
     }
 
     /// Start a rebase.
+    ///
+    /// Interactive rebases replay the list from [`Self::rebase_todo`] as is;
+    /// use [`Self::rebase_interactive`] to edit it first.
     pub async fn rebase(&self, options: &RebaseOptions) -> GitResult<RebaseResult> {
-        Ok(RebaseResult {
-            completed: true,
-            current_step: 0,
-            total_steps: 0,
-            conflicts: vec![],
-        })
+        if options.interactive {
+            let todo = self.rebase_todo(&options.onto, options.autosquash).await?;
+            return self.rebase_interactive(&todo, options).await;
+        }
+
+        let onto = self.resolve_commit(&options.onto).await?;
+        let mut args = vec!["rebase"];
+        if options.autostash {
+            args.push("--autostash");
+        }
+        if options.preserve_merges {
+            args.push("--rebase-merges");
+        }
+        args.push(&onto);
+
+        let output = self.git(&args, &[]).await?;
+        self.rebase_result(&output).await
+    }
+
+    /// List the commits between `onto` and HEAD as an interactive rebase
+    /// todo list, optionally autosquashing `fixup!` and `squash!` commits.
+    pub async fn rebase_todo(&self, onto: &str, autosquash: bool) -> GitResult<RebaseTodo> {
+        // Like git's own todo list: commits already upstream are left out
+        let range = format!("{}...HEAD", self.resolve_commit(onto).await?);
+        let output = self
+            .git(
+                &["log", "--reverse", "--topo-order", "--no-merges", "--cherry-pick", "--right-only", "--format=%H%x1f%s", &range],
+                &[],
+            )
+            .await?;
+        if !output.status.success() {
+            return Err(GitError::Other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+
+        let mut todo = RebaseTodo::new(onto);
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            if let Some((commit, summary)) = line.split_once('\x1f') {
+                todo.items.push(TodoItem::pick(commit, summary));
+            }
+        }
+        if autosquash {
+            todo.autosquash();
+        }
+        Ok(todo)
+    }
+
+    /// Run an interactive rebase with an edited todo list.
+    ///
+    /// The list is handed to `git rebase -i` through the sequence editor and
+    /// no editor is opened: rewording and squashing use the messages set on
+    /// the items, or keep the ones git proposes. Stops for `edit` steps,
+    /// conflicts and failed `exec` steps are reported in the result.
+    pub async fn rebase_interactive(&self, todo: &RebaseTodo, options: &RebaseOptions) -> GitResult<RebaseResult> {
+        todo.validate().map_err(GitError::Other)?;
+        let onto = self.resolve_commit(&todo.onto).await?;
+
+        let dir = self.git_dir().await?.join(REBASE_FILES);
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+        tokio::fs::create_dir_all(&dir).await.map_err(|e| GitError::Other(e.to_string()))?;
+
+        let mut text = String::new();
+        for (index, item) in todo.items.iter().enumerate() {
+            let Some(message) = item.message.as_ref().filter(|_| matches!(item.action, TodoAction::Reword | TodoAction::Squash)) else {
+                text.push_str(&format!("{}\n", item));
+                continue;
+            };
+
+            // Amend the message right after the step instead of opening an editor
+            let path = dir.join(format!("message-{}", index));
+            tokio::fs::write(&path, message).await.map_err(|e| GitError::Other(e.to_string()))?;
+            let action = if item.action == TodoAction::Reword { TodoAction::Pick } else { item.action };
+            text.push_str(&format!("{}\n", TodoItem { action, ..item.clone() }));
+            text.push_str(&format!(
+                "exec git commit --amend --only --no-verify --allow-empty -F {}\n",
+                shell_quote(&path.to_string_lossy())
+            ));
+        }
+
+        let todo_path = dir.join("git-rebase-todo");
+        tokio::fs::write(&todo_path, text).await.map_err(|e| GitError::Other(e.to_string()))?;
+
+        let sequence_editor = format!("cp {}", shell_quote(&todo_path.to_string_lossy()));
+        let mut args = vec!["rebase", "-i", "--no-autosquash"];
+        if options.autostash {
+            args.push("--autostash");
+        }
+        args.push(&onto);
+
+        let output = self
+            .git(&args, &[("GIT_SEQUENCE_EDITOR", &sequence_editor), ("GIT_EDITOR", ":")])
+            .await?;
+        self.rebase_result(&output).await
     }
 
     /// Continue a rebase.
     pub async fn rebase_continue(&self) -> GitResult<RebaseResult> {
-        Ok(RebaseResult {
-            completed: true,
-            current_step: 0,
-            total_steps: 0,
-            conflicts: vec![],
-        })
+        let output = self.git(&["rebase", "--continue"], &[("GIT_EDITOR", ":")]).await?;
+        self.rebase_result(&output).await
     }
 
     /// Abort a rebase.
     pub async fn rebase_abort(&self) -> GitResult<()> {
+        let output = self.git(&["rebase", "--abort"], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::Other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        let _ = tokio::fs::remove_dir_all(self.git_dir().await?.join(REBASE_FILES)).await;
         Ok(())
     }
 
     /// Skip current rebase step.
     pub async fn rebase_skip(&self) -> GitResult<RebaseResult> {
+        let output = self.git(&["rebase", "--skip"], &[("GIT_EDITOR", ":")]).await?;
+        self.rebase_result(&output).await
+    }
+
+    /// Work out where a rebase command left the repository.
+    async fn rebase_result(&self, output: &std::process::Output) -> GitResult<RebaseResult> {
+        let git_dir = self.git_dir().await?;
+        let state = self.state().await?;
+
+        if !matches!(
+            state,
+            RepositoryState::Rebase
+                | RepositoryState::RebaseInteractive
+                | RepositoryState::RebaseMerge
+                | RepositoryState::ApplyMailboxOrRebase
+        ) {
+            if !output.status.success() {
+                return Err(GitError::Other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+            }
+            let _ = tokio::fs::remove_dir_all(git_dir.join(REBASE_FILES)).await;
+            return Ok(RebaseResult {
+                completed: true,
+                current_step: 0,
+                total_steps: 0,
+                conflicts: vec![],
+                stop: None,
+            });
+        }
+
+        let merge_dir = git_dir.join("rebase-merge");
+        let (dir, step_file, total_file) = if merge_dir.exists() {
+            (merge_dir, "msgnum", "end")
+        } else {
+            (git_dir.join("rebase-apply"), "next", "last")
+        };
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).ok().map(|s| s.trim().to_string());
+        let number = |name: &str| read(name).and_then(|s| s.parse().ok()).unwrap_or(0);
+
+        let conflicts = self.unmerged_paths().await?;
+        let stopped = read("stopped-sha");
+        let last_done = read("done")
+            .and_then(|done| done.lines().rev().find_map(TodoItem::parse))
+            .and_then(Result::ok);
+
+        let stop = if !conflicts.is_empty() {
+            Some(RebaseStop::Conflict { commit: stopped })
+        } else {
+            match last_done {
+                Some(item) if item.action == TodoAction::Exec && !output.status.success() => {
+                    Some(RebaseStop::Exec { command: item.target })
+                }
+                Some(item) if item.action == TodoAction::Edit => Some(RebaseStop::Edit {
+                    commit: stopped.unwrap_or(item.target),
+                }),
+                _ if !output.status.success() => Some(RebaseStop::Conflict { commit: stopped }),
+                _ => None,
+            }
+        };
+
         Ok(RebaseResult {
-            completed: true,
-            current_step: 0,
-            total_steps: 0,
-            conflicts: vec![],
+            completed: false,
+            current_step: number(step_file),
+            total_steps: number(total_file),
+            conflicts,
+            stop,
         })
     }
 
+    /// Paths with unresolved conflicts.
+    async fn unmerged_paths(&self) -> GitResult<Vec<PathBuf>> {
+        let output = self.git(&["diff", "--name-only", "--diff-filter=U", "-z"], &[]).await?;
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from)
+            .collect())
+    }
+
+    /// Versions of a conflicted file from the index stages: the merge base
+    /// (`:1:`), ours (`:2:`) and theirs (`:3:`), or `None` when the file has
+    /// no conflict.
+    pub async fn conflict_stages(&self, path: &Path) -> GitResult<Option<ConflictStages>> {
+        let path_arg = path.to_string_lossy();
+        let output = self.git(&["ls-files", "--unmerged", "-z", "--", &path_arg], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::IndexError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+
+        let mut stages = ConflictStages::default();
+        let mut found = false;
+        for entry in String::from_utf8_lossy(&output.stdout).split('\0') {
+            // <mode> <object> <stage>\t<path>
+            let Some((info, _)) = entry.split_once('\t') else {
+                continue;
+            };
+            let fields: Vec<&str> = info.split(' ').collect();
+            let [_, object, stage] = fields[..] else {
+                continue;
+            };
+
+            let blob = self.git(&["cat-file", "blob", object], &[]).await?;
+            if !blob.status.success() {
+                return Err(GitError::IndexError(String::from_utf8_lossy(&blob.stderr).trim().to_string()));
+            }
+            match stage {
+                "1" => stages.base = Some(blob.stdout),
+                "2" => stages.ours = Some(blob.stdout),
+                "3" => stages.theirs = Some(blob.stdout),
+                _ => continue,
+            }
+            found = true;
+        }
+        Ok(found.then_some(stages))
+    }
+
+    /// Mark a conflicted file as resolved by staging its working tree version.
+    pub async fn mark_resolved(&self, path: &Path) -> GitResult<()> {
+        let path_arg = path.to_string_lossy();
+        let output = self.git(&["add", "--", &path_arg], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::IndexError(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        Ok(())
+    }
+
+    /// Start bisecting between a bad commit and known good commits.
+    pub async fn bisect_start(&self, bad: &str, good: &[&str]) -> GitResult<BisectStep> {
+        let mut commits = vec![self.resolve_commit(bad).await?];
+        for commit in good {
+            commits.push(self.resolve_commit(commit).await?);
+        }
+        let mut args = vec!["bisect", "start"];
+        args.extend(commits.iter().map(String::as_str));
+        args.push("--");
+        self.bisect(&args).await
+    }
+
+    /// Mark a commit (default: the one checked out) as good.
+    pub async fn bisect_good(&self, commit: Option<&str>) -> GitResult<BisectStep> {
+        self.bisect_mark("good", commit).await
+    }
+
+    /// Mark a commit (default: the one checked out) as bad.
+    pub async fn bisect_bad(&self, commit: Option<&str>) -> GitResult<BisectStep> {
+        self.bisect_mark("bad", commit).await
+    }
+
+    /// Skip a commit (default: the one checked out) that can't be tested.
+    pub async fn bisect_skip(&self, commit: Option<&str>) -> GitResult<BisectStep> {
+        self.bisect_mark("skip", commit).await
+    }
+
+    async fn bisect_mark(&self, mark: &str, commit: Option<&str>) -> GitResult<BisectStep> {
+        let commit = match commit {
+            Some(commit) => Some(self.resolve_commit(commit).await?),
+            None => None,
+        };
+        self.bisect(&["bisect", mark].into_iter().chain(commit.as_deref()).collect::<Vec<_>>()).await
+    }
+
+    /// Check out the next commit to test.
+    pub async fn bisect_next(&self) -> GitResult<BisectStep> {
+        self.bisect(&["bisect", "next"]).await
+    }
+
+    /// End the bisect and go back to the original HEAD.
+    pub async fn bisect_reset(&self) -> GitResult<()> {
+        let output = self.git(&["bisect", "reset"], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::Other(String::from_utf8_lossy(&output.stderr).trim().to_string()));
+        }
+        Ok(())
+    }
+
+    /// Commits marked so far and those still candidates for the first bad
+    /// commit, or `None` when not bisecting.
+    pub async fn bisect_range(&self) -> GitResult<Option<BisectRange>> {
+        if self.state().await? != RepositoryState::Bisect {
+            return Ok(None);
+        }
+
+        let output = self
+            .git(&["for-each-ref", "--format=%(refname) %(objectname)", "refs/bisect"], &[])
+            .await?;
+        let mut range = BisectRange::default();
+        for line in String::from_utf8_lossy(&output.stdout).lines() {
+            let Some((name, commit)) = line.split_once(' ') else {
+                continue;
+            };
+            let name = name.trim_start_matches("refs/bisect/");
+            if name == "bad" {
+                range.bad = Some(commit.to_string());
+            } else if name.starts_with("good-") {
+                range.good.push(commit.to_string());
+            } else if name.starts_with("skip-") {
+                range.skipped.push(commit.to_string());
+            }
+        }
+
+        if let Some(bad) = &range.bad
+            && !range.good.is_empty()
+        {
+            let mut args = vec!["rev-list", bad.as_str(), "--not"];
+            args.extend(range.good.iter().map(String::as_str));
+            let output = self.git(&args, &[]).await?;
+            range.remaining = String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .filter(|commit| Some(*commit) != range.bad.as_deref())
+                .map(str::to_string)
+                .collect();
+        }
+
+        let output = self.git(&["rev-parse", "HEAD"], &[]).await?;
+        if output.status.success() {
+            range.current = Some(String::from_utf8_lossy(&output.stdout).trim().to_string());
+        }
+        Ok(Some(range))
+    }
+
+    /// Run a bisect command and work out where it left the search.
+    async fn bisect(&self, args: &[&str]) -> GitResult<BisectStep> {
+        let output = self.git(args, &[]).await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        match BisectStep::parse(&stdout) {
+            Some(step) => Ok(step),
+            None if output.status.success() => Ok(BisectStep::Waiting),
+            None => Err(GitError::Other(String::from_utf8_lossy(&output.stderr).trim().to_string())),
+        }
+    }
+
     /// Create a stash.
     pub async fn stash(&self, options: &StashOptions) -> GitResult<StashEntry> {
         Ok(StashEntry {
@@ -708,8 +1089,84 @@ impl GitOperations {
 
     /// Get repository state.
     pub async fn state(&self) -> GitResult<RepositoryState> {
-        Ok(RepositoryState::Clean)
+        let git_dir = self.git_dir().await?;
+        let exists = |name: &str| git_dir.join(name).exists();
+
+        let state = if exists("rebase-merge/interactive") {
+            RepositoryState::RebaseInteractive
+        } else if exists("rebase-merge") {
+            RepositoryState::RebaseMerge
+        } else if exists("rebase-apply/rebasing") {
+            RepositoryState::Rebase
+        } else if exists("rebase-apply/applying") {
+            RepositoryState::ApplyMailbox
+        } else if exists("rebase-apply") {
+            RepositoryState::ApplyMailboxOrRebase
+        } else if exists("MERGE_HEAD") {
+            RepositoryState::Merge
+        } else if exists("REVERT_HEAD") {
+            if exists("sequencer/todo") {
+                RepositoryState::RevertSequence
+            } else {
+                RepositoryState::Revert
+            }
+        } else if exists("CHERRY_PICK_HEAD") {
+            if exists("sequencer/todo") {
+                RepositoryState::CherryPickSequence
+            } else {
+                RepositoryState::CherryPick
+            }
+        } else if exists("BISECT_LOG") {
+            RepositoryState::Bisect
+        } else {
+            RepositoryState::Clean
+        };
+        Ok(state)
+    }
+
+    /// Absolute path of the repository's git directory.
+    async fn git_dir(&self) -> GitResult<PathBuf> {
+        let output = self.git(&["rev-parse", "--absolute-git-dir"], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::NotARepository(self.repo_path.clone()));
+        }
+        Ok(PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()))
     }
+
+    /// Resolve a revision given by the user to a commit id, so it can't be
+    /// taken for an option when passed on to git.
+    async fn resolve_commit(&self, rev: &str) -> GitResult<String> {
+        if rev.is_empty() || rev.starts_with('-') {
+            return Err(GitError::RefError(format!("Invalid revision: {}", rev)));
+        }
+        let spec = format!("{}^{{commit}}", rev);
+        let output = self.git(&["rev-parse", "--verify", "--quiet", "--end-of-options", &spec], &[]).await?;
+        if !output.status.success() {
+            return Err(GitError::RefError(format!("Unknown revision: {}", rev)));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Run git in the repository and collect its output.
+    async fn git(&self, args: &[&str], envs: &[(&str, &str)]) -> GitResult<std::process::Output> {
+        tokio::process::Command::new("git")
+            .args(args)
+            .envs(envs.iter().copied())
+            .current_dir(&self.repo_path)
+            .stdin(Stdio::null())
+            .output()
+            .await
+            .map_err(|e| GitError::Other(e.to_string()))
+    }
+}
+
+/// Directory in the git dir holding the todo list and messages of an
+/// interactive rebase started from here.
+const REBASE_FILES: &str = "foxkit-rebase";
+
+/// Quote a string for the POSIX shell git runs editors and `exec` steps in.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
 }
 
 /// Commit information.
@@ -751,6 +1208,17 @@ pub struct MergeResult {
     pub conflicts: Vec<PathBuf>,
 }
 
+/// Versions of a conflicted file in the index.
+///
+/// A stage is missing when that side doesn't have the file, e.g. there is
+/// no base when both sides added it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConflictStages {
+    pub base: Option<Vec<u8>>,
+    pub ours: Option<Vec<u8>>,
+    pub theirs: Option<Vec<u8>>,
+}
+
 /// Result of a rebase operation.
 #[derive(Debug, Clone)]
 pub struct RebaseResult {
@@ -758,6 +1226,8 @@ pub struct RebaseResult {
     pub current_step: usize,
     pub total_steps: usize,
     pub conflicts: Vec<PathBuf>,
+    /// Why an unfinished rebase stopped.
+    pub stop: Option<RebaseStop>,
 }
 
 /// Reset modes.
@@ -912,6 +1382,7 @@ pub struct BatchResult {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::{git_in, test_repo};
 
     #[tokio::test]
     async fn test_commit_options() {
@@ -942,4 +1413,184 @@ mod tests {
         let commit = ops.commit(&CommitOptions::with_message("test")).await.unwrap();
         assert!(!commit.hash.is_empty());
     }
+
+    #[tokio::test]
+    async fn test_stage_and_discard_lines() {
+        let dir = test_repo("partial-stage");
+        let git = |args: &[&str]| git_in(&dir, args);
+        std::fs::write(dir.join("notes.txt"), "one\ntwo\nthree\nfour\n").unwrap();
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+        std::fs::write(dir.join("notes.txt"), "one\nTWO\nTHREE\nfour\n").unwrap();
+
+        let ops = GitOperations::new(&dir);
+        let path = Path::new("notes.txt");
+
+        // Stage the change on line 2 from the gutter
+        let diff = crate::diff::diff_file(&dir, path, false).await.unwrap().unwrap();
+        let hunk = diff.hunk_at_line(2).unwrap();
+        ops.stage_hunk(path, hunk, &HunkSelection::NewLines(2..=2)).await.unwrap();
+        assert_eq!(git(&["show", ":notes.txt"]), "one\nTWO\nthree\nfour\n");
+
+        // Discard what is left of the working tree change
+        let diff = crate::diff::diff_file(&dir, path, false).await.unwrap().unwrap();
+        ops.discard_hunk(path, &diff.hunks[0], &HunkSelection::All).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "one\nTWO\nthree\nfour\n");
+
+        // And unstage the staged line again
+        let diff = crate::diff::diff_file(&dir, path, true).await.unwrap().unwrap();
+        ops.unstage_hunk(path, &diff.hunks[0], &HunkSelection::All).await.unwrap();
+        assert_eq!(git(&["show", ":notes.txt"]), "one\ntwo\nthree\nfour\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_interactive_rebase() {
+        let dir = test_repo("interactive-rebase");
+        let git = |args: &[&str]| git_in(&dir, args);
+        let commit = |file: &str, subject: &str| {
+            std::fs::write(dir.join(file), subject).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", subject]);
+        };
+        commit("base.txt", "Base");
+        commit("parser.rs", "Add parser");
+        commit("lexer.rs", "Add lexer");
+        commit("parser.rs", "fixup! Add parser");
+
+        let ops = GitOperations::new(&dir);
+        let mut todo = ops.rebase_todo("HEAD~3", true).await.unwrap();
+        let actions: Vec<_> = todo.items.iter().map(|i| i.action).collect();
+        assert_eq!(actions, vec![TodoAction::Pick, TodoAction::Fixup, TodoAction::Pick]);
+
+        todo.set_action(0, TodoAction::Edit);
+        todo.reword(2, "Add a lexer");
+
+        let result = ops.rebase_interactive(&todo, &RebaseOptions::default()).await.unwrap();
+        assert!(!result.completed);
+        assert!(matches!(result.stop, Some(RebaseStop::Edit { .. })));
+        assert_eq!(ops.state().await.unwrap(), RepositoryState::RebaseInteractive);
+
+        let result = ops.rebase_continue().await.unwrap();
+        assert!(result.completed);
+        assert_eq!(ops.state().await.unwrap(), RepositoryState::Clean);
+        assert_eq!(git(&["log", "--format=%s"]), "Add a lexer\nAdd parser\nBase\n");
+        assert_eq!(std::fs::read_to_string(dir.join("parser.rs")).unwrap(), "fixup! Add parser");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_rebase_todo_skips_upstream() {
+        let dir = test_repo("rebase-todo-upstream");
+        let git = |args: &[&str]| git_in(&dir, args);
+        let commit = |file: &str, subject: &str| {
+            std::fs::write(dir.join(file), subject).unwrap();
+            git(&["add", file]);
+            git(&["commit", "-q", "-m", subject]);
+        };
+        commit("base.txt", "Base");
+        git(&["branch", "upstream"]);
+        commit("parser.rs", "Add parser");
+        commit("lexer.rs", "Add lexer");
+        git(&["checkout", "-q", "upstream"]);
+        git(&["cherry-pick", "HEAD@{1}~1"]);
+        commit("readme.md", "Add readme");
+        git(&["checkout", "-q", "-"]);
+
+        let ops = GitOperations::new(&dir);
+        let todo = ops.rebase_todo("upstream", false).await.unwrap();
+        let subjects: Vec<_> = todo.items.iter().map(|i| i.summary.as_str()).collect();
+        assert_eq!(subjects, vec!["Add lexer"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_conflict_stages() {
+        let dir = test_repo("conflict-stages");
+        let git = |args: &[&str]| git_in(&dir, args);
+        std::fs::write(dir.join("notes.txt"), "one\ntwo\n").unwrap();
+        git(&["add", "notes.txt"]);
+        git(&["commit", "-q", "-m", "Base"]);
+        git(&["checkout", "-q", "-b", "theirs"]);
+        std::fs::write(dir.join("notes.txt"), "one\nthem\n").unwrap();
+        git(&["commit", "-q", "-am", "Theirs"]);
+        git(&["checkout", "-q", "-"]);
+        std::fs::write(dir.join("notes.txt"), "one\nus\n").unwrap();
+        git(&["commit", "-q", "-am", "Ours"]);
+        let _ = std::process::Command::new("git").args(["merge", "-q", "theirs"]).current_dir(&dir).output();
+
+        let ops = GitOperations::new(&dir);
+        let stages = ops.conflict_stages(Path::new("notes.txt")).await.unwrap().unwrap();
+        assert_eq!(stages.base.as_deref(), Some(&b"one\ntwo\n"[..]));
+        assert_eq!(stages.ours.as_deref(), Some(&b"one\nus\n"[..]));
+        assert_eq!(stages.theirs.as_deref(), Some(&b"one\nthem\n"[..]));
+
+        std::fs::write(dir.join("notes.txt"), "one\nus and them\n").unwrap();
+        ops.mark_resolved(Path::new("notes.txt")).await.unwrap();
+        assert_eq!(ops.conflict_stages(Path::new("notes.txt")).await.unwrap(), None);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_bisect() {
+        let dir = test_repo("bisect");
+        let git = |args: &[&str]| git_in(&dir, args);
+        for n in 1..=6 {
+            let content = if n >= 4 { "broken" } else { "fine" };
+            std::fs::write(dir.join("state.txt"), format!("{} {}", content, n)).unwrap();
+            git(&["add", "state.txt"]);
+            git(&["commit", "-q", "-m", &format!("Commit {}", n)]);
+        }
+        let first_bad = git(&["rev-parse", "HEAD~2"]).trim().to_string();
+
+        let ops = GitOperations::new(&dir);
+        let mut step = ops.bisect_start("HEAD", &["HEAD~5"]).await.unwrap();
+        assert_eq!(ops.state().await.unwrap(), RepositoryState::Bisect);
+
+        let range = ops.bisect_range().await.unwrap().unwrap();
+        assert_eq!(range.good.len(), 1);
+        assert_eq!(range.remaining.len(), 4);
+        let current = range.current.clone().unwrap();
+        assert!(range.remaining.contains(&current));
+        assert_eq!(range.mark(&current), Some(crate::BisectMark::Current));
+
+        while let BisectStep::Testing { .. } = step {
+            let state = std::fs::read_to_string(dir.join("state.txt")).unwrap();
+            step = if state.starts_with("broken") {
+                ops.bisect_bad(None).await.unwrap()
+            } else {
+                ops.bisect_good(None).await.unwrap()
+            };
+        }
+        assert_eq!(step, BisectStep::FirstBad { commit: first_bad });
+
+        ops.bisect_reset().await.unwrap();
+        assert!(ops.bisect_range().await.unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_revisions_are_not_options() {
+        let dir = test_repo("revisions");
+        std::fs::write(dir.join("a.txt"), "a\n").unwrap();
+        git_in(&dir, &["add", "a.txt"]);
+        git_in(&dir, &["commit", "-q", "-m", "A"]);
+
+        let ops = GitOperations::new(&dir);
+        let options = RebaseOptions { onto: "--exec=touch pwned".into(), ..Default::default() };
+        assert!(matches!(ops.rebase(&options).await, Err(GitError::RefError(_))));
+        assert!(matches!(ops.rebase_todo("--root", false).await, Err(GitError::RefError(_))));
+        assert!(!dir.join("pwned").exists());
+
+        assert!(matches!(ops.bisect_start("HEAD", &["--term-old=x"]).await, Err(GitError::RefError(_))));
+        assert!(matches!(ops.bisect_start("missing", &[]).await, Err(GitError::RefError(_))));
+        assert_eq!(ops.state().await.unwrap(), RepositoryState::Clean);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,195 @@
+//! Patches built from part of a diff
+//!
+//! Turns a hunk, or a selection of its lines, back into a unified patch that
+//! `git apply` can stage, unstage or discard, like `git add -p` does.
+
+use std::ops::{Range, RangeInclusive};
+
+use crate::diff::{DiffHunk, DiffLineKind};
+
+/// Which changes of a hunk an operation applies to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkSelection {
+    /// Every change in the hunk
+    All,
+    /// Indices into `DiffHunk::lines`, as selected in the diff editor
+    Lines(Range<usize>),
+    /// Changes touching a range of new-file lines (1-based, inclusive), as
+    /// selected from gutter markers
+    ///
+    /// Deleted lines belong to the additions replacing them, or to the line
+    /// before them when nothing replaces them.
+    NewLines(RangeInclusive<u32>),
+}
+
+impl HunkSelection {
+    /// Whether the line at `index` in `hunk` is selected
+    pub fn contains(&self, hunk: &DiffHunk, index: usize) -> bool {
+        match self {
+            HunkSelection::All => true,
+            HunkSelection::Lines(range) => range.contains(&index),
+            HunkSelection::NewLines(range) => range.contains(&new_position(hunk, index)),
+        }
+    }
+}
+
+/// Line of the new file a hunk line is shown at
+///
+/// Deletions are paired with the additions that follow them in order, and
+/// surplus deletions go with the last addition.
+fn new_position(hunk: &DiffHunk, index: usize) -> u32 {
+    let line = hunk.new_start + hunk.lines[..index]
+        .iter()
+        .filter(|l| l.kind != DiffLineKind::Deletion)
+        .count() as u32;
+
+    if hunk.lines[index].kind != DiffLineKind::Deletion {
+        return line;
+    }
+    let first = hunk.lines[..index]
+        .iter()
+        .rposition(|l| l.kind != DiffLineKind::Deletion)
+        .map_or(0, |n| n + 1);
+    let additions = hunk.lines[index..]
+        .iter()
+        .skip_while(|l| l.kind == DiffLineKind::Deletion)
+        .take_while(|l| l.kind == DiffLineKind::Addition)
+        .count();
+
+    if additions == 0 {
+        line.saturating_sub(1).max(1)
+    } else {
+        line + (index - first).min(additions - 1) as u32
+    }
+}
+
+fn is_change(kind: DiffLineKind) -> bool {
+    matches!(kind, DiffLineKind::Addition | DiffLineKind::Deletion)
+}
+
+/// Build a patch for `path` containing the selected changes of `hunk`
+///
+/// Unselected changes are kept as they are on the side the patch is applied
+/// to: the old side by default, or the new side when `reverse` is set and
+/// the patch is going to be applied with `git apply --reverse`. Within a
+/// block of changes, selected lines stay next to each other so a replaced
+/// line lands where the original was. Returns `None` when the selection has
+/// no changes.
+pub fn build_patch(path: &str, hunk: &DiffHunk, selection: &HunkSelection, reverse: bool) -> Option<String> {
+    let selected = |index: usize| selection.contains(hunk, index);
+    let mut patch_lines = Vec::new();
+
+    let mut index = 0;
+    while index < hunk.lines.len() {
+        let kind = hunk.lines[index].kind;
+        if !is_change(kind) {
+            if kind == DiffLineKind::Context {
+                patch_lines.push((' ', index));
+            }
+            index += 1;
+            continue;
+        }
+
+        let end = hunk.lines[index..]
+            .iter()
+            .position(|l| !is_change(l.kind))
+            .map_or(hunk.lines.len(), |n| index + n);
+        let (deletions, additions): (Vec<usize>, Vec<usize>) =
+            (index..end).partition(|&i| hunk.lines[i].kind == DiffLineKind::Deletion);
+
+        if reverse {
+            // Additions are on the new side and keep their order; selected
+            // deletions go before the first selected addition
+            let split = additions.iter().position(|&i| selected(i)).unwrap_or(0);
+            patch_lines.extend(additions[..split].iter().map(|&i| (' ', i)));
+            patch_lines.extend(deletions.iter().filter(|&&i| selected(i)).map(|&i| ('-', i)));
+            patch_lines.extend(additions[split..].iter().map(|&i| (if selected(i) { '+' } else { ' ' }, i)));
+        } else {
+            // Deletions are on the old side and keep their order; selected
+            // additions go after the last selected deletion
+            let split = deletions.iter().rposition(|&i| selected(i)).map_or(deletions.len(), |n| n + 1);
+            patch_lines.extend(deletions[..split].iter().map(|&i| (if selected(i) { '-' } else { ' ' }, i)));
+            patch_lines.extend(additions.iter().filter(|&&i| selected(i)).map(|&i| ('+', i)));
+            patch_lines.extend(deletions[split..].iter().map(|&i| (' ', i)));
+        }
+        index = end;
+    }
+
+    if patch_lines.iter().all(|(prefix, _)| *prefix == ' ') {
+        return None;
+    }
+
+    let old_lines = patch_lines.iter().filter(|(prefix, _)| *prefix != '+').count() as u32;
+    let new_lines = patch_lines.iter().filter(|(prefix, _)| *prefix != '-').count() as u32;
+    let (old_start, new_start) = if reverse {
+        (other_start(hunk.new_start, new_lines, old_lines), hunk.new_start)
+    } else {
+        (hunk.old_start, other_start(hunk.old_start, old_lines, new_lines))
+    };
+
+    let mut patch = format!(
+        "diff --git a/{path} b/{path}\n--- a/{path}\n+++ b/{path}\n@@ -{old_start},{old_lines} +{new_start},{new_lines} @@\n"
+    );
+    for (prefix, index) in patch_lines {
+        let line = &hunk.lines[index];
+        patch.push(prefix);
+        patch.push_str(&line.content);
+        patch.push('\n');
+        if line.no_newline {
+            patch.push_str("\\ No newline at end of file\n");
+        }
+    }
+    Some(patch)
+}
+
+/// Start of the other side of a hunk, given one side's start and both sides'
+/// lengths
+///
+/// An empty side starts at the line before the change.
+fn other_start(start: u32, lines: u32, other_lines: u32) -> u32 {
+    if lines == 0 {
+        start + 1
+    } else if other_lines == 0 {
+        start.saturating_sub(1)
+    } else {
+        start
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::parse_unified_diff;
+
+    const DIFF: &str = "\
+--- a/notes.txt
++++ b/notes.txt
+@@ -1,4 +1,4 @@
+ one
+-two
+-three
++TWO
++THREE
+ four
+";
+
+    #[test]
+    fn test_build_patch_from_selection() {
+        let diff = parse_unified_diff(DIFF);
+        let hunk = &diff.hunks[0];
+
+        // Staging only the first replacement keeps "three" as context after it
+        let patch = build_patch("notes.txt", hunk, &HunkSelection::NewLines(2..=2), false).unwrap();
+        assert!(patch.ends_with("@@ -1,4 +1,4 @@\n one\n-two\n+TWO\n three\n four\n"));
+
+        // Unstaging only "THREE" keeps "TWO" as context on the index side
+        let patch = build_patch("notes.txt", hunk, &HunkSelection::Lines(4..5), true).unwrap();
+        assert!(patch.ends_with("@@ -1,3 +1,4 @@\n one\n TWO\n+THREE\n four\n"));
+
+        // Additions alone keep the deleted lines as context
+        let patch = build_patch("notes.txt", hunk, &HunkSelection::Lines(3..5), false).unwrap();
+        assert!(patch.ends_with("@@ -1,4 +1,6 @@\n one\n two\n three\n+TWO\n+THREE\n four\n"));
+
+        assert!(build_patch("notes.txt", hunk, &HunkSelection::Lines(0..1), false).is_none());
+    }
+}
@@ -0,0 +1,406 @@
+//! Interactive rebase todo lists
+//!
+//! Models the todo list `git rebase -i` hands to its sequence editor so it
+//! can be edited programmatically: change actions, reorder commits, add
+//! `exec` steps and autosquash `fixup!`/`squash!`/`amend!` commits.
+
+use std::fmt;
+use std::str::FromStr;
+
+/// Action for one step of an interactive rebase
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TodoAction {
+    /// Use the commit
+    Pick,
+    /// Use the commit, but change its message
+    Reword,
+    /// Use the commit, but stop to amend it
+    Edit,
+    /// Meld into the previous commit, combining messages
+    Squash,
+    /// Meld into the previous commit, keeping its message
+    Fixup,
+    /// Remove the commit
+    Drop,
+    /// Run a shell command
+    Exec,
+}
+
+impl TodoAction {
+    /// Keyword used in the todo file
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            TodoAction::Pick => "pick",
+            TodoAction::Reword => "reword",
+            TodoAction::Edit => "edit",
+            TodoAction::Squash => "squash",
+            TodoAction::Fixup => "fixup",
+            TodoAction::Drop => "drop",
+            TodoAction::Exec => "exec",
+        }
+    }
+
+    /// Whether the step melds into the commit before it
+    pub fn melds(&self) -> bool {
+        matches!(self, TodoAction::Squash | TodoAction::Fixup)
+    }
+}
+
+impl FromStr for TodoAction {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pick" | "p" => Ok(TodoAction::Pick),
+            "reword" | "r" => Ok(TodoAction::Reword),
+            "edit" | "e" => Ok(TodoAction::Edit),
+            "squash" | "s" => Ok(TodoAction::Squash),
+            "fixup" | "f" => Ok(TodoAction::Fixup),
+            "drop" | "d" => Ok(TodoAction::Drop),
+            "exec" | "x" => Ok(TodoAction::Exec),
+            other => Err(format!("unknown rebase action: {}", other)),
+        }
+    }
+}
+
+impl fmt::Display for TodoAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.keyword())
+    }
+}
+
+/// Message taken from a `fixup` step instead of the commit it melds into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FixupMessage {
+    /// `fixup -C`: use the fixup commit's message
+    Use,
+    /// `fixup -c`: use the fixup commit's message, opening an editor on it
+    Edit,
+}
+
+impl FixupMessage {
+    /// Flag used in the todo file
+    pub fn flag(&self) -> &'static str {
+        match self {
+            FixupMessage::Use => "-C",
+            FixupMessage::Edit => "-c",
+        }
+    }
+}
+
+/// One step of an interactive rebase
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TodoItem {
+    pub action: TodoAction,
+    /// Commit hash, or the shell command for `exec`
+    pub target: String,
+    /// Commit subject
+    pub summary: String,
+    /// New message for `reword` and `squash`, used instead of an editor
+    pub message: Option<String>,
+    /// Take the message of a `fixup` step, as for `amend!` commits
+    pub fixup_message: Option<FixupMessage>,
+}
+
+impl TodoItem {
+    /// Pick a commit
+    pub fn pick(commit: impl Into<String>, summary: impl Into<String>) -> Self {
+        Self {
+            action: TodoAction::Pick,
+            target: commit.into(),
+            summary: summary.into(),
+            message: None,
+            fixup_message: None,
+        }
+    }
+
+    /// Run a shell command
+    pub fn exec(command: impl Into<String>) -> Self {
+        Self {
+            action: TodoAction::Exec,
+            target: command.into(),
+            summary: String::new(),
+            message: None,
+            fixup_message: None,
+        }
+    }
+
+    /// Parse a line of a todo file, skipping blanks and comments
+    pub fn parse(line: &str) -> Option<Result<Self, String>> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (keyword, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let action = match keyword.parse::<TodoAction>() {
+            Ok(action) => action,
+            Err(e) => return Some(Err(e)),
+        };
+        let rest = rest.trim();
+
+        if action == TodoAction::Exec {
+            return Some(Ok(Self::exec(rest)));
+        }
+
+        // `fixup -C <commit>` and `fixup -c <commit>` take over the message
+        let (fixup_message, rest) = if let Some(rest) = rest.strip_prefix("-C ") {
+            (Some(FixupMessage::Use), rest.trim_start())
+        } else if let Some(rest) = rest.strip_prefix("-c ") {
+            (Some(FixupMessage::Edit), rest.trim_start())
+        } else {
+            (None, rest)
+        };
+        let (commit, summary) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        if commit.is_empty() {
+            return Some(Err(format!("missing commit for {}", action)));
+        }
+
+        Some(Ok(Self {
+            action,
+            target: commit.to_string(),
+            summary: summary.trim().to_string(),
+            message: None,
+            fixup_message,
+        }))
+    }
+
+    /// Whether the item refers to `commit`, given as a full or short hash
+    pub fn is_commit(&self, commit: &str) -> bool {
+        self.action != TodoAction::Exec
+            && !commit.is_empty()
+            && (self.target.starts_with(commit) || commit.starts_with(&self.target))
+    }
+}
+
+impl fmt::Display for TodoItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.action == TodoAction::Exec {
+            return write!(f, "exec {}", self.target);
+        }
+
+        write!(f, "{}", self.action)?;
+        if let Some(fixup_message) = self.fixup_message
+            && self.action == TodoAction::Fixup
+        {
+            write!(f, " {}", fixup_message.flag())?;
+        }
+        if self.summary.is_empty() {
+            write!(f, " {}", self.target)
+        } else {
+            write!(f, " {} {}", self.target, self.summary)
+        }
+    }
+}
+
+/// Editable todo list for an interactive rebase
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RebaseTodo {
+    /// Commit the list is replayed onto
+    pub onto: String,
+    pub items: Vec<TodoItem>,
+}
+
+impl RebaseTodo {
+    pub fn new(onto: impl Into<String>) -> Self {
+        Self {
+            onto: onto.into(),
+            items: Vec::new(),
+        }
+    }
+
+    /// Parse the contents of a todo file
+    pub fn parse(onto: impl Into<String>, text: &str) -> Result<Self, String> {
+        let items = text.lines().filter_map(TodoItem::parse).collect::<Result<_, _>>()?;
+        Ok(Self { onto: onto.into(), items })
+    }
+
+    /// Change the action of the item at `index`
+    pub fn set_action(&mut self, index: usize, action: TodoAction) {
+        if let Some(item) = self.items.get_mut(index)
+            && item.action != TodoAction::Exec
+        {
+            item.action = action;
+        }
+    }
+
+    /// Reword the item at `index` with a new message
+    pub fn reword(&mut self, index: usize, message: impl Into<String>) {
+        if let Some(item) = self.items.get_mut(index)
+            && item.action != TodoAction::Exec
+        {
+            item.action = TodoAction::Reword;
+            item.message = Some(message.into());
+        }
+    }
+
+    /// Move the item at `from` so it ends up at `to`
+    pub fn move_item(&mut self, from: usize, to: usize) {
+        if from < self.items.len() && to < self.items.len() {
+            let item = self.items.remove(from);
+            self.items.insert(to, item);
+        }
+    }
+
+    /// Run `command` after the item at `index`
+    pub fn insert_exec(&mut self, index: usize, command: impl Into<String>) {
+        let at = (index + 1).min(self.items.len());
+        self.items.insert(at, TodoItem::exec(command));
+    }
+
+    /// Move `fixup!`, `squash!` and `amend!` commits after the commit they
+    /// target and mark them to meld into it, like `git rebase --autosquash`
+    ///
+    /// The target is matched by subject, or by hash prefix. Nested prefixes
+    /// such as `fixup! fixup! subject` resolve to the same target.
+    pub fn autosquash(&mut self) {
+        let mut index = 0;
+        while index < self.items.len() {
+            let item = &self.items[index];
+            let Some((action, fixup_message, subject)) = autosquash_subject(&item.summary) else {
+                index += 1;
+                continue;
+            };
+
+            let target = self.items[..index].iter().position(|other| {
+                other.action != TodoAction::Exec
+                    && autosquash_subject(&other.summary).is_none()
+                    && (other.summary == subject || other.is_commit(subject))
+            });
+            let Some(target) = target else {
+                index += 1;
+                continue;
+            };
+
+            // Land after the target and anything already melded into it
+            let mut at = target + 1;
+            while at < index && self.items[at].action.melds() {
+                at += 1;
+            }
+
+            let mut item = self.items.remove(index);
+            item.action = action;
+            item.fixup_message = fixup_message;
+            self.items.insert(at, item);
+            index += 1;
+        }
+    }
+
+    /// Check the list can be handed to git
+    pub fn validate(&self) -> Result<(), String> {
+        let first = self.items.iter().find(|i| !matches!(i.action, TodoAction::Drop | TodoAction::Exec));
+        if let Some(first) = first
+            && first.action.melds()
+        {
+            return Err(format!("cannot {} without a previous commit", first.action));
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for RebaseTodo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for item in &self.items {
+            writeln!(f, "{}", item)?;
+        }
+        Ok(())
+    }
+}
+
+/// Action and target subject of an autosquash commit subject. `amend!`
+/// commits are fixups that replace the target's message with their own.
+fn autosquash_subject(summary: &str) -> Option<(TodoAction, Option<FixupMessage>, &str)> {
+    let (action, fixup_message, mut subject) = if let Some(rest) = summary.strip_prefix("fixup! ") {
+        (TodoAction::Fixup, None, rest)
+    } else if let Some(rest) = summary.strip_prefix("amend! ") {
+        (TodoAction::Fixup, Some(FixupMessage::Use), rest)
+    } else if let Some(rest) = summary.strip_prefix("squash! ") {
+        (TodoAction::Squash, None, rest)
+    } else {
+        return None;
+    };
+
+    while let Some(rest) = ["fixup! ", "amend! ", "squash! "]
+        .iter()
+        .find_map(|prefix| subject.strip_prefix(prefix))
+    {
+        subject = rest;
+    }
+    Some((action, fixup_message, subject.trim()))
+}
+
+/// Where an interactive rebase stopped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RebaseStop {
+    /// Stopped on an `edit` step, ready to amend the commit
+    Edit { commit: String },
+    /// Applying a commit left conflicts to resolve
+    Conflict { commit: Option<String> },
+    /// An `exec` step failed
+    Exec { command: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_and_autosquash() {
+        let text = "\
+pick 1111111 Add parser
+pick 2222222 Add lexer
+pick 3333333 fixup! Add parser
+p 4444444 squash! 2222222
+pick 5555555 fixup! fixup! Add parser
+
+# Rebase 0000000..5555555 onto 0000000 (5 commands)
+";
+        let mut todo = RebaseTodo::parse("0000000", text).unwrap();
+        assert_eq!(todo.items.len(), 5);
+
+        todo.autosquash();
+        let order: Vec<_> = todo.items.iter().map(|i| (i.action, i.target.as_str())).collect();
+        assert_eq!(order, vec![
+            (TodoAction::Pick, "1111111"),
+            (TodoAction::Fixup, "3333333"),
+            (TodoAction::Fixup, "5555555"),
+            (TodoAction::Pick, "2222222"),
+            (TodoAction::Squash, "4444444"),
+        ]);
+
+        todo.move_item(3, 0);
+        todo.insert_exec(0, "cargo test");
+        assert_eq!(todo.to_string().lines().take(2).collect::<Vec<_>>(), vec![
+            "pick 2222222 Add lexer",
+            "exec cargo test",
+        ]);
+        assert!(todo.validate().is_ok());
+
+        todo.set_action(0, TodoAction::Fixup);
+        assert!(todo.validate().is_err());
+    }
+
+    #[test]
+    fn test_fixup_message() {
+        let text = "\
+pick 1111111 Add parser
+pick 2222222 amend! Add parser
+fixup -c 3333333 Tweak parser
+";
+        let mut todo = RebaseTodo::parse("0000000", text).unwrap();
+        assert_eq!(todo.items[1].fixup_message, None);
+        assert_eq!(todo.items[2].fixup_message, Some(FixupMessage::Edit));
+
+        todo.autosquash();
+        assert_eq!(todo.items[1].action, TodoAction::Fixup);
+        assert_eq!(todo.items[1].fixup_message, Some(FixupMessage::Use));
+
+        let printed = todo.to_string();
+        assert_eq!(printed.lines().collect::<Vec<_>>(), vec![
+            "pick 1111111 Add parser",
+            "fixup -C 2222222 amend! Add parser",
+            "fixup -c 3333333 Tweak parser",
+        ]);
+        assert_eq!(RebaseTodo::parse("0000000", &printed).unwrap(), todo);
+    }
+}
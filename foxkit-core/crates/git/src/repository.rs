@@ -23,6 +23,14 @@ impl Repository {
         Ok(repo.path().to_path_buf())
     }
 
+    /// Discover the working tree root of the repository containing path
+    pub fn discover_workdir(path: impl AsRef<Path>) -> Result<PathBuf> {
+        let repo = gix::discover(path)?;
+        repo.work_dir()
+            .map(Path::to_path_buf)
+            .ok_or_else(|| anyhow::anyhow!("Repository has no working tree"))
+    }
+
     /// Get HEAD reference
     pub fn head(&self) -> Result<String> {
         let head = self.repo.head_ref()?;
@@ -118,6 +126,42 @@ impl Repository {
         Ok(commits)
     }
 
+    /// Contents of a file in the index or in HEAD, or `None` when it isn't
+    /// tracked there
+    ///
+    /// `path` may be absolute or relative to the working tree.
+    pub fn blob(&self, path: &Path, base: DiffBase) -> Result<Option<Vec<u8>>> {
+        let path = match self.repo.work_dir() {
+            Some(workdir) => path.strip_prefix(workdir).unwrap_or(path),
+            None => path,
+        };
+
+        let id = match base {
+            DiffBase::Index => {
+                let index = self.repo.index_or_empty()?;
+                let key = gix::path::to_unix_separators_on_windows(gix::path::into_bstr(path));
+                match index.entry_by_path(key.as_ref()) {
+                    Some(entry) => entry.id,
+                    None => return Ok(None),
+                }
+            }
+            DiffBase::Head => {
+                // An unborn branch has nothing to compare against
+                let Ok(commit) = self.repo.head_commit() else {
+                    return Ok(None);
+                };
+                let tree = commit.tree()?;
+                let mut buf = Vec::new();
+                match tree.lookup_entry_by_path(path, &mut buf)? {
+                    Some(entry) => entry.object_id(),
+                    None => return Ok(None),
+                }
+            }
+        };
+
+        Ok(Some(self.repo.find_object(id)?.detach().data))
+    }
+
     /// Stage a file
     pub fn stage(&self, path: &Path) -> Result<()> {
         // Note: gix staging is complex, simplified here
@@ -139,6 +183,16 @@ impl Repository {
     }
 }
 
+/// Version of a file unsaved changes are compared against
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiffBase {
+    /// The staged version, so staged lines don't show up as changes
+    #[default]
+    Index,
+    /// The last commit
+    Head,
+}
+
 /// A git commit
 #[derive(Debug, Clone)]
 pub struct Commit {
@@ -163,3 +217,30 @@ pub struct Remote {
     pub name: String,
     pub url: Option<String>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{git_in, test_repo};
+
+    #[test]
+    fn test_blob_from_index_and_head() {
+        let dir = test_repo("blob");
+        let git = |args: &[&str]| git_in(&dir, args);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "committed\n").unwrap();
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "init"]);
+        std::fs::write(dir.join("src/lib.rs"), "staged\n").unwrap();
+        git(&["add", "."]);
+
+        let repo = Repository::open(&dir).unwrap();
+        let path = Path::new("src/lib.rs");
+        assert_eq!(repo.blob(path, DiffBase::Head).unwrap().unwrap(), b"committed\n");
+        assert_eq!(repo.blob(path, DiffBase::Index).unwrap().unwrap(), b"staged\n");
+        assert_eq!(repo.blob(&dir.join("src/lib.rs"), DiffBase::Index).unwrap().unwrap(), b"staged\n");
+        assert!(repo.blob(Path::new("missing.rs"), DiffBase::Head).unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -44,6 +44,57 @@ impl Status {
     }
 }
 
+/// Read the status of the repository at `repo_path`
+///
+/// Paths are relative to the repository root and untracked directories are
+/// listed file by file.
+pub async fn status(repo_path: &Path) -> anyhow::Result<Status> {
+    let output = tokio::process::Command::new("git")
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=all"])
+        .current_dir(repo_path)
+        .output()
+        .await?;
+
+    if !output.status.success() {
+        anyhow::bail!("git status failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(parse_porcelain(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parse `git status --porcelain=v1 -z` output
+pub fn parse_porcelain(output: &str) -> Status {
+    let mut status = Status::new();
+    let mut fields = output.split('\0').filter(|f| !f.is_empty());
+
+    while let Some(field) = fields.next() {
+        if field.len() < 4 {
+            continue;
+        }
+        let mut codes = field[..2].chars();
+        let (x, y) = (codes.next().unwrap_or(' '), codes.next().unwrap_or(' '));
+        let mut entry = FileStatus::new(PathBuf::from(&field[3..]));
+
+        let conflicted = matches!((x, y), ('D', 'D') | ('A', 'A') | ('U', _) | (_, 'U'));
+        if conflicted {
+            entry.index = StatusKind::Conflicted;
+            entry.workdir = StatusKind::Conflicted;
+        } else if matches!(x, '?' | '!') {
+            // Untracked and ignored files aren't in the index
+            entry.workdir = StatusKind::from_porcelain(y);
+        } else {
+            entry.index = StatusKind::from_porcelain(x);
+            entry.workdir = StatusKind::from_porcelain(y);
+        }
+        // Renames and copies are followed by the original path
+        if matches!(x, 'R' | 'C') {
+            entry.old_path = fields.next().map(PathBuf::from);
+        }
+        status.entries.push(entry);
+    }
+
+    status
+}
+
 /// Status of a single file
 #[derive(Debug, Clone)]
 pub struct FileStatus {
@@ -98,6 +149,21 @@ pub enum StatusKind {
 }
 
 impl StatusKind {
+    /// Map a porcelain status letter
+    pub fn from_porcelain(code: char) -> Self {
+        match code {
+            'M' | 'T' => StatusKind::Modified,
+            'A' => StatusKind::Added,
+            'D' => StatusKind::Deleted,
+            'R' => StatusKind::Renamed,
+            'C' => StatusKind::Copied,
+            '?' => StatusKind::Untracked,
+            '!' => StatusKind::Ignored,
+            'U' => StatusKind::Conflicted,
+            _ => StatusKind::Unchanged,
+        }
+    }
+
     pub fn to_change_kind(self) -> ChangeKind {
         match self {
             StatusKind::Unchanged => ChangeKind::Modified, // Shouldn't happen
@@ -118,3 +184,21 @@ impl Default for StatusKind {
         Self::Unchanged
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let status = parse_porcelain("M  src/a.rs\0 M src/b.rs\0R  new.rs\0old.rs\0?? notes/todo.md\0UU both.rs\0");
+        assert_eq!(status.entries.len(), 5);
+
+        assert_eq!(status.staged().count(), 3);
+        assert_eq!(status.entries[1].workdir, StatusKind::Modified);
+        assert_eq!(status.entries[2].path, PathBuf::from("new.rs"));
+        assert_eq!(status.entries[2].old_path, Some(PathBuf::from("old.rs")));
+        assert_eq!(status.untracked().next().unwrap().path, PathBuf::from("notes/todo.md"));
+        assert_eq!(status.entries[4].index, StatusKind::Conflicted);
+    }
+}
@@ -0,0 +1,34 @@
+//! Scratch repositories for tests, here and in crates that depend on git
+//! (with the `test-support` feature).
+
+use std::path::{Path, PathBuf};
+
+/// Create an empty repository in a fresh temp directory, with a test
+/// identity configured.
+pub fn test_repo(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("foxkit-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    git_in(&dir, &["init", "-q"]);
+    git_in(&dir, &["config", "user.name", "Test"]);
+    git_in(&dir, &["config", "user.email", "test@example.com"]);
+    dir
+}
+
+/// Run git in `dir` and return its output. Panics if git fails.
+pub fn git_in(dir: &Path, args: &[&str]) -> String {
+    git_with_env(dir, &[], args)
+}
+
+/// Run git in `dir` with extra environment variables, e.g. to set the
+/// author or commit dates. Panics if git fails.
+pub fn git_with_env(dir: &Path, env: &[(&str, &str)], args: &[&str]) -> String {
+    let output = std::process::Command::new("git")
+        .args(args)
+        .envs(env.iter().copied())
+        .current_dir(dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
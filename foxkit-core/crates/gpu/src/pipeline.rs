@@ -151,6 +151,7 @@ impl EditorRenderPipeline {
         // Build scene from non-text commands
         self.scene_builder.clear();
         self.scene_builder.process_commands(&other_commands);
+        self.renderer.prepare_images(self.scene_builder.scene());
 
         // Build text vertices
         let mut all_glyphs = Vec::new();
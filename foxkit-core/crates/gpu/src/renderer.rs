@@ -1,5 +1,7 @@
 //! GPU renderer - draws primitives
 
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
 use std::sync::Arc;
 use anyhow::Result;
 use wgpu::*;
@@ -41,11 +43,23 @@ pub struct Renderer {
     index_buffer: Buffer,
     uniform_buffer: Buffer,
     uniform_bind_group: BindGroup,
+    image_pipeline: RenderPipeline,
+    image_bind_group_layout: BindGroupLayout,
+    image_sampler: Sampler,
+    /// Uploaded images by scene image id
+    images: HashMap<u64, ImageTexture>,
     viewport_size: [f32; 2],
     max_vertices: usize,
     max_indices: usize,
 }
 
+/// An image uploaded as a texture
+struct ImageTexture {
+    bind_group: BindGroup,
+    /// Kept alive for the bind group
+    _texture: Texture,
+}
+
 /// Uniforms
 #[derive(Clone, Copy, Pod, Zeroable)]
 #[repr(C)]
@@ -132,6 +146,73 @@ impl Renderer {
             multiview: None,
         });
 
+        let image_shader = device.create_shader_module(ShaderModuleDescriptor {
+            label: Some("Image Shader"),
+            source: ShaderSource::Wgsl(include_str!("shaders/image.wgsl").into()),
+        });
+
+        let image_sampler = device.create_sampler(&SamplerDescriptor {
+            label: Some("Image Sampler"),
+            address_mode_u: AddressMode::ClampToEdge,
+            address_mode_v: AddressMode::ClampToEdge,
+            mag_filter: FilterMode::Linear,
+            min_filter: FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let image_bind_group_layout = device.create_bind_group_layout(&BindGroupLayoutDescriptor {
+            label: Some("Image Bind Group Layout"),
+            entries: &[
+                BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Texture {
+                        sample_type: TextureSampleType::Float { filterable: true },
+                        view_dimension: TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: ShaderStages::FRAGMENT,
+                    ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let image_pipeline_layout = device.create_pipeline_layout(&PipelineLayoutDescriptor {
+            label: Some("Image Pipeline Layout"),
+            bind_group_layouts: &[&uniform_bind_group_layout, &image_bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let image_pipeline = device.create_render_pipeline(&RenderPipelineDescriptor {
+            label: Some("Image Pipeline"),
+            layout: Some(&image_pipeline_layout),
+            vertex: VertexState {
+                module: &image_shader,
+                entry_point: "vs_main",
+                buffers: &[Vertex::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(FragmentState {
+                module: &image_shader,
+                entry_point: "fs_main",
+                targets: &[Some(ColorTargetState {
+                    format,
+                    blend: Some(BlendState::ALPHA_BLENDING),
+                    write_mask: ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+            multiview: None,
+        });
+
         let max_vertices = 65536;
         let max_indices = 65536 * 6;
 
@@ -157,6 +238,10 @@ impl Renderer {
             index_buffer,
             uniform_buffer,
             uniform_bind_group,
+            image_pipeline,
+            image_bind_group_layout,
+            image_sampler,
+            images: HashMap::new(),
             viewport_size: [800.0, 600.0],
             max_vertices,
             max_indices,
@@ -168,6 +253,75 @@ impl Renderer {
         self.viewport_size = [width as f32, height as f32];
     }
 
+    /// Upload the images a scene draws that aren't on the GPU yet and free
+    /// the ones it no longer draws. Images are only drawn once prepared.
+    pub fn prepare_images(&mut self, scene: &Scene) {
+        let max_dimension = self.device.limits().max_texture_dimension_2d;
+        let mut drawn = HashSet::new();
+
+        for primitive in scene.primitives() {
+            let Primitive::Image { id, width, height, rgba, .. } = primitive else {
+                continue;
+            };
+            drawn.insert(*id);
+            let (width, height) = (*width, *height);
+            if self.images.contains_key(id)
+                || width == 0
+                || height == 0
+                || width.max(height) > max_dimension
+                || rgba.len() < width as usize * height as usize * 4
+            {
+                continue;
+            }
+
+            let size = Extent3d { width, height, depth_or_array_layers: 1 };
+            let texture = self.device.create_texture(&TextureDescriptor {
+                label: Some("Image Texture"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: TextureDimension::D2,
+                format: TextureFormat::Rgba8UnormSrgb,
+                usage: TextureUsages::TEXTURE_BINDING | TextureUsages::COPY_DST,
+                view_formats: &[],
+            });
+            self.queue.write_texture(
+                ImageCopyTexture {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: Origin3d::ZERO,
+                    aspect: TextureAspect::All,
+                },
+                &rgba[..width as usize * height as usize * 4],
+                ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(width * 4),
+                    rows_per_image: Some(height),
+                },
+                size,
+            );
+
+            let view = texture.create_view(&TextureViewDescriptor::default());
+            let bind_group = self.device.create_bind_group(&BindGroupDescriptor {
+                label: Some("Image Bind Group"),
+                layout: &self.image_bind_group_layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(&view),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&self.image_sampler),
+                    },
+                ],
+            });
+            self.images.insert(*id, ImageTexture { bind_group, _texture: texture });
+        }
+
+        self.images.retain(|id, _| drawn.contains(id));
+    }
+
     /// Render a scene
     pub fn render<'a>(&'a self, pass: &mut RenderPass<'a>, scene: &Scene) {
         // Update uniforms
@@ -184,6 +338,19 @@ impl Renderer {
         for primitive in scene.primitives() {
             self.tesselate_primitive(primitive, &mut vertices, &mut indices);
         }
+        let shapes = indices.len() as u32;
+
+        // Images go on top, each drawn with its own texture
+        let mut images: Vec<(&ImageTexture, Range<u32>)> = Vec::new();
+        for primitive in scene.primitives() {
+            if let Primitive::Image { rect, id, .. } = primitive
+                && let Some(image) = self.images.get(id)
+            {
+                let start = indices.len() as u32;
+                self.tesselate_quad(rect, Color::WHITE, 0.0, &mut vertices, &mut indices);
+                images.push((image, start..indices.len() as u32));
+            }
+        }
 
         if vertices.is_empty() {
             return;
@@ -198,7 +365,17 @@ impl Renderer {
         pass.set_bind_group(0, &self.uniform_bind_group, &[]);
         pass.set_vertex_buffer(0, self.vertex_buffer.slice(..));
         pass.set_index_buffer(self.index_buffer.slice(..), IndexFormat::Uint32);
-        pass.draw_indexed(0..indices.len() as u32, 0, 0..1);
+        if shapes > 0 {
+            pass.draw_indexed(0..shapes, 0, 0..1);
+        }
+
+        if !images.is_empty() {
+            pass.set_pipeline(&self.image_pipeline);
+            for (image, range) in images {
+                pass.set_bind_group(1, &image.bind_group, &[]);
+                pass.draw_indexed(range, 0, 0..1);
+            }
+        }
     }
 
     fn tesselate_primitive(&self, primitive: &Primitive, vertices: &mut Vec<Vertex>, indices: &mut Vec<u32>) {
@@ -212,6 +389,9 @@ impl Renderer {
             Primitive::Text { .. } => {
                 // Text handled by TextRenderer
             }
+            Primitive::Image { .. } => {
+                // Drawn after the shapes with the image pipeline
+            }
         }
     }
 
@@ -1,5 +1,7 @@
 //! Scene graph for GPU rendering
 
+use std::sync::Arc;
+
 use crate::{Color, Point, Rect};
 
 /// Scene - collection of primitives to render
@@ -58,6 +60,18 @@ impl Scene {
         });
     }
 
+    /// Draw an RGBA8 image scaled into a rectangle. `id` identifies the
+    /// pixels so renderers can cache the uploaded texture
+    pub fn draw_image(&mut self, rect: Rect, id: u64, width: u32, height: u32, rgba: Arc<Vec<u8>>) {
+        self.add(Primitive::Image {
+            rect,
+            id,
+            width,
+            height,
+            rgba,
+        });
+    }
+
     /// Get all primitives
     pub fn primitives(&self) -> &[Primitive] {
         &self.primitives
@@ -108,6 +122,14 @@ pub enum Primitive {
         font_size: f32,
         font_family: Option<String>,
     },
+    /// RGBA8 image
+    Image {
+        rect: Rect,
+        id: u64,
+        width: u32,
+        height: u32,
+        rgba: Arc<Vec<u8>>,
+    },
 }
 
 /// Layer for z-ordering
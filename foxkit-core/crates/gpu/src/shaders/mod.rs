@@ -7,3 +7,6 @@ pub const QUAD_SHADER: &str = include_str!("quad.wgsl");
 
 /// Text shader source  
 pub const TEXT_SHADER: &str = include_str!("text.wgsl");
+
+/// Image shader source
+pub const IMAGE_SHADER: &str = include_str!("image.wgsl");
@@ -83,6 +83,89 @@ impl GutterService {
         decs
     }
 
+    /// Show line coverage, replacing any earlier coverage for the file
+    pub fn set_coverage(&self, file: &PathBuf, lines: Vec<LineCoverage>) {
+        self.remove_by_source(file, "coverage");
+
+        let config = self.config.read();
+        if !config.show_coverage {
+            return;
+        }
+
+        let mut decorations = self.decorations.write();
+        let decs = decorations.entry(file.clone()).or_default();
+        for line in lines {
+            let decoration = GutterDecoration::new(line.line, "coverage");
+            decs.push(if line.hits > 0 {
+                decoration
+                    .with_background(config.coverage_covered_color.clone())
+                    .with_tooltip(format!("Covered ({} hits)", line.hits))
+            } else if line.changed {
+                decoration
+                    .with_glyph(GutterGlyph::uncovered_change())
+                    .with_background(config.coverage_uncovered_color.clone())
+                    .with_priority(1)
+                    .with_tooltip("Changed line not covered by tests")
+            } else {
+                decoration
+                    .with_background(config.coverage_uncovered_color.clone())
+                    .with_tooltip("Not covered")
+            });
+        }
+    }
+
+    /// Hide line coverage
+    pub fn clear_coverage(&self, file: &PathBuf) {
+        self.remove_by_source(file, "coverage");
+    }
+
+    /// Show changes against the diff base, replacing any earlier changes for
+    /// the file
+    pub fn set_changes(&self, file: &PathBuf, changes: &[LineChange]) {
+        self.remove_by_source(file, "scm");
+
+        let config = self.config.read();
+        if !config.show_changes {
+            return;
+        }
+
+        let mut decorations = self.decorations.write();
+        let decs = decorations.entry(file.clone()).or_default();
+        for change in changes {
+            match change.kind {
+                LineChangeKind::Added | LineChangeKind::Modified => {
+                    let (glyph, color, tooltip) = if change.kind == LineChangeKind::Added {
+                        (GutterGlyph::added(), &config.added_color, "Added")
+                    } else {
+                        (GutterGlyph::modified(), &config.modified_color, "Modified")
+                    };
+                    for line in change.start_line..change.end_line {
+                        decs.push(
+                            GutterDecoration::new(line, "scm")
+                                .with_glyph(glyph.clone())
+                                .with_background(color.clone())
+                                .with_tooltip(tooltip),
+                        );
+                    }
+                }
+                LineChangeKind::Deleted => {
+                    // Drawn on the line above the removed lines
+                    decs.push(
+                        GutterDecoration::new(change.start_line.saturating_sub(1), "scm")
+                            .with_glyph(GutterGlyph::deleted())
+                            .with_background(config.deleted_color.clone())
+                            .with_tooltip("Deleted"),
+                    );
+                }
+            }
+        }
+    }
+
+    /// Hide changes against the diff base
+    pub fn clear_changes(&self, file: &PathBuf) {
+        self.remove_by_source(file, "scm");
+    }
+
     /// Clear decorations for file
     pub fn clear_file(&self, file: &PathBuf) {
         self.decorations.write().remove(file);
@@ -280,6 +363,51 @@ impl GutterGlyph {
     pub fn info() -> Self {
         Self::Icon("info".to_string())
     }
+
+    pub fn uncovered_change() -> Self {
+        Self::Icon("coverage-uncovered-change".to_string())
+    }
+
+    pub fn added() -> Self {
+        Self::Icon("diff-added".to_string())
+    }
+
+    pub fn modified() -> Self {
+        Self::Icon("diff-modified".to_string())
+    }
+
+    pub fn deleted() -> Self {
+        Self::Icon("diff-removed".to_string())
+    }
+}
+
+/// Coverage of a single line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineCoverage {
+    /// Line number (0-based)
+    pub line: u32,
+    /// Execution count
+    pub hits: u32,
+    /// Line changed against the diff base
+    pub changed: bool,
+}
+
+/// Kind of change against the diff base
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineChangeKind {
+    Added,
+    Modified,
+    Deleted,
+}
+
+/// Lines changed against the diff base
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LineChange {
+    /// First changed line (0-based)
+    pub start_line: u32,
+    /// Line after the last changed line, equal to `start_line` for deletions
+    pub end_line: u32,
+    pub kind: LineChangeKind,
 }
 
 /// Fold indicator
@@ -326,6 +454,20 @@ pub struct GutterConfig {
     pub highlight_active: bool,
     /// Render final newline
     pub render_final_newline: bool,
+    /// Show line coverage
+    pub show_coverage: bool,
+    /// Covered line color
+    pub coverage_covered_color: String,
+    /// Uncovered line color
+    pub coverage_uncovered_color: String,
+    /// Show changes against the diff base
+    pub show_changes: bool,
+    /// Added line color
+    pub added_color: String,
+    /// Modified line color
+    pub modified_color: String,
+    /// Deleted line color
+    pub deleted_color: String,
 }
 
 impl Default for GutterConfig {
@@ -336,6 +478,13 @@ impl Default for GutterConfig {
             glyph_margin: true,
             highlight_active: true,
             render_final_newline: true,
+            show_coverage: true,
+            coverage_covered_color: "#2ea04326".to_string(),
+            coverage_uncovered_color: "#f8514926".to_string(),
+            show_changes: true,
+            added_color: "#2ea043".to_string(),
+            modified_color: "#0078d4".to_string(),
+            deleted_color: "#f85149".to_string(),
         }
     }
 }
@@ -352,3 +501,42 @@ pub enum LineNumberMode {
     /// Show every N lines
     Interval(u32),
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_changes() {
+        let service = GutterService::new();
+        let file = PathBuf::from("src/lib.rs");
+        service.add_decoration(&file, GutterDecoration::new(0, "breakpoints"));
+
+        service.set_changes(&file, &[
+            LineChange { start_line: 1, end_line: 3, kind: LineChangeKind::Added },
+            LineChange { start_line: 5, end_line: 6, kind: LineChangeKind::Modified },
+            LineChange { start_line: 8, end_line: 8, kind: LineChangeKind::Deleted },
+        ]);
+        let changes: Vec<_> = service.get_decorations(&file)
+            .into_iter()
+            .filter(|d| d.source == "scm")
+            .map(|d| (d.line, d.tooltip.unwrap(), d.background.unwrap()))
+            .collect();
+        assert_eq!(changes, vec![
+            (1, "Added".to_string(), "#2ea043".to_string()),
+            (2, "Added".to_string(), "#2ea043".to_string()),
+            (5, "Modified".to_string(), "#0078d4".to_string()),
+            (7, "Deleted".to_string(), "#f85149".to_string()),
+        ]);
+
+        // New changes replace the old ones
+        service.set_changes(&file, &[LineChange { start_line: 0, end_line: 1, kind: LineChangeKind::Modified }]);
+        assert_eq!(service.get_decorations(&file).len(), 2);
+
+        // Hidden changes clear what was shown and leave other sources alone
+        service.configure(GutterConfig { show_changes: false, ..Default::default() });
+        service.set_changes(&file, &[LineChange { start_line: 0, end_line: 1, kind: LineChangeKind::Added }]);
+        let sources: Vec<_> = service.get_decorations(&file).into_iter().map(|d| d.source).collect();
+        assert_eq!(sources, vec!["breakpoints"]);
+    }
+}
@@ -1,10 +1,16 @@
 //! # Foxkit Merge Editor
 //!
 //! 3-way merge conflict resolution editor.
+//!
+//! Conflicts come from merging the index stages of a conflicted file, so
+//! changes only one side made are resolved up front and what's left are the
+//! regions both sides changed differently.
 
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicU64, Ordering};
+use diff::{diff_words, merge3_with_style, ConflictStyle, DiffOp};
+use git::{GitError, GitOperations, GitResult};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
@@ -48,6 +54,65 @@ impl MergeEditorService {
         session
     }
 
+    /// Open merge session with a conflict style
+    pub fn open_session_with_style(
+        &self,
+        file: PathBuf,
+        base: String,
+        ours: String,
+        theirs: String,
+        style: ConflictStyle,
+    ) -> MergeSession {
+        let session = MergeSession::with_style(file.clone(), base, ours, theirs, style);
+        self.sessions.write().insert(file, session.clone());
+        let _ = self.event_tx.send(MergeEvent::SessionOpened(session.clone()));
+        session
+    }
+
+    /// Open a merge session for a conflicted file in the repository
+    ///
+    /// The base, ours and theirs versions are read from index stages 1, 2
+    /// and 3, so whatever markers are in the working tree file don't matter.
+    /// Returns `None` when the file has no conflict.
+    pub async fn open_conflict(
+        &self,
+        ops: &GitOperations,
+        file: &Path,
+        style: ConflictStyle,
+    ) -> GitResult<Option<MergeSession>> {
+        let Some(stages) = ops.conflict_stages(file).await? else {
+            return Ok(None);
+        };
+        let text = |stage: Option<Vec<u8>>| String::from_utf8_lossy(&stage.unwrap_or_default()).into_owned();
+        Ok(Some(self.open_session_with_style(
+            file.to_path_buf(),
+            text(stages.base),
+            text(stages.ours),
+            text(stages.theirs),
+            style,
+        )))
+    }
+
+    /// Write the merged file and stage it, once every conflict is resolved
+    ///
+    /// Returns `false` without touching the file while conflicts are left.
+    /// The session is closed once the file is staged.
+    pub async fn mark_resolved(&self, ops: &GitOperations, file: &Path) -> GitResult<bool> {
+        let content = match self.get_merged_result(&file.to_path_buf()) {
+            Some(MergeResult::Complete { content }) => content,
+            _ => return Ok(false),
+        };
+
+        tokio::fs::write(ops.repo_path().join(file), content)
+            .await
+            .map_err(|e| GitError::Other(e.to_string()))?;
+        ops.mark_resolved(file).await?;
+
+        self.close_session(&file.to_path_buf());
+        let _ = self.event_tx.send(MergeEvent::FileResolved(file.to_path_buf()));
+        Ok(true)
+    }
+
     /// Get session
     pub fn get_session(&self, file: &PathBuf) -> Option<MergeSession> {
         self.sessions.read().get(file).cloned()
@@ -62,84 +127,75 @@ impl MergeEditorService {
 
     /// Accept current (ours)
     pub fn accept_current(&self, file: &PathBuf, conflict_id: &ConflictId) {
-        if let Some(session) = self.sessions.write().get_mut(file) {
-            if let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) {
-                conflict.resolution = Some(Resolution::Current);
-                conflict.resolved_content = Some(conflict.current.clone());
-                let _ = self.event_tx.send(MergeEvent::ConflictResolved {
-                    file: file.clone(),
-                    conflict_id: conflict_id.clone(),
-                    resolution: Resolution::Current,
-                });
-            }
-        }
+        self.resolve(file, conflict_id, Resolution::Current, |c| c.current.clone());
     }
 
     /// Accept incoming (theirs)
     pub fn accept_incoming(&self, file: &PathBuf, conflict_id: &ConflictId) {
-        if let Some(session) = self.sessions.write().get_mut(file) {
-            if let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) {
-                conflict.resolution = Some(Resolution::Incoming);
-                conflict.resolved_content = Some(conflict.incoming.clone());
-                let _ = self.event_tx.send(MergeEvent::ConflictResolved {
-                    file: file.clone(),
-                    conflict_id: conflict_id.clone(),
-                    resolution: Resolution::Incoming,
-                });
-            }
-        }
+        self.resolve(file, conflict_id, Resolution::Incoming, |c| c.incoming.clone());
     }
 
     /// Accept both
     pub fn accept_both(&self, file: &PathBuf, conflict_id: &ConflictId, current_first: bool) {
-        if let Some(session) = self.sessions.write().get_mut(file) {
-            if let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) {
-                let content = if current_first {
-                    format!("{}\n{}", conflict.current, conflict.incoming)
-                } else {
-                    format!("{}\n{}", conflict.incoming, conflict.current)
-                };
-                conflict.resolution = Some(Resolution::Both);
-                conflict.resolved_content = Some(content);
-                let _ = self.event_tx.send(MergeEvent::ConflictResolved {
-                    file: file.clone(),
-                    conflict_id: conflict_id.clone(),
-                    resolution: Resolution::Both,
-                });
-            }
-        }
+        self.resolve(file, conflict_id, Resolution::Both, |c| {
+            let (first, second) = if current_first {
+                (&c.current, &c.incoming)
+            } else {
+                (&c.incoming, &c.current)
+            };
+            [first.as_str(), second.as_str()]
+                .into_iter()
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n")
+        });
     }
 
     /// Accept base
     pub fn accept_base(&self, file: &PathBuf, conflict_id: &ConflictId) {
-        if let Some(session) = self.sessions.write().get_mut(file) {
-            if let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) {
-                conflict.resolution = Some(Resolution::Base);
-                conflict.resolved_content = Some(conflict.base.clone());
-                let _ = self.event_tx.send(MergeEvent::ConflictResolved {
-                    file: file.clone(),
-                    conflict_id: conflict_id.clone(),
-                    resolution: Resolution::Base,
-                });
-            }
-        }
+        self.resolve(file, conflict_id, Resolution::Base, |c| c.base.clone());
     }
 
     /// Accept custom
     pub fn accept_custom(&self, file: &PathBuf, conflict_id: &ConflictId, content: String) {
-        if let Some(session) = self.sessions.write().get_mut(file) {
-            if let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) {
-                conflict.resolution = Some(Resolution::Custom);
-                conflict.resolved_content = Some(content);
-                let _ = self.event_tx.send(MergeEvent::ConflictResolved {
-                    file: file.clone(),
-                    conflict_id: conflict_id.clone(),
-                    resolution: Resolution::Custom,
-                });
-            }
+        self.resolve(file, conflict_id, Resolution::Custom, |_| content);
+    }
+
+    /// Record a resolution, and announce when it was the last one left
+    fn resolve(
+        &self,
+        file: &PathBuf,
+        conflict_id: &ConflictId,
+        resolution: Resolution,
+        content: impl FnOnce(&Conflict) -> String,
+    ) {
+        let mut sessions = self.sessions.write();
+        let Some(session) = sessions.get_mut(file) else {
+            return;
+        };
+        let Some(conflict) = session.conflicts.iter_mut().find(|c| &c.id == conflict_id) else {
+            return;
+        };
+
+        conflict.resolved_content = Some(content(conflict));
+        conflict.resolution = Some(resolution);
+        let _ = self.event_tx.send(MergeEvent::ConflictResolved {
+            file: file.clone(),
+            conflict_id: conflict_id.clone(),
+            resolution,
+        });
+        if session.is_complete() {
+            let _ = self.event_tx.send(MergeEvent::AllResolved(file.clone()));
         }
     }
 
+    /// Word-level differences between the two sides of a conflict
+    pub fn refine_conflict(&self, file: &PathBuf, conflict_id: &ConflictId) -> Option<ConflictRefinement> {
+        let sessions = self.sessions.read();
+        let conflict = sessions.get(file)?.conflicts.iter().find(|c| &c.id == conflict_id)?;
+        Some(conflict.refine())
+    }
+
     /// Reset conflict resolution
     pub fn reset_conflict(&self, file: &PathBuf, conflict_id: &ConflictId) {
         if let Some(session) = self.sessions.write().get_mut(file) {
@@ -269,101 +325,64 @@ pub struct MergeSession {
     pub ours: String,
     /// Incoming (theirs) content
     pub theirs: String,
+    /// How conflicts are shown in the result
+    pub style: ConflictStyle,
     /// Detected conflicts
     pub conflicts: Vec<Conflict>,
-    /// Result content
+    /// Result content, with non-conflicting changes from both sides merged
+    /// and conflict markers around the rest
     pub result: String,
 }
 
 impl MergeSession {
     pub fn new(file: PathBuf, base: String, ours: String, theirs: String) -> Self {
-        let conflicts = Self::detect_conflicts(&base, &ours, &theirs);
-        let result = ours.clone(); // Start with ours
+        Self::with_style(file, base, ours, theirs, ConflictStyle::default())
+    }
+
+    pub fn with_style(file: PathBuf, base: String, ours: String, theirs: String, style: ConflictStyle) -> Self {
+        let merged = merge3_with_style(&base, &ours, &theirs, style);
+        let conflicts = merged
+            .conflicts
+            .into_iter()
+            .map(|c| Conflict {
+                id: ConflictId::new(),
+                start_line: c.start_line as u32,
+                end_line: c.end_line.saturating_sub(1) as u32,
+                base: c.base.join("\n"),
+                current: c.ours.join("\n"),
+                incoming: c.theirs.join("\n"),
+                resolution: None,
+                resolved_content: None,
+            })
+            .collect();
 
         Self {
             file,
             base,
             ours,
             theirs,
+            style,
             conflicts,
-            result,
-        }
-    }
-
-    fn detect_conflicts(base: &str, ours: &str, theirs: &str) -> Vec<Conflict> {
-        // Simple conflict detection - in real impl would use diff3
-        let mut conflicts = Vec::new();
-
-        // Look for git conflict markers
-        let lines: Vec<&str> = ours.lines().collect();
-        let mut i = 0;
-
-        while i < lines.len() {
-            if lines[i].starts_with("<<<<<<<") {
-                let start = i;
-                let mut separator = None;
-                let mut end = None;
-
-                // Find separator and end
-                for j in (i + 1)..lines.len() {
-                    if lines[j].starts_with("=======") {
-                        separator = Some(j);
-                    } else if lines[j].starts_with(">>>>>>>") {
-                        end = Some(j);
-                        break;
-                    }
-                }
-
-                if let (Some(sep), Some(e)) = (separator, end) {
-                    let current_lines: Vec<_> = lines[(start + 1)..sep].to_vec();
-                    let incoming_lines: Vec<_> = lines[(sep + 1)..e].to_vec();
-
-                    conflicts.push(Conflict {
-                        id: ConflictId::new(),
-                        start_line: start as u32,
-                        end_line: e as u32,
-                        base: String::new(), // Would extract from base
-                        current: current_lines.join("\n"),
-                        incoming: incoming_lines.join("\n"),
-                        resolution: None,
-                        resolved_content: None,
-                    });
-
-                    i = e + 1;
-                    continue;
-                }
-            }
-            i += 1;
+            result: merged.content,
         }
-
-        // If no markers found, check for differences
-        if conflicts.is_empty() && ours != theirs {
-            conflicts.push(Conflict {
-                id: ConflictId::new(),
-                start_line: 0,
-                end_line: lines.len() as u32,
-                base: base.to_string(),
-                current: ours.to_string(),
-                incoming: theirs.to_string(),
-                resolution: None,
-                resolved_content: None,
-            });
-        }
-
-        conflicts
     }
 
+    /// Result with resolved conflicts filled in, and markers left around
+    /// unresolved ones
     pub fn build_merged(&self) -> String {
         if self.conflicts.is_empty() {
-            return self.ours.clone();
+            return self.result.clone();
         }
 
-        // Build from resolved conflicts
         let mut result = String::new();
-        let lines: Vec<&str> = self.ours.lines().collect();
+        let lines: Vec<&str> = self.result.lines().collect();
         let mut i = 0;
 
         for conflict in &self.conflicts {
+            let Some(ref content) = conflict.resolved_content else {
+                continue;
+            };
+
             // Add lines before conflict
             while i < conflict.start_line as usize && i < lines.len() {
                 result.push_str(lines[i]);
@@ -372,7 +391,7 @@ impl MergeSession {
             }
 
             // Add resolved content
-            if let Some(ref content) = conflict.resolved_content {
+            if !content.is_empty() {
                 result.push_str(content);
                 if !content.ends_with('\n') {
                     result.push('\n');
@@ -390,6 +409,9 @@ impl MergeSession {
             i += 1;
         }
 
+        if !self.result.ends_with('\n') {
+            result.pop();
+        }
         result
     }
 
@@ -423,6 +445,27 @@ pub struct Conflict {
     pub resolved_content: Option<String>,
 }
 
+impl Conflict {
+    /// Word-level differences from the current side to the incoming side
+    pub fn refine(&self) -> ConflictRefinement {
+        let mut current = WordDiff { segments: Vec::new() };
+        let mut incoming = WordDiff { segments: Vec::new() };
+
+        for op in diff_words(&self.current, &self.incoming).ops {
+            match op {
+                DiffOp::Equal(text) => {
+                    current.push(&text, WordDiffKind::Equal);
+                    incoming.push(&text, WordDiffKind::Equal);
+                }
+                DiffOp::Delete(text) => current.push(&text, WordDiffKind::Removed),
+                DiffOp::Insert(text) => incoming.push(&text, WordDiffKind::Added),
+            }
+        }
+
+        ConflictRefinement { current, incoming }
+    }
+}
+
 /// Resolution type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Resolution {
@@ -454,6 +497,10 @@ pub enum MergeEvent {
         file: PathBuf,
         conflict_id: ConflictId,
     },
+    /// Every conflict in the file has a resolution
+    AllResolved(PathBuf),
+    /// The merged file was written and staged
+    FileResolved(PathBuf),
 }
 
 /// Word-level diff for conflict display
@@ -462,15 +509,87 @@ pub struct WordDiff {
     pub segments: Vec<WordDiffSegment>,
 }
 
+impl WordDiff {
+    /// Append text, extending the last segment when it's of the same kind
+    fn push(&mut self, text: &str, kind: WordDiffKind) {
+        match self.segments.last_mut() {
+            Some(last) if last.kind == kind => last.text.push_str(text),
+            _ => self.segments.push(WordDiffSegment {
+                text: text.to_string(),
+                kind,
+            }),
+        }
+    }
+}
+
+/// Word-level differences between the two sides of a conflict
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConflictRefinement {
+    /// Current side, with words missing from incoming marked removed
+    pub current: WordDiff,
+    /// Incoming side, with words missing from current marked added
+    pub incoming: WordDiff,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WordDiffSegment {
     pub text: String,
     pub kind: WordDiffKind,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WordDiffKind {
     Equal,
     Added,
     Removed,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_session() {
+        let service = MergeEditorService::new();
+        let mut events = service.subscribe();
+        let file = PathBuf::from("config.toml");
+        let session = service.open_session_with_style(
+            file.clone(),
+            "name = \"app\"\nversion = 1\nport = 80\n".into(),
+            "name = \"app\"\nversion = 2\nport = 8080\n".into(),
+            "name = \"server\"\nversion = 1\nport = 9090\n".into(),
+            ConflictStyle::Diff3,
+        );
+
+        // The name change only one side made is merged, the port isn't
+        assert_eq!(session.conflicts.len(), 1);
+        let conflict = &session.conflicts[0];
+        assert_eq!(conflict.current, "version = 2\nport = 8080");
+        assert_eq!(conflict.base, "version = 1\nport = 80");
+        assert!(session.result.starts_with("name = \"server\"\n<<<<<<< ours\n"));
+
+        let refinement = conflict.refine();
+        let removed: Vec<_> = refinement.current.segments.iter()
+            .filter(|s| s.kind == WordDiffKind::Removed)
+            .map(|s| s.text.as_str())
+            .collect();
+        assert_eq!(removed, vec!["2", "8080"]);
+
+        service.accept_incoming(&file, &conflict.id);
+        service.accept_current(&file, &conflict.id);
+        match service.get_merged_result(&file) {
+            Some(MergeResult::Complete { content }) => {
+                assert_eq!(content, "name = \"server\"\nversion = 2\nport = 8080\n");
+            }
+            other => panic!("expected complete merge, got {:?}", other),
+        }
+
+        let mut all_resolved = 0;
+        while let Ok(event) = events.try_recv() {
+            if matches!(event, MergeEvent::AllResolved(_)) {
+                all_resolved += 1;
+            }
+        }
+        assert_eq!(all_resolved, 2);
+    }
+}
@@ -175,6 +175,36 @@ impl MinimapDecorationsService {
         }
     }
 
+    /// Show coverage, replacing any earlier coverage for the file
+    pub fn set_coverage(&self, file: &PathBuf, ranges: Vec<CoverageRange>) {
+        self.remove_by_source(file, "coverage");
+
+        let config = self.config.read();
+        if !config.show_coverage {
+            return;
+        }
+
+        for range in ranges {
+            let (color, tooltip) = match range.kind {
+                CoverageKind::Covered => (config.coverage_covered_color.clone(), "Covered"),
+                CoverageKind::Uncovered => (config.coverage_uncovered_color.clone(), "Not covered"),
+                CoverageKind::UncoveredChange => {
+                    (config.coverage_uncovered_change_color.clone(), "Changed lines not covered")
+                }
+            };
+
+            self.add_decoration(file, MinimapDecoration {
+                line: range.start_line,
+                end_line: Some(range.end_line),
+                color,
+                lane: DecorationLane::Center,
+                kind: DecorationKind::Range,
+                source: "coverage".to_string(),
+                tooltip: Some(tooltip.to_string()),
+            });
+        }
+    }
+
     /// Add selection highlight
     pub fn add_selection(&self, file: &PathBuf, start_line: u32, end_line: u32) {
         let config = self.config.read();
@@ -304,6 +334,23 @@ pub enum GitChangeKind {
     Deleted,
 }
 
+/// Run of lines with the same coverage
+#[derive(Debug, Clone)]
+pub struct CoverageRange {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub kind: CoverageKind,
+}
+
+/// Coverage kind
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageKind {
+    Covered,
+    Uncovered,
+    /// Uncovered and changed against the diff base
+    UncoveredChange,
+}
+
 /// Minimap decorations configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MinimapDecorationsConfig {
@@ -315,6 +362,8 @@ pub struct MinimapDecorationsConfig {
     pub show_search: bool,
     /// Show git changes
     pub show_git_changes: bool,
+    /// Show coverage
+    pub show_coverage: bool,
     /// Error color
     pub error_color: String,
     /// Warning color
@@ -329,6 +378,12 @@ pub struct MinimapDecorationsConfig {
     pub git_modified_color: String,
     /// Git deleted color
     pub git_deleted_color: String,
+    /// Covered lines color
+    pub coverage_covered_color: String,
+    /// Uncovered lines color
+    pub coverage_uncovered_color: String,
+    /// Uncovered changed lines color
+    pub coverage_uncovered_change_color: String,
 }
 
 impl Default for MinimapDecorationsConfig {
@@ -338,6 +393,7 @@ impl Default for MinimapDecorationsConfig {
             show_warnings: true,
             show_search: true,
             show_git_changes: true,
+            show_coverage: true,
             error_color: "#ff0000".to_string(),
             warning_color: "#ffcc00".to_string(),
             search_color: "#515c6a".to_string(),
@@ -345,6 +401,9 @@ impl Default for MinimapDecorationsConfig {
             git_added_color: "#587c0c".to_string(),
             git_modified_color: "#0c7d9d".to_string(),
             git_deleted_color: "#94151b".to_string(),
+            coverage_covered_color: "#2ea043".to_string(),
+            coverage_uncovered_color: "#f85149".to_string(),
+            coverage_uncovered_change_color: "#ff7b72".to_string(),
         }
     }
 }
@@ -54,6 +54,50 @@ impl ProblemsService {
         let _ = self.events.send(ProblemsEvent::DiagnosticsChanged { file: file.clone() });
     }
 
+    /// Append diagnostics for a file, keeping existing items
+    pub fn add_diagnostics(&self, file: PathBuf, items: Vec<ProblemItem>) {
+        if items.is_empty() {
+            return;
+        }
+        self.diagnostics.write().entry(file.clone()).or_default().extend(items);
+        self.update_stats();
+
+        let _ = self.events.send(ProblemsEvent::DiagnosticsChanged { file });
+    }
+
+    /// Clear diagnostics reported by a source across all files
+    pub fn clear_source(&self, source: &str) {
+        self.clear_where(|item| item.source.as_deref() == Some(source));
+    }
+
+    /// Clear diagnostics reported by an owner across all files
+    pub fn clear_owner(&self, owner: &str) {
+        self.clear_where(|item| item.owner.as_deref() == Some(owner));
+    }
+
+    fn clear_where(&self, matches: impl Fn(&ProblemItem) -> bool) {
+        let mut changed = Vec::new();
+        {
+            let mut diagnostics = self.diagnostics.write();
+            diagnostics.retain(|file, items| {
+                let before = items.len();
+                items.retain(|item| !matches(item));
+                if items.len() != before {
+                    changed.push(file.clone());
+                }
+                !items.is_empty()
+            });
+        }
+        if changed.is_empty() {
+            return;
+        }
+        self.update_stats();
+
+        for file in changed {
+            let _ = self.events.send(ProblemsEvent::DiagnosticsChanged { file });
+        }
+    }
+
     /// Clear all diagnostics
     pub fn clear_all(&self) {
         self.diagnostics.write().clear();
@@ -169,6 +213,9 @@ pub struct ProblemItem {
     pub related: Vec<RelatedInformation>,
     /// Tags
     pub tags: Vec<ProblemTag>,
+    /// What reported the item (e.g. a task run), for clearing only its items
+    #[serde(default)]
+    pub owner: Option<String>,
 }
 
 impl ProblemItem {
@@ -181,6 +228,7 @@ impl ProblemItem {
             location,
             related: Vec::new(),
             tags: Vec::new(),
+            owner: None,
         }
     }
 
@@ -193,6 +241,7 @@ impl ProblemItem {
             location,
             related: Vec::new(),
             tags: Vec::new(),
+            owner: None,
         }
     }
 
@@ -201,6 +250,11 @@ impl ProblemItem {
         self
     }
 
+    pub fn with_owner(mut self, owner: impl Into<String>) -> Self {
+        self.owner = Some(owner.into());
+        self
+    }
+
     pub fn with_code(mut self, code: impl Into<String>) -> Self {
         self.code = Some(ProblemCode::String(code.into()));
         self
@@ -10,6 +10,7 @@ pub mod symbols;
 
 use std::sync::Arc;
 use std::collections::HashMap;
+use std::path::Path;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
@@ -56,6 +57,13 @@ impl QuickOpenService {
         self.providers.write().insert(id.to_string(), Arc::new(provider));
     }
 
+    /// Register the tasks detected in the workspace at `root`
+    pub async fn register_tasks(&self, root: &Path) -> anyhow::Result<()> {
+        let provider = providers::TaskListProvider::detect(root).await?;
+        self.register("tasks", provider);
+        Ok(())
+    }
+
     /// Subscribe to events
     pub fn subscribe(&self) -> broadcast::Receiver<QuickOpenEvent> {
         self.events.subscribe()
@@ -1,6 +1,6 @@
 //! Quick open providers
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use async_trait::async_trait;
 
 use crate::QuickPickItem;
@@ -190,6 +190,85 @@ impl Provider for CommandProvider {
     }
 }
 
+/// Task provider
+pub struct TaskListProvider {
+    tasks: Vec<TaskInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct TaskInfo {
+    pub name: String,
+    /// Command line, shown as detail
+    pub command: String,
+    /// Monorepo package the task belongs to
+    pub package: Option<String>,
+    /// Defined at the workspace root rather than in a package below it
+    pub workspace: bool,
+}
+
+impl From<&task::Task> for TaskInfo {
+    fn from(task: &task::Task) -> Self {
+        let command = std::iter::once(&task.command)
+            .chain(&task.args)
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            name: task.name.clone(),
+            command,
+            package: task.package.clone(),
+            workspace: task.package.is_none(),
+        }
+    }
+}
+
+impl TaskListProvider {
+    pub fn new(tasks: Vec<TaskInfo>) -> Self {
+        Self { tasks }
+    }
+
+    /// Provider for the tasks detected in the workspace at `root`
+    pub async fn detect(root: &Path) -> anyhow::Result<Self> {
+        let tasks = task::providers::TaskProviderRegistry::new().detect(root).await?;
+        Ok(Self::new(tasks.iter()
+            .map(|task| TaskInfo {
+                // The root can be a package too, so go by where the task runs
+                workspace: task.cwd.as_deref() == Some(root),
+                ..TaskInfo::from(task)
+            })
+            .collect()))
+    }
+}
+
+#[async_trait]
+impl Provider for TaskListProvider {
+    fn id(&self) -> &str {
+        "tasks"
+    }
+
+    fn prefix(&self) -> Option<&str> {
+        Some("task ")
+    }
+
+    async fn provide(&self, _ctx: &ProviderContext) -> anyhow::Result<Vec<QuickPickItem>> {
+        // Workspace-level tasks first, then grouped by package
+        let mut tasks: Vec<&TaskInfo> = self.tasks.iter().collect();
+        tasks.sort_by(|a, b| (!a.workspace, &a.package, &a.name).cmp(&(!b.workspace, &b.package, &b.name)));
+
+        Ok(tasks.into_iter()
+            .map(|task| {
+                let mut item = QuickPickItem::new(&task.name)
+                    .with_detail(&task.command)
+                    .with_icon("tools");
+                if let Some(package) = &task.package {
+                    item = item.with_description(package);
+                }
+                item
+            })
+            .collect())
+    }
+}
+
 /// Symbol provider
 pub struct SymbolProvider;
 
@@ -246,3 +325,37 @@ impl Provider for WorkspaceSymbolProvider {
         Ok(Vec::new())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_workspace_tasks_first() {
+        let root = std::env::temp_dir().join(format!("foxkit-quickopen-tasks-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("package.json", r#"{"name": "acme", "workspaces": ["packages/*"], "scripts": {"lint": "eslint ."}}"#);
+        write("packages/ui/package.json", r#"{"name": "@acme/ui", "scripts": {"build": "tsc"}}"#);
+
+        let provider = TaskListProvider::detect(&root).await.unwrap();
+        let ctx = ProviderContext {
+            workspace: root.clone(),
+            current_file: None,
+            query: String::new(),
+        };
+        let items = provider.provide(&ctx).await.unwrap();
+        let labels: Vec<_> = items.iter().map(|i| (i.label.as_str(), i.description.as_deref())).collect();
+        assert_eq!(labels, vec![
+            ("npm: lint", Some("acme")),
+            ("npm: build - @acme/ui", Some("@acme/ui")),
+        ]);
+        assert_eq!(items[0].detail.as_deref(), Some("npm run lint"));
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
@@ -1,45 +1,57 @@
 //! Git blame
+//!
+//! Blames files with move/copy detection and `.git-blame-ignore-revs`, and
+//! keeps the blame lined up with unsaved edits by diffing the buffer against
+//! the blamed text, so annotations follow lines while typing.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
+use diff::{myers_diff, DiffOp};
+use git::blame::{blame_file, Blame, BlameLine, BlameOptions};
 use serde::{Deserialize, Serialize};
 
 /// Blame service
 pub struct BlameService {
     repo_path: PathBuf,
-    cache: parking_lot::RwLock<HashMap<PathBuf, BlameResult>>,
+    options: parking_lot::RwLock<BlameOptions>,
+    /// Blame of the files as saved
+    cache: parking_lot::RwLock<HashMap<PathBuf, Blame>>,
+    /// Blame shifted over unsaved edits
+    live: parking_lot::RwLock<HashMap<PathBuf, Blame>>,
 }
 
 impl BlameService {
     pub fn new(repo_path: PathBuf) -> Self {
         Self {
             repo_path,
+            options: parking_lot::RwLock::new(BlameOptions::default()),
             cache: parking_lot::RwLock::new(HashMap::new()),
+            live: parking_lot::RwLock::new(HashMap::new()),
         }
     }
 
+    /// Change how files are blamed, e.g. to turn off copy detection
+    pub fn set_options(&self, options: BlameOptions) {
+        *self.options.write() = options;
+        self.clear_cache();
+    }
+
     /// Get blame for file
     pub async fn blame(&self, file: &PathBuf) -> anyhow::Result<BlameResult> {
         // Check cache
+        if let Some(live) = self.live.read().get(file) {
+            return Ok(BlameResult::from(live));
+        }
         if let Some(cached) = self.cache.read().get(file) {
-            return Ok(cached.clone());
+            return Ok(BlameResult::from(cached));
         }
 
-        let output = tokio::process::Command::new("git")
-            .args([
-                "blame",
-                "--porcelain",
-                file.to_str().unwrap_or(""),
-            ])
-            .current_dir(&self.repo_path)
-            .output()
-            .await?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let result = parse_blame_output(&stdout)?;
+        let options = self.options.read().clone();
+        let blame = blame_file(&self.repo_path, file, &options).await?;
+        let result = BlameResult::from(&blame);
 
         // Cache result
-        self.cache.write().insert(file.clone(), result.clone());
+        self.cache.write().insert(file.clone(), blame);
 
         Ok(result)
     }
@@ -51,19 +63,66 @@ impl BlameService {
         start_line: u32,
         end_line: u32,
     ) -> anyhow::Result<BlameResult> {
-        let output = tokio::process::Command::new("git")
-            .args([
-                "blame",
-                "--porcelain",
-                &format!("-L{},{}", start_line, end_line),
-                file.to_str().unwrap_or(""),
-            ])
-            .current_dir(&self.repo_path)
-            .output()
-            .await?;
+        let options = self.options.read().clone().lines(start_line as usize, end_line as usize);
+        let blame = blame_file(&self.repo_path, file, &options).await?;
+        Ok(BlameResult::from(&blame))
+    }
+
+    /// Blame a file as of a revision
+    pub async fn blame_revision(&self, file: &Path, revision: &str) -> anyhow::Result<BlameResult> {
+        let options = self.options.read().clone().at(revision);
+        let blame = blame_file(&self.repo_path, file, &options).await?;
+        Ok(BlameResult::from(&blame))
+    }
 
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        parse_blame_output(&stdout)
+    /// Blame the revision before the commit that last changed `line` (0-based)
+    ///
+    /// Returns the blame of the parent commit and the line to show in it,
+    /// or `None` when the line was added by the root commit or isn't
+    /// committed yet. The line is followed through the commit's diff, so a
+    /// changed line lands on the lines it replaced. Works on any blame, so
+    /// calling it again on the result keeps walking back through the line's
+    /// history.
+    pub async fn blame_previous(
+        &self,
+        blame: &BlameResult,
+        line: u32,
+    ) -> anyhow::Result<Option<(BlameResult, u32)>> {
+        let Some(line_blame) = blame.lines.get(line as usize) else {
+            return Ok(None);
+        };
+        let (Some(commit), Some(path)) = (&line_blame.previous_commit, &line_blame.previous_path) else {
+            return Ok(None);
+        };
+
+        let previous = self.blame_revision(Path::new(path), commit).await?;
+        let diff = git::diff::diff_blobs(
+            &self.repo_path,
+            &format!("{}:{}", commit, path),
+            &format!("{}:{}", line_blame.commit_id, line_blame.original_path),
+        ).await?;
+        let target = diff.map_or(line_blame.original_line, |diff| diff.old_line(line_blame.original_line));
+        let target = target.saturating_sub(1).min(previous.lines.len().saturating_sub(1) as u32);
+        Ok(Some((previous, target)))
+    }
+
+    /// Shift the blame of a file over its unsaved buffer text
+    ///
+    /// Lines the buffer still has keep their commit, and new or changed lines
+    /// show as not committed yet. Only the region between the unchanged
+    /// start and end of the file is diffed, so this is cheap per keystroke.
+    /// Returns whether the blame changed; files not blamed yet are skipped.
+    pub fn update_buffer(&self, file: &Path, text: &str) -> bool {
+        let Some(shifted) = self.cache.read().get(file).map(|blame| shift_blame(blame, text)) else {
+            return false;
+        };
+
+        let mut live = self.live.write();
+        if live.get(file).is_some_and(|old| same_lines(old, &shifted)) {
+            return false;
+        }
+        live.insert(file.to_path_buf(), shifted);
+        true
     }
 
     /// Get inline blame annotation for line
@@ -88,68 +147,92 @@ impl BlameService {
     /// Invalidate cache for file
     pub fn invalidate(&self, file: &PathBuf) {
         self.cache.write().remove(file);
+        self.live.write().remove(file);
     }
 
     /// Clear all cache
     pub fn clear_cache(&self) {
         self.cache.write().clear();
+        self.live.write().clear();
     }
 }
 
-fn parse_blame_output(output: &str) -> anyhow::Result<BlameResult> {
-    let mut lines = Vec::new();
-    let mut commits: HashMap<String, BlameCommit> = HashMap::new();
-    
-    let mut current_commit_id = String::new();
-    let mut current_author = String::new();
-    let mut current_author_mail = String::new();
-    let mut current_author_time = 0i64;
-    let mut current_summary = String::new();
-    let mut current_line = 0u32;
-
-    for line in output.lines() {
-        if line.starts_with(|c: char| c.is_ascii_hexdigit()) && line.len() >= 40 {
-            // New commit line: <sha> <orig_line> <final_line> [<num_lines>]
-            let parts: Vec<_> = line.split_whitespace().collect();
-            if parts.len() >= 3 {
-                current_commit_id = parts[0].to_string();
-                current_line = parts[2].parse().unwrap_or(0);
-            }
-        } else if let Some(author) = line.strip_prefix("author ") {
-            current_author = author.to_string();
-        } else if let Some(mail) = line.strip_prefix("author-mail ") {
-            current_author_mail = mail.trim_matches(|c| c == '<' || c == '>').to_string();
-        } else if let Some(time) = line.strip_prefix("author-time ") {
-            current_author_time = time.parse().unwrap_or(0);
-        } else if let Some(summary) = line.strip_prefix("summary ") {
-            current_summary = summary.to_string();
-        } else if line.starts_with('\t') {
-            // Content line - commit info is complete
-            if !current_commit_id.is_empty() {
-                let commit = commits.entry(current_commit_id.clone())
-                    .or_insert_with(|| BlameCommit {
-                        id: current_commit_id.clone(),
-                        author: current_author.clone(),
-                        author_email: current_author_mail.clone(),
-                        timestamp: current_author_time,
-                        summary: current_summary.clone(),
-                    });
-
-                lines.push(LineBlame {
-                    line: current_line,
-                    commit_id: current_commit_id.clone(),
-                    author: commit.author.clone(),
-                    timestamp: commit.timestamp,
-                    summary: commit.summary.clone(),
-                });
+/// Blame of `text`, taking lines it shares with the blamed text from `blame`
+fn shift_blame(blame: &Blame, text: &str) -> Blame {
+    let old: Vec<&str> = blame.lines.iter().map(|l| l.content.as_str()).collect();
+    let new: Vec<&str> = text.lines().collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let mut shifted = Blame::new(blame.path.clone());
+    let keep = |line: &BlameLine, line_number: usize| BlameLine { line_number, ..line.clone() };
+
+    shifted.lines.extend(blame.lines[..prefix].iter().cloned());
+    let mut old_index = prefix;
+    for op in myers_diff(&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]).ops {
+        let line_number = shifted.lines.len() + 1;
+        match op {
+            DiffOp::Equal(_) => {
+                shifted.lines.push(keep(&blame.lines[old_index], line_number));
+                old_index += 1;
             }
+            DiffOp::Delete(_) => old_index += 1,
+            DiffOp::Insert(content) => shifted.lines.push(BlameLine::uncommitted(line_number, content)),
         }
     }
+    for line in &blame.lines[old.len() - suffix..] {
+        let line_number = shifted.lines.len() + 1;
+        shifted.lines.push(keep(line, line_number));
+    }
+    shifted
+}
 
-    Ok(BlameResult {
-        lines,
-        commits: commits.into_values().collect(),
-    })
+fn same_lines(a: &Blame, b: &Blame) -> bool {
+    a.lines.len() == b.lines.len()
+        && a.lines.iter().zip(&b.lines).all(|(x, y)| x.commit_id == y.commit_id && x.content == y.content)
+}
+
+impl From<&Blame> for BlameResult {
+    fn from(blame: &Blame) -> Self {
+        let mut commits: HashMap<String, BlameCommit> = HashMap::new();
+        let lines = blame
+            .lines
+            .iter()
+            .map(|line| {
+                commits.entry(line.commit_id.clone()).or_insert_with(|| BlameCommit {
+                    id: line.commit_id.clone(),
+                    author: line.author.clone(),
+                    author_email: line.email.clone(),
+                    timestamp: line.timestamp,
+                    summary: line.summary.clone().unwrap_or_default(),
+                });
+
+                let (previous_commit, previous_path) = line.previous.clone().unzip();
+                LineBlame {
+                    line: line.line_number as u32,
+                    commit_id: line.commit_id.clone(),
+                    author: line.author.clone(),
+                    timestamp: line.timestamp,
+                    summary: line.summary.clone().unwrap_or_default(),
+                    original_line: line.original_line as u32,
+                    original_path: line.original_path.clone(),
+                    previous_commit,
+                    previous_path,
+                }
+            })
+            .collect();
+
+        BlameResult {
+            lines,
+            commits: commits.into_values().collect(),
+        }
+    }
 }
 
 fn format_relative_date(timestamp: i64) -> String {
@@ -194,6 +277,14 @@ pub struct LineBlame {
     pub author: String,
     pub timestamp: i64,
     pub summary: String,
+    /// Line number in the commit (1-based)
+    pub original_line: u32,
+    /// Path in the commit, which differs when the line was moved or copied
+    pub original_path: String,
+    /// Parent of the commit, for blaming the previous revision
+    pub previous_commit: Option<String>,
+    /// Path of the file in `previous_commit`
+    pub previous_path: Option<String>,
 }
 
 /// Blame commit
@@ -233,3 +324,104 @@ impl BlameAnnotation {
         format!("{}, {}", self.author, self.date)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git::test_support::{git_in, test_repo};
+
+    #[test]
+    fn test_shift_blame_over_edits() {
+        let mut blame = Blame::new("main.rs");
+        for (n, (commit, content)) in [("a", "fn main() {"), ("b", "    run();"), ("a", "}")].into_iter().enumerate() {
+            blame.lines.push(BlameLine {
+                commit_id: commit.repeat(40),
+                content: content.to_string(),
+                ..BlameLine::uncommitted(n + 1, "")
+            });
+        }
+
+        let shifted = shift_blame(&blame, "// entry\nfn main() {\n    run(1);\n}\n");
+        let commits: Vec<_> = shifted.lines.iter().map(|l| (l.line_number, l.is_committed())).collect();
+        assert_eq!(commits, vec![(1, false), (2, true), (3, false), (4, true)]);
+        assert_eq!(shifted.lines[3].commit_id, "a".repeat(40));
+
+        let service = BlameService::new(PathBuf::from("."));
+        service.cache.write().insert(PathBuf::from("main.rs"), blame);
+        assert!(service.update_buffer(Path::new("main.rs"), "fn main() {\n}\n"));
+        assert!(!service.update_buffer(Path::new("main.rs"), "fn main() {\n}"));
+        assert_eq!(service.live.read()[Path::new("main.rs")].lines.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_blame_previous() {
+        let dir = test_repo("scm-blame-previous");
+        std::fs::write(dir.join("list.txt"), "alpha\nbravo\ncharlie\ndelta\necho\n").unwrap();
+        git_in(&dir, &["add", "list.txt"]);
+        git_in(&dir, &["commit", "-q", "-m", "Add list"]);
+        std::fs::write(dir.join("list.txt"), "x-ray\nyankee\nalpha\nbravo\nCHARLIE\ndelta\necho\n").unwrap();
+        git_in(&dir, &["commit", "-q", "-am", "Edit list"]);
+
+        let service = BlameService::new(dir.clone());
+        let blame = service.blame(&PathBuf::from("list.txt")).await.unwrap();
+        assert_eq!(blame.lines[4].summary, "Edit list");
+
+        // The changed line lands on the line it replaced, not on its own
+        // line number in the parent
+        let (previous, line) = service.blame_previous(&blame, 4).await.unwrap().unwrap();
+        assert_eq!(previous.lines.len(), 5);
+        assert_eq!(line, 2);
+        assert_eq!(previous.lines[2].summary, "Add list");
+
+        let (_, line) = service.blame_previous(&blame, 0).await.unwrap().unwrap();
+        assert_eq!(line, 0);
+
+        // Added by the root commit
+        assert!(service.blame_previous(&blame, 5).await.unwrap().is_none());
+        assert!(service.blame_previous(&previous, 2).await.unwrap().is_none());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_move_and_copy_attribution() {
+        let dir = test_repo("scm-blame-moves");
+        let git = |args: &[&str]| git_in(&dir, args);
+        let header = "fn parse_header(input: &str) -> Option<Header> {\n    let (name, value) = input.split_once(':')?;\n    Some(Header { name: name.trim().into(), value: value.trim().into() })\n}\n";
+        let status = "fn parse_status(line: &str) -> Option<u16> {\n    let code = line.split_whitespace().nth(1)?;\n    code.parse().ok().filter(|code| (100..600).contains(code))\n}\n";
+
+        std::fs::write(dir.join("a.rs"), format!("{}\n{}", header, status)).unwrap();
+        git(&["add", "a.rs"]);
+        git(&["commit", "-q", "-m", "Add parsers"]);
+        // Move one function to a new file and swap the order in the old one
+        std::fs::write(dir.join("a.rs"), format!("// Parsers\n{}", status)).unwrap();
+        std::fs::write(dir.join("b.rs"), header).unwrap();
+        git(&["add", "a.rs", "b.rs"]);
+        git(&["commit", "-q", "-m", "Split parsers"]);
+        std::fs::write(dir.join("c.rs"), format!("{}\n{}", header, status)).unwrap();
+        git(&["add", "c.rs"]);
+        git(&["commit", "-q", "-m", "Add c"]);
+        std::fs::write(dir.join("c.rs"), format!("{}\n{}", status, header)).unwrap();
+        git(&["commit", "-q", "-am", "Reorder c"]);
+
+        let service = BlameService::new(dir.clone());
+        let summaries = |blame: &BlameResult| blame.lines.iter().map(|l| l.summary.clone()).collect::<Vec<_>>();
+
+        // -C finds the lines in the file they were moved from
+        let blame = service.blame(&PathBuf::from("b.rs")).await.unwrap();
+        assert_eq!(summaries(&blame), vec!["Add parsers"; 4]);
+        assert_eq!(blame.lines[0].original_path, "a.rs");
+        // -M follows the function moved within the file
+        let blame = service.blame(&PathBuf::from("c.rs")).await.unwrap();
+        assert_eq!(summaries(&blame)[5..], vec!["Add c"; 4]);
+
+        service.set_options(BlameOptions { detect_moves: false, detect_copies: 0, ..Default::default() });
+        let blame = service.blame(&PathBuf::from("b.rs")).await.unwrap();
+        assert_eq!(summaries(&blame), vec!["Split parsers"; 4]);
+        assert_eq!(blame.lines[0].original_path, "b.rs");
+        let blame = service.blame(&PathBuf::from("c.rs")).await.unwrap();
+        assert_eq!(summaries(&blame)[5..], vec!["Reorder c"; 4]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,417 @@
+//! Live change markers for unsaved buffers
+//!
+//! Diffs the buffer text against the file's blob in the index or HEAD on
+//! every edit, so the gutter shows added, modified and deleted lines before
+//! the file is saved. Edits arrive as `editor.buffer_changed` events.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use buffer::{Buffer, SharedBuffer};
+use diff::{myers_diff, DiffOp};
+use foxkit_core::event::{editor::BufferChanged, EventEmitter};
+use git::DiffBase;
+use gutter::{GutterService, LineChange, LineChangeKind};
+use parking_lot::RwLock;
+use rope::Point;
+
+/// Time an edit may spend diffing before falling back to a coarse result
+pub const DEFAULT_BUDGET: Duration = Duration::from_millis(4);
+
+/// Lines changed against the diff base
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirtyChange {
+    pub kind: LineChangeKind,
+    /// Lines in the buffer (0-based), empty for deletions
+    pub lines: Range<u32>,
+    /// Lines they replace in the diff base (0-based)
+    pub original: Range<u32>,
+}
+
+impl DirtyChange {
+    /// Whether the change's gutter marker is on `line`
+    pub fn contains(&self, line: u32) -> bool {
+        match self.kind {
+            LineChangeKind::Deleted => self.lines.start.saturating_sub(1) == line,
+            _ => self.lines.contains(&line),
+        }
+    }
+}
+
+/// Diff of a buffer against its diff base, kept up to date as it's edited
+pub struct DirtyDiff {
+    base: Vec<String>,
+    current: Vec<String>,
+    changes: Vec<DirtyChange>,
+    exact: bool,
+    budget: Duration,
+    /// Measured cost of the diff per compared line pair
+    nanos_per_cell: f64,
+}
+
+impl DirtyDiff {
+    pub fn new(base: &str) -> Self {
+        let base: Vec<String> = base.lines().map(String::from).collect();
+        Self {
+            current: base.clone(),
+            base,
+            changes: Vec::new(),
+            exact: true,
+            budget: DEFAULT_BUDGET,
+            nanos_per_cell: 10.0,
+        }
+    }
+
+    /// Time each update may spend diffing
+    pub fn with_budget(mut self, budget: Duration) -> Self {
+        self.budget = budget;
+        self
+    }
+
+    /// Replace the diff base, e.g. after staging or committing
+    pub fn set_base(&mut self, base: &str) {
+        self.base = base.lines().map(String::from).collect();
+        self.recompute(false);
+    }
+
+    /// Diff new buffer text, returning whether the changes may have moved
+    ///
+    /// Only the region between the unchanged start and end of the file is
+    /// diffed. When that would take longer than the budget, the region is
+    /// reported as one change until [`DirtyDiff::refine`] runs.
+    pub fn update(&mut self, text: &str) -> bool {
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        if lines == self.current {
+            return false;
+        }
+        self.current = lines;
+        self.recompute(false);
+        true
+    }
+
+    /// Finish a diff that was cut short by the budget
+    pub fn refine(&mut self) -> bool {
+        if self.exact {
+            return false;
+        }
+        self.recompute(true);
+        true
+    }
+
+    /// Whether the changes come from a full diff
+    pub fn is_exact(&self) -> bool {
+        self.exact
+    }
+
+    pub fn changes(&self) -> &[DirtyChange] {
+        &self.changes
+    }
+
+    /// Change whose gutter marker is on `line`
+    pub fn change_at(&self, line: u32) -> Option<&DirtyChange> {
+        self.changes.iter().find(|c| c.contains(line))
+    }
+
+    /// Original text of the change at `line`, for showing inline
+    pub fn peek_original(&self, line: u32) -> Option<String> {
+        let change = self.change_at(line)?;
+        let lines = &self.base[change.original.start as usize..change.original.end as usize];
+        Some(lines.iter().map(|l| format!("{}\n", l)).collect())
+    }
+
+    /// Put the original text of the change at `line` back into the buffer
+    pub fn revert_change(&mut self, buffer: &mut Buffer, line: u32) -> bool {
+        let Some(change) = self.change_at(line).cloned() else {
+            return false;
+        };
+
+        let text = buffer.text();
+        let offset = |line: u32| {
+            if (line as usize) < buffer.line_count() {
+                buffer.point_to_offset(Point::new(line as usize, 0))
+            } else {
+                text.len()
+            }
+        };
+        let mut start = offset(change.lines.start);
+        let end = offset(change.lines.end);
+        let mut original = self.base[change.original.start as usize..change.original.end as usize]
+            .iter()
+            .map(|l| format!("{}\n", l))
+            .collect::<String>();
+
+        // The last line has no newline to replace
+        if end == text.len() && !text.is_empty() && !text.ends_with('\n') {
+            if start == end {
+                original.insert(0, '\n');
+                original.pop();
+            } else if original.pop().is_none() {
+                start -= 1;
+            }
+        }
+
+        buffer.replace(start..end, &original);
+        self.update(&buffer.text());
+        true
+    }
+
+    /// Changes as gutter markers
+    pub fn line_changes(&self) -> Vec<LineChange> {
+        self.changes
+            .iter()
+            .map(|c| LineChange {
+                start_line: c.lines.start,
+                end_line: c.lines.end,
+                kind: c.kind,
+            })
+            .collect()
+    }
+
+    fn recompute(&mut self, force: bool) {
+        let (old, new) = (&self.base, &self.current);
+        let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let old_mid = &old[prefix..old.len() - suffix];
+        let new_mid = &new[prefix..new.len() - suffix];
+
+        let cells = (old_mid.len() * new_mid.len()) as f64;
+        if !force && cells * self.nanos_per_cell > self.budget.as_nanos() as f64 {
+            // Too big to diff within the frame, so mark the whole region
+            self.changes = changes_from_ops(
+                old_mid.iter().map(|l| DiffOp::Delete(l.clone()))
+                    .chain(new_mid.iter().map(|l| DiffOp::Insert(l.clone()))),
+                prefix as u32,
+            );
+            self.exact = false;
+            return;
+        }
+
+        let started = Instant::now();
+        let old_mid: Vec<&str> = old_mid.iter().map(String::as_str).collect();
+        let new_mid: Vec<&str> = new_mid.iter().map(String::as_str).collect();
+        let diff = myers_diff(&old_mid, &new_mid);
+
+        // Only large diffs time reliably
+        if cells > 10_000.0 {
+            let measured = started.elapsed().as_nanos() as f64 / cells;
+            self.nanos_per_cell = self.nanos_per_cell * 0.5 + measured * 0.5;
+        }
+
+        self.changes = changes_from_ops(diff.ops.into_iter(), prefix as u32);
+        self.exact = true;
+    }
+}
+
+/// Group diff ops into changes, starting at `line` in both texts
+fn changes_from_ops(ops: impl Iterator<Item = DiffOp>, line: u32) -> Vec<DirtyChange> {
+    let mut changes: Vec<DirtyChange> = Vec::new();
+    let mut old_line = line;
+    let mut new_line = line;
+    let mut pending: Option<DirtyChange> = None;
+
+    for op in ops {
+        match op {
+            DiffOp::Equal(_) => {
+                changes.extend(pending.take());
+                old_line += 1;
+                new_line += 1;
+            }
+            DiffOp::Delete(_) | DiffOp::Insert(_) => {
+                let change = pending.get_or_insert(DirtyChange {
+                    kind: LineChangeKind::Deleted,
+                    lines: new_line..new_line,
+                    original: old_line..old_line,
+                });
+                if op.is_delete() {
+                    old_line += 1;
+                    change.original.end = old_line;
+                } else {
+                    new_line += 1;
+                    change.lines.end = new_line;
+                }
+                change.kind = match (change.original.is_empty(), change.lines.is_empty()) {
+                    (true, _) => LineChangeKind::Added,
+                    (false, true) => LineChangeKind::Deleted,
+                    (false, false) => LineChangeKind::Modified,
+                };
+            }
+        }
+    }
+    changes.extend(pending);
+    changes
+}
+
+/// Keeps gutter change markers in sync with open buffers
+pub struct DirtyDiffService {
+    gutter: Arc<GutterService>,
+    diffs: RwLock<HashMap<PathBuf, DirtyDiff>>,
+    /// Buffers of the tracked files, read again when they change
+    buffers: RwLock<HashMap<PathBuf, SharedBuffer>>,
+}
+
+impl DirtyDiffService {
+    pub fn new(gutter: Arc<GutterService>) -> Self {
+        Self {
+            gutter,
+            diffs: RwLock::new(HashMap::new()),
+            buffers: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Update markers whenever a tracked buffer reports an edit
+    ///
+    /// Handlers run when the event is emitted, so emit it after releasing
+    /// the buffer's write lock.
+    pub fn listen(self: &Arc<Self>, events: &EventEmitter) {
+        let service = Arc::downgrade(self);
+        events.on(move |event: &BufferChanged| {
+            if let Some(service) = service.upgrade() {
+                service.buffer_changed(&event.path);
+            }
+        });
+    }
+
+    fn buffer_changed(&self, file: &Path) {
+        let buffer = self.buffers.read().get(file).cloned();
+        if let Some(buffer) = buffer {
+            self.update(file, &buffer.read());
+        }
+    }
+
+    /// Start tracking a file against its version in the index or HEAD
+    ///
+    /// Files that aren't tracked there get no markers. Calling this again
+    /// reloads the base, e.g. after staging or committing.
+    pub fn open(&self, repo: &git::Repository, file: &Path, base: DiffBase, buffer: &SharedBuffer) -> anyhow::Result<()> {
+        let Some(blob) = repo.blob(file, base)? else {
+            self.close(file);
+            return Ok(());
+        };
+
+        let mut diff = DirtyDiff::new(&String::from_utf8_lossy(&blob));
+        diff.update(&buffer.read().text());
+        diff.refine();
+        self.gutter.set_changes(&file.to_path_buf(), &diff.line_changes());
+        self.diffs.write().insert(file.to_path_buf(), diff);
+        self.buffers.write().insert(file.to_path_buf(), buffer.clone());
+        Ok(())
+    }
+
+    /// Update markers after an edit
+    pub fn update(&self, file: &Path, buffer: &Buffer) {
+        let mut diffs = self.diffs.write();
+        if let Some(diff) = diffs.get_mut(file)
+            && diff.update(&buffer.text())
+        {
+            self.gutter.set_changes(&file.to_path_buf(), &diff.line_changes());
+        }
+    }
+
+    /// Finish diffs cut short by the frame budget, e.g. when the editor is idle
+    pub fn refine(&self, file: &Path) {
+        let mut diffs = self.diffs.write();
+        if let Some(diff) = diffs.get_mut(file)
+            && diff.refine()
+        {
+            self.gutter.set_changes(&file.to_path_buf(), &diff.line_changes());
+        }
+    }
+
+    /// Original text of the change at `line`
+    pub fn peek_original(&self, file: &Path, line: u32) -> Option<String> {
+        self.diffs.read().get(file)?.peek_original(line)
+    }
+
+    /// Revert the change at `line` in the buffer
+    pub fn revert_change(&self, file: &Path, buffer: &mut Buffer, line: u32) -> bool {
+        let mut diffs = self.diffs.write();
+        let Some(diff) = diffs.get_mut(file) else {
+            return false;
+        };
+        if !diff.revert_change(buffer, line) {
+            return false;
+        }
+        self.gutter.set_changes(&file.to_path_buf(), &diff.line_changes());
+        true
+    }
+
+    /// Stop tracking a file
+    pub fn close(&self, file: &Path) {
+        self.diffs.write().remove(file);
+        self.buffers.write().remove(file);
+        self.gutter.clear_changes(&file.to_path_buf());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dirty_diff() {
+        let mut diff = DirtyDiff::new("one\ntwo\nthree\nfour\n");
+        let mut buffer = Buffer::from_text("zero\none\nTWO\nthree\n");
+        diff.update(&buffer.text());
+
+        let kinds: Vec<_> = diff.changes().iter().map(|c| (c.kind, c.lines.clone())).collect();
+        assert_eq!(kinds, vec![
+            (LineChangeKind::Added, 0..1),
+            (LineChangeKind::Modified, 2..3),
+            (LineChangeKind::Deleted, 4..4),
+        ]);
+
+        assert_eq!(diff.peek_original(2), Some("two\n".to_string()));
+        assert_eq!(diff.peek_original(3), Some("four\n".to_string()));
+
+        assert!(diff.revert_change(&mut buffer, 3));
+        assert!(diff.revert_change(&mut buffer, 2));
+        assert!(diff.revert_change(&mut buffer, 0));
+        assert_eq!(buffer.text(), "one\ntwo\nthree\nfour\n");
+        assert!(diff.changes().is_empty());
+
+        // Past the budget the edited region is one coarse change until refined
+        let mut diff = DirtyDiff::new("a\nb\nc\n").with_budget(Duration::ZERO);
+        diff.update("a\nB\nc\nd\n");
+        assert!(!diff.is_exact());
+        assert_eq!(diff.changes().len(), 1);
+        assert!(diff.refine());
+        assert_eq!(diff.changes().len(), 2);
+    }
+
+    #[test]
+    fn test_service_follows_buffer_edits() {
+        let dir = git::test_support::test_repo("dirty-diff");
+        std::fs::write(dir.join("notes.txt"), "one\ntwo\n").unwrap();
+        git::test_support::git_in(&dir, &["add", "notes.txt"]);
+
+        let gutter = Arc::new(GutterService::new());
+        let service = Arc::new(DirtyDiffService::new(gutter.clone()));
+        let events = EventEmitter::new();
+        service.listen(&events);
+
+        let repo = git::Repository::open(&dir).unwrap();
+        let file = dir.join("notes.txt");
+        let buffer = buffer::shared_buffer(Buffer::from_file(&file, "one\ntwo\n"));
+        service.open(&repo, &file, DiffBase::Index, &buffer).unwrap();
+        assert!(gutter.get_decorations(&file).is_empty());
+
+        buffer.write().insert(0, "zero\n");
+        events.emit(BufferChanged { path: file.clone() });
+        let lines: Vec<_> = gutter.get_decorations(&file).iter().map(|d| d.line).collect();
+        assert_eq!(lines, vec![0]);
+
+        service.close(&file);
+        events.emit(BufferChanged { path: file.clone() });
+        assert!(gutter.get_decorations(&file).is_empty());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -8,6 +8,7 @@ pub mod changes;
 pub mod history;
 pub mod blame;
 pub mod views;
+pub mod dirty_diff;
 
 use std::path::PathBuf;
 use std::sync::Arc;
@@ -19,6 +20,7 @@ use tokio::sync::broadcast;
 pub use provider::{ScmProvider, BuiltinProviders};
 pub use repository::{Repository, RepositoryState};
 pub use changes::{Change, ChangeKind, ResourceState};
+pub use dirty_diff::{DirtyDiff, DirtyDiffService, DirtyChange};
 
 /// SCM service
 pub struct ScmService {
@@ -0,0 +1,301 @@
+//! Commit graph lane layout
+//!
+//! Assigns each commit a lane, newest first, so branches run down their own
+//! column: a commit takes the lane waiting for it, its first parent carries
+//! the lane on, and further parents of (octopus) merges open new lanes. A
+//! lane keeps its colour for as long as it runs, so long-lived branches are
+//! the same colour wherever they're scrolled to.
+//!
+//! Only the lane state is kept while laying out. It's snapshotted every
+//! [`CHECKPOINT_INTERVAL`] rows, so rows for any window of the graph are
+//! rebuilt by replaying at most that many commits.
+
+use std::ops::Range;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{Commit, ConnectionKind, GraphConnection, GraphRow};
+
+/// Rows between lane state snapshots
+pub const CHECKPOINT_INTERVAL: usize = 256;
+
+/// A lane waiting for a commit further down
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Lane {
+    /// Commit the lane runs to
+    pub commit: String,
+    /// Index into [`crate::LANE_COLORS`], modulo its length
+    pub color: usize,
+}
+
+/// Lanes open between two rows
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LaneState {
+    lanes: Vec<Option<Lane>>,
+    next_color: usize,
+    /// Lanes that ended in hidden commits, drawn into the next visible row
+    pending: Vec<GraphConnection>,
+}
+
+impl LaneState {
+    /// Place a commit and move its lane on to its parents
+    ///
+    /// Returns the commit's row, or `None` for hidden commits. Hidden
+    /// commits still pass their lanes on to their parents, so the lanes of
+    /// visible commits stay connected through them.
+    pub fn place(&mut self, hash: &str, parents: &[String], visible: bool) -> Option<GraphRow> {
+        let waiting: Vec<usize> = (0..self.lanes.len())
+            .filter(|&i| self.lanes[i].as_ref().is_some_and(|l| l.commit == hash))
+            .collect();
+
+        let (lane, color) = match waiting.first() {
+            Some(&lane) => (lane, self.lanes[lane].as_ref().map_or(0, |l| l.color)),
+            None => {
+                let color = self.new_color();
+                (self.free_lane(), color)
+            }
+        };
+
+        // Lanes of other children end here
+        let mut connections = std::mem::take(&mut self.pending);
+        for &other in &waiting[waiting.len().min(1)..] {
+            if let Some(ended) = self.lanes[other].take() {
+                connections.push(GraphConnection {
+                    from_lane: other,
+                    to_lane: lane,
+                    kind: ConnectionKind::Branch,
+                    color: ended.color,
+                });
+            }
+        }
+        self.lanes[lane] = None;
+
+        // Lanes passing by this row
+        let passing: Vec<usize> = (0..self.lanes.len()).filter(|&i| self.lanes[i].is_some()).collect();
+
+        for (i, parent) in parents.iter().enumerate() {
+            // The first parent always carries the lane on, even when another
+            // lane already runs to it, so a branch keeps its column until
+            // the lanes meet at the parent
+            let existing = if i == 0 { None } else { self.lane_of(parent) };
+            let kind = if i == 0 { ConnectionKind::Direct } else { ConnectionKind::Merge };
+            let (to_lane, edge_color) = match existing {
+                Some(existing) => (existing, self.lanes[existing].as_ref().map_or(color, |l| l.color)),
+                None => {
+                    let (to_lane, edge_color) = if i == 0 {
+                        (lane, color)
+                    } else {
+                        (self.free_lane(), self.new_color())
+                    };
+                    self.lanes[to_lane] = Some(Lane {
+                        commit: parent.clone(),
+                        color: edge_color,
+                    });
+                    (to_lane, edge_color)
+                }
+            };
+            connections.push(GraphConnection {
+                from_lane: lane,
+                to_lane,
+                kind,
+                color: edge_color,
+            });
+        }
+
+        for i in passing {
+            let color = self.lanes[i].as_ref().map_or(0, |l| l.color);
+            connections.push(GraphConnection {
+                from_lane: i,
+                to_lane: i,
+                kind: ConnectionKind::Direct,
+                color,
+            });
+        }
+
+        while let Some(None) = self.lanes.last() {
+            self.lanes.pop();
+        }
+
+        if !visible {
+            self.pending = connections
+                .into_iter()
+                .filter(|c| matches!(c.kind, ConnectionKind::Branch))
+                .collect();
+            return None;
+        }
+
+        Some(GraphRow {
+            commit_hash: hash.to_string(),
+            lane,
+            color,
+            connections,
+            active_lanes: self.lanes.iter().filter(|l| l.is_some()).count(),
+        })
+    }
+
+    /// Lanes open below the last placed commit, including gaps
+    pub fn width(&self) -> usize {
+        self.lanes.len()
+    }
+
+    fn lane_of(&self, commit: &str) -> Option<usize> {
+        self.lanes.iter().position(|l| l.as_ref().is_some_and(|l| l.commit == commit))
+    }
+
+    fn free_lane(&mut self) -> usize {
+        self.lanes.iter().position(Option::is_none).unwrap_or_else(|| {
+            self.lanes.push(None);
+            self.lanes.len() - 1
+        })
+    }
+
+    fn new_color(&mut self) -> usize {
+        self.next_color += 1;
+        self.next_color - 1
+    }
+}
+
+/// Lane state before a row
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Checkpoint {
+    /// Index of the row's commit among all commits
+    commit: usize,
+    row: usize,
+    state: LaneState,
+}
+
+/// Incremental layout of a commit graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GraphLayout {
+    checkpoints: Vec<Checkpoint>,
+    /// State after the commits laid out so far
+    state: LaneState,
+    laid_out: usize,
+    rows: usize,
+    lane_count: usize,
+}
+
+impl GraphLayout {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Lay out commits added to the end of `commits` since the last call
+    ///
+    /// `visible` says which commits get rows, in step with `commits`.
+    pub fn extend(&mut self, commits: &[Commit], visible: &[bool]) {
+        for (index, commit) in commits.iter().enumerate().skip(self.laid_out) {
+            if visible[index] && self.rows.is_multiple_of(CHECKPOINT_INTERVAL) {
+                self.checkpoints.push(Checkpoint {
+                    commit: index,
+                    row: self.rows,
+                    state: self.state.clone(),
+                });
+            }
+            if let Some(row) = self.state.place(&commit.hash, &commit.parents, visible[index]) {
+                self.rows += 1;
+                self.lane_count = self.lane_count.max(row.lane + 1);
+            }
+            self.lane_count = self.lane_count.max(self.state.width());
+        }
+        self.laid_out = commits.len();
+    }
+
+    /// Rows laid out so far
+    pub fn row_count(&self) -> usize {
+        self.rows
+    }
+
+    /// Widest the graph has been so far
+    pub fn lane_count(&self) -> usize {
+        self.lane_count.max(1)
+    }
+
+    /// Rows in `range`, replayed from the closest snapshot before it
+    pub fn rows(&self, commits: &[Commit], visible: &[bool], range: Range<usize>) -> Vec<GraphRow> {
+        let end = range.end.min(self.rows);
+        if range.start >= end {
+            return Vec::new();
+        }
+
+        let at = self.checkpoints.partition_point(|c| c.row <= range.start).saturating_sub(1);
+        let Some(checkpoint) = self.checkpoints.get(at) else {
+            return Vec::new();
+        };
+
+        let mut state = checkpoint.state.clone();
+        let mut row = checkpoint.row;
+        let mut rows = Vec::with_capacity(end - range.start);
+        for (index, commit) in commits.iter().enumerate().take(self.laid_out).skip(checkpoint.commit) {
+            if let Some(placed) = state.place(&commit.hash, &commit.parents, visible[index]) {
+                if row >= range.start {
+                    rows.push(placed);
+                }
+                row += 1;
+                if row >= end {
+                    break;
+                }
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Person;
+    use chrono::Utc;
+
+    fn commit(hash: &str, parents: &[&str]) -> Commit {
+        let person = Person { name: "Ann".into(), email: "ann@example.com".into() };
+        Commit {
+            hash: hash.into(),
+            short_hash: hash.into(),
+            parents: parents.iter().map(|p| p.to_string()).collect(),
+            author: person.clone(),
+            committer: person,
+            message: hash.into(),
+            summary: hash.into(),
+            timestamp: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_layout_lanes() {
+        // m is an octopus merge of a, b and c, which fork from root
+        let commits = vec![
+            commit("m", &["a", "b", "c"]),
+            commit("a", &["root"]),
+            commit("b", &["root"]),
+            commit("c", &["root"]),
+            commit("root", &[]),
+        ];
+        let visible = vec![true; commits.len()];
+        let mut layout = GraphLayout::new();
+        layout.extend(&commits, &visible);
+        assert_eq!(layout.row_count(), 5);
+        assert_eq!(layout.lane_count(), 3);
+
+        let rows = layout.rows(&commits, &visible, 0..5);
+        let lanes: Vec<_> = rows.iter().map(|r| r.lane).collect();
+        assert_eq!(lanes, vec![0, 0, 1, 2, 0]);
+        assert_eq!(rows[0].connections.iter().filter(|c| matches!(c.kind, ConnectionKind::Merge)).count(), 2);
+        // The side branches end in root, and the first-parent line keeps its colour
+        assert_eq!(rows[4].connections.iter().filter(|c| matches!(c.kind, ConnectionKind::Branch)).count(), 2);
+        assert_eq!(rows[4].color, rows[0].color);
+
+        // A window is the same as the full layout, wherever it starts
+        let window = layout.rows(&commits, &visible, 2..4);
+        assert_eq!(window.iter().map(|r| (r.lane, r.color)).collect::<Vec<_>>(),
+            rows[2..4].iter().map(|r| (r.lane, r.color)).collect::<Vec<_>>());
+
+        // Hidden commits keep passing their lane on
+        let visible = vec![true, false, true, true, true];
+        let mut layout = GraphLayout::new();
+        layout.extend(&commits, &visible);
+        let rows = layout.rows(&commits, &visible, 0..10);
+        assert_eq!(rows.iter().map(|r| r.commit_hash.as_str()).collect::<Vec<_>>(), vec!["m", "b", "c", "root"]);
+        assert_eq!(rows[3].lane, 0);
+    }
+}
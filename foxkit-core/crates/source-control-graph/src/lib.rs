@@ -1,19 +1,36 @@
 //! # Foxkit Source Control Graph
 //!
 //! Git commit graph visualization.
+//!
+//! Commits stream in from a revision walk as rows are asked for, and only
+//! the rows on screen are laid out, so the graph opens at once in
+//! repositories with hundreds of thousands of commits.
+
+pub mod layout;
+pub mod walk;
 
 use std::collections::HashMap;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
-use parking_lot::RwLock;
+use git::{BisectMark, BisectRange, BisectStep};
+use parking_lot::{Mutex, RwLock};
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
+pub use layout::{GraphLayout, LaneState, CHECKPOINT_INTERVAL};
+pub use walk::{CommitStream, WalkOptions, WalkedCommit};
+
 /// Source control graph service
 pub struct SourceControlGraphService {
     /// Current graph
     graph: RwLock<Option<CommitGraph>>,
+    /// Walk feeding the graph, when it comes from a repository
+    stream: Mutex<Option<CommitStream>>,
     /// Configuration
     config: RwLock<GraphConfig>,
+    /// Bisect in progress
+    bisect: RwLock<Option<BisectRange>>,
     /// Event sender
     event_tx: broadcast::Sender<GraphEvent>,
 }
@@ -24,7 +41,9 @@ impl SourceControlGraphService {
 
         Self {
             graph: RwLock::new(None),
+            stream: Mutex::new(None),
             config: RwLock::new(GraphConfig::default()),
+            bisect: RwLock::new(None),
             event_tx,
         }
     }
@@ -37,28 +56,118 @@ impl SourceControlGraphService {
     /// Load graph
     pub fn load_graph(&self, commits: Vec<Commit>, refs: Vec<GitRef>) {
         let graph = CommitGraph::build(commits, refs);
+        *self.stream.lock() = None;
         *self.graph.write() = Some(graph.clone());
         let _ = self.event_tx.send(GraphEvent::Loaded(graph));
     }
 
+    /// Load the graph of a repository, walking history as rows are needed
+    ///
+    /// The walk starts from the commits `refs` point at, or HEAD without
+    /// refs, and stops at `max_commits` from the config if one is set.
+    pub fn open_repository(&self, repo_path: &Path, refs: Vec<GitRef>) -> anyhow::Result<()> {
+        self.start_walk(CommitGraph::streamed(repo_path, refs, GraphFilter::default()))?;
+        let graph = self.graph.read().clone();
+        if let Some(graph) = graph {
+            let _ = self.event_tx.send(GraphEvent::Loaded(graph));
+        }
+        Ok(())
+    }
+
+    /// Only show commits matching `filter`
+    ///
+    /// Hidden commits still connect the lanes of the ones around them.
+    /// Graphs from a repository are walked again, so date and path
+    /// filters only read the history they need.
+    pub fn set_filter(&self, filter: GraphFilter) -> anyhow::Result<()> {
+        let Some(graph) = self.graph.read().clone() else {
+            return Ok(());
+        };
+
+        match &graph.repo_path {
+            Some(repo_path) => {
+                self.start_walk(CommitGraph::streamed(repo_path, graph.refs.clone(), filter.clone()))?;
+            }
+            None => {
+                let mut filtered = CommitGraph::new(graph.refs.clone(), filter.clone());
+                filtered.push(graph.commits.into_iter().map(|commit| WalkedCommit { commit, touches_path: true }));
+                *self.graph.write() = Some(filtered);
+            }
+        }
+        let _ = self.event_tx.send(GraphEvent::FilterChanged(filter));
+        Ok(())
+    }
+
+    /// Restart the walk for an empty streamed graph and read its first page
+    fn start_walk(&self, graph: CommitGraph) -> anyhow::Result<()> {
+        let options = WalkOptions {
+            tips: graph.refs.iter().map(|r| r.commit.clone()).collect(),
+            since: graph.filter.since,
+            path: graph.filter.path.clone(),
+            limit: self.config.read().max_commits,
+        };
+        let repo_path = graph.repo_path.clone().unwrap_or_default();
+        *self.stream.lock() = Some(CommitStream::open(&repo_path, options));
+        *self.graph.write() = Some(graph);
+        self.load_rows(1)
+    }
+
+    /// Read commits from the walk until at least `rows` rows are laid out
+    /// or history runs out
+    fn load_rows(&self, rows: usize) -> anyhow::Result<()> {
+        let mut stream = self.stream.lock();
+        let Some(source) = stream.as_mut() else {
+            return Ok(());
+        };
+
+        let mut loaded = false;
+        while self.graph.read().as_ref().is_some_and(|g| g.row_count() < rows) {
+            match source.next_batch() {
+                Some(Ok(batch)) => {
+                    if let Some(graph) = self.graph.write().as_mut() {
+                        graph.push(batch);
+                    }
+                    loaded = true;
+                }
+                Some(Err(e)) => return Err(e),
+                None => break,
+            }
+        }
+
+        if loaded {
+            let total = self.graph.read().as_ref().map_or(0, |g| g.commits.len());
+            let _ = self.event_tx.send(GraphEvent::CommitsLoaded { total, complete: source.is_done() });
+        }
+        Ok(())
+    }
+
     /// Get graph
     pub fn graph(&self) -> Option<CommitGraph> {
         self.graph.read().clone()
     }
 
-    /// Get visible commits (paginated)
+    /// Get visible commits (paginated), walking further as needed
     pub fn visible_commits(&self, offset: usize, limit: usize) -> Vec<GraphRow> {
+        if let Err(e) = self.load_rows(offset + limit) {
+            tracing::warn!("Failed to walk commit history: {}", e);
+        }
         self.graph.read()
             .as_ref()
-            .map(|g| g.rows.iter().skip(offset).take(limit).cloned().collect())
+            .map(|g| g.rows(offset..offset + limit))
             .unwrap_or_default()
     }
 
+    /// Whether the walk has finished: all of history has been read, or
+    /// `max_commits` commits when the config sets a limit
+    pub fn is_fully_loaded(&self) -> bool {
+        self.stream.lock().as_ref().is_none_or(CommitStream::is_done)
+    }
+
     /// Get commit by hash
     pub fn get_commit(&self, hash: &str) -> Option<Commit> {
         self.graph.read()
             .as_ref()
-            .and_then(|g| g.commits.get(hash).cloned())
+            .and_then(|g| g.get(hash).cloned())
     }
 
     /// Get refs for commit
@@ -76,7 +185,7 @@ impl SourceControlGraphService {
         self.graph.read()
             .as_ref()
             .map(|g| {
-                g.commits.values()
+                g.commits.iter()
                     .filter(|c| {
                         c.hash.starts_with(&query) ||
                         c.message.to_lowercase().contains(&query) ||
@@ -96,7 +205,7 @@ impl SourceControlGraphService {
             .map(|g| {
                 let branch_ref = g.refs.iter().find(|r| r.name == branch);
                 if let Some(r) = branch_ref {
-                    g.commits.get(&r.commit).cloned().into_iter().collect()
+                    g.get(&r.commit).cloned().into_iter().collect()
                 } else {
                     Vec::new()
                 }
@@ -104,6 +213,52 @@ impl SourceControlGraphService {
             .unwrap_or_default()
     }
 
+    /// Show the state of a bisect, or stop showing it with `None`
+    pub fn set_bisect(&self, range: Option<BisectRange>) {
+        *self.bisect.write() = range.clone();
+        let _ = self.event_tx.send(GraphEvent::BisectChanged(range));
+    }
+
+    /// Bisect in progress
+    pub fn bisect(&self) -> Option<BisectRange> {
+        self.bisect.read().clone()
+    }
+
+    /// Role of a commit in the bisect in progress
+    pub fn bisect_mark(&self, hash: &str) -> Option<BisectMark> {
+        self.bisect.read().as_ref()?.mark(hash)
+    }
+
+    /// Rows of the commits still in play in the bisect: the candidates for
+    /// the first bad commit, skipped or not, plus the bad commit itself
+    pub fn bisect_rows(&self) -> Vec<GraphRow> {
+        let bisect = self.bisect.read();
+        let Some(range) = bisect.as_ref() else {
+            return Vec::new();
+        };
+
+        self.graph.read()
+            .as_ref()
+            .map(|g| {
+                g.rows(0..g.row_count())
+                    .into_iter()
+                    .filter(|row| matches!(
+                        range.mark(&row.commit_hash),
+                        Some(BisectMark::Bad | BisectMark::Current | BisectMark::Remaining | BisectMark::Skipped)
+                    ))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Follow a bisect step, opening the first bad commit in the commit
+    /// details view once it's found
+    pub fn bisect_step(&self, step: &BisectStep) {
+        if let BisectStep::FirstBad { commit } = step {
+            let _ = self.event_tx.send(GraphEvent::ShowCommitDetails(commit.clone()));
+        }
+    }
+
     /// Configure
     pub fn configure(&self, config: GraphConfig) {
         *self.config.write() = config;
@@ -116,6 +271,7 @@ impl SourceControlGraphService {
 
     /// Clear graph
     pub fn clear(&self) {
+        *self.stream.lock() = None;
         *self.graph.write() = None;
         let _ = self.event_tx.send(GraphEvent::Cleared);
     }
@@ -130,97 +286,109 @@ impl Default for SourceControlGraphService {
 /// Commit graph
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommitGraph {
-    /// Commits by hash
-    pub commits: HashMap<String, Commit>,
+    /// Commits loaded so far, newest first
+    pub commits: Vec<Commit>,
     /// Refs (branches, tags)
     pub refs: Vec<GitRef>,
-    /// Graph rows for rendering
-    pub rows: Vec<GraphRow>,
-    /// Lane count
-    pub lane_count: usize,
+    /// Which commits get rows
+    pub filter: GraphFilter,
+    /// Repository the commits are walked from, if streamed
+    pub repo_path: Option<PathBuf>,
+    /// Position of each commit in `commits`
+    index: HashMap<String, usize>,
+    /// Whether each commit passes the filter
+    visible: Vec<bool>,
+    layout: GraphLayout,
 }
 
 impl CommitGraph {
     pub fn build(commits: Vec<Commit>, refs: Vec<GitRef>) -> Self {
-        let mut commit_map = HashMap::new();
-        for commit in &commits {
-            commit_map.insert(commit.hash.clone(), commit.clone());
-        }
-
-        // Build graph rows
-        let rows = Self::layout_graph(&commits);
-        let lane_count = rows.iter().map(|r| r.lane + 1).max().unwrap_or(1);
+        let mut graph = Self::new(refs, GraphFilter::default());
+        graph.push(commits.into_iter().map(|commit| WalkedCommit { commit, touches_path: true }));
+        graph
+    }
 
+    fn new(refs: Vec<GitRef>, filter: GraphFilter) -> Self {
         Self {
-            commits: commit_map,
+            commits: Vec::new(),
             refs,
-            rows,
-            lane_count,
+            filter,
+            repo_path: None,
+            index: HashMap::new(),
+            visible: Vec::new(),
+            layout: GraphLayout::new(),
         }
     }
 
-    fn layout_graph(commits: &[Commit]) -> Vec<GraphRow> {
-        let mut rows = Vec::new();
-        let mut active_lanes: Vec<Option<String>> = Vec::new();
-
-        for commit in commits {
-            // Find or create lane for this commit
-            let lane = active_lanes.iter()
-                .position(|l| l.as_ref() == Some(&commit.hash))
-                .unwrap_or_else(|| {
-                    // Find first empty lane or add new one
-                    active_lanes.iter()
-                        .position(|l| l.is_none())
-                        .unwrap_or_else(|| {
-                            active_lanes.push(None);
-                            active_lanes.len() - 1
-                        })
-                });
-
-            // Build connections
-            let mut connections = Vec::new();
-
-            // Connect to parents
-            for (i, parent) in commit.parents.iter().enumerate() {
-                let parent_lane = if i == 0 {
-                    // First parent continues in same lane
-                    lane
-                } else {
-                    // Other parents get new lanes
-                    active_lanes.iter()
-                        .position(|l| l.is_none())
-                        .unwrap_or_else(|| {
-                            active_lanes.push(None);
-                            active_lanes.len() - 1
-                        })
-                };
-
-                connections.push(GraphConnection {
-                    from_lane: lane,
-                    to_lane: parent_lane,
-                    kind: if i == 0 { ConnectionKind::Direct } else { ConnectionKind::Merge },
-                });
-
-                // Mark parent lane as active
-                if parent_lane < active_lanes.len() {
-                    active_lanes[parent_lane] = Some(parent.clone());
-                }
-            }
-
-            // Clear current lane if no parents
-            if commit.parents.is_empty() {
-                active_lanes[lane] = None;
-            }
+    fn streamed(repo_path: &Path, refs: Vec<GitRef>, filter: GraphFilter) -> Self {
+        Self {
+            repo_path: Some(repo_path.to_path_buf()),
+            ..Self::new(refs, filter)
+        }
+    }
 
-            rows.push(GraphRow {
-                commit_hash: commit.hash.clone(),
-                lane,
-                connections,
-                active_lanes: active_lanes.iter().filter(|l| l.is_some()).count(),
-            });
+    /// Add commits to the end of the graph and lay them out
+    pub fn push(&mut self, commits: impl IntoIterator<Item = WalkedCommit>) {
+        for walked in commits {
+            self.visible.push(self.filter.matches(&walked.commit, walked.touches_path));
+            self.index.insert(walked.commit.hash.clone(), self.commits.len());
+            self.commits.push(walked.commit);
         }
+        self.layout.extend(&self.commits, &self.visible);
+    }
+
+    /// Commit by hash
+    pub fn get(&self, hash: &str) -> Option<&Commit> {
+        self.index.get(hash).map(|&i| &self.commits[i])
+    }
+
+    /// Rows laid out so far
+    pub fn row_count(&self) -> usize {
+        self.layout.row_count()
+    }
+
+    /// Lane count
+    pub fn lane_count(&self) -> usize {
+        self.layout.lane_count()
+    }
+
+    /// Graph rows for rendering
+    pub fn rows(&self, range: Range<usize>) -> Vec<GraphRow> {
+        self.layout.rows(&self.commits, &self.visible, range)
+    }
+}
+
+/// Which commits the graph shows
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GraphFilter {
+    /// Author name or email containing this, ignoring case
+    pub author: Option<String>,
+    /// Commits changing this path, relative to the working tree
+    pub path: Option<PathBuf>,
+    /// Commits from this time on
+    pub since: Option<DateTime<Utc>>,
+    /// Commits up to this time
+    pub until: Option<DateTime<Utc>>,
+}
 
-        rows
+impl GraphFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Whether a commit passes, given whether it changes the filtered path
+    pub fn matches(&self, commit: &Commit, touches_path: bool) -> bool {
+        if let Some(author) = &self.author {
+            let author = author.to_lowercase();
+            if !commit.author.name.to_lowercase().contains(&author)
+                && !commit.author.email.to_lowercase().contains(&author)
+            {
+                return false;
+            }
+        }
+        (self.path.is_none() || touches_path)
+            && self.since.is_none_or(|since| commit.timestamp >= since)
+            && self.until.is_none_or(|until| commit.timestamp <= until)
     }
 }
 
@@ -231,7 +399,10 @@ pub struct GraphRow {
     pub commit_hash: String,
     /// Lane index
     pub lane: usize,
-    /// Connections to next row
+    /// Colour of the commit's lane, see [`lane_color`]
+    pub color: usize,
+    /// Connections to next row, and for [`ConnectionKind::Branch`], from
+    /// lanes of other children ending in this commit
     pub connections: Vec<GraphConnection>,
     /// Number of active lanes
     pub active_lanes: usize,
@@ -243,6 +414,8 @@ pub struct GraphConnection {
     pub from_lane: usize,
     pub to_lane: usize,
     pub kind: ConnectionKind,
+    /// Colour of the lane the connection belongs to
+    pub color: usize,
 }
 
 /// Connection kind
@@ -361,8 +534,9 @@ pub struct GraphConfig {
     pub show_stashes: bool,
     /// Color scheme
     pub color_scheme: ColorScheme,
-    /// Max commits to load
-    pub max_commits: usize,
+    /// Max commits to load; history is read as rows are needed, so
+    /// there is no limit by default
+    pub max_commits: Option<usize>,
     /// Show author avatars
     pub show_avatars: bool,
     /// Date format
@@ -376,7 +550,7 @@ impl Default for GraphConfig {
             show_tags: true,
             show_stashes: false,
             color_scheme: ColorScheme::Branch,
-            max_commits: 1000,
+            max_commits: None,
             show_avatars: true,
             date_format: DateFormat::Relative,
         }
@@ -407,7 +581,15 @@ pub enum DateFormat {
 #[derive(Debug, Clone)]
 pub enum GraphEvent {
     Loaded(CommitGraph),
+    /// More commits were read from the walk
+    CommitsLoaded { total: usize, complete: bool },
+    /// The graph was filtered again
+    FilterChanged(GraphFilter),
     Cleared,
+    /// Bisect started, narrowed or ended
+    BisectChanged(Option<BisectRange>),
+    /// Open a commit in the commit details view
+    ShowCommitDetails(String),
 }
 
 /// Lane colors
@@ -422,6 +604,7 @@ pub const LANE_COLORS: &[&str] = &[
     "#c586c0", // magenta
 ];
 
-pub fn lane_color(lane: usize) -> &'static str {
-    LANE_COLORS[lane % LANE_COLORS.len()]
+/// Colour for a lane colour index
+pub fn lane_color(color: usize) -> &'static str {
+    LANE_COLORS[color % LANE_COLORS.len()]
 }
@@ -0,0 +1,311 @@
+//! Streaming commit walk
+//!
+//! Walks history with gix on a background thread, newest first, and hands
+//! commits over in batches as the graph asks for them. The channel holds one
+//! batch, so the walk stays just ahead of what's been shown rather than
+//! reading the whole history of a large repository up front.
+//!
+//! Lanes need children before parents, which commit dates don't guarantee,
+//! so the ids and parents of the walk are ordered topologically first. Only
+//! that pass reads all of history; commit details and path changes are
+//! still read a batch at a time.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+
+use chrono::{DateTime, Utc};
+use gix::traverse::commit::Sorting;
+
+use crate::{Commit, Person};
+
+/// Commits per batch
+pub const BATCH_SIZE: usize = 512;
+
+/// What to walk
+#[derive(Debug, Clone, Default)]
+pub struct WalkOptions {
+    /// Commits to start from, HEAD when empty
+    pub tips: Vec<String>,
+    /// Stop at commits older than this
+    pub since: Option<DateTime<Utc>>,
+    /// Work out which commits change this path (relative to the working tree)
+    pub path: Option<PathBuf>,
+    /// Stop after this many commits
+    pub limit: Option<usize>,
+}
+
+/// A commit from the walk
+#[derive(Debug, Clone)]
+pub struct WalkedCommit {
+    pub commit: Commit,
+    /// Whether the commit changes [`WalkOptions::path`], always `true`
+    /// without a path
+    pub touches_path: bool,
+}
+
+/// Commits arriving from a walk in progress
+pub struct CommitStream {
+    rx: Receiver<anyhow::Result<Vec<WalkedCommit>>>,
+    done: bool,
+}
+
+impl CommitStream {
+    /// Start walking the repository at `repo_path`
+    pub fn open(repo_path: &Path, options: WalkOptions) -> Self {
+        let (tx, rx) = mpsc::sync_channel(1);
+        let repo_path = repo_path.to_path_buf();
+        std::thread::spawn(move || {
+            if let Err(e) = walk(&repo_path, &options, &tx) {
+                let _ = tx.send(Err(e));
+            }
+        });
+        Self { rx, done: false }
+    }
+
+    /// Next batch of commits, waiting for the walk if needed, or `None` once
+    /// it's finished
+    pub fn next_batch(&mut self) -> Option<anyhow::Result<Vec<WalkedCommit>>> {
+        if self.done {
+            return None;
+        }
+        match self.rx.recv() {
+            Ok(Ok(batch)) if !batch.is_empty() => Some(Ok(batch)),
+            Ok(Err(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+            Ok(Ok(_)) | Err(_) => {
+                self.done = true;
+                None
+            }
+        }
+    }
+
+    /// Whether the walk has finished
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+fn walk(
+    repo_path: &Path,
+    options: &WalkOptions,
+    tx: &SyncSender<anyhow::Result<Vec<WalkedCommit>>>,
+) -> anyhow::Result<()> {
+    let mut repo = gix::open(repo_path)?;
+    repo.object_cache_size_if_unset(16 * 1024 * 1024);
+
+    let tips = if options.tips.is_empty() {
+        vec![repo.head_id()?.detach()]
+    } else {
+        options
+            .tips
+            .iter()
+            .map(|tip| gix::ObjectId::from_hex(tip.as_bytes()))
+            .collect::<Result<_, _>>()?
+    };
+    let sorting = match options.since {
+        Some(since) => Sorting::ByCommitTimeNewestFirstCutoffOlderThan { seconds: since.timestamp() },
+        None => Sorting::ByCommitTimeNewestFirst,
+    };
+
+    let mut batch = Vec::with_capacity(BATCH_SIZE);
+    let mut buf = Vec::new();
+    let walked = repo.rev_walk(tips).sorting(sorting).all()?.collect::<Result<Vec<_>, _>>()?;
+    for (count, info) in topo_order(walked).into_iter().enumerate() {
+        if options.limit.is_some_and(|limit| count >= limit) {
+            break;
+        }
+
+        let commit = info.object()?;
+        let touches_path = match &options.path {
+            Some(path) => {
+                let entry = |commit: &gix::Commit<'_>, buf: &mut Vec<u8>| -> anyhow::Result<_> {
+                    Ok(commit.tree()?.lookup_entry_by_path(path, buf)?.map(|e| e.object_id()))
+                };
+                let own = entry(&commit, &mut buf)?;
+                let mut parents = info.parent_ids().peekable();
+                if parents.peek().is_none() {
+                    own.is_some()
+                } else {
+                    // Like `git log -- <path>`, merges only count when they
+                    // differ from every parent
+                    let mut touches = true;
+                    for parent in parents {
+                        let parent = parent.object()?.try_into_commit()?;
+                        touches &= entry(&parent, &mut buf)? != own;
+                    }
+                    touches
+                }
+            }
+            None => true,
+        };
+
+        let author = commit.author()?;
+        let committer = commit.committer()?;
+        let message = commit.message_raw()?.to_string();
+        let hash = info.id.to_string();
+        batch.push(WalkedCommit {
+            commit: Commit {
+                short_hash: hash[..7].to_string(),
+                hash,
+                parents: info.parent_ids.iter().map(|p| p.to_string()).collect(),
+                author: Person {
+                    name: author.name.to_string(),
+                    email: author.email.to_string(),
+                },
+                committer: Person {
+                    name: committer.name.to_string(),
+                    email: committer.email.to_string(),
+                },
+                summary: message.lines().next().unwrap_or_default().to_string(),
+                message,
+                timestamp: DateTime::from_timestamp(committer.time.seconds, 0).unwrap_or_default(),
+            },
+            touches_path,
+        });
+
+        if batch.len() == BATCH_SIZE
+            && tx.send(Ok(std::mem::replace(&mut batch, Vec::with_capacity(BATCH_SIZE)))).is_err()
+        {
+            // Nobody is listening any more
+            return Ok(());
+        }
+    }
+
+    if !batch.is_empty() {
+        let _ = tx.send(Ok(batch));
+    }
+    Ok(())
+}
+
+/// Order walked commits so each comes after all of its children, newest
+/// ready commit first like `git log --date-order`. A date walk alone puts
+/// a commit dated before its parent (clock skew, rebases) after the parent.
+fn topo_order(walked: Vec<gix::revision::walk::Info<'_>>) -> Vec<gix::revision::walk::Info<'_>> {
+    let index: HashMap<gix::ObjectId, usize> = walked.iter().enumerate().map(|(i, info)| (info.id, i)).collect();
+    let parents = |i: usize| walked[i].parent_ids.iter().filter_map(|id| index.get(id).copied()).collect::<Vec<_>>();
+
+    let mut children = vec![0usize; walked.len()];
+    for i in 0..walked.len() {
+        for parent in parents(i) {
+            children[parent] += 1;
+        }
+    }
+
+    let time = |i: usize| walked[i].commit_time.unwrap_or_default();
+    let mut ready: BinaryHeap<_> = (0..walked.len())
+        .filter(|&i| children[i] == 0)
+        .map(|i| (time(i), Reverse(i)))
+        .collect();
+    let mut order = Vec::with_capacity(walked.len());
+    while let Some((_, Reverse(i))) = ready.pop() {
+        order.push(i);
+        for parent in parents(i) {
+            children[parent] -= 1;
+            if children[parent] == 0 {
+                ready.push((time(parent), Reverse(parent)));
+            }
+        }
+    }
+
+    let mut walked: Vec<_> = walked.into_iter().map(Some).collect();
+    order.into_iter().filter_map(|i| walked[i].take()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{GraphFilter, SourceControlGraphService};
+    use git::test_support::{git_with_env, test_repo};
+
+    #[test]
+    fn test_walk_repository() {
+        let dir = test_repo("graph-walk");
+        let git = |author: &str, date: &str, args: &[&str]| {
+            let env = [
+                ("GIT_AUTHOR_NAME", author),
+                ("GIT_COMMITTER_NAME", author),
+                ("GIT_AUTHOR_DATE", date),
+                ("GIT_COMMITTER_DATE", date),
+            ];
+            git_with_env(&dir, &env, args);
+        };
+
+        git("Ann", "2024-01-01T00:00:00Z", &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        for (n, (author, file)) in [("Ann", "a.txt"), ("Bob", "b.txt"), ("Ann", "a.txt")].into_iter().enumerate() {
+            let date = format!("2024-01-0{}T00:00:00Z", n + 1);
+            std::fs::write(dir.join(file), n.to_string()).unwrap();
+            git(author, &date, &["add", file]);
+            git(author, &date, &["commit", "-q", "-m", &format!("Commit {}", n)]);
+        }
+        git("Bob", "2024-01-04T00:00:00Z", &["checkout", "-q", "-b", "topic", "HEAD~1"]);
+        std::fs::write(dir.join("b.txt"), "topic").unwrap();
+        git("Bob", "2024-01-04T00:00:00Z", &["commit", "-q", "-am", "Topic"]);
+        git("Ann", "2024-01-05T00:00:00Z", &["checkout", "-q", "main"]);
+        git("Ann", "2024-01-05T00:00:00Z", &["merge", "-q", "--no-ff", "-m", "Merge topic", "topic"]);
+
+        let mut stream = CommitStream::open(&dir, WalkOptions {
+            path: Some(PathBuf::from("b.txt")),
+            ..Default::default()
+        });
+        let commits = stream.next_batch().unwrap().unwrap();
+        assert!(stream.next_batch().is_none());
+        let touching: Vec<_> = commits.iter().filter(|c| c.touches_path).map(|c| c.commit.summary.as_str()).collect();
+        assert_eq!(touching, vec!["Topic", "Commit 1"]);
+
+        let service = SourceControlGraphService::new();
+        service.open_repository(&dir, Vec::new()).unwrap();
+        let rows = service.visible_commits(0, 10);
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows.iter().map(|r| r.lane).max(), Some(1));
+        assert!(service.is_fully_loaded());
+
+        service.set_filter(GraphFilter {
+            author: Some("bob".into()),
+            since: DateTime::from_timestamp(1704153600, 0),
+            ..Default::default()
+        }).unwrap();
+        let summaries: Vec<_> = service.visible_commits(0, 10)
+            .iter()
+            .filter_map(|r| service.get_commit(&r.commit_hash))
+            .map(|c| c.summary)
+            .collect();
+        assert_eq!(summaries, vec!["Topic", "Commit 1"]);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_walk_children_before_parents() {
+        let dir = test_repo("graph-topo");
+        let git = |date: &str, args: &[&str]| {
+            git_with_env(&dir, &[("GIT_AUTHOR_DATE", date), ("GIT_COMMITTER_DATE", date)], args);
+        };
+
+        git("2024-01-03T00:00:00Z", &["symbolic-ref", "HEAD", "refs/heads/main"]);
+        git("2024-01-03T00:00:00Z", &["commit", "-q", "--allow-empty", "-m", "Base"]);
+        git("2024-01-03T00:00:00Z", &["checkout", "-q", "-b", "skewed"]);
+        // Dated before its parent
+        git("2024-01-01T00:00:00Z", &["commit", "-q", "--allow-empty", "-m", "Skewed"]);
+        git("2024-01-05T00:00:00Z", &["checkout", "-q", "main"]);
+        git("2024-01-05T00:00:00Z", &["commit", "-q", "--allow-empty", "-m", "Main"]);
+        git("2024-01-06T00:00:00Z", &["merge", "-q", "--no-ff", "-m", "Merge skewed", "skewed"]);
+
+        let mut stream = CommitStream::open(&dir, WalkOptions::default());
+        let commits = stream.next_batch().unwrap().unwrap();
+        let summaries: Vec<_> = commits.iter().map(|c| c.commit.summary.as_str()).collect();
+        assert_eq!(summaries, vec!["Merge skewed", "Main", "Skewed", "Base"]);
+
+        let service = SourceControlGraphService::new();
+        service.open_repository(&dir, Vec::new()).unwrap();
+        let rows = service.visible_commits(0, 10);
+        assert_eq!(rows.len(), 4);
+        assert_eq!(rows[3].lane, 0);
+        assert_eq!(rows.iter().map(|r| r.lane).max(), Some(1));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
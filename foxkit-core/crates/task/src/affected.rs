@@ -0,0 +1,399 @@
+//! Affected-only task runs
+//!
+//! Runs a task for just the monorepo packages touched by the current change
+//! set. Files changed since the merge base with a base ref, plus untracked
+//! files, are mapped to the packages that own them; those packages and
+//! everything that depends on them are affected. The affected packages get
+//! one task each, ordered by their internal dependencies and run stage by
+//! stage on a [`TaskScheduler`].
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use monorepo::{DependencyGraph, Package, PackageKind, PackageManager};
+
+use crate::providers::NpmProvider;
+use crate::scheduler::{NodeState, TaskGraph, TaskScheduler};
+use crate::{Task, TaskId};
+
+/// Task to run for each affected package
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AffectedTarget {
+    Build,
+    Test,
+    Lint,
+}
+
+impl AffectedTarget {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AffectedTarget::Build => "build",
+            AffectedTarget::Test => "test",
+            AffectedTarget::Lint => "lint",
+        }
+    }
+}
+
+impl FromStr for AffectedTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s {
+            "build" => Ok(AffectedTarget::Build),
+            "test" => Ok(AffectedTarget::Test),
+            "lint" => Ok(AffectedTarget::Lint),
+            _ => anyhow::bail!("Unknown target: {} (expected build, test or lint)", s),
+        }
+    }
+}
+
+impl std::fmt::Display for AffectedTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Packages affected by a change set
+#[derive(Debug, Clone, Default)]
+pub struct AffectedPackages {
+    /// Changed files, relative to the repository root
+    pub changed_files: Vec<PathBuf>,
+    /// Packages owning a changed file
+    pub changed: Vec<String>,
+    /// Packages depending on a changed package, directly or transitively
+    pub dependents: Vec<String>,
+}
+
+impl AffectedPackages {
+    /// Map changed files to their packages and add the packages' dependents
+    ///
+    /// `owners` pairs each changed file with the package that owns it, if
+    /// any. A change owned by a workspace root (lockfiles, shared config)
+    /// affects every package.
+    pub fn resolve(
+        changed_files: Vec<PathBuf>,
+        owners: &[Option<Package>],
+        packages: &[Package],
+        graph: &DependencyGraph,
+    ) -> anyhow::Result<Self> {
+        let known: HashSet<&str> = packages.iter().map(|p| p.name.as_str()).collect();
+        let mut changed = BTreeSet::new();
+
+        for owner in owners.iter().flatten() {
+            if !known.contains(owner.name.as_str()) {
+                continue;
+            }
+            if owner.kind == PackageKind::WorkspaceRoot {
+                changed.extend(packages.iter()
+                    .filter(|p| !p.is_workspace_root())
+                    .map(|p| p.name.clone()));
+            } else {
+                changed.insert(owner.name.clone());
+            }
+        }
+
+        let changed: Vec<String> = changed.into_iter().collect();
+        let mut dependents: Vec<String> = graph.dependents(&changed)?
+            .into_iter()
+            .filter(|name| !changed.contains(name))
+            .collect();
+        dependents.sort();
+
+        Ok(Self { changed_files, changed, dependents })
+    }
+
+    /// All affected package names
+    pub fn all(&self) -> impl Iterator<Item = &str> {
+        self.changed.iter().chain(&self.dependents).map(String::as_str)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changed.is_empty() && self.dependents.is_empty()
+    }
+}
+
+/// Find the packages under `root` affected by changes against `base`
+pub async fn affected_packages(root: &Path, base: &str) -> anyhow::Result<(AffectedPackages, Vec<Package>)> {
+    let workdir = git::Repository::discover_workdir(root)?;
+
+    let mut changed_files: BTreeSet<PathBuf> = git::diff::changed_files(&workdir, base).await?
+        .into_iter()
+        .collect();
+    changed_files.extend(git::status::status(&workdir).await?
+        .untracked()
+        .map(|entry| entry.path.clone()));
+    let changed_files: Vec<PathBuf> = changed_files.into_iter().collect();
+
+    let mut owners = Vec::with_capacity(changed_files.len());
+    for file in &changed_files {
+        owners.push(monorepo::find_package_for_path(&workdir.join(file)).await?);
+    }
+
+    let packages = monorepo::detector::detect_packages(root).await?;
+    let graph = DependencyGraph::build(&packages)?;
+    let affected = AffectedPackages::resolve(changed_files, &owners, &packages, &graph)?;
+    Ok((affected, packages))
+}
+
+/// Name of the task running `target` for a package
+pub fn task_name(target: AffectedTarget, package: &str) -> String {
+    format!("{}: {}", target, package)
+}
+
+/// The task running `target` in a package, or `None` when the package's
+/// tooling has no such step, e.g. a `package.json` without the script
+pub fn package_task(package: &Package, target: AffectedTarget) -> Option<Task> {
+    use AffectedTarget::*;
+
+    let name = task_name(target, &package.name);
+    let args = |args: &[&str]| args.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    let task = if let Some(json) = package_json(package) {
+        // Workspace members only name their package manager through the
+        // lockfile at the root, so look it up like the npm task provider
+        json.get("scripts")?.get(target.as_str())?;
+        let pm = NpmProvider::package_manager(&package.path, &json);
+        Task::process(&name, pm, args(&["run", target.as_str()]))
+    } else {
+        match (package.package_manager.as_ref()?, target) {
+            (PackageManager::Cargo, Build) => Task::process(&name, "cargo", args(&["build", "-p", &package.name])),
+            (PackageManager::Cargo, Test) => Task::process(&name, "cargo", args(&["test", "-p", &package.name])),
+            (PackageManager::Cargo, Lint) => Task::process(&name, "cargo", args(&["clippy", "-p", &package.name])),
+            (PackageManager::Go, Build) => Task::process(&name, "go", args(&["build", "./..."])),
+            (PackageManager::Go, Test) => Task::process(&name, "go", args(&["test", "./..."])),
+            (PackageManager::Go, Lint) => Task::process(&name, "go", args(&["vet", "./..."])),
+            (PackageManager::Maven, Build) => Task::process(&name, "mvn", args(&["compile"])),
+            (PackageManager::Maven, Test) => Task::process(&name, "mvn", args(&["test"])),
+            (PackageManager::Maven, Lint) => Task::process(&name, "mvn", args(&["verify", "-DskipTests"])),
+            (PackageManager::Gradle, Build) => Task::process(&name, "gradle", args(&["assemble"])),
+            (PackageManager::Gradle, Test) => Task::process(&name, "gradle", args(&["test"])),
+            (PackageManager::Gradle, Lint) => Task::process(&name, "gradle", args(&["check", "-x", "test"])),
+            (PackageManager::Pip | PackageManager::Poetry | PackageManager::Uv, Test) => {
+                Task::process(&name, "python", args(&["-m", "pytest"]))
+            }
+            (PackageManager::Pip | PackageManager::Poetry | PackageManager::Uv, Lint) => {
+                Task::process(&name, "ruff", args(&["check", "."]))
+            }
+            _ => return None,
+        }
+    };
+
+    let task = match target {
+        Build => task.as_build(),
+        Test => task.as_test(),
+        Lint => task,
+    };
+    Some(task.in_dir(package.path.clone()))
+}
+
+/// The package's `package.json`, if it has one
+fn package_json(package: &Package) -> Option<serde_json::Value> {
+    let text = std::fs::read_to_string(package.path.join("package.json")).ok()?;
+    serde_json::from_str(&text).ok()
+}
+
+/// Tasks for the affected packages, each depending on the tasks of the
+/// affected packages it depends on
+pub fn affected_task_graph(
+    affected: &AffectedPackages,
+    packages: &[Package],
+    target: AffectedTarget,
+) -> TaskGraph {
+    let affected: HashSet<&str> = affected.all().collect();
+    let tasks: Vec<Task> = packages.iter()
+        .filter(|p| affected.contains(p.name.as_str()))
+        .filter_map(|p| package_task(p, target).map(|task| (p, task)))
+        .map(|(package, mut task)| {
+            task.depends_on = package.all_dependencies()
+                .into_iter()
+                .filter(|dep| affected.contains(dep))
+                .map(|dep| task_name(target, dep))
+                .collect();
+            task
+        })
+        .collect();
+
+    // Drop edges to packages whose tooling has no task for this target
+    let names: HashSet<String> = tasks.iter().map(|t| t.name.clone()).collect();
+    let mut graph = TaskGraph::new();
+    for mut task in tasks {
+        task.depends_on.retain(|dep| names.contains(dep));
+        graph.add(task);
+    }
+    graph
+}
+
+/// Outcome of an affected-only run
+#[derive(Debug, Clone, Default)]
+pub struct AffectedRun {
+    pub affected: AffectedPackages,
+    /// Tasks in the order their stages ran
+    pub stages: Vec<Vec<String>>,
+    pub succeeded: Vec<String>,
+    pub failed: Vec<String>,
+    /// Tasks cancelled or never started because a task failed
+    pub skipped: Vec<String>,
+}
+
+impl AffectedRun {
+    pub fn success(&self) -> bool {
+        self.failed.is_empty() && self.skipped.is_empty()
+    }
+}
+
+/// Run `target` for every package under `root` affected by changes against
+/// `base`
+///
+/// Stages from [`TaskGraph::parallel_stages`] run one after another on
+/// `scheduler`; tasks within a stage run in parallel. A failing stage stops
+/// the run and the remaining tasks are reported as skipped, along with any
+/// task of the stage cancelled by a fail-fast policy.
+pub async fn run_affected(
+    scheduler: &TaskScheduler,
+    root: &Path,
+    base: &str,
+    target: AffectedTarget,
+) -> anyhow::Result<AffectedRun> {
+    let (affected, packages) = affected_packages(root, base).await?;
+    let graph = affected_task_graph(&affected, &packages, target);
+    let mut stages = graph.parallel_stages();
+    for stage in &mut stages {
+        stage.sort();
+    }
+
+    let mut run = AffectedRun { affected, ..Default::default() };
+
+    for (i, stage) in stages.iter().enumerate() {
+        let queued: HashSet<TaskId> = stage.iter()
+            .filter_map(|name| graph.get(name))
+            .map(|task| scheduler.queue(task.clone(), 0))
+            .collect();
+        scheduler.run().await;
+
+        for node in scheduler.graph().into_iter().filter(|node| queued.contains(&node.id)) {
+            match node.state {
+                NodeState::Succeeded | NodeState::Ready => run.succeeded.push(node.name),
+                NodeState::Failed => run.failed.push(node.name),
+                _ => run.skipped.push(node.name),
+            }
+        }
+
+        if !run.success() {
+            run.skipped.extend(stages[i + 1..].iter().flatten().cloned());
+            break;
+        }
+    }
+
+    run.stages = stages;
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, kind: PackageKind, deps: &[&str]) -> Package {
+        Package {
+            name: name.to_string(),
+            version: None,
+            path: PathBuf::from(name),
+            kind,
+            package_manager: Some(PackageManager::Cargo),
+            build_system: Some("cargo".into()),
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+            dev_dependencies: vec![],
+            peer_dependencies: vec![],
+            source_files: vec![],
+            entry_points: vec![],
+        }
+    }
+
+    fn workspace() -> Vec<Package> {
+        vec![
+            package("root", PackageKind::WorkspaceRoot, &[]),
+            package("app", PackageKind::App, &["ui", "utils"]),
+            package("ui", PackageKind::Library, &["utils"]),
+            package("utils", PackageKind::Library, &[]),
+            package("cli", PackageKind::App, &[]),
+        ]
+    }
+
+    #[test]
+    fn test_affected_stages() {
+        let packages = workspace();
+        let graph = DependencyGraph::build(&packages).unwrap();
+        let owners = vec![Some(packages[3].clone()), None];
+        let affected = AffectedPackages::resolve(
+            vec![PathBuf::from("utils/src/lib.rs"), PathBuf::from("README.md")],
+            &owners,
+            &packages,
+            &graph,
+        ).unwrap();
+
+        assert_eq!(affected.changed, vec!["utils"]);
+        assert_eq!(affected.dependents, vec!["app", "ui"]);
+
+        let tasks = affected_task_graph(&affected, &packages, AffectedTarget::Test);
+        assert_eq!(tasks.parallel_stages(), vec![
+            vec!["test: utils".to_string()],
+            vec!["test: ui".to_string()],
+            vec!["test: app".to_string()],
+        ]);
+        let app = tasks.get("test: app").unwrap();
+        assert_eq!(app.args, vec!["test", "-p", "app"]);
+        assert_eq!(app.cwd, Some(PathBuf::from("app")));
+    }
+
+    #[test]
+    fn test_workspace_root_change_affects_all() {
+        let packages = workspace();
+        let graph = DependencyGraph::build(&packages).unwrap();
+        let affected = AffectedPackages::resolve(
+            vec![PathBuf::from("Cargo.lock")],
+            &[Some(packages[0].clone())],
+            &packages,
+            &graph,
+        ).unwrap();
+
+        assert_eq!(affected.changed, vec!["app", "cli", "ui", "utils"]);
+        assert!(affected.dependents.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_run_affected_in_repository() {
+        use git::test_support::{git_in, test_repo};
+
+        let dir = test_repo("affected-run");
+        let write = |path: &str, content: &str| {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("package.json", r#"{"name": "acme", "private": true, "workspaces": ["packages/*"]}"#);
+        write("packages/utils/package.json", r#"{"name": "utils", "scripts": {"test": "echo ok > ran"}}"#);
+        write("packages/utils/index.js", "");
+        write("packages/ui/package.json", r#"{"name": "ui", "dependencies": {"utils": "*"}, "scripts": {"test": "echo ok > ran"}}"#);
+        // Affected, but has no test script
+        write("packages/docs/package.json", r#"{"name": "docs", "dependencies": {"utils": "*"}}"#);
+        write("packages/cli/package.json", r#"{"name": "cli", "scripts": {"test": "exit 1"}}"#);
+        git_in(&dir, &["add", "."]);
+        git_in(&dir, &["commit", "-q", "-m", "init"]);
+        write("packages/utils/index.js", "module.exports = 1;\n");
+
+        let (affected, _) = affected_packages(&dir, "HEAD").await.unwrap();
+        assert_eq!(affected.changed_files, vec![PathBuf::from("packages/utils/index.js")]);
+        assert_eq!(affected.changed, vec!["utils"]);
+        assert_eq!(affected.dependents, vec!["docs", "ui"]);
+
+        let scheduler = TaskScheduler::new(2);
+        let run = run_affected(&scheduler, &dir, "HEAD", AffectedTarget::Test).await.unwrap();
+        assert!(run.success(), "{:?}", run);
+        assert_eq!(run.stages, vec![vec!["test: utils".to_string()], vec!["test: ui".to_string()]]);
+        assert!(dir.join("packages/utils/ran").exists());
+        assert!(dir.join("packages/ui/ran").exists());
+        assert!(!dir.join("packages/cli/ran").exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,152 @@
+//! Automated bisect
+//!
+//! Drives `git bisect` with a task as the predicate, like `git bisect run`
+//! but stepped from here: each checked-out commit is tested by running the
+//! task through the [`TaskRunner`], so its output goes to the task panel and
+//! the terminal stays free while the search runs.
+
+use std::path::PathBuf;
+
+use git::{BisectStep, GitOperations};
+use tokio::sync::broadcast;
+
+use crate::runner::TaskRunner;
+use crate::{Task, TaskEvent, TaskId};
+
+/// How the predicate judged a commit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BisectVerdict {
+    Good,
+    Bad,
+    Skip,
+}
+
+impl BisectVerdict {
+    /// Verdict for a task's exit code, following `git bisect run`: 0 is good,
+    /// 125 skips the commit, 1 to 127 is bad and anything else aborts
+    pub fn from_exit_code(code: i32) -> Option<Self> {
+        match code {
+            0 => Some(BisectVerdict::Good),
+            125 => Some(BisectVerdict::Skip),
+            1..=127 => Some(BisectVerdict::Bad),
+            _ => None,
+        }
+    }
+}
+
+/// Result of an automated bisect
+#[derive(Debug, Clone)]
+pub struct BisectRun {
+    /// Commits tested, in order, with their verdicts
+    pub tested: Vec<(String, BisectVerdict)>,
+    /// Where the bisect ended: the first bad commit, or the candidates when
+    /// only skipped commits are left
+    pub result: BisectStep,
+}
+
+/// Bisect with `task` as the predicate until the first bad commit is found
+///
+/// The bisect must already be started with a good and a bad commit. Tasks
+/// without a working directory run in the repository. `on_step` sees every
+/// step so views can follow the narrowing range. The bisect is left in
+/// place for [`GitOperations::bisect_reset`] once the result has been looked at.
+pub async fn run_bisect(
+    runner: &TaskRunner,
+    events: &broadcast::Sender<TaskEvent>,
+    ops: &GitOperations,
+    task: &Task,
+    mut on_step: impl FnMut(&BisectStep),
+) -> anyhow::Result<BisectRun> {
+    let mut task = task.clone();
+    if task.cwd.is_none() {
+        task.cwd = Some(PathBuf::from(ops.repo_path()));
+    }
+
+    let mut tested = Vec::new();
+    let mut step = ops.bisect_next().await?;
+    loop {
+        on_step(&step);
+        let commit = match &step {
+            BisectStep::Testing { commit, .. } => commit.clone(),
+            BisectStep::Waiting => anyhow::bail!("Bisect needs a good and a bad commit before it can run"),
+            BisectStep::FirstBad { .. } | BisectStep::Inconclusive { .. } => break,
+        };
+
+        let code = run_to_completion(runner, events, &task).await?;
+        let verdict = BisectVerdict::from_exit_code(code).ok_or_else(|| {
+            anyhow::anyhow!("Task {} exited with {} on {}, aborting bisect", task.name, code, commit)
+        })?;
+        tested.push((commit, verdict));
+
+        step = match verdict {
+            BisectVerdict::Good => ops.bisect_good(None).await?,
+            BisectVerdict::Bad => ops.bisect_bad(None).await?,
+            BisectVerdict::Skip => ops.bisect_skip(None).await?,
+        };
+    }
+
+    Ok(BisectRun { tested, result: step })
+}
+
+/// Run a task and wait for its exit code
+async fn run_to_completion(
+    runner: &TaskRunner,
+    events: &broadcast::Sender<TaskEvent>,
+    task: &Task,
+) -> anyhow::Result<i32> {
+    let mut rx = events.subscribe();
+    let id = TaskId::new();
+    runner.run(id, task, events.clone()).await?;
+
+    loop {
+        match rx.recv().await {
+            Ok(TaskEvent::Completed { id: done, exit_code }) if done == id => return Ok(exit_code),
+            Ok(TaskEvent::Failed { id: done, error }) if done == id => anyhow::bail!(error),
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => anyhow::bail!("Task events closed"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use git::test_support::{git_in, test_repo};
+
+    #[tokio::test]
+    async fn test_run_bisect() {
+        let dir = test_repo("task-bisect");
+        let git = |args: &[&str]| git_in(&dir, args).trim().to_string();
+
+        for n in 1..=8 {
+            // Commit 3 can't be tested, commit 6 breaks the build
+            let state = match n {
+                3 => "untestable",
+                n if n >= 6 => "broken",
+                _ => "fine",
+            };
+            std::fs::write(dir.join("state.txt"), format!("{} {}", state, n)).unwrap();
+            git(&["add", "state.txt"]);
+            git(&["commit", "-q", "-m", &format!("Commit {}", n)]);
+        }
+        let first_bad = git(&["rev-parse", "HEAD~2"]);
+
+        let ops = GitOperations::new(&dir);
+        ops.bisect_start("HEAD", &["HEAD~7"]).await.unwrap();
+
+        let task = Task::shell(
+            "check",
+            "case $(cat state.txt) in fine*) exit 0;; untestable*) exit 125;; *) exit 1;; esac",
+        );
+        let (events, _) = broadcast::channel(64);
+        let mut steps = 0;
+        let run = run_bisect(&TaskRunner::new(), &events, &ops, &task, |_| steps += 1).await.unwrap();
+
+        assert_eq!(run.result, BisectStep::FirstBad { commit: first_bad });
+        assert_eq!(steps, run.tested.len() + 1);
+        assert!(run.tested.iter().any(|(_, v)| *v == BisectVerdict::Bad));
+
+        ops.bisect_reset().await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
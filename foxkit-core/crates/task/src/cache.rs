@@ -0,0 +1,952 @@
+//! Task cache
+//!
+//! Content-hashed caching of task results. A task's cache key hashes its
+//! command, declared inputs, declared environment variables and the current
+//! outputs of the tasks it depends on. A successful run stores the declared outputs
+//! and the task's log in a local content-addressed store; a later run with
+//! the same key restores them instead of running the task.
+//!
+//! Artifacts travel between backends as gzipped tarballs, which is also the
+//! payload format of the `/v8/artifacts` remote cache API used by
+//! [`HttpCache`].
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use async_trait::async_trait;
+use dependency_graph::IncrementalBuild;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::Task;
+
+/// Bumped whenever the key derivation or artifact layout changes
+const CACHE_VERSION: &str = "foxkit-task-cache-v1";
+/// Archive directory holding the log and metadata next to the outputs
+const META_DIR: &str = ".foxkit-cache";
+/// Directories skipped when a task declares no inputs
+const DEFAULT_IGNORED_DIRS: &[&str] = &["node_modules", "target"];
+
+/// Cache settings of a task.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskCacheConfig {
+    /// Input file globs relative to the task's working directory; `!`
+    /// excludes. When empty, every file except outputs, hidden entries,
+    /// `node_modules` and `target` is an input.
+    #[serde(default)]
+    pub inputs: Vec<String>,
+    /// Output file globs restored on a cache hit
+    #[serde(default)]
+    pub outputs: Vec<String>,
+    /// Environment variables whose values affect the result
+    #[serde(default)]
+    pub env: Vec<String>,
+}
+
+/// A file stored in an artifact.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CachedFile {
+    /// Path relative to the task's working directory
+    pub path: PathBuf,
+    /// Unix permission bits
+    pub mode: u32,
+    pub contents: Vec<u8>,
+}
+
+/// The stored result of a task run.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheArtifact {
+    pub key: String,
+    pub task: String,
+    pub files: Vec<CachedFile>,
+    /// Output lines, replayed on a hit
+    pub logs: Vec<String>,
+    pub duration_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ArtifactMeta {
+    key: String,
+    task: String,
+    duration_ms: u64,
+}
+
+impl CacheArtifact {
+    /// Digest of the output files, as used in the keys of dependent tasks.
+    pub fn outputs_digest(&self) -> String {
+        digest_files(self.files.iter().map(|f| (f.path.clone(), sha256_hex(&f.contents))).collect())
+    }
+
+    /// Pack into a gzipped tarball.
+    pub fn to_archive(&self) -> anyhow::Result<Vec<u8>> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        let mut builder = tar::Builder::new(encoder);
+        let mut append = |path: &Path, mode: u32, contents: &[u8]| -> anyhow::Result<()> {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(mode);
+            header.set_mtime(0);
+            header.set_cksum();
+            builder.append_data(&mut header, path, contents)?;
+            Ok(())
+        };
+
+        let meta = serde_json::to_vec(&ArtifactMeta {
+            key: self.key.clone(),
+            task: self.task.clone(),
+            duration_ms: self.duration_ms,
+        })?;
+        append(&Path::new(META_DIR).join("meta.json"), 0o644, &meta)?;
+        append(&Path::new(META_DIR).join("task.log"), 0o644, self.logs.join("\n").as_bytes())?;
+        for file in &self.files {
+            check_relative(&file.path)?;
+            append(&file.path, file.mode, &file.contents)?;
+        }
+
+        Ok(builder.into_inner()?.finish()?)
+    }
+
+    /// Unpack a gzipped tarball produced by [`CacheArtifact::to_archive`].
+    pub fn from_archive(bytes: &[u8]) -> anyhow::Result<Self> {
+        let mut archive = tar::Archive::new(flate2::read::GzDecoder::new(bytes));
+        let mut artifact = CacheArtifact::default();
+        let mut meta = None;
+
+        for entry in archive.entries()? {
+            let mut entry = entry?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path()?.into_owned();
+            // Archives may come from a remote cache; never write outside the task
+            check_relative(&path)?;
+            let mode = entry.header().mode()?;
+            let mut contents = Vec::new();
+            entry.read_to_end(&mut contents)?;
+
+            if path == Path::new(META_DIR).join("meta.json") {
+                meta = Some(serde_json::from_slice::<ArtifactMeta>(&contents)?);
+            } else if path == Path::new(META_DIR).join("task.log") {
+                let log = String::from_utf8_lossy(&contents);
+                artifact.logs = if log.is_empty() {
+                    Vec::new()
+                } else {
+                    log.split('\n').map(str::to_string).collect()
+                };
+            } else {
+                artifact.files.push(CachedFile { path, mode, contents });
+            }
+        }
+
+        let meta = meta.ok_or_else(|| anyhow::anyhow!("cache artifact without metadata"))?;
+        artifact.key = meta.key;
+        artifact.task = meta.task;
+        artifact.duration_ms = meta.duration_ms;
+        Ok(artifact)
+    }
+}
+
+/// Storage for cache artifacts.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    /// Backend name for logs
+    fn name(&self) -> &str;
+
+    /// Fetch the artifact stored under `key`
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheArtifact>>;
+
+    /// Store an artifact under its key
+    async fn put(&self, artifact: &CacheArtifact) -> anyhow::Result<()>;
+}
+
+/// On-disk content-addressed store.
+///
+/// File contents and logs are stored once per SHA-256 under `blobs/`, and
+/// `entries/<key>.json` lists the blobs of each artifact.
+#[derive(Clone)]
+pub struct LocalCache {
+    root: PathBuf,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LocalEntry {
+    key: String,
+    task: String,
+    files: Vec<LocalFile>,
+    logs: String,
+    duration_ms: u64,
+}
+
+#[derive(Serialize, Deserialize)]
+struct LocalFile {
+    path: PathBuf,
+    mode: u32,
+    hash: String,
+}
+
+impl LocalCache {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Cache stored in `.foxkit/cache` of a workspace
+    pub fn in_workspace(workspace_root: &Path) -> Self {
+        Self::new(workspace_root.join(".foxkit").join("cache"))
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.root.join("blobs").join(&hash[..2]).join(&hash[2..])
+    }
+
+    fn entry_path(&self, key: &str) -> PathBuf {
+        self.root.join("entries").join(format!("{}.json", key))
+    }
+
+    fn write_blob(&self, contents: &[u8]) -> anyhow::Result<String> {
+        let hash = sha256_hex(contents);
+        let path = self.blob_path(&hash);
+        if !path.exists() {
+            write_atomic(&path, contents)?;
+        }
+        Ok(hash)
+    }
+
+    fn read_blob(&self, hash: &str) -> anyhow::Result<Vec<u8>> {
+        check_hash(hash)?;
+        let contents = std::fs::read(self.blob_path(hash))?;
+        if sha256_hex(&contents) != hash {
+            anyhow::bail!("corrupt cache blob {}", hash);
+        }
+        Ok(contents)
+    }
+
+    /// Remove every entry and blob
+    pub fn clear(&self) -> anyhow::Result<()> {
+        if self.root.exists() {
+            std::fs::remove_dir_all(&self.root)?;
+        }
+        Ok(())
+    }
+
+    fn read_entry(&self, key: &str) -> anyhow::Result<Option<CacheArtifact>> {
+        check_hash(key)?;
+        let entry: LocalEntry = match std::fs::read(self.entry_path(key)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+
+        // The store sits inside the workspace; never trust its paths
+        let files = entry.files.iter()
+            .map(|file| {
+                check_relative(&file.path)?;
+                Ok(CachedFile {
+                    path: file.path.clone(),
+                    mode: file.mode,
+                    contents: self.read_blob(&file.hash)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let log = String::from_utf8(self.read_blob(&entry.logs)?)?;
+
+        Ok(Some(CacheArtifact {
+            key: entry.key,
+            task: entry.task,
+            files,
+            logs: serde_json::from_str(&log)?,
+            duration_ms: entry.duration_ms,
+        }))
+    }
+
+    fn write_entry(&self, artifact: &CacheArtifact) -> anyhow::Result<()> {
+        check_hash(&artifact.key)?;
+        let files = artifact.files.iter()
+            .map(|file| {
+                check_relative(&file.path)?;
+                Ok(LocalFile {
+                    path: file.path.clone(),
+                    mode: file.mode,
+                    hash: self.write_blob(&file.contents)?,
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let logs = self.write_blob(&serde_json::to_vec(&artifact.logs)?)?;
+
+        let entry = LocalEntry {
+            key: artifact.key.clone(),
+            task: artifact.task.clone(),
+            files,
+            logs,
+            duration_ms: artifact.duration_ms,
+        };
+        write_atomic(&self.entry_path(&artifact.key), &serde_json::to_vec_pretty(&entry)?)
+    }
+}
+
+#[async_trait]
+impl CacheBackend for LocalCache {
+    fn name(&self) -> &str {
+        "local"
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheArtifact>> {
+        let cache = self.clone();
+        let key = key.to_string();
+        tokio::task::spawn_blocking(move || cache.read_entry(&key)).await?
+    }
+
+    async fn put(&self, artifact: &CacheArtifact) -> anyhow::Result<()> {
+        let cache = self.clone();
+        let artifact = artifact.clone();
+        tokio::task::spawn_blocking(move || cache.write_entry(&artifact)).await?
+    }
+}
+
+/// Remote cache speaking the `/v8/artifacts/{hash}` HTTP API.
+pub struct HttpCache {
+    client: reqwest::Client,
+    base_url: String,
+    token: Option<String>,
+    team: Option<String>,
+}
+
+impl HttpCache {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            token: None,
+            team: None,
+        }
+    }
+
+    /// Bearer token sent with every request
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    /// Team the artifacts belong to
+    pub fn with_team(mut self, team: &str) -> Self {
+        self.team = Some(team.to_string());
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, key: &str) -> reqwest::RequestBuilder {
+        let mut request = self.client.request(method, format!("{}/v8/artifacts/{}", self.base_url, key));
+        if let Some(team) = &self.team {
+            request = request.query(&[("teamId", team)]);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        request
+    }
+}
+
+#[async_trait]
+impl CacheBackend for HttpCache {
+    fn name(&self) -> &str {
+        &self.base_url
+    }
+
+    async fn get(&self, key: &str) -> anyhow::Result<Option<CacheArtifact>> {
+        check_hash(key)?;
+        let response = self.request(reqwest::Method::GET, key).send().await?;
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let bytes = response.error_for_status()?.bytes().await?;
+        let artifact = CacheArtifact::from_archive(&bytes)?;
+        if artifact.key != key {
+            anyhow::bail!("remote cache returned artifact {} for {}", artifact.key, key);
+        }
+        Ok(Some(artifact))
+    }
+
+    async fn put(&self, artifact: &CacheArtifact) -> anyhow::Result<()> {
+        check_hash(&artifact.key)?;
+        self.request(reqwest::Method::PUT, &artifact.key)
+            .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+            .header("x-artifact-duration", artifact.duration_ms.to_string())
+            .body(artifact.to_archive()?)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Task cache combining the local store with an optional remote backend.
+pub struct TaskCache {
+    local: LocalCache,
+    remote: Option<Arc<dyn CacheBackend>>,
+    /// Key each task last ran or was restored with
+    incremental: RwLock<IncrementalBuild>,
+}
+
+impl TaskCache {
+    pub fn new(local: LocalCache) -> Self {
+        Self {
+            local,
+            remote: None,
+            incremental: RwLock::new(IncrementalBuild::new()),
+        }
+    }
+
+    /// Also read from and write to a remote cache
+    pub fn with_remote(mut self, remote: Arc<dyn CacheBackend>) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    pub fn local(&self) -> &LocalCache {
+        &self.local
+    }
+
+    /// Compute the cache key of a task.
+    ///
+    /// `tasks` resolves dependencies by name. A dependency with declared
+    /// outputs contributes the digest of those files as they are on disk;
+    /// one without contributes its own key.
+    pub async fn key(&self, task: &Task, tasks: &HashMap<String, Task>) -> anyhow::Result<String> {
+        let task = task.clone();
+        let tasks = tasks.clone();
+        tokio::task::spawn_blocking(move || task_key(&task, &tasks, &mut Vec::new())).await?
+    }
+
+    /// Look an artifact up locally, then remotely.
+    ///
+    /// Remote hits are written to the local store; remote failures are
+    /// logged and treated as misses.
+    pub async fn fetch(&self, key: &str) -> anyhow::Result<Option<CacheArtifact>> {
+        if let Some(artifact) = self.local.get(key).await? {
+            return Ok(Some(artifact));
+        }
+        let Some(remote) = &self.remote else {
+            return Ok(None);
+        };
+        match remote.get(key).await {
+            Ok(Some(artifact)) => {
+                self.local.put(&artifact).await?;
+                Ok(Some(artifact))
+            }
+            Ok(None) => Ok(None),
+            Err(e) => {
+                tracing::warn!("Remote cache {} lookup of {} failed: {}", remote.name(), key, e);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Write an artifact's outputs into the task's working directory
+    pub async fn restore(&self, task: &Task, artifact: &CacheArtifact) -> anyhow::Result<()> {
+        // Reject the whole artifact before writing anything
+        for file in &artifact.files {
+            check_relative(&file.path)?;
+        }
+
+        let cwd = task_dir(task);
+        let up_to_date = !self.incremental.read().needs_rebuild(&task.name, &artifact.key);
+        let files = artifact.files.clone();
+        tokio::task::spawn_blocking(move || -> anyhow::Result<()> {
+            for file in files {
+                let path = cwd.join(&file.path);
+                if up_to_date && std::fs::read(&path).is_ok_and(|contents| contents == file.contents) {
+                    continue;
+                }
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                std::fs::write(&path, &file.contents)?;
+                set_mode(&path, file.mode)?;
+            }
+            Ok(())
+        })
+        .await??;
+
+        self.finished(task, artifact);
+        Ok(())
+    }
+
+    /// Collect the declared outputs of a successful run.
+    pub async fn capture(&self, task: &Task, key: &str, logs: Vec<String>, duration_ms: u64) -> anyhow::Result<CacheArtifact> {
+        let config = task.cache.clone().unwrap_or_default();
+        let cwd = task_dir(task);
+        let files = tokio::task::spawn_blocking(move || {
+            matching_files(&cwd, &config.outputs)?
+                .into_iter()
+                .map(|path| {
+                    let full = cwd.join(&path);
+                    Ok(CachedFile {
+                        mode: file_mode(&full)?,
+                        contents: std::fs::read(&full)?,
+                        path,
+                    })
+                })
+                .collect::<anyhow::Result<Vec<_>>>()
+        })
+        .await??;
+
+        Ok(CacheArtifact {
+            key: key.to_string(),
+            task: task.name.clone(),
+            files,
+            logs,
+            duration_ms,
+        })
+    }
+
+    /// Store an artifact locally and remotely.
+    pub async fn store(&self, task: &Task, artifact: &CacheArtifact) -> anyhow::Result<()> {
+        self.finished(task, artifact);
+        self.local.put(artifact).await?;
+        if let Some(remote) = &self.remote
+            && let Err(e) = remote.put(artifact).await
+        {
+            tracing::warn!("Remote cache {} upload of {} failed: {}", remote.name(), artifact.key, e);
+        }
+        Ok(())
+    }
+
+    /// Whether a task last ran (or was restored) with a different key
+    pub fn needs_run(&self, task: &str, key: &str) -> bool {
+        self.incremental.read().needs_rebuild(task, key)
+    }
+
+    fn finished(&self, task: &Task, artifact: &CacheArtifact) {
+        self.incremental.write().record_build(&task.name, &artifact.key);
+    }
+}
+
+/// Cache key of a task; `visiting` holds the dependents being keyed, so a
+/// dependency cycle ends in a name instead of recursing
+fn task_key(task: &Task, tasks: &HashMap<String, Task>, visiting: &mut Vec<String>) -> anyhow::Result<String> {
+    let config = task.cache.clone().unwrap_or_default();
+    let cwd = task_dir(task);
+
+    let mut hasher = Sha256::new();
+    let mut field = |name: &str, value: &[u8]| {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(value);
+        hasher.update([0]);
+    };
+
+    field("version", CACHE_VERSION.as_bytes());
+    field("name", task.name.as_bytes());
+    field("type", serde_json::to_string(&task.task_type)?.as_bytes());
+    field("command", task.command.as_bytes());
+    for arg in &task.args {
+        field("arg", arg.as_bytes());
+    }
+
+    let mut env: Vec<_> = task.env.iter().collect();
+    env.sort();
+    for (key, value) in env {
+        field("env", format!("{}={}", key, value).as_bytes());
+    }
+    let mut declared = config.env.clone();
+    declared.sort();
+    for var in declared {
+        let value = std::env::var(&var).unwrap_or_default();
+        field("env-input", format!("{}={}", var, value).as_bytes());
+    }
+
+    for path in input_files(&cwd, &config)? {
+        let contents = std::fs::read(cwd.join(&path))?;
+        field("input", path.to_string_lossy().as_bytes());
+        field("hash", sha256_hex(&contents).as_bytes());
+    }
+
+    let mut dependencies = task.depends_on.clone();
+    dependencies.sort();
+    visiting.push(task.name.clone());
+    for dependency in dependencies {
+        let Some(definition) = tasks.get(&dependency).filter(|_| !visiting.contains(&dependency)) else {
+            field("dependency", dependency.as_bytes());
+            continue;
+        };
+        let outputs = definition.cache.as_ref().map(|c| c.outputs.as_slice()).unwrap_or_default();
+        let digest = if outputs.is_empty() {
+            format!("key:{}", task_key(definition, tasks, visiting)?)
+        } else {
+            format!("outputs:{}", outputs_digest(&task_dir(definition), outputs)?)
+        };
+        field("dependency", format!("{}={}", dependency, digest).as_bytes());
+    }
+    visiting.pop();
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Digest of the files matching output globs, as they are on disk
+fn outputs_digest(cwd: &Path, outputs: &[String]) -> anyhow::Result<String> {
+    let files = matching_files(cwd, outputs)?
+        .into_iter()
+        .map(|path| {
+            let hash = sha256_hex(&std::fs::read(cwd.join(&path))?);
+            Ok((path, hash))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(digest_files(files))
+}
+
+fn digest_files(mut files: Vec<(PathBuf, String)>) -> String {
+    files.sort();
+    let mut hasher = Sha256::new();
+    for (path, hash) in files {
+        hasher.update(path.to_string_lossy().as_bytes());
+        hasher.update([0]);
+        hasher.update(hash.as_bytes());
+        hasher.update([0]);
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+fn task_dir(task: &Task) -> PathBuf {
+    match &task.cwd {
+        Some(cwd) => cwd.clone(),
+        None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+    }
+}
+
+/// Input files of a task, relative to `cwd` and sorted
+fn input_files(cwd: &Path, config: &TaskCacheConfig) -> anyhow::Result<Vec<PathBuf>> {
+    if !config.inputs.is_empty() {
+        return matching_files(cwd, &config.inputs);
+    }
+
+    let outputs = patterns(&config.outputs)?;
+    let mut files = Vec::new();
+    let walker = walkdir::WalkDir::new(cwd).into_iter().filter_entry(|entry| {
+        let name = entry.file_name().to_string_lossy();
+        entry.depth() == 0
+            || !(name.starts_with('.') || entry.file_type().is_dir() && DEFAULT_IGNORED_DIRS.contains(&name.as_ref()))
+    });
+    for entry in walker {
+        let entry = entry?;
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        let relative = entry.path().strip_prefix(cwd)?.to_path_buf();
+        if !outputs.iter().any(|p| p.matches_path(&relative)) {
+            files.push(relative);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Files under `cwd` matching globs, with `!` globs excluding; directories
+/// matched by a glob contribute all files below them
+fn matching_files(cwd: &Path, globs: &[String]) -> anyhow::Result<Vec<PathBuf>> {
+    let excludes: Vec<String> = globs.iter()
+        .filter_map(|g| g.strip_prefix('!').map(str::to_string))
+        .collect();
+    let excludes = patterns(&excludes)?;
+    let base = glob::Pattern::escape(&cwd.to_string_lossy());
+
+    let mut files = Vec::new();
+    for pattern in globs.iter().filter(|g| !g.starts_with('!')) {
+        for path in glob::glob(&format!("{}/{}", base, normalize_glob(pattern)))? {
+            let path = path?;
+            if path.is_dir() {
+                for entry in walkdir::WalkDir::new(&path) {
+                    let entry = entry?;
+                    if entry.file_type().is_file() {
+                        files.push(entry.path().strip_prefix(cwd)?.to_path_buf());
+                    }
+                }
+            } else if path.is_file() {
+                files.push(path.strip_prefix(cwd)?.to_path_buf());
+            }
+        }
+    }
+    files.retain(|path| !excludes.iter().any(|p| p.matches_path(path)));
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+fn patterns(globs: &[String]) -> anyhow::Result<Vec<glob::Pattern>> {
+    Ok(globs.iter().map(|g| glob::Pattern::new(&normalize_glob(g))).collect::<Result<_, _>>()?)
+}
+
+/// A trailing `**` means everything below, which `glob` only matches as `**/*`
+fn normalize_glob(pattern: &str) -> String {
+    if pattern == "**" || pattern.ends_with("/**") {
+        format!("{}/*", pattern)
+    } else {
+        pattern.to_string()
+    }
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+fn check_relative(path: &Path) -> anyhow::Result<()> {
+    if path.components().any(|c| !matches!(c, Component::Normal(_))) {
+        anyhow::bail!("unsafe path in cache artifact: {}", path.display());
+    }
+    Ok(())
+}
+
+/// Keys and blob names end up in paths and URLs
+fn check_hash(hash: &str) -> anyhow::Result<()> {
+    if hash.len() < 8 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+        anyhow::bail!("invalid cache hash: {}", hash);
+    }
+    Ok(())
+}
+
+fn write_atomic(path: &Path, contents: &[u8]) -> anyhow::Result<()> {
+    let parent = path.parent().ok_or_else(|| anyhow::anyhow!("no parent: {}", path.display()))?;
+    std::fs::create_dir_all(parent)?;
+    let tmp = parent.join(format!(".tmp-{}-{}", std::process::id(), crate::TaskId::new().0));
+    std::fs::write(&tmp, contents)?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> anyhow::Result<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Ok(std::fs::metadata(path)?.permissions().mode() & 0o777)
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> anyhow::Result<u32> {
+    Ok(0o644)
+}
+
+#[cfg(unix)]
+fn set_mode(path: &Path, mode: u32) -> anyhow::Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_mode(_path: &Path, _mode: u32) -> anyhow::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("foxkit-task-cache-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn cached_task(dir: &Path) -> Task {
+        let mut task = Task::shell("build", "cp src/in.txt dist/out.txt").in_dir(dir.to_path_buf());
+        task.cache = Some(TaskCacheConfig {
+            inputs: vec!["src/**".to_string(), "!src/*.tmp".to_string()],
+            outputs: vec!["dist".to_string()],
+            env: vec!["FOXKIT_CACHE_TEST_VAR".to_string()],
+        });
+        task
+    }
+
+    #[tokio::test]
+    async fn test_key_and_local_roundtrip() {
+        let dir = temp_dir("local");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("dist")).unwrap();
+        std::fs::write(dir.join("src/in.txt"), "v1").unwrap();
+        std::fs::write(dir.join("dist/out.txt"), "built v1").unwrap();
+
+        let cache = TaskCache::new(LocalCache::new(dir.join(".cache")));
+        let task = cached_task(&dir);
+        let tasks = HashMap::from([(task.name.clone(), task.clone())]);
+        let key = cache.key(&task, &tasks).await.unwrap();
+
+        // Excluded and undeclared files don't affect the key
+        std::fs::write(dir.join("src/scratch.tmp"), "noise").unwrap();
+        std::fs::write(dir.join("README"), "noise").unwrap();
+        assert_eq!(cache.key(&task, &tasks).await.unwrap(), key);
+
+        assert!(cache.fetch(&key).await.unwrap().is_none());
+        let artifact = cache.capture(&task, &key, vec!["copied".to_string()], 12).await.unwrap();
+        cache.store(&task, &artifact).await.unwrap();
+        assert!(!cache.needs_run("build", &key));
+
+        std::fs::remove_dir_all(dir.join("dist")).unwrap();
+        let fetched = cache.fetch(&key).await.unwrap().unwrap();
+        assert_eq!(fetched, artifact);
+        cache.restore(&task, &fetched).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("dist/out.txt")).unwrap(), "built v1");
+
+        std::fs::write(dir.join("src/in.txt"), "v2").unwrap();
+        assert_ne!(cache.key(&task, &tasks).await.unwrap(), key);
+
+        // A dependency's outputs on disk are part of the key, however they
+        // were produced
+        std::fs::write(dir.join("src/in.txt"), "v1").unwrap();
+        let dependent = Task::shell("package", "true").in_dir(dir.clone()).depends("build");
+        let before = cache.key(&dependent, &tasks).await.unwrap();
+        std::fs::write(dir.join("dist/out.txt"), "built differently").unwrap();
+        assert_ne!(cache.key(&dependent, &tasks).await.unwrap(), before);
+
+        // Without declared outputs the dependency's own key stands in
+        let tasks = HashMap::from([("build".to_string(), Task::shell("build", "make").in_dir(dir.clone()))]);
+        let before = cache.key(&dependent, &tasks).await.unwrap();
+        std::fs::write(dir.join("src/in.txt"), "v3").unwrap();
+        assert_ne!(cache.key(&dependent, &tasks).await.unwrap(), before);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_archive_roundtrip() {
+        let artifact = CacheArtifact {
+            key: "ab".repeat(32),
+            task: "build".to_string(),
+            files: vec![CachedFile { path: PathBuf::from("dist/a.js"), mode: 0o755, contents: b"x".to_vec() }],
+            logs: vec!["line one".to_string(), "line two".to_string()],
+            duration_ms: 5,
+        };
+        let archive = artifact.to_archive().unwrap();
+        assert_eq!(CacheArtifact::from_archive(&archive).unwrap(), artifact);
+
+        let mut evil = artifact.clone();
+        evil.files[0].path = PathBuf::from("../escape");
+        assert!(evil.to_archive().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_local_entry_with_unsafe_path_is_rejected() {
+        let dir = temp_dir("unsafe");
+        let cache = TaskCache::new(LocalCache::new(dir.join(".cache")));
+        let task = Task::shell("build", "true").in_dir(dir.join("work"));
+        let key = "cd".repeat(32);
+        let artifact = CacheArtifact {
+            key: key.clone(),
+            task: "build".to_string(),
+            files: vec![CachedFile { path: PathBuf::from("out.txt"), mode: 0o644, contents: b"x".to_vec() }],
+            ..Default::default()
+        };
+        cache.local().put(&artifact).await.unwrap();
+
+        // Tamper with the stored entry
+        let entry_path = cache.local().entry_path(&key);
+        for path in ["../escape.txt", "/tmp/escape.txt"] {
+            let mut entry: serde_json::Value = serde_json::from_slice(&std::fs::read(&entry_path).unwrap()).unwrap();
+            entry["files"][0]["path"] = path.into();
+            std::fs::write(&entry_path, serde_json::to_vec(&entry).unwrap()).unwrap();
+            assert!(cache.fetch(&key).await.is_err());
+        }
+
+        let mut evil = artifact.clone();
+        evil.files[0].path = PathBuf::from("../escape.txt");
+        assert!(cache.restore(&task, &evil).await.is_err());
+        assert!(!dir.join("escape.txt").exists());
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    /// Minimal stand-in for a remote cache server
+    async fn serve_artifacts(listener: tokio::net::TcpListener, store: Arc<RwLock<HashMap<String, Vec<u8>>>>) {
+        loop {
+            let Ok((stream, _)) = listener.accept().await else { return };
+            let store = store.clone();
+            tokio::spawn(async move {
+                let mut reader = BufReader::new(stream);
+                loop {
+                    let mut request_line = String::new();
+                    if reader.read_line(&mut request_line).await.unwrap_or(0) == 0 {
+                        return;
+                    }
+                    let mut length = 0;
+                    let mut authorized = false;
+                    loop {
+                        let mut header = String::new();
+                        reader.read_line(&mut header).await.unwrap();
+                        let header = header.trim_end();
+                        if header.is_empty() {
+                            break;
+                        }
+                        let (name, value) = header.split_once(": ").unwrap();
+                        match name.to_ascii_lowercase().as_str() {
+                            "content-length" => length = value.parse().unwrap(),
+                            "authorization" => authorized = value == "Bearer secret",
+                            _ => {}
+                        }
+                    }
+                    let mut body = vec![0; length];
+                    reader.read_exact(&mut body).await.unwrap();
+
+                    let mut parts = request_line.split_whitespace();
+                    let method = parts.next().unwrap().to_string();
+                    let path = parts.next().unwrap();
+                    let key = path.trim_start_matches("/v8/artifacts/").split('?').next().unwrap().to_string();
+                    let (status, response) = if !authorized {
+                        ("401 Unauthorized", Vec::new())
+                    } else if method == "PUT" {
+                        store.write().insert(key, body);
+                        ("202 Accepted", Vec::new())
+                    } else {
+                        match store.read().get(&key) {
+                            Some(bytes) => ("200 OK", bytes.clone()),
+                            None => ("404 Not Found", Vec::new()),
+                        }
+                    };
+                    let head = format!("HTTP/1.1 {}\r\ncontent-length: {}\r\n\r\n", status, response.len());
+                    let stream = reader.get_mut();
+                    stream.write_all(head.as_bytes()).await.unwrap();
+                    stream.write_all(&response).await.unwrap();
+                }
+            });
+        }
+    }
+
+    #[tokio::test]
+    async fn test_http_cache_against_stand_in_server() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let store = Arc::new(RwLock::new(HashMap::new()));
+        tokio::spawn(serve_artifacts(listener, store.clone()));
+
+        let dir = temp_dir("http");
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("dist")).unwrap();
+        std::fs::write(dir.join("src/in.txt"), "v1").unwrap();
+        std::fs::write(dir.join("dist/out.txt"), "built").unwrap();
+        let task = cached_task(&dir);
+
+        let remote = Arc::new(HttpCache::new(&url).with_token("secret").with_team("team_1"));
+        let producer = TaskCache::new(LocalCache::new(dir.join(".cache-a"))).with_remote(remote.clone());
+        let key = producer.key(&task, &HashMap::new()).await.unwrap();
+        let artifact = producer.capture(&task, &key, vec!["log".to_string()], 3).await.unwrap();
+        producer.store(&task, &artifact).await.unwrap();
+        assert!(store.read().contains_key(&key));
+
+        // A fresh machine gets the artifact from the remote and keeps a local copy
+        let consumer = TaskCache::new(LocalCache::new(dir.join(".cache-b"))).with_remote(remote);
+        assert_eq!(consumer.fetch(&key).await.unwrap(), Some(artifact.clone()));
+        assert_eq!(consumer.local().get(&key).await.unwrap(), Some(artifact));
+
+        let unauthorized = HttpCache::new(&url);
+        assert!(unauthorized.get(&key).await.is_err());
+        assert!(remote_miss(&url).await);
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    async fn remote_miss(url: &str) -> bool {
+        HttpCache::new(url).with_token("secret").get(&"0".repeat(64)).await.unwrap().is_none()
+    }
+}
@@ -1,7 +1,8 @@
 //! Task configuration
 
+use std::collections::HashMap;
 use serde::{Deserialize, Serialize};
-use crate::Task;
+use crate::{ProblemMatcher, Task};
 
 /// Task configuration file (tasks.json)
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -12,6 +13,9 @@ pub struct TaskConfig {
     /// Tasks
     #[serde(default)]
     pub tasks: Vec<Task>,
+    /// Named problem matchers referenced by tasks
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub problem_matchers: HashMap<String, ProblemMatcher>,
     /// OS-specific configuration
     pub windows: Option<OsConfig>,
     pub linux: Option<OsConfig>,
@@ -27,6 +31,7 @@ impl Default for TaskConfig {
         Self {
             version: default_version(),
             tasks: Vec::new(),
+            problem_matchers: HashMap::new(),
             windows: None,
             linux: None,
             osx: None,
@@ -61,6 +66,7 @@ impl TaskConfig {
                 Task::shell("test", "npm test"),
                 Task::shell("dev", "npm run dev").in_background(),
             ],
+            problem_matchers: HashMap::new(),
             windows: None,
             linux: None,
             osx: None,
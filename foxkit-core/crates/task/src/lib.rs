@@ -2,12 +2,17 @@
 //!
 //! Task runner, build system, and watch mode.
 
+pub mod affected;
+pub mod bisect;
+pub mod cache;
 pub mod config;
+pub mod problem_matcher;
+pub mod providers;
 pub mod runner;
 pub mod scheduler;
 pub mod watcher;
 
-pub use scheduler::{TaskScheduler, TaskGraph, SchedulerEvent, QueuedTask};
+pub use scheduler::{TaskScheduler, TaskGraph, SchedulerEvent, QueuedTask, TaskNode, NodeState, SkipReason};
 
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -17,9 +22,14 @@ use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
+pub use affected::{AffectedPackages, AffectedRun, AffectedTarget};
+pub use bisect::{BisectRun, BisectVerdict};
+pub use cache::{CacheBackend, HttpCache, LocalCache, TaskCache, TaskCacheConfig};
 pub use config::TaskConfig;
 pub use runner::{TaskRunner, TaskHandle};
 pub use watcher::FileWatcher;
+pub use problem_matcher::{ProblemMatcher, ProblemMatcherRegistry, ProblemPattern, ProblemReporter};
+pub use providers::{TaskProvider, TaskProviderRegistry};
 
 /// Task ID
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -72,6 +82,15 @@ pub struct Task {
     /// Presentation options
     #[serde(default)]
     pub presentation: TaskPresentation,
+    /// Cache settings; tasks without them always run
+    #[serde(default)]
+    pub cache: Option<TaskCacheConfig>,
+    /// What the scheduler does when the task fails
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+    /// Monorepo package the task belongs to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
 }
 
 impl Task {
@@ -90,6 +109,9 @@ impl Task {
             watch: Vec::new(),
             group: None,
             presentation: TaskPresentation::default(),
+            cache: None,
+            on_failure: FailurePolicy::default(),
+            package: None,
         }
     }
 
@@ -108,6 +130,9 @@ impl Task {
             watch: Vec::new(),
             group: None,
             presentation: TaskPresentation::default(),
+            cache: None,
+            on_failure: FailurePolicy::default(),
+            package: None,
         }
     }
 
@@ -126,6 +151,9 @@ impl Task {
             watch: Vec::new(),
             group: None,
             presentation: TaskPresentation::default(),
+            cache: None,
+            on_failure: FailurePolicy::default(),
+            package: None,
         }
     }
 
@@ -144,6 +172,9 @@ impl Task {
             watch: Vec::new(),
             group: None,
             presentation: TaskPresentation::default(),
+            cache: None,
+            on_failure: FailurePolicy::default(),
+            package: None,
         }
     }
 
@@ -177,6 +208,22 @@ impl Task {
         self
     }
 
+    /// Cache results keyed on inputs, restoring outputs on a hit
+    pub fn cached(mut self, inputs: Vec<String>, outputs: Vec<String>) -> Self {
+        self.cache = Some(TaskCacheConfig {
+            inputs,
+            outputs,
+            env: Vec::new(),
+        });
+        self
+    }
+
+    /// Set the failure policy
+    pub fn on_failure(mut self, policy: FailurePolicy) -> Self {
+        self.on_failure = policy;
+        self
+    }
+
     /// Run in background
     pub fn in_background(mut self) -> Self {
         self.background = true;
@@ -215,6 +262,19 @@ pub enum TaskGroup {
     None,
 }
 
+/// What the scheduler does when a task fails
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FailurePolicy {
+    /// Cancel running tasks and skip everything not started yet
+    #[default]
+    FailFast,
+    /// Skip the task's dependents; unrelated tasks keep going
+    Continue,
+    /// Run the task up to N more times, then fail fast
+    Retry(u32),
+}
+
 /// Task presentation
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TaskPresentation {
@@ -277,6 +337,10 @@ pub enum TaskEvent {
     Output { id: TaskId, data: String },
     Completed { id: TaskId, exit_code: i32 },
     Failed { id: TaskId, error: String },
+    /// The problem matcher saw a watch cycle start
+    BackgroundBegan { id: TaskId },
+    /// The problem matcher saw a watch cycle finish
+    BackgroundEnded { id: TaskId },
 }
 
 /// Task service
@@ -289,6 +353,8 @@ pub struct TaskService {
     events: broadcast::Sender<TaskEvent>,
     /// Task runner
     runner: Arc<TaskRunner>,
+    /// Providers used by auto-detection
+    providers: Arc<TaskProviderRegistry>,
 }
 
 impl TaskService {
@@ -299,9 +365,26 @@ impl TaskService {
             running: RwLock::new(HashMap::new()),
             events,
             runner: Arc::new(TaskRunner::new()),
+            providers: Arc::new(TaskProviderRegistry::new()),
         }
     }
 
+    /// Report matched task problems to the problems panel
+    pub fn with_problems(self, problems: Arc<problems::ProblemsService>) -> Self {
+        self.runner.set_problems(problems);
+        self
+    }
+
+    /// Problem matchers available to tasks
+    pub fn problem_matchers(&self) -> &Arc<ProblemMatcherRegistry> {
+        self.runner.matchers()
+    }
+
+    /// Task providers used by auto-detection
+    pub fn providers(&self) -> &Arc<TaskProviderRegistry> {
+        &self.providers
+    }
+
     /// Register a task
     pub fn register(&self, task: Task) {
         self.tasks.write().insert(task.name.clone(), task);
@@ -317,6 +400,14 @@ impl TaskService {
         self.tasks.read().values().cloned().collect()
     }
 
+    /// Tasks belonging to a monorepo package
+    pub fn list_for_package(&self, package: &str) -> Vec<Task> {
+        self.tasks.read().values()
+            .filter(|task| task.package.as_deref() == Some(package))
+            .cloned()
+            .collect()
+    }
+
     /// Run a task by name
     pub async fn run(&self, name: &str) -> anyhow::Result<TaskId> {
         let task = self.get(name)
@@ -335,6 +426,30 @@ impl TaskService {
         Ok(id)
     }
 
+    /// Run `target` for the monorepo packages under `root` affected by
+    /// changes against the `base` ref
+    pub async fn run_affected(
+        &self,
+        root: &std::path::Path,
+        base: &str,
+        target: AffectedTarget,
+    ) -> anyhow::Result<AffectedRun> {
+        affected::run_affected(&TaskScheduler::default(), root, base, target).await
+    }
+
+    /// Bisect with a task as the predicate until the first bad commit is
+    /// found, streaming its output as task events
+    pub async fn run_bisect(
+        &self,
+        ops: &git::GitOperations,
+        name: &str,
+        on_step: impl FnMut(&git::BisectStep),
+    ) -> anyhow::Result<BisectRun> {
+        let task = self.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Task not found: {}", name))?;
+        bisect::run_bisect(&self.runner, &self.events, ops, &task, on_step).await
+    }
+
     /// Cancel a running task
     pub fn cancel(&self, id: TaskId) -> bool {
         if let Some(handle) = self.running.write().remove(&id) {
@@ -360,6 +475,9 @@ impl TaskService {
         let content = std::fs::read_to_string(path)?;
         let config: TaskConfig = serde_json::from_str(&content)?;
         
+        for (name, matcher) in config.problem_matchers {
+            self.problem_matchers().register(&name, matcher);
+        }
         for task in config.tasks {
             self.register(task);
         }
@@ -367,45 +485,22 @@ impl TaskService {
         Ok(())
     }
 
-    /// Auto-detect tasks from project
+    /// Auto-detect tasks defined in the project root
     pub fn auto_detect(&self, project_root: &std::path::Path) {
-        // Detect npm tasks
-        let package_json = project_root.join("package.json");
-        if package_json.exists() {
-            if let Ok(content) = std::fs::read_to_string(&package_json) {
-                if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                    if let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) {
-                        for (name, _) in scripts {
-                            self.register(Task::npm(&format!("npm: {}", name), name));
-                        }
-                    }
-                }
-            }
-        }
-
-        // Detect cargo tasks
-        let cargo_toml = project_root.join("Cargo.toml");
-        if cargo_toml.exists() {
-            self.register(Task::cargo("cargo: build", "build", vec![]));
-            self.register(Task::cargo("cargo: check", "check", vec![]));
-            self.register(Task::cargo("cargo: test", "test", vec![]));
-            self.register(Task::cargo("cargo: run", "run", vec![]));
-            self.register(Task::cargo("cargo: clippy", "clippy", vec![]));
+        for task in self.providers.provide(project_root) {
+            self.register(task);
         }
+    }
 
-        // Detect Makefile tasks
-        let makefile = project_root.join("Makefile");
-        if makefile.exists() {
-            if let Ok(content) = std::fs::read_to_string(&makefile) {
-                for line in content.lines() {
-                    if let Some(target) = line.strip_suffix(':') {
-                        if !target.starts_with('.') && !target.contains(' ') {
-                            self.register(Task::shell(&format!("make: {}", target), &format!("make {}", target)));
-                        }
-                    }
-                }
-            }
+    /// Auto-detect tasks in the workspace root and each of its packages,
+    /// returning how many were found
+    pub async fn auto_detect_workspace(&self, root: &std::path::Path) -> anyhow::Result<usize> {
+        let tasks = self.providers.detect(root).await?;
+        let count = tasks.len();
+        for task in tasks {
+            self.register(task);
         }
+        Ok(count)
     }
 }
 
@@ -0,0 +1,1063 @@
+//! Problem matchers
+//!
+//! Turns task output into problems using the VS Code problem matcher format:
+//! regex patterns (optionally spanning several lines with a looping last
+//! pattern) and background begin/end markers for watch tasks. Matchers with
+//! the `cargo-json` format additionally understand rustc diagnostics emitted
+//! by `--message-format=json`.
+//!
+//! Reported lines and columns are 1-based, as printed by the tools.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use parking_lot::{Mutex, RwLock};
+use regex::{Captures, Regex};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use problems::{ProblemCode, ProblemItem, ProblemLocation, ProblemSeverity, ProblemsService, RelatedInformation};
+
+/// A problem matcher definition.
+///
+/// Unset fields are inherited from `base` when it names another matcher.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemMatcher {
+    /// Matcher this one extends (e.g. `$tsc`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Owner of the reported problems; a re-run clears the owner's problems.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub owner: Option<String>,
+    /// Source shown with each problem, defaults to the owner.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    /// Severity used when the pattern doesn't capture one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<String>,
+    /// How captured file names are resolved.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file_location: Option<FileLocation>,
+    /// Line patterns; each must match the line after the previous one.
+    #[serde(default, deserialize_with = "one_or_many", skip_serializing_if = "Vec::is_empty")]
+    pub pattern: Vec<ProblemPattern>,
+    /// Markers delimiting a compile cycle of a watch task.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub background: Option<BackgroundMatcher>,
+    /// Structured output understood besides the patterns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<OutputFormat>,
+}
+
+impl ProblemMatcher {
+    /// Create a matcher with a single pattern.
+    pub fn new(owner: &str, pattern: ProblemPattern) -> Self {
+        Self {
+            owner: Some(owner.to_string()),
+            pattern: vec![pattern],
+            ..Default::default()
+        }
+    }
+
+    /// Add a pattern matching the line after the previous pattern.
+    pub fn then(mut self, pattern: ProblemPattern) -> Self {
+        self.pattern.push(pattern);
+        self
+    }
+
+    /// Set how file names are resolved.
+    pub fn with_file_location(mut self, location: FileLocation) -> Self {
+        self.file_location = Some(location);
+        self
+    }
+
+    /// Set the default severity.
+    pub fn with_severity(mut self, severity: &str) -> Self {
+        self.severity = Some(severity.to_string());
+        self
+    }
+
+    /// Set the background markers.
+    pub fn with_background(mut self, begins: &str, ends: &str, active_begins: bool) -> Self {
+        self.background = Some(BackgroundMatcher {
+            active_begins,
+            begins_pattern: begins.to_string(),
+            ends_pattern: ends.to_string(),
+        });
+        self
+    }
+
+    /// Set the structured output format.
+    pub fn with_format(mut self, format: OutputFormat) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Fill unset fields from `base`.
+    fn inherit(mut self, base: &ProblemMatcher) -> Self {
+        self.owner = self.owner.or_else(|| base.owner.clone());
+        self.source = self.source.or_else(|| base.source.clone());
+        self.severity = self.severity.or_else(|| base.severity.clone());
+        self.file_location = self.file_location.or_else(|| base.file_location.clone());
+        if self.pattern.is_empty() {
+            self.pattern = base.pattern.clone();
+        }
+        self.background = self.background.or_else(|| base.background.clone());
+        self.format = self.format.or(base.format);
+        self
+    }
+}
+
+/// One line pattern of a problem matcher.
+///
+/// The numeric fields are capture group indices.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemPattern {
+    /// Regular expression matched against a line
+    pub regexp: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<usize>,
+    /// Group holding `line`, `line,column` or `line,column,endLine,endColumn`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub location: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_line: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end_column: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub severity: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<usize>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<usize>,
+    /// Keep matching this (last) pattern, one problem per line
+    #[serde(default, rename = "loop")]
+    pub looping: bool,
+}
+
+impl ProblemPattern {
+    pub fn new(regexp: &str) -> Self {
+        Self {
+            regexp: regexp.to_string(),
+            ..Default::default()
+        }
+    }
+
+    pub fn file(mut self, group: usize) -> Self {
+        self.file = Some(group);
+        self
+    }
+
+    pub fn line(mut self, group: usize) -> Self {
+        self.line = Some(group);
+        self
+    }
+
+    pub fn column(mut self, group: usize) -> Self {
+        self.column = Some(group);
+        self
+    }
+
+    pub fn severity(mut self, group: usize) -> Self {
+        self.severity = Some(group);
+        self
+    }
+
+    pub fn code(mut self, group: usize) -> Self {
+        self.code = Some(group);
+        self
+    }
+
+    pub fn message(mut self, group: usize) -> Self {
+        self.message = Some(group);
+        self
+    }
+
+    pub fn looping(mut self) -> Self {
+        self.looping = true;
+        self
+    }
+}
+
+/// How file names captured by a pattern are turned into paths.
+///
+/// Serialized as in VS Code: `"absolute"`, `"relative"`, `"autoDetect"` or
+/// `["relative", "<base>"]`. Bases may use `${workspaceFolder}` or `${cwd}`,
+/// both of which resolve to the task's working directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileLocation {
+    Absolute,
+    Relative(Option<String>),
+    AutoDetect(Option<String>),
+}
+
+impl Default for FileLocation {
+    fn default() -> Self {
+        Self::Relative(None)
+    }
+}
+
+impl FileLocation {
+    /// Resolve a captured file name against the task's working directory.
+    pub fn resolve(&self, file: &str, cwd: &Path) -> PathBuf {
+        let path = Path::new(file);
+        let base = |base: &Option<String>| match base {
+            Some(base) => PathBuf::from(
+                base.replace("${workspaceFolder}", &cwd.to_string_lossy())
+                    .replace("${cwd}", &cwd.to_string_lossy()),
+            ),
+            None => cwd.to_path_buf(),
+        };
+        match self {
+            Self::Absolute => path.to_path_buf(),
+            Self::Relative(b) => base(b).join(path),
+            Self::AutoDetect(b) => {
+                if path.is_absolute() {
+                    return path.to_path_buf();
+                }
+                let joined = base(b).join(path);
+                if joined.exists() || !path.exists() {
+                    joined
+                } else {
+                    path.to_path_buf()
+                }
+            }
+        }
+    }
+}
+
+impl Serialize for FileLocation {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Absolute => serializer.serialize_str("absolute"),
+            Self::Relative(None) => serializer.serialize_str("relative"),
+            Self::AutoDetect(None) => serializer.serialize_str("autoDetect"),
+            Self::Relative(Some(base)) => ["relative", base.as_str()].serialize(serializer),
+            Self::AutoDetect(Some(base)) => ["autoDetect", base.as_str()].serialize(serializer),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for FileLocation {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Kind(String),
+            WithBase(Vec<String>),
+        }
+
+        let (kind, base) = match Raw::deserialize(deserializer)? {
+            Raw::Kind(kind) => (kind, None),
+            Raw::WithBase(mut parts) => {
+                if parts.is_empty() {
+                    return Err(serde::de::Error::custom("empty fileLocation"));
+                }
+                let kind = parts.remove(0);
+                (kind, parts.into_iter().next())
+            }
+        };
+        match kind.as_str() {
+            "absolute" => Ok(Self::Absolute),
+            "relative" => Ok(Self::Relative(base)),
+            "autoDetect" => Ok(Self::AutoDetect(base)),
+            other => Err(serde::de::Error::custom(format!("unknown fileLocation: {}", other))),
+        }
+    }
+}
+
+/// Background (watch) markers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackgroundMatcher {
+    /// Whether a cycle is already running when the task starts
+    #[serde(default)]
+    pub active_begins: bool,
+    #[serde(deserialize_with = "regexp_or_string")]
+    pub begins_pattern: String,
+    #[serde(deserialize_with = "regexp_or_string")]
+    pub ends_pattern: String,
+}
+
+/// Structured output formats.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputFormat {
+    /// Plain text, patterns only
+    #[default]
+    Text,
+    /// Cargo/rustc JSON diagnostics (`--message-format=json`)
+    CargoJson,
+}
+
+fn one_or_many<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<ProblemPattern>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(ProblemPattern),
+        Many(Vec<ProblemPattern>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(pattern) => vec![pattern],
+        OneOrMany::Many(patterns) => patterns,
+    })
+}
+
+fn regexp_or_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        String(String),
+        Object { regexp: String },
+    }
+
+    Ok(match Raw::deserialize(deserializer)? {
+        Raw::String(regexp) | Raw::Object { regexp } => regexp,
+    })
+}
+
+/// Parse a severity name as printed by tools.
+pub fn parse_severity(severity: &str) -> Option<ProblemSeverity> {
+    match severity.trim().to_ascii_lowercase().as_str() {
+        "error" | "fatal" | "fatal error" | "error: internal compiler error" => Some(ProblemSeverity::Error),
+        "warning" | "warn" => Some(ProblemSeverity::Warning),
+        "info" | "information" | "note" | "failure-note" => Some(ProblemSeverity::Information),
+        "hint" | "help" => Some(ProblemSeverity::Hint),
+        _ => None,
+    }
+}
+
+/// A matcher with its regular expressions compiled.
+#[derive(Debug)]
+pub struct CompiledMatcher {
+    source: String,
+    severity: Option<ProblemSeverity>,
+    file_location: FileLocation,
+    patterns: Vec<(ProblemPattern, Regex)>,
+    begins: Option<Regex>,
+    ends: Option<Regex>,
+    active_begins: bool,
+    format: OutputFormat,
+}
+
+impl CompiledMatcher {
+    /// Compile a fully resolved matcher.
+    pub fn compile(matcher: &ProblemMatcher) -> anyhow::Result<Self> {
+        let owner = matcher.owner.clone().unwrap_or_else(|| "tasks".to_string());
+        let patterns = matcher.pattern.iter()
+            .map(|pattern| Ok((pattern.clone(), Regex::new(&pattern.regexp)?)))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        if let Some(pattern) = patterns.iter().rev().skip(1).find(|(p, _)| p.looping) {
+            anyhow::bail!("only the last pattern may loop: {}", pattern.0.regexp);
+        }
+        let (begins, ends, active_begins) = match &matcher.background {
+            Some(background) => (
+                Some(Regex::new(&background.begins_pattern)?),
+                Some(Regex::new(&background.ends_pattern)?),
+                background.active_begins,
+            ),
+            None => (None, None, false),
+        };
+
+        Ok(Self {
+            source: matcher.source.clone().unwrap_or(owner),
+            severity: matcher.severity.as_deref().and_then(parse_severity),
+            file_location: matcher.file_location.clone().unwrap_or_default(),
+            patterns,
+            begins,
+            ends,
+            active_begins,
+            format: matcher.format.unwrap_or_default(),
+        })
+    }
+
+    /// Source attached to reported problems
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Whether the matcher tracks watch cycles
+    pub fn is_background(&self) -> bool {
+        self.begins.is_some()
+    }
+
+    /// Whether a watch cycle is active when the task starts
+    pub fn active_begins(&self) -> bool {
+        self.active_begins
+    }
+
+    pub fn format(&self) -> OutputFormat {
+        self.format
+    }
+}
+
+/// A problem attributed to a file.
+#[derive(Debug, Clone)]
+pub struct MatchedProblem {
+    pub file: PathBuf,
+    pub item: ProblemItem,
+}
+
+/// Something recognised in a line of output.
+#[derive(Debug, Clone)]
+pub enum MatcherOutput {
+    Problem(Box<MatchedProblem>),
+    /// A watch cycle started; problems of the previous cycle are stale
+    BackgroundBegan,
+    /// A watch cycle finished
+    BackgroundEnded,
+}
+
+#[derive(Debug, Clone, Default)]
+struct ProblemData {
+    file: Option<String>,
+    line: Option<u32>,
+    column: Option<u32>,
+    end_line: Option<u32>,
+    end_column: Option<u32>,
+    severity: Option<String>,
+    code: Option<String>,
+    message: Option<String>,
+}
+
+impl ProblemData {
+    fn fill(&mut self, pattern: &ProblemPattern, caps: &Captures) {
+        let text = |group: Option<usize>| {
+            group
+                .and_then(|g| caps.get(g))
+                .map(|m| m.as_str().trim())
+                .filter(|s| !s.is_empty())
+        };
+        let number = |group: Option<usize>| text(group).and_then(|s| s.parse().ok());
+
+        if let Some(file) = text(pattern.file) {
+            self.file = Some(file.to_string());
+        }
+        if let Some(location) = text(pattern.location) {
+            let parts: Vec<u32> = location.split(',').filter_map(|p| p.trim().parse().ok()).collect();
+            self.line = parts.first().copied();
+            self.column = parts.get(1).copied();
+            self.end_line = parts.get(2).copied();
+            self.end_column = parts.get(3).copied();
+        }
+        if let Some(line) = number(pattern.line) {
+            self.line = Some(line);
+        }
+        if let Some(column) = number(pattern.column) {
+            self.column = Some(column);
+        }
+        if let Some(end_line) = number(pattern.end_line) {
+            self.end_line = Some(end_line);
+        }
+        if let Some(end_column) = number(pattern.end_column) {
+            self.end_column = Some(end_column);
+        }
+        if let Some(severity) = text(pattern.severity) {
+            self.severity = Some(severity.to_string());
+        }
+        if let Some(code) = text(pattern.code) {
+            self.code = Some(code.to_string());
+        }
+        if let Some(message) = text(pattern.message) {
+            self.message = Some(message.to_string());
+        }
+    }
+}
+
+/// Streams output lines of one task through a matcher.
+pub struct LineMatcher {
+    matcher: Arc<CompiledMatcher>,
+    cwd: PathBuf,
+    /// Index of the pattern the next line must match
+    index: usize,
+    /// Data captured by the patterns before `index`
+    data: ProblemData,
+}
+
+impl LineMatcher {
+    pub fn new(matcher: Arc<CompiledMatcher>, cwd: PathBuf) -> Self {
+        Self {
+            matcher,
+            cwd,
+            index: 0,
+            data: ProblemData::default(),
+        }
+    }
+
+    pub fn matcher(&self) -> &Arc<CompiledMatcher> {
+        &self.matcher
+    }
+
+    /// Feed one line of output (without its newline).
+    pub fn feed(&mut self, line: &str) -> Vec<MatcherOutput> {
+        let line = strip_ansi(line);
+        let line = line.trim_end_matches('\r');
+
+        if let Some(begins) = &self.matcher.begins
+            && begins.is_match(line)
+        {
+            self.reset();
+            return vec![MatcherOutput::BackgroundBegan];
+        }
+        if let Some(ends) = &self.matcher.ends
+            && ends.is_match(line)
+        {
+            self.reset();
+            return vec![MatcherOutput::BackgroundEnded];
+        }
+
+        if self.matcher.format == OutputFormat::CargoJson
+            && line.starts_with('{')
+            && let Some(problems) = self.cargo_problems(line)
+        {
+            return problems.into_iter().map(|p| MatcherOutput::Problem(Box::new(p))).collect();
+        }
+
+        self.match_patterns(line)
+            .and_then(|data| self.problem(data))
+            .map(|p| MatcherOutput::Problem(Box::new(p)))
+            .into_iter()
+            .collect()
+    }
+
+    fn reset(&mut self) {
+        self.index = 0;
+        self.data = ProblemData::default();
+    }
+
+    fn match_patterns(&mut self, line: &str) -> Option<ProblemData> {
+        let matcher = self.matcher.clone();
+        let patterns = &matcher.patterns;
+        if patterns.is_empty() {
+            return None;
+        }
+
+        if self.index > 0 {
+            let (pattern, regex) = &patterns[self.index];
+            if let Some(caps) = regex.captures(line) {
+                let mut data = self.data.clone();
+                data.fill(pattern, &caps);
+                if self.index + 1 < patterns.len() {
+                    self.data = data;
+                    self.index += 1;
+                    return None;
+                }
+                if !pattern.looping {
+                    self.reset();
+                }
+                return Some(data);
+            }
+            // The sequence broke; the line may start a new one
+            self.reset();
+        }
+
+        let (pattern, regex) = &patterns[0];
+        let caps = regex.captures(line)?;
+        let mut data = ProblemData::default();
+        data.fill(pattern, &caps);
+        if patterns.len() == 1 {
+            return Some(data);
+        }
+        self.data = data;
+        self.index = 1;
+        None
+    }
+
+    fn problem(&self, data: ProblemData) -> Option<MatchedProblem> {
+        let file = data.file?;
+        let message = data.message?;
+        let severity = data.severity.as_deref()
+            .and_then(parse_severity)
+            .or(self.matcher.severity)
+            .unwrap_or(ProblemSeverity::Error);
+
+        let mut location = ProblemLocation::new(data.line.unwrap_or(1), data.column.unwrap_or(1));
+        if let Some(end_line) = data.end_line {
+            location = location.with_end(end_line, data.end_column.unwrap_or(u32::MAX));
+        }
+
+        Some(MatchedProblem {
+            file: self.matcher.file_location.resolve(&file, &self.cwd),
+            item: ProblemItem {
+                severity,
+                message,
+                source: Some(self.matcher.source.clone()),
+                code: data.code.map(ProblemCode::String),
+                location,
+                related: Vec::new(),
+                tags: Vec::new(),
+                owner: None,
+            },
+        })
+    }
+
+    /// Problems from a cargo or rustc JSON line, `None` if it isn't one.
+    fn cargo_problems(&self, line: &str) -> Option<Vec<MatchedProblem>> {
+        let message: CargoMessage = serde_json::from_str(line).ok()?;
+        let (diagnostic, manifest) = match message {
+            CargoMessage::Cargo { reason, message, manifest_path } => {
+                if reason != "compiler-message" {
+                    return Some(Vec::new());
+                }
+                (message?, manifest_path)
+            }
+            CargoMessage::Rustc(diagnostic) => (diagnostic, None),
+        };
+
+        let roots = cargo_roots(&self.cwd, manifest.as_deref());
+        let Some(span) = diagnostic.spans.iter().find(|s| s.is_primary) else {
+            return Some(Vec::new());
+        };
+
+        let mut message = diagnostic.message.clone();
+        let mut related = Vec::new();
+        for child in &diagnostic.children {
+            match child.spans.iter().find(|s| s.is_primary).or(child.spans.first()) {
+                Some(child_span) => related.push(RelatedInformation {
+                    file: resolve_cargo_file(&roots, &child_span.file_name),
+                    location: child_span.location(),
+                    message: child.message.clone(),
+                }),
+                None => {
+                    message.push('\n');
+                    message.push_str(&child.level);
+                    message.push_str(": ");
+                    message.push_str(&child.message);
+                }
+            }
+        }
+
+        Some(vec![MatchedProblem {
+            file: resolve_cargo_file(&roots, &span.file_name),
+            item: ProblemItem {
+                severity: parse_severity(&diagnostic.level).unwrap_or(ProblemSeverity::Error),
+                message,
+                source: Some(self.matcher.source.clone()),
+                code: diagnostic.code.map(|code| ProblemCode::String(code.code)),
+                location: span.location(),
+                related,
+                tags: Vec::new(),
+                owner: None,
+            },
+        }])
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum CargoMessage {
+    Cargo {
+        reason: String,
+        message: Option<RustcDiagnostic>,
+        manifest_path: Option<PathBuf>,
+    },
+    Rustc(RustcDiagnostic),
+}
+
+#[derive(Deserialize)]
+struct RustcDiagnostic {
+    message: String,
+    code: Option<RustcCode>,
+    level: String,
+    #[serde(default)]
+    spans: Vec<RustcSpan>,
+    #[serde(default)]
+    children: Vec<RustcDiagnostic>,
+}
+
+#[derive(Deserialize)]
+struct RustcCode {
+    code: String,
+}
+
+#[derive(Deserialize)]
+struct RustcSpan {
+    file_name: String,
+    line_start: u32,
+    line_end: u32,
+    column_start: u32,
+    column_end: u32,
+    is_primary: bool,
+}
+
+impl RustcSpan {
+    fn location(&self) -> ProblemLocation {
+        ProblemLocation::new(self.line_start, self.column_start).with_end(self.line_end, self.column_end)
+    }
+}
+
+/// Directories rustc paths may be relative to: the working directory, then
+/// the package and its ancestors (the workspace root is among them).
+fn cargo_roots(cwd: &Path, manifest: Option<&Path>) -> Vec<PathBuf> {
+    let mut roots = vec![cwd.to_path_buf()];
+    if let Some(dir) = manifest.and_then(Path::parent) {
+        roots.extend(dir.ancestors().map(Path::to_path_buf));
+    }
+    roots
+}
+
+fn resolve_cargo_file(roots: &[PathBuf], file: &str) -> PathBuf {
+    let path = Path::new(file);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    roots.iter()
+        .map(|root| root.join(path))
+        .find(|candidate| candidate.exists())
+        .unwrap_or_else(|| roots[0].join(path))
+}
+
+fn strip_ansi(line: &str) -> std::borrow::Cow<'_, str> {
+    static ANSI: OnceLock<Regex> = OnceLock::new();
+    if !line.contains('\x1b') {
+        return line.into();
+    }
+    ANSI.get_or_init(|| Regex::new(r"\x1b(?:\[[0-?]*[ -/]*[@-~]|\][^\x07\x1b]*(?:\x07|\x1b\\)|[@-Z\\-_])").unwrap())
+        .replace_all(line, "")
+}
+
+/// Named problem matchers, including the built-in `$` ones.
+pub struct ProblemMatcherRegistry {
+    matchers: RwLock<HashMap<String, ProblemMatcher>>,
+    compiled: RwLock<HashMap<String, Arc<CompiledMatcher>>>,
+}
+
+impl ProblemMatcherRegistry {
+    pub fn new() -> Self {
+        Self {
+            matchers: RwLock::new(builtin_matchers().into_iter().collect()),
+            compiled: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register (or replace) a named matcher
+    pub fn register(&self, name: &str, matcher: ProblemMatcher) {
+        self.matchers.write().insert(name.to_string(), matcher);
+        self.compiled.write().clear();
+    }
+
+    /// Look up a matcher, accepting names with or without the `$` prefix
+    pub fn get(&self, name: &str) -> Option<ProblemMatcher> {
+        let matchers = self.matchers.read();
+        matchers.get(name)
+            .or_else(|| match name.strip_prefix('$') {
+                Some(bare) => matchers.get(bare),
+                None => matchers.get(&format!("${}", name)),
+            })
+            .cloned()
+    }
+
+    /// Registered matcher names
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.matchers.read().keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// Resolve `base` chains of a matcher
+    pub fn resolve(&self, matcher: &ProblemMatcher) -> anyhow::Result<ProblemMatcher> {
+        let mut resolved = matcher.clone();
+        let mut seen = HashSet::new();
+        while let Some(base) = resolved.base.take() {
+            if !seen.insert(base.clone()) {
+                anyhow::bail!("problem matcher base cycle at {}", base);
+            }
+            let parent = self.get(&base)
+                .ok_or_else(|| anyhow::anyhow!("Unknown problem matcher: {}", base))?;
+            resolved.base = parent.base.clone();
+            resolved = resolved.inherit(&parent);
+        }
+        Ok(resolved)
+    }
+
+    /// Compile a named matcher, caching the result
+    pub fn compile(&self, name: &str) -> anyhow::Result<Arc<CompiledMatcher>> {
+        if let Some(compiled) = self.compiled.read().get(name) {
+            return Ok(compiled.clone());
+        }
+        let matcher = self.get(name)
+            .ok_or_else(|| anyhow::anyhow!("Unknown problem matcher: {}", name))?;
+        let compiled = Arc::new(CompiledMatcher::compile(&self.resolve(&matcher)?)?);
+        self.compiled.write().insert(name.to_string(), compiled.clone());
+        Ok(compiled)
+    }
+}
+
+impl Default for ProblemMatcherRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Built-in matchers for common toolchains.
+pub fn builtin_matchers() -> Vec<(String, ProblemMatcher)> {
+    let rustc = ProblemMatcher::new(
+        "rustc",
+        ProblemPattern::new(r"^(warning|error)(?:\[(\w+)\])?: (.*)$").severity(1).code(2).message(3),
+    )
+    .then(ProblemPattern::new(r"^\s*--> (.*?):(\d+):(\d+)$").file(1).line(2).column(3))
+    .with_file_location(FileLocation::AutoDetect(None))
+    .with_format(OutputFormat::CargoJson);
+
+    let tsc = ProblemMatcher::new(
+        "typescript",
+        ProblemPattern::new(r"^([^\s].*?)[\(:](\d+)[,:](\d+)(?:\):\s+|\s+-\s+)(error|warning|info)\s+(TS\d+)\s*:\s*(.*)$")
+            .file(1).line(2).column(3).severity(4).code(5).message(6),
+    )
+    .with_file_location(FileLocation::Relative(None));
+
+    let tsc_watch = ProblemMatcher {
+        base: Some("$tsc".to_string()),
+        ..Default::default()
+    }
+    .with_background(
+        r"(?:Starting compilation in watch mode|File change detected\. Starting incremental compilation)\.\.\.",
+        r"(?:Compilation complete\.|Found \d+ errors?\.) Watching for file changes\.",
+        true,
+    );
+
+    let eslint_stylish = ProblemMatcher::new("eslint", ProblemPattern::new(r"^([^\s].*)$").file(1))
+        .then(
+            ProblemPattern::new(r"^\s+(\d+):(\d+)\s+(error|warning|info)\s+(.*?)(?:\s\s+(\S+))?$")
+                .line(1).column(2).severity(3).message(4).code(5).looping(),
+        )
+        .with_file_location(FileLocation::AutoDetect(None));
+
+    let eslint_compact = ProblemMatcher::new(
+        "eslint",
+        ProblemPattern::new(r"^(.+?):\sline\s(\d+),\scol\s(\d+),\s(Error|Warning|Info)\s-\s(.+?)(?:\s\((\S+)\))?$")
+            .file(1).line(2).column(3).severity(4).message(5).code(6),
+    )
+    .with_file_location(FileLocation::AutoDetect(None));
+
+    let gcc = ProblemMatcher::new(
+        "cpp",
+        ProblemPattern::new(r"^(.*?):(\d+):(\d*):?\s+(?:fatal\s+)?(warning|error):\s+(.*)$")
+            .file(1).line(2).column(3).severity(4).message(5),
+    )
+    .with_file_location(FileLocation::AutoDetect(None));
+
+    let go = ProblemMatcher::new(
+        "go",
+        ProblemPattern::new(r"^\s*(?:vet: )?((?:[A-Za-z]:)?[^:\s][^:]*\.go):(\d+)(?::(\d+))?:\s+(.*)$")
+            .file(1).line(2).column(3).message(4),
+    )
+    .with_file_location(FileLocation::AutoDetect(None));
+
+    let pytest = ProblemMatcher::new(
+        "pytest",
+        ProblemPattern::new(r"^([^\s:][^:]*\.py):(\d+):\s+((?:\w+\.)*\w*(?:Error|Exception|Failed)\b.*)$")
+            .file(1).line(2).message(3),
+    )
+    .with_severity("error")
+    .with_file_location(FileLocation::AutoDetect(None));
+
+    vec![
+        ("$rustc".to_string(), rustc.clone()),
+        ("$cargo".to_string(), rustc),
+        ("$tsc".to_string(), tsc),
+        ("$tsc-watch".to_string(), tsc_watch),
+        ("$eslint-stylish".to_string(), eslint_stylish),
+        ("$eslint-compact".to_string(), eslint_compact),
+        ("$gcc".to_string(), gcc),
+        ("$go".to_string(), go),
+        ("$pytest".to_string(), pytest),
+    ]
+}
+
+/// Streams matched problems of one task run into the problems panel.
+///
+/// Reported items are tagged with an owner, usually the task. Creating a
+/// reporter clears the owner's problems from its previous run, as does the
+/// start of each watch cycle; problems of other owners with the same
+/// source (other tasks, language servers) are kept.
+pub struct ProblemReporter {
+    service: Arc<ProblemsService>,
+    owner: String,
+    seen: Mutex<HashSet<(PathBuf, u32, u32, String)>>,
+}
+
+impl ProblemReporter {
+    pub fn new(service: Arc<ProblemsService>, owner: impl Into<String>) -> Self {
+        let owner = owner.into();
+        service.clear_owner(&owner);
+        Self {
+            service,
+            owner,
+            seen: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Report a problem, ignoring duplicates (cargo reports some per target)
+    pub fn report(&self, problem: MatchedProblem) {
+        let key = (
+            problem.file.clone(),
+            problem.item.location.line,
+            problem.item.location.column,
+            problem.item.message.clone(),
+        );
+        if !self.seen.lock().insert(key) {
+            return;
+        }
+        self.service.add_diagnostics(problem.file, vec![problem.item.with_owner(self.owner.clone())]);
+    }
+
+    /// Drop the problems reported so far
+    pub fn restart(&self) {
+        self.seen.lock().clear();
+        self.service.clear_owner(&self.owner);
+    }
+
+    /// Number of distinct problems reported since the last restart
+    pub fn count(&self) -> usize {
+        self.seen.lock().len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn problems(registry: &ProblemMatcherRegistry, matcher: &str, cwd: &Path, lines: &[&str]) -> Vec<MatchedProblem> {
+        let mut matcher = LineMatcher::new(registry.compile(matcher).unwrap(), cwd.to_path_buf());
+        lines.iter()
+            .flat_map(|line| matcher.feed(line))
+            .filter_map(|output| match output {
+                MatcherOutput::Problem(problem) => Some(*problem),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_single_and_looping_patterns() {
+        let registry = ProblemMatcherRegistry::new();
+        let cwd = Path::new("/work");
+        let found = problems(&registry, "$tsc", cwd, &[
+            "src/app.ts(3,7): error TS2304: Cannot find name 'foo'.",
+            "\x1b[96msrc/b.ts\x1b[0m:10:2 - warning TS6133: 'x' is declared but never read.",
+        ]);
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].file, PathBuf::from("/work/src/app.ts"));
+        assert_eq!(found[0].item.location.line, 3);
+        assert_eq!(found[0].item.location.column, 7);
+        assert_eq!(found[1].item.severity, ProblemSeverity::Warning);
+        assert!(matches!(&found[1].item.code, Some(ProblemCode::String(c)) if c == "TS6133"));
+
+        let found = problems(&registry, "$eslint-stylish", cwd, &[
+            "/work/src/a.js",
+            "  1:10  error    'x' is defined but never used  no-unused-vars",
+            "  2:1   warning  Unexpected console statement   no-console",
+            "",
+            "/work/src/b.js",
+            "  5:3  error  Missing semicolon  semi",
+            "",
+            "✖ 3 problems (2 errors, 1 warning)",
+        ]);
+        assert_eq!(found.len(), 3);
+        assert_eq!(found[1].file, PathBuf::from("/work/src/a.js"));
+        assert_eq!(found[1].item.message, "Unexpected console statement");
+        assert_eq!(found[2].file, PathBuf::from("/work/src/b.js"));
+        assert_eq!(found[2].item.location.line, 5);
+    }
+
+    #[test]
+    fn test_multiline_rustc_text() {
+        let registry = ProblemMatcherRegistry::new();
+        let found = problems(&registry, "$rustc", Path::new("/work"), &[
+            "error[E0425]: cannot find value `y` in this scope",
+            " --> src/main.rs:2:13",
+            "warning: unused variable: `x`",
+            "  |",
+            "warning: `demo` (bin \"demo\") generated 1 warning",
+        ]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file, PathBuf::from("/work/src/main.rs"));
+        assert_eq!(found[0].item.location.line, 2);
+        assert!(matches!(&found[0].item.code, Some(ProblemCode::String(c)) if c == "E0425"));
+    }
+
+    #[test]
+    fn test_cargo_json() {
+        let line = r#"{"reason":"compiler-message","package_id":"demo","manifest_path":"/nowhere/demo/Cargo.toml","message":{"message":"unused variable: `x`","code":{"code":"unused_variables","explanation":null},"level":"warning","spans":[{"file_name":"src/lib.rs","byte_start":0,"byte_end":1,"line_start":4,"line_end":4,"column_start":9,"column_end":10,"is_primary":true,"text":[],"label":null,"suggested_replacement":null,"suggestion_applicability":null,"expansion":null}],"children":[{"message":"`#[warn(unused_variables)]` on by default","code":null,"level":"note","spans":[],"children":[],"rendered":null}],"rendered":"warning: unused variable"}}"#;
+        let registry = ProblemMatcherRegistry::new();
+        let found = problems(&registry, "$rustc", Path::new("/work"), &[
+            line,
+            r#"{"reason":"build-finished","success":true}"#,
+        ]);
+        assert_eq!(found.len(), 1);
+        let problem = &found[0];
+        assert_eq!(problem.file, PathBuf::from("/work/src/lib.rs"));
+        assert_eq!(problem.item.severity, ProblemSeverity::Warning);
+        assert_eq!(problem.item.location.column, 9);
+        assert_eq!(problem.item.location.end_column, Some(10));
+        assert!(problem.item.message.ends_with("note: `#[warn(unused_variables)]` on by default"));
+    }
+
+    #[test]
+    fn test_background_and_reporter() {
+        let registry = ProblemMatcherRegistry::new();
+        let compiled = registry.compile("$tsc-watch").unwrap();
+        assert!(compiled.is_background());
+        assert_eq!(compiled.source(), "typescript");
+
+        let service = Arc::new(ProblemsService::new());
+        // Problems of another owner with the same source survive restarts
+        let other = ProblemItem::error("from the language server", ProblemLocation::new(1, 1))
+            .with_source("typescript");
+        service.set_diagnostics(PathBuf::from("/work/src/a.ts"), vec![other]);
+
+        let reporter = ProblemReporter::new(service.clone(), "task:watch");
+        let mut matcher = LineMatcher::new(compiled, PathBuf::from("/work"));
+
+        let mut began = 0;
+        let mut ended = 0;
+        for line in [
+            "[12:00:00 PM] Starting compilation in watch mode...",
+            "src/a.ts(1,1): error TS1005: ';' expected.",
+            "src/a.ts(1,1): error TS1005: ';' expected.",
+            "[12:00:01 PM] Found 1 error. Watching for file changes.",
+            "[12:00:05 PM] File change detected. Starting incremental compilation...",
+        ] {
+            for output in matcher.feed(line) {
+                match output {
+                    MatcherOutput::Problem(problem) => reporter.report(*problem),
+                    MatcherOutput::BackgroundBegan => {
+                        began += 1;
+                        reporter.restart();
+                    }
+                    MatcherOutput::BackgroundEnded => {
+                        ended += 1;
+                        assert_eq!(service.stats().errors, 2);
+                    }
+                }
+            }
+        }
+        assert_eq!((began, ended), (2, 1));
+        assert_eq!(service.stats().total(), 1);
+    }
+
+    #[test]
+    fn test_custom_matcher_json() {
+        let json = r#"{
+            "base": "$gcc",
+            "owner": "mycc",
+            "fileLocation": ["relative", "${workspaceFolder}/build"],
+            "pattern": {"regexp": "^(.*)@(\\d+,\\d+): (.*)$", "file": 1, "location": 2, "message": 3}
+        }"#;
+        let matcher: ProblemMatcher = serde_json::from_str(json).unwrap();
+        let registry = ProblemMatcherRegistry::new();
+        registry.register("mycc", matcher);
+
+        let found = problems(&registry, "$mycc", Path::new("/work"), &["x.c@4,2: boom"]);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].file, PathBuf::from("/work/build/x.c"));
+        assert_eq!(found[0].item.source.as_deref(), Some("mycc"));
+        assert_eq!((found[0].item.location.line, found[0].item.location.column), (4, 2));
+    }
+}
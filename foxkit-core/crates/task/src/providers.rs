@@ -0,0 +1,688 @@
+//! Task providers
+//!
+//! Enumerate the tasks a project already defines: package.json scripts,
+//! Cargo commands, targets and aliases, Makefile targets, justfile recipes,
+//! `deno.json` tasks, Gradle and Maven goals and pyproject scripts. Across a
+//! monorepo every package directory is asked, and its tasks are tagged with
+//! the package so pickers can group them.
+
+use std::collections::{BTreeMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use parking_lot::RwLock;
+
+use crate::{Task, TaskGroup};
+
+/// Finds the tasks one build tool defines in a directory
+pub trait TaskProvider: Send + Sync {
+    /// Provider ID, also the prefix of task names (e.g. "npm")
+    fn id(&self) -> &str;
+
+    /// Tasks defined in `dir`, running there
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>>;
+}
+
+/// Registered task providers
+pub struct TaskProviderRegistry {
+    providers: RwLock<Vec<Arc<dyn TaskProvider>>>,
+}
+
+impl TaskProviderRegistry {
+    /// Registry with the built-in providers
+    pub fn new() -> Self {
+        Self {
+            providers: RwLock::new(builtin_providers()),
+        }
+    }
+
+    /// Register a provider, replacing one with the same ID
+    pub fn register(&self, provider: impl TaskProvider + 'static) {
+        let mut providers = self.providers.write();
+        providers.retain(|p| p.id() != provider.id());
+        providers.push(Arc::new(provider));
+    }
+
+    /// Registered provider IDs
+    pub fn ids(&self) -> Vec<String> {
+        self.providers.read().iter().map(|p| p.id().to_string()).collect()
+    }
+
+    /// Tasks of every provider in `dir`
+    pub fn provide(&self, dir: &Path) -> Vec<Task> {
+        let providers = self.providers.read().clone();
+        let mut tasks = Vec::new();
+        for provider in providers {
+            match provider.provide(dir) {
+                Ok(found) => tasks.extend(found.into_iter().map(|task| task.in_dir(dir.to_path_buf()))),
+                Err(e) => tracing::warn!("Task provider {} failed in {:?}: {}", provider.id(), dir, e),
+            }
+        }
+        tasks
+    }
+
+    /// Tasks of the workspace at `root` and every package in it
+    ///
+    /// Tasks are tagged with the package they belong to. Names of tasks in
+    /// packages below the root get the package name appended so they stay
+    /// unique.
+    pub async fn detect(&self, root: &Path) -> anyhow::Result<Vec<Task>> {
+        let packages = monorepo::detector::detect_packages(root).await?;
+
+        let mut dirs: Vec<(PathBuf, Option<String>)> = vec![(
+            root.to_path_buf(),
+            packages.iter().find(|p| p.path == root).map(|p| p.name.clone()),
+        )];
+        let mut seen: HashSet<PathBuf> = HashSet::from([root.to_path_buf()]);
+        for package in &packages {
+            if seen.insert(package.path.clone()) {
+                dirs.push((package.path.clone(), Some(package.name.clone())));
+            }
+        }
+
+        let mut tasks = Vec::new();
+        for (i, (dir, package)) in dirs.into_iter().enumerate() {
+            for mut task in self.provide(&dir) {
+                if i > 0
+                    && let Some(package) = &package
+                {
+                    task.name = format!("{} - {}", task.name, package);
+                }
+                task.package = package.clone();
+                tasks.push(task);
+            }
+        }
+        Ok(tasks)
+    }
+}
+
+impl Default for TaskProviderRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The built-in providers
+pub fn builtin_providers() -> Vec<Arc<dyn TaskProvider>> {
+    vec![
+        Arc::new(NpmProvider),
+        Arc::new(CargoProvider),
+        Arc::new(MakeProvider),
+        Arc::new(JustProvider),
+        Arc::new(DenoProvider),
+        Arc::new(GradleProvider),
+        Arc::new(MavenProvider),
+        Arc::new(PyprojectProvider),
+    ]
+}
+
+/// Put well-known task names into the build or test group
+fn grouped(task: Task, name: &str) -> Task {
+    match name {
+        "build" => task.as_build(),
+        "compile" | "all" | "assemble" => {
+            let mut task = task;
+            task.group = Some(TaskGroup::Build { is_default: false });
+            task
+        }
+        "test" => task.as_test(),
+        _ => task,
+    }
+}
+
+/// Owned argument list
+fn owned(args: &[&str]) -> Vec<String> {
+    args.iter().map(|a| a.to_string()).collect()
+}
+
+/// Nearest of `names` in `dir` or one of its ancestors
+fn find_up(dir: &Path, names: &[&str]) -> Option<PathBuf> {
+    dir.ancestors()
+        .flat_map(|d| names.iter().map(move |name| d.join(name)))
+        .find(|path| path.exists())
+}
+
+/// Scripts from `package.json`, run with the workspace's package manager
+pub struct NpmProvider;
+
+impl NpmProvider {
+    /// Package manager of the workspace containing `dir`
+    pub(crate) fn package_manager(dir: &Path, json: &serde_json::Value) -> &'static str {
+        // `"packageManager": "pnpm@9.1.0"` takes precedence over lockfiles
+        if let Some(pm) = json.get("packageManager").and_then(|v| v.as_str()) {
+            for name in ["pnpm", "yarn", "bun", "npm"] {
+                if pm.starts_with(&format!("{}@", name)) {
+                    return name;
+                }
+            }
+        }
+        for d in dir.ancestors() {
+            if d.join("pnpm-lock.yaml").exists() || d.join("pnpm-workspace.yaml").exists() {
+                return "pnpm";
+            }
+            if d.join("yarn.lock").exists() {
+                return "yarn";
+            }
+            if d.join("bun.lockb").exists() || d.join("bun.lock").exists() {
+                return "bun";
+            }
+            if d.join("package-lock.json").exists() {
+                return "npm";
+            }
+        }
+        "npm"
+    }
+}
+
+impl TaskProvider for NpmProvider {
+    fn id(&self) -> &str {
+        "npm"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let path = dir.join("package.json");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let json: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let pm = Self::package_manager(dir, &json);
+
+        let Some(scripts) = json.get("scripts").and_then(|s| s.as_object()) else {
+            return Ok(Vec::new());
+        };
+        Ok(scripts.keys()
+            .map(|script| {
+                let task = Task::process(&format!("npm: {}", script), pm, owned(&["run", script]));
+                grouped(task, script)
+            })
+            .collect())
+    }
+}
+
+/// Cargo commands, targets and aliases from `.cargo/config.toml`
+pub struct CargoProvider;
+
+impl CargoProvider {
+    /// Names of a target kind: declared ones plus those found by convention
+    fn targets(manifest: &toml::Value, dir: &Path, kind: &str, auto_dir: &str) -> Vec<String> {
+        let mut names: Vec<String> = manifest.get(kind)
+            .and_then(|t| t.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|t| t.get("name").and_then(|n| n.as_str()).map(str::to_string))
+            .collect();
+
+        if let Ok(entries) = std::fs::read_dir(dir.join(auto_dir)) {
+            let mut found: Vec<String> = entries.flatten()
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    if path.extension().is_some_and(|e| e == "rs") {
+                        path.file_stem().map(|s| s.to_string_lossy().to_string())
+                    } else if path.join("main.rs").exists() {
+                        path.file_name().map(|s| s.to_string_lossy().to_string())
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+            found.sort();
+            names.extend(found);
+        }
+
+        let mut seen = HashSet::new();
+        names.retain(|name| seen.insert(name.clone()));
+        names
+    }
+
+    /// `[alias]` entries of the Cargo config files above `dir`, nearest first
+    fn aliases(dir: &Path) -> BTreeMap<String, String> {
+        let mut aliases = BTreeMap::new();
+        for d in dir.ancestors() {
+            for file in [".cargo/config.toml", ".cargo/config"] {
+                let Ok(content) = std::fs::read_to_string(d.join(file)) else {
+                    continue;
+                };
+                let Ok(config) = toml::from_str::<toml::Value>(&content) else {
+                    tracing::warn!("Invalid Cargo config {:?}", d.join(file));
+                    continue;
+                };
+                let Some(table) = config.get("alias").and_then(|a| a.as_table()) else {
+                    continue;
+                };
+                for (name, value) in table {
+                    let expansion = match value {
+                        toml::Value::String(s) => s.clone(),
+                        toml::Value::Array(parts) => parts.iter()
+                            .filter_map(|p| p.as_str())
+                            .collect::<Vec<_>>()
+                            .join(" "),
+                        _ => continue,
+                    };
+                    aliases.entry(name.clone()).or_insert(expansion);
+                }
+            }
+        }
+        aliases
+    }
+}
+
+impl TaskProvider for CargoProvider {
+    fn id(&self) -> &str {
+        "cargo"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let path = dir.join("Cargo.toml");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let manifest: toml::Value = toml::from_str(&std::fs::read_to_string(path)?)?;
+        let package = manifest.get("package")
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str());
+        // Members of a workspace are selected with `-p`
+        let scope: Vec<String> = match package {
+            Some(name) => vec!["-p".to_string(), name.to_string()],
+            None => Vec::new(),
+        };
+        let args = |extra: &[&str]| {
+            scope.iter().cloned().chain(extra.iter().map(|a| a.to_string())).collect::<Vec<_>>()
+        };
+
+        let mut tasks: Vec<Task> = ["build", "check", "test", "clippy", "doc"].iter()
+            .map(|sub| grouped(Task::cargo(&format!("cargo: {}", sub), sub, args(&[])), sub))
+            .collect();
+
+        if package.is_some() {
+            let mut bins = Self::targets(&manifest, dir, "bin", "src/bin");
+            if dir.join("src/main.rs").exists() {
+                bins.insert(0, package.unwrap_or_default().to_string());
+            }
+            for bin in bins {
+                tasks.push(Task::cargo(&format!("cargo: run --bin {}", bin), "run", args(&["--bin", &bin])));
+            }
+            for example in Self::targets(&manifest, dir, "example", "examples") {
+                tasks.push(Task::cargo(
+                    &format!("cargo: run --example {}", example),
+                    "run",
+                    args(&["--example", &example]),
+                ));
+            }
+            for test in Self::targets(&manifest, dir, "test", "tests") {
+                tasks.push(Task::cargo(&format!("cargo: test --test {}", test), "test", args(&["--test", &test])));
+            }
+            for bench in Self::targets(&manifest, dir, "bench", "benches") {
+                tasks.push(Task::cargo(&format!("cargo: bench --bench {}", bench), "bench", args(&["--bench", &bench])));
+            }
+        }
+
+        for alias in Self::aliases(dir).into_keys() {
+            tasks.push(Task::cargo(&format!("cargo: {}", alias), &alias, Vec::new()));
+        }
+        Ok(tasks)
+    }
+}
+
+/// Targets of a Makefile
+pub struct MakeProvider;
+
+/// Explicit targets in Makefile text, skipping special and pattern rules
+pub fn parse_make_targets(content: &str) -> Vec<String> {
+    let mut targets = Vec::new();
+    let mut seen = HashSet::new();
+
+    for line in content.lines() {
+        if line.starts_with(['\t', ' ', '#']) {
+            continue;
+        }
+        let Some(colon) = line.find(':') else {
+            continue;
+        };
+        // `x := y` and `x ::= y` are assignments
+        if line[colon..].trim_start_matches(':').starts_with('=') {
+            continue;
+        }
+        let names = &line[..colon];
+        if names.contains(['=', '$', '%', '(']) {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            if !name.starts_with('.') && seen.insert(name.to_string()) {
+                targets.push(name.to_string());
+            }
+        }
+    }
+
+    targets
+}
+
+impl TaskProvider for MakeProvider {
+    fn id(&self) -> &str {
+        "make"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let Some(path) = ["GNUmakefile", "makefile", "Makefile"].iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+        else {
+            return Ok(Vec::new());
+        };
+        let content = std::fs::read_to_string(path)?;
+        Ok(parse_make_targets(&content).into_iter()
+            .map(|target| grouped(Task::process(&format!("make: {}", target), "make", owned(&[&target])), &target))
+            .collect())
+    }
+}
+
+/// Recipes of a justfile
+pub struct JustProvider;
+
+/// Public recipe names in justfile text
+pub fn parse_just_recipes(content: &str) -> Vec<String> {
+    const KEYWORDS: &[&str] = &["set", "alias", "export", "import", "mod"];
+    let mut recipes = Vec::new();
+    let mut private = false;
+
+    for line in content.lines() {
+        if line.trim().is_empty() || line.starts_with([' ', '\t', '#']) {
+            continue;
+        }
+        if line.starts_with('[') {
+            private |= line.contains("private");
+            continue;
+        }
+        let header = line.trim_start_matches('@');
+        let name_len = header.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'))
+            .unwrap_or(header.len());
+        let (name, rest) = header.split_at(name_len);
+        let is_private = std::mem::take(&mut private);
+
+        if name.is_empty() || KEYWORDS.contains(&name) && rest.starts_with(' ') {
+            continue;
+        }
+        // Parameters come before the colon; `name := value` is a variable
+        let Some(colon) = rest.find(':') else {
+            continue;
+        };
+        if rest[colon + 1..].starts_with('=') {
+            continue;
+        }
+        if !is_private && !name.starts_with('_') {
+            recipes.push(name.to_string());
+        }
+    }
+
+    recipes
+}
+
+impl TaskProvider for JustProvider {
+    fn id(&self) -> &str {
+        "just"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let Some(path) = ["justfile", "Justfile", ".justfile"].iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+        else {
+            return Ok(Vec::new());
+        };
+        let content = std::fs::read_to_string(path)?;
+        Ok(parse_just_recipes(&content).into_iter()
+            .map(|recipe| grouped(Task::process(&format!("just: {}", recipe), "just", owned(&[&recipe])), &recipe))
+            .collect())
+    }
+}
+
+/// Tasks from `deno.json` or `deno.jsonc`
+pub struct DenoProvider;
+
+/// Remove `//` and `/* */` comments from JSONC, leaving strings alone
+fn strip_json_comments(content: &str) -> String {
+    let mut out = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    let mut in_string = false;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => out.extend(chars.next()),
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match (c, chars.peek()) {
+            ('"', _) => {
+                in_string = true;
+                out.push(c);
+            }
+            ('/', Some('/')) => {
+                while chars.next_if(|&c| c != '\n').is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                let mut prev = ' ';
+                for c in chars.by_ref() {
+                    if prev == '*' && c == '/' {
+                        break;
+                    }
+                    prev = c;
+                }
+            }
+            _ => out.push(c),
+        }
+    }
+
+    out
+}
+
+impl TaskProvider for DenoProvider {
+    fn id(&self) -> &str {
+        "deno"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let Some(path) = ["deno.json", "deno.jsonc"].iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+        else {
+            return Ok(Vec::new());
+        };
+        let json: serde_json::Value = serde_json::from_str(&strip_json_comments(&std::fs::read_to_string(path)?))?;
+        let Some(tasks) = json.get("tasks").and_then(|t| t.as_object()) else {
+            return Ok(Vec::new());
+        };
+        Ok(tasks.keys()
+            .map(|name| grouped(Task::process(&format!("deno: {}", name), "deno", owned(&["task", name])), name))
+            .collect())
+    }
+}
+
+/// Standard Gradle tasks plus those registered in the build script
+pub struct GradleProvider;
+
+impl TaskProvider for GradleProvider {
+    fn id(&self) -> &str {
+        "gradle"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let Some(path) = ["build.gradle.kts", "build.gradle"].iter()
+            .map(|name| dir.join(name))
+            .find(|path| path.exists())
+        else {
+            return Ok(Vec::new());
+        };
+        let script = std::fs::read_to_string(path)?;
+        let gradle = find_up(dir, &["gradlew"])
+            .map(|wrapper| wrapper.to_string_lossy().to_string())
+            .unwrap_or_else(|| "gradle".to_string());
+
+        let mut names: Vec<String> = ["build", "assemble", "test", "check", "clean"].iter()
+            .map(|s| s.to_string())
+            .collect();
+        let registered = regex::Regex::new(
+            r#"(?m)tasks\.(?:register|create)(?:<[^>]*>)?\(\s*["']([\w-]+)["']|^\s*task\s+([A-Za-z_]\w*)"#,
+        )?;
+        for caps in registered.captures_iter(&script) {
+            if let Some(name) = caps.get(1).or_else(|| caps.get(2))
+                && !names.iter().any(|n| n == name.as_str())
+            {
+                names.push(name.as_str().to_string());
+            }
+        }
+
+        Ok(names.into_iter()
+            .map(|name| grouped(Task::process(&format!("gradle: {}", name), &gradle, owned(&[&name])), &name))
+            .collect())
+    }
+}
+
+/// Maven lifecycle phases
+pub struct MavenProvider;
+
+impl TaskProvider for MavenProvider {
+    fn id(&self) -> &str {
+        "maven"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        if !dir.join("pom.xml").exists() {
+            return Ok(Vec::new());
+        }
+        let mvn = find_up(dir, &["mvnw"])
+            .map(|wrapper| wrapper.to_string_lossy().to_string())
+            .unwrap_or_else(|| "mvn".to_string());
+
+        Ok(["clean", "compile", "test", "package", "verify", "install"].iter()
+            .map(|phase| grouped(Task::process(&format!("maven: {}", phase), &mvn, owned(&[phase])), phase))
+            .collect())
+    }
+}
+
+/// Scripts declared in `pyproject.toml`
+pub struct PyprojectProvider;
+
+impl TaskProvider for PyprojectProvider {
+    fn id(&self) -> &str {
+        "python"
+    }
+
+    fn provide(&self, dir: &Path) -> anyhow::Result<Vec<Task>> {
+        let path = dir.join("pyproject.toml");
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let pyproject: toml::Value = toml::from_str(&std::fs::read_to_string(path)?)?;
+        let table = |keys: &[&str]| {
+            keys.iter()
+                .try_fold(&pyproject, |value, key| value.get(key))
+                .and_then(|v| v.as_table())
+                .map(|t| t.keys().cloned().collect::<Vec<_>>())
+                .unwrap_or_default()
+        };
+
+        // Console scripts run inside the project's environment
+        let runner: &[&str] = if pyproject.get("tool").and_then(|t| t.get("poetry")).is_some() {
+            &["poetry", "run"]
+        } else if dir.join("uv.lock").exists() {
+            &["uv", "run"]
+        } else {
+            &[]
+        };
+        let sources: [(&[&str], &[&str]); 5] = [
+            (&["project", "scripts"], runner),
+            (&["tool", "poetry", "scripts"], &["poetry", "run"]),
+            (&["tool", "pdm", "scripts"], &["pdm", "run"]),
+            (&["tool", "hatch", "envs", "default", "scripts"], &["hatch", "run"]),
+            (&["tool", "poe", "tasks"], &["poe"]),
+        ];
+
+        let mut seen = HashSet::new();
+        let mut tasks = Vec::new();
+        for (keys, prefix) in sources {
+            for name in table(keys) {
+                if seen.insert(name.clone()) {
+                    let task = match prefix.split_first() {
+                        Some((command, rest)) => {
+                            let mut rest = owned(rest);
+                            rest.push(name.clone());
+                            Task::process(&format!("python: {}", name), command, rest)
+                        }
+                        None => Task::process(&format!("python: {}", name), &name, Vec::new()),
+                    };
+                    tasks.push(grouped(task, &name));
+                }
+            }
+        }
+        Ok(tasks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_make_and_just() {
+        let makefile = "CC := gcc\nall: build\n.PHONY: all build\nbuild test: deps\n\t$(CC) -o x\n%.o: %.c\n$(OUT): x\n";
+        assert_eq!(parse_make_targets(makefile), vec!["all", "build", "test"]);
+
+        let justfile = "set shell := [\"bash\", \"-c\"]\nversion := \"1\"\nalias b := build\n\n# Build it\nbuild target='debug': fmt\n    cargo build\n@fmt:\n    cargo fmt\n_helper:\n    true\n[private]\nsecret:\n    true\ntest *args:\n    cargo test {{args}}\n";
+        assert_eq!(parse_just_recipes(justfile), vec!["build", "fmt", "test"]);
+
+        assert_eq!(
+            strip_json_comments("{\n  // tasks\n  \"tasks\": {\"dev\": \"deno run http://x\" /* watch */}\n}"),
+            "{\n  \n  \"tasks\": {\"dev\": \"deno run http://x\" }\n}",
+        );
+    }
+
+    #[tokio::test]
+    async fn test_detect_workspace_tasks() {
+        let root = std::env::temp_dir().join(format!("foxkit-task-providers-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let write = |path: &str, content: &str| {
+            let path = root.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+        };
+        write("package.json", r#"{"name": "root", "workspaces": ["packages/*"], "scripts": {"lint": "eslint ."}}"#);
+        write("pnpm-lock.yaml", "");
+        write("packages/ui/package.json", r#"{"name": "@acme/ui", "scripts": {"build": "tsc", "test": "vitest"}}"#);
+        write("tools/cli/Cargo.toml", "[package]\nname = \"acme-cli\"\n\n[[example]]\nname = \"demo\"\n");
+        write("tools/cli/src/main.rs", "fn main() {}");
+        write("tools/cli/src/bin/gen.rs", "fn main() {}");
+        write(".cargo/config.toml", "[alias]\nxtask = \"run -p xtask --\"\n");
+        write("tools/cli/justfile", "release:\n    cargo build --release\n");
+
+        let registry = TaskProviderRegistry::new();
+        let tasks = registry.detect(&root).await.unwrap();
+        let find = |name: &str| tasks.iter()
+            .find(|t| t.name == name)
+            .unwrap_or_else(|| panic!("no task {}", name));
+
+        let lint = find("npm: lint");
+        assert_eq!(lint.command, "pnpm");
+        assert_eq!(lint.args, vec!["run", "lint"]);
+        assert_eq!(lint.package.as_deref(), Some("root"));
+        assert_eq!(lint.cwd.as_deref(), Some(root.as_path()));
+
+        let build = find("npm: build - @acme/ui");
+        assert_eq!(build.args, vec!["run", "build"]);
+        assert_eq!(build.problem_matcher, None);
+        assert!(matches!(build.group, Some(TaskGroup::Build { is_default: true })));
+        assert_eq!(build.cwd, Some(root.join("packages/ui")));
+
+        let bin = find("cargo: run --bin gen - acme-cli");
+        assert_eq!(bin.args, vec!["-p", "acme-cli", "--bin", "gen"]);
+        assert_eq!(bin.package.as_deref(), Some("acme-cli"));
+        find("cargo: run --bin acme-cli - acme-cli");
+        find("cargo: run --example demo - acme-cli");
+        find("cargo: xtask - acme-cli");
+        find("just: release - acme-cli");
+
+        std::fs::remove_dir_all(&root).ok();
+    }
+}
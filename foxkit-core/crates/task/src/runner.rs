@@ -1,11 +1,22 @@
 //! Task runner
 
+use std::borrow::Cow;
+use std::path::PathBuf;
 use std::process::Stdio;
-use tokio::io::{AsyncBufReadExt, BufReader};
+use std::sync::Arc;
+use parking_lot::RwLock;
+use problems::ProblemsService;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
 use tokio::process::{Child, Command};
 use tokio::sync::broadcast;
 
 use crate::{Task, TaskId, TaskType, TaskEvent};
+use crate::problem_matcher::{
+    CompiledMatcher, LineMatcher, MatcherOutput, OutputFormat, ProblemMatcherRegistry, ProblemReporter,
+};
+
+/// Cargo subcommands that accept `--message-format`
+const CARGO_JSON_SUBCOMMANDS: &[&str] = &["build", "check", "clippy", "test", "bench", "run", "doc", "rustc"];
 
 /// Task runner
 pub struct TaskRunner {
@@ -13,6 +24,10 @@ pub struct TaskRunner {
     shell: String,
     /// Shell args
     shell_args: Vec<String>,
+    /// Problem matchers
+    matchers: Arc<ProblemMatcherRegistry>,
+    /// Problems panel receiving matched problems
+    problems: RwLock<Option<Arc<ProblemsService>>>,
 }
 
 impl TaskRunner {
@@ -26,7 +41,34 @@ impl TaskRunner {
             (shell, vec!["-c".to_string()])
         };
 
-        Self { shell, shell_args }
+        Self {
+            shell,
+            shell_args,
+            matchers: Arc::new(ProblemMatcherRegistry::new()),
+            problems: RwLock::new(None),
+        }
+    }
+
+    /// Problem matchers available to tasks
+    pub fn matchers(&self) -> &Arc<ProblemMatcherRegistry> {
+        &self.matchers
+    }
+
+    /// Report matched problems to the problems panel
+    pub fn set_problems(&self, problems: Arc<ProblemsService>) {
+        *self.problems.write() = Some(problems);
+    }
+
+    /// Compiled problem matcher of a task
+    fn problem_matcher(&self, task: &Task) -> Option<Arc<CompiledMatcher>> {
+        let name = task.problem_matcher.as_deref()?;
+        match self.matchers.compile(name) {
+            Ok(matcher) => Some(matcher),
+            Err(e) => {
+                tracing::warn!("Problem matcher {} of task {}: {}", name, task.name, e);
+                None
+            }
+        }
     }
 
     /// Run a task
@@ -36,7 +78,17 @@ impl TaskRunner {
         task: &Task,
         events: broadcast::Sender<TaskEvent>,
     ) -> anyhow::Result<TaskHandle> {
-        let command = self.build_command(task);
+        let matcher = self.problem_matcher(task);
+        let command = self.build_command(task, matcher.as_deref());
+        let cwd = match &task.cwd {
+            Some(cwd) => cwd.clone(),
+            None => std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")),
+        };
+
+        // Problems of the task's previous run are cleared when the reporter is created
+        let reporter = matcher.as_ref()
+            .and(self.problems.read().clone())
+            .map(|service| Arc::new(ProblemReporter::new(service, format!("task:{}", task.name))));
         
         // Notify start
         let _ = events.send(TaskEvent::Started {
@@ -47,7 +99,7 @@ impl TaskRunner {
         let mut child = Command::new(&self.shell)
             .args(&self.shell_args)
             .arg(&command)
-            .current_dir(task.cwd.as_deref().unwrap_or(std::path::Path::new(".")))
+            .current_dir(&cwd)
             .envs(&task.env)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -57,38 +109,33 @@ impl TaskRunner {
         let stdout = child.stdout.take();
         let stderr = child.stderr.take();
 
+        if let Some(matcher) = &matcher
+            && matcher.is_background()
+            && matcher.active_begins()
+        {
+            let _ = events.send(TaskEvent::BackgroundBegan { id });
+        }
+
+        // Each stream gets its own matcher so multi-line patterns don't interleave
+        let line_matcher = || matcher.clone().map(|m| LineMatcher::new(m, cwd.clone()));
+        let mut forwarders = Vec::new();
         if let Some(stdout) = stdout {
-            let events = events.clone();
-            tokio::spawn(async move {
-                let reader = BufReader::new(stdout);
-                let mut lines = reader.lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    let _ = events.send(TaskEvent::Output {
-                        id,
-                        data: format!("{}\n", line),
-                    });
-                }
-            });
+            forwarders.push(tokio::spawn(forward_output(stdout, id, events.clone(), line_matcher(), reporter.clone())));
         }
 
         if let Some(stderr) = stderr {
-            let events = events.clone();
-            tokio::spawn(async move {
-                let reader = BufReader::new(stderr);
-                let mut lines = reader.lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    let _ = events.send(TaskEvent::Output {
-                        id,
-                        data: format!("{}\n", line),
-                    });
-                }
-            });
+            forwarders.push(tokio::spawn(forward_output(stderr, id, events.clone(), line_matcher(), reporter)));
         }
 
-        // Wait for completion in background
+        // Wait for completion in background; output and problems are all
+        // forwarded by the time `Completed` is sent
         let name = task.name.clone();
         tokio::spawn(async move {
-            match child.wait().await {
+            let status = child.wait().await;
+            for forwarder in forwarders {
+                let _ = forwarder.await;
+            }
+            match status {
                 Ok(status) => {
                     let exit_code = status.code().unwrap_or(-1);
                     let _ = events.send(TaskEvent::Completed { id, exit_code });
@@ -109,7 +156,7 @@ impl TaskRunner {
     }
 
     /// Build command string
-    fn build_command(&self, task: &Task) -> String {
+    fn build_command(&self, task: &Task, matcher: Option<&CompiledMatcher>) -> String {
         match task.task_type {
             TaskType::Shell => {
                 if task.args.is_empty() {
@@ -118,18 +165,29 @@ impl TaskRunner {
                     format!("{} {}", task.command, task.args.join(" "))
                 }
             }
-            TaskType::Process => {
-                format!("{} {}", task.command, task.args.join(" "))
-            }
+            // Process arguments are literal, so quote them for the shell
+            TaskType::Process => std::iter::once(task.command.as_str())
+                .chain(task.args.iter().map(String::as_str))
+                .map(shell_quote)
+                .collect::<Vec<_>>()
+                .join(" "),
             TaskType::Npm => {
                 format!("npm run {}", task.command)
             }
             TaskType::Cargo => {
-                if task.args.is_empty() {
-                    format!("cargo {}", task.command)
-                } else {
-                    format!("cargo {} {}", task.command, task.args.join(" "))
+                let mut command = format!("cargo {}", task.command);
+                // Structured diagnostics, rendered back to text for the terminal
+                if matcher.is_some_and(|m| m.format() == OutputFormat::CargoJson)
+                    && CARGO_JSON_SUBCOMMANDS.contains(&task.command.as_str())
+                    && !task.args.iter().any(|a| a.starts_with("--message-format"))
+                {
+                    command.push_str(" --message-format=json-diagnostic-rendered-ansi");
+                }
+                for arg in &task.args {
+                    command.push(' ');
+                    command.push_str(&shell_quote(arg));
                 }
+                command
             }
             TaskType::Gradle => {
                 format!("./gradlew {}", task.command)
@@ -145,6 +203,84 @@ impl TaskRunner {
     }
 }
 
+/// Quote an argument for the task shell, leaving plain words as they are
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    if !arg.is_empty() && arg.bytes().all(|b| b.is_ascii_alphanumeric() || b"-_./:=@+,".contains(&b)) {
+        return Cow::Borrowed(arg);
+    }
+    if cfg!(windows) {
+        Cow::Owned(format!("\"{}\"", arg.replace('"', "\"\"")))
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', "'\\''")))
+    }
+}
+
+/// Stream lines of a task's output as events, feeding its problem matcher
+async fn forward_output<R: AsyncRead + Unpin>(
+    reader: R,
+    id: TaskId,
+    events: broadcast::Sender<TaskEvent>,
+    mut matcher: Option<LineMatcher>,
+    reporter: Option<Arc<ProblemReporter>>,
+) {
+    let mut lines = BufReader::new(reader).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        let Some(matcher) = matcher.as_mut() else {
+            let _ = events.send(TaskEvent::Output { id, data: format!("{}\n", line) });
+            continue;
+        };
+
+        for output in matcher.feed(&line) {
+            match output {
+                MatcherOutput::Problem(problem) => {
+                    if let Some(reporter) = &reporter {
+                        reporter.report(*problem);
+                    }
+                }
+                MatcherOutput::BackgroundBegan => {
+                    if let Some(reporter) = &reporter {
+                        reporter.restart();
+                    }
+                    let _ = events.send(TaskEvent::BackgroundBegan { id });
+                }
+                MatcherOutput::BackgroundEnded => {
+                    let _ = events.send(TaskEvent::BackgroundEnded { id });
+                }
+            }
+        }
+
+        let data = match matcher.matcher().format() {
+            OutputFormat::CargoJson => match cargo_display(&line) {
+                Some(data) => data,
+                None => continue,
+            },
+            OutputFormat::Text => format!("{}\n", line),
+        };
+        let _ = events.send(TaskEvent::Output { id, data });
+    }
+}
+
+/// Terminal text for a line of cargo JSON output, `None` for messages that
+/// only carry build metadata
+fn cargo_display(line: &str) -> Option<String> {
+    let value = match line.starts_with('{').then(|| serde_json::from_str::<serde_json::Value>(line)) {
+        Some(Ok(value)) => value,
+        _ => return Some(format!("{}\n", line)),
+    };
+    match value.get("reason").and_then(|r| r.as_str()) {
+        Some("compiler-message") => value.pointer("/message/rendered")
+            .and_then(|r| r.as_str())
+            .map(str::to_string),
+        Some(_) => None,
+        None => Some(
+            value.get("rendered")
+                .and_then(|r| r.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("{}\n", line)),
+        ),
+    }
+}
+
 impl Default for TaskRunner {
     fn default() -> Self {
         Self::new()
@@ -167,3 +303,48 @@ impl TaskHandle {
         tracing::info!("Cancelling task: {}", self.name);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_process_args_are_quoted() {
+        let runner = TaskRunner::new();
+        let task = Task::process("npm: x", "npm", vec!["run".into(), "a b; rm -rf ~".into(), "it's".into()]);
+        assert_eq!(runner.build_command(&task, None), "npm run 'a b; rm -rf ~' 'it'\\''s'");
+    }
+
+    #[tokio::test]
+    async fn test_problems_reported_and_cleared_on_rerun() {
+        let dir = std::env::temp_dir().join(format!("foxkit-task-matcher-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let problems = Arc::new(ProblemsService::new());
+        let runner = TaskRunner::new();
+        problems.set_filter(problems::ProblemsFilter::all());
+        runner.set_problems(problems.clone());
+
+        let mut task = Task::shell("cc", "printf 'main.c:3:5: error: expected ;\\n' >&2").in_dir(dir.clone());
+        task.problem_matcher = Some("$gcc".to_string());
+
+        // Another reporter with the same source, e.g. a language server
+        let clangd = problems::ProblemItem::error("unused include", problems::ProblemLocation::new(1, 1))
+            .with_source("cpp");
+        problems.set_diagnostics(dir.join("other.c"), vec![clangd]);
+
+        for _ in 0..2 {
+            let (events, mut rx) = broadcast::channel(16);
+            runner.run(TaskId::new(), &task, events).await.unwrap();
+            while !matches!(rx.recv().await.unwrap(), TaskEvent::Completed { .. }) {}
+
+            assert_eq!(problems.stats().errors, 2);
+            let items = problems.get_file_problems(&dir.join("main.c"));
+            assert_eq!(items.len(), 1);
+            assert_eq!(items[0].location.line, 3);
+            assert_eq!(items[0].owner.as_deref(), Some("task:cc"));
+            assert_eq!(problems.get_file_problems(&dir.join("other.c")).len(), 1);
+        }
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
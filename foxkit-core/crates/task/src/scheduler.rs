@@ -1,13 +1,21 @@
 //! Task Scheduler
 //!
-//! Parallel task scheduling with dependencies.
+//! Runs a dependency graph of tasks in parallel. A task starts as soon as
+//! every task it `depends_on` has finished. Background tasks unblock their
+//! dependents once their problem matcher reports the end of the first watch
+//! cycle and keep running afterwards. What happens when a task fails is up
+//! to its [`FailurePolicy`].
 
 use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
 use parking_lot::RwLock;
-use tokio::sync::{broadcast, mpsc, Semaphore};
+use tokio::sync::{broadcast, mpsc};
 
-use super::{Task, TaskId};
+use super::{FailurePolicy, Task, TaskId};
+use crate::cache::TaskCache;
+use crate::problem_matcher::{CompiledMatcher, LineMatcher, MatcherOutput, ProblemMatcherRegistry};
 
 /// Task scheduler for parallel execution
 pub struct TaskScheduler {
@@ -17,12 +25,20 @@ pub struct TaskScheduler {
     running: Arc<RwLock<HashMap<TaskId, RunningTask>>>,
     /// Task queue
     queue: RwLock<VecDeque<QueuedTask>>,
-    /// Completed tasks
+    /// Tasks that succeeded, and background tasks that became ready
     completed: Arc<RwLock<HashSet<String>>>,
+    /// Tasks that failed, were cancelled or were skipped
+    failed: Arc<RwLock<HashSet<String>>>,
+    /// State of every queued task, for graph views
+    nodes: Arc<RwLock<HashMap<TaskId, TaskNode>>>,
+    /// Latest queued definition of each task, by name
+    definitions: Arc<RwLock<HashMap<String, Task>>>,
     /// Event sender
     event_tx: broadcast::Sender<SchedulerEvent>,
-    /// Semaphore for parallelism control
-    semaphore: Arc<Semaphore>,
+    /// Result cache for tasks with cache settings
+    cache: Option<Arc<TaskCache>>,
+    /// Problem matchers telling when background tasks are ready
+    matchers: Arc<ProblemMatcherRegistry>,
 }
 
 /// A task in the queue
@@ -43,17 +59,92 @@ pub struct RunningTask {
     pub cancel_tx: Option<mpsc::Sender<()>>,
 }
 
+/// State of a task in the graph
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    Queued,
+    Running,
+    /// A background task that finished its first watch cycle
+    Ready,
+    Succeeded,
+    Failed,
+    Skipped,
+    Cancelled,
+}
+
+/// A task in the graph, as shown by a live graph view
+#[derive(Debug, Clone)]
+pub struct TaskNode {
+    pub id: TaskId,
+    pub name: String,
+    /// Names of the tasks this one waits for
+    pub dependencies: Vec<String>,
+    pub state: NodeState,
+    /// Current or last attempt, counting from 1; 0 before the first start
+    pub attempt: u32,
+    pub background: bool,
+}
+
+/// Why a task was not run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// A dependency failed, was cancelled or was skipped
+    DependencyFailed(String),
+    /// No queued or finished task has this name
+    MissingDependency(String),
+    /// The task waits on itself through its dependencies
+    DependencyCycle,
+    /// Another task failed and its policy stopped the run
+    FailFast(String),
+}
+
 /// Scheduler events
 #[derive(Debug, Clone)]
 pub enum SchedulerEvent {
-    TaskQueued { id: TaskId, name: String },
-    TaskStarted { id: TaskId, name: String },
-    TaskCompleted { id: TaskId, name: String, success: bool, duration_ms: u64 },
+    TaskQueued { id: TaskId, name: String, dependencies: Vec<String> },
+    /// `attempt` counts from 1 and goes up with every retry
+    TaskStarted { id: TaskId, name: String, attempt: u32 },
+    /// A background task finished its first watch cycle; its dependents may start
+    TaskReady { id: TaskId, name: String },
+    /// An attempt failed and the task runs again
+    TaskRetrying { id: TaskId, name: String, attempt: u32, error: String },
+    TaskCompleted { id: TaskId, name: String, success: bool, duration_ms: u64, error: Option<String> },
     TaskCancelled { id: TaskId, name: String },
+    TaskSkipped { id: TaskId, name: String, reason: SkipReason },
     TaskOutput { id: TaskId, output: String },
+    /// Outputs and logs were restored from the cache instead of running
+    TaskCacheHit { id: TaskId, name: String, key: String },
+    /// No cached result; the task runs and its result is stored under `key`
+    TaskCacheMiss { id: TaskId, name: String, key: String },
     QueueEmpty,
 }
 
+/// Progress reported by running tasks to [`TaskScheduler::run`]
+enum Progress {
+    Ready(TaskId),
+    Finished { id: TaskId, name: String, failed: bool, policy: FailurePolicy },
+}
+
+/// How a task run ended
+enum Outcome {
+    Succeeded,
+    Failed(String),
+    Cancelled,
+}
+
+/// What a spawned task needs from the scheduler
+#[derive(Clone)]
+struct Execution {
+    running: Arc<RwLock<HashMap<TaskId, RunningTask>>>,
+    completed: Arc<RwLock<HashSet<String>>>,
+    failed: Arc<RwLock<HashSet<String>>>,
+    nodes: Arc<RwLock<HashMap<TaskId, TaskNode>>>,
+    definitions: Arc<RwLock<HashMap<String, Task>>>,
+    event_tx: broadcast::Sender<SchedulerEvent>,
+    cache: Option<Arc<TaskCache>>,
+    progress: mpsc::UnboundedSender<Progress>,
+}
+
 impl TaskScheduler {
     pub fn new(max_parallel: usize) -> Self {
         let (event_tx, _) = broadcast::channel(256);
@@ -62,21 +153,50 @@ impl TaskScheduler {
             running: Arc::new(RwLock::new(HashMap::new())),
             queue: RwLock::new(VecDeque::new()),
             completed: Arc::new(RwLock::new(HashSet::new())),
+            failed: Arc::new(RwLock::new(HashSet::new())),
+            nodes: Arc::new(RwLock::new(HashMap::new())),
+            definitions: Arc::new(RwLock::new(HashMap::new())),
             event_tx,
-            semaphore: Arc::new(Semaphore::new(max_parallel)),
+            cache: None,
+            matchers: Arc::new(ProblemMatcherRegistry::new()),
         }
     }
 
+    /// Skip tasks whose cached result matches their inputs
+    pub fn with_cache(mut self, cache: Arc<TaskCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Use these problem matchers to tell when background tasks are ready
+    pub fn with_matchers(mut self, matchers: Arc<ProblemMatcherRegistry>) -> Self {
+        self.matchers = matchers;
+        self
+    }
+
     /// Queue a task for execution
     pub fn queue(&self, task: Task, priority: i32) -> TaskId {
         let id = TaskId::new();
         let dependencies = task.depends_on.clone();
-        
+
+        // The task is pending again until this run finishes
+        self.completed.write().remove(&task.name);
+        self.failed.write().remove(&task.name);
+        self.nodes.write().insert(id, TaskNode {
+            id,
+            name: task.name.clone(),
+            dependencies: dependencies.clone(),
+            state: NodeState::Queued,
+            attempt: 0,
+            background: task.background,
+        });
+        self.definitions.write().insert(task.name.clone(), task.clone());
+
         self.queue.write().push_back(QueuedTask {
             id,
             task: task.clone(),
             priority,
-            dependencies,
+            dependencies: dependencies.clone(),
         });
         
         // Sort by priority (higher first)
@@ -85,11 +205,27 @@ impl TaskScheduler {
         let _ = self.event_tx.send(SchedulerEvent::TaskQueued {
             id,
             name: task.name.clone(),
+            dependencies,
         });
         
         id
     }
 
+    /// Queue every task of a graph
+    pub fn queue_graph(&self, graph: &TaskGraph) -> Vec<TaskId> {
+        let mut names = graph.execution_order();
+        // Tasks on a cycle are missing from the order; queue them to be skipped
+        let ordered: HashSet<String> = names.iter().cloned().collect();
+        let mut cyclic: Vec<String> = graph.tasks.keys().filter(|n| !ordered.contains(*n)).cloned().collect();
+        cyclic.sort();
+        names.extend(cyclic);
+
+        names.iter()
+            .filter_map(|name| graph.get(name))
+            .map(|task| self.queue(task.clone(), 0))
+            .collect()
+    }
+
     /// Check if a task is ready to run (all dependencies completed)
     fn is_ready(&self, task: &QueuedTask) -> bool {
         let completed = self.completed.read();
@@ -109,84 +245,288 @@ impl TaskScheduler {
         None
     }
 
-    /// Run the scheduler
+    /// Skip queued tasks whose dependencies can no longer finish
+    fn skip_unsatisfiable(&self) {
+        loop {
+            let skipped = {
+                let mut queue = self.queue.write();
+                let completed = self.completed.read();
+                let failed = self.failed.read();
+                let pending: HashSet<&str> = queue.iter().map(|t| t.task.name.as_str()).collect();
+                let running: HashSet<String> = self.running.read().values().map(|t| t.task.name.clone()).collect();
+
+                let found = queue.iter().enumerate().find_map(|(i, task)| {
+                    task.dependencies.iter()
+                        .filter(|dep| !completed.contains(*dep))
+                        .filter(|dep| !pending.contains(dep.as_str()) && !running.contains(*dep))
+                        .map(|dep| if failed.contains(dep) {
+                            SkipReason::DependencyFailed(dep.clone())
+                        } else {
+                            SkipReason::MissingDependency(dep.clone())
+                        })
+                        .next()
+                        .map(|reason| (i, reason))
+                });
+                found.map(|(i, reason)| (queue.remove(i).unwrap(), reason))
+            };
+
+            match skipped {
+                Some((task, reason)) => self.skip(task, reason),
+                None => break,
+            }
+        }
+    }
+
+    /// Skip everything still queued
+    fn skip_queued(&self, reason: impl Fn() -> SkipReason) {
+        let queued: Vec<QueuedTask> = self.queue.write().drain(..).collect();
+        for task in queued {
+            self.skip(task, reason());
+        }
+    }
+
+    fn skip(&self, task: QueuedTask, reason: SkipReason) {
+        self.failed.write().insert(task.task.name.clone());
+        if let Some(node) = self.nodes.write().get_mut(&task.id) {
+            node.state = NodeState::Skipped;
+        }
+        let _ = self.event_tx.send(SchedulerEvent::TaskSkipped {
+            id: task.id,
+            name: task.task.name,
+            reason,
+        });
+    }
+
+    /// Run queued tasks until none is left
+    ///
+    /// Returns once every task finished or was skipped, except background
+    /// tasks that became ready: those keep running until they exit or are
+    /// cancelled.
     pub async fn run(&self) {
+        let (progress_tx, mut progress_rx) = mpsc::unbounded_channel();
+        // Tasks holding a slot: started, and not yet finished or ready
+        let mut active = HashSet::new();
+
         loop {
-            // Wait for a permit
-            let permit = self.semaphore.clone().acquire_owned().await.unwrap();
-            
-            // Get next ready task
-            let task = match self.pop_ready() {
-                Some(t) => t,
-                None => {
-                    // No ready tasks, check if we're done
-                    drop(permit);
-                    if self.queue.read().is_empty() && self.running.read().is_empty() {
-                        let _ = self.event_tx.send(SchedulerEvent::QueueEmpty);
-                        break;
+            self.skip_unsatisfiable();
+            while active.len() < self.max_parallel.max(1) {
+                let Some(task) = self.pop_ready() else { break };
+                active.insert(task.id);
+                self.start(task, progress_tx.clone());
+            }
+
+            if active.is_empty() {
+                // Nothing running can unblock what is left
+                self.skip_queued(|| SkipReason::DependencyCycle);
+                break;
+            }
+
+            let Some(progress) = progress_rx.recv().await else { break };
+            match progress {
+                Progress::Ready(id) => {
+                    active.remove(&id);
+                }
+                Progress::Finished { id, name, failed, policy } => {
+                    // Failures after a background task became ready don't affect the graph
+                    if active.remove(&id) && failed && policy != FailurePolicy::Continue {
+                        self.fail_fast(&name);
                     }
-                    // Wait a bit and try again
-                    tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                    continue;
                 }
-            };
-            
-            // Execute task
-            let id = task.id;
-            let name = task.task.name.clone();
-            let event_tx = self.event_tx.clone();
-            let completed = self.completed.clone();
-            
-            let (cancel_tx, mut cancel_rx) = mpsc::channel::<()>(1);
-            
-            self.running.write().insert(id, RunningTask {
-                id,
-                task: task.task.clone(),
-                started_at: std::time::Instant::now(),
-                cancel_tx: Some(cancel_tx),
-            });
-            
-            let _ = event_tx.send(SchedulerEvent::TaskStarted {
-                id,
-                name: name.clone(),
+            }
+        }
+
+        let _ = self.event_tx.send(SchedulerEvent::QueueEmpty);
+    }
+
+    /// Stop the run after `name` failed
+    fn fail_fast(&self, name: &str) {
+        let running_ids: Vec<TaskId> = self.running.read().keys().copied().collect();
+        for id in running_ids {
+            self.cancel(id);
+        }
+        self.skip_queued(|| SkipReason::FailFast(name.to_string()));
+    }
+
+    /// Spawn a task, retrying it as its policy allows
+    fn start(&self, queued: QueuedTask, progress: mpsc::UnboundedSender<Progress>) {
+        let id = queued.id;
+        let task = queued.task;
+        let (cancel_tx, mut cancel_rx) = mpsc::channel::<()>(1);
+
+        self.running.write().insert(id, RunningTask {
+            id,
+            task: task.clone(),
+            started_at: std::time::Instant::now(),
+            cancel_tx: Some(cancel_tx),
+        });
+
+        let matcher = task.problem_matcher.as_deref()
+            .filter(|_| task.background)
+            .and_then(|name| match self.matchers.compile(name) {
+                Ok(matcher) => Some(matcher),
+                Err(e) => {
+                    tracing::warn!("Problem matcher {} of task {}: {}", name, task.name, e);
+                    None
+                }
             });
-            
-            let task_clone = task.task.clone();
-            let running = self.running.clone();
-            
-            tokio::spawn(async move {
-                let started = std::time::Instant::now();
-                
-                // Execute the task
+        let exec = Execution {
+            running: self.running.clone(),
+            completed: self.completed.clone(),
+            failed: self.failed.clone(),
+            nodes: self.nodes.clone(),
+            definitions: self.definitions.clone(),
+            event_tx: self.event_tx.clone(),
+            cache: self.cache.clone(),
+            progress,
+        };
+
+        tokio::spawn(async move {
+            let started = std::time::Instant::now();
+            let retries = match task.on_failure {
+                FailurePolicy::Retry(retries) => retries,
+                _ => 0,
+            };
+            let ready = AtomicBool::new(false);
+            let mut attempt = 1;
+
+            let outcome = loop {
+                exec.set_state(id, NodeState::Running, Some(attempt));
+                let _ = exec.event_tx.send(SchedulerEvent::TaskStarted {
+                    id,
+                    name: task.name.clone(),
+                    attempt,
+                });
+
                 let result = tokio::select! {
-                    result = Self::execute_task(&task_clone, &event_tx, id) => result,
-                    _ = cancel_rx.recv() => Err(anyhow::anyhow!("Cancelled")),
+                    result = Self::run_task(&task, &exec, id, matcher.clone(), &ready) => result,
+                    _ = cancel_rx.recv() => break Outcome::Cancelled,
                 };
-                
-                let duration_ms = started.elapsed().as_millis() as u64;
-                let success = result.is_ok();
-                
-                // Remove from running
-                running.write().remove(&id);
-                
-                // Mark as completed
-                if success {
-                    completed.write().insert(name.clone());
+                match result {
+                    Ok(()) => break Outcome::Succeeded,
+                    Err(e) if attempt <= retries && !ready.load(Ordering::SeqCst) => {
+                        let _ = exec.event_tx.send(SchedulerEvent::TaskRetrying {
+                            id,
+                            name: task.name.clone(),
+                            attempt,
+                            error: e.to_string(),
+                        });
+                        attempt += 1;
+                    }
+                    Err(e) => break Outcome::Failed(e.to_string()),
                 }
-                
-                let _ = event_tx.send(SchedulerEvent::TaskCompleted {
+            };
+
+            exec.running.write().remove(&id);
+            let was_ready = ready.load(Ordering::SeqCst);
+            let duration_ms = started.elapsed().as_millis() as u64;
+
+            match &outcome {
+                Outcome::Succeeded => {
+                    exec.completed.write().insert(task.name.clone());
+                    exec.set_state(id, NodeState::Succeeded, None);
+                }
+                Outcome::Failed(_) | Outcome::Cancelled if !was_ready => {
+                    exec.failed.write().insert(task.name.clone());
+                }
+                _ => {}
+            }
+            if let Outcome::Failed(error) = &outcome {
+                exec.set_state(id, NodeState::Failed, None);
+                let _ = exec.event_tx.send(SchedulerEvent::TaskCompleted {
+                    id,
+                    name: task.name.clone(),
+                    success: false,
+                    duration_ms,
+                    error: Some(error.clone()),
+                });
+            } else if let Outcome::Succeeded = outcome {
+                let _ = exec.event_tx.send(SchedulerEvent::TaskCompleted {
                     id,
-                    name,
-                    success,
+                    name: task.name.clone(),
+                    success: true,
                     duration_ms,
+                    error: None,
                 });
-                
-                drop(permit);
+            }
+
+            // The loop may have returned while a ready background task ran on
+            let _ = exec.progress.send(Progress::Finished {
+                id,
+                name: task.name,
+                failed: matches!(outcome, Outcome::Failed(_)),
+                policy: task.on_failure,
             });
+        });
+    }
+
+    /// Run a task, going through the cache when it has cache settings
+    async fn run_task(
+        task: &Task,
+        exec: &Execution,
+        id: TaskId,
+        matcher: Option<Arc<CompiledMatcher>>,
+        ready: &AtomicBool,
+    ) -> anyhow::Result<()> {
+        let event_tx = &exec.event_tx;
+        let mark_ready = || {
+            if !ready.swap(true, Ordering::SeqCst) {
+                exec.completed.write().insert(task.name.clone());
+                exec.set_state(id, NodeState::Ready, None);
+                let _ = event_tx.send(SchedulerEvent::TaskReady { id, name: task.name.clone() });
+                let _ = exec.progress.send(Progress::Ready(id));
+            }
+        };
+
+        let cache = exec.cache.as_deref().filter(|_| task.cache.is_some() && !task.background);
+        let Some(cache) = cache else {
+            return Self::execute_task(task, event_tx, id, matcher, &mark_ready).await.map(|_| ());
+        };
+
+        let definitions = exec.definitions.read().clone();
+        let key = cache.key(task, &definitions).await?;
+        match cache.fetch(&key).await {
+            Ok(Some(artifact)) => {
+                cache.restore(task, &artifact).await?;
+                let _ = event_tx.send(SchedulerEvent::TaskCacheHit {
+                    id,
+                    name: task.name.clone(),
+                    key,
+                });
+                for line in artifact.logs {
+                    let _ = event_tx.send(SchedulerEvent::TaskOutput { id, output: line });
+                }
+                return Ok(());
+            }
+            Ok(None) => {}
+            Err(e) => tracing::warn!("Cache lookup for {} failed: {}", task.name, e),
         }
+
+        let _ = event_tx.send(SchedulerEvent::TaskCacheMiss {
+            id,
+            name: task.name.clone(),
+            key: key.clone(),
+        });
+        let started = std::time::Instant::now();
+        let logs = Self::execute_task(task, event_tx, id, None, &mark_ready).await?;
+        let artifact = cache.capture(task, &key, logs, started.elapsed().as_millis() as u64).await?;
+        if let Err(e) = cache.store(task, &artifact).await {
+            tracing::warn!("Caching {} failed: {}", task.name, e);
+        }
+        Ok(())
     }
 
-    async fn execute_task(task: &Task, event_tx: &broadcast::Sender<SchedulerEvent>, id: TaskId) -> anyhow::Result<()> {
+    /// Run a task's process, returning its output lines
+    ///
+    /// Background tasks call `on_ready` when `matcher` sees the end of a
+    /// watch cycle, or right after spawning when they have no background
+    /// matcher; their output isn't kept.
+    async fn execute_task(
+        task: &Task,
+        event_tx: &broadcast::Sender<SchedulerEvent>,
+        id: TaskId,
+        matcher: Option<Arc<CompiledMatcher>>,
+        on_ready: &(dyn Fn() + Send + Sync),
+    ) -> anyhow::Result<Vec<String>> {
         let mut cmd = tokio::process::Command::new(&task.command);
         cmd.args(&task.args);
         
@@ -200,47 +540,91 @@ impl TaskScheduler {
         
         cmd.stdout(std::process::Stdio::piped());
         cmd.stderr(std::process::Stdio::piped());
+        cmd.kill_on_drop(true);
         
         let mut child = cmd.spawn()?;
+
+        let matcher = matcher.filter(|m| task.background && m.is_background());
+        if task.background && matcher.is_none() {
+            on_ready();
+        }
+        let cwd = task.cwd.clone()
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| PathBuf::from(".")));
+        let (ended_tx, mut ended_rx) = mpsc::unbounded_channel::<()>();
         
         // Read output
-        if let Some(stdout) = child.stdout.take() {
+        let keep_logs = !task.background;
+        let logs = Arc::new(parking_lot::Mutex::new(Vec::new()));
+        let mut readers = Vec::new();
+        let streams: [Option<Box<dyn tokio::io::AsyncRead + Send + Unpin>>; 2] = [
+            child.stdout.take().map(|s| Box::new(s) as _),
+            child.stderr.take().map(|s| Box::new(s) as _),
+        ];
+        for stream in streams.into_iter().flatten() {
             let event_tx = event_tx.clone();
-            tokio::spawn(async move {
+            let logs = logs.clone();
+            // Each stream gets its own matcher so multi-line patterns don't interleave
+            let mut matcher = matcher.clone().map(|m| LineMatcher::new(m, cwd.clone()));
+            let ended_tx = ended_tx.clone();
+            readers.push(tokio::spawn(async move {
                 use tokio::io::AsyncBufReadExt;
-                let mut reader = tokio::io::BufReader::new(stdout).lines();
+                let mut reader = tokio::io::BufReader::new(stream).lines();
                 while let Ok(Some(line)) = reader.next_line().await {
+                    if let Some(matcher) = matcher.as_mut()
+                        && matcher.feed(&line).iter().any(|o| matches!(o, MatcherOutput::BackgroundEnded))
+                    {
+                        let _ = ended_tx.send(());
+                    }
+                    if keep_logs {
+                        logs.lock().push(line.clone());
+                    }
                     let _ = event_tx.send(SchedulerEvent::TaskOutput {
                         id,
                         output: line,
                     });
                 }
-            });
+            }));
         }
+        drop(ended_tx);
         
-        let status = child.wait().await?;
+        let status = loop {
+            tokio::select! {
+                status = child.wait() => break status?,
+                Some(()) = ended_rx.recv() => on_ready(),
+            }
+        };
+        for reader in readers {
+            let _ = reader.await;
+        }
         
         if status.success() {
-            Ok(())
+            Ok(std::mem::take(&mut *logs.lock()))
         } else {
             Err(anyhow::anyhow!("Task failed with exit code: {:?}", status.code()))
         }
     }
 
-    /// Cancel a running task
+    /// Cancel a running or queued task
     pub fn cancel(&self, id: TaskId) {
-        if let Some(task) = self.running.write().remove(&id) {
+        let name = if let Some(task) = self.running.write().remove(&id) {
             if let Some(cancel_tx) = task.cancel_tx {
                 let _ = cancel_tx.try_send(());
             }
-            let _ = self.event_tx.send(SchedulerEvent::TaskCancelled {
-                id,
-                name: task.task.name.clone(),
-            });
+            task.task.name
         } else {
-            // Remove from queue if not running
-            self.queue.write().retain(|t| t.id != id);
+            let mut queue = self.queue.write();
+            let Some(pos) = queue.iter().position(|t| t.id == id) else {
+                return;
+            };
+            let task = queue.remove(pos).unwrap().task;
+            self.failed.write().insert(task.name.clone());
+            task.name
+        };
+
+        if let Some(node) = self.nodes.write().get_mut(&id) {
+            node.state = NodeState::Cancelled;
         }
+        let _ = self.event_tx.send(SchedulerEvent::TaskCancelled { id, name });
     }
 
     /// Cancel all tasks
@@ -249,7 +633,10 @@ impl TaskScheduler {
         for id in running_ids {
             self.cancel(id);
         }
-        self.queue.write().clear();
+        let queued_ids: Vec<TaskId> = self.queue.read().iter().map(|t| t.id).collect();
+        for id in queued_ids {
+            self.cancel(id);
+        }
     }
 
     /// Get running tasks
@@ -264,6 +651,13 @@ impl TaskScheduler {
         self.queue.read().iter().map(|t| (t.id, t.task.name.clone())).collect()
     }
 
+    /// Snapshot of every queued task and its state, in queue order
+    pub fn graph(&self) -> Vec<TaskNode> {
+        let mut nodes: Vec<TaskNode> = self.nodes.read().values().cloned().collect();
+        nodes.sort_by_key(|node| node.id.0);
+        nodes
+    }
+
     /// Subscribe to scheduler events
     pub fn subscribe(&self) -> broadcast::Receiver<SchedulerEvent> {
         self.event_tx.subscribe()
@@ -272,7 +666,21 @@ impl TaskScheduler {
     /// Set max parallel tasks
     pub fn set_max_parallel(&mut self, max: usize) {
         self.max_parallel = max;
-        // Note: This won't affect already acquired permits
+    }
+}
+
+impl Execution {
+    fn set_state(&self, id: TaskId, state: NodeState, attempt: Option<u32>) {
+        if let Some(node) = self.nodes.write().get_mut(&id) {
+            // Cancellation is final
+            if node.state == NodeState::Cancelled {
+                return;
+            }
+            node.state = state;
+            if let Some(attempt) = attempt {
+                node.attempt = attempt;
+            }
+        }
     }
 }
 
@@ -297,6 +705,11 @@ impl TaskGraph {
         self.tasks.insert(task.name.clone(), task);
     }
 
+    /// Get a task by name
+    pub fn get(&self, name: &str) -> Option<&Task> {
+        self.tasks.get(name)
+    }
+
     /// Get execution order (topological sort)
     pub fn execution_order(&self) -> Vec<String> {
         let mut in_degree: HashMap<String, usize> = HashMap::new();
@@ -385,3 +798,154 @@ impl Default for TaskGraph {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::LocalCache;
+
+    #[tokio::test]
+    async fn test_cache_hit_skips_task() {
+        let dir = std::env::temp_dir().join(format!("foxkit-scheduler-cache-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("src/main.txt"), "source").unwrap();
+
+        let cache = Arc::new(TaskCache::new(LocalCache::new(dir.join(".cache"))));
+        let task = Task::process("build", "sh", vec![
+            "-c".to_string(),
+            "echo built; echo run >> runs.log; mkdir -p out; cp src/main.txt out/main.txt".to_string(),
+        ])
+        .in_dir(dir.clone())
+        .cached(vec!["src/**".to_string()], vec!["out/**".to_string()]);
+
+        let mut hits = 0;
+        for _ in 0..2 {
+            let scheduler = TaskScheduler::new(2).with_cache(cache.clone());
+            let mut events = scheduler.subscribe();
+            scheduler.queue(task.clone(), 0);
+            std::fs::remove_dir_all(dir.join("out")).ok();
+            scheduler.run().await;
+            let mut output = Vec::new();
+            loop {
+                match events.recv().await.unwrap() {
+                    SchedulerEvent::TaskCacheHit { .. } => hits += 1,
+                    SchedulerEvent::TaskOutput { output: line, .. } => output.push(line),
+                    SchedulerEvent::TaskCompleted { success, .. } => {
+                        assert!(success);
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+            assert_eq!(output, vec!["built".to_string()]);
+            assert_eq!(std::fs::read_to_string(dir.join("out/main.txt")).unwrap(), "source");
+        }
+
+        assert_eq!(hits, 1);
+        assert_eq!(std::fs::read_to_string(dir.join("runs.log")).unwrap(), "run\n");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    fn sh(name: &str, script: &str) -> Task {
+        Task::process(name, "sh", vec!["-c".to_string(), script.to_string()])
+    }
+
+    fn states(scheduler: &TaskScheduler) -> HashMap<String, NodeState> {
+        scheduler.graph().into_iter().map(|node| (node.name, node.state)).collect()
+    }
+
+    #[tokio::test]
+    async fn test_dag_with_failure_policies() {
+        let dir = std::env::temp_dir().join(format!("foxkit-scheduler-dag-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut graph = TaskGraph::new();
+        graph.add(sh("gen", "echo gen >> order.log").in_dir(dir.clone()));
+        graph.add(sh("lib", "echo lib >> order.log").in_dir(dir.clone()).depends("gen"));
+        graph.add(sh("lint", "exit 1").on_failure(FailurePolicy::Continue));
+        graph.add(sh("docs", "true").depends("lint"));
+        // Fails on the first attempt only
+        graph.add(sh("flaky", "test -f flaky || { touch flaky; exit 1; }")
+            .in_dir(dir.clone())
+            .on_failure(FailurePolicy::Retry(2)));
+        graph.add(sh("app", "true").depends("lib").depends("flaky"));
+        graph.add(sh("orphan", "true").depends("missing"));
+
+        let scheduler = TaskScheduler::new(4);
+        let mut events = scheduler.subscribe();
+        scheduler.queue_graph(&graph);
+        scheduler.run().await;
+
+        let states = states(&scheduler);
+        assert_eq!(states["app"], NodeState::Succeeded);
+        assert_eq!(states["lint"], NodeState::Failed);
+        assert_eq!(states["docs"], NodeState::Skipped);
+        assert_eq!(states["orphan"], NodeState::Skipped);
+        assert_eq!(std::fs::read_to_string(dir.join("order.log")).unwrap(), "gen\nlib\n");
+
+        let mut retries = 0;
+        let mut skipped = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            match event {
+                SchedulerEvent::TaskRetrying { name, attempt, .. } => {
+                    assert_eq!((name.as_str(), attempt), ("flaky", 1));
+                    retries += 1;
+                }
+                SchedulerEvent::TaskSkipped { name, reason, .. } => skipped.push((name, reason)),
+                _ => {}
+            }
+        }
+        assert_eq!(retries, 1);
+        skipped.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(skipped, vec![
+            ("docs".to_string(), SkipReason::DependencyFailed("lint".to_string())),
+            ("orphan".to_string(), SkipReason::MissingDependency("missing".to_string())),
+        ]);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_fail_fast_cancels_and_skips() {
+        let scheduler = TaskScheduler::new(4);
+        scheduler.queue(sh("slow", "sleep 5"), 0);
+        scheduler.queue(sh("broken", "sleep 0.1; exit 2"), 0);
+        scheduler.queue(sh("after", "true").depends("slow"), 0);
+
+        let started = std::time::Instant::now();
+        scheduler.run().await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(4));
+
+        let states = states(&scheduler);
+        assert_eq!(states["broken"], NodeState::Failed);
+        assert_eq!(states["slow"], NodeState::Cancelled);
+        assert_eq!(states["after"], NodeState::Skipped);
+    }
+
+    #[tokio::test]
+    async fn test_background_task_ready_on_matcher() {
+        let matchers = Arc::new(ProblemMatcherRegistry::new());
+        matchers.register(
+            "watch",
+            crate::ProblemMatcher::new("watch", crate::ProblemPattern::new(r"^error: (.*)$").message(1))
+                .with_background("^compiling", "^watching", false),
+        );
+        let scheduler = TaskScheduler::new(2).with_matchers(matchers);
+
+        let mut watch = sh("watch", "echo compiling; sleep 0.2; echo watching; sleep 5").in_background();
+        watch.problem_matcher = Some("watch".to_string());
+        scheduler.queue(watch, 0);
+        scheduler.queue(sh("serve", "true").depends("watch"), 0);
+
+        tokio::time::timeout(std::time::Duration::from_secs(3), scheduler.run()).await.unwrap();
+        let before = states(&scheduler);
+        assert_eq!(before["watch"], NodeState::Ready);
+        assert_eq!(before["serve"], NodeState::Succeeded);
+        assert_eq!(scheduler.running_tasks().len(), 1);
+
+        scheduler.cancel_all();
+        assert!(scheduler.running_tasks().is_empty());
+        assert_eq!(states(&scheduler)["watch"], NodeState::Cancelled);
+    }
+}
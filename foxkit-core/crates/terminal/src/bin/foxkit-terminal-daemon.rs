@@ -0,0 +1,24 @@
+//! Headless terminal daemon
+//!
+//! Owns terminal PTYs so sessions survive editor restarts. Usage:
+//! `foxkit-terminal-daemon [socket-path]`
+
+#[cfg(unix)]
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    use std::sync::Arc;
+    use terminal::daemon::{default_socket_path, TerminalDaemon};
+
+    let socket_path = std::env::args_os()
+        .nth(1)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(default_socket_path);
+
+    Arc::new(TerminalDaemon::new(socket_path)).run().await
+}
+
+#[cfg(not(unix))]
+fn main() {
+    eprintln!("foxkit-terminal-daemon is only supported on Unix");
+    std::process::exit(1);
+}
@@ -0,0 +1,841 @@
+//! Headless terminal daemon.
+//!
+//! The daemon owns PTYs so shells, dev servers and builds keep running when
+//! the editor restarts. Editors connect over a Unix socket, list the running
+//! sessions and attach to them; recent output is replayed on attach before
+//! live output resumes. Messages are length-prefixed JSON frames.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{UnixListener, UnixStream};
+use tokio::sync::{broadcast, mpsc, oneshot, Notify};
+use tokio::task::JoinHandle;
+
+use crate::{Pty, TerminalSize};
+
+/// Output kept per session for replay on attach.
+pub const DEFAULT_REPLAY_BYTES: usize = 1024 * 1024;
+
+/// Largest frame accepted on the socket.
+const MAX_FRAME_SIZE: usize = 16 * 1024 * 1024;
+
+/// How often exited sessions are reaped.
+const REAP_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Socket path used when none is configured.
+pub fn default_socket_path() -> PathBuf {
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("foxkit").join("terminal-daemon.sock"),
+        None => std::env::temp_dir().join(format!("foxkit-terminal-{}.sock", nix::unistd::getuid())),
+    }
+}
+
+/// Daemon program installed next to the editor executable.
+pub fn default_daemon_program() -> Result<PathBuf> {
+    Ok(std::env::current_exe()?.with_file_name("foxkit-terminal-daemon"))
+}
+
+/// What to run in a new session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionSpec {
+    pub shell: String,
+    pub args: Vec<String>,
+    pub cwd: PathBuf,
+    pub env: HashMap<String, String>,
+    pub size: TerminalSize,
+    pub title: String,
+}
+
+/// A running session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionInfo {
+    pub id: u64,
+    pub title: String,
+    pub shell: String,
+    pub cwd: PathBuf,
+    pub size: TerminalSize,
+    /// Shell process ID.
+    pub pid: i32,
+    /// Start time in seconds since the Unix epoch.
+    pub started: u64,
+    /// Number of attached clients.
+    pub attached: usize,
+}
+
+/// Daemon protocol message.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum DaemonMessage {
+    // Requests
+    Create { request_id: u64, spec: SessionSpec },
+    List { request_id: u64 },
+    Attach { request_id: u64, session_id: u64 },
+    Detach { session_id: u64 },
+    Input { session_id: u64, data: Vec<u8> },
+    Resize { session_id: u64, size: TerminalSize },
+    Kill { session_id: u64 },
+    Shutdown,
+
+    // Responses and notifications
+    Created { request_id: u64, session: SessionInfo },
+    Sessions { request_id: u64, sessions: Vec<SessionInfo> },
+    Attached { request_id: u64, session: SessionInfo, replay: Vec<u8> },
+    Output { session_id: u64, data: Vec<u8> },
+    Exited { session_id: u64, exit_code: Option<i32> },
+    Error { request_id: u64, message: String },
+}
+
+impl DaemonMessage {
+    /// Request ID of a response.
+    fn request_id(&self) -> Option<u64> {
+        match self {
+            Self::Created { request_id, .. }
+            | Self::Sessions { request_id, .. }
+            | Self::Attached { request_id, .. }
+            | Self::Error { request_id, .. } => Some(*request_id),
+            _ => None,
+        }
+    }
+}
+
+/// Write a frame: `[length: 4 bytes][json payload]`.
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, msg: &DaemonMessage) -> Result<()> {
+    let json = serde_json::to_vec(msg)?;
+    if json.len() > MAX_FRAME_SIZE {
+        anyhow::bail!("Message too large: {} bytes", json.len());
+    }
+    writer.write_all(&(json.len() as u32).to_be_bytes()).await?;
+    writer.write_all(&json).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Read a frame. Returns `None` when the peer closed the connection.
+pub async fn read_message<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<DaemonMessage>> {
+    let mut header = [0u8; 4];
+    match reader.read_exact(&mut header).await {
+        Ok(_) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let length = u32::from_be_bytes(header) as usize;
+    if length > MAX_FRAME_SIZE {
+        anyhow::bail!("Frame too large: {} bytes", length);
+    }
+    let mut body = vec![0u8; length];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(serde_json::from_slice(&body)?))
+}
+
+/// The most recent output of a session.
+#[derive(Debug)]
+pub struct ReplayBuffer {
+    data: VecDeque<u8>,
+    max_bytes: usize,
+}
+
+impl ReplayBuffer {
+    /// Create a buffer keeping at most `max_bytes`.
+    pub fn new(max_bytes: usize) -> Self {
+        Self { data: VecDeque::new(), max_bytes }
+    }
+
+    /// Append output, dropping the oldest whole lines when full.
+    pub fn push(&mut self, data: &[u8]) {
+        self.data.extend(data);
+        if self.data.len() > self.max_bytes {
+            self.data.drain(..self.data.len() - self.max_bytes);
+            // Start the replay on a line boundary when there is one
+            if let Some(newline) = self.data.iter().position(|&b| b == b'\n') {
+                self.data.drain(..=newline);
+            }
+        }
+    }
+
+    /// Buffered output.
+    pub fn contents(&self) -> Vec<u8> {
+        self.data.iter().copied().collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+}
+
+/// A PTY owned by the daemon.
+struct Session {
+    info: SessionInfo,
+    pty: Pty,
+    input: mpsc::UnboundedSender<Vec<u8>>,
+    /// Locked while output is recorded and sent, so attach sees each chunk
+    /// either in the replay or live, never both.
+    replay: Arc<Mutex<ReplayBuffer>>,
+    output: broadcast::Sender<DaemonMessage>,
+    /// The PTY reached end of file; all output has been read.
+    eof: Arc<AtomicBool>,
+    /// Exit status, once the process has been reaped.
+    exit_code: Option<i32>,
+}
+
+impl Session {
+    fn info(&self) -> SessionInfo {
+        SessionInfo { attached: self.output.receiver_count(), ..self.info.clone() }
+    }
+}
+
+/// Headless process owning terminal sessions.
+pub struct TerminalDaemon {
+    socket_path: PathBuf,
+    sessions: RwLock<HashMap<u64, Session>>,
+    next_id: AtomicU64,
+    replay_bytes: usize,
+    shutdown: Notify,
+}
+
+impl TerminalDaemon {
+    /// Create a daemon listening on `socket_path`.
+    pub fn new(socket_path: impl Into<PathBuf>) -> Self {
+        Self {
+            socket_path: socket_path.into(),
+            sessions: RwLock::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+            replay_bytes: DEFAULT_REPLAY_BYTES,
+            shutdown: Notify::new(),
+        }
+    }
+
+    /// Set how much output each session keeps for replay.
+    pub fn with_replay_bytes(mut self, bytes: usize) -> Self {
+        self.replay_bytes = bytes;
+        self
+    }
+
+    /// Get the socket path.
+    pub fn socket_path(&self) -> &Path {
+        &self.socket_path
+    }
+
+    /// Serve clients until a `Shutdown` message arrives.
+    pub async fn run(self: Arc<Self>) -> Result<()> {
+        let listener = self.bind().await?;
+        tracing::info!("Terminal daemon listening on {}", self.socket_path.display());
+
+        let reaper = tokio::spawn({
+            let daemon = Arc::clone(&self);
+            async move {
+                let mut interval = tokio::time::interval(REAP_INTERVAL);
+                loop {
+                    interval.tick().await;
+                    daemon.reap();
+                }
+            }
+        });
+
+        loop {
+            tokio::select! {
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, _)) => {
+                        tokio::spawn(Arc::clone(&self).serve(stream));
+                    }
+                    Err(e) => tracing::warn!("Terminal daemon accept failed: {}", e),
+                },
+                _ = self.shutdown.notified() => break,
+            }
+        }
+
+        reaper.abort();
+        for (_, session) in self.sessions.write().drain() {
+            let _ = session.pty.kill();
+        }
+        let _ = std::fs::remove_file(&self.socket_path);
+        Ok(())
+    }
+
+    /// Bind the socket, replacing a stale one left by a dead daemon.
+    async fn bind(&self) -> Result<UnixListener> {
+        if let Some(dir) = self.socket_path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        if self.socket_path.exists() {
+            if UnixStream::connect(&self.socket_path).await.is_ok() {
+                anyhow::bail!("A terminal daemon is already listening on {}", self.socket_path.display());
+            }
+            std::fs::remove_file(&self.socket_path)?;
+        }
+        let listener = UnixListener::bind(&self.socket_path)
+            .with_context(|| format!("Failed to bind {}", self.socket_path.display()))?;
+
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&self.socket_path, std::fs::Permissions::from_mode(0o600))?;
+        Ok(listener)
+    }
+
+    /// Drop sessions whose process exited and whose output has been read,
+    /// and tell attached clients.
+    fn reap(&self) {
+        let mut sessions = self.sessions.write();
+        let mut finished = Vec::new();
+        for (id, session) in sessions.iter_mut() {
+            if session.exit_code.is_none() {
+                session.exit_code = session.pty.try_wait();
+            }
+            if session.exit_code.is_some() && session.eof.load(Ordering::SeqCst) {
+                finished.push(*id);
+            }
+        }
+        for id in finished {
+            if let Some(session) = sessions.remove(&id) {
+                let exit_code = session.exit_code.filter(|code| *code >= 0);
+                let _ = session.output.send(DaemonMessage::Exited { session_id: id, exit_code });
+            }
+        }
+    }
+
+    /// Handle one client connection.
+    async fn serve(self: Arc<Self>, stream: UnixStream) {
+        let (mut reader, mut writer) = stream.into_split();
+        let (out_tx, mut out_rx) = mpsc::unbounded_channel::<DaemonMessage>();
+        let writer_task = tokio::spawn(async move {
+            while let Some(msg) = out_rx.recv().await {
+                if write_message(&mut writer, &msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut attachments: HashMap<u64, JoinHandle<()>> = HashMap::new();
+        loop {
+            let msg = match read_message(&mut reader).await {
+                Ok(Some(msg)) => msg,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("Terminal daemon client error: {}", e);
+                    break;
+                }
+            };
+
+            match msg {
+                DaemonMessage::Create { request_id, spec } => {
+                    let response = match self.create(spec).await {
+                        Ok(session) => DaemonMessage::Created { request_id, session },
+                        Err(e) => DaemonMessage::Error { request_id, message: e.to_string() },
+                    };
+                    let _ = out_tx.send(response);
+                }
+                DaemonMessage::List { request_id } => {
+                    let mut sessions: Vec<SessionInfo> = self.sessions.read().values().map(Session::info).collect();
+                    sessions.sort_by_key(|s| s.id);
+                    let _ = out_tx.send(DaemonMessage::Sessions { request_id, sessions });
+                }
+                DaemonMessage::Attach { request_id, session_id } => {
+                    if let Some(task) = attachments.remove(&session_id) {
+                        task.abort();
+                    }
+                    match self.attach(session_id) {
+                        Some((session, replay, mut rx)) => {
+                            let _ = out_tx.send(DaemonMessage::Attached { request_id, session, replay });
+                            let out_tx = out_tx.clone();
+                            let task = tokio::spawn(async move {
+                                loop {
+                                    match rx.recv().await {
+                                        Ok(msg) => {
+                                            if out_tx.send(msg).is_err() {
+                                                break;
+                                            }
+                                        }
+                                        Err(broadcast::error::RecvError::Lagged(n)) => {
+                                            tracing::warn!("Terminal client lagged, dropped {} chunks", n);
+                                        }
+                                        Err(broadcast::error::RecvError::Closed) => break,
+                                    }
+                                }
+                            });
+                            attachments.insert(session_id, task);
+                        }
+                        None => {
+                            let message = format!("No such session: {}", session_id);
+                            let _ = out_tx.send(DaemonMessage::Error { request_id, message });
+                        }
+                    }
+                }
+                DaemonMessage::Detach { session_id } => {
+                    if let Some(task) = attachments.remove(&session_id) {
+                        task.abort();
+                    }
+                }
+                DaemonMessage::Input { session_id, data } => {
+                    if let Some(session) = self.sessions.read().get(&session_id) {
+                        let _ = session.input.send(data);
+                    }
+                }
+                DaemonMessage::Resize { session_id, size } => {
+                    if let Some(session) = self.sessions.write().get_mut(&session_id) {
+                        session.info.size = size;
+                        let _ = session.pty.resize(size.rows, size.cols);
+                    }
+                }
+                DaemonMessage::Kill { session_id } => {
+                    // Forget the session right away: its input goes nowhere
+                    // and the PTY closes once the process group is gone
+                    let session = self.sessions.write().remove(&session_id);
+                    if let Some(session) = session {
+                        let _ = session.pty.kill();
+                        let _ = session.output.send(DaemonMessage::Exited { session_id, exit_code: None });
+                    }
+                }
+                DaemonMessage::Shutdown => self.shutdown.notify_one(),
+                other => tracing::warn!("Unexpected message from terminal client: {:?}", other),
+            }
+        }
+
+        // Sessions keep running after the client goes away
+        for (_, task) in attachments {
+            task.abort();
+        }
+        writer_task.abort();
+    }
+
+    /// Start a session.
+    async fn create(&self, spec: SessionSpec) -> Result<SessionInfo> {
+        let id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let replay = Arc::new(Mutex::new(ReplayBuffer::new(self.replay_bytes)));
+        let (output, _) = broadcast::channel(1024);
+        let eof = Arc::new(AtomicBool::new(false));
+
+        let (pty, input) = Pty::spawn(&spec.shell, &spec.args, &spec.cwd, &spec.env, spec.size, {
+            let replay = Arc::clone(&replay);
+            let output = output.clone();
+            let eof = Arc::clone(&eof);
+            move |data: &[u8]| {
+                if data.is_empty() {
+                    eof.store(true, Ordering::SeqCst);
+                    return;
+                }
+                let mut replay = replay.lock();
+                replay.push(data);
+                let _ = output.send(DaemonMessage::Output { session_id: id, data: data.to_vec() });
+            }
+        })
+        .await?;
+
+        let info = SessionInfo {
+            id,
+            title: spec.title,
+            shell: spec.shell,
+            cwd: spec.cwd,
+            size: spec.size,
+            pid: pty.pid(),
+            started: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            attached: 0,
+        };
+        tracing::info!("Started terminal session {} ({})", id, info.shell);
+        self.sessions.write().insert(id, Session {
+            info: info.clone(),
+            pty,
+            input,
+            replay,
+            output,
+            eof,
+            exit_code: None,
+        });
+        Ok(info)
+    }
+
+    /// Snapshot the replay and subscribe to live output.
+    fn attach(&self, session_id: u64) -> Option<(SessionInfo, Vec<u8>, broadcast::Receiver<DaemonMessage>)> {
+        let sessions = self.sessions.read();
+        let session = sessions.get(&session_id)?;
+        let replay = session.replay.lock();
+        let rx = session.output.subscribe();
+        Some((session.info(), replay.contents(), rx))
+    }
+}
+
+type OutputSink = Box<dyn Fn(&[u8]) + Send + Sync>;
+
+struct ClientInner {
+    out: mpsc::UnboundedSender<DaemonMessage>,
+    pending: Mutex<HashMap<u64, oneshot::Sender<DaemonMessage>>>,
+    outputs: Mutex<HashMap<u64, OutputSink>>,
+    exits: broadcast::Sender<(u64, Option<i32>)>,
+    next_id: AtomicU64,
+}
+
+/// Connection from the editor to the terminal daemon.
+#[derive(Clone)]
+pub struct DaemonClient {
+    inner: Arc<ClientInner>,
+}
+
+impl DaemonClient {
+    /// Connect to a running daemon.
+    pub async fn connect(socket_path: &Path) -> Result<Self> {
+        let stream = UnixStream::connect(socket_path)
+            .await
+            .with_context(|| format!("Failed to connect to {}", socket_path.display()))?;
+        let (mut reader, mut writer) = stream.into_split();
+
+        let (out, mut out_rx) = mpsc::unbounded_channel::<DaemonMessage>();
+        let (exits, _) = broadcast::channel(64);
+        let inner = Arc::new(ClientInner {
+            out,
+            pending: Mutex::new(HashMap::new()),
+            outputs: Mutex::new(HashMap::new()),
+            exits,
+            next_id: AtomicU64::new(1),
+        });
+
+        tokio::spawn(async move {
+            while let Some(msg) = out_rx.recv().await {
+                if write_message(&mut writer, &msg).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        let weak = Arc::downgrade(&inner);
+        tokio::spawn(async move {
+            while let Ok(Some(msg)) = read_message(&mut reader).await {
+                let Some(inner) = weak.upgrade() else { break };
+                inner.dispatch(msg);
+            }
+            if let Some(inner) = weak.upgrade() {
+                // Fail outstanding requests
+                inner.pending.lock().clear();
+            }
+        });
+
+        Ok(Self { inner })
+    }
+
+    /// Connect to the daemon, starting `program` first if none is running.
+    pub async fn connect_or_spawn(socket_path: &Path, program: &Path) -> Result<Self> {
+        if let Ok(client) = Self::connect(socket_path).await {
+            return Ok(client);
+        }
+
+        use std::os::unix::process::CommandExt;
+        std::process::Command::new(program)
+            .arg(socket_path)
+            .stdin(std::process::Stdio::null())
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            // Own process group, so it outlives the editor's terminal and signals
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("Failed to start {}", program.display()))?;
+
+        for _ in 0..100 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            if let Ok(client) = Self::connect(socket_path).await {
+                return Ok(client);
+            }
+        }
+        anyhow::bail!("Terminal daemon did not start listening on {}", socket_path.display())
+    }
+
+    /// Start a session.
+    pub async fn create(&self, spec: SessionSpec) -> Result<SessionInfo> {
+        match self.request(|request_id| DaemonMessage::Create { request_id, spec }).await? {
+            DaemonMessage::Created { session, .. } => Ok(session),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    /// List running sessions.
+    pub async fn list(&self) -> Result<Vec<SessionInfo>> {
+        match self.request(|request_id| DaemonMessage::List { request_id }).await? {
+            DaemonMessage::Sessions { sessions, .. } => Ok(sessions),
+            other => anyhow::bail!("Unexpected response: {:?}", other),
+        }
+    }
+
+    /// Attach to a session. `output` first receives the replayed output,
+    /// then live output. Returns the session and its input sender.
+    pub async fn attach(
+        &self,
+        session_id: u64,
+        output: impl Fn(&[u8]) + Send + Sync + 'static,
+    ) -> Result<(SessionInfo, mpsc::UnboundedSender<Vec<u8>>)> {
+        self.inner.outputs.lock().insert(session_id, Box::new(output));
+        let session = match self.request(|request_id| DaemonMessage::Attach { request_id, session_id }).await {
+            Ok(DaemonMessage::Attached { session, .. }) => session,
+            Ok(other) => {
+                self.inner.outputs.lock().remove(&session_id);
+                anyhow::bail!("Unexpected response: {:?}", other)
+            }
+            Err(e) => {
+                self.inner.outputs.lock().remove(&session_id);
+                return Err(e);
+            }
+        };
+
+        let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
+        let out = self.inner.out.clone();
+        tokio::spawn(async move {
+            while let Some(data) = input_rx.recv().await {
+                if out.send(DaemonMessage::Input { session_id, data }).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok((session, input_tx))
+    }
+
+    /// Stop receiving output; the session keeps running.
+    pub fn detach(&self, session_id: u64) {
+        self.inner.outputs.lock().remove(&session_id);
+        let _ = self.inner.out.send(DaemonMessage::Detach { session_id });
+    }
+
+    /// Resize a session.
+    pub fn resize(&self, session_id: u64, size: TerminalSize) {
+        let _ = self.inner.out.send(DaemonMessage::Resize { session_id, size });
+    }
+
+    /// Kill a session's process.
+    pub fn kill(&self, session_id: u64) {
+        let _ = self.inner.out.send(DaemonMessage::Kill { session_id });
+    }
+
+    /// Stop the daemon and every session it owns.
+    pub fn shutdown(&self) {
+        let _ = self.inner.out.send(DaemonMessage::Shutdown);
+    }
+
+    /// Subscribe to `(session_id, exit_code)` for attached sessions.
+    pub fn subscribe_exits(&self) -> broadcast::Receiver<(u64, Option<i32>)> {
+        self.inner.exits.subscribe()
+    }
+
+    async fn request(&self, build: impl FnOnce(u64) -> DaemonMessage) -> Result<DaemonMessage> {
+        let request_id = self.inner.next_id.fetch_add(1, Ordering::SeqCst);
+        let (tx, rx) = oneshot::channel();
+        self.inner.pending.lock().insert(request_id, tx);
+        self.inner
+            .out
+            .send(build(request_id))
+            .map_err(|_| anyhow::anyhow!("Terminal daemon connection closed"))?;
+
+        match rx.await.map_err(|_| anyhow::anyhow!("Terminal daemon connection closed"))? {
+            DaemonMessage::Error { message, .. } => anyhow::bail!(message),
+            response => Ok(response),
+        }
+    }
+}
+
+impl ClientInner {
+    fn dispatch(&self, msg: DaemonMessage) {
+        match &msg {
+            DaemonMessage::Output { session_id, data } => {
+                if let Some(output) = self.outputs.lock().get(session_id) {
+                    output(data);
+                }
+                return;
+            }
+            DaemonMessage::Exited { session_id, exit_code } => {
+                self.outputs.lock().remove(session_id);
+                let _ = self.exits.send((*session_id, *exit_code));
+                return;
+            }
+            DaemonMessage::Attached { session, replay, .. } => {
+                // Replay before completing the request, so it lands ahead of live output
+                if let Some(output) = self.outputs.lock().get(&session.id) {
+                    output(replay);
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(request_id) = msg.request_id()
+            && let Some(tx) = self.pending.lock().remove(&request_id)
+        {
+            let _ = tx.send(msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_buffer() {
+        let mut replay = ReplayBuffer::new(8);
+        replay.push(b"one\ntwo\n");
+        assert_eq!(replay.contents(), b"one\ntwo\n");
+        replay.push(b"three");
+        // Trimmed to the last 8 bytes, then to the next line start
+        assert_eq!(replay.contents(), b"three");
+    }
+
+    #[tokio::test]
+    async fn test_frame_roundtrip() {
+        let (mut a, mut b) = tokio::io::duplex(1024);
+        let msg = DaemonMessage::Output { session_id: 3, data: b"hi".to_vec() };
+        write_message(&mut a, &msg).await.unwrap();
+        drop(a);
+        assert!(matches!(
+            read_message(&mut b).await.unwrap(),
+            Some(DaemonMessage::Output { session_id: 3, data }) if data == b"hi"
+        ));
+        assert!(read_message(&mut b).await.unwrap().is_none());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_reattach_replays_output() {
+        let dir = std::env::temp_dir().join(format!("foxkit-daemon-test-{}", std::process::id()));
+        let socket = dir.join("daemon.sock");
+        let daemon = Arc::new(TerminalDaemon::new(&socket));
+        let server = tokio::spawn(Arc::clone(&daemon).run());
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let spec = SessionSpec {
+            shell: "/bin/sh".into(),
+            args: vec!["-c".into(), "echo persisted; read line; echo got $line".into()],
+            cwd: std::env::temp_dir(),
+            env: HashMap::new(),
+            size: TerminalSize::default(),
+            title: "test".into(),
+        };
+        let session = {
+            let client = DaemonClient::connect(&socket).await.unwrap();
+            client.create(spec).await.unwrap()
+            // The editor goes away here
+        };
+
+        let client = DaemonClient::connect(&socket).await.unwrap();
+        let sessions = client.list().await.unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].id, session.id);
+
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let (_, input) = client
+            .attach(session.id, {
+                let received = Arc::clone(&received);
+                move |data: &[u8]| received.lock().extend_from_slice(data)
+            })
+            .await
+            .unwrap();
+
+        let mut exits = client.subscribe_exits();
+        // Wait for the replayed output, then talk to the still-running shell
+        for _ in 0..200 {
+            if String::from_utf8_lossy(&received.lock()).contains("persisted") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        input.send(b"back\n".to_vec()).unwrap();
+
+        let (id, code) = tokio::time::timeout(Duration::from_secs(5), exits.recv()).await.unwrap().unwrap();
+        assert_eq!((id, code), (session.id, Some(0)));
+        let output = String::from_utf8_lossy(&received.lock()).to_string();
+        assert!(output.contains("persisted"));
+        assert!(output.contains("got back"));
+        assert!(client.list().await.unwrap().is_empty());
+
+        client.shutdown();
+        server.await.unwrap().unwrap();
+        assert!(!socket.exists());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_kill_interactive_shell() {
+        let dir = std::env::temp_dir().join(format!("foxkit-daemon-kill-{}", std::process::id()));
+        let socket = dir.join("daemon.sock");
+        let daemon = Arc::new(TerminalDaemon::new(&socket));
+        let server = tokio::spawn(Arc::clone(&daemon).run());
+        while !socket.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        // An interactive bash ignores SIGTERM
+        let spec = SessionSpec {
+            shell: "bash".into(),
+            args: vec!["--norc".into(), "--noprofile".into(), "-i".into()],
+            cwd: std::env::temp_dir(),
+            env: [("PS1".to_string(), "ready$ ".to_string())].into(),
+            size: TerminalSize::default(),
+            title: "test".into(),
+        };
+        let client = DaemonClient::connect(&socket).await.unwrap();
+        let session = client.create(spec.clone()).await.unwrap();
+        let received = Arc::new(Mutex::new(Vec::new()));
+        client
+            .attach(session.id, {
+                let received = Arc::clone(&received);
+                move |data: &[u8]| received.lock().extend_from_slice(data)
+            })
+            .await
+            .unwrap();
+        for _ in 0..500 {
+            if String::from_utf8_lossy(&received.lock()).contains("ready$") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let mut exits = client.subscribe_exits();
+        client.kill(session.id);
+        let (id, _) = tokio::time::timeout(Duration::from_secs(5), exits.recv()).await.unwrap().unwrap();
+        assert_eq!(id, session.id);
+        assert!(client.list().await.unwrap().is_empty());
+
+        // The shell is killed and reaped
+        let pid = nix::unistd::Pid::from_raw(session.pid);
+        let mut gone = false;
+        for _ in 0..250 {
+            if nix::sys::signal::kill(pid, None).is_err() {
+                gone = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert!(gone, "killed shell is still running or unreaped");
+
+        // Input for the killed session doesn't reach a new one
+        let other = client.create(spec).await.unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let (_, input) = client
+            .attach(other.id, {
+                let seen = Arc::clone(&seen);
+                move |data: &[u8]| seen.lock().extend_from_slice(data)
+            })
+            .await
+            .unwrap();
+        let _ = client.inner.out.send(DaemonMessage::Input { session_id: session.id, data: b"echo inj''ected\n".to_vec() });
+        input.send(b"echo d''one\n".to_vec()).unwrap();
+        for _ in 0..500 {
+            if String::from_utf8_lossy(&seen.lock()).contains("done") {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        let output = String::from_utf8_lossy(&seen.lock()).to_string();
+        assert!(output.contains("done"));
+        assert!(!output.contains("injected"));
+
+        client.shutdown();
+        tokio::time::timeout(Duration::from_secs(5), server).await.unwrap().unwrap().unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
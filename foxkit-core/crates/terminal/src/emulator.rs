@@ -6,8 +6,14 @@
 //! - SGR (Select Graphic Rendition) for colors/styles
 //! - Cursor movement and positioning
 //! - Screen manipulation (scrolling, clearing, etc.)
+//! - Inline images (sixel, kitty graphics, iTerm2)
 
+use crate::graphics::{
+    decode_sixel, ITermFile, ImagePlacement, ImageStore, KittyCommand, TerminalImage, VisibleImage,
+    MAX_PAYLOAD_BYTES,
+};
 use crate::screen::{Cell, CellStyle, Color, Screen};
+use crate::shell_integration::{CommandBlock, CommandTracker, ShellMark};
 use std::collections::VecDeque;
 
 /// Terminal emulator state machine states.
@@ -31,6 +37,8 @@ pub enum ParserState {
     DcsParam,
     /// DCS passthrough mode.
     DcsPassthrough,
+    /// APC (Application Program Command) string.
+    ApcString,
     /// Collecting UTF-8 continuation bytes.
     Utf8,
 }
@@ -187,6 +195,27 @@ pub enum TerminalEvent {
     DeviceAttributes,
     /// Terminal resize request.
     ResizeRequest { cols: u16, rows: u16 },
+    /// Switched to or from the alternate screen.
+    AlternateScreen { active: bool },
+    /// Shell integration mark (OSC 133/633/7).
+    ShellIntegration(ShellMark),
+    /// A command finished running.
+    CommandFinished(CommandBlock),
+    /// An image was placed on the screen.
+    ImagePlaced { image_id: u32 },
+    /// Reply to a kitty graphics command, to be written back to the program.
+    GraphicsReply(String),
+}
+
+/// Where the cursor goes after an image is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImageCursor {
+    /// Leave the cursor where it is.
+    Stay,
+    /// Move to the last row of the image, after its last column.
+    Beside,
+    /// Move to the start of the line below the image.
+    Below,
 }
 
 /// VT100/xterm terminal emulator.
@@ -195,8 +224,14 @@ pub struct TerminalEmulator {
     state: ParserState,
     /// Screen buffer.
     screen: Screen,
-    /// Alternate screen buffer.
+    /// Primary screen, parked while the alternate screen is active.
     alt_screen: Option<Screen>,
+    /// Window title (OSC 0/2).
+    title: Option<String>,
+    /// Command blocks from shell integration.
+    commands: CommandTracker,
+    /// Inline images and their placements.
+    images: ImageStore,
     /// Cursor state.
     cursor: Cursor,
     /// Saved cursor (primary screen).
@@ -219,6 +254,12 @@ pub struct TerminalEmulator {
     osc_string: String,
     /// OSC number.
     osc_number: u16,
+    /// DCS parameters.
+    dcs_params: String,
+    /// DCS final byte.
+    dcs_final: u8,
+    /// DCS or APC payload being collected.
+    string_payload: Vec<u8>,
     /// UTF-8 buffer for multi-byte sequences.
     utf8_buffer: Vec<u8>,
     /// UTF-8 bytes remaining.
@@ -245,8 +286,11 @@ impl TerminalEmulator {
 
         Self {
             state: ParserState::Ground,
-            screen: Screen::new(cols, rows),
+            screen: Screen::new(rows, cols),
             alt_screen: None,
+            title: None,
+            commands: CommandTracker::new(),
+            images: ImageStore::new(),
             cursor: Cursor::default(),
             saved_cursor: None,
             saved_cursor_alt: None,
@@ -258,6 +302,9 @@ impl TerminalEmulator {
             csi_command: CsiCommand::new(),
             osc_string: String::new(),
             osc_number: 0,
+            dcs_params: String::new(),
+            dcs_final: 0,
+            string_payload: Vec::new(),
             utf8_buffer: Vec::with_capacity(4),
             utf8_remaining: 0,
             events: VecDeque::new(),
@@ -268,6 +315,12 @@ impl TerminalEmulator {
 
     /// Process input bytes through the terminal emulator.
     pub fn process(&mut self, data: &[u8]) {
+        self.process_bytes(data);
+        self.sync_cursor();
+        self.images.prune(self.primary_screen().line_offset());
+    }
+
+    fn process_bytes(&mut self, data: &[u8]) {
         for &byte in data {
             self.process_byte(byte);
         }
@@ -286,6 +339,7 @@ impl TerminalEmulator {
             ParserState::DcsEntry => self.handle_dcs_entry(byte),
             ParserState::DcsParam => self.handle_dcs_param(byte),
             ParserState::DcsPassthrough => self.handle_dcs_passthrough(byte),
+            ParserState::ApcString => self.handle_apc_string(byte),
         }
     }
 
@@ -297,7 +351,7 @@ impl TerminalEmulator {
             0x07 => self.events.push_back(TerminalEvent::Bell),
             0x08 => self.cursor_back(1), // BS - backspace
             0x09 => self.tab(),           // HT - horizontal tab
-            0x0A | 0x0B | 0x0C => self.line_feed(), // LF, VT, FF
+            0x0A..=0x0C => self.line_feed(), // LF, VT, FF
             0x0D => self.carriage_return(), // CR
             0x0E => {} // SO - shift out (ignore)
             0x0F => {} // SI - shift in (ignore)
@@ -357,7 +411,16 @@ impl TerminalEmulator {
                 self.osc_number = 0;
                 self.state = ParserState::OscString;
             }
-            b'P' => self.state = ParserState::DcsEntry,
+            b'P' => {
+                self.dcs_params.clear();
+                self.dcs_final = 0;
+                self.string_payload.clear();
+                self.state = ParserState::DcsEntry;
+            }
+            b'_' => {
+                self.string_payload.clear();
+                self.state = ParserState::ApcString;
+            }
             b'7' => self.save_cursor(),  // DECSC
             b'8' => self.restore_cursor(), // DECRC
             b'D' => {
@@ -483,7 +546,7 @@ impl TerminalEmulator {
                 self.osc_number = self.osc_number * 10 + (byte - b'0') as u16;
             }
             _ => {
-                if byte >= 0x20 {
+                if byte >= 0x20 && self.osc_string.len() < MAX_PAYLOAD_BYTES {
                     self.osc_string.push(byte as char);
                 }
             }
@@ -496,7 +559,7 @@ impl TerminalEmulator {
             self.utf8_buffer.push(byte);
             self.utf8_remaining -= 1;
             if self.utf8_remaining == 0 {
-                if let Ok(s) = std::str::from_utf8(&self.utf8_buffer) {
+                if let Ok(s) = String::from_utf8(std::mem::take(&mut self.utf8_buffer)) {
                     for c in s.chars() {
                         self.print_char(c);
                     }
@@ -512,8 +575,14 @@ impl TerminalEmulator {
     /// Handle DCS entry.
     fn handle_dcs_entry(&mut self, byte: u8) {
         match byte {
-            b'0'..=b'9' | b';' => self.state = ParserState::DcsParam,
-            0x40..=0x7E => self.state = ParserState::DcsPassthrough,
+            b'0'..=b'9' | b';' => {
+                self.dcs_params.push(byte as char);
+                self.state = ParserState::DcsParam;
+            }
+            0x40..=0x7E => {
+                self.dcs_final = byte;
+                self.state = ParserState::DcsPassthrough;
+            }
             _ => self.state = ParserState::Ground,
         }
     }
@@ -521,7 +590,11 @@ impl TerminalEmulator {
     /// Handle DCS parameters.
     fn handle_dcs_param(&mut self, byte: u8) {
         match byte {
-            0x40..=0x7E => self.state = ParserState::DcsPassthrough,
+            b'0'..=b'9' | b';' => self.dcs_params.push(byte as char),
+            0x40..=0x7E => {
+                self.dcs_final = byte;
+                self.state = ParserState::DcsPassthrough;
+            }
             0x1B => self.state = ParserState::Escape,
             _ => {}
         }
@@ -529,8 +602,26 @@ impl TerminalEmulator {
 
     /// Handle DCS passthrough.
     fn handle_dcs_passthrough(&mut self, byte: u8) {
-        if byte == 0x1B || byte == 0x9C {
-            self.state = ParserState::Ground;
+        match byte {
+            0x1B | 0x9C => {
+                // ST, either ESC \ or the 8-bit form
+                self.execute_dcs();
+                self.state = if byte == 0x1B { ParserState::Escape } else { ParserState::Ground };
+            }
+            _ if self.string_payload.len() < MAX_PAYLOAD_BYTES => self.string_payload.push(byte),
+            _ => {}
+        }
+    }
+
+    /// Handle APC string collection.
+    fn handle_apc_string(&mut self, byte: u8) {
+        match byte {
+            0x1B | 0x9C => {
+                self.execute_apc();
+                self.state = if byte == 0x1B { ParserState::Escape } else { ParserState::Ground };
+            }
+            _ if self.string_payload.len() < MAX_PAYLOAD_BYTES => self.string_payload.push(byte),
+            _ => {}
         }
     }
 
@@ -561,6 +652,7 @@ impl TerminalEmulator {
             b'S' => self.scroll_up(cmd.param1() as usize),
             b'T' => self.scroll_down(cmd.param1() as usize),
             b'X' => self.erase_chars(cmd.param1() as usize),
+            b'c' if cmd.param(0, 0) == 0 => self.events.push_back(TerminalEvent::DeviceAttributes),
             b'd' => self.cursor_row(cmd.param1() as usize),
             b'g' => self.clear_tabs(cmd.param(0, 0)),
             b'm' => self.execute_sgr(),
@@ -578,7 +670,7 @@ impl TerminalEmulator {
         let cmd = &self.csi_command;
         let set = cmd.final_byte == b'h';
         
-        for &param in &cmd.params {
+        for param in cmd.params.clone() {
             match param {
                 1 => self.set_mode(TerminalMode::CursorKeys, set),
                 6 => self.set_mode(TerminalMode::Origin, set),
@@ -592,7 +684,17 @@ impl TerminalEmulator {
                 1004 => self.set_mode(TerminalMode::MouseFocus, set),
                 1005 => self.set_mode(TerminalMode::MouseUtf8, set),
                 1006 => self.set_mode(TerminalMode::MouseSgr, set),
-                1049 => self.set_alternate_screen(set),
+                47 | 1047 => self.set_alternate_screen(set),
+                1049 => {
+                    // Save the cursor on the primary screen, restore it on exit
+                    if set {
+                        self.save_cursor();
+                    }
+                    self.set_alternate_screen(set);
+                    if !set {
+                        self.restore_cursor();
+                    }
+                }
                 2004 => self.set_mode(TerminalMode::BracketedPaste, set),
                 _ => {}
             }
@@ -616,7 +718,7 @@ impl TerminalEmulator {
                 3 => self.current_style.italic = true,
                 4 => self.current_style.underline = true,
                 5 | 6 => self.current_style.blink = true,
-                7 => self.current_style.reverse = true,
+                7 => self.current_style.inverse = true,
                 8 => self.current_style.hidden = true,
                 9 => self.current_style.strikethrough = true,
                 21 => self.current_style.bold = false,
@@ -627,35 +729,35 @@ impl TerminalEmulator {
                 23 => self.current_style.italic = false,
                 24 => self.current_style.underline = false,
                 25 => self.current_style.blink = false,
-                27 => self.current_style.reverse = false,
+                27 => self.current_style.inverse = false,
                 28 => self.current_style.hidden = false,
                 29 => self.current_style.strikethrough = false,
                 
                 // Foreground colors
-                30..=37 => self.current_style.fg = Color::Indexed((params[i] - 30) as u8),
+                30..=37 => self.current_style.foreground = Color::Indexed((params[i] - 30) as u8),
                 38 => {
                     if let Some(color) = self.parse_extended_color(&params[i..]) {
-                        self.current_style.fg = color;
+                        self.current_style.foreground = color;
                         i += self.extended_color_params(&params[i..]);
                     }
                 }
-                39 => self.current_style.fg = Color::Default,
+                39 => self.current_style.foreground = Color::Default,
                 
                 // Background colors
-                40..=47 => self.current_style.bg = Color::Indexed((params[i] - 40) as u8),
+                40..=47 => self.current_style.background = Color::Indexed((params[i] - 40) as u8),
                 48 => {
                     if let Some(color) = self.parse_extended_color(&params[i..]) {
-                        self.current_style.bg = color;
+                        self.current_style.background = color;
                         i += self.extended_color_params(&params[i..]);
                     }
                 }
-                49 => self.current_style.bg = Color::Default,
+                49 => self.current_style.background = Color::Default,
                 
                 // Bright foreground colors
-                90..=97 => self.current_style.fg = Color::Indexed((params[i] - 90 + 8) as u8),
+                90..=97 => self.current_style.foreground = Color::Indexed((params[i] - 90 + 8) as u8),
                 
                 // Bright background colors
-                100..=107 => self.current_style.bg = Color::Indexed((params[i] - 100 + 8) as u8),
+                100..=107 => self.current_style.background = Color::Indexed((params[i] - 100 + 8) as u8),
                 
                 _ => {}
             }
@@ -696,6 +798,7 @@ impl TerminalEmulator {
         match self.osc_number {
             0 => {
                 // Set icon name and window title
+                self.title = Some(self.osc_string.clone());
                 self.events.push_back(TerminalEvent::TitleChanged(self.osc_string.clone()));
                 self.events.push_back(TerminalEvent::IconNameChanged(self.osc_string.clone()));
             }
@@ -705,6 +808,7 @@ impl TerminalEmulator {
             }
             2 => {
                 // Set window title
+                self.title = Some(self.osc_string.clone());
                 self.events.push_back(TerminalEvent::TitleChanged(self.osc_string.clone()));
             }
             8 => {
@@ -723,6 +827,22 @@ impl TerminalEmulator {
                 };
                 self.events.push_back(TerminalEvent::Hyperlink { url, id });
             }
+            7 => {
+                // Working directory: OSC 7 ; file://host/path ST
+                if let Some(mark) = ShellMark::parse_cwd(&self.osc_string) {
+                    self.shell_mark(mark);
+                }
+            }
+            133 | 633 => {
+                // Shell integration: OSC 133 ; A|B|C|D ST (633 adds E and P)
+                if let Some(mark) = ShellMark::parse(&self.osc_string) {
+                    self.shell_mark(mark);
+                }
+            }
+            1337 => {
+                // iTerm2 inline image: OSC 1337 ; File=args : base64 ST
+                self.execute_iterm_file();
+            }
             52 => {
                 // Clipboard: OSC 52 ; clipboard ; base64-data ST
                 let parts: Vec<&str> = self.osc_string.splitn(2, ';').collect();
@@ -737,19 +857,193 @@ impl TerminalEmulator {
         }
     }
 
+    /// Execute a DCS string. Only sixel (final byte q) is supported.
+    fn execute_dcs(&mut self) {
+        if self.dcs_final != b'q' {
+            return;
+        }
+        let payload = std::mem::take(&mut self.string_payload);
+        if let Some(image) = decode_sixel(&self.dcs_params, &payload) {
+            let span = image.cell_span(self.images.cell_size());
+            let id = self.images.add(image);
+            self.place_image(id, 0, span, 0, ImageCursor::Below);
+        }
+    }
+
+    /// Execute an APC string. Only kitty graphics commands are supported.
+    fn execute_apc(&mut self) {
+        let payload = std::mem::take(&mut self.string_payload);
+        let Some(command) = KittyCommand::parse(&payload) else { return };
+        let Some(command) = self.images.kitty_chunk(command) else { return };
+        let result = self.kitty_command(&command);
+        if let Some(reply) = command.reply(result) {
+            self.events.push_back(TerminalEvent::GraphicsReply(reply));
+        }
+    }
+
+    /// Run a complete kitty graphics command.
+    fn kitty_command(&mut self, command: &KittyCommand) -> Result<(), &'static str> {
+        match command.action {
+            'q' => command.decode_image().map(|_| ()),
+            't' | 'T' => {
+                let image = command.decode_image()?;
+                let id = if command.image_id != 0 {
+                    self.images.insert(command.image_id, image);
+                    command.image_id
+                } else {
+                    self.images.add(image)
+                };
+                if command.action == 'T' {
+                    self.kitty_place(id, command)
+                } else {
+                    Ok(())
+                }
+            }
+            'p' => self.kitty_place(command.image_id, command),
+            'd' => {
+                self.kitty_delete(command);
+                Ok(())
+            }
+            _ => Err("EINVAL:unsupported action"),
+        }
+    }
+
+    /// Place a stored image for a kitty command.
+    fn kitty_place(&mut self, image_id: u32, command: &KittyCommand) -> Result<(), &'static str> {
+        let image = self.images.image(image_id).ok_or("ENOENT:no such image")?;
+        let (cols, rows) = image.cell_span(self.images.cell_size());
+        let span = (
+            if command.cols > 0 { command.cols as usize } else { cols },
+            if command.rows > 0 { command.rows as usize } else { rows },
+        );
+        let cursor = if command.keep_cursor { ImageCursor::Stay } else { ImageCursor::Beside };
+        self.place_image(image_id, command.placement_id, span, command.z_index, cursor);
+        Ok(())
+    }
+
+    /// Delete kitty placements; upper-case targets also free the image data.
+    fn kitty_delete(&mut self, command: &KittyCommand) {
+        let alternate = self.alt_screen.is_some();
+        let top = self.screen.absolute_line(0);
+        let mut removed = Vec::new();
+        self.images.remove_placements(|p| {
+            let hit = match command.delete.to_ascii_lowercase() {
+                'a' => p.alternate == alternate && p.line + p.rows > top,
+                'i' => {
+                    p.image_id == command.image_id
+                        && (command.placement_id == 0 || p.placement_id == command.placement_id)
+                }
+                _ => false,
+            };
+            if hit {
+                removed.push(p.image_id);
+            }
+            hit
+        });
+
+        if command.delete.is_ascii_uppercase() {
+            if command.delete == 'I' {
+                removed.push(command.image_id);
+            }
+            for id in removed {
+                if !self.images.placements().iter().any(|p| p.image_id == id) {
+                    self.images.delete_image(id);
+                }
+            }
+        }
+    }
+
+    /// Execute an iTerm2 inline image.
+    fn execute_iterm_file(&mut self) {
+        let Some(file) = ITermFile::parse(&self.osc_string) else { return };
+        if !file.inline {
+            // Downloads are not supported
+            return;
+        }
+        let Some(image) = TerminalImage::decode(&file.data) else { return };
+        let span = file.cell_span(&image, self.images.cell_size(), (self.cols, self.rows));
+        let id = self.images.add(image);
+        self.place_image(id, 0, span, 0, ImageCursor::Below);
+    }
+
+    /// Place an image at the cursor and move the cursor past it.
+    fn place_image(
+        &mut self,
+        image_id: u32,
+        placement_id: u32,
+        (cols, rows): (usize, usize),
+        z_index: i32,
+        cursor: ImageCursor,
+    ) {
+        let col = self.cursor.col;
+        self.images.place(ImagePlacement {
+            image_id,
+            placement_id,
+            line: self.screen.absolute_line(self.cursor.row),
+            col,
+            cols,
+            rows,
+            z_index,
+            alternate: self.alt_screen.is_some(),
+        });
+        self.events.push_back(TerminalEvent::ImagePlaced { image_id });
+
+        match cursor {
+            ImageCursor::Stay => {}
+            ImageCursor::Beside => {
+                for _ in 1..rows {
+                    self.line_feed();
+                }
+                self.cursor.col = (col + cols).min(self.cols - 1);
+            }
+            ImageCursor::Below => {
+                for _ in 0..rows {
+                    self.line_feed();
+                }
+                self.carriage_return();
+            }
+        }
+    }
+
+    /// Drop image placements on the visible part of the current screen.
+    fn clear_visible_images(&mut self) {
+        let alternate = self.alt_screen.is_some();
+        let top = self.screen.absolute_line(0);
+        self.images.remove_placements(|p| p.alternate == alternate && p.line + p.rows > top);
+    }
+
+    /// Record a shell integration mark at the cursor.
+    fn shell_mark(&mut self, mark: ShellMark) {
+        let screen = self.primary_screen();
+        let row = if self.alt_screen.is_some() { screen.cursor().0 } else { self.cursor.row };
+        let line = screen.absolute_line(row);
+        let col = self.cursor.col;
+        let first_line = screen.line_offset();
+
+        if mark == ShellMark::CommandExecuted {
+            // Fall back to the typed text when the shell doesn't send 633 E
+            let screen = self.alt_screen.as_ref().unwrap_or(&self.screen);
+            self.commands.fill_command(|block| screen.text_range(block.command_start, (line, col)));
+        }
+        self.commands.prune(first_line);
+        let finished = self.commands.handle(mark.clone(), line, col).cloned();
+
+        self.events.push_back(TerminalEvent::ShellIntegration(mark));
+        if let Some(block) = finished {
+            self.events.push_back(TerminalEvent::CommandFinished(block));
+        }
+    }
+
     /// Execute window manipulation sequence.
     fn execute_window_manipulation(&mut self) {
         let cmd = &self.csi_command;
-        match cmd.param(0, 0) {
-            8 => {
-                // Resize terminal
-                let rows = cmd.param(1, 0);
-                let cols = cmd.param(2, 0);
-                if rows > 0 && cols > 0 {
-                    self.events.push_back(TerminalEvent::ResizeRequest { cols, rows });
-                }
+        if cmd.param(0, 0) == 8 {
+            // Resize terminal
+            let rows = cmd.param(1, 0);
+            let cols = cmd.param(2, 0);
+            if rows > 0 && cols > 0 {
+                self.events.push_back(TerminalEvent::ResizeRequest { cols, rows });
             }
-            _ => {}
         }
     }
 
@@ -825,11 +1119,21 @@ impl TerminalEmulator {
                 }
                 self.erase_line(1);
             }
-            2 | 3 => {
-                // Erase entire display (3 also clears scrollback)
+            2 => {
+                // Erase entire display
                 for row in 0..self.rows {
                     self.screen.clear_row(row);
                 }
+                self.clear_visible_images();
+            }
+            3 => {
+                // Erase the scrollback only; the visible display is kept
+                self.screen.clear_scrollback();
+                if self.alt_screen.is_none() {
+                    let first_line = self.screen.line_offset();
+                    self.images.prune(first_line);
+                    self.commands.prune(first_line);
+                }
             }
             _ => {}
         }
@@ -1010,17 +1314,19 @@ impl TerminalEmulator {
 
     fn set_alternate_screen(&mut self, enable: bool) {
         if enable && self.alt_screen.is_none() {
-            let main = std::mem::replace(&mut self.screen, Screen::new(self.cols, self.rows));
+            let main = std::mem::replace(&mut self.screen, Screen::alternate(self.rows, self.cols));
             self.alt_screen = Some(main);
             self.cursor = Cursor::default();
         } else if !enable && self.alt_screen.is_some() {
             if let Some(main) = self.alt_screen.take() {
                 self.screen = main;
             }
-            if let Some(saved) = self.saved_cursor.clone() {
-                self.cursor = saved.cursor;
-            }
+            self.images.clear_alternate();
+        } else {
+            return;
         }
+        self.set_mode(TerminalMode::AlternateScreen, enable);
+        self.events.push_back(TerminalEvent::AlternateScreen { active: enable });
     }
 
     fn set_scroll_region(&mut self, top: usize, bottom: usize) {
@@ -1052,6 +1358,7 @@ impl TerminalEmulator {
         // Handle autowrap
         if self.cursor.col >= self.cols {
             if self.modes.contains(&TerminalMode::AutoWrap) {
+                self.screen.set_wrapped(self.cursor.row, true);
                 self.carriage_return();
                 self.line_feed();
             } else {
@@ -1060,7 +1367,7 @@ impl TerminalEmulator {
         }
 
         let cell = Cell {
-            c,
+            char: c,
             style: self.current_style.clone(),
         };
         self.screen.set_cell(self.cursor.row, self.cursor.col, cell);
@@ -1069,20 +1376,25 @@ impl TerminalEmulator {
 
     /// Reset the terminal to initial state.
     pub fn reset(&mut self) {
+        let (width, height) = self.images.cell_size();
         *self = Self::new(self.cols, self.rows);
+        self.images.set_cell_size(width, height);
     }
 
-    /// Resize the terminal.
+    /// Resize the terminal. The primary screen reflows soft-wrapped lines.
     pub fn resize(&mut self, cols: usize, rows: usize) {
         self.cols = cols;
         self.rows = rows;
-        self.screen.resize(cols, rows);
-        if let Some(ref mut alt) = self.alt_screen {
-            alt.resize(cols, rows);
+        self.sync_cursor();
+        self.screen.resize(rows, cols);
+        if let Some(ref mut main) = self.alt_screen {
+            main.resize(rows, cols);
         }
+        let (row, col) = self.screen.cursor();
+        self.cursor.row = row;
+        self.cursor.col = col;
+        self.scroll_top = 0;
         self.scroll_bottom = rows.saturating_sub(1);
-        self.cursor.col = self.cursor.col.min(cols.saturating_sub(1));
-        self.cursor.row = self.cursor.row.min(rows.saturating_sub(1));
         
         // Rebuild tab stops
         self.tab_stops.clear();
@@ -1101,6 +1413,65 @@ impl TerminalEmulator {
         &self.screen
     }
 
+    /// Get the primary screen, even while the alternate screen is active.
+    pub fn primary_screen(&self) -> &Screen {
+        self.alt_screen.as_ref().unwrap_or(&self.screen)
+    }
+
+    /// Check if the alternate screen is active.
+    pub fn is_alternate_screen(&self) -> bool {
+        self.alt_screen.is_some()
+    }
+
+    /// Set the scrollback size of the primary screen.
+    pub fn set_scrollback(&mut self, lines: usize) {
+        match self.alt_screen {
+            Some(ref mut main) => main.set_max_scrollback(lines),
+            None => self.screen.set_max_scrollback(lines),
+        }
+    }
+
+    /// Get command blocks from shell integration.
+    pub fn commands(&self) -> &CommandTracker {
+        &self.commands
+    }
+
+    /// Get the output of a finished command.
+    pub fn command_output(&self, block: &CommandBlock) -> Option<String> {
+        let (start, end) = (block.output_start?, block.output_end?);
+        Some(self.primary_screen().text_range(start, end))
+    }
+
+    /// Get the output of the last finished command.
+    pub fn last_command_output(&self) -> Option<String> {
+        self.commands.last_finished().and_then(|b| self.command_output(b))
+    }
+
+    /// Get inline images and their placements.
+    pub fn images(&self) -> &ImageStore {
+        &self.images
+    }
+
+    /// Set the cell size in pixels, used to size images in cells.
+    pub fn set_cell_size(&mut self, width: u32, height: u32) {
+        self.images.set_cell_size(width, height);
+    }
+
+    /// Image placements overlapping the current screen, for rendering.
+    pub fn visible_images(&self) -> Vec<VisibleImage> {
+        self.images.visible(self.screen.absolute_line(0), self.rows, self.alt_screen.is_some())
+    }
+
+    /// Get the window title set by the program, if any.
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    /// Mirror the cursor into the screen buffer.
+    fn sync_cursor(&mut self) {
+        self.screen.set_cursor(self.cursor.row, self.cursor.col, self.cursor.visible);
+    }
+
     /// Get the cursor state.
     pub fn cursor(&self) -> &Cursor {
         &self.cursor
@@ -1118,7 +1489,7 @@ impl TerminalEmulator {
 
     /// Get device attributes response (primary).
     pub fn device_attributes(&self) -> &'static str {
-        "\x1b[?62;c" // VT220
+        "\x1b[?62;4c" // VT220 with sixel graphics
     }
 }
 
@@ -1146,13 +1517,13 @@ mod tests {
     fn test_sgr_colors() {
         let mut emu = TerminalEmulator::new(80, 24);
         emu.process(b"\x1b[31m"); // Red foreground
-        assert!(matches!(emu.current_style.fg, Color::Indexed(1)));
+        assert!(matches!(emu.current_style.foreground, Color::Indexed(1)));
         
         emu.process(b"\x1b[38;5;200m"); // 256-color
-        assert!(matches!(emu.current_style.fg, Color::Indexed(200)));
+        assert!(matches!(emu.current_style.foreground, Color::Indexed(200)));
         
         emu.process(b"\x1b[38;2;255;128;0m"); // RGB
-        assert!(matches!(emu.current_style.fg, Color::Rgb(255, 128, 0)));
+        assert!(matches!(emu.current_style.foreground, Color::Rgb(255, 128, 0)));
     }
 
     #[test]
@@ -1169,4 +1540,114 @@ mod tests {
         let events = emu.take_events();
         assert!(events.iter().any(|e| matches!(e, TerminalEvent::TitleChanged(t) if t == "My Title")));
     }
+
+    #[test]
+    fn test_alternate_screen() {
+        let mut emu = TerminalEmulator::new(10, 3);
+        emu.process(b"shell");
+        emu.process(b"\x1b[?1049h");
+        assert!(emu.is_alternate_screen());
+        emu.process(b"vim");
+        assert_eq!(emu.screen().row_text(0), "vim       ");
+        assert_eq!(emu.primary_screen().row_text(0), "shell     ");
+
+        emu.process(b"\x1b[?1049l");
+        assert!(!emu.is_alternate_screen());
+        assert_eq!(emu.screen().row_text(0), "shell     ");
+        assert_eq!(emu.cursor.col, 5);
+        let events = emu.take_events();
+        assert!(events.iter().any(|e| matches!(e, TerminalEvent::AlternateScreen { active: false })));
+    }
+
+    #[test]
+    fn test_autowrap_reflow() {
+        let mut emu = TerminalEmulator::new(5, 3);
+        emu.process(b"abcdefg");
+        assert!(emu.screen().is_wrapped(0));
+
+        emu.resize(10, 3);
+        assert_eq!(emu.screen().row_text(0), "abcdefg   ");
+        assert_eq!((emu.cursor.row, emu.cursor.col), (0, 7));
+    }
+
+    #[test]
+    fn test_scrollback_clear() {
+        let mut emu = TerminalEmulator::new(10, 2);
+        emu.process(b"1\r\n2\r\n3\r\n");
+        assert_eq!(emu.screen().scrollback_len(), 2);
+        emu.process(b"\x1b[3J");
+        assert_eq!(emu.screen().scrollback_len(), 0);
+
+        // The visible content and cursor survive
+        assert_eq!(emu.screen().row_text(0).trim_end(), "3");
+        assert_eq!((emu.cursor.row, emu.cursor.col), (1, 0));
+        emu.process(b"4");
+        assert_eq!(emu.screen().row_text(1).trim_end(), "4");
+    }
+
+    #[test]
+    fn test_sixel_placement() {
+        let mut emu = TerminalEmulator::new(10, 3);
+        emu.set_cell_size(4, 6);
+        emu.process(b"x\r\ny\r\nab");
+        // 8x12 red image: two sixel bands of 8 columns
+        emu.process(b"\x1bP0;1q#1;2;100;0;0#1!8~-!8~\x1b\\text\r\n");
+
+        let placement = &emu.images().placements()[0];
+        assert_eq!((placement.line, placement.col, placement.cols, placement.rows), (2, 2, 2, 2));
+        let image = emu.images().image(placement.image_id).unwrap();
+        assert_eq!((image.width, image.height), (8, 12));
+        assert_eq!(image.pixel(7, 11), Some([255, 0, 0, 255]));
+
+        // The cursor moved below the image, scrolling it up
+        assert_eq!(emu.screen().row_text(1), "text      ");
+        assert_eq!(emu.visible_images()[0].row, -1);
+    }
+
+    #[test]
+    fn test_kitty_placement() {
+        let mut emu = TerminalEmulator::new(10, 5);
+        emu.set_cell_size(1, 1);
+        // 2x2 RGBA, transmitted as image 3 and displayed 4 cells wide
+        emu.process(b"\x1b_Ga=T,f=32,s=2,v=2,i=3,c=4;/wAA/wD/AP8AAP//AAAA/w==\x1b\\");
+
+        let placement = &emu.images().placements()[0];
+        assert_eq!((placement.image_id, placement.cols, placement.rows), (3, 4, 2));
+        assert_eq!(emu.images().image(3).unwrap().pixel(1, 1), Some([0, 0, 0, 255]));
+        assert_eq!((emu.cursor.row, emu.cursor.col), (1, 4));
+        let events = emu.take_events();
+        assert!(events.iter().any(|e| matches!(e, TerminalEvent::GraphicsReply(r) if r == "\x1b_Gi=3;OK\x1b\\")));
+
+        emu.process(b"\x1b_Ga=d,d=I,i=3\x1b\\");
+        assert!(emu.images().placements().is_empty());
+        assert!(emu.images().image(3).is_none());
+    }
+
+    #[test]
+    fn test_iterm_inline_image() {
+        use base64::Engine;
+
+        let mut png = Vec::new();
+        image::RgbaImage::from_pixel(30, 20, image::Rgba([0, 128, 255, 255]))
+            .write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let data = base64::engine::general_purpose::STANDARD.encode(&png);
+
+        let mut emu = TerminalEmulator::new(20, 10);
+        emu.set_cell_size(10, 10);
+        emu.process(format!("\x1b]1337;File=inline=1;height=4:{}\x07", data).as_bytes());
+
+        // 4 rows = 40px tall, so 60px wide = 6 columns
+        let placement = &emu.images().placements()[0];
+        assert_eq!((placement.cols, placement.rows), (6, 4));
+        assert_eq!((emu.cursor.row, emu.cursor.col), (4, 0));
+
+        // Images on the alternate screen go away with it
+        emu.process(b"\x1b[?1049h");
+        emu.process(format!("\x1b]1337;File=inline=1:{}\x07", data).as_bytes());
+        assert_eq!(emu.images().placements().len(), 2);
+        assert_eq!(emu.visible_images().len(), 1);
+        emu.process(b"\x1b[?1049l");
+        assert_eq!(emu.images().placements().len(), 1);
+    }
 }
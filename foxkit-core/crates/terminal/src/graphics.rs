@@ -0,0 +1,896 @@
+//! Inline terminal images.
+//!
+//! Decodes sixel (DCS q), the kitty graphics protocol (APC G) and iTerm2
+//! inline images (OSC 1337 File=) into RGBA images placed on cells.
+//! Placements are anchored to absolute screen lines, so they scroll with the
+//! text and leave with it when the scrollback drops those lines.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Read;
+use std::sync::Arc;
+
+use base64::Engine;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+
+/// Largest width or height accepted for a decoded image, in pixels.
+pub const MAX_IMAGE_DIMENSION: u32 = 10_000;
+
+/// Largest area accepted for a decoded image, in pixels (64 MiB of RGBA).
+pub const MAX_IMAGE_PIXELS: u64 = 16 * 1024 * 1024;
+
+/// Largest total size of the RGBA pixels an image store keeps, in bytes.
+/// The oldest images are evicted past it.
+pub const MAX_STORE_BYTES: usize = 320 * 1024 * 1024;
+
+/// Largest escape sequence payload collected for an image, in bytes.
+pub const MAX_PAYLOAD_BYTES: usize = 64 * 1024 * 1024;
+
+/// Image ids assigned by the terminal start here; lower ids belong to programs
+/// using the kitty protocol.
+const AUTO_ID_BASE: u32 = 1 << 31;
+
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &base64::alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// A decoded image.
+#[derive(Debug, Clone)]
+pub struct TerminalImage {
+    /// Width in pixels.
+    pub width: u32,
+    /// Height in pixels.
+    pub height: u32,
+    /// RGBA8 pixels, row by row.
+    pub rgba: Arc<Vec<u8>>,
+}
+
+impl TerminalImage {
+    /// Create an image from RGBA8 pixels.
+    pub fn new(width: u32, height: u32, rgba: Vec<u8>) -> Option<Self> {
+        if !image_size_allowed(width, height) {
+            return None;
+        }
+        if rgba.len() != (width * height * 4) as usize {
+            return None;
+        }
+        Some(Self { width, height, rgba: Arc::new(rgba) })
+    }
+
+    /// Decode a PNG, JPEG or GIF file.
+    pub fn decode(bytes: &[u8]) -> Option<Self> {
+        let mut reader = image::ImageReader::new(std::io::Cursor::new(bytes)).with_guessed_format().ok()?;
+        let mut limits = image::Limits::default();
+        limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+        limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+        limits.max_alloc = Some(MAX_IMAGE_PIXELS * 4);
+        reader.limits(limits);
+        let image = reader.decode().ok()?.into_rgba8();
+        let (width, height) = image.dimensions();
+        Self::new(width, height, image.into_raw())
+    }
+
+    /// Get the pixel at a position.
+    pub fn pixel(&self, x: u32, y: u32) -> Option<[u8; 4]> {
+        let i = ((y * self.width + x) * 4) as usize;
+        (x < self.width && y < self.height).then(|| [self.rgba[i], self.rgba[i + 1], self.rgba[i + 2], self.rgba[i + 3]])
+    }
+
+    /// Number of cells the image covers at a cell size.
+    pub fn cell_span(&self, cell_size: (u32, u32)) -> (usize, usize) {
+        (
+            self.width.div_ceil(cell_size.0.max(1)).max(1) as usize,
+            self.height.div_ceil(cell_size.1.max(1)).max(1) as usize,
+        )
+    }
+
+    /// Size of the pixel data in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.rgba.len()
+    }
+}
+
+/// Whether an image of this size may be decoded.
+fn image_size_allowed(width: u32, height: u32) -> bool {
+    width > 0
+        && height > 0
+        && width <= MAX_IMAGE_DIMENSION
+        && height <= MAX_IMAGE_DIMENSION
+        && width as u64 * height as u64 <= MAX_IMAGE_PIXELS
+}
+
+/// An image placed on the terminal grid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImagePlacement {
+    /// Placed image.
+    pub image_id: u32,
+    /// Kitty placement id (0 = none).
+    pub placement_id: u32,
+    /// Absolute line of the top row (see `Screen::line_offset`).
+    pub line: usize,
+    /// Left column.
+    pub col: usize,
+    /// Width in cells.
+    pub cols: usize,
+    /// Height in cells.
+    pub rows: usize,
+    /// Stacking order relative to text; negative values draw below it.
+    pub z_index: i32,
+    /// Placed on the alternate screen.
+    pub alternate: bool,
+}
+
+/// A placement relative to the visible screen, for rendering.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisibleImage {
+    pub image_id: u32,
+    /// Top row; negative when partly scrolled off the top.
+    pub row: isize,
+    pub col: usize,
+    pub cols: usize,
+    pub rows: usize,
+    pub z_index: i32,
+}
+
+/// Images and their placements.
+#[derive(Debug)]
+pub struct ImageStore {
+    images: HashMap<u32, TerminalImage>,
+    /// Image ids, oldest first.
+    order: VecDeque<u32>,
+    /// Total size of the stored pixels.
+    bytes: usize,
+    placements: Vec<ImagePlacement>,
+    next_id: u32,
+    /// Cell size in pixels.
+    cell_size: (u32, u32),
+    /// Kitty transfer waiting for more chunks.
+    pending_kitty: Option<KittyCommand>,
+}
+
+impl ImageStore {
+    /// Create an empty store.
+    pub fn new() -> Self {
+        Self {
+            images: HashMap::new(),
+            order: VecDeque::new(),
+            bytes: 0,
+            placements: Vec::new(),
+            next_id: AUTO_ID_BASE,
+            cell_size: (10, 20),
+            pending_kitty: None,
+        }
+    }
+
+    /// Get the cell size in pixels.
+    pub fn cell_size(&self) -> (u32, u32) {
+        self.cell_size
+    }
+
+    /// Set the cell size in pixels, used to size new placements.
+    pub fn set_cell_size(&mut self, width: u32, height: u32) {
+        self.cell_size = (width.max(1), height.max(1));
+    }
+
+    /// Store an image under a new id.
+    pub fn add(&mut self, image: TerminalImage) -> u32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(AUTO_ID_BASE);
+        self.insert(id, image);
+        id
+    }
+
+    /// Store an image under a program-chosen id, replacing the old one.
+    /// The oldest images and their placements are evicted to stay within
+    /// `MAX_STORE_BYTES`.
+    pub fn insert(&mut self, id: u32, image: TerminalImage) {
+        self.remove_image(id);
+        self.bytes += image.byte_len();
+        self.images.insert(id, image);
+        self.order.push_back(id);
+        while self.bytes > MAX_STORE_BYTES {
+            match self.order.front() {
+                Some(&oldest) if oldest != id => self.delete_image(oldest),
+                _ => break,
+            }
+        }
+    }
+
+    /// Total size of the stored pixels in bytes.
+    pub fn byte_len(&self) -> usize {
+        self.bytes
+    }
+
+    /// Get an image.
+    pub fn image(&self, id: u32) -> Option<&TerminalImage> {
+        self.images.get(&id)
+    }
+
+    /// Number of stored images.
+    pub fn image_count(&self) -> usize {
+        self.images.len()
+    }
+
+    /// Add a placement. A placement with the same image and placement id is
+    /// replaced.
+    pub fn place(&mut self, placement: ImagePlacement) {
+        if placement.placement_id != 0 {
+            self.placements.retain(|p| {
+                p.image_id != placement.image_id || p.placement_id != placement.placement_id
+            });
+        }
+        self.placements.push(placement);
+    }
+
+    /// All placements.
+    pub fn placements(&self) -> &[ImagePlacement] {
+        &self.placements
+    }
+
+    /// Remove placements matching a predicate.
+    pub fn remove_placements(&mut self, mut remove: impl FnMut(&ImagePlacement) -> bool) {
+        self.placements.retain(|p| !remove(p));
+        self.drop_unplaced();
+    }
+
+    /// Delete an image and its placements.
+    pub fn delete_image(&mut self, id: u32) {
+        self.remove_image(id);
+        self.placements.retain(|p| p.image_id != id);
+    }
+
+    fn remove_image(&mut self, id: u32) {
+        if let Some(image) = self.images.remove(&id) {
+            self.bytes -= image.byte_len();
+            self.order.retain(|&i| i != id);
+        }
+    }
+
+    /// Drop placements above the first retained scrollback line.
+    pub fn prune(&mut self, first_line: usize) {
+        let before = self.placements.len();
+        self.placements.retain(|p| p.alternate || p.line + p.rows > first_line);
+        if self.placements.len() != before {
+            self.drop_unplaced();
+        }
+    }
+
+    /// Drop all placements on the alternate screen.
+    pub fn clear_alternate(&mut self) {
+        self.remove_placements(|p| p.alternate);
+    }
+
+    /// Drop everything.
+    pub fn clear(&mut self) {
+        self.images.clear();
+        self.order.clear();
+        self.bytes = 0;
+        self.placements.clear();
+        self.pending_kitty = None;
+    }
+
+    /// Images sent inline (not by id) are only kept while placed.
+    fn drop_unplaced(&mut self) {
+        let unplaced: Vec<u32> = self.images
+            .keys()
+            .copied()
+            .filter(|&id| id >= AUTO_ID_BASE && !self.placements.iter().any(|p| p.image_id == id))
+            .collect();
+        for id in unplaced {
+            self.remove_image(id);
+        }
+    }
+
+    /// Placements overlapping the visible screen, bottom-most z first.
+    pub fn visible(&self, top_line: usize, rows: usize, alternate: bool) -> Vec<VisibleImage> {
+        let mut visible: Vec<VisibleImage> = self.placements
+            .iter()
+            .filter(|p| p.alternate == alternate)
+            .filter(|p| p.line + p.rows > top_line && p.line < top_line + rows)
+            .map(|p| VisibleImage {
+                image_id: p.image_id,
+                row: p.line as isize - top_line as isize,
+                col: p.col,
+                cols: p.cols,
+                rows: p.rows,
+                z_index: p.z_index,
+            })
+            .collect();
+        visible.sort_by_key(|v| v.z_index);
+        visible
+    }
+
+    /// Collect kitty chunks. Returns the command once the last chunk arrived.
+    pub fn kitty_chunk(&mut self, command: KittyCommand) -> Option<KittyCommand> {
+        let command = match self.pending_kitty.take() {
+            Some(mut pending) => {
+                if pending.payload.len() + command.payload.len() > MAX_PAYLOAD_BYTES {
+                    return None;
+                }
+                pending.payload.extend_from_slice(&command.payload);
+                pending.more = command.more;
+                pending
+            }
+            None => command,
+        };
+        if command.more {
+            self.pending_kitty = Some(command);
+            None
+        } else {
+            Some(command)
+        }
+    }
+}
+
+impl Default for ImageStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Sixel
+// ---------------------------------------------------------------------------
+
+/// VT340 default palette for the first 16 color registers.
+const SIXEL_PALETTE: [[u8; 3]; 16] = [
+    [0, 0, 0], [51, 51, 204], [204, 36, 36], [51, 204, 51],
+    [204, 51, 204], [51, 204, 204], [204, 204, 51], [120, 120, 120],
+    [69, 69, 69], [87, 87, 153], [153, 69, 69], [87, 153, 87],
+    [153, 87, 153], [87, 153, 153], [153, 153, 87], [204, 204, 204],
+];
+
+/// Decode sixel data. `params` are the DCS parameters before `q`; the second
+/// one selects a transparent background when it is 1.
+pub fn decode_sixel(params: &str, data: &[u8]) -> Option<TerminalImage> {
+    let transparent = params.split(';').nth(1) == Some("1");
+    let mut palette: Vec<[u8; 3]> = SIXEL_PALETTE.to_vec();
+    palette.resize(256, [0, 0, 0]);
+
+    let background = if transparent { [0, 0, 0, 0] } else {
+        let [r, g, b] = SIXEL_PALETTE[0];
+        [r, g, b, 255]
+    };
+    let mut canvas = SixelCanvas::new(background);
+    let mut color = 0usize;
+    let (mut x, mut y) = (0u32, 0u32);
+
+    let mut i = 0;
+    while i < data.len() {
+        let byte = data[i];
+        i += 1;
+        match byte {
+            b'"' => {
+                // Raster attributes: Pan ; Pad ; Ph ; Pv
+                let (nums, next) = sixel_numbers(data, i);
+                i = next;
+                if let [_, _, ph, pv, ..] = nums[..] {
+                    canvas.extend(ph.min(MAX_IMAGE_DIMENSION), pv.min(MAX_IMAGE_DIMENSION));
+                }
+            }
+            b'#' => {
+                // Color: # Pc (select) or # Pc ; Pu ; Px ; Py ; Pz (define)
+                let (nums, next) = sixel_numbers(data, i);
+                i = next;
+                let Some(&register) = nums.first() else { continue };
+                color = register as usize % palette.len();
+                if let [_, space, a, b, c, ..] = nums[..] {
+                    palette[color] = match space {
+                        1 => hls_to_rgb(a, b, c),
+                        _ => [percent(a), percent(b), percent(c)],
+                    };
+                }
+            }
+            b'!' => {
+                // Repeat: ! Pn <sixel>
+                let (nums, next) = sixel_numbers(data, i);
+                i = next;
+                if let Some(&sixel @ 0x3F..=0x7E) = data.get(i) {
+                    i += 1;
+                    let count = nums.first().copied().unwrap_or(1).max(1);
+                    for _ in 0..count {
+                        if x >= MAX_IMAGE_DIMENSION {
+                            break;
+                        }
+                        canvas.column(x, y, sixel, palette[color]);
+                        x += 1;
+                    }
+                }
+            }
+            b'$' => x = 0,
+            b'-' => {
+                x = 0;
+                y += 6;
+                if y >= MAX_IMAGE_DIMENSION {
+                    break;
+                }
+            }
+            0x3F..=0x7E if x < MAX_IMAGE_DIMENSION => {
+                canvas.column(x, y, byte, palette[color]);
+                x += 1;
+            }
+            _ => {}
+        }
+    }
+    canvas.finish()
+}
+
+/// RGBA buffer a sixel image is drawn into. It grows as pixels are set, but
+/// never past `MAX_IMAGE_DIMENSION` or `MAX_IMAGE_PIXELS`; pixels beyond
+/// that are dropped.
+struct SixelCanvas {
+    background: [u8; 4],
+    /// Allocated size.
+    width: u32,
+    height: u32,
+    rgba: Vec<u8>,
+    /// Size of the image: the raster attributes or the drawn pixels.
+    used: (u32, u32),
+}
+
+impl SixelCanvas {
+    fn new(background: [u8; 4]) -> Self {
+        Self { background, width: 0, height: 0, rgba: Vec::new(), used: (0, 0) }
+    }
+
+    /// Grow the image to at least `width` by `height`, if allowed.
+    fn extend(&mut self, width: u32, height: u32) -> bool {
+        let (width, height) = (width.max(self.used.0), height.max(self.used.1));
+        if !image_size_allowed(width.max(1), height.max(1)) {
+            return false;
+        }
+        if width > self.width || height > self.height {
+            // Grow geometrically to keep reallocations rare, but stay in bounds
+            let mut grown = (
+                width.max(self.width.saturating_mul(2)).min(MAX_IMAGE_DIMENSION),
+                height.max(self.height.saturating_mul(2)).min(MAX_IMAGE_DIMENSION),
+            );
+            if !image_size_allowed(grown.0, grown.1) {
+                grown = (width.max(self.width), height.max(self.height));
+            }
+            if !image_size_allowed(grown.0, grown.1) {
+                grown = (width, height);
+            }
+            self.resize(grown.0, grown.1);
+        }
+        self.used = (width, height);
+        true
+    }
+
+    fn resize(&mut self, width: u32, height: u32) {
+        let mut rgba = self.background.repeat((width * height) as usize);
+        let (copy_width, copy_height) = (self.width.min(width), self.height.min(height));
+        for row in 0..copy_height {
+            let from = (row * self.width * 4) as usize;
+            let to = (row * width * 4) as usize;
+            let len = (copy_width * 4) as usize;
+            rgba[to..to + len].copy_from_slice(&self.rgba[from..from + len]);
+        }
+        self.rgba = rgba;
+        self.width = width;
+        self.height = height;
+    }
+
+    /// Draw a sixel: six vertical pixels starting at `y`.
+    fn column(&mut self, x: u32, y: u32, sixel: u8, [r, g, b]: [u8; 3]) {
+        let bits = sixel - 0x3F;
+        for bit in 0..6 {
+            if bits & (1 << bit) != 0 && self.extend(x + 1, y + bit + 1) {
+                let offset = (((y + bit) * self.width + x) * 4) as usize;
+                self.rgba[offset..offset + 4].copy_from_slice(&[r, g, b, 255]);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Option<TerminalImage> {
+        let (width, height) = self.used;
+        if width == 0 || height == 0 {
+            return None;
+        }
+        if (width, height) != (self.width, self.height) {
+            self.resize(width, height);
+        }
+        TerminalImage::new(width, height, self.rgba)
+    }
+}
+
+/// Parse `;`-separated numbers starting at `i`.
+fn sixel_numbers(data: &[u8], mut i: usize) -> (Vec<u32>, usize) {
+    let mut nums = Vec::new();
+    let mut current: Option<u32> = None;
+    while let Some(&byte) = data.get(i) {
+        match byte {
+            b'0'..=b'9' => {
+                current = Some(current.unwrap_or(0).saturating_mul(10).saturating_add((byte - b'0') as u32));
+            }
+            b';' => nums.push(current.take().unwrap_or(0)),
+            _ => break,
+        }
+        i += 1;
+    }
+    if let Some(n) = current {
+        nums.push(n);
+    }
+    (nums, i)
+}
+
+fn percent(value: u32) -> u8 {
+    (value.min(100) * 255 / 100) as u8
+}
+
+/// Sixel HLS: hue 0 is blue, lightness and saturation are percentages.
+fn hls_to_rgb(hue: u32, lightness: u32, saturation: u32) -> [u8; 3] {
+    let h = ((hue + 240) % 360) as f64 / 360.0;
+    let l = lightness.min(100) as f64 / 100.0;
+    let s = saturation.min(100) as f64 / 100.0;
+    if s == 0.0 {
+        let v = (l * 255.0).round() as u8;
+        return [v, v, v];
+    }
+    let q = if l < 0.5 { l * (1.0 + s) } else { l + s - l * s };
+    let p = 2.0 * l - q;
+    let channel = |t: f64| {
+        let t = t.rem_euclid(1.0);
+        let v = if t < 1.0 / 6.0 {
+            p + (q - p) * 6.0 * t
+        } else if t < 0.5 {
+            q
+        } else if t < 2.0 / 3.0 {
+            p + (q - p) * (2.0 / 3.0 - t) * 6.0
+        } else {
+            p
+        };
+        (v * 255.0).round() as u8
+    };
+    [channel(h + 1.0 / 3.0), channel(h), channel(h - 1.0 / 3.0)]
+}
+
+// ---------------------------------------------------------------------------
+// Kitty graphics protocol
+// ---------------------------------------------------------------------------
+
+/// A kitty graphics command (`APC G keys ; payload ST`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KittyCommand {
+    /// Action: t (transmit), T (transmit and display), p (put), d (delete), q (query).
+    pub action: char,
+    /// Pixel format: 24 (RGB), 32 (RGBA) or 100 (PNG).
+    pub format: u32,
+    /// Transmission medium; only d (direct) is supported.
+    pub medium: char,
+    /// Width and height in pixels for raw formats.
+    pub width: u32,
+    pub height: u32,
+    /// Image id chosen by the program.
+    pub image_id: u32,
+    /// Placement id.
+    pub placement_id: u32,
+    /// More chunks follow.
+    pub more: bool,
+    /// Payload is zlib-compressed.
+    pub compressed: bool,
+    /// Requested size in cells (0 = from the image).
+    pub cols: u32,
+    pub rows: u32,
+    /// Stacking order.
+    pub z_index: i32,
+    /// Leave the cursor where it is.
+    pub keep_cursor: bool,
+    /// What to delete.
+    pub delete: char,
+    /// 1 = suppress OK replies, 2 = suppress all replies.
+    pub quiet: u32,
+    /// Base64 payload.
+    pub payload: Vec<u8>,
+}
+
+impl Default for KittyCommand {
+    fn default() -> Self {
+        Self {
+            action: 't',
+            format: 32,
+            medium: 'd',
+            width: 0,
+            height: 0,
+            image_id: 0,
+            placement_id: 0,
+            more: false,
+            compressed: false,
+            cols: 0,
+            rows: 0,
+            z_index: 0,
+            keep_cursor: false,
+            delete: 'a',
+            quiet: 0,
+            payload: Vec::new(),
+        }
+    }
+}
+
+impl KittyCommand {
+    /// Parse the APC payload, starting with `G`.
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let data = data.strip_prefix(b"G")?;
+        let (keys, payload) = match data.iter().position(|&b| b == b';') {
+            Some(i) => (&data[..i], &data[i + 1..]),
+            None => (data, &[][..]),
+        };
+
+        let mut command = Self { payload: payload.to_vec(), ..Self::default() };
+        for pair in std::str::from_utf8(keys).ok()?.split(',') {
+            let Some((key, value)) = pair.split_once('=') else { continue };
+            let number = || value.parse::<u32>().unwrap_or(0);
+            let letter = || value.chars().next().unwrap_or_default();
+            match key {
+                "a" => command.action = letter(),
+                "f" => command.format = number(),
+                "t" => command.medium = letter(),
+                "s" => command.width = number(),
+                "v" => command.height = number(),
+                "i" => command.image_id = number(),
+                "p" => command.placement_id = number(),
+                "m" => command.more = number() == 1,
+                "o" => command.compressed = value == "z",
+                "c" => command.cols = number(),
+                "r" => command.rows = number(),
+                "z" => command.z_index = value.parse().unwrap_or(0),
+                "C" => command.keep_cursor = number() == 1,
+                "d" => command.delete = letter(),
+                "q" => command.quiet = number(),
+                _ => {}
+            }
+        }
+        Some(command)
+    }
+
+    /// Decode the transmitted image.
+    pub fn decode_image(&self) -> Result<TerminalImage, &'static str> {
+        if self.medium != 'd' {
+            return Err("EINVAL:only direct transmission is supported");
+        }
+        let mut bytes = decode_base64(&self.payload).ok_or("EINVAL:bad base64 payload")?;
+        if self.compressed {
+            let mut inflated = Vec::new();
+            flate2::read::ZlibDecoder::new(bytes.as_slice())
+                .take(MAX_PAYLOAD_BYTES as u64)
+                .read_to_end(&mut inflated)
+                .map_err(|_| "EINVAL:bad zlib payload")?;
+            bytes = inflated;
+        }
+
+        let image = match self.format {
+            100 => TerminalImage::decode(&bytes),
+            24 => {
+                let rgba = bytes.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect();
+                TerminalImage::new(self.width, self.height, rgba)
+            }
+            32 => TerminalImage::new(self.width, self.height, bytes),
+            _ => return Err("EINVAL:unsupported format"),
+        };
+        image.ok_or("EBADF:could not decode image")
+    }
+
+    /// Reply for the program, unless it asked for quiet.
+    pub fn reply(&self, result: Result<(), &str>) -> Option<String> {
+        if self.image_id == 0 {
+            return None;
+        }
+        let message = match result {
+            Ok(()) if self.quiet == 0 => "OK",
+            Err(e) if self.quiet < 2 => e,
+            _ => return None,
+        };
+        Some(format!("\x1b_Gi={};{}\x1b\\", self.image_id, message))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// iTerm2 inline images
+// ---------------------------------------------------------------------------
+
+/// A size in an iTerm2 image request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageDimension {
+    Auto,
+    Cells(u32),
+    Pixels(u32),
+    Percent(u32),
+}
+
+impl ImageDimension {
+    fn parse(value: &str) -> Self {
+        if let Some(px) = value.strip_suffix("px") {
+            px.parse().map(Self::Pixels).unwrap_or(Self::Auto)
+        } else if let Some(pct) = value.strip_suffix('%') {
+            pct.parse().map(Self::Percent).unwrap_or(Self::Auto)
+        } else {
+            value.parse().map(Self::Cells).unwrap_or(Self::Auto)
+        }
+    }
+
+    /// Size in cells, or None for auto.
+    fn cells(self, cell: u32, available: usize) -> Option<usize> {
+        match self {
+            Self::Auto => None,
+            Self::Cells(n) => Some(n as usize),
+            Self::Pixels(px) => Some(px.div_ceil(cell.max(1)) as usize),
+            Self::Percent(p) => Some(available * p.min(100) as usize / 100),
+        }
+        .map(|n| n.max(1))
+    }
+}
+
+/// An iTerm2 `OSC 1337 ; File=args : base64 ST` request.
+#[derive(Debug, Clone)]
+pub struct ITermFile {
+    /// File name (decoded).
+    pub name: Option<String>,
+    pub width: ImageDimension,
+    pub height: ImageDimension,
+    pub preserve_aspect_ratio: bool,
+    /// Display the file; otherwise it is a download.
+    pub inline: bool,
+    /// File contents.
+    pub data: Vec<u8>,
+}
+
+impl ITermFile {
+    /// Parse the OSC 1337 payload.
+    pub fn parse(osc: &str) -> Option<Self> {
+        let rest = osc.strip_prefix("File=")?;
+        let (args, data) = rest.split_once(':')?;
+
+        let mut file = Self {
+            name: None,
+            width: ImageDimension::Auto,
+            height: ImageDimension::Auto,
+            preserve_aspect_ratio: true,
+            inline: false,
+            data: decode_base64(data.as_bytes())?,
+        };
+        for arg in args.split(';') {
+            let Some((key, value)) = arg.split_once('=') else { continue };
+            match key {
+                "name" => {
+                    file.name = decode_base64(value.as_bytes()).and_then(|n| String::from_utf8(n).ok());
+                }
+                "width" => file.width = ImageDimension::parse(value),
+                "height" => file.height = ImageDimension::parse(value),
+                "preserveAspectRatio" => file.preserve_aspect_ratio = value != "0",
+                "inline" => file.inline = value == "1",
+                _ => {}
+            }
+        }
+        Some(file)
+    }
+
+    /// Cells covered by the image on a screen `screen_cols` by `screen_rows`.
+    pub fn cell_span(&self, image: &TerminalImage, cell_size: (u32, u32), screen: (usize, usize)) -> (usize, usize) {
+        let natural = image.cell_span(cell_size);
+        let cols = self.width.cells(cell_size.0, screen.0);
+        let rows = self.height.cells(cell_size.1, screen.1);
+
+        // Scale the other side in pixel space to keep the aspect ratio
+        let aspect = |cells: usize, from: u32, to: u32, image_from: u32, image_to: u32| {
+            let pixels = cells as u64 * from as u64 * image_to as u64 / image_from.max(1) as u64;
+            (pixels.div_ceil(to.max(1) as u64) as usize).max(1)
+        };
+        match (cols, rows) {
+            (Some(c), Some(r)) => (c, r),
+            (Some(c), None) if self.preserve_aspect_ratio => {
+                (c, aspect(c, cell_size.0, cell_size.1, image.width, image.height))
+            }
+            (None, Some(r)) if self.preserve_aspect_ratio => {
+                (aspect(r, cell_size.1, cell_size.0, image.height, image.width), r)
+            }
+            (c, r) => (c.unwrap_or(natural.0), r.unwrap_or(natural.1)),
+        }
+    }
+}
+
+fn decode_base64(data: &[u8]) -> Option<Vec<u8>> {
+    let cleaned: Vec<u8> = data.iter().copied().filter(|b| !b.is_ascii_whitespace()).collect();
+    BASE64.decode(cleaned).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_sixel() {
+        // Red 2x6 column, then a green pixel on the next band
+        let image = decode_sixel("0;1", b"#1;2;100;0;0#1~~-#2;2;0;100;0@").unwrap();
+        assert_eq!((image.width, image.height), (2, 7));
+        assert_eq!(image.pixel(1, 5), Some([255, 0, 0, 255]));
+        assert_eq!(image.pixel(0, 6), Some([0, 255, 0, 255]));
+        assert_eq!(image.pixel(1, 6), Some([0, 0, 0, 0]));
+
+        let repeated = decode_sixel("", b"\"1;1;4;6!4~").unwrap();
+        assert_eq!((repeated.width, repeated.height), (4, 6));
+        assert_eq!(repeated.pixel(3, 0), Some([0, 0, 0, 255]));
+    }
+
+    #[test]
+    fn test_sixel_limits() {
+        // Raster attributes over the area cap are ignored, not allocated
+        let image = decode_sixel("", b"\"1;1;10000;10000~").unwrap();
+        assert_eq!((image.width, image.height), (1, 6));
+
+        // Drawing past the area cap drops the pixels instead of growing
+        let mut data = b"!10000~".to_vec();
+        for _ in 0..400 {
+            data.extend_from_slice(b"-~");
+        }
+        let image = decode_sixel("", &data).unwrap();
+        assert!(image.width as u64 * image.height as u64 <= MAX_IMAGE_PIXELS);
+        assert_eq!(image.width, 10000);
+    }
+
+    #[test]
+    fn test_store_quota() {
+        let mut store = ImageStore::new();
+        let side = 4096;
+        let big = || TerminalImage::new(side, side, vec![0; (side * side * 4) as usize]).unwrap();
+        let first = store.add(big());
+        store.insert(1, big());
+        for _ in 0..5 {
+            store.add(big());
+        }
+        assert!(store.byte_len() <= MAX_STORE_BYTES);
+        assert!(store.image(first).is_none());
+        assert!(store.image(1).is_none());
+
+        store.clear();
+        assert_eq!(store.byte_len(), 0);
+    }
+
+    #[test]
+    fn test_kitty_chunks() {
+        let mut store = ImageStore::new();
+        let rgb = BASE64.encode([255u8, 0, 0, 0, 0, 255]);
+        let (first, second) = rgb.split_at(4);
+
+        let chunk = KittyCommand::parse(format!("Ga=T,f=24,s=2,v=1,i=7,m=1;{}", first).as_bytes()).unwrap();
+        assert!(store.kitty_chunk(chunk).is_none());
+        let chunk = KittyCommand::parse(format!("Gm=0;{}", second).as_bytes()).unwrap();
+        let command = store.kitty_chunk(chunk).unwrap();
+        assert_eq!((command.action, command.image_id), ('T', 7));
+
+        let image = command.decode_image().unwrap();
+        assert_eq!(image.pixel(1, 0), Some([0, 0, 255, 255]));
+        assert_eq!(command.reply(Ok(())).as_deref(), Some("\x1b_Gi=7;OK\x1b\\"));
+    }
+
+    #[test]
+    fn test_iterm_file() {
+        let png = {
+            let mut bytes = Vec::new();
+            let image = image::RgbaImage::from_pixel(20, 40, image::Rgba([1, 2, 3, 255]));
+            image.write_to(&mut std::io::Cursor::new(&mut bytes), image::ImageFormat::Png).unwrap();
+            bytes
+        };
+        let osc = format!("File=name={};width=4;inline=1:{}", BASE64.encode("plot.png"), BASE64.encode(&png));
+        let file = ITermFile::parse(&osc).unwrap();
+        assert_eq!(file.name.as_deref(), Some("plot.png"));
+        assert!(file.inline);
+
+        let image = TerminalImage::decode(&file.data).unwrap();
+        assert_eq!(image.pixel(0, 0), Some([1, 2, 3, 255]));
+        // 4 cells wide = 40px, so 80px tall = 4 rows of 20px
+        assert_eq!(file.cell_span(&image, (10, 20), (80, 24)), (4, 4));
+    }
+
+    #[test]
+    fn test_store_prune() {
+        let mut store = ImageStore::new();
+        let id = store.add(TerminalImage::new(1, 1, vec![0; 4]).unwrap());
+        store.place(ImagePlacement {
+            image_id: id, placement_id: 0, line: 5, col: 0, cols: 1, rows: 2, z_index: 0, alternate: false,
+        });
+        assert_eq!(store.visible(6, 10, false)[0].row, -1);
+        store.prune(7);
+        assert!(store.placements().is_empty());
+        assert_eq!(store.image_count(), 0);
+    }
+}
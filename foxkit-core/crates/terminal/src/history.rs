@@ -135,6 +135,8 @@ pub enum SearchMode {
     Substring,
     /// Fuzzy matching.
     Fuzzy,
+    /// Regular expression match (case-sensitive).
+    Regex,
 }
 
 /// Terminal command history manager.
@@ -183,12 +185,11 @@ impl History {
         }
 
         // Handle deduplication
-        if self.config.dedupe_consecutive {
-            if let Some(last) = self.entries.front() {
-                if last.command == entry.command {
-                    return;
-                }
-            }
+        if self.config.dedupe_consecutive
+            && let Some(last) = self.entries.front()
+            && last.command == entry.command
+        {
+            return;
         }
 
         if self.config.dedupe_all {
@@ -204,20 +205,19 @@ impl History {
         }
 
         // Add to directory history if enabled
-        if self.config.per_directory {
-            if let Some(ref cwd) = entry.cwd {
-                let dir_entries = self.dir_history.entry(cwd.clone()).or_default();
-                if self.config.dedupe_consecutive {
-                    if let Some(last) = dir_entries.front() {
-                        if last.command == entry.command {
-                            return;
-                        }
-                    }
-                }
-                dir_entries.push_front(entry);
-                while dir_entries.len() > self.config.max_entries / 10 {
-                    dir_entries.pop_back();
-                }
+        if self.config.per_directory
+            && let Some(ref cwd) = entry.cwd
+        {
+            let dir_entries = self.dir_history.entry(cwd.clone()).or_default();
+            if self.config.dedupe_consecutive
+                && let Some(last) = dir_entries.front()
+                && last.command == entry.command
+            {
+                return;
+            }
+            dir_entries.push_front(entry);
+            while dir_entries.len() > self.config.max_entries / 10 {
+                dir_entries.pop_back();
             }
         }
 
@@ -290,6 +290,7 @@ impl History {
     }
 
     /// Navigate to next command (down arrow).
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Option<&HistoryEntry> {
         match self.position {
             None => None,
@@ -318,6 +319,13 @@ impl History {
     pub fn search(&self, query: &str, mode: SearchMode, max_results: usize) -> Vec<SearchResult> {
         let mut results = Vec::new();
         let query_lower = query.to_lowercase();
+        let regex = match mode {
+            SearchMode::Regex => match regex::Regex::new(query) {
+                Ok(regex) => Some(regex),
+                Err(_) => return results,
+            },
+            _ => None,
+        };
 
         for (index, entry) in self.entries.iter().enumerate() {
             let command_lower = entry.command.to_lowercase();
@@ -331,13 +339,13 @@ impl History {
                     }
                 }
                 SearchMode::Substring | SearchMode::Backward | SearchMode::Forward => {
-                    if let Some(pos) = command_lower.find(&query_lower) {
-                        Some(vec![(pos, pos + query.len())])
-                    } else {
-                        None
-                    }
+                    command_lower.find(&query_lower).map(|pos| vec![(pos, pos + query.len())])
                 }
                 SearchMode::Fuzzy => self.fuzzy_match(&command_lower, &query_lower),
+                SearchMode::Regex => regex
+                    .as_ref()
+                    .and_then(|re| re.find(&entry.command))
+                    .map(|m| vec![(m.start(), m.end())]),
             };
 
             if let Some(match_positions) = matches {
@@ -592,8 +600,10 @@ mod tests {
 
     #[test]
     fn test_dedupe_consecutive() {
-        let mut config = HistoryConfig::default();
-        config.dedupe_consecutive = true;
+        let config = HistoryConfig {
+            dedupe_consecutive: true,
+            ..Default::default()
+        };
         
         let mut history = History::with_config(config);
         history.add_command("ls");
@@ -637,6 +647,18 @@ mod tests {
         assert_eq!(results.len(), 1);
     }
 
+    #[test]
+    fn test_regex_search() {
+        let mut history = History::new();
+        history.add_command("cargo test -p terminal");
+        history.add_command("cargo build");
+
+        let results = history.search(r"-p \w+", SearchMode::Regex, 10);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].match_positions, vec![(11, 22)]);
+        assert!(history.search("(", SearchMode::Regex, 10).is_empty());
+    }
+
     #[test]
     fn test_ignore_space_prefix() {
         let mut history = History::new();
@@ -6,22 +6,44 @@
 //! - Live process visualization
 //! - Debug + terminal fusion
 //! - Task orchestration integration
-
+//! - Scrollback with reflow and search
+
+#[cfg(unix)]
+pub mod daemon;
+pub mod emulator;
+pub mod graphics;
+pub mod history;
+pub mod links;
+pub mod package_env;
+pub mod profiles;
 pub mod pty;
 pub mod screen;
 pub mod shell;
+pub mod shell_integration;
 pub mod task;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::collections::HashMap;
 use parking_lot::RwLock;
-use tokio::sync::mpsc;
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, mpsc};
 use anyhow::Result;
-
+use monorepo::package::Package;
+use workspace_trust::WorkspaceTrustService;
+
+#[cfg(unix)]
+pub use daemon::{DaemonClient, SessionInfo, SessionSpec, TerminalDaemon};
+pub use emulator::{Cursor, CursorShape, TerminalEmulator, TerminalEvent, TerminalMode};
+pub use graphics::{ImagePlacement, ImageStore, TerminalImage, VisibleImage};
+pub use history::{History, HistoryConfig, HistoryEntry, SearchMode, SearchResult, SharedHistory};
+pub use links::{LinkDetector, LinkTarget, TerminalLink};
+pub use package_env::{EnvFile, EnvFileKind, PackageEnvironment, PinnedToolchain};
+pub use profiles::{ProfileManager, ScrollbackConfig, ShellConfig, TerminalProfile};
 pub use pty::Pty;
-pub use screen::{Screen, Cell, CellStyle};
+pub use screen::{Screen, ScreenMatch, Cell, CellStyle};
 pub use shell::Shell;
+pub use shell_integration::{CommandBlock, CommandStatus, CommandTracker, ShellIntegration, ShellKind, ShellMark};
 pub use task::{Task, TaskStatus};
 
 /// Terminal instance
@@ -34,10 +56,19 @@ pub struct Terminal {
     cwd: PathBuf,
     /// Environment variables
     env: HashMap<String, String>,
+    /// Shell to spawn when none is given
+    shell: Option<String>,
     /// PTY handle
     pty: Option<Pty>,
-    /// Screen buffer
-    screen: Arc<RwLock<Screen>>,
+    /// Daemon session owning the PTY instead
+    #[cfg(unix)]
+    daemon: Option<(DaemonClient, u64)>,
+    /// Emulator, events and history fed by the PTY
+    output: TerminalOutput,
+    /// Inject shell integration when spawning?
+    shell_integration: bool,
+    /// Link detector
+    link_detector: LinkDetector,
     /// Input sender
     input_tx: Option<mpsc::UnboundedSender<Vec<u8>>>,
     /// Terminal dimensions
@@ -46,14 +77,39 @@ pub struct Terminal {
     active: bool,
     /// Associated package (for monorepo awareness)
     package: Option<String>,
+    /// Environment set up for the package
+    package_env: Option<PackageEnvironment>,
+    /// Private directory holding the injected shell integration scripts
+    integration_dir: Option<tempfile::TempDir>,
 }
 
 /// Terminal identifier
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct TerminalId(pub u64);
 
+impl TerminalId {
+    /// Generate a new unique terminal ID
+    pub fn new() -> Self {
+        use std::sync::atomic::{AtomicU64, Ordering};
+        static COUNTER: AtomicU64 = AtomicU64::new(1);
+        Self(COUNTER.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl Default for TerminalId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for TerminalId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "terminal-{}", self.0)
+    }
+}
+
 /// Terminal dimensions
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TerminalSize {
     pub rows: u16,
     pub cols: u16,
@@ -68,26 +124,46 @@ impl Default for TerminalSize {
 impl Terminal {
     /// Create a new terminal
     pub fn new(id: TerminalId) -> Self {
+        let size = TerminalSize::default();
+        let (events, _) = broadcast::channel(256);
         Self {
             id,
             title: String::from("Terminal"),
             cwd: std::env::current_dir().unwrap_or_else(|_| PathBuf::from("/")),
             env: std::env::vars().collect(),
+            shell: None,
             pty: None,
-            screen: Arc::new(RwLock::new(Screen::new(24, 80))),
+            #[cfg(unix)]
+            daemon: None,
+            output: TerminalOutput {
+                emulator: Arc::new(RwLock::new(TerminalEmulator::new(size.cols as usize, size.rows as usize))),
+                events,
+                history: SharedHistory::new(),
+                replies: Arc::new(RwLock::new(None)),
+            },
+            shell_integration: true,
+            link_detector: LinkDetector::new(),
             input_tx: None,
-            size: TerminalSize::default(),
+            size,
             active: false,
             package: None,
+            package_env: None,
+            integration_dir: None,
         }
     }
 
-    /// Create a terminal for a specific package in the monorepo
-    pub fn for_package(id: TerminalId, package_name: &str, package_path: PathBuf) -> Self {
+    /// Create a terminal for a specific package in the monorepo, with the
+    /// package's tools, virtualenv, toolchains and environment files set up.
+    /// Environment files not yet allowed are requested through `trust`
+    pub async fn for_package(id: TerminalId, package: &Package, workspace_root: &Path, trust: &WorkspaceTrustService) -> Self {
         let mut term = Self::new(id);
-        term.title = format!("Terminal: {}", package_name);
-        term.cwd = package_path;
-        term.package = Some(package_name.to_string());
+        term.title = format!("Terminal: {}", package.name);
+        term.cwd = package.path.clone();
+        term.package = Some(package.name.clone());
+
+        let environment = PackageEnvironment::detect(package, workspace_root, &term.env, trust).await;
+        environment.apply(&mut term.env);
+        term.package_env = Some(environment);
         term
     }
 
@@ -106,33 +182,148 @@ impl Terminal {
     /// Set terminal size
     pub fn with_size(mut self, rows: u16, cols: u16) -> Self {
         self.size = TerminalSize { rows, cols };
+        self.output.emulator.write().resize(cols as usize, rows as usize);
+        self
+    }
+
+    /// Record finished commands in a shared history
+    pub fn with_history(mut self, history: SharedHistory) -> Self {
+        self.output.history = history;
+        self
+    }
+
+    /// Enable or disable shell integration injection
+    pub fn with_shell_integration(mut self, enabled: bool) -> Self {
+        self.shell_integration = enabled;
         self
     }
 
+    /// Set scrollback size in lines
+    pub fn with_scrollback(self, lines: usize) -> Self {
+        self.output.emulator.write().set_scrollback(lines);
+        self
+    }
+
+    /// Apply a profile's shell, cwd, env, size and scrollback
+    pub fn with_profile(mut self, profile: &TerminalProfile) -> Self {
+        self.title = profile.name.clone();
+        self.shell = Some(profile.shell.path.to_string_lossy().into_owned());
+        if let Some(cwd) = &profile.cwd {
+            self.cwd = cwd.clone();
+        }
+        self.env.extend(profile.env.clone());
+        let scrollback = if profile.scrollback.enabled { profile.scrollback.lines } else { 0 };
+        self.with_size(profile.size.rows, profile.size.cols)
+            .with_scrollback(scrollback)
+    }
+
     /// Spawn shell process
     pub async fn spawn(&mut self, shell: Option<&str>) -> Result<()> {
-        let shell = shell.unwrap_or_else(|| {
-            std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()).leak()
-        });
-
+        let (shell, args, env) = self.launch_command(shell);
         tracing::info!("Spawning terminal with shell: {}", shell);
 
         // Create PTY
         let (pty, input_tx) = Pty::spawn(
-            shell,
+            &shell,
+            &args,
             &self.cwd,
-            &self.env,
+            &env,
             self.size,
-            Arc::clone(&self.screen),
+            {
+                let output = self.output.clone();
+                move |data: &[u8]| output.process(data)
+            },
         ).await?;
 
         self.pty = Some(pty);
+        *self.output.replies.write() = Some(input_tx.clone());
         self.input_tx = Some(input_tx);
         self.active = true;
 
         Ok(())
     }
 
+    /// Spawn the shell in the terminal daemon, so it survives editor restarts
+    #[cfg(unix)]
+    pub async fn spawn_in_daemon(&mut self, client: &DaemonClient, shell: Option<&str>) -> Result<u64> {
+        let (shell, args, env) = self.launch_command(shell);
+        tracing::info!("Spawning daemon terminal with shell: {}", shell);
+
+        let session = client.create(SessionSpec {
+            shell,
+            args,
+            cwd: self.cwd.clone(),
+            env,
+            size: self.size,
+            title: self.title.clone(),
+        }).await?;
+        self.attach(client, session.id).await?;
+        Ok(session.id)
+    }
+
+    /// Attach to a daemon session; its recent output is replayed first
+    #[cfg(unix)]
+    pub async fn attach(&mut self, client: &DaemonClient, session_id: u64) -> Result<()> {
+        let output = self.output.clone();
+        let (_, input_tx) = client.attach(session_id, move |data| output.process(data)).await?;
+
+        self.daemon = Some((client.clone(), session_id));
+        *self.output.replies.write() = Some(input_tx.clone());
+        self.input_tx = Some(input_tx);
+        self.active = true;
+        Ok(())
+    }
+
+    /// Detach from the daemon session, leaving its process running
+    #[cfg(unix)]
+    pub fn detach(&mut self) {
+        if let Some((client, session_id)) = self.daemon.take() {
+            client.detach(session_id);
+        }
+        *self.output.replies.write() = None;
+        self.input_tx = None;
+        self.active = false;
+    }
+
+    /// Get the daemon session backing this terminal
+    #[cfg(unix)]
+    pub fn session_id(&self) -> Option<u64> {
+        self.daemon.as_ref().map(|(_, id)| *id)
+    }
+
+    /// Shell, arguments and environment to launch, with shell integration
+    fn launch_command(&mut self, shell: Option<&str>) -> (String, Vec<String>, HashMap<String, String>) {
+        let shell = shell.map(str::to_string)
+            .or_else(|| self.shell.clone())
+            .unwrap_or_else(|| std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string()));
+
+        // Load shell integration alongside the user's startup files
+        let mut args = Vec::new();
+        let mut env = self.env.clone();
+        if self.shell_integration
+            && let Some(integration) = ShellConfig::new(&shell).integration()
+        {
+            // A fresh mkdtemp directory rather than a predictable path in the
+            // shared temp dir, kept alive as long as the terminal
+            let injected = tempfile::Builder::new()
+                .prefix("foxkit-shell-integration-")
+                .tempdir()
+                .and_then(|temp| {
+                    let injection = integration.inject(&temp.path().join("integration"))?;
+                    Ok((temp, injection))
+                });
+            match injected {
+                Ok((temp, injection)) => {
+                    args = injection.args;
+                    env.extend(injection.env);
+                    self.integration_dir = Some(temp);
+                }
+                Err(e) => tracing::warn!("Failed to inject shell integration: {}", e),
+            }
+        }
+        (shell, args, env)
+    }
+
     /// Write input to terminal
     pub fn write(&self, data: &[u8]) -> Result<()> {
         if let Some(tx) = &self.input_tx {
@@ -147,14 +338,28 @@ impl Terminal {
         self.write(s.as_bytes())
     }
 
+    /// Feed process output through the emulator
+    pub fn process(&self, data: &[u8]) {
+        self.output.process(data);
+    }
+
+    /// Subscribe to emulator events
+    pub fn subscribe(&self) -> broadcast::Receiver<TerminalEvent> {
+        self.output.events.subscribe()
+    }
+
     /// Resize terminal
     pub fn resize(&mut self, rows: u16, cols: u16) -> Result<()> {
         self.size = TerminalSize { rows, cols };
-        self.screen.write().resize(rows as usize, cols as usize);
+        self.output.emulator.write().resize(cols as usize, rows as usize);
         
         if let Some(pty) = &self.pty {
             pty.resize(rows, cols)?;
         }
+        #[cfg(unix)]
+        if let Some((client, session_id)) = &self.daemon {
+            client.resize(*session_id, self.size);
+        }
         
         Ok(())
     }
@@ -169,6 +374,14 @@ impl Terminal {
         &self.title
     }
 
+    /// Get the title set by the running program, or the terminal title
+    pub fn display_title(&self) -> String {
+        self.output.emulator.read().title()
+            .filter(|t| !t.is_empty())
+            .map(str::to_string)
+            .unwrap_or_else(|| self.title.clone())
+    }
+
     /// Set terminal title
     pub fn set_title(&mut self, title: impl Into<String>) {
         self.title = title.into();
@@ -179,9 +392,61 @@ impl Terminal {
         &self.cwd
     }
 
-    /// Get screen for rendering
-    pub fn screen(&self) -> &Arc<RwLock<Screen>> {
-        &self.screen
+    /// Get the working directory last reported by the shell
+    pub fn current_cwd(&self) -> PathBuf {
+        self.output.emulator.read().commands().cwd()
+            .map(PathBuf::from)
+            .unwrap_or_else(|| self.cwd.clone())
+    }
+
+    /// Get the emulator; its active screen is what gets rendered
+    pub fn emulator(&self) -> &Arc<RwLock<TerminalEmulator>> {
+        &self.output.emulator
+    }
+
+    /// Get the output of the last finished command
+    pub fn last_command_output(&self) -> Option<String> {
+        self.output.emulator.read().last_command_output()
+    }
+
+    /// Get the closest prompt line above `line`
+    pub fn previous_prompt(&self, line: usize) -> Option<usize> {
+        self.output.emulator.read().commands().previous_prompt(line)
+    }
+
+    /// Get the closest prompt line below `line`
+    pub fn next_prompt(&self, line: usize) -> Option<usize> {
+        self.output.emulator.read().commands().next_prompt(line)
+    }
+
+    /// Get exit status marks for the gutter, keyed by prompt line
+    pub fn command_marks(&self) -> Vec<(usize, CommandStatus)> {
+        self.output.emulator.read().commands().gutter_marks()
+    }
+
+    /// Search scrollback and the primary screen
+    pub fn search_scrollback(&self, query: &str, mode: SearchMode, max_results: usize) -> Vec<ScreenMatch> {
+        self.output.emulator.read().primary_screen().search(query, mode, max_results)
+    }
+
+    /// Get command history
+    pub fn history(&self) -> &SharedHistory {
+        &self.output.history
+    }
+
+    /// Add a command to history
+    pub fn add_to_history(&self, command: impl Into<String>) {
+        self.output.history.add_command(command);
+    }
+
+    /// Search command history
+    pub fn search_history(&self, query: &str, mode: SearchMode) -> Vec<SearchResult> {
+        self.output.history.search(query, mode, 50)
+    }
+
+    /// Detect links in a line of output
+    pub fn detect_links(&self, text: &str, row: usize) -> Vec<TerminalLink> {
+        self.link_detector.detect_links(text, row)
     }
 
     /// Get terminal size
@@ -199,66 +464,167 @@ impl Terminal {
         self.package.as_deref()
     }
 
+    /// Get the environment set up for the package
+    pub fn package_environment(&self) -> Option<&PackageEnvironment> {
+        self.package_env.as_ref()
+    }
+
     /// Kill the terminal process
     pub fn kill(&mut self) -> Result<()> {
         if let Some(pty) = self.pty.take() {
             pty.kill()?;
         }
+        #[cfg(unix)]
+        if let Some((client, session_id)) = self.daemon.take() {
+            client.kill(session_id);
+        }
         self.active = false;
+        *self.output.replies.write() = None;
         self.input_tx = None;
         Ok(())
     }
 
     /// Clear the screen
     pub fn clear(&self) {
-        self.screen.write().clear();
+        self.process(b"\x1b[2J\x1b[H");
         // Send clear command to terminal
         let _ = self.write(b"\x1b[2J\x1b[H");
     }
 }
 
+/// Where terminal output goes: the emulator, event subscribers and history
+pub struct TerminalOutput {
+    pub emulator: Arc<RwLock<TerminalEmulator>>,
+    pub events: broadcast::Sender<TerminalEvent>,
+    pub history: SharedHistory,
+    /// Input of the program, for answering its queries (DA, DSR, graphics)
+    pub replies: Arc<RwLock<Option<mpsc::UnboundedSender<Vec<u8>>>>>,
+}
+
+impl TerminalOutput {
+    /// Feed output into the emulator, answer the program's queries, record
+    /// finished commands and broadcast the events it produced
+    pub fn process(&self, data: &[u8]) {
+        let (pending, replies) = {
+            let mut emulator = self.emulator.write();
+            emulator.process(data);
+            let events = emulator.take_events();
+            let replies: Vec<String> = events.iter()
+                .filter_map(|event| match event {
+                    TerminalEvent::CursorPositionReport => Some(emulator.cursor_position_report()),
+                    TerminalEvent::DeviceAttributes => Some(emulator.device_attributes().to_string()),
+                    TerminalEvent::GraphicsReply(reply) => Some(reply.clone()),
+                    _ => None,
+                })
+                .collect();
+            (events, replies)
+        };
+        if let Some(tx) = self.replies.read().as_ref() {
+            for reply in replies {
+                let _ = tx.send(reply.into_bytes());
+            }
+        }
+        for event in pending {
+            if let TerminalEvent::CommandFinished(block) = &event
+                && let Some(entry) = block.history_entry()
+            {
+                self.history.add(entry);
+            }
+            let _ = self.events.send(event);
+        }
+    }
+}
+
+impl Clone for TerminalOutput {
+    fn clone(&self) -> Self {
+        Self {
+            emulator: Arc::clone(&self.emulator),
+            events: self.events.clone(),
+            history: self.history.clone_ref(),
+            replies: Arc::clone(&self.replies),
+        }
+    }
+}
+
 /// Terminal manager - manages multiple terminals
 pub struct TerminalManager {
     terminals: HashMap<TerminalId, Terminal>,
-    next_id: u64,
     active_terminal: Option<TerminalId>,
+    profiles: ProfileManager,
+    shared_history: SharedHistory,
 }
 
 impl TerminalManager {
     pub fn new() -> Self {
         Self {
             terminals: HashMap::new(),
-            next_id: 1,
             active_terminal: None,
+            profiles: ProfileManager::new(),
+            shared_history: SharedHistory::new(),
         }
     }
 
-    /// Create a new terminal
+    /// Create a new terminal with the default profile
     pub fn create(&mut self) -> TerminalId {
-        let id = TerminalId(self.next_id);
-        self.next_id += 1;
-        
-        let terminal = Terminal::new(id);
+        let profile = self.profiles.default_profile().cloned().unwrap_or_default();
+        self.create_with_profile(&profile)
+    }
+
+    /// Create a new terminal with a specific profile
+    pub fn create_with_profile(&mut self, profile: &TerminalProfile) -> TerminalId {
+        let id = TerminalId::new();
+
+        let terminal = Terminal::new(id)
+            .with_profile(profile)
+            .with_history(self.shared_history.clone_ref());
         self.terminals.insert(id, terminal);
-        
+
         if self.active_terminal.is_none() {
             self.active_terminal = Some(id);
         }
-        
+
         id
     }
 
     /// Create a terminal for a package
-    pub fn create_for_package(&mut self, name: &str, path: PathBuf) -> TerminalId {
-        let id = TerminalId(self.next_id);
-        self.next_id += 1;
-        
-        let terminal = Terminal::for_package(id, name, path);
+    pub async fn create_for_package(&mut self, package: &Package, workspace_root: &Path, trust: &WorkspaceTrustService) -> TerminalId {
+        let id = TerminalId::new();
+
+        let terminal = Terminal::for_package(id, package, workspace_root, trust)
+            .await
+            .with_history(self.shared_history.clone_ref());
         self.terminals.insert(id, terminal);
         
         id
     }
 
+    /// Recreate terminals for the sessions the daemon is running, e.g. after
+    /// an editor restart
+    #[cfg(unix)]
+    pub async fn restore_sessions(&mut self, client: &DaemonClient) -> Result<Vec<TerminalId>> {
+        let mut restored = Vec::new();
+        for session in client.list().await? {
+            let id = TerminalId::new();
+            let mut terminal = Terminal::new(id)
+                .with_size(session.size.rows, session.size.cols)
+                .with_history(self.shared_history.clone_ref());
+            terminal.title = session.title;
+            terminal.cwd = session.cwd;
+            terminal.shell = Some(session.shell);
+
+            if let Err(e) = terminal.attach(client, session.id).await {
+                tracing::warn!("Failed to reattach terminal session {}: {}", session.id, e);
+                continue;
+            }
+            self.terminals.insert(id, terminal);
+            if self.active_terminal.is_none() {
+                self.active_terminal = Some(id);
+            }
+            restored.push(id);
+        }
+        Ok(restored)
+    }
+
     /// Get a terminal by ID
     pub fn get(&self, id: TerminalId) -> Option<&Terminal> {
         self.terminals.get(&id)
@@ -309,6 +675,21 @@ impl TerminalManager {
     pub fn count(&self) -> usize {
         self.terminals.len()
     }
+
+    /// Get the profile manager
+    pub fn profiles(&self) -> &ProfileManager {
+        &self.profiles
+    }
+
+    /// Get the profile manager mutably
+    pub fn profiles_mut(&mut self) -> &mut ProfileManager {
+        &mut self.profiles
+    }
+
+    /// Get the history shared by all terminals
+    pub fn shared_history(&self) -> &SharedHistory {
+        &self.shared_history
+    }
 }
 
 impl Default for TerminalManager {
@@ -316,3 +697,105 @@ impl Default for TerminalManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_creation() {
+        let terminal = Terminal::new(TerminalId::new());
+        assert!(!terminal.is_active());
+        assert!(!terminal.title().is_empty());
+    }
+
+    #[test]
+    fn test_terminal_process() {
+        let terminal = Terminal::new(TerminalId::new());
+        terminal.process(b"Hello, World!");
+        assert_eq!(terminal.emulator().read().cursor().col, 13);
+        assert_eq!(terminal.emulator().read().screen().cursor(), (0, 13));
+    }
+
+    #[test]
+    fn test_terminal_title() {
+        let terminal = Terminal::new(TerminalId::new());
+        let mut events = terminal.subscribe();
+        terminal.process(b"\x1b]0;My Terminal\x07");
+        assert_eq!(terminal.display_title(), "My Terminal");
+        assert!(matches!(events.try_recv(), Ok(TerminalEvent::TitleChanged(t)) if t == "My Terminal"));
+    }
+
+    #[test]
+    fn test_query_replies() {
+        let terminal = Terminal::new(TerminalId::new());
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        *terminal.output.replies.write() = Some(tx);
+
+        terminal.process(b"ab\x1b[6n\x1b[c");
+        terminal.process(b"\x1b_Ga=T,f=32,s=1,v=1,i=3;/wAA/w==\x1b\\");
+        let replies: Vec<Vec<u8>> = std::iter::from_fn(|| rx.try_recv().ok()).collect();
+        assert_eq!(replies, vec![
+            b"\x1b[1;3R".to_vec(),
+            b"\x1b[?62;4c".to_vec(),
+            b"\x1b_Gi=3;OK\x1b\\".to_vec(),
+        ]);
+    }
+
+    #[test]
+    fn test_scrollback_search() {
+        let terminal = Terminal::new(TerminalId::new())
+            .with_size(2, 20)
+            .with_scrollback(100);
+        terminal.process(b"error: first\r\nok\r\nerror: second\r\n");
+        // Full-screen apps don't hide the scrollback from search
+        terminal.process(b"\x1b[?1049herror: in vim");
+
+        let matches = terminal.search_scrollback(r"error: \w+", SearchMode::Regex, 10);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[1].start_line, 2);
+    }
+
+    #[test]
+    fn test_shell_integration() {
+        let terminal = Terminal::new(TerminalId::new()).with_size(5, 20);
+        terminal.process(b"\x1b]633;P;Cwd=/repo\x07");
+        terminal.process(b"\x1b]633;A\x07$ \x1b]633;B\x07echo hi\r\n\x1b]633;C\x07");
+        terminal.process(b"hi\r\nthere\r\n\x1b]633;D;0\x07\x1b]633;A\x07$ \x1b]633;B\x07");
+
+        assert_eq!(terminal.last_command_output().as_deref(), Some("hi\nthere\n"));
+        assert_eq!(terminal.current_cwd(), PathBuf::from("/repo"));
+        assert_eq!(terminal.previous_prompt(3), Some(0));
+        assert_eq!(terminal.command_marks(), vec![(0, CommandStatus::Success)]);
+
+        let entry = terminal.history().search("echo", SearchMode::Substring, 1).remove(0).entry;
+        assert_eq!(entry.command, "echo hi");
+        assert_eq!(entry.exit_code, Some(0));
+        assert_eq!(entry.cwd, Some(PathBuf::from("/repo")));
+    }
+
+    #[test]
+    fn test_terminal_manager() {
+        let mut manager = TerminalManager::new();
+
+        let id1 = manager.create();
+        let id2 = manager.create();
+
+        assert_eq!(manager.count(), 2);
+        assert!(manager.active().is_some());
+
+        manager.set_active(id2);
+        assert_eq!(manager.active().unwrap().id(), id2);
+
+        manager.close(id1).unwrap();
+        assert_eq!(manager.count(), 1);
+    }
+
+    #[test]
+    fn test_terminal_id() {
+        let id1 = TerminalId::new();
+        let id2 = TerminalId::new();
+        assert_ne!(id1, id2);
+        assert_eq!(TerminalId(7).to_string(), "terminal-7");
+    }
+}
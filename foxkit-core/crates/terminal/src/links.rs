@@ -8,7 +8,7 @@
 
 use regex::Regex;
 use std::collections::HashMap;
-use std::path::{Path, PathBuf};
+use std::path::PathBuf;
 use std::sync::LazyLock;
 
 /// A detected or explicit hyperlink.
@@ -260,6 +260,11 @@ impl LinkDetector {
             for caps in FILE_PATH_REGEX.captures_iter(text) {
                 if let Some(path_match) = caps.get(0) {
                     let path_str = path_match.as_str();
+
+                    // Skip paths inside an already detected URL
+                    if links.iter().any(|l| path_match.start() < l.end_col && l.start_col < path_match.end()) {
+                        continue;
+                    }
                     
                     // Extract line and column if present
                     let (path, line, col) = self.parse_path_with_location(path_str);
@@ -341,16 +346,16 @@ impl LinkDetector {
         }
 
         // Try (line, col) format
-        if let Some(paren_start) = text.rfind('(') {
-            if text.ends_with(')') {
-                let path = &text[..paren_start];
-                let coords = &text[paren_start + 1..text.len() - 1];
-                
-                let parts: Vec<&str> = coords.split(',').collect();
-                if let Ok(line) = parts[0].trim().parse::<u32>() {
-                    let col = parts.get(1).and_then(|c| c.trim().parse::<u32>().ok());
-                    return (path.to_string(), Some(line), col);
-                }
+        if let Some(paren_start) = text.rfind('(')
+            && text.ends_with(')')
+        {
+            let path = &text[..paren_start];
+            let coords = &text[paren_start + 1..text.len() - 1];
+
+            let parts: Vec<&str> = coords.split(',').collect();
+            if let Ok(line) = parts[0].trim().parse::<u32>() {
+                let col = parts.get(1).and_then(|c| c.trim().parse::<u32>().ok());
+                return (path.to_string(), Some(line), col);
             }
         }
 
@@ -399,6 +404,7 @@ pub struct HyperlinkState {
 struct ActiveHyperlink {
     url: String,
     id: Option<String>,
+    #[allow(dead_code)]
     params: HashMap<String, String>,
 }
 
@@ -0,0 +1,509 @@
+//! Environment for package terminals.
+//!
+//! Works out what a terminal opened on a monorepo package needs: the
+//! workspace's `node_modules/.bin` directories on PATH, a Python virtualenv
+//! next to the package, `.env`/`.envrc` files (gated by workspace trust) and
+//! toolchains pinned by `rust-toolchain.toml` or `.nvmrc`.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use tokio::process::Command;
+
+use monorepo::package::Package;
+use workspace_trust::{EnvFileApproval, WorkspaceTrustService, WorkspaceTrustState};
+
+/// Virtualenv directory names looked for next to a package.
+const VENV_DIRS: &[&str] = &[".venv", "venv"];
+
+/// How long an `.envrc` may run before it is killed.
+const ENVRC_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Variables the shell sets itself while evaluating `.envrc`.
+const SHELL_VARS: &[&str] = &["_", "PWD", "OLDPWD", "SHLVL"];
+
+/// Helpers from the direnv stdlib that `.envrc` files commonly use.
+const ENVRC_PRELUDE: &str = r#"
+PATH_add() { local d; for d in "$@"; do export PATH="$(cd "$d" 2>/dev/null && pwd || echo "$d"):$PATH"; done; }
+path_add() { local var="$1"; shift; local d; for d in "$@"; do export "$var=$(cd "$d" 2>/dev/null && pwd || echo "$d")${!var:+:${!var}}"; done; }
+dotenv() { set -a; . "${1:-.env}"; set +a; }
+dotenv_if_exists() { [ -f "${1:-.env}" ] && dotenv "$@"; return 0; }
+source_env() { . "$1"; }
+watch_file() { :; }
+"#;
+
+/// A toolchain pinned by a file in the package or workspace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinnedToolchain {
+    /// `rust-toolchain.toml` or `rust-toolchain`; selected with `RUSTUP_TOOLCHAIN`.
+    Rust { channel: String, file: PathBuf },
+    /// `.nvmrc` or `.node-version`; `bin` is the matching nvm install, if any.
+    Node { version: String, file: PathBuf, bin: Option<PathBuf> },
+}
+
+/// Kind of environment file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnvFileKind {
+    /// `KEY=value` lines.
+    Dotenv,
+    /// Shell script evaluated like direnv does.
+    Envrc,
+}
+
+/// An environment file found for a package.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvFile {
+    pub path: PathBuf,
+    pub kind: EnvFileKind,
+    pub approval: EnvFileApproval,
+    /// Why the file could not be loaded even though it was allowed.
+    pub error: Option<String>,
+}
+
+/// Environment changes for a package terminal.
+#[derive(Debug, Clone, Default)]
+pub struct PackageEnvironment {
+    /// Directories put in front of PATH, highest priority first.
+    pub path: Vec<PathBuf>,
+    /// Variables to set.
+    pub vars: HashMap<String, String>,
+    /// Variables to remove.
+    pub unset: Vec<String>,
+    /// Python virtualenv in use.
+    pub virtualenv: Option<PathBuf>,
+    /// Pinned toolchains.
+    pub toolchains: Vec<PinnedToolchain>,
+    /// Environment files, loaded or waiting for approval.
+    pub env_files: Vec<EnvFile>,
+}
+
+impl PackageEnvironment {
+    /// Detect the environment for a package. `base` is the environment the
+    /// terminal would otherwise start with. Environment files that need a
+    /// decision are requested from `trust` and skipped for now.
+    pub async fn detect(
+        package: &Package,
+        workspace_root: &Path,
+        base: &HashMap<String, String>,
+        trust: &WorkspaceTrustService,
+    ) -> Self {
+        let mut env = Self::default();
+        let dirs = package_dirs(&package.path, workspace_root);
+
+        // node_modules/.bin, nearest first
+        env.path.extend(
+            dirs.iter()
+                .map(|dir| dir.join("node_modules").join(".bin"))
+                .filter(|bin| bin.is_dir()),
+        );
+
+        if let Some(venv) = dirs.iter().find_map(|dir| find_virtualenv(dir)) {
+            env.path.push(venv.join(if cfg!(windows) { "Scripts" } else { "bin" }));
+            env.vars.insert("VIRTUAL_ENV".to_string(), venv.to_string_lossy().into_owned());
+            env.unset.push("PYTHONHOME".to_string());
+            env.virtualenv = Some(venv);
+        }
+
+        if let Some(toolchain) = dirs.iter().find_map(|dir| rust_toolchain(dir)) {
+            if let PinnedToolchain::Rust { channel, .. } = &toolchain {
+                env.vars.insert("RUSTUP_TOOLCHAIN".to_string(), channel.clone());
+            }
+            env.toolchains.push(toolchain);
+        }
+
+        if let Some(toolchain) = dirs.iter().find_map(|dir| node_version(dir, base)) {
+            if let PinnedToolchain::Node { bin: Some(bin), .. } = &toolchain {
+                env.path.push(bin.clone());
+            }
+            env.toolchains.push(toolchain);
+        }
+
+        env.load_env_files(&dirs, base, trust).await;
+        env
+    }
+
+    /// Apply the changes to a terminal environment.
+    pub fn apply(&self, env: &mut HashMap<String, String>) {
+        for name in &self.unset {
+            env.remove(name);
+        }
+        env.extend(self.vars.clone());
+
+        if !self.path.is_empty() {
+            let existing = env.get("PATH").cloned().unwrap_or_default();
+            // An .envrc may already have put some of these on its PATH
+            let rest: Vec<PathBuf> = std::env::split_paths(&existing).filter(|p| !self.path.contains(p)).collect();
+            let paths = self.path.iter().cloned().chain(rest);
+            if let Ok(path) = std::env::join_paths(paths) {
+                env.insert("PATH".to_string(), path.to_string_lossy().into_owned());
+            }
+        }
+    }
+
+    /// Environment files waiting for the user to allow them.
+    pub fn pending_env_files(&self) -> impl Iterator<Item = &EnvFile> {
+        self.env_files.iter().filter(|f| f.approval == EnvFileApproval::Pending)
+    }
+
+    /// Load `.env` files (workspace root first, so the package overrides it)
+    /// and the nearest `.envrc`.
+    async fn load_env_files(&mut self, dirs: &[PathBuf], base: &HashMap<String, String>, trust: &WorkspaceTrustService) {
+        let mut files: Vec<(PathBuf, EnvFileKind)> = dirs
+            .iter()
+            .rev()
+            .map(|dir| dir.join(".env"))
+            .filter(|path| path.is_file())
+            .map(|path| (path, EnvFileKind::Dotenv))
+            .collect();
+        if let Some(envrc) = dirs.iter().map(|dir| dir.join(".envrc")).find(|path| path.is_file()) {
+            files.push((envrc, EnvFileKind::Envrc));
+        }
+
+        for (path, kind) in files {
+            let Ok(content) = std::fs::read(&path) else { continue };
+            let approval = match trust.env_file_approval(&path, &content) {
+                // Plain .env files only set variables; a trusted folder is enough
+                EnvFileApproval::Pending
+                    if kind == EnvFileKind::Dotenv
+                        && trust.check_trust(&[path.parent().unwrap_or(&path).to_path_buf()])
+                            == WorkspaceTrustState::Trusted =>
+                {
+                    EnvFileApproval::Allowed
+                }
+                approval => approval,
+            };
+
+            let mut error = None;
+            match approval {
+                EnvFileApproval::Allowed => match kind {
+                    EnvFileKind::Dotenv => {
+                        self.vars.extend(parse_dotenv(&String::from_utf8_lossy(&content)));
+                    }
+                    EnvFileKind::Envrc => {
+                        let mut current = base.clone();
+                        self.apply(&mut current);
+                        match eval_envrc(&path, &current, ENVRC_TIMEOUT).await {
+                            Ok(changes) => {
+                                for (name, value) in changes {
+                                    match value {
+                                        Some(value) => {
+                                            self.unset.retain(|n| *n != name);
+                                            self.vars.insert(name, value);
+                                        }
+                                        None => {
+                                            self.vars.remove(&name);
+                                            self.unset.push(name);
+                                        }
+                                    }
+                                }
+                            }
+                            Err(e) => {
+                                tracing::warn!("Failed to load {}: {}", path.display(), e);
+                                error = Some(e.to_string());
+                            }
+                        }
+                    }
+                },
+                EnvFileApproval::Pending => trust.request_env_file_approval(path.clone()),
+                EnvFileApproval::Denied => {}
+            }
+            self.env_files.push(EnvFile { path, kind, approval, error });
+        }
+    }
+}
+
+/// The package directory and its ancestors up to the workspace root.
+fn package_dirs(package: &Path, root: &Path) -> Vec<PathBuf> {
+    if !package.starts_with(root) {
+        return vec![package.to_path_buf()];
+    }
+    package
+        .ancestors()
+        .take_while(|dir| dir.starts_with(root))
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+fn find_virtualenv(dir: &Path) -> Option<PathBuf> {
+    VENV_DIRS
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|venv| venv.join("pyvenv.cfg").is_file())
+}
+
+fn rust_toolchain(dir: &Path) -> Option<PinnedToolchain> {
+    for name in ["rust-toolchain.toml", "rust-toolchain"] {
+        let file = dir.join(name);
+        let Ok(content) = std::fs::read_to_string(&file) else { continue };
+
+        // The legacy file may be TOML or just the channel name
+        let channel = match content.parse::<toml::Table>() {
+            Ok(table) => table
+                .get("toolchain")
+                .and_then(|t| t.get("channel"))
+                .and_then(|c| c.as_str())
+                .map(str::to_string),
+            Err(_) => content.lines().next().map(|l| l.trim().to_string()).filter(|l| !l.is_empty()),
+        };
+        if let Some(channel) = channel {
+            return Some(PinnedToolchain::Rust { channel, file });
+        }
+    }
+    None
+}
+
+fn node_version(dir: &Path, base: &HashMap<String, String>) -> Option<PinnedToolchain> {
+    for name in [".nvmrc", ".node-version"] {
+        let file = dir.join(name);
+        let Ok(content) = std::fs::read_to_string(&file) else { continue };
+        let Some(version) = content.lines().next().map(str::trim).filter(|v| !v.is_empty()) else {
+            continue;
+        };
+        let bin = nvm_dir(base).and_then(|nvm| resolve_nvm_version(&nvm, version));
+        return Some(PinnedToolchain::Node { version: version.to_string(), file, bin });
+    }
+    None
+}
+
+fn nvm_dir(base: &HashMap<String, String>) -> Option<PathBuf> {
+    base.get("NVM_DIR")
+        .map(PathBuf::from)
+        .or_else(|| base.get("HOME").map(|home| Path::new(home).join(".nvm")))
+}
+
+/// Find the `bin` directory of the newest installed node matching a version
+/// or nvm alias.
+fn resolve_nvm_version(nvm: &Path, version: &str) -> Option<PathBuf> {
+    let mut wanted = version.trim_start_matches('v').to_string();
+    if !wanted.starts_with(|c: char| c.is_ascii_digit()) {
+        // Aliases such as `default` or `lts/iron` are files naming a version
+        wanted = std::fs::read_to_string(nvm.join("alias").join(version))
+            .ok()?
+            .trim()
+            .trim_start_matches('v')
+            .to_string();
+    }
+
+    let parse = |v: &str| -> Vec<u64> { v.split('.').filter_map(|n| n.parse().ok()).collect() };
+    std::fs::read_dir(nvm.join("versions").join("node"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().trim_start_matches('v').to_string();
+            (name == wanted || name.starts_with(&format!("{}.", wanted))).then(|| (parse(&name), entry.path()))
+        })
+        .max_by(|a, b| a.0.cmp(&b.0))
+        .map(|(_, path)| path.join("bin"))
+}
+
+/// Parse `KEY=value` lines. Supports `export`, comments and quoting.
+pub fn parse_dotenv(content: &str) -> Vec<(String, String)> {
+    let mut vars = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else { continue };
+        let key = key.trim();
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            continue;
+        }
+
+        let value = value.trim();
+        let value = if let Some(rest) = value.strip_prefix('"') {
+            let mut out = String::new();
+            let mut chars = rest.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some('n') => out.push('\n'),
+                        Some('t') => out.push('\t'),
+                        Some(other) => out.push(other),
+                        None => {}
+                    },
+                    c => out.push(c),
+                }
+            }
+            out
+        } else if let Some(rest) = value.strip_prefix('\'') {
+            rest.split('\'').next().unwrap_or_default().to_string()
+        } else {
+            // Unquoted values end at an inline comment
+            value.split(" #").next().unwrap_or_default().trim_end().to_string()
+        };
+        vars.push((key.to_string(), value));
+    }
+    vars
+}
+
+/// Evaluate an `.envrc` in its directory and return the variables it set
+/// (`Some`) or removed (`None`). The shell is killed after `timeout`.
+async fn eval_envrc(
+    path: &Path,
+    base: &HashMap<String, String>,
+    timeout: Duration,
+) -> anyhow::Result<Vec<(String, Option<String>)>> {
+    let dir = path.parent().unwrap_or(Path::new("."));
+    let script = format!("{}\nset -e\n. \"$1\" >&2\nenv -0", ENVRC_PRELUDE);
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(script)
+        .arg("foxkit-envrc")
+        .arg(path)
+        .current_dir(dir)
+        .env_clear()
+        .envs(base)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let output = match tokio::time::timeout(timeout, output).await {
+        Ok(output) => output?,
+        Err(_) => anyhow::bail!("timed out after {}s", timeout.as_secs_f64()),
+    };
+    if !output.status.success() {
+        anyhow::bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+
+    let after: HashMap<String, String> = output
+        .stdout
+        .split(|&b| b == 0)
+        .filter_map(|entry| {
+            let entry = String::from_utf8_lossy(entry);
+            let (name, value) = entry.split_once('=')?;
+            Some((name.to_string(), value.to_string()))
+        })
+        .filter(|(name, _)| !SHELL_VARS.contains(&name.as_str()))
+        .collect();
+
+    let mut changes: Vec<(String, Option<String>)> = after
+        .iter()
+        .filter(|(name, value)| base.get(*name) != Some(value))
+        .map(|(name, value)| (name.clone(), Some(value.clone())))
+        .collect();
+    changes.extend(
+        base.keys()
+            .filter(|name| !after.contains_key(*name) && !SHELL_VARS.contains(&name.as_str()))
+            .map(|name| (name.clone(), None)),
+    );
+    changes.sort();
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use monorepo::package::PackageKind;
+
+    fn package(path: PathBuf) -> Package {
+        Package {
+            name: "web".to_string(),
+            version: None,
+            path,
+            kind: PackageKind::App,
+            package_manager: None,
+            build_system: None,
+            dependencies: Vec::new(),
+            dev_dependencies: Vec::new(),
+            peer_dependencies: Vec::new(),
+            source_files: Vec::new(),
+            entry_points: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_dotenv() {
+        let vars = parse_dotenv("# db\nexport DB_URL=\"postgres://x\\n\"\nPORT=3000 # dev\nNAME='a b'\nbad line\n");
+        assert_eq!(vars, vec![
+            ("DB_URL".to_string(), "postgres://x\n".to_string()),
+            ("PORT".to_string(), "3000".to_string()),
+            ("NAME".to_string(), "a b".to_string()),
+        ]);
+    }
+
+    #[tokio::test]
+    async fn test_package_environment() {
+        let root = std::env::temp_dir().join(format!("foxkit-package-env-{}", std::process::id()));
+        let pkg = root.join("packages").join("web");
+        let nvm = root.join("nvm");
+        std::fs::create_dir_all(pkg.join("node_modules/.bin")).unwrap();
+        std::fs::create_dir_all(root.join("node_modules/.bin")).unwrap();
+        std::fs::create_dir_all(pkg.join(".venv")).unwrap();
+        std::fs::create_dir_all(nvm.join("versions/node/v20.9.0/bin")).unwrap();
+        std::fs::create_dir_all(nvm.join("versions/node/v20.11.1/bin")).unwrap();
+        std::fs::write(pkg.join(".venv/pyvenv.cfg"), "home = /usr/bin\n").unwrap();
+        std::fs::write(root.join("rust-toolchain.toml"), "[toolchain]\nchannel = \"1.79.0\"\n").unwrap();
+        std::fs::write(pkg.join(".nvmrc"), "v20\n").unwrap();
+        std::fs::write(root.join(".env"), "API=root\nROOT_ONLY=1\n").unwrap();
+        std::fs::write(pkg.join(".env"), "API=web\n").unwrap();
+        std::fs::write(pkg.join(".envrc"), "export FROM_ENVRC=$API-ok\nunset DROP_ME\n").unwrap();
+
+        let base: HashMap<String, String> = [
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            ("NVM_DIR".to_string(), nvm.to_string_lossy().into_owned()),
+            ("DROP_ME".to_string(), "1".to_string()),
+            ("API".to_string(), "base".to_string()),
+        ].into();
+        let trust = WorkspaceTrustService::new();
+        let mut events = trust.subscribe();
+
+        // Untrusted: toolchains and PATH apply, env files wait for approval
+        let env = PackageEnvironment::detect(&package(pkg.clone()), &root, &base, &trust).await;
+        assert_eq!(env.path, vec![
+            pkg.join("node_modules/.bin"),
+            root.join("node_modules/.bin"),
+            pkg.join(".venv/bin"),
+            nvm.join("versions/node/v20.11.1/bin"),
+        ]);
+        assert_eq!(env.vars.get("RUSTUP_TOOLCHAIN").map(String::as_str), Some("1.79.0"));
+        assert_eq!(env.vars.get("VIRTUAL_ENV"), Some(&pkg.join(".venv").to_string_lossy().into_owned()));
+        assert_eq!(env.pending_env_files().count(), 3);
+        assert!(!env.vars.contains_key("API"));
+        assert!(matches!(
+            events.try_recv(),
+            Ok(workspace_trust::WorkspaceTrustEvent::EnvFileApprovalRequested { path }) if path == root.join(".env")
+        ));
+
+        // Trusting the workspace loads .env files; .envrc needs its own allow
+        trust.grant_trust(root.clone());
+        let content = std::fs::read(pkg.join(".envrc")).unwrap();
+        trust.allow_env_file(pkg.join(".envrc"), &content);
+        let env = PackageEnvironment::detect(&package(pkg.clone()), &root, &base, &trust).await;
+        assert_eq!(env.pending_env_files().count(), 0);
+
+        let mut applied = base.clone();
+        env.apply(&mut applied);
+        assert_eq!(applied["API"], "web");
+        assert_eq!(applied["ROOT_ONLY"], "1");
+        assert_eq!(applied["FROM_ENVRC"], "web-ok");
+        assert!(!applied.contains_key("DROP_ME"));
+        assert!(applied["PATH"].starts_with(&*pkg.join("node_modules/.bin").to_string_lossy()));
+        assert!(applied["PATH"].ends_with(":/usr/bin"));
+
+        // Editing the .envrc needs a new approval
+        std::fs::write(pkg.join(".envrc"), "export FROM_ENVRC=changed\n").unwrap();
+        let env = PackageEnvironment::detect(&package(pkg.clone()), &root, &base, &trust).await;
+        assert_eq!(env.pending_env_files().map(|f| f.kind).collect::<Vec<_>>(), vec![EnvFileKind::Envrc]);
+
+        let _ = std::fs::remove_dir_all(root);
+    }
+
+    #[tokio::test]
+    async fn test_envrc_timeout() {
+        let dir = std::env::temp_dir().join(format!("foxkit-envrc-timeout-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".envrc"), "sleep 30\n").unwrap();
+
+        let base: HashMap<String, String> = [("PATH".to_string(), std::env::var("PATH").unwrap_or_default())].into();
+        let started = std::time::Instant::now();
+        let err = eval_envrc(&dir.join(".envrc"), &base, Duration::from_millis(200)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(started.elapsed() < Duration::from_secs(10));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -11,6 +11,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
 
+use crate::TerminalSize;
+
 /// A complete terminal profile.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TerminalProfile {
@@ -219,21 +221,6 @@ impl Default for ShellConfig {
     }
 }
 
-/// Terminal dimensions.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
-pub struct TerminalSize {
-    /// Number of columns.
-    pub cols: u16,
-    /// Number of rows.
-    pub rows: u16,
-}
-
-impl Default for TerminalSize {
-    fn default() -> Self {
-        Self { cols: 80, rows: 24 }
-    }
-}
-
 /// Font configuration.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FontConfig {
@@ -684,10 +671,10 @@ impl ProfileManager {
     pub fn set_default(&mut self, id: &str) -> bool {
         if self.profiles.contains_key(id) {
             // Unmark old default
-            if let Some(old_id) = &self.default_profile_id {
-                if let Some(old) = self.profiles.get_mut(old_id) {
-                    old.is_default = false;
-                }
+            if let Some(old_id) = &self.default_profile_id
+                && let Some(old) = self.profiles.get_mut(old_id)
+            {
+                old.is_default = false;
             }
             // Mark new default
             if let Some(new) = self.profiles.get_mut(id) {
@@ -2,35 +2,51 @@
 
 use std::path::Path;
 use std::collections::HashMap;
+#[cfg(unix)]
+use std::os::fd::{AsRawFd, OwnedFd};
+#[cfg(unix)]
 use std::sync::Arc;
-use parking_lot::RwLock;
+#[cfg(unix)]
+use std::time::Duration;
+#[cfg(unix)]
+use parking_lot::Mutex;
 use tokio::sync::mpsc;
 use anyhow::Result;
 
-use crate::{Screen, TerminalSize};
+use crate::TerminalSize;
+
+/// How long a killed process group gets to exit after SIGHUP before SIGKILL.
+#[cfg(unix)]
+const KILL_GRACE: Duration = Duration::from_millis(500);
 
 /// PTY handle
 pub struct Pty {
-    /// Child process ID
+    /// Child process ID, also its process group ID
     #[cfg(unix)]
     pid: i32,
-    /// Master FD
+    /// Master side, shared with the reader and writer tasks so the fd stays
+    /// open (and its number unused) until all of them are done
+    #[cfg(unix)]
+    master: Arc<OwnedFd>,
+    /// Exit code once the child has been reaped
     #[cfg(unix)]
-    master_fd: i32,
+    status: Arc<Mutex<Option<i32>>>,
 }
 
 impl Pty {
-    /// Spawn a new PTY with the given shell
+    /// Spawn a new PTY with the given shell; `output` receives everything
+    /// the process writes, then an empty slice once the PTY closes
     pub async fn spawn(
         shell: &str,
+        args: &[String],
         cwd: &Path,
         env: &HashMap<String, String>,
         size: TerminalSize,
-        screen: Arc<RwLock<Screen>>,
+        output: impl Fn(&[u8]) + Send + 'static,
     ) -> Result<(Self, mpsc::UnboundedSender<Vec<u8>>)> {
         #[cfg(unix)]
         {
-            Self::spawn_unix(shell, cwd, env, size, screen).await
+            Self::spawn_unix(shell, args, cwd, env, size, output).await
         }
         
         #[cfg(not(unix))]
@@ -43,14 +59,12 @@ impl Pty {
     #[cfg(unix)]
     async fn spawn_unix(
         shell: &str,
+        args: &[String],
         cwd: &Path,
         env: &HashMap<String, String>,
         size: TerminalSize,
-        screen: Arc<RwLock<Screen>>,
+        output: impl Fn(&[u8]) + Send + 'static,
     ) -> Result<(Self, mpsc::UnboundedSender<Vec<u8>>)> {
-        use std::os::unix::io::{AsRawFd, FromRawFd};
-        use std::process::Stdio;
-        
         // Create PTY
         let pty_pair = nix::pty::openpty(None, None)?;
         let master_fd = pty_pair.master.as_raw_fd();
@@ -103,63 +117,56 @@ impl Pty {
                 
                 // Exec shell
                 let shell_cstr = std::ffi::CString::new(shell)?;
-                nix::unistd::execvp(&shell_cstr, &[&shell_cstr])?;
+                let mut argv = vec![shell_cstr.clone()];
+                for arg in args {
+                    argv.push(std::ffi::CString::new(arg.as_str())?);
+                }
+                nix::unistd::execvp(&shell_cstr, &argv)?;
                 
                 unreachable!()
             }
             nix::unistd::ForkResult::Parent { child } => {
                 // Parent process
                 drop(pty_pair.slave);
+                let master = Arc::new(pty_pair.master);
                 
                 let pid = child.as_raw();
                 
                 // Create input channel
                 let (input_tx, mut input_rx) = mpsc::unbounded_channel::<Vec<u8>>();
                 
-                // Spawn read task
-                let screen_clone = Arc::clone(&screen);
-                let master_fd_clone = master_fd;
-                tokio::spawn(async move {
+                // Spawn read task; reads block, so keep them off the async workers
+                let reader_master = Arc::clone(&master);
+                tokio::task::spawn_blocking(move || {
                     let mut buffer = [0u8; 4096];
-                    let master_file = unsafe { std::fs::File::from_raw_fd(master_fd_clone) };
-                    let mut parser = vt100::Parser::new(size.rows, size.cols, 1000);
-                    
                     loop {
-                        use std::io::Read;
-                        let mut master_ref = &master_file;
-                        match master_ref.read(&mut buffer) {
+                        match nix::unistd::read(reader_master.as_raw_fd(), &mut buffer) {
                             Ok(0) => break, // EOF
-                            Ok(n) => {
-                                // Parse VT100 sequences
-                                parser.process(&buffer[..n]);
-                                
-                                // Update screen
-                                let vt_screen = parser.screen();
-                                let mut screen = screen_clone.write();
-                                screen.update_from_vt100(vt_screen);
-                            }
-                            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                            Ok(n) => output(&buffer[..n]),
+                            Err(nix::errno::Errno::EINTR) => continue,
+                            // EIO once every process has closed the slave side
                             Err(_) => break,
                         }
                     }
-                    
-                    // Don't close the fd, we'll handle it in Pty::kill
-                    std::mem::forget(master_file);
+                    output(&[]);
                 });
                 
                 // Spawn write task
-                let master_fd_write = master_fd;
+                let writer_master = Arc::clone(&master);
                 tokio::spawn(async move {
                     while let Some(data) = input_rx.recv().await {
-                        use std::io::Write;
-                        let mut master_file = unsafe { std::fs::File::from_raw_fd(master_fd_write) };
-                        let _ = master_file.write_all(&data);
-                        let _ = master_file.flush();
-                        std::mem::forget(master_file);
+                        let mut data = &data[..];
+                        while !data.is_empty() {
+                            match nix::unistd::write(&*writer_master, data) {
+                                Ok(n) => data = &data[n..],
+                                Err(nix::errno::Errno::EINTR) => continue,
+                                Err(_) => return,
+                            }
+                        }
                     }
                 });
                 
-                Ok((Self { pid, master_fd }, input_tx))
+                Ok((Self { pid, master, status: Arc::new(Mutex::new(None)) }, input_tx))
             }
         }
     }
@@ -176,26 +183,86 @@ impl Pty {
             };
             
             unsafe {
-                nix::libc::ioctl(self.master_fd, nix::libc::TIOCSWINSZ, &winsize);
+                nix::libc::ioctl(self.master.as_raw_fd(), nix::libc::TIOCSWINSZ, &winsize);
             }
         }
         
         Ok(())
     }
 
-    /// Kill the PTY process
+    /// Child process ID
+    #[cfg(unix)]
+    pub fn pid(&self) -> i32 {
+        self.pid
+    }
+
+    /// Exit code of the child if it has exited (128 + signal when killed)
+    pub fn try_wait(&self) -> Option<i32> {
+        #[cfg(unix)]
+        {
+            reap(self.pid, &mut self.status.lock())
+        }
+
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    /// Kill the PTY process and everything in its process group: SIGHUP,
+    /// like closing a terminal window, then SIGKILL for whatever is still
+    /// running after a grace period. The child is reaped in the background
     pub fn kill(&self) -> Result<()> {
         #[cfg(unix)]
         {
-            use nix::sys::signal::{kill, Signal};
+            use nix::sys::signal::{killpg, Signal};
             use nix::unistd::Pid;
-            
-            let _ = kill(Pid::from_raw(self.pid), Signal::SIGTERM);
-            
-            // Close master fd
-            let _ = nix::unistd::close(self.master_fd);
+
+            // The shell leads its own session and process group (setsid)
+            let group = Pid::from_raw(self.pid);
+            let _ = killpg(group, Signal::SIGHUP);
+            let _ = killpg(group, Signal::SIGCONT);
+
+            let (pid, status) = (self.pid, Arc::clone(&self.status));
+            std::thread::spawn(move || {
+                let deadline = std::time::Instant::now() + KILL_GRACE;
+                let mut killed = false;
+                loop {
+                    // Signal only while the child is unreaped: until then its
+                    // pid, and with it the group id, can't be reused
+                    let mut status = status.lock();
+                    if reap(pid, &mut status).is_some() {
+                        break;
+                    }
+                    if !killed && std::time::Instant::now() >= deadline {
+                        let _ = killpg(group, Signal::SIGKILL);
+                        killed = true;
+                    }
+                    drop(status);
+                    std::thread::sleep(Duration::from_millis(20));
+                }
+            });
         }
         
         Ok(())
     }
 }
+
+/// Reap the child if it has exited, remembering its exit code (128 + signal
+/// when killed)
+#[cfg(unix)]
+fn reap(pid: i32, status: &mut Option<i32>) -> Option<i32> {
+    use nix::sys::wait::{waitpid, WaitPidFlag, WaitStatus};
+    use nix::unistd::Pid;
+
+    if status.is_none() {
+        *status = match waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)) {
+            Ok(WaitStatus::Exited(_, code)) => Some(code),
+            Ok(WaitStatus::Signaled(_, signal, _)) => Some(128 + signal as i32),
+            Ok(_) => None,
+            // Reaped elsewhere
+            Err(_) => Some(-1),
+        };
+    }
+    *status
+}
@@ -1,9 +1,17 @@
 //! Terminal screen buffer
 
+use std::collections::VecDeque;
+use regex::Regex;
+
+use crate::history::SearchMode;
+
+/// Default number of scrollback lines
+pub const DEFAULT_SCROLLBACK: usize = 10000;
+
 /// Terminal screen
 pub struct Screen {
-    /// Grid of cells
-    cells: Vec<Vec<Cell>>,
+    /// Visible rows
+    lines: Vec<Line>,
     /// Number of rows
     rows: usize,
     /// Number of columns
@@ -14,14 +22,39 @@ pub struct Screen {
     cursor_col: usize,
     /// Cursor visible?
     cursor_visible: bool,
-    /// Scrollback buffer
-    scrollback: Vec<Vec<Cell>>,
+    /// Scrollback ring buffer, oldest line first
+    scrollback: VecDeque<Line>,
     /// Max scrollback lines
     max_scrollback: usize,
+    /// Lines dropped off the front of the scrollback so far
+    dropped: usize,
+    /// Alternate screens keep no scrollback and don't reflow
+    alternate: bool,
 }
 
-/// A single cell in the terminal
+/// A physical row
 #[derive(Debug, Clone)]
+struct Line {
+    cells: Vec<Cell>,
+    /// The row continues on the next one (soft wrap)
+    wrapped: bool,
+}
+
+impl Line {
+    fn blank(cols: usize) -> Self {
+        Self {
+            cells: vec![Cell::default(); cols],
+            wrapped: false,
+        }
+    }
+
+    fn is_blank(&self) -> bool {
+        self.cells.iter().all(Cell::is_blank)
+    }
+}
+
+/// A single cell in the terminal
+#[derive(Debug, Clone, PartialEq)]
 pub struct Cell {
     /// Character (empty = space)
     pub char: char,
@@ -29,6 +62,13 @@ pub struct Cell {
     pub style: CellStyle,
 }
 
+impl Cell {
+    /// Nothing visible in the cell
+    pub fn is_blank(&self) -> bool {
+        self.char == ' ' && self.style.background == Color::Default && !self.style.inverse
+    }
+}
+
 impl Default for Cell {
     fn default() -> Self {
         Self {
@@ -39,20 +79,24 @@ impl Default for Cell {
 }
 
 /// Cell styling
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq)]
 pub struct CellStyle {
     pub foreground: Color,
     pub background: Color,
     pub bold: bool,
+    pub dim: bool,
     pub italic: bool,
     pub underline: bool,
+    pub blink: bool,
     pub strikethrough: bool,
     pub inverse: bool,
+    pub hidden: bool,
 }
 
 /// Terminal color
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
 pub enum Color {
+    #[default]
     Default,
     Black,
     Red,
@@ -74,71 +118,299 @@ pub enum Color {
     Indexed(u8),
 }
 
-impl Default for Color {
-    fn default() -> Self {
-        Self::Default
-    }
+
+/// A search match in the scrollback and visible screen. Lines count from the
+/// oldest scrollback line; the end is exclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScreenMatch {
+    pub start_line: usize,
+    pub start_col: usize,
+    pub end_line: usize,
+    pub end_col: usize,
 }
 
 impl Screen {
     /// Create a new screen
     pub fn new(rows: usize, cols: usize) -> Self {
-        let cells = vec![vec![Cell::default(); cols]; rows];
-        
         Self {
-            cells,
+            lines: vec![Line::blank(cols); rows],
             rows,
             cols,
             cursor_row: 0,
             cursor_col: 0,
             cursor_visible: true,
-            scrollback: Vec::new(),
-            max_scrollback: 10000,
+            scrollback: VecDeque::new(),
+            max_scrollback: DEFAULT_SCROLLBACK,
+            dropped: 0,
+            alternate: false,
+        }
+    }
+
+    /// Create an alternate screen (full-screen apps)
+    pub fn alternate(rows: usize, cols: usize) -> Self {
+        let mut screen = Self::new(rows, cols);
+        screen.max_scrollback = 0;
+        screen.alternate = true;
+        screen
+    }
+
+    /// Set the scrollback size
+    pub fn with_scrollback(mut self, lines: usize) -> Self {
+        self.set_max_scrollback(lines);
+        self
+    }
+
+    /// Change the scrollback size, dropping the oldest lines if needed
+    pub fn set_max_scrollback(&mut self, lines: usize) {
+        self.max_scrollback = if self.alternate { 0 } else { lines };
+        while self.scrollback.len() > self.max_scrollback {
+            self.scrollback.pop_front();
+            self.dropped += 1;
         }
     }
 
-    /// Resize the screen
+    /// Get max scrollback lines
+    pub fn max_scrollback(&self) -> usize {
+        self.max_scrollback
+    }
+
+    /// Is this an alternate screen?
+    pub fn is_alternate(&self) -> bool {
+        self.alternate
+    }
+
+    /// Resize the screen, rewrapping soft-wrapped lines to the new width
     pub fn resize(&mut self, rows: usize, cols: usize) {
+        if rows == self.rows && cols == self.cols {
+            return;
+        }
+        if self.alternate || rows == 0 || cols == 0 {
+            self.resize_truncating(rows, cols);
+        } else {
+            self.reflow(rows, cols);
+        }
+    }
+
+    fn resize_truncating(&mut self, rows: usize, cols: usize) {
         // Resize existing rows
-        for row in &mut self.cells {
-            row.resize(cols, Cell::default());
+        for line in &mut self.lines {
+            line.cells.resize(cols, Cell::default());
         }
-        
+
         // Add or remove rows
-        self.cells.resize(rows, vec![Cell::default(); cols]);
-        
+        self.lines.resize(rows, Line::blank(cols));
+
         self.rows = rows;
         self.cols = cols;
-        
+
         // Clamp cursor
         self.cursor_row = self.cursor_row.min(rows.saturating_sub(1));
         self.cursor_col = self.cursor_col.min(cols.saturating_sub(1));
     }
 
+    fn reflow(&mut self, rows: usize, cols: usize) {
+        // Rows below both the cursor and the last content are just padding
+        let used = self.lines.iter()
+            .rposition(|line| !line.is_blank())
+            .map_or(0, |i| i + 1)
+            .max(self.cursor_row + 1)
+            .min(self.lines.len());
+        let old: Vec<Line> = self.scrollback.drain(..)
+            .chain(self.lines.drain(..used))
+            .collect();
+        let cursor_line = old.len() - used + self.cursor_row;
+
+        // Join soft-wrapped rows into logical lines
+        let mut logical: Vec<Vec<Cell>> = Vec::new();
+        let mut current: Vec<Cell> = Vec::new();
+        let mut cursor = (0, 0);
+        for (i, line) in old.into_iter().enumerate() {
+            if i == cursor_line {
+                cursor = (logical.len(), current.len() + self.cursor_col);
+            }
+            let wrapped = line.wrapped;
+            current.extend(line.cells);
+            if !wrapped {
+                logical.push(std::mem::take(&mut current));
+            }
+        }
+        if !current.is_empty() {
+            logical.push(current);
+        }
+
+        // Wrap them again at the new width
+        let mut lines: Vec<Line> = Vec::new();
+        let (mut cursor_row, mut cursor_col) = (0, 0);
+        for (index, mut cells) in logical.into_iter().enumerate() {
+            let mut len = cells.iter().rposition(|c| !c.is_blank()).map_or(0, |i| i + 1);
+            if index == cursor.0 {
+                len = len.max(cursor.1);
+            }
+            cells.truncate(len);
+
+            let start = lines.len();
+            for chunk in cells.chunks(cols) {
+                let mut cells = chunk.to_vec();
+                cells.resize(cols, Cell::default());
+                lines.push(Line { cells, wrapped: true });
+            }
+            if index == cursor.0 {
+                cursor_row = start + cursor.1 / cols;
+                cursor_col = cursor.1 % cols;
+                while lines.len() <= cursor_row {
+                    lines.push(Line { cells: vec![Cell::default(); cols], wrapped: true });
+                }
+            }
+            if lines.len() == start {
+                lines.push(Line::blank(cols));
+            }
+            if let Some(last) = lines.last_mut() {
+                last.wrapped = false;
+            }
+        }
+
+        // Keep the cursor on screen; everything above goes to scrollback
+        let start = lines.len().saturating_sub(rows).min(cursor_row);
+        let mut visible = lines.split_off(start);
+        visible.truncate(rows);
+        visible.resize(rows, Line::blank(cols));
+
+        self.scrollback = lines.into();
+        while self.scrollback.len() > self.max_scrollback {
+            self.scrollback.pop_front();
+            self.dropped += 1;
+        }
+        self.lines = visible;
+        self.rows = rows;
+        self.cols = cols;
+        self.cursor_row = cursor_row - start;
+        self.cursor_col = cursor_col.min(cols - 1);
+    }
+
     /// Clear the screen
     pub fn clear(&mut self) {
-        for row in &mut self.cells {
-            for cell in row {
-                *cell = Cell::default();
-            }
+        for line in &mut self.lines {
+            *line = Line::blank(self.cols);
         }
         self.cursor_row = 0;
         self.cursor_col = 0;
     }
 
+    /// Clear the scrollback
+    pub fn clear_scrollback(&mut self) {
+        self.dropped += self.scrollback.len();
+        self.scrollback.clear();
+    }
+
     /// Get cell at position
     pub fn cell(&self, row: usize, col: usize) -> Option<&Cell> {
-        self.cells.get(row).and_then(|r| r.get(col))
+        self.lines.get(row).and_then(|r| r.cells.get(col))
     }
 
     /// Get mutable cell at position
     pub fn cell_mut(&mut self, row: usize, col: usize) -> Option<&mut Cell> {
-        self.cells.get_mut(row).and_then(|r| r.get_mut(col))
+        self.lines.get_mut(row).and_then(|r| r.cells.get_mut(col))
+    }
+
+    /// Set cell at position
+    pub fn set_cell(&mut self, row: usize, col: usize, cell: Cell) {
+        if let Some(target) = self.cell_mut(row, col) {
+            *target = cell;
+        }
     }
 
     /// Get a row
     pub fn row(&self, idx: usize) -> Option<&[Cell]> {
-        self.cells.get(idx).map(|r| r.as_slice())
+        self.lines.get(idx).map(|r| r.cells.as_slice())
+    }
+
+    /// Does the row continue on the next one?
+    pub fn is_wrapped(&self, row: usize) -> bool {
+        self.lines.get(row).is_some_and(|l| l.wrapped)
+    }
+
+    /// Mark a row as soft-wrapped onto the next one
+    pub fn set_wrapped(&mut self, row: usize, wrapped: bool) {
+        if let Some(line) = self.lines.get_mut(row) {
+            line.wrapped = wrapped;
+        }
+    }
+
+    /// Blank a row
+    pub fn clear_row(&mut self, row: usize) {
+        if let Some(line) = self.lines.get_mut(row) {
+            *line = Line::blank(self.cols);
+        }
+    }
+
+    /// Insert blank cells at a position, shifting the rest right
+    pub fn insert_cells(&mut self, row: usize, col: usize, n: usize) {
+        if let Some(line) = self.lines.get_mut(row)
+            && col < line.cells.len()
+        {
+            let n = n.min(line.cells.len() - col);
+            line.cells.truncate(line.cells.len() - n);
+            line.cells.splice(col..col, std::iter::repeat_n(Cell::default(), n));
+        }
+    }
+
+    /// Delete cells at a position, shifting the rest left
+    pub fn delete_cells(&mut self, row: usize, col: usize, n: usize) {
+        if let Some(line) = self.lines.get_mut(row)
+            && col < line.cells.len()
+        {
+            let n = n.min(line.cells.len() - col);
+            line.cells.drain(col..col + n);
+            line.cells.resize(self.cols, Cell::default());
+        }
+    }
+
+    /// Insert a blank line at `row`, pushing lines down to `bottom`
+    pub fn insert_line(&mut self, row: usize, bottom: usize) {
+        if row <= bottom && bottom < self.lines.len() {
+            self.lines.remove(bottom);
+            self.lines.insert(row, Line::blank(self.cols));
+        }
+    }
+
+    /// Delete the line at `row`, pulling lines up from `bottom`
+    pub fn delete_line(&mut self, row: usize, bottom: usize) {
+        if row <= bottom && bottom < self.lines.len() {
+            self.lines.remove(row);
+            self.lines.insert(bottom, Line::blank(self.cols));
+        }
+    }
+
+    /// Scroll the region up one line; lines leaving the top of the screen
+    /// go to scrollback
+    pub fn scroll_up(&mut self, top: usize, bottom: usize) {
+        if top > bottom || bottom >= self.lines.len() {
+            return;
+        }
+        let line = self.lines.remove(top);
+        self.lines.insert(bottom, Line::blank(self.cols));
+
+        if top == 0 {
+            if self.max_scrollback == 0 {
+                // Still count it so absolute lines keep scrolling
+                self.dropped += 1;
+                return;
+            }
+            if self.scrollback.len() == self.max_scrollback {
+                self.scrollback.pop_front();
+                self.dropped += 1;
+            }
+            self.scrollback.push_back(line);
+        }
+    }
+
+    /// Scroll the region down one line
+    pub fn scroll_down(&mut self, top: usize, bottom: usize) {
+        if top > bottom || bottom >= self.lines.len() {
+            return;
+        }
+        self.lines.remove(bottom);
+        self.lines.insert(top, Line::blank(self.cols));
     }
 
     /// Get screen dimensions
@@ -156,59 +428,68 @@ impl Screen {
         self.cursor_visible
     }
 
-    /// Update screen from vt100 parser
-    pub fn update_from_vt100(&mut self, vt_screen: &vt100::Screen) {
-        // Update dimensions if needed
-        let (vt_rows, vt_cols) = vt_screen.size();
-        if self.rows != vt_rows as usize || self.cols != vt_cols as usize {
-            self.resize(vt_rows as usize, vt_cols as usize);
-        }
-
-        // Copy cells
-        for row in 0..self.rows {
-            for col in 0..self.cols {
-                let vt_cell = vt_screen.cell(row as u16, col as u16);
-                if let Some(vt_cell) = vt_cell {
-                    if let Some(cell) = self.cell_mut(row, col) {
-                        cell.char = vt_cell.contents().chars().next().unwrap_or(' ');
-                        
-                        // Copy styles
-                        cell.style.bold = vt_cell.bold();
-                        cell.style.italic = vt_cell.italic();
-                        cell.style.underline = vt_cell.underline();
-                        cell.style.inverse = vt_cell.inverse();
-                        
-                        // Copy colors
-                        cell.style.foreground = convert_vt_color(vt_cell.fgcolor());
-                        cell.style.background = convert_vt_color(vt_cell.bgcolor());
-                    }
-                }
-            }
-        }
-
-        // Update cursor
-        let (cursor_row, cursor_col) = vt_screen.cursor_position();
-        self.cursor_row = cursor_row as usize;
-        self.cursor_col = cursor_col as usize;
-        self.cursor_visible = !vt_screen.hide_cursor();
+    /// Move the cursor
+    pub fn set_cursor(&mut self, row: usize, col: usize, visible: bool) {
+        self.cursor_row = row.min(self.rows.saturating_sub(1));
+        self.cursor_col = col.min(self.cols.saturating_sub(1));
+        self.cursor_visible = visible;
     }
 
     /// Get row as string
     pub fn row_text(&self, idx: usize) -> String {
-        self.cells.get(idx)
-            .map(|row| row.iter().map(|c| c.char).collect())
+        self.lines.get(idx)
+            .map(|row| row.cells.iter().map(|c| c.char).collect())
             .unwrap_or_default()
     }
 
     /// Get all text content
     pub fn text(&self) -> String {
-        self.cells
+        self.lines
             .iter()
-            .map(|row| row.iter().map(|c| c.char).collect::<String>())
+            .map(|row| row.cells.iter().map(|c| c.char).collect::<String>())
             .collect::<Vec<_>>()
             .join("\n")
     }
 
+    /// Number of lines dropped from the scrollback so far. Adding it to a
+    /// line index gives an absolute line that survives further scrolling
+    pub fn line_offset(&self) -> usize {
+        self.dropped
+    }
+
+    /// Absolute line of a visible row
+    pub fn absolute_line(&self, row: usize) -> usize {
+        self.dropped + self.scrollback.len() + row
+    }
+
+    /// Text between two absolute positions (end exclusive). Soft-wrapped
+    /// rows are joined without a newline
+    pub fn text_range(&self, start: (usize, usize), end: (usize, usize)) -> String {
+        let mut text = String::new();
+        for abs in start.0.max(self.dropped)..=end.0 {
+            let Some(line) = self.physical_line(abs - self.dropped) else {
+                break;
+            };
+            let from = if abs == start.0 { start.1 } else { 0 };
+            let to = if abs == end.0 { end.1 } else { line.cells.len() };
+            let row: String = line.cells.get(from..to.min(line.cells.len()))
+                .unwrap_or_default()
+                .iter()
+                .map(|c| c.char)
+                .collect();
+
+            if line.wrapped && abs != end.0 {
+                text.push_str(&row);
+            } else {
+                text.push_str(row.trim_end());
+                if abs != end.0 {
+                    text.push('\n');
+                }
+            }
+        }
+        text
+    }
+
     /// Get scrollback line count
     pub fn scrollback_len(&self) -> usize {
         self.scrollback.len()
@@ -216,30 +497,176 @@ impl Screen {
 
     /// Get scrollback line
     pub fn scrollback_line(&self, idx: usize) -> Option<&[Cell]> {
-        self.scrollback.get(idx).map(|r| r.as_slice())
+        self.scrollback.get(idx).map(|r| r.cells.as_slice())
+    }
+
+    /// Scrollback plus visible line count
+    pub fn total_lines(&self) -> usize {
+        self.scrollback.len() + self.lines.len()
+    }
+
+    /// Get a line counting from the oldest scrollback line
+    pub fn line(&self, idx: usize) -> Option<&[Cell]> {
+        self.physical_line(idx).map(|l| l.cells.as_slice())
+    }
+
+    fn physical_line(&self, idx: usize) -> Option<&Line> {
+        match idx.checked_sub(self.scrollback.len()) {
+            Some(row) => self.lines.get(row),
+            None => self.scrollback.get(idx),
+        }
+    }
+
+    /// Search scrollback and screen. Matches may span soft-wrapped rows
+    pub fn search(&self, query: &str, mode: SearchMode, max_results: usize) -> Vec<ScreenMatch> {
+        let Some(regex) = search_regex(query, mode) else {
+            return Vec::new();
+        };
+
+        let mut results = Vec::new();
+        let mut text = String::new();
+        // Char index -> (line, col)
+        let mut positions: Vec<(usize, usize)> = Vec::new();
+
+        let total = self.total_lines();
+        for idx in 0..total {
+            let Some(line) = self.physical_line(idx) else {
+                break;
+            };
+            for (col, cell) in line.cells.iter().enumerate() {
+                text.push(cell.char);
+                positions.push((idx, col));
+            }
+            if line.wrapped && idx + 1 < total {
+                continue;
+            }
+
+            let trimmed = text.trim_end_matches(' ');
+            let mut char_index = 0;
+            let mut byte_index = 0;
+            for m in regex.find_iter(trimmed).filter(|m| !m.is_empty()) {
+                char_index += trimmed[byte_index..m.start()].chars().count();
+                let start = positions[char_index];
+                let end_index = char_index + m.as_str().chars().count() - 1;
+                let end = positions[end_index];
+                char_index = end_index + 1;
+                byte_index = m.end();
+
+                results.push(ScreenMatch {
+                    start_line: start.0,
+                    start_col: start.1,
+                    end_line: end.0,
+                    end_col: end.1 + 1,
+                });
+            }
+
+            text.clear();
+            positions.clear();
+        }
+
+        if mode == SearchMode::Backward {
+            results.reverse();
+        }
+        results.truncate(max_results);
+        results
     }
 }
 
-fn convert_vt_color(color: vt100::Color) -> Color {
-    match color {
-        vt100::Color::Default => Color::Default,
-        vt100::Color::Idx(0) => Color::Black,
-        vt100::Color::Idx(1) => Color::Red,
-        vt100::Color::Idx(2) => Color::Green,
-        vt100::Color::Idx(3) => Color::Yellow,
-        vt100::Color::Idx(4) => Color::Blue,
-        vt100::Color::Idx(5) => Color::Magenta,
-        vt100::Color::Idx(6) => Color::Cyan,
-        vt100::Color::Idx(7) => Color::White,
-        vt100::Color::Idx(8) => Color::BrightBlack,
-        vt100::Color::Idx(9) => Color::BrightRed,
-        vt100::Color::Idx(10) => Color::BrightGreen,
-        vt100::Color::Idx(11) => Color::BrightYellow,
-        vt100::Color::Idx(12) => Color::BrightBlue,
-        vt100::Color::Idx(13) => Color::BrightMagenta,
-        vt100::Color::Idx(14) => Color::BrightCyan,
-        vt100::Color::Idx(15) => Color::BrightWhite,
-        vt100::Color::Idx(n) => Color::Indexed(n),
-        vt100::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+/// Compile a history-style search into a regex
+fn search_regex(query: &str, mode: SearchMode) -> Option<Regex> {
+    if query.is_empty() {
+        return None;
+    }
+    let pattern = match mode {
+        SearchMode::Regex => query.to_string(),
+        SearchMode::Prefix => format!("(?i)^{}", regex::escape(query)),
+        SearchMode::Substring | SearchMode::Backward | SearchMode::Forward => {
+            format!("(?i){}", regex::escape(query))
+        }
+        SearchMode::Fuzzy => format!(
+            "(?i){}",
+            query.chars()
+                .map(|c| regex::escape(&c.to_string()))
+                .collect::<Vec<_>>()
+                .join(".*?")
+        ),
+    };
+    Regex::new(&pattern).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(screen: &mut Screen, row: usize, text: &str) {
+        for (col, c) in text.chars().enumerate() {
+            screen.set_cell(row, col, Cell { char: c, style: CellStyle::default() });
+        }
+    }
+
+    #[test]
+    fn test_scrollback_ring_buffer() {
+        let mut screen = Screen::new(2, 10).with_scrollback(3);
+        for i in 0..6 {
+            write(&mut screen, 1, &format!("line {}", i));
+            screen.scroll_up(0, 1);
+        }
+        assert_eq!(screen.scrollback_len(), 3);
+        let first: String = screen.scrollback_line(0).unwrap().iter().map(|c| c.char).collect();
+        assert_eq!(first.trim_end(), "line 2");
+
+        let mut alt = Screen::alternate(2, 10);
+        alt.scroll_up(0, 1);
+        assert_eq!(alt.scrollback_len(), 0);
+    }
+
+    #[test]
+    fn test_reflow() {
+        let mut screen = Screen::new(3, 10);
+        write(&mut screen, 0, "abcdefghij");
+        screen.set_wrapped(0, true);
+        write(&mut screen, 1, "klm");
+        write(&mut screen, 2, "xyz");
+        screen.set_cursor(2, 3, true);
+
+        // Narrower: the wrapped line takes 3 rows, the top goes to scrollback
+        screen.resize(3, 5);
+        assert_eq!(screen.scrollback_len(), 1);
+        assert_eq!(screen.row_text(0), "fghij");
+        assert!(screen.is_wrapped(0));
+        assert_eq!(screen.row_text(1), "klm  ");
+        assert_eq!(screen.row_text(2), "xyz  ");
+        assert_eq!(screen.cursor(), (2, 3));
+
+        // Wider again: lines are joined back and pulled out of scrollback
+        screen.resize(3, 20);
+        assert_eq!(screen.scrollback_len(), 0);
+        assert_eq!(screen.row_text(0).trim_end(), "abcdefghijklm");
+        assert_eq!(screen.row_text(1).trim_end(), "xyz");
+        assert_eq!(screen.cursor(), (1, 3));
+    }
+
+    #[test]
+    fn test_search() {
+        let mut screen = Screen::new(2, 8);
+        write(&mut screen, 0, "error: a");
+        screen.scroll_up(0, 1);
+        write(&mut screen, 0, "warn");
+        write(&mut screen, 1, "Error 42");
+
+        let matches = screen.search("error", SearchMode::Substring, 10);
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0], ScreenMatch { start_line: 0, start_col: 0, end_line: 0, end_col: 5 });
+
+        let matches = screen.search(r"\d+", SearchMode::Regex, 10);
+        assert_eq!(matches, vec![ScreenMatch { start_line: 2, start_col: 6, end_line: 2, end_col: 8 }]);
+
+        // Matches across a soft wrap
+        let mut wrapped = Screen::new(2, 4);
+        write(&mut wrapped, 0, "hell");
+        wrapped.set_wrapped(0, true);
+        write(&mut wrapped, 1, "o");
+        let matches = wrapped.search("hello", SearchMode::Substring, 10);
+        assert_eq!(matches, vec![ScreenMatch { start_line: 0, start_col: 0, end_line: 1, end_col: 1 }]);
     }
 }
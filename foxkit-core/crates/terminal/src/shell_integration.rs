@@ -0,0 +1,695 @@
+//! Shell integration.
+//!
+//! Understands the FinalTerm (OSC 133) and VS Code (OSC 633) shell integration
+//! sequences plus OSC 7 working directory reports, and groups the output into
+//! command blocks. Injection scripts for bash, zsh and fish make the shell emit
+//! those sequences.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::history::HistoryEntry;
+use crate::profiles::ShellConfig;
+
+/// Maximum number of command blocks kept per terminal.
+pub const MAX_COMMAND_BLOCKS: usize = 1000;
+
+/// A shell integration mark.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ShellMark {
+    /// The prompt is about to be drawn (A).
+    PromptStart,
+    /// The prompt is drawn and command input starts (B).
+    CommandStart,
+    /// The command was submitted and its output starts (C).
+    CommandExecuted,
+    /// The command finished (D). No exit code means no command was run.
+    CommandFinished { exit_code: Option<i32> },
+    /// The command line as typed (633 E).
+    CommandLine(String),
+    /// The working directory changed (OSC 7 or 633 P;Cwd=).
+    Cwd(PathBuf),
+}
+
+impl ShellMark {
+    /// Parse the payload of an OSC 133 or OSC 633 sequence.
+    pub fn parse(data: &str) -> Option<Self> {
+        let mut parts = data.split(';');
+        match parts.next()? {
+            "A" => Some(Self::PromptStart),
+            "B" => Some(Self::CommandStart),
+            "C" => Some(Self::CommandExecuted),
+            "D" => Some(Self::CommandFinished {
+                exit_code: parts.next().and_then(|c| c.trim().parse().ok()),
+            }),
+            "E" => Some(Self::CommandLine(unescape(parts.next().unwrap_or_default()))),
+            "P" => {
+                let (key, value) = parts.next()?.split_once('=')?;
+                (key == "Cwd").then(|| Self::Cwd(PathBuf::from(unescape(value))))
+            }
+            _ => None,
+        }
+    }
+
+    /// Parse an OSC 7 `file://host/path` report.
+    pub fn parse_cwd(data: &str) -> Option<Self> {
+        let rest = data.strip_prefix("file://")?;
+        let path = &rest[rest.find('/')?..];
+        Some(Self::Cwd(PathBuf::from(percent_decode(path))))
+    }
+}
+
+/// Undo the OSC 633 escaping of `\` and control characters (`\\`, `\xHH`).
+fn unescape(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.peek() {
+            Some('\\') => {
+                chars.next();
+                out.push('\\');
+            }
+            Some('x') => {
+                chars.next();
+                let hex: String = chars.by_ref().take(2).collect();
+                match u8::from_str_radix(&hex, 16) {
+                    Ok(byte) => out.push(byte as char),
+                    Err(_) => {
+                        out.push_str("\\x");
+                        out.push_str(&hex);
+                    }
+                }
+            }
+            _ => out.push('\\'),
+        }
+    }
+    out
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value.get(i + 1..i + 3).and_then(|h| u8::from_str_radix(h, 16).ok())
+        {
+            out.push(byte);
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Gutter status of a command block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// Still running.
+    Running,
+    /// Exited with code 0.
+    Success,
+    /// Exited with a non-zero code.
+    Failure(i32),
+    /// Finished without reporting an exit code.
+    Unknown,
+}
+
+/// A prompt, its command and the command's output.
+///
+/// Lines are absolute screen lines (see `Screen::line_offset`); they are not
+/// adjusted when a resize reflows the scrollback.
+#[derive(Debug, Clone)]
+pub struct CommandBlock {
+    /// Line the prompt starts on.
+    pub prompt_line: usize,
+    /// Where command input starts, after the prompt.
+    pub command_start: (usize, usize),
+    /// Where the output starts.
+    pub output_start: Option<(usize, usize)>,
+    /// Where the output ends.
+    pub output_end: Option<(usize, usize)>,
+    /// The command line.
+    pub command: Option<String>,
+    /// Working directory the command ran in.
+    pub cwd: Option<PathBuf>,
+    /// Exit code.
+    pub exit_code: Option<i32>,
+    /// How long the command ran.
+    pub duration: Option<Duration>,
+    /// When the command was submitted.
+    started: Option<Instant>,
+}
+
+impl CommandBlock {
+    fn new(line: usize, col: usize) -> Self {
+        Self {
+            prompt_line: line,
+            command_start: (line, col),
+            output_start: None,
+            output_end: None,
+            command: None,
+            cwd: None,
+            exit_code: None,
+            duration: None,
+            started: None,
+        }
+    }
+
+    /// Check if the command has finished.
+    pub fn is_finished(&self) -> bool {
+        self.output_end.is_some()
+    }
+
+    /// Gutter status.
+    pub fn status(&self) -> CommandStatus {
+        match (self.is_finished(), self.exit_code) {
+            (false, _) => CommandStatus::Running,
+            (true, Some(0)) => CommandStatus::Success,
+            (true, Some(code)) => CommandStatus::Failure(code),
+            (true, None) => CommandStatus::Unknown,
+        }
+    }
+
+    /// Last line belonging to the block.
+    pub fn end_line(&self) -> usize {
+        self.output_end
+            .or(self.output_start)
+            .map_or(self.command_start.0, |(line, _)| line)
+    }
+
+    /// Check if a line is inside the block.
+    pub fn contains(&self, line: usize) -> bool {
+        (self.prompt_line..=self.end_line()).contains(&line)
+    }
+
+    /// Build a history entry for a finished command.
+    pub fn history_entry(&self) -> Option<HistoryEntry> {
+        let command = self.command.as_deref()?.trim();
+        if command.is_empty() {
+            return None;
+        }
+        let mut entry = HistoryEntry::new(command);
+        entry.cwd = self.cwd.clone();
+        entry.exit_code = self.exit_code;
+        entry.duration_ms = self.duration.map(|d| d.as_millis() as u64);
+        Some(entry)
+    }
+}
+
+/// Tracks command blocks from shell integration marks.
+#[derive(Debug, Default)]
+pub struct CommandTracker {
+    blocks: VecDeque<CommandBlock>,
+    cwd: Option<PathBuf>,
+    enabled: bool,
+}
+
+impl CommandTracker {
+    /// Create a new tracker.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handle a mark at an absolute position. Returns the block when its
+    /// command finished.
+    pub fn handle(&mut self, mark: ShellMark, line: usize, col: usize) -> Option<&CommandBlock> {
+        self.enabled = true;
+        match mark {
+            ShellMark::PromptStart => {
+                // A prompt without a command before it (Ctrl-C, empty line)
+                if self.blocks.back().is_some_and(|b| b.output_start.is_none()) {
+                    self.blocks.pop_back();
+                }
+                if self.blocks.len() == MAX_COMMAND_BLOCKS {
+                    self.blocks.pop_front();
+                }
+                self.blocks.push_back(CommandBlock::new(line, col));
+            }
+            ShellMark::CommandStart => match self.running_mut() {
+                Some(block) if block.output_start.is_none() => block.command_start = (line, col),
+                _ => self.blocks.push_back(CommandBlock::new(line, col)),
+            },
+            ShellMark::CommandLine(command) => {
+                if let Some(block) = self.running_mut() {
+                    block.command = Some(command);
+                }
+            }
+            ShellMark::CommandExecuted => {
+                let cwd = self.cwd.clone();
+                if let Some(block) = self.running_mut() {
+                    block.output_start = Some((line, col));
+                    block.started = Some(Instant::now());
+                    block.cwd = cwd;
+                }
+            }
+            ShellMark::CommandFinished { exit_code } => {
+                let block = self.running_mut()?;
+                block.output_start?;
+                block.output_end = Some((line, col));
+                block.exit_code = exit_code;
+                block.duration = block.started.map(|s| s.elapsed());
+                return self.blocks.back();
+            }
+            ShellMark::Cwd(path) => self.cwd = Some(path),
+        }
+        None
+    }
+
+    fn running_mut(&mut self) -> Option<&mut CommandBlock> {
+        self.blocks.back_mut().filter(|b| !b.is_finished())
+    }
+
+    /// Check if the shell has sent any integration marks.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Working directory last reported by the shell.
+    pub fn cwd(&self) -> Option<&Path> {
+        self.cwd.as_deref()
+    }
+
+    /// All blocks, oldest first.
+    pub fn blocks(&self) -> impl Iterator<Item = &CommandBlock> {
+        self.blocks.iter()
+    }
+
+    /// The block whose command is running or being typed.
+    pub fn current(&self) -> Option<&CommandBlock> {
+        self.blocks.back().filter(|b| !b.is_finished())
+    }
+
+    /// The most recently finished command.
+    pub fn last_finished(&self) -> Option<&CommandBlock> {
+        self.blocks.iter().rev().find(|b| b.is_finished())
+    }
+
+    /// The block containing a line.
+    pub fn block_at(&self, line: usize) -> Option<&CommandBlock> {
+        self.blocks.iter().rev().find(|b| b.contains(line))
+    }
+
+    /// Prompt line of the closest prompt above a line.
+    pub fn previous_prompt(&self, line: usize) -> Option<usize> {
+        self.blocks.iter().rev().map(|b| b.prompt_line).find(|&l| l < line)
+    }
+
+    /// Prompt line of the closest prompt below a line.
+    pub fn next_prompt(&self, line: usize) -> Option<usize> {
+        self.blocks.iter().map(|b| b.prompt_line).find(|&l| l > line)
+    }
+
+    /// Status marks for the gutter, keyed by prompt line.
+    pub fn gutter_marks(&self) -> Vec<(usize, CommandStatus)> {
+        self.blocks
+            .iter()
+            .filter(|b| b.output_start.is_some())
+            .map(|b| (b.prompt_line, b.status()))
+            .collect()
+    }
+
+    /// Set the command text of the running block if the shell didn't report it.
+    pub fn fill_command(&mut self, command: impl FnOnce(&CommandBlock) -> String) {
+        if let Some(block) = self.running_mut()
+            && block.command.is_none()
+        {
+            block.command = Some(command(block));
+        }
+    }
+
+    /// Drop blocks that scrolled out of the scrollback.
+    pub fn prune(&mut self, first_line: usize) {
+        while self.blocks.front().is_some_and(|b| b.end_line() < first_line) {
+            self.blocks.pop_front();
+        }
+    }
+
+    /// Forget all blocks, e.g. when the scrollback is cleared.
+    pub fn clear(&mut self) {
+        self.blocks.clear();
+    }
+}
+
+/// Shells with an injection script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl ShellKind {
+    /// Guess the shell from its executable.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_stem()?.to_str()?;
+        match name.trim_start_matches('-') {
+            "bash" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+}
+
+/// Arguments and environment that load the integration script.
+#[derive(Debug, Clone, Default)]
+pub struct Injection {
+    /// Extra shell arguments.
+    pub args: Vec<String>,
+    /// Extra environment variables.
+    pub env: HashMap<String, String>,
+}
+
+/// Shell integration script for a shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ShellIntegration {
+    kind: ShellKind,
+}
+
+impl ShellIntegration {
+    /// Integration for a configured shell, if it is supported.
+    pub fn for_shell(shell: &ShellConfig) -> Option<Self> {
+        ShellKind::from_path(&shell.path).map(|kind| Self { kind })
+    }
+
+    /// The shell this integration is for.
+    pub fn kind(&self) -> ShellKind {
+        self.kind
+    }
+
+    /// The integration script.
+    pub fn script(&self) -> &'static str {
+        match self.kind {
+            ShellKind::Bash => BASH_SCRIPT,
+            ShellKind::Zsh => ZSH_SCRIPT,
+            ShellKind::Fish => FISH_SCRIPT,
+        }
+    }
+
+    /// Write the script into `dir` and return what the shell needs to load it.
+    /// The user's own startup files are still sourced.
+    ///
+    /// `dir` is created private to the current user and must not exist yet, so
+    /// another user cannot plant startup files the shell would then source.
+    pub fn inject(&self, dir: &Path) -> std::io::Result<Injection> {
+        let mut builder = std::fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(dir)?;
+        let mut injection = Injection::default();
+        match self.kind {
+            ShellKind::Bash => {
+                let path = dir.join("foxkit.bash");
+                std::fs::write(&path, BASH_SCRIPT)?;
+                injection.args = vec!["--init-file".into(), path.to_string_lossy().into_owned()];
+            }
+            ShellKind::Zsh => {
+                // zsh has no --init-file; point ZDOTDIR at startup files that
+                // source the user's ones first
+                std::fs::write(dir.join(".zshenv"), ZSH_ENV_SCRIPT)?;
+                std::fs::write(dir.join(".zshrc"), ZSH_SCRIPT)?;
+                let user_zdotdir = std::env::var("ZDOTDIR")
+                    .or_else(|_| std::env::var("HOME"))
+                    .unwrap_or_default();
+                injection.env.insert("FOXKIT_USER_ZDOTDIR".into(), user_zdotdir);
+                injection.env.insert("ZDOTDIR".into(), dir.to_string_lossy().into_owned());
+            }
+            ShellKind::Fish => {
+                let path = dir.join("foxkit.fish");
+                std::fs::write(&path, FISH_SCRIPT)?;
+                injection.args = vec![
+                    "--init-command".into(),
+                    format!("source '{}'", path.to_string_lossy().replace('\'', "\\'")),
+                ];
+            }
+        }
+        injection.env.insert("FOXKIT_SHELL_INTEGRATION".into(), "1".into());
+        Ok(injection)
+    }
+}
+
+impl ShellConfig {
+    /// Shell integration for this shell, if supported.
+    pub fn integration(&self) -> Option<ShellIntegration> {
+        ShellIntegration::for_shell(self)
+    }
+}
+
+const BASH_SCRIPT: &str = r#"# Foxkit shell integration for bash
+if [ -n "$__foxkit_loaded" ]; then return; fi
+__foxkit_loaded=1
+
+if shopt -q login_shell; then
+    [ -r /etc/profile ] && . /etc/profile
+    for __foxkit_rc in ~/.bash_profile ~/.bash_login ~/.profile; do
+        if [ -r "$__foxkit_rc" ]; then . "$__foxkit_rc"; break; fi
+    done
+    unset __foxkit_rc
+elif [ -r ~/.bashrc ]; then
+    . ~/.bashrc
+fi
+
+__foxkit_escape() {
+    local s="${1//\\/\\\\}"
+    s="${s//;/\\x3b}"
+    printf '%s' "${s//$'\n'/\\x0a}"
+}
+
+__foxkit_ready=0
+__foxkit_in_command=0
+
+__foxkit_preexec() {
+    [ "$__foxkit_ready" = 1 ] || return
+    [ -n "$COMP_LINE" ] && return
+    # PROMPT_COMMAND runs on an empty Enter too, without a command line
+    local prompt_command="${PROMPT_COMMAND//$'\n'/;}"
+    case ";${prompt_command//; /;};" in
+        *";$BASH_COMMAND;"*) return ;;
+    esac
+    __foxkit_ready=0
+    __foxkit_in_command=1
+    local cmd
+    cmd="$(HISTTIMEFORMAT= builtin history 1 | sed 's/^ *[0-9]* *//')"
+    printf '\e]633;E;%s\a\e]633;C\a' "$(__foxkit_escape "$cmd")"
+}
+
+__foxkit_precmd() {
+    local code=$?
+    __foxkit_ready=0
+    if [ "$__foxkit_in_command" = 1 ]; then
+        printf '\e]633;D;%s\a' "$code"
+    else
+        printf '\e]633;D\a'
+    fi
+    __foxkit_in_command=0
+    printf '\e]633;P;Cwd=%s\a' "$(__foxkit_escape "$PWD")"
+}
+
+PROMPT_COMMAND="__foxkit_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND};__foxkit_ready=1"
+PS1="\[\e]633;A\a\]$PS1\[\e]633;B\a\]"
+trap '__foxkit_preexec' DEBUG
+"#;
+
+const ZSH_ENV_SCRIPT: &str = r#"# Foxkit shell integration for zsh
+__foxkit_zdotdir="$ZDOTDIR"
+ZDOTDIR="$FOXKIT_USER_ZDOTDIR"
+[ -r "$ZDOTDIR/.zshenv" ] && . "$ZDOTDIR/.zshenv"
+FOXKIT_USER_ZDOTDIR="$ZDOTDIR"
+ZDOTDIR="$__foxkit_zdotdir"
+unset __foxkit_zdotdir
+"#;
+
+const ZSH_SCRIPT: &str = r#"# Foxkit shell integration for zsh
+ZDOTDIR="$FOXKIT_USER_ZDOTDIR"
+[ -r "$ZDOTDIR/.zshrc" ] && . "$ZDOTDIR/.zshrc"
+
+__foxkit_escape() {
+    local s="${1//\\/\\\\}"
+    s="${s//;/\\x3b}"
+    print -rn -- "${s//$'\n'/\\x0a}"
+}
+
+__foxkit_precmd() {
+    local code=$?
+    if [[ -n $__foxkit_in_command ]]; then
+        print -n "\e]633;D;$code\a"
+    else
+        print -n "\e]633;D\a"
+    fi
+    unset __foxkit_in_command
+    print -n "\e]633;P;Cwd=$(__foxkit_escape "$PWD")\a"
+    if [[ $PS1 != *633\;A* ]]; then
+        PS1=$'%{\e]633;A\a%}'"$PS1"$'%{\e]633;B\a%}'
+    fi
+}
+
+__foxkit_preexec() {
+    __foxkit_in_command=1
+    print -n "\e]633;E;$(__foxkit_escape "$1")\a\e]633;C\a"
+}
+
+autoload -Uz add-zsh-hook
+add-zsh-hook precmd __foxkit_precmd
+add-zsh-hook preexec __foxkit_preexec
+"#;
+
+const FISH_SCRIPT: &str = r#"# Foxkit shell integration for fish
+if set -q __foxkit_loaded
+    exit
+end
+set -g __foxkit_loaded 1
+
+function __foxkit_escape
+    string replace -a '\\' '\\\\' -- $argv | string replace -a ';' '\\x3b' | string join '\\x0a'
+end
+
+function __foxkit_preexec --on-event fish_preexec
+    printf '\e]633;E;%s\a\e]633;C\a' (__foxkit_escape $argv)
+end
+
+function __foxkit_postexec --on-event fish_postexec
+    printf '\e]633;D;%s\a' $status
+end
+
+function __foxkit_cwd --on-variable PWD
+    printf '\e]633;P;Cwd=%s\a' (__foxkit_escape $PWD)
+end
+
+functions -c fish_prompt __foxkit_fish_prompt
+function fish_prompt
+    printf '\e]633;A\a'
+    __foxkit_fish_prompt
+    printf '\e]633;B\a'
+end
+
+__foxkit_cwd
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_marks() {
+        assert_eq!(ShellMark::parse("A"), Some(ShellMark::PromptStart));
+        assert_eq!(ShellMark::parse("D;1"), Some(ShellMark::CommandFinished { exit_code: Some(1) }));
+        assert_eq!(ShellMark::parse("D"), Some(ShellMark::CommandFinished { exit_code: None }));
+        assert_eq!(
+            ShellMark::parse(r"E;echo a\x3bb \\n;nonce"),
+            Some(ShellMark::CommandLine(r"echo a;b \n".to_string()))
+        );
+        assert_eq!(
+            ShellMark::parse("P;Cwd=/tmp/x"),
+            Some(ShellMark::Cwd(PathBuf::from("/tmp/x")))
+        );
+        assert_eq!(
+            ShellMark::parse_cwd("file://host/home/me/my%20dir"),
+            Some(ShellMark::Cwd(PathBuf::from("/home/me/my dir")))
+        );
+    }
+
+    #[test]
+    fn test_command_blocks() {
+        let mut tracker = CommandTracker::new();
+        tracker.handle(ShellMark::Cwd(PathBuf::from("/repo")), 0, 0);
+        tracker.handle(ShellMark::PromptStart, 0, 0);
+        tracker.handle(ShellMark::CommandStart, 0, 2);
+        tracker.handle(ShellMark::CommandLine("false".into()), 0, 7);
+        tracker.handle(ShellMark::CommandExecuted, 1, 0);
+        let block = tracker.handle(ShellMark::CommandFinished { exit_code: Some(1) }, 1, 0).unwrap();
+        assert_eq!(block.status(), CommandStatus::Failure(1));
+
+        let entry = block.history_entry().unwrap();
+        assert_eq!(entry.command, "false");
+        assert_eq!(entry.exit_code, Some(1));
+        assert_eq!(entry.cwd, Some(PathBuf::from("/repo")));
+        assert!(entry.duration_ms.is_some());
+
+        // An empty prompt is dropped
+        tracker.handle(ShellMark::PromptStart, 1, 0);
+        tracker.handle(ShellMark::CommandStart, 1, 2);
+        assert!(tracker.handle(ShellMark::CommandFinished { exit_code: None }, 2, 0).is_none());
+        tracker.handle(ShellMark::PromptStart, 2, 0);
+        assert_eq!(tracker.blocks().count(), 2);
+
+        assert_eq!(tracker.previous_prompt(2), Some(0));
+        assert_eq!(tracker.next_prompt(0), Some(2));
+        assert_eq!(tracker.gutter_marks(), vec![(0, CommandStatus::Failure(1))]);
+    }
+
+    #[test]
+    fn test_detect_shell() {
+        let bash = ShellConfig::new("/usr/local/bin/bash").integration().unwrap();
+        assert_eq!(bash.kind(), ShellKind::Bash);
+        assert!(bash.script().contains("633;C"));
+        assert!(ShellConfig::new("/bin/tcsh").integration().is_none());
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("zsh");
+        let zsh = ShellConfig::zsh().integration().unwrap();
+        let injection = zsh.inject(&dir).unwrap();
+        assert_eq!(injection.env.get("ZDOTDIR").map(PathBuf::from), Some(dir.clone()));
+        assert!(dir.join(".zshrc").exists());
+
+        // A directory that already exists is never reused
+        assert_eq!(zsh.inject(&dir).unwrap_err().kind(), std::io::ErrorKind::AlreadyExists);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_bash_empty_enter() {
+        use std::io::Write;
+
+        let temp = tempfile::tempdir().unwrap();
+        let injection = ShellConfig::bash().integration().unwrap().inject(&temp.path().join("bash")).unwrap();
+        let mut child = std::process::Command::new("bash")
+            .args(&injection.args)
+            .arg("-i")
+            .env_clear()
+            .env("HOME", temp.path())
+            .env("PATH", "/usr/bin:/bin")
+            .stdin(std::process::Stdio::piped())
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap()
+            .write_all(b"echo hi\n\n\nPROMPT_COMMAND=\"$PROMPT_COMMAND; true\"\n\nexit\n")
+            .unwrap();
+        let output = child.wait_with_output().unwrap();
+
+        let marks: Vec<ShellMark> = String::from_utf8_lossy(&output.stdout)
+            .split("\x1b]633;")
+            .filter_map(|osc| ShellMark::parse(osc.split('\x07').next()?))
+            .collect();
+        let commands: Vec<&str> = marks.iter()
+            .filter_map(|mark| match mark {
+                ShellMark::CommandLine(command) => Some(command.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(commands, ["echo hi", "PROMPT_COMMAND=\"$PROMPT_COMMAND; true\"", "exit"]);
+        let executed = marks.iter().filter(|mark| **mark == ShellMark::CommandExecuted).count();
+        assert_eq!(executed, 3);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_injection_dir_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp = tempfile::tempdir().unwrap();
+        let dir = temp.path().join("bash");
+        ShellConfig::bash().integration().unwrap().inject(&dir).unwrap();
+        let mode = std::fs::metadata(&dir).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+    }
+}
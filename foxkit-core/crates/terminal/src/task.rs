@@ -268,7 +268,7 @@ impl TaskRunner {
         for id in ids {
             // Clone needed data for the task
             if let Some(task) = self.tasks.get(&id).cloned() {
-                let output_tx = self.output_tx.clone();
+                let _output_tx = self.output_tx.clone();
                 
                 let handle = tokio::spawn(async move {
                     // Execute task (simplified - real impl would be more complex)
@@ -77,6 +77,10 @@ impl TestRunner for VitestRunner {
         "vitest"
     }
 
+    fn extensions(&self) -> &[&str] {
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx"]
+    }
+
     async fn run(
         &self,
         tests: &[TestId],
@@ -164,6 +168,10 @@ impl TestRunner for GoTestRunner {
         "go-test"
     }
 
+    fn extensions(&self) -> &[&str] {
+        &["go"]
+    }
+
     async fn run(
         &self,
         tests: &[TestId],
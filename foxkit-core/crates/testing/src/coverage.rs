@@ -1,9 +1,17 @@
 //! Code coverage
 
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+use quick_xml::events::Event;
+use quick_xml::Reader;
 use serde::{Deserialize, Serialize};
 
+use gutter::{GutterService, LineCoverage};
+use minimap_decorations::{CoverageKind, CoverageRange, MinimapDecorationsService};
+
+use crate::formats::attributes;
+
 /// Coverage report
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CoverageReport {
@@ -91,8 +99,123 @@ impl CoverageReport {
 
     /// Parse Cobertura XML format
     pub fn from_cobertura(content: &str) -> anyhow::Result<Self> {
-        // Would parse XML
-        Ok(Self::default())
+        let mut report = Self::default();
+        let mut reader = Reader::from_str(content);
+        let mut sources: Vec<PathBuf> = Vec::new();
+        let mut in_source = false;
+        let mut current_file: Option<PathBuf> = None;
+        let mut method: Option<FunctionCoverage> = None;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) if e.name().as_ref() == b"source" => in_source = true,
+                Event::End(e) if e.name().as_ref() == b"source" => in_source = false,
+                Event::Text(text) if in_source => {
+                    let source = text.unescape()?;
+                    if !source.trim().is_empty() {
+                        sources.push(PathBuf::from(source.trim()));
+                    }
+                }
+                Event::Start(e) if e.name().as_ref() == b"class" => {
+                    current_file = attributes(&e)?
+                        .remove("filename")
+                        .map(|filename| resolve_source(&sources, &filename));
+                    if let Some(file) = &current_file {
+                        report.files
+                            .entry(file.clone())
+                            .or_insert_with(|| FileCoverage::new(file.clone()));
+                    }
+                }
+                Event::End(e) if e.name().as_ref() == b"class" => current_file = None,
+                Event::Start(e) if e.name().as_ref() == b"method" => {
+                    method = Some(FunctionCoverage {
+                        name: attributes(&e)?.remove("name").unwrap_or_default(),
+                        line: 0,
+                        hits: 0,
+                    });
+                }
+                Event::End(e) if e.name().as_ref() == b"method" => {
+                    if let (Some(func), Some(file)) = (method.take(), &current_file)
+                        && let Some(cov) = report.files.get_mut(file)
+                    {
+                        cov.functions.push(func);
+                    }
+                }
+                Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"line" => {
+                    let attrs = attributes(&e)?;
+                    let (Some(line), Some(hits)) = (
+                        attrs.get("number").and_then(|n| n.parse::<u32>().ok()),
+                        attrs.get("hits").and_then(|h| h.parse::<u64>().ok()),
+                    ) else {
+                        continue;
+                    };
+                    let hits = hits.min(u32::MAX as u64) as u32;
+
+                    // Method lines repeat the class lines; they only locate the method
+                    if let Some(func) = &mut method {
+                        if func.line == 0 {
+                            func.line = line;
+                            func.hits = hits;
+                        }
+                        continue;
+                    }
+
+                    let Some(cov) = current_file.as_ref().and_then(|f| report.files.get_mut(f)) else {
+                        continue;
+                    };
+                    let entry = cov.lines.entry(line).or_default();
+                    *entry = (*entry).max(hits);
+
+                    if attrs.get("branch").map(String::as_str) == Some("true")
+                        && let Some((covered, total)) = attrs.get("condition-coverage")
+                            .and_then(|c| parse_condition_coverage(c))
+                    {
+                        for i in 0..total {
+                            cov.branches.push(BranchCoverage { line, taken: i < covered });
+                        }
+                    }
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        report.compute_summary();
+        Ok(report)
+    }
+
+    /// Parse a report, detecting LCOV or Cobertura from its content
+    pub fn parse(content: &str) -> anyhow::Result<Self> {
+        if content.trim_start().starts_with('<') {
+            Self::from_cobertura(content)
+        } else {
+            Self::from_lcov(content)
+        }
+    }
+
+    /// Merge another report, summing hit counts
+    pub fn merge(&mut self, other: CoverageReport) {
+        for (path, cov) in other.files {
+            match self.files.get_mut(&path) {
+                Some(existing) => {
+                    for (line, hits) in cov.lines {
+                        let entry = existing.lines.entry(line).or_default();
+                        *entry = entry.saturating_add(hits);
+                    }
+                    existing.branches.extend(cov.branches);
+                    for func in cov.functions {
+                        match existing.functions.iter_mut().find(|f| f.name == func.name) {
+                            Some(f) => f.hits = f.hits.saturating_add(func.hits),
+                            None => existing.functions.push(func),
+                        }
+                    }
+                }
+                None => {
+                    self.files.insert(path, cov);
+                }
+            }
+        }
+        self.compute_summary();
     }
 
     /// Compute summary from file coverage
@@ -145,6 +268,17 @@ impl CoverageReport {
         self.files.get(path)
     }
 
+    /// Find coverage for a file, matching relative paths by suffix since
+    /// tools disagree on whether to report absolute paths
+    pub fn find_file(&self, path: &Path) -> Option<&FileCoverage> {
+        self.files.get(path).or_else(|| {
+            self.files.iter()
+                .filter(|(p, _)| p.ends_with(path) || path.ends_with(p))
+                .max_by_key(|(p, _)| p.components().count())
+                .map(|(_, cov)| cov)
+        })
+    }
+
     /// Get line coverage for rendering
     pub fn get_line_decorations(&self, path: &PathBuf) -> Vec<LineCoverageDecoration> {
         self.files.get(path)
@@ -241,3 +375,381 @@ pub struct LineCoverageDecoration {
     pub covered: bool,
     pub hits: u32,
 }
+
+/// Coverage of the lines changed against a diff base
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeCoverageReport {
+    /// Files with added or modified lines
+    pub files: Vec<FileChangeCoverage>,
+}
+
+impl ChangeCoverageReport {
+    /// Intersect coverage with the added lines of `diffs`, whose paths are
+    /// relative to `root`
+    pub fn new(report: &CoverageReport, diffs: &[git::Diff], root: &Path) -> Self {
+        let mut files = Vec::new();
+
+        for diff in diffs {
+            let Some(path) = diff.path() else {
+                continue;
+            };
+            let changed: BTreeSet<u32> = diff.added_lines().into_iter().collect();
+            if changed.is_empty() {
+                continue;
+            }
+
+            let path = root.join(path);
+            let coverage = report.find_file(&path);
+            let mut covered = Vec::new();
+            let mut uncovered = Vec::new();
+            for &line in &changed {
+                match coverage.and_then(|c| c.lines.get(&line)) {
+                    Some(&hits) if hits > 0 => covered.push(line),
+                    Some(_) => uncovered.push(line),
+                    None => {}
+                }
+            }
+
+            files.push(FileChangeCoverage {
+                path,
+                changed,
+                covered,
+                uncovered,
+                instrumented: coverage.is_some(),
+            });
+        }
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        Self { files }
+    }
+
+    /// Get the change coverage of a file
+    pub fn get_file(&self, path: &Path) -> Option<&FileChangeCoverage> {
+        self.files.iter().find(|f| f.path == path)
+    }
+
+    /// Changed executable lines
+    pub fn total_lines(&self) -> usize {
+        self.files.iter().map(|f| f.covered.len() + f.uncovered.len()).sum()
+    }
+
+    /// Changed lines executed by tests
+    pub fn covered_lines(&self) -> usize {
+        self.files.iter().map(|f| f.covered.len()).sum()
+    }
+
+    /// Fraction of changed executable lines covered
+    pub fn line_coverage(&self) -> f64 {
+        match self.total_lines() {
+            0 => 1.0,
+            total => self.covered_lines() as f64 / total as f64,
+        }
+    }
+
+    /// Files with uncovered changes
+    pub fn uncovered_files(&self) -> impl Iterator<Item = &FileChangeCoverage> {
+        self.files.iter().filter(|f| !f.uncovered.is_empty())
+    }
+
+    /// Plain text report listing uncovered changed lines
+    pub fn format_report(&self) -> String {
+        let mut out = format!(
+            "Changed lines covered: {}/{} ({:.1}%)\n",
+            self.covered_lines(),
+            self.total_lines(),
+            self.line_coverage() * 100.0,
+        );
+        for file in self.uncovered_files() {
+            let ranges: Vec<_> = file.uncovered_ranges()
+                .into_iter()
+                .map(|(start, end)| if start == end {
+                    start.to_string()
+                } else {
+                    format!("{}-{}", start, end)
+                })
+                .collect();
+            out.push_str(&format!("{}: {}\n", file.path.display(), ranges.join(", ")));
+        }
+        out
+    }
+}
+
+/// Coverage of the changed lines of one file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileChangeCoverage {
+    pub path: PathBuf,
+    /// Added or modified lines (1-based)
+    pub changed: BTreeSet<u32>,
+    /// Changed lines executed by tests
+    pub covered: Vec<u32>,
+    /// Changed executable lines no test reached
+    pub uncovered: Vec<u32>,
+    /// Whether the coverage report knows the file at all
+    pub instrumented: bool,
+}
+
+impl FileChangeCoverage {
+    /// Uncovered lines grouped into runs of consecutive lines
+    pub fn uncovered_ranges(&self) -> Vec<(u32, u32)> {
+        let mut ranges: Vec<(u32, u32)> = Vec::new();
+        for &line in &self.uncovered {
+            match ranges.last_mut() {
+                Some((_, end)) if *end + 1 == line => *end = line,
+                _ => ranges.push((line, line)),
+            }
+        }
+        ranges
+    }
+}
+
+/// Renders coverage into the gutter and minimap
+pub struct CoverageOverlay {
+    gutter: Arc<GutterService>,
+    minimap: Arc<MinimapDecorationsService>,
+}
+
+impl CoverageOverlay {
+    pub fn new(gutter: Arc<GutterService>, minimap: Arc<MinimapDecorationsService>) -> Self {
+        Self { gutter, minimap }
+    }
+
+    /// Show coverage for a file, flagging uncovered changed lines
+    pub fn show(&self, file: &PathBuf, report: &CoverageReport, changes: Option<&ChangeCoverageReport>) {
+        let Some(coverage) = report.find_file(file) else {
+            self.hide(file);
+            return;
+        };
+        let changed = changes.and_then(|c| c.get_file(file)).map(|c| &c.changed);
+
+        let mut lines: Vec<LineCoverage> = coverage.lines.iter()
+            .filter(|(line, _)| **line > 0)
+            .map(|(&line, &hits)| LineCoverage {
+                line: line - 1,
+                hits,
+                changed: changed.is_some_and(|c| c.contains(&line)),
+            })
+            .collect();
+        lines.sort_by_key(|l| l.line);
+
+        self.minimap.set_coverage(file, coverage_ranges(&lines));
+        self.gutter.set_coverage(file, lines);
+    }
+
+    /// Hide coverage for a file
+    pub fn hide(&self, file: &PathBuf) {
+        self.gutter.clear_coverage(file);
+        self.minimap.remove_by_source(file, "coverage");
+    }
+}
+
+/// Group sorted line coverage into minimap ranges
+fn coverage_ranges(lines: &[LineCoverage]) -> Vec<CoverageRange> {
+    let mut ranges: Vec<CoverageRange> = Vec::new();
+    for line in lines {
+        let kind = match (line.hits > 0, line.changed) {
+            (true, _) => CoverageKind::Covered,
+            (false, false) => CoverageKind::Uncovered,
+            (false, true) => CoverageKind::UncoveredChange,
+        };
+        match ranges.last_mut() {
+            Some(range) if range.kind == kind && range.end_line + 1 == line.line => {
+                range.end_line = line.line;
+            }
+            _ => ranges.push(CoverageRange {
+                start_line: line.line,
+                end_line: line.line,
+                kind,
+            }),
+        }
+    }
+    ranges
+}
+
+/// Coverage tool wrapped around a test command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverageTool {
+    /// cargo-llvm-cov
+    LlvmCov,
+    /// c8 (V8 coverage for Node)
+    C8,
+    /// pytest-cov
+    PytestCov,
+}
+
+impl CoverageTool {
+    /// Where the tool should write its LCOV output for one run
+    pub fn output_path(&self) -> PathBuf {
+        let id = uuid::Uuid::new_v4();
+        match self {
+            Self::LlvmCov => std::env::temp_dir().join(format!("llvm-cov-{}.info", id)),
+            // c8 writes `lcov.info` into a reports directory
+            Self::C8 => std::env::temp_dir().join(format!("c8-{}", id)),
+            Self::PytestCov => std::env::temp_dir().join(format!("pytest-cov-{}.info", id)),
+        }
+    }
+
+    /// Arguments asking the tool for an LCOV report at `output`
+    pub fn args(&self, output: &Path) -> Vec<String> {
+        match self {
+            Self::LlvmCov => vec![
+                "llvm-cov".into(),
+                "--lcov".into(),
+                "--output-path".into(),
+                output.display().to_string(),
+            ],
+            Self::C8 => vec![
+                "c8".into(),
+                "--reporter=lcovonly".into(),
+                "--reports-dir".into(),
+                output.display().to_string(),
+            ],
+            Self::PytestCov => vec![
+                "--cov".into(),
+                format!("--cov-report=lcov:{}", output.display()),
+            ],
+        }
+    }
+
+    /// Read and remove the report written to `output`
+    pub async fn collect(&self, output: &Path) -> Option<CoverageReport> {
+        let file = match self {
+            Self::C8 => output.join("lcov.info"),
+            Self::LlvmCov | Self::PytestCov => output.to_path_buf(),
+        };
+
+        let content = tokio::fs::read_to_string(&file).await;
+        let _ = match self {
+            Self::C8 => tokio::fs::remove_dir_all(output).await,
+            Self::LlvmCov | Self::PytestCov => tokio::fs::remove_file(output).await,
+        };
+
+        match content.map_err(anyhow::Error::from).and_then(|c| CoverageReport::from_lcov(&c)) {
+            Ok(report) => Some(report),
+            Err(e) => {
+                tracing::warn!("No coverage from {:?}: {}", self, e);
+                None
+            }
+        }
+    }
+}
+
+/// Resolve a Cobertura filename against the report's source roots
+fn resolve_source(sources: &[PathBuf], filename: &str) -> PathBuf {
+    let path = Path::new(filename);
+    if path.is_absolute() {
+        return path.to_path_buf();
+    }
+    sources.iter()
+        .map(|source| source.join(path))
+        .find(|candidate| candidate.exists())
+        .or_else(|| sources.first().map(|source| source.join(path)))
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Parse `condition-coverage="50% (1/2)"` into (covered, total)
+fn parse_condition_coverage(value: &str) -> Option<(u32, u32)> {
+    let inner = value.split_once('(')?.1.strip_suffix(')')?;
+    let (covered, total) = inner.split_once('/')?;
+    Some((covered.trim().parse().ok()?, total.trim().parse().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COBERTURA: &str = r#"<?xml version="1.0" ?>
+<coverage line-rate="0.75" branch-rate="0.5" version="7.4">
+  <sources>
+    <source>/work/project</source>
+  </sources>
+  <packages>
+    <package name="app">
+      <classes>
+        <class name="math.py" filename="app/math.py" line-rate="0.75">
+          <methods>
+            <method name="add" signature="">
+              <lines><line number="2" hits="3"/></lines>
+            </method>
+          </methods>
+          <lines>
+            <line number="1" hits="1"/>
+            <line number="2" hits="3"/>
+            <line number="4" hits="1" branch="true" condition-coverage="50% (1/2)"/>
+            <line number="5" hits="0"/>
+          </lines>
+        </class>
+      </classes>
+    </package>
+  </packages>
+</coverage>"#;
+
+    #[test]
+    fn test_from_cobertura() {
+        let report = CoverageReport::parse(COBERTURA).unwrap();
+        let file = report.get_file(&PathBuf::from("/work/project/app/math.py")).unwrap();
+
+        assert_eq!(file.lines.len(), 4);
+        assert_eq!(file.lines[&2], 3);
+        assert_eq!(file.lines[&5], 0);
+        assert_eq!(file.branches.len(), 2);
+        assert_eq!(file.branches.iter().filter(|b| b.taken).count(), 1);
+        assert_eq!(file.functions.len(), 1);
+        assert_eq!(file.functions[0].name, "add");
+        assert_eq!(file.functions[0].line, 2);
+
+        assert_eq!(report.summary.covered_lines, 3);
+        assert!(report.find_file(Path::new("app/math.py")).is_some());
+    }
+
+    #[test]
+    fn test_change_coverage() {
+        let lcov = "SF:/repo/src/lib.rs\nDA:1,1\nDA:2,0\nDA:3,0\nDA:5,2\nDA:6,0\nend_of_record\n";
+        let report = CoverageReport::from_lcov(lcov).unwrap();
+        let diff = git::diff::parse_unified_diff(
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -1,0 +2,4 @@\n+a\n+b\n+c\n+d\n@@ -9,0 +14 @@\n+e\n",
+        );
+
+        let changes = ChangeCoverageReport::new(&report, &[diff], Path::new("/repo"));
+        let file = changes.get_file(Path::new("/repo/src/lib.rs")).unwrap();
+
+        // Line 4 isn't executable and line 14 isn't in the report
+        assert_eq!(file.changed.len(), 5);
+        assert_eq!(file.covered, vec![5]);
+        assert_eq!(file.uncovered, vec![2, 3]);
+        assert_eq!(file.uncovered_ranges(), vec![(2, 3)]);
+        assert_eq!(changes.total_lines(), 3);
+        assert!(changes.format_report().contains("/repo/src/lib.rs: 2-3"));
+    }
+
+    #[test]
+    fn test_overlay() {
+        let gutter = Arc::new(GutterService::new());
+        let minimap = Arc::new(MinimapDecorationsService::new());
+        let overlay = CoverageOverlay::new(gutter.clone(), minimap.clone());
+
+        let file = PathBuf::from("/repo/src/lib.rs");
+        let report = CoverageReport::from_lcov(
+            "SF:/repo/src/lib.rs\nDA:1,1\nDA:2,1\nDA:3,0\nDA:4,0\nend_of_record\n",
+        ).unwrap();
+        let diff = git::diff::parse_unified_diff("--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -3 +4 @@\n+x\n");
+        let changes = ChangeCoverageReport::new(&report, &[diff], Path::new("/repo"));
+
+        overlay.show(&file, &report, Some(&changes));
+
+        // Gutter lines are zero-based
+        let decorations = gutter.get_decorations(&file);
+        assert_eq!(decorations.len(), 4);
+        assert!(gutter.get_decoration_at_line(&file, 3).unwrap().glyph.is_some());
+        assert!(gutter.get_decoration_at_line(&file, 2).unwrap().glyph.is_none());
+
+        let ranges: Vec<_> = minimap.get_decorations(&file)
+            .iter()
+            .map(|d| d.line_range())
+            .collect();
+        assert_eq!(ranges, vec![(0, 1), (2, 2), (3, 3)]);
+
+        overlay.hide(&file);
+        assert!(gutter.get_decorations(&file).is_empty());
+        assert!(minimap.get_decorations(&file).is_empty());
+    }
+}
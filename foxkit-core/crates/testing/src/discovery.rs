@@ -1,10 +1,17 @@
 //! Test discovery
+//!
+//! Tests are found by running each language's `tests.scm` query (loaded
+//! through `treesitter::QueryLoader`) over the syntax tree. Items nest by
+//! range, so `describe` blocks, test classes and modules become suites and
+//! Go subtests sit under their parent test. Open buffers keep their tree
+//! so edits reparse incrementally.
 
-use std::path::PathBuf;
-use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use regex::Regex;
+use treesitter::{InputEdit, Language, Parser, Query, QueryLoader, QueryType, Tree};
 
 use crate::TestId;
 
@@ -12,57 +19,26 @@ use crate::TestId;
 pub struct TestDiscovery {
     /// Discovered tests by file
     tests: RwLock<HashMap<PathBuf, Vec<TestItem>>>,
-    /// Discovery patterns by language
-    patterns: HashMap<String, Vec<TestPattern>>,
+    /// Syntax trees of open buffers, reused for incremental parsing
+    trees: RwLock<HashMap<PathBuf, (Language, Tree)>>,
+    /// Compiled test queries by language (`None` if the language has none)
+    queries: RwLock<HashMap<Language, Option<Arc<Query>>>>,
+    /// Loader for `tests.scm` queries
+    query_loader: QueryLoader,
 }
 
 impl TestDiscovery {
     pub fn new() -> Self {
-        let mut patterns = HashMap::new();
-        
-        // Rust patterns
-        patterns.insert("rust".to_string(), vec![
-            TestPattern {
-                pattern: Regex::new(r"#\[test\]").unwrap(),
-                kind: TestPatternKind::Attribute,
-            },
-            TestPattern {
-                pattern: Regex::new(r"#\[tokio::test\]").unwrap(),
-                kind: TestPatternKind::Attribute,
-            },
-            TestPattern {
-                pattern: Regex::new(r"#\[bench\]").unwrap(),
-                kind: TestPatternKind::Benchmark,
-            },
-        ]);
-        
-        // JavaScript/TypeScript patterns
-        patterns.insert("javascript".to_string(), vec![
-            TestPattern {
-                pattern: Regex::new(r#"(it|test)\s*\(\s*['"`]"#).unwrap(),
-                kind: TestPatternKind::FunctionCall,
-            },
-            TestPattern {
-                pattern: Regex::new(r#"describe\s*\(\s*['"`]"#).unwrap(),
-                kind: TestPatternKind::Suite,
-            },
-        ]);
-        
-        // Python patterns
-        patterns.insert("python".to_string(), vec![
-            TestPattern {
-                pattern: Regex::new(r"def\s+test_").unwrap(),
-                kind: TestPatternKind::Function,
-            },
-            TestPattern {
-                pattern: Regex::new(r"class\s+Test").unwrap(),
-                kind: TestPatternKind::Class,
-            },
-        ]);
+        Self::with_query_loader(QueryLoader::with_default_paths())
+    }
 
+    /// Create with a custom query loader
+    pub fn with_query_loader(query_loader: QueryLoader) -> Self {
         Self {
             tests: RwLock::new(HashMap::new()),
-            patterns,
+            trees: RwLock::new(HashMap::new()),
+            queries: RwLock::new(HashMap::new()),
+            query_loader,
         }
     }
 
@@ -73,8 +49,7 @@ impl TestDiscovery {
         // Walk workspace and find test files
         for entry in walkdir(workspace)? {
             if let Some(tests) = self.discover_file(&entry).await? {
-                all_tests.extend(tests.clone());
-                self.tests.write().insert(entry, tests);
+                all_tests.extend(tests);
             }
         }
 
@@ -83,36 +58,15 @@ impl TestDiscovery {
 
     /// Discover tests in a single file
     pub async fn discover_file(&self, file: &PathBuf) -> anyhow::Result<Option<Vec<TestItem>>> {
-        let lang = detect_language(file);
-        
-        let patterns = match lang {
-            Some(l) => self.patterns.get(l),
-            None => return Ok(None),
-        };
-
-        let patterns = match patterns {
-            Some(p) => p,
-            None => return Ok(None),
+        let Some(language) = detect_language(file) else {
+            return Ok(None);
         };
+        if self.query(language).is_none() {
+            return Ok(None);
+        }
 
         let content = tokio::fs::read_to_string(file).await?;
-        let mut tests = Vec::new();
-
-        for (line_num, line) in content.lines().enumerate() {
-            for pattern in patterns {
-                if pattern.pattern.is_match(line) {
-                    // Look for the actual test function/method
-                    if let Some(item) = self.extract_test_item(
-                        file,
-                        &content,
-                        line_num,
-                        &pattern.kind,
-                    ) {
-                        tests.push(item);
-                    }
-                }
-            }
-        }
+        let tests = self.discover_source(file, &content);
 
         if tests.is_empty() {
             Ok(None)
@@ -121,104 +75,78 @@ impl TestDiscovery {
         }
     }
 
-    /// Extract test item from source
-    fn extract_test_item(
-        &self,
-        file: &PathBuf,
-        content: &str,
-        line_num: usize,
-        kind: &TestPatternKind,
-    ) -> Option<TestItem> {
-        let lines: Vec<_> = content.lines().collect();
-        
-        match kind {
-            TestPatternKind::Attribute => {
-                // Look for function on next line
-                for i in (line_num + 1)..lines.len().min(line_num + 5) {
-                    let line = lines[i].trim();
-                    if line.starts_with("fn ") || line.starts_with("async fn ") {
-                        let name = extract_function_name(line)?;
-                        return Some(TestItem {
-                            id: TestId::new(format!("{}::{}", file.display(), name)),
-                            name,
-                            kind: TestItemKind::Test,
-                            file: file.clone(),
-                            line: i as u32,
-                            children: Vec::new(),
-                        });
-                    }
-                }
-            }
-            TestPatternKind::FunctionCall => {
-                // Extract test name from it("name", ...) or test("name", ...)
-                let line = lines[line_num];
-                let name = extract_string_arg(line)?;
-                return Some(TestItem {
-                    id: TestId::new(format!("{}::{}", file.display(), name)),
-                    name,
-                    kind: TestItemKind::Test,
-                    file: file.clone(),
-                    line: line_num as u32,
-                    children: Vec::new(),
-                });
-            }
-            TestPatternKind::Suite => {
-                // describe block - extract name and find child tests
-                let line = lines[line_num];
-                let name = extract_string_arg(line)?;
-                return Some(TestItem {
-                    id: TestId::new(format!("{}::{}", file.display(), name)),
-                    name,
-                    kind: TestItemKind::Suite,
-                    file: file.clone(),
-                    line: line_num as u32,
-                    children: Vec::new(), // Would recursively find
-                });
-            }
-            TestPatternKind::Function => {
-                let line = lines[line_num].trim();
-                let name = extract_python_function_name(line)?;
-                return Some(TestItem {
-                    id: TestId::new(format!("{}::{}", file.display(), name)),
-                    name,
-                    kind: TestItemKind::Test,
-                    file: file.clone(),
-                    line: line_num as u32,
-                    children: Vec::new(),
-                });
-            }
-            TestPatternKind::Class => {
-                let line = lines[line_num].trim();
-                let name = extract_python_class_name(line)?;
-                return Some(TestItem {
-                    id: TestId::new(format!("{}::{}", file.display(), name)),
-                    name,
-                    kind: TestItemKind::Suite,
-                    file: file.clone(),
-                    line: line_num as u32,
-                    children: Vec::new(),
-                });
-            }
-            TestPatternKind::Benchmark => {
-                // Similar to Attribute but for benchmarks
-                for i in (line_num + 1)..lines.len().min(line_num + 5) {
-                    let line = lines[i].trim();
-                    if line.starts_with("fn ") {
-                        let name = extract_function_name(line)?;
-                        return Some(TestItem {
-                            id: TestId::new(format!("{}::{}", file.display(), name)),
-                            name,
-                            kind: TestItemKind::Benchmark,
-                            file: file.clone(),
-                            line: i as u32,
-                            children: Vec::new(),
-                        });
-                    }
-                }
+    /// Discover tests in file contents without keeping the syntax tree
+    pub fn discover_source(&self, file: &Path, source: &str) -> Vec<TestItem> {
+        self.reparse(file, source, None, false)
+    }
+
+    /// Discover tests in a newly opened buffer, keeping its tree for
+    /// incremental reparsing until the buffer is closed
+    pub fn open_buffer(&self, file: &Path, source: &str) -> Vec<TestItem> {
+        self.reparse(file, source, None, true)
+    }
+
+    /// Apply buffer edits and rediscover, reusing the previous tree
+    pub fn edit_buffer(&self, file: &Path, edits: &[InputEdit], source: &str) -> Vec<TestItem> {
+        let old_tree = self.trees.write().remove(file).map(|(_, mut tree)| {
+            for edit in edits {
+                tree.edit(edit);
             }
+            tree
+        });
+        self.reparse(file, source, old_tree.as_ref(), true)
+    }
+
+    /// Drop the syntax tree kept for a closed buffer
+    pub fn close_buffer(&self, file: &Path) {
+        self.trees.write().remove(file);
+    }
+
+    /// Parse and collect items, keeping the tree only for open buffers
+    fn reparse(&self, file: &Path, source: &str, old_tree: Option<&Tree>, keep_tree: bool) -> Vec<TestItem> {
+        let Some(language) = detect_language(file) else {
+            return Vec::new();
+        };
+        let Some(query) = self.query(language) else {
+            return Vec::new();
+        };
+
+        let Some(tree) = Parser::new(language).parse(source, old_tree) else {
+            return Vec::new();
+        };
+
+        let items = collect_items(file, &query, &tree, source);
+        if keep_tree {
+            self.trees.write().insert(file.to_path_buf(), (language, tree));
         }
 
-        None
+        if items.is_empty() {
+            self.tests.write().remove(file);
+        } else {
+            self.tests.write().insert(file.to_path_buf(), items.clone());
+        }
+        items
+    }
+
+    /// Compiled test query for a language
+    fn query(&self, language: Language) -> Option<Arc<Query>> {
+        if let Some(query) = self.queries.read().get(&language) {
+            return query.clone();
+        }
+
+        let query = language.is_available()
+            .then(|| self.query_loader.load(language.id(), QueryType::Tests))
+            .flatten()
+            .and_then(|loaded| match Query::new(language, &loaded.source) {
+                Ok(query) => Some(Arc::new(query)),
+                Err(e) => {
+                    tracing::warn!("Invalid test query for {}: {}", language.id(), e);
+                    None
+                }
+            });
+
+        self.queries.write().insert(language, query.clone());
+        query
     }
 
     /// Get cached tests for file
@@ -226,9 +154,56 @@ impl TestDiscovery {
         self.tests.read().get(file).cloned()
     }
 
+    /// Cached top-level items of all files
+    pub fn all_tests(&self) -> Vec<TestItem> {
+        self.tests_under(Path::new(""))
+    }
+
+    /// Cached top-level items of files under a directory, by file path
+    pub fn tests_under(&self, dir: &Path) -> Vec<TestItem> {
+        let tests = self.tests.read();
+        let mut files: Vec<_> = tests.keys().filter(|file| file.starts_with(dir)).collect();
+        files.sort();
+        files.into_iter().flat_map(|file| tests[file].iter().cloned()).collect()
+    }
+
+    /// Innermost cached test or suite containing a line
+    pub fn test_at(&self, file: &Path, line: u32) -> Option<TestItem> {
+        let tests = self.tests.read();
+        let mut items = tests.get(file)?.as_slice();
+        let mut found = None;
+
+        while let Some(item) = items.iter().find(|i| i.range.contains_line(line)) {
+            found = Some(item);
+            items = &item.children;
+        }
+
+        found.cloned()
+    }
+
+    /// Runnable tests for a set of ids: suites are replaced by the tests
+    /// they contain, ids that aren't cached are kept as they are
+    pub fn expand(&self, ids: &[TestId]) -> Vec<TestId> {
+        let tests = self.tests.read();
+        let mut seen = HashSet::new();
+        let mut expanded = Vec::new();
+
+        for id in ids {
+            let item = tests.get(id.file()).and_then(|items| find_item(items, id));
+            let runnable = match item {
+                Some(item) => item.runnable_ids(),
+                None => vec![id.clone()],
+            };
+            expanded.extend(runnable.into_iter().filter(|id| seen.insert(id.clone())));
+        }
+
+        expanded
+    }
+
     /// Clear cache
     pub fn clear(&self) {
         self.tests.write().clear();
+        self.trees.write().clear();
     }
 }
 
@@ -246,11 +221,37 @@ pub struct TestItem {
     pub kind: TestItemKind,
     pub file: PathBuf,
     pub line: u32,
+    /// Exact source range
+    #[serde(default)]
+    pub range: TestRange,
+    /// Runs once per parameter set (rstest, `test.each`, `parametrize`)
+    #[serde(default)]
+    pub parametrized: bool,
     pub children: Vec<TestItem>,
 }
 
+impl TestItem {
+    /// Number of leaf tests (cases and subtests count individually)
+    pub fn test_count(&self) -> usize {
+        if self.children.is_empty() {
+            usize::from(self.kind != TestItemKind::Suite)
+        } else {
+            self.children.iter().map(TestItem::test_count).sum()
+        }
+    }
+
+    /// Ids a runner can select: the item itself, or for a suite the
+    /// tests nested in it. Cases and subtests run with their test.
+    pub fn runnable_ids(&self) -> Vec<TestId> {
+        if self.kind != TestItemKind::Suite {
+            return vec![self.id.clone()];
+        }
+        self.children.iter().flat_map(TestItem::runnable_ids).collect()
+    }
+}
+
 /// Test item kind
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TestItemKind {
     Test,
     Suite,
@@ -258,103 +259,343 @@ pub enum TestItemKind {
     DocTest,
 }
 
-/// Test pattern
-struct TestPattern {
-    pattern: Regex,
-    kind: TestPatternKind,
+/// Source range of a test item (zero-based)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestRange {
+    pub start_line: u32,
+    pub start_column: u32,
+    pub end_line: u32,
+    pub end_column: u32,
 }
 
-/// Test pattern kind
-#[derive(Debug, Clone)]
-enum TestPatternKind {
-    Attribute,
-    FunctionCall,
-    Suite,
-    Function,
-    Class,
-    Benchmark,
+impl TestRange {
+    /// Does the range cover a line?
+    pub fn contains_line(&self, line: u32) -> bool {
+        self.start_line <= line && line <= self.end_line
+    }
 }
 
-fn detect_language(file: &PathBuf) -> Option<&'static str> {
-    let ext = file.extension()?.to_str()?;
-    match ext {
-        "rs" => Some("rust"),
-        "ts" | "tsx" | "js" | "jsx" | "mjs" => Some("javascript"),
-        "py" => Some("python"),
-        _ => None,
+/// Item found by the query, before nesting
+struct Candidate {
+    kind: TestItemKind,
+    name: String,
+    start_byte: usize,
+    end_byte: usize,
+    range: TestRange,
+    parametrized: bool,
+    cases: Vec<(usize, TestRange)>,
+}
+
+/// Run the test query and nest the captured items by range
+fn collect_items(file: &Path, query: &Query, tree: &Tree, source: &str) -> Vec<TestItem> {
+    // Keyed by the name node, since several patterns can match one item
+    let mut candidates: HashMap<usize, Candidate> = HashMap::new();
+    let mut cases: HashMap<usize, Vec<(usize, TestRange)>> = HashMap::new();
+
+    for m in query.matches(tree.root_node(), source) {
+        let capture = |name: &str| m.captures.iter().find(|c| c.name == name).map(|c| c.node);
+
+        if let (Some(case), Some(of)) = (capture("test.case"), capture("test.case.of")) {
+            cases.entry(of.start_byte()).or_default().push((case.start_byte(), node_range(&case)));
+            continue;
+        }
+
+        let Some((kind, definition, name)) = [
+            ("test", TestItemKind::Test),
+            ("suite", TestItemKind::Suite),
+            ("bench", TestItemKind::Benchmark),
+        ]
+        .into_iter()
+        .find_map(|(prefix, kind)| {
+            let definition = capture(&format!("{}.definition", prefix))?;
+            let name = capture(&format!("{}.name", prefix))?;
+            Some((kind, definition, name))
+        }) else {
+            continue;
+        };
+
+        let parametrized = capture("parametrized").is_some();
+        let candidate = candidates.entry(name.start_byte()).or_insert_with(|| Candidate {
+            kind,
+            name: name.text(source).trim_matches(['"', '\'', '`']).to_string(),
+            start_byte: definition.start_byte(),
+            end_byte: definition.end_byte(),
+            range: node_range(&definition),
+            parametrized,
+            cases: Vec::new(),
+        });
+
+        // Keep the widest definition, e.g. including Python decorators
+        if definition.end_byte() - definition.start_byte() > candidate.end_byte - candidate.start_byte {
+            candidate.start_byte = definition.start_byte();
+            candidate.end_byte = definition.end_byte();
+            candidate.range = node_range(&definition);
+        }
+        candidate.parametrized |= parametrized;
+    }
+
+    for (name_byte, mut found) in cases {
+        if let Some(candidate) = candidates.get_mut(&name_byte) {
+            found.sort_by_key(|(start, _)| *start);
+            found.dedup_by_key(|(start, _)| *start);
+            candidate.cases = found;
+            candidate.parametrized = true;
+        }
+    }
+
+    let mut candidates: Vec<_> = candidates.into_values().collect();
+    candidates.sort_by(|a, b| a.start_byte.cmp(&b.start_byte).then(b.end_byte.cmp(&a.end_byte)));
+
+    let prefix = file.display().to_string();
+    let mut iter = candidates.into_iter().peekable();
+    let mut items = Vec::new();
+    while let Some(candidate) = iter.next() {
+        if let Some(item) = nest(candidate, &mut iter, file, &prefix) {
+            items.push(item);
+        }
+    }
+    items
+}
+
+/// Build an item, consuming following candidates it contains as children.
+/// Suites without any tests are dropped.
+fn nest(
+    candidate: Candidate,
+    rest: &mut std::iter::Peekable<std::vec::IntoIter<Candidate>>,
+    file: &Path,
+    parent_id: &str,
+) -> Option<TestItem> {
+    let id = format!("{}::{}", parent_id, candidate.name);
+    let mut children = Vec::new();
+
+    while let Some(next) = rest.next_if(|next| next.end_byte <= candidate.end_byte) {
+        if let Some(child) = nest(next, rest, file, &id) {
+            children.push(child);
+        }
+    }
+
+    children.extend(candidate.cases.iter().enumerate().map(|(i, (_, range))| {
+        let name = format!("case_{}", i + 1);
+        TestItem {
+            id: TestId::new(format!("{}::{}", id, name)),
+            name,
+            kind: TestItemKind::Test,
+            file: file.to_path_buf(),
+            line: range.start_line,
+            range: *range,
+            parametrized: false,
+            children: Vec::new(),
+        }
+    }));
+
+    if candidate.kind == TestItemKind::Suite && children.is_empty() {
+        return None;
     }
+
+    Some(TestItem {
+        id: TestId::new(id),
+        name: candidate.name,
+        kind: candidate.kind,
+        file: file.to_path_buf(),
+        line: candidate.range.start_line,
+        range: candidate.range,
+        parametrized: candidate.parametrized,
+        children,
+    })
+}
+
+/// Item with the given id, searching nested items
+fn find_item<'a>(items: &'a [TestItem], id: &TestId) -> Option<&'a TestItem> {
+    items.iter().find_map(|item| {
+        if item.id == *id {
+            Some(item)
+        } else {
+            find_item(&item.children, id)
+        }
+    })
+}
+
+fn node_range(node: &treesitter::Node<'_>) -> TestRange {
+    let start = node.start_position();
+    let end = node.end_position();
+    TestRange {
+        start_line: start.row as u32,
+        start_column: start.column as u32,
+        end_line: end.row as u32,
+        end_column: end.column as u32,
+    }
+}
+
+fn detect_language(file: &Path) -> Option<Language> {
+    let ext = file.extension()?.to_str()?;
+    Language::from_extension(ext).filter(Language::is_available)
 }
 
 fn walkdir(path: &PathBuf) -> anyhow::Result<Vec<PathBuf>> {
     let mut files = Vec::new();
-    
+
     fn walk(dir: &PathBuf, files: &mut Vec<PathBuf>) -> anyhow::Result<()> {
         if dir.is_file() {
             files.push(dir.clone());
             return Ok(());
         }
-        
+
         for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
             let path = entry.path();
-            
+
             // Skip hidden and common non-source directories
             let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
             if name.starts_with('.') || name == "node_modules" || name == "target" {
                 continue;
             }
-            
+
             if path.is_dir() {
                 walk(&path, files)?;
             } else if path.is_file() {
                 files.push(path);
             }
         }
-        
+
         Ok(())
     }
-    
+
     walk(path, &mut files)?;
     Ok(files)
 }
 
-fn extract_function_name(line: &str) -> Option<String> {
-    let line = line.trim();
-    let start = if line.starts_with("async fn ") {
-        9
-    } else if line.starts_with("fn ") {
-        3
-    } else {
-        return None;
-    };
-    
-    let rest = &line[start..];
-    let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_')?;
-    Some(rest[..end].to_string())
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn extract_string_arg(line: &str) -> Option<String> {
-    // Find first string in line
-    for quote in ['"', '\'', '`'] {
-        if let Some(start) = line.find(quote) {
-            if let Some(end) = line[start + 1..].find(quote) {
-                return Some(line[start + 1..start + 1 + end].to_string());
-            }
-        }
+    fn discover(file: &str, source: &str) -> Vec<TestItem> {
+        TestDiscovery::with_query_loader(QueryLoader::new()).discover_source(Path::new(file), source)
     }
-    None
+
+    fn names(items: &[TestItem]) -> Vec<&str> {
+        items.iter().map(|i| i.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_rust_modules_and_attributes() {
+        let source = r#"
+fn helper() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn fetches() {}
+
+    /// Cases
+    #[rstest]
+    #[case(1)]
+    #[case(2)]
+    fn doubles(#[case] n: u32) {}
+
+    mod empty {}
 }
+"#;
+        let items = discover("src/lib.rs", source);
+        assert_eq!(names(&items), vec!["tests"]);
 
-fn extract_python_function_name(line: &str) -> Option<String> {
-    let start = line.find("def ")? + 4;
-    let rest = &line[start..];
-    let end = rest.find('(')?;
-    Some(rest[..end].to_string())
+        let suite = &items[0];
+        assert_eq!(suite.kind, TestItemKind::Suite);
+        assert_eq!(names(&suite.children), vec!["fetches", "doubles"]);
+        assert_eq!(suite.children[0].id, TestId::new("src/lib.rs::tests::fetches"));
+        assert_eq!(suite.children[0].range.start_line, 8);
+
+        let doubles = &suite.children[1];
+        assert!(doubles.parametrized);
+        assert_eq!(names(&doubles.children), vec!["case_1", "case_2"]);
+        assert_eq!(doubles.children[1].line, 13);
+        assert_eq!(suite.test_count(), 3);
+    }
+
+    #[test]
+    fn test_javascript_describe_each() {
+        let source = r#"
+describe("math", () => {
+  it("adds", () => {});
+  describe.each([[1, 2]])("with %i", (a, b) => {
+    test.only(`works`, () => {});
+  });
+});
+"#;
+        let items = discover("math.test.ts", source);
+        assert_eq!(names(&items), vec!["math"]);
+        let math = &items[0];
+        assert_eq!(names(&math.children), vec!["adds", "with %i"]);
+        assert!(math.children[1].parametrized);
+        assert_eq!(math.children[1].children[0].id, TestId::new("math.test.ts::math::with %i::works"));
+    }
+
+    #[test]
+    fn test_python_parametrize() {
+        let source = r#"
+import pytest
+
+@pytest.mark.parametrize("n", [1, 2])
+def test_square(n):
+    pass
+
+class TestMath:
+    def test_add(self):
+        pass
+
+    def helper(self):
+        pass
+"#;
+        let items = discover("test_math.py", source);
+        assert_eq!(names(&items), vec!["test_square", "TestMath"]);
+        assert!(items[0].parametrized);
+        assert_eq!(items[0].range.start_line, 3);
+        assert_eq!(names(&items[1].children), vec!["test_add"]);
+    }
+
+    #[test]
+    fn test_go_subtests() {
+        let source = r#"
+package math
+
+func TestAdd(t *testing.T) {
+	t.Run("positive", func(t *testing.T) {})
+	t.Run(`negative`, func(t *testing.T) {})
 }
 
-fn extract_python_class_name(line: &str) -> Option<String> {
-    let start = line.find("class ")? + 6;
-    let rest = &line[start..];
-    let end = rest.find(|c: char| !c.is_alphanumeric() && c != '_')?;
-    Some(rest[..end].to_string())
+func TestMain(m *testing.M) {}
+"#;
+        let items = discover("math_test.go", source);
+        assert_eq!(names(&items), vec!["TestAdd"]);
+        assert_eq!(names(&items[0].children), vec!["positive", "negative"]);
+    }
+
+    #[test]
+    fn test_incremental_edit() {
+        let discovery = TestDiscovery::with_query_loader(QueryLoader::new());
+        let file = Path::new("src/lib.rs");
+        let before = "#[test]\nfn a() {}\n";
+        assert_eq!(discovery.discover_source(file, before).len(), 1);
+        assert!(discovery.trees.read().is_empty());
+        assert_eq!(discovery.open_buffer(file, before).len(), 1);
+
+        let after = "#[test]\nfn a() {}\n#[test]\nfn b() {}\n";
+        let edit = InputEdit {
+            start_byte: before.len(),
+            old_end_byte: before.len(),
+            new_end_byte: after.len(),
+            start_position: (2, 0),
+            old_end_position: (2, 0),
+            new_end_position: (4, 0),
+        };
+        let items = discovery.edit_buffer(file, &[edit], after);
+
+        assert_eq!(names(&items), vec!["a", "b"]);
+        assert_eq!(items[1].range.start_line, 3);
+        assert_eq!(discovery.test_at(file, 3).unwrap().name, "b");
+
+        discovery.close_buffer(file);
+        assert!(discovery.trees.read().is_empty());
+    }
 }
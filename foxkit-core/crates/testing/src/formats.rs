@@ -0,0 +1,704 @@
+//! Structured test result formats
+//!
+//! Parses libtest/nextest JSON, JUnit XML and TAP output into `TestResult`s
+//! with per-test durations, captured output, failure locations and
+//! expected/actual values.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+use std::time::Duration;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{TestId, TestMessage, TestOutcome, TestResult};
+
+/// Structured result format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResultFormat {
+    /// libtest `--format json` (also emitted by nextest's `libtest-json`)
+    LibtestJson,
+    /// JUnit XML (Jest, pytest `--junitxml`, go-junit-report, ...)
+    JUnitXml,
+    /// Test Anything Protocol
+    Tap,
+}
+
+impl ResultFormat {
+    /// Guess the format from the content
+    pub fn detect(content: &str) -> Option<Self> {
+        let trimmed = content.trim_start();
+        if trimmed.starts_with("<?xml") || trimmed.starts_with("<testsuite") {
+            Some(Self::JUnitXml)
+        } else if trimmed.starts_with("TAP version") || TAP_PLAN.is_match(trimmed.lines().next()?) {
+            Some(Self::Tap)
+        } else if trimmed.lines().any(|l| l.starts_with('{') && l.contains("\"type\"")) {
+            Some(Self::LibtestJson)
+        } else {
+            None
+        }
+    }
+}
+
+/// Parse results in the given format
+pub fn parse_results(format: ResultFormat, content: &str) -> anyhow::Result<Vec<TestResult>> {
+    match format {
+        ResultFormat::LibtestJson => Ok(parse_libtest_json(content)),
+        ResultFormat::JUnitXml => parse_junit_xml(content),
+        ResultFormat::Tap => Ok(parse_tap(content)),
+    }
+}
+
+static RUST_PANIC_LOCATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"panicked at (?:'.*?', )?([^\s:]+):(\d+):(\d+)").unwrap()
+});
+static FILE_LOCATION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"([^\s():'"]+\.[A-Za-z]+):(\d+)(?::(\d+))?"#).unwrap()
+});
+static TAP_PLAN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*1\.\.\d+").unwrap());
+static TAP_POINT: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(\s*)(ok|not ok)\b(?:\s+\d+)?\s*(?:-\s*)?([^#]*?)\s*(?:#\s*(\w+)\b\s*(.*))?$").unwrap()
+});
+
+/// Duration from a reported number of seconds; negative, NaN and
+/// overflowing values count as no duration
+fn secs(secs: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(secs).ok()
+}
+
+// ═══════════════════════════════════════════════════════════════
+// libtest / nextest JSON
+// ═══════════════════════════════════════════════════════════════
+
+#[derive(Deserialize)]
+struct LibtestEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    event: String,
+    #[serde(default)]
+    name: Option<String>,
+    #[serde(default)]
+    exec_time: Option<f64>,
+    #[serde(default)]
+    stdout: Option<String>,
+    #[serde(default)]
+    message: Option<String>,
+}
+
+/// Parse libtest JSON lines; non-JSON lines (build output, etc.) are skipped
+pub fn parse_libtest_json(content: &str) -> Vec<TestResult> {
+    let mut results = Vec::new();
+
+    for line in content.lines().map(str::trim).filter(|l| l.starts_with('{')) {
+        let Ok(event) = serde_json::from_str::<LibtestEvent>(line) else {
+            continue;
+        };
+        if event.kind != "test" {
+            continue;
+        }
+        let Some(name) = event.name else {
+            continue;
+        };
+
+        let outcome = match event.event.as_str() {
+            "ok" | "allowed_fail" => TestOutcome::Passed,
+            "failed" => TestOutcome::Failed,
+            "ignored" => TestOutcome::Skipped,
+            // "started" and "timeout" (a warning, the test keeps running)
+            _ => continue,
+        };
+
+        // nextest prefixes names with the binary ID: `crate::bin$module::test`
+        let name = name.rsplit_once('$').map_or(name.as_str(), |(_, test)| test);
+        let mut result = TestResult {
+            test_id: TestId::new(name),
+            outcome,
+            duration: event.exec_time.and_then(secs).unwrap_or_default(),
+            messages: Vec::new(),
+        };
+
+        let output = event.stdout.unwrap_or_default();
+        if outcome == TestOutcome::Failed {
+            let failure = match output.find("thread '") {
+                Some(start) if output[start..].contains("panicked at") => &output[start..],
+                _ => event.message.as_deref().unwrap_or(&output),
+            };
+            result.messages.push(failure_message(failure));
+        }
+        if !output.is_empty() {
+            result.messages.push(TestMessage::output(output));
+        }
+
+        results.push(result);
+    }
+
+    results
+}
+
+// ═══════════════════════════════════════════════════════════════
+// JUnit XML
+// ═══════════════════════════════════════════════════════════════
+
+/// Element whose text is being collected
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum JUnitText {
+    Failure,
+    Error,
+    Skipped,
+    SystemOut,
+    SystemErr,
+}
+
+#[derive(Default)]
+struct JUnitCase {
+    id: String,
+    duration: Duration,
+    file: Option<String>,
+    line: Option<u32>,
+    outcome: Option<TestOutcome>,
+    failure_message: Option<String>,
+    failure_text: String,
+    output: String,
+}
+
+impl JUnitCase {
+    fn from_element(element: &BytesStart, suite: Option<&str>) -> anyhow::Result<Self> {
+        let attrs = attributes(element)?;
+        let name = attrs.get("name").cloned().unwrap_or_default();
+        let classname = attrs.get("classname").cloned()
+            .or_else(|| suite.map(String::from))
+            .unwrap_or_default();
+
+        Ok(Self {
+            id: if classname.is_empty() || classname == name {
+                name
+            } else {
+                format!("{}::{}", classname, name)
+            },
+            duration: attrs.get("time")
+                .and_then(|t| t.replace(',', "").parse::<f64>().ok())
+                .and_then(secs)
+                .unwrap_or_default(),
+            file: attrs.get("file").cloned(),
+            line: attrs.get("line").and_then(|l| l.parse().ok()),
+            ..Default::default()
+        })
+    }
+
+    /// Handle a child element, returning whose text to collect
+    fn begin_child(&mut self, element: &BytesStart) -> anyhow::Result<Option<JUnitText>> {
+        let kind = match element.name().as_ref() {
+            b"failure" => JUnitText::Failure,
+            b"error" => JUnitText::Error,
+            b"skipped" => JUnitText::Skipped,
+            b"system-out" => JUnitText::SystemOut,
+            b"system-err" => JUnitText::SystemErr,
+            _ => return Ok(None),
+        };
+
+        match kind {
+            JUnitText::Failure | JUnitText::Error => {
+                self.outcome = Some(if kind == JUnitText::Failure {
+                    TestOutcome::Failed
+                } else {
+                    TestOutcome::Errored
+                });
+                self.failure_message = attributes(element)?.remove("message");
+            }
+            JUnitText::Skipped => self.outcome = Some(TestOutcome::Skipped),
+            JUnitText::SystemOut | JUnitText::SystemErr => {}
+        }
+
+        Ok(Some(kind))
+    }
+
+    fn push_text(&mut self, kind: JUnitText, text: &str) {
+        match kind {
+            JUnitText::Failure | JUnitText::Error => self.failure_text.push_str(text),
+            JUnitText::SystemOut | JUnitText::SystemErr => self.output.push_str(text),
+            JUnitText::Skipped => {}
+        }
+    }
+
+    fn into_result(self) -> TestResult {
+        let outcome = self.outcome.unwrap_or(TestOutcome::Passed);
+        let mut messages = Vec::new();
+
+        if matches!(outcome, TestOutcome::Failed | TestOutcome::Errored) {
+            let text = match (&self.failure_message, self.failure_text.trim()) {
+                (Some(message), "") => message.clone(),
+                (Some(message), text) if !text.contains(message.as_str()) => format!("{}\n{}", message, text),
+                (_, text) => text.to_string(),
+            };
+            let mut message = failure_message(&text);
+            if message.location.is_none()
+                && let (Some(file), Some(line)) = (self.file, self.line)
+            {
+                message = message.at(file, line, None);
+            }
+            messages.push(message);
+        }
+        if !self.output.trim().is_empty() {
+            messages.push(TestMessage::output(self.output));
+        }
+
+        TestResult {
+            test_id: TestId::new(self.id),
+            outcome,
+            duration: self.duration,
+            messages,
+        }
+    }
+}
+
+pub(crate) fn attributes(element: &BytesStart) -> anyhow::Result<HashMap<String, String>> {
+    let mut attrs = HashMap::new();
+    for attr in element.attributes() {
+        let attr = attr?;
+        attrs.insert(
+            String::from_utf8_lossy(attr.key.as_ref()).into_owned(),
+            attr.unescape_value()?.into_owned(),
+        );
+    }
+    Ok(attrs)
+}
+
+/// Parse a JUnit XML report
+pub fn parse_junit_xml(content: &str) -> anyhow::Result<Vec<TestResult>> {
+    let mut reader = Reader::from_str(content);
+    let mut results = Vec::new();
+    let mut suites: Vec<String> = Vec::new();
+    let mut case: Option<JUnitCase> = None;
+    let mut collecting: Option<JUnitText> = None;
+
+    loop {
+        match reader.read_event()? {
+            Event::Start(e) | Event::Empty(e) if e.name().as_ref() == b"testsuite" => {
+                if let Some(name) = attributes(&e)?.remove("name") {
+                    suites.push(name);
+                }
+            }
+            Event::End(e) if e.name().as_ref() == b"testsuite" => {
+                suites.pop();
+            }
+            Event::Start(e) if e.name().as_ref() == b"testcase" => {
+                case = Some(JUnitCase::from_element(&e, suites.last().map(String::as_str))?);
+            }
+            Event::Empty(e) if e.name().as_ref() == b"testcase" => {
+                let case = JUnitCase::from_element(&e, suites.last().map(String::as_str))?;
+                results.push(case.into_result());
+            }
+            Event::End(e) if e.name().as_ref() == b"testcase" => {
+                if let Some(case) = case.take() {
+                    results.push(case.into_result());
+                }
+                collecting = None;
+            }
+            Event::Start(e) => {
+                if let Some(case) = case.as_mut() {
+                    collecting = case.begin_child(&e)?;
+                }
+            }
+            Event::Empty(e) => {
+                if let Some(case) = case.as_mut() {
+                    case.begin_child(&e)?;
+                }
+            }
+            Event::End(_) => collecting = None,
+            Event::Text(text) => {
+                if let (Some(case), Some(kind)) = (case.as_mut(), collecting) {
+                    case.push_text(kind, &text.unescape()?);
+                }
+            }
+            Event::CData(data) => {
+                if let (Some(case), Some(kind)) = (case.as_mut(), collecting) {
+                    case.push_text(kind, &String::from_utf8_lossy(&data));
+                }
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+
+    Ok(results)
+}
+
+// ═══════════════════════════════════════════════════════════════
+// TAP
+// ═══════════════════════════════════════════════════════════════
+
+struct TapPoint {
+    id: String,
+    ok: bool,
+    directive: Option<String>,
+    diagnostics: HashMap<String, String>,
+    comments: String,
+}
+
+impl TapPoint {
+    fn into_result(self) -> Option<TestResult> {
+        // Node's runner reports suites as test points too
+        if self.diagnostics.get("type").map(String::as_str) == Some("suite") {
+            return None;
+        }
+
+        let directive = self.directive.as_deref().map(str::to_ascii_uppercase);
+        let outcome = match (self.ok, directive.as_deref()) {
+            (_, Some("SKIP")) => TestOutcome::Skipped,
+            (false, Some("TODO")) => TestOutcome::Skipped,
+            (true, _) => TestOutcome::Passed,
+            (false, _) => TestOutcome::Failed,
+        };
+
+        let diag = &self.diagnostics;
+        let mut messages = Vec::new();
+        if outcome == TestOutcome::Failed {
+            let text = diag.get("message")
+                .or_else(|| diag.get("error"))
+                .cloned()
+                .unwrap_or_else(|| self.comments.trim().to_string());
+            let expected = diag.get("expected").or_else(|| diag.get("wanted"));
+            let actual = diag.get("actual").or_else(|| diag.get("found"));
+
+            let mut message = match (expected, actual) {
+                (Some(expected), Some(actual)) => TestMessage::comparison(&text, expected, actual),
+                _ => failure_message(&text),
+            };
+
+            let location = match (diag.get("at.file"), diag.get("at.line")) {
+                (Some(file), Some(line)) => line.parse().ok().map(|line| {
+                    (file.clone(), line, diag.get("at.column").and_then(|c| c.parse().ok()))
+                }),
+                _ => ["location", "at", "stack"].iter()
+                    .filter_map(|key| diag.get(*key))
+                    .find_map(|value| find_location(value)),
+            };
+            if let Some((file, line, column)) = location {
+                message = message.at(file, line, column);
+            }
+            messages.push(message);
+        }
+
+        Some(TestResult {
+            test_id: TestId::new(self.id),
+            outcome,
+            duration: diag.get("duration_ms")
+                .and_then(|d| d.parse::<f64>().ok())
+                .and_then(|ms| secs(ms / 1000.0))
+                .unwrap_or_default(),
+            messages,
+        })
+    }
+}
+
+/// Parse TAP (13/14, including Node-style indented subtests and YAML diagnostics)
+pub fn parse_tap(content: &str) -> Vec<TestResult> {
+    let mut results = Vec::new();
+    let mut subtests: Vec<(usize, String)> = Vec::new();
+    let mut pending: Option<TapPoint> = None;
+    let mut yaml: Option<(usize, Vec<&str>)> = None;
+
+    for line in content.lines() {
+        let indent = line.len() - line.trim_start().len();
+        let trimmed = line.trim();
+
+        if let Some((yaml_indent, lines)) = yaml.as_mut() {
+            if trimmed == "..." && indent == *yaml_indent {
+                if let Some(point) = pending.as_mut() {
+                    point.diagnostics = parse_yaml_block(lines);
+                }
+                yaml = None;
+            } else {
+                lines.push(line);
+            }
+            continue;
+        }
+
+        if trimmed == "---" && pending.is_some() {
+            yaml = Some((indent, Vec::new()));
+        } else if let Some(name) = trimmed.strip_prefix("# Subtest:") {
+            subtests.push((indent, name.trim().to_string()));
+        } else if let Some(caps) = TAP_POINT.captures(line) {
+            if let Some(point) = pending.take() {
+                results.extend(point.into_result());
+            }
+
+            let indent = caps[1].len();
+            let description = caps[3].trim().to_string();
+            subtests.retain(|(i, _)| *i < indent);
+
+            let mut path: Vec<&str> = subtests.iter().map(|(_, name)| name.as_str()).collect();
+            path.push(&description);
+
+            pending = Some(TapPoint {
+                id: path.join("::"),
+                ok: &caps[2] == "ok",
+                directive: caps.get(4).map(|d| d.as_str().to_string()),
+                diagnostics: HashMap::new(),
+                comments: String::new(),
+            });
+        } else if let Some(comment) = trimmed.strip_prefix('#')
+            && let Some(point) = pending.as_mut()
+        {
+            point.comments.push_str(comment.trim());
+            point.comments.push('\n');
+        }
+    }
+
+    if let Some(point) = pending {
+        results.extend(point.into_result());
+    }
+
+    results
+}
+
+/// Flatten a TAP YAML diagnostics block: nested keys become `parent.key`,
+/// block scalars (`|`, `|-`, `>-`) are joined into one value
+fn parse_yaml_block(lines: &[&str]) -> HashMap<String, String> {
+    let mut values = HashMap::new();
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        let indent = line.len() - line.trim_start().len();
+        i += 1;
+
+        let Some((key, value)) = line.trim().split_once(':') else {
+            continue;
+        };
+        parents.retain(|(p, _)| *p < indent);
+        let key = parents.iter()
+            .map(|(_, k)| k.as_str())
+            .chain(std::iter::once(key.trim()))
+            .collect::<Vec<_>>()
+            .join(".");
+        let value = value.trim();
+
+        if value.starts_with('|') || value.starts_with('>') {
+            let mut block = Vec::new();
+            while i < lines.len() {
+                let next = lines[i];
+                let next_indent = next.len() - next.trim_start().len();
+                if !next.trim().is_empty() && next_indent <= indent {
+                    break;
+                }
+                block.push(next.trim());
+                i += 1;
+            }
+            let separator = if value.starts_with('>') { " " } else { "\n" };
+            values.insert(key, block.join(separator).trim().to_string());
+        } else if value.is_empty() {
+            parents.push((indent, key));
+        } else {
+            values.insert(key, unquote(value).to_string());
+        }
+    }
+
+    values
+}
+
+fn unquote(value: &str) -> &str {
+    for quote in ['\'', '"'] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner;
+        }
+    }
+    value
+}
+
+// ═══════════════════════════════════════════════════════════════
+// Failure messages
+// ═══════════════════════════════════════════════════════════════
+
+/// Build a failure message, extracting the location and any
+/// expected/actual pair (Rust `left`/`right`, Jest `Expected`/`Received`)
+fn failure_message(text: &str) -> TestMessage {
+    let text = text.trim();
+    let mut message = match comparison_values(text) {
+        Some((expected, actual)) => TestMessage::comparison(text, expected, actual),
+        None => TestMessage::error(text),
+    };
+
+    let location = RUST_PANIC_LOCATION.captures(text)
+        .and_then(|caps| Some((caps[1].to_string(), caps[2].parse().ok()?, caps[3].parse().ok())))
+        .or_else(|| find_location(text));
+    if let Some((file, line, column)) = location {
+        message = message.at(file, line, column);
+    }
+
+    message
+}
+
+fn find_location(text: &str) -> Option<(String, u32, Option<u32>)> {
+    let caps = FILE_LOCATION.captures(text)?;
+    Some((
+        caps[1].to_string(),
+        caps[2].parse().ok()?,
+        caps.get(3).and_then(|c| c.as_str().parse().ok()),
+    ))
+}
+
+fn comparison_values(text: &str) -> Option<(String, String)> {
+    let mut expected = None;
+    let mut actual = None;
+
+    for line in text.lines().map(str::trim) {
+        if let Some(value) = line.strip_prefix("right:").or_else(|| line.strip_prefix("Expected:")) {
+            expected.get_or_insert_with(|| value.trim().to_string());
+        } else if let Some(value) = line.strip_prefix("left:")
+            .or_else(|| line.strip_prefix("Received:"))
+            .or_else(|| line.strip_prefix("Actual:"))
+        {
+            actual.get_or_insert_with(|| value.trim().to_string());
+        }
+    }
+
+    Some((expected?, actual?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::TestMessageKind;
+
+    #[test]
+    fn test_libtest_json() {
+        let output = r#"
+   Compiling foo v0.1.0
+{ "type": "suite", "event": "started", "test_count": 3 }
+{ "type": "test", "event": "started", "name": "net::tests::parses" }
+{ "type": "test", "name": "net::tests::parses", "event": "ok", "exec_time": 0.25 }
+{ "type": "test", "name": "net::tests::compares", "event": "failed", "exec_time": 0.01, "stdout": "debug line\nthread 'net::tests::compares' panicked at src/net.rs:42:9:\nassertion `left == right` failed\n  left: 1\n right: 2\n" }
+{ "type": "test", "name": "foo::bin/foo$slow", "event": "ignored" }
+{ "type": "suite", "event": "failed", "passed": 1, "failed": 1, "ignored": 1 }
+"#;
+        let results = parse_libtest_json(output);
+        assert_eq!(results.len(), 3);
+
+        assert_eq!(results[0].outcome, TestOutcome::Passed);
+        assert_eq!(results[0].duration, Duration::from_millis(250));
+
+        let failed = &results[1];
+        assert_eq!(failed.outcome, TestOutcome::Failed);
+        let failure = &failed.messages[0];
+        assert!(matches!(failure.kind, TestMessageKind::Comparison));
+        assert_eq!(failure.expected.as_deref(), Some("2"));
+        assert_eq!(failure.actual.as_deref(), Some("1"));
+        let location = failure.location.as_ref().unwrap();
+        assert_eq!((location.file.as_str(), location.line, location.column), ("src/net.rs", 42, Some(9)));
+        assert!(matches!(failed.messages[1].kind, TestMessageKind::Output));
+
+        assert_eq!(results[2].test_id, TestId::new("slow"));
+        assert_eq!(results[2].outcome, TestOutcome::Skipped);
+    }
+
+    #[test]
+    fn test_junit_xml() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<testsuites>
+  <testsuite name="pytest" tests="4">
+    <testcase classname="tests.test_math" name="test_add" time="0.002" />
+    <testcase classname="tests.test_math" name="test_div" time="0.5">
+      <failure message="assert 1 == 2">tests/test_math.py:12: AssertionError</failure>
+      <system-out>captured &amp; printed</system-out>
+    </testcase>
+    <testcase classname="tests.test_math" name="test_skip"><skipped message="later"/></testcase>
+    <testcase name="TestBroken" time="0"><error message="boom"><![CDATA[panic: boom]]></error></testcase>
+  </testsuite>
+</testsuites>"#;
+        let results = parse_junit_xml(xml).unwrap();
+        assert_eq!(results.len(), 4);
+
+        assert_eq!(results[0].test_id, TestId::new("tests.test_math::test_add"));
+        assert_eq!(results[0].outcome, TestOutcome::Passed);
+
+        let failed = &results[1];
+        assert_eq!(failed.outcome, TestOutcome::Failed);
+        assert_eq!(failed.duration, Duration::from_millis(500));
+        let location = failed.messages[0].location.as_ref().unwrap();
+        assert_eq!((location.file.as_str(), location.line), ("tests/test_math.py", 12));
+        assert_eq!(failed.messages[1].text, "captured & printed");
+
+        assert_eq!(results[2].outcome, TestOutcome::Skipped);
+        assert_eq!(results[3].test_id, TestId::new("pytest::TestBroken"));
+        assert_eq!(results[3].outcome, TestOutcome::Errored);
+        assert!(results[3].messages[0].text.contains("panic: boom"));
+    }
+
+    #[test]
+    fn test_tap() {
+        let tap = "TAP version 13
+# Subtest: math
+    # Subtest: adds
+    ok 1 - adds
+      ---
+      duration_ms: 1.5
+      ...
+    # Subtest: compares
+    not ok 2 - compares
+      ---
+      duration_ms: 2
+      location: '/src/math.test.js:10:3'
+      failureType: 'testCodeFailure'
+      error: |-
+        Expected values to be strictly equal
+      expected: 3
+      actual: 4
+      ...
+    1..2
+not ok 1 - math
+  ---
+  type: 'suite'
+  ...
+ok 2 - pending # SKIP not ready
+not ok 3 - tape style
+  ---
+    operator: equal
+    at:
+      file: test/a.js
+      line: 7
+  ...
+1..3
+";
+        let results = parse_tap(tap);
+        assert_eq!(results.len(), 4);
+
+        assert_eq!(results[0].test_id, TestId::new("math::adds"));
+        assert_eq!(results[0].duration, Duration::from_micros(1500));
+
+        let failed = &results[1];
+        assert_eq!(failed.outcome, TestOutcome::Failed);
+        let message = &failed.messages[0];
+        assert_eq!(message.text, "Expected values to be strictly equal");
+        assert_eq!(message.expected.as_deref(), Some("3"));
+        assert_eq!(message.location.as_ref().unwrap().line, 10);
+
+        assert_eq!(results[2].outcome, TestOutcome::Skipped);
+        assert_eq!(results[3].messages[0].location.as_ref().unwrap().file, "test/a.js");
+    }
+
+    #[test]
+    fn test_invalid_durations() {
+        let json = r#"{ "type": "test", "name": "a", "event": "ok", "exec_time": -1.0 }
+{ "type": "test", "name": "b", "event": "ok", "exec_time": 1e300 }"#;
+        assert!(parse_libtest_json(json).iter().all(|r| r.duration == Duration::ZERO));
+
+        let xml = r#"<testsuite><testcase name="a" time="NaN"/><testcase name="b" time="-3"/></testsuite>"#;
+        assert!(parse_junit_xml(xml).unwrap().iter().all(|r| r.duration == Duration::ZERO));
+
+        let tap = "ok 1 - a\n  ---\n  duration_ms: 1e400\n  ...\n1..1\n";
+        assert_eq!(parse_tap(tap)[0].duration, Duration::ZERO);
+    }
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(ResultFormat::detect("TAP version 14
+1..0"), Some(ResultFormat::Tap));
+        assert_eq!(ResultFormat::detect("<testsuites></testsuites>"), Some(ResultFormat::JUnitXml));
+        assert_eq!(ResultFormat::detect("running 0 tests"), None);
+        assert_eq!(ResultFormat::detect("<?xml version=\"1.0\"?><testsuites/>"), Some(ResultFormat::JUnitXml));
+        assert_eq!(ResultFormat::detect("{ \"type\": \"suite\" }"), Some(ResultFormat::LibtestJson));
+    }
+}
@@ -0,0 +1,461 @@
+//! Test run history
+//!
+//! Every run's results are kept, tagged with the commit they ran on. A test
+//! that both passes and fails on the same commit is flaky.
+
+use std::collections::{HashMap, VecDeque};
+use std::io::Write;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use crate::{TestId, TestOutcome, TestResult, TestRunId};
+
+/// Runs kept by default
+const DEFAULT_MAX_RUNS: usize = 500;
+
+/// One recorded test run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRunRecord {
+    pub run_id: TestRunId,
+    /// Commit the run was made on
+    pub commit: Option<String>,
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub results: Vec<RecordedResult>,
+}
+
+impl TestRunRecord {
+    pub fn new(run_id: TestRunId, commit: Option<String>, results: &[TestResult]) -> Self {
+        Self {
+            run_id,
+            commit,
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            results: results.iter()
+                .map(|r| RecordedResult {
+                    test_id: r.test_id.clone(),
+                    outcome: r.outcome,
+                    duration: r.duration,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A test result as kept in the history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedResult {
+    pub test_id: TestId,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+}
+
+/// One test's result in a past run
+#[derive(Debug, Clone)]
+pub struct TestHistoryEntry {
+    pub run_id: TestRunId,
+    pub commit: Option<String>,
+    pub timestamp: u64,
+    pub outcome: TestOutcome,
+    pub duration: Duration,
+}
+
+impl TestHistoryEntry {
+    fn is_failure(&self) -> bool {
+        matches!(self.outcome, TestOutcome::Failed | TestOutcome::Errored)
+    }
+}
+
+/// How flaky a test has been
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Flakiness {
+    /// Runs that passed or failed
+    pub runs: usize,
+    pub failures: usize,
+    /// Commits on which the test both passed and failed
+    pub flaky_commits: usize,
+    /// Pass/fail flips between consecutive runs on the same commit
+    pub flips: usize,
+}
+
+impl Flakiness {
+    /// Has the test flipped on an unchanged commit?
+    pub fn is_flaky(&self) -> bool {
+        self.flaky_commits > 0
+    }
+
+    /// Fraction of runs that failed
+    pub fn failure_rate(&self) -> f64 {
+        if self.runs == 0 {
+            0.0
+        } else {
+            self.failures as f64 / self.runs as f64
+        }
+    }
+
+    /// Badge text for the test explorer
+    pub fn badge(&self) -> Option<String> {
+        self.is_flaky()
+            .then(|| format!("flaky {}/{}", self.failures, self.runs))
+    }
+}
+
+/// Durations of a test across runs, oldest first
+#[derive(Debug, Clone, Default)]
+pub struct DurationTrend {
+    pub samples: Vec<DurationSample>,
+}
+
+/// Duration of one run of a test
+#[derive(Debug, Clone)]
+pub struct DurationSample {
+    pub commit: Option<String>,
+    pub timestamp: u64,
+    pub duration: Duration,
+}
+
+impl DurationTrend {
+    /// Mean duration
+    pub fn average(&self) -> Option<Duration> {
+        let count = self.samples.len() as u32;
+        (count > 0).then(|| self.samples.iter().map(|s| s.duration).sum::<Duration>() / count)
+    }
+
+    /// Latest duration relative to the mean of the earlier ones
+    /// (1.5 = 50% slower)
+    pub fn change(&self) -> Option<f64> {
+        let (latest, earlier) = self.samples.split_last()?;
+        if earlier.is_empty() {
+            return None;
+        }
+        let mean = earlier.iter().map(|s| s.duration.as_secs_f64()).sum::<f64>() / earlier.len() as f64;
+        (mean > 0.0).then(|| latest.duration.as_secs_f64() / mean)
+    }
+}
+
+/// Test run history, optionally persisted as JSON lines
+pub struct TestHistory {
+    runs: RwLock<VecDeque<TestRunRecord>>,
+    path: Option<PathBuf>,
+    max_runs: usize,
+}
+
+impl TestHistory {
+    /// In-memory history
+    pub fn new() -> Self {
+        Self {
+            runs: RwLock::new(VecDeque::new()),
+            path: None,
+            max_runs: DEFAULT_MAX_RUNS,
+        }
+    }
+
+    /// History stored at `path`, loading earlier runs
+    pub fn open(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut runs: VecDeque<TestRunRecord> = std::fs::read_to_string(&path)
+            .map(|content| {
+                content.lines()
+                    .filter_map(|line| serde_json::from_str(line).ok())
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        let history_len = runs.len();
+        while runs.len() > DEFAULT_MAX_RUNS {
+            runs.pop_front();
+        }
+
+        let history = Self {
+            runs: RwLock::new(runs),
+            path: Some(path),
+            max_runs: DEFAULT_MAX_RUNS,
+        };
+        if history_len > DEFAULT_MAX_RUNS
+            && let Err(e) = history.rewrite()
+        {
+            tracing::warn!("Failed to compact test history: {}", e);
+        }
+        history
+    }
+
+    /// Limit the number of runs kept
+    pub fn with_max_runs(mut self, max_runs: usize) -> Self {
+        self.max_runs = max_runs.max(1);
+        self
+    }
+
+    /// Record a run, returning the tests whose outcome flipped against an
+    /// earlier run on the same commit
+    pub fn record(&self, record: TestRunRecord) -> anyhow::Result<Vec<TestId>> {
+        let flipped = match &record.commit {
+            Some(commit) => self.flipped_on(commit, &record.results),
+            None => Vec::new(),
+        };
+
+        let line = serde_json::to_string(&record)?;
+        let compact = {
+            let mut runs = self.runs.write();
+            runs.push_back(record);
+            let compact = runs.len() > self.max_runs;
+            while runs.len() > self.max_runs {
+                runs.pop_front();
+            }
+            compact
+        };
+
+        if compact {
+            self.rewrite()?;
+        } else if let Some(path) = &self.path {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)?;
+        }
+
+        Ok(flipped)
+    }
+
+    fn flipped_on(&self, commit: &str, results: &[RecordedResult]) -> Vec<TestId> {
+        let runs = self.runs.read();
+        results.iter()
+            .filter(|result| !matches!(result.outcome, TestOutcome::Skipped))
+            .filter(|result| {
+                let failed = matches!(result.outcome, TestOutcome::Failed | TestOutcome::Errored);
+                runs.iter()
+                    .rev()
+                    .filter(|run| run.commit.as_deref() == Some(commit))
+                    .flat_map(|run| &run.results)
+                    .find(|r| r.test_id == result.test_id && !matches!(r.outcome, TestOutcome::Skipped))
+                    .is_some_and(|previous| {
+                        matches!(previous.outcome, TestOutcome::Failed | TestOutcome::Errored) != failed
+                    })
+            })
+            .map(|result| result.test_id.clone())
+            .collect()
+    }
+
+    fn rewrite(&self) -> anyhow::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut content = String::new();
+        for run in self.runs.read().iter() {
+            content.push_str(&serde_json::to_string(run)?);
+            content.push('\n');
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// All recorded runs, oldest first
+    pub fn runs(&self) -> Vec<TestRunRecord> {
+        self.runs.read().iter().cloned().collect()
+    }
+
+    /// A test's results, oldest first
+    pub fn entries(&self, test_id: &TestId) -> Vec<TestHistoryEntry> {
+        self.runs.read()
+            .iter()
+            .flat_map(|run| {
+                run.results.iter()
+                    .filter(|r| &r.test_id == test_id)
+                    .map(|r| TestHistoryEntry {
+                        run_id: run.run_id.clone(),
+                        commit: run.commit.clone(),
+                        timestamp: run.timestamp,
+                        outcome: r.outcome,
+                        duration: r.duration,
+                    })
+            })
+            .collect()
+    }
+
+    /// Tests whose most recent result is a failure
+    pub fn last_failed(&self) -> Vec<TestId> {
+        let mut latest: HashMap<&TestId, TestOutcome> = HashMap::new();
+        let runs = self.runs.read();
+        for result in runs.iter().flat_map(|run| &run.results) {
+            if !matches!(result.outcome, TestOutcome::Skipped) {
+                latest.insert(&result.test_id, result.outcome);
+            }
+        }
+
+        let mut failed: Vec<TestId> = latest.into_iter()
+            .filter(|(_, outcome)| matches!(outcome, TestOutcome::Failed | TestOutcome::Errored))
+            .map(|(id, _)| id.clone())
+            .collect();
+        failed.sort_by(|a, b| a.0.cmp(&b.0));
+        failed
+    }
+
+    /// How flaky a test has been
+    pub fn flakiness(&self, test_id: &TestId) -> Flakiness {
+        let entries = self.entries(test_id);
+        let mut flakiness = Flakiness::default();
+        let mut by_commit: HashMap<&str, Vec<bool>> = HashMap::new();
+
+        for entry in entries.iter().filter(|e| !matches!(e.outcome, TestOutcome::Skipped)) {
+            flakiness.runs += 1;
+            if entry.is_failure() {
+                flakiness.failures += 1;
+            }
+            if let Some(commit) = &entry.commit {
+                by_commit.entry(commit).or_default().push(entry.is_failure());
+            }
+        }
+
+        for outcomes in by_commit.values() {
+            let flips = outcomes.windows(2).filter(|w| w[0] != w[1]).count();
+            if flips > 0 {
+                flakiness.flaky_commits += 1;
+                flakiness.flips += flips;
+            }
+        }
+        flakiness
+    }
+
+    /// Flaky tests, most flips first
+    pub fn flaky_tests(&self) -> Vec<(TestId, Flakiness)> {
+        let mut ids: Vec<TestId> = self.runs.read()
+            .iter()
+            .flat_map(|run| run.results.iter().map(|r| r.test_id.clone()))
+            .collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+        ids.dedup();
+
+        let mut flaky: Vec<_> = ids.into_iter()
+            .map(|id| {
+                let flakiness = self.flakiness(&id);
+                (id, flakiness)
+            })
+            .filter(|(_, flakiness)| flakiness.is_flaky())
+            .collect();
+        flaky.sort_by(|a, b| b.1.flips.cmp(&a.1.flips).then_with(|| a.0.0.cmp(&b.0.0)));
+        flaky
+    }
+
+    /// Durations of the last `limit` passed or failed runs of a test
+    pub fn duration_trend(&self, test_id: &TestId, limit: usize) -> DurationTrend {
+        let samples: Vec<_> = self.entries(test_id)
+            .into_iter()
+            .filter(|e| !matches!(e.outcome, TestOutcome::Skipped))
+            .map(|e| DurationSample {
+                commit: e.commit,
+                timestamp: e.timestamp,
+                duration: e.duration,
+            })
+            .collect();
+        let skip = samples.len().saturating_sub(limit);
+        DurationTrend {
+            samples: samples.into_iter().skip(skip).collect(),
+        }
+    }
+
+    /// Forget all runs
+    pub fn clear(&self) -> anyhow::Result<()> {
+        self.runs.write().clear();
+        if let Some(path) = &self.path
+            && path.exists()
+        {
+            std::fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+}
+
+impl Default for TestHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(commit: &str, results: &[(&str, TestOutcome, u64)]) -> TestRunRecord {
+        let results: Vec<_> = results.iter()
+            .map(|(id, outcome, ms)| TestResult {
+                test_id: TestId::new(*id),
+                outcome: *outcome,
+                duration: Duration::from_millis(*ms),
+                messages: Vec::new(),
+            })
+            .collect();
+        TestRunRecord::new(TestRunId::new(), Some(commit.to_string()), &results)
+    }
+
+    #[test]
+    fn test_flaky_detection() {
+        let history = TestHistory::new();
+        let flipped = history.record(run("abc", &[
+            ("stable", TestOutcome::Passed, 10),
+            ("flaky", TestOutcome::Passed, 10),
+            ("broken", TestOutcome::Failed, 10),
+        ])).unwrap();
+        assert!(flipped.is_empty());
+
+        let flipped = history.record(run("abc", &[
+            ("stable", TestOutcome::Passed, 10),
+            ("flaky", TestOutcome::Failed, 10),
+            ("broken", TestOutcome::Failed, 10),
+        ])).unwrap();
+        assert_eq!(flipped, vec![TestId::new("flaky")]);
+
+        // Fixed on a new commit: not flaky
+        history.record(run("def", &[("broken", TestOutcome::Passed, 10)])).unwrap();
+
+        let flaky = history.flaky_tests();
+        assert_eq!(flaky.len(), 1);
+        assert_eq!(flaky[0].0, TestId::new("flaky"));
+        assert_eq!(flaky[0].1.badge().as_deref(), Some("flaky 1/2"));
+        assert!(!history.flakiness(&TestId::new("broken")).is_flaky());
+
+        assert_eq!(history.last_failed(), vec![TestId::new("flaky")]);
+    }
+
+    #[test]
+    fn test_duration_trend() {
+        let history = TestHistory::new();
+        for ms in [100, 100, 100, 200] {
+            history.record(run("abc", &[("slow", TestOutcome::Passed, ms)])).unwrap();
+        }
+        history.record(run("abc", &[("slow", TestOutcome::Skipped, 0)])).unwrap();
+
+        let trend = history.duration_trend(&TestId::new("slow"), 10);
+        assert_eq!(trend.samples.len(), 4);
+        assert_eq!(trend.average(), Some(Duration::from_millis(125)));
+        assert!((trend.change().unwrap() - 2.0).abs() < 1e-9);
+        assert_eq!(history.duration_trend(&TestId::new("slow"), 2).samples.len(), 2);
+    }
+
+    #[test]
+    fn test_persistence() {
+        let path = std::env::temp_dir()
+            .join(format!("foxkit-test-history-{}", uuid::Uuid::new_v4()))
+            .join("history.jsonl");
+
+        let history = TestHistory::open(&path).with_max_runs(2);
+        for outcome in [TestOutcome::Failed, TestOutcome::Passed, TestOutcome::Failed] {
+            history.record(run("abc", &[("t", outcome, 5)])).unwrap();
+        }
+        assert_eq!(history.runs().len(), 2);
+
+        let reopened = TestHistory::open(&path);
+        assert_eq!(reopened.runs().len(), 2);
+        assert!(reopened.flakiness(&TestId::new("t")).is_flaky());
+
+        reopened.clear().unwrap();
+        assert!(!path.exists());
+        let _ = std::fs::remove_dir_all(path.parent().unwrap());
+    }
+}
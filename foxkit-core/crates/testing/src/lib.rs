@@ -5,20 +5,27 @@
 pub mod discovery;
 pub mod runner;
 pub mod results;
+pub mod formats;
 pub mod adapters;
 pub mod coverage;
 pub mod ui;
+pub mod watch;
+pub mod history;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::collections::HashMap;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use tokio::sync::broadcast;
 
-pub use discovery::{TestDiscovery, TestItem, TestItemKind};
+pub use discovery::{TestDiscovery, TestItem, TestItemKind, TestRange};
 pub use runner::{TestRunner, TestRunConfig, TestRunProfile};
 pub use results::{TestResult, TestOutcome, TestMessage};
+pub use formats::{ResultFormat, parse_results};
+pub use watch::{TestWatcher, TestWatchConfig, TestWatchMode};
+pub use coverage::{CoverageReport, ChangeCoverageReport, CoverageOverlay};
+pub use history::{TestHistory, TestRunRecord, Flakiness, DurationTrend};
 
 /// Test service
 pub struct TestService {
@@ -32,6 +39,16 @@ pub struct TestService {
     events: broadcast::Sender<TestEvent>,
     /// Configuration
     config: RwLock<TestConfig>,
+    /// Watch mode change tracking
+    watcher: Arc<TestWatcher>,
+    /// Running watch loop
+    watch_task: RwLock<Option<tokio::task::JoinHandle<()>>>,
+    /// Coverage from the latest coverage run
+    coverage: RwLock<Option<CoverageReport>>,
+    /// Past run results
+    history: Arc<TestHistory>,
+    /// Commit the workspace is on, recorded with each run
+    commit: RwLock<Option<String>>,
 }
 
 impl TestService {
@@ -44,9 +61,30 @@ impl TestService {
             results: RwLock::new(HashMap::new()),
             events,
             config: RwLock::new(TestConfig::default()),
+            watcher: Arc::new(TestWatcher::default()),
+            watch_task: RwLock::new(None),
+            coverage: RwLock::new(None),
+            history: Arc::new(TestHistory::new()),
+            commit: RwLock::new(None),
         }
     }
 
+    /// Use a (persisted) run history
+    pub fn with_history(mut self, history: TestHistory) -> Self {
+        self.history = Arc::new(history);
+        self
+    }
+
+    /// Get the run history
+    pub fn history(&self) -> &Arc<TestHistory> {
+        &self.history
+    }
+
+    /// Set the commit runs are recorded against
+    pub fn set_commit(&self, commit: Option<String>) {
+        *self.commit.write() = commit;
+    }
+
     /// Configure testing
     pub fn configure(&self, config: TestConfig) {
         *self.config.write() = config;
@@ -69,17 +107,85 @@ impl TestService {
         let items = self.discovery.discover(workspace).await?;
         
         let _ = self.events.send(TestEvent::DiscoveryCompleted {
-            test_count: items.len(),
+            test_count: items.iter().map(TestItem::test_count).sum(),
         });
 
         Ok(items)
     }
 
+    /// Get test discovery
+    pub fn discovery(&self) -> &Arc<TestDiscovery> {
+        &self.discovery
+    }
+
+    /// Discover tests in an opened buffer
+    pub fn buffer_opened(&self, file: &Path, source: &str) -> Vec<TestItem> {
+        let items = self.discovery.open_buffer(file, source);
+        let _ = self.events.send(TestEvent::TestsChanged {
+            file: file.to_path_buf(),
+            items: items.clone(),
+        });
+        items
+    }
+
+    /// Forget the syntax tree of a closed buffer
+    pub fn buffer_closed(&self, file: &Path) {
+        self.discovery.close_buffer(file);
+    }
+
+    /// Rediscover tests in an edited buffer
+    pub fn buffer_edited(
+        &self,
+        file: &Path,
+        edits: &[treesitter::InputEdit],
+        source: &str,
+    ) -> Vec<TestItem> {
+        let items = self.discovery.edit_buffer(file, edits, source);
+        let _ = self.events.send(TestEvent::TestsChanged {
+            file: file.to_path_buf(),
+            items: items.clone(),
+        });
+        items
+    }
+
     /// Run tests
     pub async fn run(
         &self,
         tests: &[TestId],
         profile: TestRunProfile,
+    ) -> anyhow::Result<TestRunResult> {
+        // Runners select tests by exact id, so suites go out as their tests
+        let tests = &self.discovery.expand(tests);
+        match profile {
+            TestRunProfile::RerunFailed => {
+                let failed = self.history.last_failed();
+                let tests: Vec<_> = if tests.is_empty() {
+                    failed
+                } else {
+                    tests.iter().filter(|t| failed.contains(t)).cloned().collect()
+                };
+                self.run_once(&tests, profile).await
+            }
+            TestRunProfile::UntilFailure(times) => {
+                let times = times.max(1);
+                let mut iteration = 1;
+                loop {
+                    let _ = self.events.send(TestEvent::RepeatIteration { iteration, times });
+                    let result = self.run_once(tests, profile).await?;
+                    if result.failed > 0 || iteration == times {
+                        return Ok(result);
+                    }
+                    iteration += 1;
+                }
+            }
+            _ => self.run_once(tests, profile).await,
+        }
+    }
+
+    async fn run_once(
+        &self,
+        tests: &[TestId],
+        profile: TestRunProfile,
     ) -> anyhow::Result<TestRunResult> {
         let run_id = TestRunId::new();
         
@@ -90,28 +196,35 @@ impl TestService {
 
         let config = TestRunConfig {
             profile,
+            coverage: self.config.read().enable_coverage,
             ..Default::default()
         };
 
+        let started = std::time::Instant::now();
+        let mut results = Vec::with_capacity(tests.len());
+        let mut ran = Vec::new();
+
+        for (framework, runner, ids) in self.group_by_runner(tests, &mut results) {
+            for test_id in &ids {
+                let _ = self.events.send(TestEvent::TestStarted {
+                    run_id: run_id.clone(),
+                    test_id: test_id.clone(),
+                });
+            }
+
+            let reported = runner.run(&ids, &config).await;
+            if let Err(e) = &reported {
+                tracing::warn!("{} run failed: {}", framework, e);
+            }
+            results.extend(match_results(&framework, &ids, reported));
+            ran.push(runner);
+        }
+
         let mut passed = 0;
         let mut failed = 0;
         let mut skipped = 0;
 
-        for test_id in tests {
-            let _ = self.events.send(TestEvent::TestStarted {
-                run_id: run_id.clone(),
-                test_id: test_id.clone(),
-            });
-
-            // Find appropriate runner
-            // For now, simulate running
-            let result = TestResult {
-                test_id: test_id.clone(),
-                outcome: TestOutcome::Passed,
-                duration: std::time::Duration::from_millis(100),
-                messages: Vec::new(),
-            };
-
+        for result in &results {
             match result.outcome {
                 TestOutcome::Passed => passed += 1,
                 TestOutcome::Failed => failed += 1,
@@ -119,7 +232,7 @@ impl TestService {
                 TestOutcome::Errored => failed += 1,
             }
 
-            self.results.write().insert(test_id.clone(), result.clone());
+            self.results.write().insert(result.test_id.clone(), result.clone());
 
             let _ = self.events.send(TestEvent::TestCompleted {
                 run_id: run_id.clone(),
@@ -132,16 +245,132 @@ impl TestService {
             passed,
             failed,
             skipped,
-            duration: std::time::Duration::from_secs(1),
+            duration: started.elapsed(),
         };
 
         let _ = self.events.send(TestEvent::RunCompleted {
             result: run_result.clone(),
         });
 
+        self.record_history(run_id, &results);
+
+        if config.collects_coverage() {
+            self.collect_coverage(&ran);
+        }
+
         Ok(run_result)
     }
 
+    /// Record a run, reporting tests that flipped on an unchanged commit
+    fn record_history(&self, run_id: TestRunId, results: &[TestResult]) {
+        let commit = self.commit.read().clone();
+        match self.history.record(TestRunRecord::new(run_id, commit, results)) {
+            Ok(flipped) if !flipped.is_empty() => {
+                let tests = flipped.into_iter()
+                    .map(|id| {
+                        let flakiness = self.history.flakiness(&id);
+                        (id, flakiness)
+                    })
+                    .collect();
+                let _ = self.events.send(TestEvent::FlakyTestsDetected { tests });
+            }
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Failed to record test history: {}", e),
+        }
+    }
+
+    /// Split tests between the runners for their files, in request order.
+    /// Tests no runner handles are reported as errored.
+    fn group_by_runner(
+        &self,
+        tests: &[TestId],
+        unrunnable: &mut Vec<TestResult>,
+    ) -> Vec<(String, Arc<dyn TestRunner>, Vec<TestId>)> {
+        let mut groups: Vec<(String, Arc<dyn TestRunner>, Vec<TestId>)> = Vec::new();
+
+        for test_id in tests {
+            let Some((framework, runner)) = self.runner_for(test_id) else {
+                unrunnable.push(TestResult {
+                    test_id: test_id.clone(),
+                    outcome: TestOutcome::Errored,
+                    duration: std::time::Duration::ZERO,
+                    messages: vec![TestMessage::error(format!(
+                        "No test runner registered for {}",
+                        test_id.file().display()
+                    ))],
+                });
+                continue;
+            };
+            match groups.iter_mut().find(|(name, ..)| *name == framework) {
+                Some((.., ids)) => ids.push(test_id.clone()),
+                None => groups.push((framework, runner, vec![test_id.clone()])),
+            }
+        }
+
+        groups
+    }
+
+    /// Runner for a test, chosen by the extension of its file. When several
+    /// runners claim an extension the first framework by name wins.
+    fn runner_for(&self, test_id: &TestId) -> Option<(String, Arc<dyn TestRunner>)> {
+        let extension = test_id.file().extension()?.to_str()?;
+        let runners = self.runners.read();
+        runners.iter()
+            .filter(|(_, runner)| runner.extensions().contains(&extension))
+            .min_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(framework, runner)| (framework.clone(), Arc::clone(runner)))
+    }
+
+    /// Gather coverage reported by the runners of a coverage run
+    fn collect_coverage(&self, runners: &[Arc<dyn TestRunner>]) {
+        let mut collected: Option<CoverageReport> = None;
+        for report in runners.iter().filter_map(|r| r.take_coverage()) {
+            match &mut collected {
+                Some(merged) => merged.merge(report),
+                None => collected = Some(report),
+            }
+        }
+
+        if let Some(report) = collected {
+            self.set_coverage(report);
+        }
+    }
+
+    /// Get coverage from the latest coverage run
+    pub fn coverage(&self) -> Option<CoverageReport> {
+        self.coverage.read().clone()
+    }
+
+    /// Replace the current coverage, e.g. with a report loaded from disk
+    pub fn set_coverage(&self, report: CoverageReport) {
+        let summary = report.summary.clone();
+        *self.coverage.write() = Some(report);
+        let _ = self.events.send(TestEvent::CoverageUpdated { summary });
+    }
+
+    /// Load an LCOV or Cobertura report
+    pub async fn load_coverage(&self, path: &Path) -> anyhow::Result<()> {
+        let content = tokio::fs::read_to_string(path).await?;
+        self.set_coverage(CoverageReport::parse(&content)?);
+        Ok(())
+    }
+
+    /// Clear coverage
+    pub fn clear_coverage(&self) {
+        if self.coverage.write().take().is_some() {
+            let _ = self.events.send(TestEvent::CoverageCleared);
+        }
+    }
+
+    /// Coverage of the changes since the merge base of HEAD and `base`
+    pub async fn change_coverage(&self, repo: &Path, base: &str) -> anyhow::Result<ChangeCoverageReport> {
+        let Some(report) = self.coverage() else {
+            anyhow::bail!("No coverage collected");
+        };
+        let diffs = git::diff::diff_merge_base(repo, base).await?;
+        Ok(ChangeCoverageReport::new(&report, &diffs, repo))
+    }
+
     /// Run all tests
     pub async fn run_all(&self, workspace: &PathBuf, profile: TestRunProfile) -> anyhow::Result<TestRunResult> {
         let tests = self.discover(workspace).await?;
@@ -175,6 +404,84 @@ impl TestService {
     pub fn cancel(&self) {
         let _ = self.events.send(TestEvent::RunCancelled);
     }
+
+    /// Get the watch mode change tracker
+    pub fn watcher(&self) -> &Arc<TestWatcher> {
+        &self.watcher
+    }
+
+    /// Start watch mode: file changes reported by `files` re-run the
+    /// tests they affect, streaming results as `TestEvent`s
+    pub fn start_watch(self: &Arc<Self>, files: &file_watcher::FileWatcherService, config: TestWatchConfig) {
+        self.stop_watch();
+
+        let debounce = config.debounce.max(std::time::Duration::from_millis(10));
+        self.watcher.configure(config);
+
+        let service = Arc::clone(self);
+        let mut changes = files.subscribe();
+        let task = tokio::spawn(async move {
+            let mut tick = tokio::time::interval(debounce);
+            loop {
+                tokio::select! {
+                    event = changes.recv() => match event {
+                        Ok(file_watcher::FileWatcherEvent::FileChanged { change }) => service.watcher.add(change),
+                        Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+                        Err(broadcast::error::RecvError::Closed) => break,
+                    },
+                    _ = tick.tick() => {
+                        let batch = service.watcher.flush();
+                        if !batch.is_empty()
+                            && let Err(e) = service.run_changes(&batch).await
+                        {
+                            tracing::warn!("Watch run failed: {}", e);
+                        }
+                    }
+                }
+            }
+        });
+
+        *self.watch_task.write() = Some(task);
+        let _ = self.events.send(TestEvent::WatchStarted);
+    }
+
+    /// Stop watch mode
+    pub fn stop_watch(&self) {
+        if let Some(task) = self.watch_task.write().take() {
+            task.abort();
+            let _ = self.events.send(TestEvent::WatchStopped);
+        }
+    }
+
+    /// Is watch mode on?
+    pub fn is_watching(&self) -> bool {
+        self.watch_task.read().is_some()
+    }
+
+    /// Re-run the tests affected by a batch of file changes
+    pub async fn run_changes(
+        &self,
+        changes: &[file_watcher::FileChange],
+    ) -> anyhow::Result<Option<TestRunResult>> {
+        let tests = self.watcher.plan(changes, &self.discovery).await?;
+
+        let _ = self.events.send(TestEvent::WatchTriggered {
+            files: changes.iter().map(|c| c.path.clone()).collect(),
+            test_count: tests.len(),
+        });
+        for change in changes {
+            let _ = self.events.send(TestEvent::TestsChanged {
+                file: change.path.clone(),
+                items: self.discovery.get_tests(&change.path).unwrap_or_default(),
+            });
+        }
+
+        if tests.is_empty() {
+            return Ok(None);
+        }
+        let profile = self.watcher.config().profile;
+        self.run(&tests, profile).await.map(Some)
+    }
 }
 
 impl Default for TestService {
@@ -183,6 +490,37 @@ impl Default for TestService {
     }
 }
 
+/// Pair the requested tests with what their runner reported. Tests the
+/// runner did not report are skipped, or errored if the run itself failed.
+fn match_results(
+    framework: &str,
+    tests: &[TestId],
+    reported: anyhow::Result<Vec<TestResult>>,
+) -> Vec<TestResult> {
+    let (mut reported, error) = match reported {
+        Ok(results) => (results, None),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+
+    tests.iter()
+        .map(|test_id| {
+            if let Some(i) = reported.iter().position(|r| &r.test_id == test_id) {
+                return reported.swap_remove(i);
+            }
+            match &error {
+                Some(error) => TestResult {
+                    test_id: test_id.clone(),
+                    outcome: TestOutcome::Errored,
+                    duration: std::time::Duration::ZERO,
+                    messages: vec![TestMessage::error(error.clone())],
+                },
+                None => TestResult::skipped(test_id.clone())
+                    .with_message(TestMessage::output(format!("{} reported no result", framework))),
+            }
+        })
+        .collect()
+}
+
 /// Test ID
 #[derive(Debug, Clone, Hash, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TestId(pub String);
@@ -191,6 +529,16 @@ impl TestId {
     pub fn new(id: impl Into<String>) -> Self {
         Self(id.into())
     }
+
+    /// File the test was discovered in (the part before the first `::`)
+    pub fn file(&self) -> &Path {
+        Path::new(self.0.split_once("::").map_or(self.0.as_str(), |(file, _)| file))
+    }
+
+    /// Path of the test within its file, e.g. `tests::adds`
+    pub fn path(&self) -> &str {
+        self.0.split_once("::").map_or(self.0.as_str(), |(_, path)| path)
+    }
 }
 
 /// Test run ID
@@ -244,12 +592,20 @@ impl Default for TestConfig {
 pub enum TestEvent {
     DiscoveryStarted,
     DiscoveryCompleted { test_count: usize },
+    TestsChanged { file: PathBuf, items: Vec<TestItem> },
     RunStarted { run_id: TestRunId, test_count: usize },
     TestStarted { run_id: TestRunId, test_id: TestId },
     TestCompleted { run_id: TestRunId, result: TestResult },
     RunCompleted { result: TestRunResult },
     RunCancelled,
     DebugStarted { test_id: TestId },
+    WatchStarted,
+    WatchStopped,
+    WatchTriggered { files: Vec<PathBuf>, test_count: usize },
+    CoverageUpdated { summary: coverage::CoverageSummary },
+    CoverageCleared,
+    RepeatIteration { iteration: u32, times: u32 },
+    FlakyTestsDetected { tests: Vec<(TestId, Flakiness)> },
 }
 
 /// Test run result
@@ -261,3 +617,149 @@ pub struct TestRunResult {
     pub skipped: usize,
     pub duration: std::time::Duration,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use parking_lot::Mutex;
+
+    /// Runner for `.rs` tests that fails tests named `broken`, and tests
+    /// named `flaky` on every other call
+    #[derive(Default)]
+    struct FakeRunner {
+        calls: AtomicUsize,
+        requested: Mutex<Vec<Vec<TestId>>>,
+        coverage: Mutex<Option<CoverageReport>>,
+    }
+
+    #[async_trait::async_trait]
+    impl TestRunner for FakeRunner {
+        fn name(&self) -> &str {
+            "fake"
+        }
+
+        fn extensions(&self) -> &[&str] {
+            &["rs"]
+        }
+
+        async fn run(&self, tests: &[TestId], config: &TestRunConfig) -> anyhow::Result<Vec<TestResult>> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            self.requested.lock().push(tests.to_vec());
+            if config.collects_coverage() {
+                *self.coverage.lock() = Some(CoverageReport::from_lcov("SF:src/lib.rs\nDA:1,1\nend_of_record\n")?);
+            }
+
+            Ok(tests.iter()
+                .map(|id| {
+                    let duration = std::time::Duration::from_millis(7);
+                    match id.path() {
+                        "broken" => TestResult::failed(id.clone(), duration, "assertion failed"),
+                        "flaky" if call % 2 == 1 => TestResult::failed(id.clone(), duration, "timed out"),
+                        _ => TestResult::passed(id.clone(), duration),
+                    }
+                })
+                .collect())
+        }
+
+        async fn debug(&self, _test: &TestId, _config: &TestRunConfig) -> anyhow::Result<()> {
+            Ok(())
+        }
+
+        fn cancel(&self) {}
+
+        fn take_coverage(&self) -> Option<CoverageReport> {
+            self.coverage.lock().take()
+        }
+    }
+
+    fn service() -> (TestService, Arc<FakeRunner>) {
+        let service = TestService::new();
+        let runner = Arc::new(FakeRunner::default());
+        service.register_runner("fake", runner.clone());
+        (service, runner)
+    }
+
+    #[tokio::test]
+    async fn test_run_dispatches_to_runner() {
+        let (service, runner) = service();
+        let ok = TestId::new("src/lib.rs::ok");
+        let broken = TestId::new("src/lib.rs::broken");
+        let unknown = TestId::new("test_math.py::test_add");
+
+        let result = service.run(&[ok.clone(), broken.clone(), unknown.clone()], TestRunProfile::Run).await.unwrap();
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 2, 0));
+        assert_eq!(service.get_result(&ok).unwrap().duration, std::time::Duration::from_millis(7));
+        assert_eq!(service.get_result(&broken).unwrap().outcome, TestOutcome::Failed);
+        assert_eq!(service.get_result(&unknown).unwrap().outcome, TestOutcome::Errored);
+        assert_eq!(*runner.requested.lock(), vec![vec![ok.clone(), broken.clone()]]);
+
+        let mut last_failed = service.history().last_failed();
+        last_failed.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(last_failed, vec![broken.clone(), unknown]);
+
+        let rerun = service.run(&[ok, broken.clone()], TestRunProfile::RerunFailed).await.unwrap();
+        assert_eq!(rerun.failed, 1);
+        assert_eq!(runner.requested.lock().last().unwrap(), &vec![broken]);
+    }
+
+    #[tokio::test]
+    async fn test_alternating_test_is_flaky() {
+        let (service, runner) = service();
+        service.set_commit(Some("abc123".into()));
+        let mut events = service.subscribe();
+        let flaky = TestId::new("src/lib.rs::flaky");
+
+        let result = service.run(std::slice::from_ref(&flaky), TestRunProfile::UntilFailure(5)).await.unwrap();
+        assert_eq!(result.failed, 1);
+        assert_eq!(runner.calls.load(Ordering::SeqCst), 2);
+
+        let mut detected = None;
+        while let Ok(event) = events.try_recv() {
+            if let TestEvent::FlakyTestsDetected { tests } = event {
+                detected = Some(tests);
+            }
+        }
+        let detected = detected.expect("flaky test reported");
+        assert_eq!(detected[0].0, flaky);
+        assert!(detected[0].1.is_flaky());
+        assert_eq!(service.history().last_failed(), vec![flaky]);
+    }
+
+    #[tokio::test]
+    async fn test_run_suite_with_cargo() {
+        let dir = std::env::temp_dir().join(format!("foxkit-suite-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"suite\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n").unwrap();
+        std::fs::write(dir.join("src/lib.rs"), "#[cfg(test)]\nmod tests {\n    #[test]\n    fn adds() {\n        assert_eq!(1 + 1, 2);\n    }\n\n    #[test]\n    fn breaks() {\n        assert_eq!(1 + 1, 3);\n    }\n}\n").unwrap();
+
+        let service = TestService::new();
+        service.register_runner("cargo", Arc::new(runner::CargoTestRunner::new(dir.clone())));
+        let items = service.discover(&dir).await.unwrap();
+        let suite = items.iter().find(|item| item.kind == TestItemKind::Suite).expect("tests module discovered");
+
+        let result = service.run(std::slice::from_ref(&suite.id), TestRunProfile::Run).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+        let result = result.unwrap();
+
+        assert_eq!((result.passed, result.failed, result.skipped), (1, 1, 0));
+        let outcome = |name: &str| service.get_result(&TestId::new(format!("{}::tests::{name}", dir.join("src/lib.rs").display()))).unwrap().outcome;
+        assert_eq!(outcome("adds"), TestOutcome::Passed);
+        assert_eq!(outcome("breaks"), TestOutcome::Failed);
+    }
+
+    #[tokio::test]
+    async fn test_coverage_from_runners_that_ran() {
+        let (service, _runner) = service();
+        let idle = Arc::new(FakeRunner::default());
+        *idle.coverage.lock() = Some(CoverageReport::from_lcov("SF:other.rs\nDA:1,0\nend_of_record\n").unwrap());
+        service.register_runner("idle", idle.clone());
+
+        service.run(&[TestId::new("src/lib.rs::ok")], TestRunProfile::Coverage).await.unwrap();
+
+        let coverage = service.coverage().expect("coverage collected");
+        assert!(coverage.find_file(Path::new("src/lib.rs")).is_some());
+        assert!(coverage.find_file(Path::new("other.rs")).is_none());
+        assert!(idle.coverage.lock().is_some());
+    }
+}
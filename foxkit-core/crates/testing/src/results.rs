@@ -156,6 +156,11 @@ impl TestMessage {
         });
         self
     }
+
+    /// Diff of expected vs actual, for comparison failures
+    pub fn diff(&self) -> Option<TestDiff> {
+        Some(TestDiff::compute(self.expected.as_deref()?, self.actual.as_deref()?))
+    }
 }
 
 /// Test message kind
@@ -1,10 +1,13 @@
 //! Test runner
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use async_trait::async_trait;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 
+use crate::coverage::{CoverageReport, CoverageTool};
 use crate::{TestId, TestResult, TestOutcome, TestMessage};
 
 /// Test runner trait
@@ -13,6 +16,11 @@ pub trait TestRunner: Send + Sync {
     /// Framework name
     fn name(&self) -> &str;
 
+    /// Extensions of the test files this runner runs
+    fn extensions(&self) -> &[&str] {
+        &[]
+    }
+
     /// Run tests
     async fn run(
         &self,
@@ -25,6 +33,11 @@ pub trait TestRunner: Send + Sync {
 
     /// Cancel running tests
     fn cancel(&self);
+
+    /// Take the coverage collected by the last coverage run
+    fn take_coverage(&self) -> Option<CoverageReport> {
+        None
+    }
 }
 
 /// Test run configuration
@@ -46,6 +59,13 @@ pub struct TestRunConfig {
     pub parallel: bool,
 }
 
+impl TestRunConfig {
+    /// Should the run collect coverage?
+    pub fn collects_coverage(&self) -> bool {
+        self.coverage || matches!(self.profile, TestRunProfile::Coverage)
+    }
+}
+
 /// Test run profile
 #[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
 pub enum TestRunProfile {
@@ -53,16 +73,24 @@ pub enum TestRunProfile {
     Run,
     Debug,
     Coverage,
+    /// Run only the tests that failed last time
+    RerunFailed,
+    /// Repeat the run up to N times, stopping at the first failure
+    UntilFailure(u32),
 }
 
 /// Cargo test runner
 pub struct CargoTestRunner {
     workspace: PathBuf,
+    coverage: Mutex<Option<CoverageReport>>,
 }
 
 impl CargoTestRunner {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self {
+            workspace,
+            coverage: Mutex::new(None),
+        }
     }
 }
 
@@ -72,31 +100,120 @@ impl TestRunner for CargoTestRunner {
         "cargo-test"
     }
 
+    fn extensions(&self) -> &[&str] {
+        &["rs"]
+    }
+
     async fn run(
         &self,
         tests: &[TestId],
         config: &TestRunConfig,
     ) -> anyhow::Result<Vec<TestResult>> {
-        let mut results = Vec::new();
-
-        // Build cargo test command
-        let mut cmd = tokio::process::Command::new("cargo");
-        cmd.arg("test");
-        cmd.current_dir(&self.workspace);
+        let packages = self.metadata().await?;
 
-        // Add test filters
+        // Discovered ids are file-prefixed; libtest knows tests by module
+        // path, which is only unique within one test binary
+        let mut selected: Vec<(&CargoPackage, &CargoTarget, Vec<String>)> = Vec::new();
+        let mut ids: HashMap<(PathBuf, String), TestId> = HashMap::new();
         for test in tests {
-            cmd.arg("--").arg(&test.0);
+            let file = self.workspace.join(test.file());
+            let file = std::fs::canonicalize(&file).unwrap_or(file);
+            let Some((package, target)) = target_for(&packages, &file) else {
+                continue;
+            };
+            let path = libtest_path(test);
+            ids.insert((target.src_path.clone(), path.clone()), test.clone());
+            match selected.iter_mut().find(|(_, t, _)| t.src_path == target.src_path) {
+                Some((_, _, filters)) => filters.push(path),
+                None => selected.push((package, target, vec![path])),
+            }
+        }
+        if !tests.is_empty() && selected.is_empty() {
+            return Ok(Vec::new());
         }
 
-        // Run and parse output
-        let output = cmd.output().await?;
-        
-        // Parse test results from output
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        results.extend(parse_cargo_test_output(&stdout, tests));
+        let mut results = Vec::new();
+        if config.collects_coverage() {
+            // Plain output doesn't say which binary ran a test, so each
+            // target runs on its own
+            if tests.is_empty() {
+                selected = packages.iter()
+                    .flat_map(|package| package.targets.iter().filter(|t| t.test).map(move |t| (package, t, Vec::new())))
+                    .collect();
+            }
+
+            let tool = CoverageTool::LlvmCov;
+            let mut coverage: Option<CoverageReport> = None;
+            for (package, target, filters) in &selected {
+                let output = tool.output_path();
+                let mut cmd = tokio::process::Command::new("cargo");
+                cmd.args(tool.args(&output));
+                cmd.arg("-p").arg(&package.name).args(target.flags());
+                cmd.current_dir(&self.workspace);
+                cmd.args(&config.args);
+                cmd.arg("--");
+                if !filters.is_empty() {
+                    cmd.args(filters);
+                    cmd.arg("--exact");
+                }
+
+                let stdout = cmd.output().await?.stdout;
+                if let Some(report) = tool.collect(&output).await {
+                    match coverage.as_mut() {
+                        Some(merged) => merged.merge(report),
+                        None => coverage = Some(report),
+                    }
+                }
+                results.extend(parse_cargo_test_output(&String::from_utf8_lossy(&stdout))
+                    .into_iter()
+                    .map(|result| (target.src_path.clone(), result)));
+            }
+            *self.coverage.lock() = coverage;
+        } else {
+            let mut binaries = Vec::new();
+            if tests.is_empty() {
+                binaries = self.build(&[], config).await?;
+            } else {
+                let mut by_package: Vec<(&CargoPackage, Vec<String>)> = Vec::new();
+                for (package, target, _) in &selected {
+                    match by_package.iter_mut().find(|(p, _)| p.manifest_path == package.manifest_path) {
+                        Some((_, args)) => args.extend(target.flags()),
+                        None => {
+                            let mut args = vec!["-p".to_string(), package.name.clone()];
+                            args.extend(target.flags());
+                            by_package.push((package, args));
+                        }
+                    }
+                }
+                for (_, args) in &by_package {
+                    binaries.extend(self.build(args, config).await?);
+                }
+            }
+
+            for binary in binaries {
+                let filters = selected.iter()
+                    .find(|(_, target, _)| target.src_path == binary.src_path)
+                    .map(|(_, _, filters)| filters.as_slice())
+                    .unwrap_or_default();
+                if !tests.is_empty() && filters.is_empty() {
+                    continue;
+                }
+                results.extend(binary.run(filters, config).await?
+                    .into_iter()
+                    .map(|result| (binary.src_path.clone(), result)));
+            }
+        }
 
-        Ok(results)
+        Ok(results.into_iter()
+            .map(|(src_path, mut result)| {
+                let key = (src_path, result.test_id.0.clone());
+                result.test_id = match ids.get(&key) {
+                    Some(test) => test.clone(),
+                    None => discovered_id(&key.0, &key.1),
+                };
+                result
+            })
+            .collect())
     }
 
     async fn debug(&self, test: &TestId, config: &TestRunConfig) -> anyhow::Result<()> {
@@ -108,27 +225,252 @@ impl TestRunner for CargoTestRunner {
     fn cancel(&self) {
         // Would cancel running process
     }
+
+    fn take_coverage(&self) -> Option<CoverageReport> {
+        self.coverage.lock().take()
+    }
 }
 
-fn parse_cargo_test_output(output: &str, tests: &[TestId]) -> Vec<TestResult> {
-    let mut results = Vec::new();
+impl CargoTestRunner {
+    /// Workspace packages and their targets
+    async fn metadata(&self) -> anyhow::Result<Vec<CargoPackage>> {
+        let output = tokio::process::Command::new("cargo")
+            .args(["metadata", "--no-deps", "--format-version", "1"])
+            .current_dir(&self.workspace)
+            .output()
+            .await?;
+        if !output.status.success() {
+            anyhow::bail!("cargo metadata failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+        Ok(serde_json::from_slice::<CargoMetadata>(&output.stdout)?.packages)
+    }
 
-    for test in tests {
-        // Simple parsing - real implementation would be more robust
-        let outcome = if output.contains(&format!("test {} ... ok", test.0)) {
-            TestOutcome::Passed
-        } else if output.contains(&format!("test {} ... FAILED", test.0)) {
-            TestOutcome::Failed
-        } else if output.contains(&format!("test {} ... ignored", test.0)) {
-            TestOutcome::Skipped
-        } else {
-            TestOutcome::Passed // Default
+    /// Build test binaries with `cargo test --no-run`, limited by package
+    /// and target selection arguments
+    async fn build(&self, selection: &[String], config: &TestRunConfig) -> anyhow::Result<Vec<TestBinary>> {
+        let output = tokio::process::Command::new("cargo")
+            .args(["test", "--no-run", "--message-format=json-render-diagnostics"])
+            .args(selection)
+            .args(&config.args)
+            .current_dir(&self.workspace)
+            .output()
+            .await?;
+        if !output.status.success() {
+            anyhow::bail!("cargo test build failed: {}", String::from_utf8_lossy(&output.stderr));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| serde_json::from_str::<CargoArtifact>(line).ok())
+            .filter(|artifact| artifact.reason == "compiler-artifact" && artifact.profile.test)
+            .filter_map(|artifact| {
+                Some(TestBinary {
+                    executable: artifact.executable?,
+                    package_dir: artifact.manifest_path.parent()?.to_path_buf(),
+                    src_path: artifact.target?.src_path,
+                })
+            })
+            .collect())
+    }
+}
+
+/// `cargo metadata` output
+#[derive(Deserialize)]
+struct CargoMetadata {
+    packages: Vec<CargoPackage>,
+}
+
+#[derive(Deserialize)]
+struct CargoPackage {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<CargoTarget>,
+}
+
+/// A package target; its crate root identifies the test binary
+#[derive(Deserialize)]
+struct CargoTarget {
+    name: String,
+    kind: Vec<String>,
+    src_path: PathBuf,
+    /// Tested by a plain `cargo test`
+    #[serde(default = "default_true")]
+    test: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl CargoTarget {
+    fn is_lib(&self) -> bool {
+        self.kind.iter().any(|k| k.ends_with("lib") || k == "proc-macro")
+    }
+
+    /// `cargo test` arguments selecting this target
+    fn flags(&self) -> Vec<String> {
+        let flag = match self.kind.first().map(String::as_str) {
+            Some("bin") => "--bin",
+            Some("test") => "--test",
+            Some("bench") => "--bench",
+            Some("example") => "--example",
+            _ => return vec!["--lib".to_string()],
+        };
+        vec![flag.to_string(), self.name.clone()]
+    }
+}
+
+/// Target a source file is compiled into: the target it is the crate root
+/// of, or else the one with the deepest crate root directory containing it
+/// (the library when a binary shares its directory)
+fn target_for<'a>(packages: &'a [CargoPackage], file: &Path) -> Option<(&'a CargoPackage, &'a CargoTarget)> {
+    let targets = packages.iter()
+        .flat_map(|package| package.targets.iter().map(move |target| (package, target)));
+    if let Some(found) = targets.clone().find(|(_, target)| target.src_path == file) {
+        return Some(found);
+    }
+    targets.filter(|(_, target)| target.src_path.parent().is_some_and(|dir| file.starts_with(dir)))
+        .max_by_key(|(_, target)| (target.src_path.components().count(), target.is_lib()))
+}
+
+/// `cargo --message-format=json` artifact message
+#[derive(Deserialize)]
+struct CargoArtifact {
+    reason: String,
+    #[serde(default)]
+    executable: Option<PathBuf>,
+    #[serde(default)]
+    manifest_path: PathBuf,
+    #[serde(default)]
+    target: Option<CargoTarget>,
+    #[serde(default)]
+    profile: CargoProfile,
+}
+
+#[derive(Default, Deserialize)]
+struct CargoProfile {
+    #[serde(default)]
+    test: bool,
+}
+
+/// A built libtest binary
+struct TestBinary {
+    executable: PathBuf,
+    package_dir: PathBuf,
+    /// Crate root of the target
+    src_path: PathBuf,
+}
+
+impl TestBinary {
+    /// Run the binary, asking libtest for JSON events.
+    ///
+    /// libtest only accepts `--format json` behind `-Z unstable-options`, so
+    /// `RUSTC_BOOTSTRAP` is set for the test process alone. The crate was
+    /// already built without it, so it can't enable nightly features there.
+    async fn run(&self, filters: &[String], config: &TestRunConfig) -> anyhow::Result<Vec<TestResult>> {
+        let mut cmd = tokio::process::Command::new(&self.executable);
+        cmd.current_dir(config.cwd.as_ref().unwrap_or(&self.package_dir));
+        cmd.env("CARGO_MANIFEST_DIR", &self.package_dir);
+        cmd.envs(config.env.iter().map(|(k, v)| (k, v)));
+        cmd.env("RUSTC_BOOTSTRAP", "1");
+        cmd.args(["-Z", "unstable-options", "--format", "json", "--report-time"]);
+        if !filters.is_empty() {
+            cmd.args(filters);
+            cmd.arg("--exact");
+        }
+
+        let output = cmd.output().await?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+
+        let results = crate::formats::parse_libtest_json(&stdout);
+        if !results.is_empty() {
+            return Ok(results);
+        }
+
+        // Custom harnesses ignore the JSON format but may print the plain summary
+        Ok(parse_cargo_test_output(&stdout))
+    }
+}
+
+/// libtest path of a discovered test: the module path of its file within
+/// the crate followed by the test's path within the file
+fn libtest_path(test: &TestId) -> String {
+    let mut path = module_path(test.file());
+    path.push(test.path().to_string());
+    path.join("::")
+}
+
+/// Discovery id of a test libtest reported from the target rooted at
+/// `src_path`: module files are looked up the way `mod` declarations
+/// resolve them, the rest of the path is the test's path within its file
+fn discovered_id(src_path: &Path, libtest_path: &str) -> TestId {
+    let segments: Vec<&str> = libtest_path.split("::").collect();
+    let mut file = src_path.to_path_buf();
+    let mut dir = src_path.parent().unwrap_or(Path::new("")).to_path_buf();
+    let mut consumed = 0;
+
+    for module in &segments[..segments.len() - 1] {
+        let candidates = [dir.join(format!("{}.rs", module)), dir.join(module).join("mod.rs")];
+        let Some(found) = candidates.into_iter().find(|c| c.is_file()) else {
+            break;
         };
+        file = found;
+        dir = dir.join(module);
+        consumed += 1;
+    }
+
+    TestId::new(format!("{}::{}", file.display(), segments[consumed..].join("::")))
+}
+
+/// Module path of a source file, e.g. `src/parser/lexer.rs` is `parser::lexer`.
+/// Crate roots (`lib.rs`, `main.rs`, `src/bin/*`, `tests/*`, ...) have none.
+fn module_path(file: &Path) -> Vec<String> {
+    let components: Vec<String> = file.components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect();
+
+    let root = components.iter().rposition(|c| c == "src").or_else(|| {
+        components.iter().rposition(|c| matches!(c.as_str(), "tests" | "benches" | "examples"))
+    });
+    let Some(root) = root else {
+        return Vec::new();
+    };
+
+    let mut modules = &components[root + 1..];
+    if components[root] != "src" {
+        // The target's own file or directory
+        modules = modules.get(1..).unwrap_or_default();
+    } else if modules.first().is_some_and(|m| m == "bin") {
+        modules = modules.get(2..).unwrap_or_default();
+    }
+
+    let mut modules = modules.to_vec();
+    if let Some(last) = modules.pop() {
+        let stem = Path::new(&last).file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or(last);
+        if !matches!(stem.as_str(), "lib" | "main" | "mod") {
+            modules.push(stem);
+        }
+    }
+    modules
+}
 
+fn parse_cargo_test_output(output: &str) -> Vec<TestResult> {
+    let mut results = Vec::new();
+
+    for line in output.lines() {
+        let Some((name, status)) = line.strip_prefix("test ").and_then(|l| l.rsplit_once(" ... ")) else {
+            continue;
+        };
+        let outcome = match status.trim() {
+            "ok" => TestOutcome::Passed,
+            "FAILED" => TestOutcome::Failed,
+            s if s.starts_with("ignored") => TestOutcome::Skipped,
+            _ => continue,
+        };
         results.push(TestResult {
-            test_id: test.clone(),
+            test_id: TestId::new(name),
             outcome,
-            duration: Duration::from_millis(100),
+            duration: Duration::ZERO,
             messages: Vec::new(),
         });
     }
@@ -139,11 +481,15 @@ fn parse_cargo_test_output(output: &str, tests: &[TestId]) -> Vec<TestResult> {
 /// Jest test runner
 pub struct JestRunner {
     workspace: PathBuf,
+    coverage: Mutex<Option<CoverageReport>>,
 }
 
 impl JestRunner {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self {
+            workspace,
+            coverage: Mutex::new(None),
+        }
     }
 }
 
@@ -153,12 +499,23 @@ impl TestRunner for JestRunner {
         "jest"
     }
 
+    fn extensions(&self) -> &[&str] {
+        &["js", "jsx", "mjs", "cjs", "ts", "tsx"]
+    }
+
     async fn run(
         &self,
         tests: &[TestId],
         config: &TestRunConfig,
     ) -> anyhow::Result<Vec<TestResult>> {
+        let coverage = config.collects_coverage().then(|| {
+            let tool = CoverageTool::C8;
+            (tool, tool.output_path())
+        });
         let mut cmd = tokio::process::Command::new("npx");
+        if let Some((tool, output)) = &coverage {
+            cmd.args(tool.args(output));
+        }
         cmd.arg("jest");
         cmd.arg("--json");
         cmd.current_dir(&self.workspace);
@@ -171,6 +528,10 @@ impl TestRunner for JestRunner {
         let output = cmd.output().await?;
         let stdout = String::from_utf8_lossy(&output.stdout);
 
+        if let Some((tool, output)) = coverage {
+            *self.coverage.lock() = tool.collect(&output).await;
+        }
+
         // Parse Jest JSON output
         parse_jest_output(&stdout, tests)
     }
@@ -181,6 +542,10 @@ impl TestRunner for JestRunner {
     }
 
     fn cancel(&self) {}
+
+    fn take_coverage(&self) -> Option<CoverageReport> {
+        self.coverage.lock().take()
+    }
 }
 
 fn parse_jest_output(output: &str, tests: &[TestId]) -> anyhow::Result<Vec<TestResult>> {
@@ -202,11 +567,15 @@ fn parse_jest_output(output: &str, tests: &[TestId]) -> anyhow::Result<Vec<TestR
 /// Pytest runner
 pub struct PytestRunner {
     workspace: PathBuf,
+    coverage: Mutex<Option<CoverageReport>>,
 }
 
 impl PytestRunner {
     pub fn new(workspace: PathBuf) -> Self {
-        Self { workspace }
+        Self {
+            workspace,
+            coverage: Mutex::new(None),
+        }
     }
 }
 
@@ -216,36 +585,43 @@ impl TestRunner for PytestRunner {
         "pytest"
     }
 
+    fn extensions(&self) -> &[&str] {
+        &["py"]
+    }
+
     async fn run(
         &self,
         tests: &[TestId],
         config: &TestRunConfig,
     ) -> anyhow::Result<Vec<TestResult>> {
-        let mut cmd = tokio::process::Command::new("pytest");
-        cmd.arg("--json-report");
-        cmd.current_dir(&self.workspace);
+        let report = std::env::temp_dir().join(format!("pytest-{}.xml", uuid::Uuid::new_v4()));
 
-        for test in tests {
-            cmd.arg("-k").arg(&test.0);
+        let mut cmd = self.command(tests, &report);
+
+        let coverage = config.collects_coverage().then(|| {
+            let tool = CoverageTool::PytestCov;
+            (tool, tool.output_path())
+        });
+        if let Some((tool, output)) = &coverage {
+            cmd.args(tool.args(output));
         }
 
         let output = cmd.output().await?;
-        
-        let mut results = Vec::new();
-        for test in tests {
-            results.push(TestResult {
-                test_id: test.clone(),
-                outcome: if output.status.success() {
-                    TestOutcome::Passed
-                } else {
-                    TestOutcome::Failed
-                },
-                duration: Duration::from_millis(100),
-                messages: Vec::new(),
-            });
+
+        let xml = match tokio::fs::read_to_string(&report).await {
+            Ok(xml) => xml,
+            Err(_) => anyhow::bail!(
+                "pytest produced no report: {}",
+                String::from_utf8_lossy(&output.stderr)
+            ),
+        };
+        let _ = tokio::fs::remove_file(&report).await;
+
+        if let Some((tool, output)) = coverage {
+            *self.coverage.lock() = tool.collect(&output).await;
         }
 
-        Ok(results)
+        Ok(self.map_results(tests, crate::formats::parse_junit_xml(&xml)?))
     }
 
     async fn debug(&self, test: &TestId, config: &TestRunConfig) -> anyhow::Result<()> {
@@ -254,4 +630,186 @@ impl TestRunner for PytestRunner {
     }
 
     fn cancel(&self) {}
+
+    fn take_coverage(&self) -> Option<CoverageReport> {
+        self.coverage.lock().take()
+    }
+}
+
+impl PytestRunner {
+    /// pytest command selecting tests by node id (`path::Class::test`)
+    fn command(&self, tests: &[TestId], report: &Path) -> tokio::process::Command {
+        let mut cmd = tokio::process::Command::new("pytest");
+        cmd.arg(format!("--junitxml={}", report.display()));
+        cmd.arg("--rootdir").arg(&self.workspace);
+        cmd.current_dir(&self.workspace);
+        cmd.args(tests.iter().map(|test| self.node_id(test)));
+        cmd
+    }
+
+    /// Node id of a discovered test, relative to the workspace
+    fn node_id(&self, test: &TestId) -> String {
+        let file = test.file();
+        let file = file.strip_prefix(&self.workspace).unwrap_or(file).display();
+        match test.0.split_once("::") {
+            Some(_) => format!("{}::{}", file, test.path()),
+            None => file.to_string(),
+        }
+    }
+
+    /// Give JUnit results the ids of the tests that were asked for.
+    ///
+    /// pytest reports a test as `classname` (dotted module path relative to
+    /// the root dir, then any classes) and `name`, with a `[params]` suffix
+    /// per parametrized case. Cases fold into their test, which takes the
+    /// worst outcome.
+    fn map_results(&self, tests: &[TestId], results: Vec<TestResult>) -> Vec<TestResult> {
+        let ids: HashMap<String, &TestId> = tests.iter()
+            .map(|test| (junit_id(&self.node_id(test)), test))
+            .collect();
+
+        let mut mapped: Vec<TestResult> = Vec::new();
+        for mut result in results {
+            let reported = &result.test_id.0;
+            let test = ids.get(reported).or_else(|| {
+                let (test, _params) = reported.strip_suffix(']')?.rsplit_once('[')?;
+                ids.get(test)
+            });
+            let Some(&test) = test else {
+                mapped.push(result);
+                continue;
+            };
+
+            match mapped.iter_mut().find(|r| &r.test_id == test) {
+                Some(existing) => {
+                    if outcome_rank(result.outcome) > outcome_rank(existing.outcome) {
+                        existing.outcome = result.outcome;
+                    }
+                    existing.duration += result.duration;
+                    existing.messages.append(&mut result.messages);
+                }
+                None => {
+                    result.test_id = test.clone();
+                    mapped.push(result);
+                }
+            }
+        }
+        mapped
+    }
+}
+
+/// JUnit id (`classname::name`) pytest reports for a node id
+fn junit_id(node_id: &str) -> String {
+    let mut parts = node_id.split("::");
+    let module = parts.next().unwrap_or_default();
+    let module = module.strip_suffix(".py").unwrap_or(module).replace(['/', '\\'], ".");
+    let mut parts: Vec<&str> = parts.collect();
+    let Some(name) = parts.pop() else {
+        return module;
+    };
+
+    let mut classname = module;
+    for class in parts {
+        classname.push('.');
+        classname.push_str(class);
+    }
+    format!("{}::{}", classname, name)
+}
+
+/// Severity of an outcome when several cases report for one test
+fn outcome_rank(outcome: TestOutcome) -> u8 {
+    match outcome {
+        TestOutcome::Skipped => 0,
+        TestOutcome::Passed => 1,
+        TestOutcome::Failed => 2,
+        TestOutcome::Errored => 3,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_libtest_path() {
+        let path = |id: &str| libtest_path(&TestId::new(id));
+        assert_eq!(path("src/lib.rs::tests::adds"), "tests::adds");
+        assert_eq!(path("/ws/crates/a/src/parser/mod.rs::tests::x"), "parser::tests::x");
+        assert_eq!(path("/ws/crates/a/src/parser/lexer.rs::tests::x"), "parser::lexer::tests::x");
+        assert_eq!(path("src/bin/tool/main.rs::tests::x"), "tests::x");
+        assert_eq!(path("src/bin/tool/args.rs::tests::x"), "args::tests::x");
+        assert_eq!(path("/ws/tests/api.rs::login"), "login");
+        assert_eq!(path("/ws/tests/common/mod.rs::helper"), "helper");
+    }
+
+    #[test]
+    fn test_pytest_node_ids() {
+        let runner = PytestRunner::new(PathBuf::from("/ws"));
+        let square = TestId::new("/ws/tests/test_math.py::test_square");
+        let add = TestId::new("/ws/tests/test_math.py::TestMath::test_add");
+        let tests = [square.clone(), add.clone()];
+
+        let cmd = runner.command(&tests, Path::new("/tmp/report.xml"));
+        let args: Vec<_> = cmd.as_std().get_args().map(|a| a.to_string_lossy().into_owned()).collect();
+        assert_eq!(&args[3..], ["tests/test_math.py::test_square", "tests/test_math.py::TestMath::test_add"]);
+        assert!(!args.iter().any(|a| a == "-k"));
+
+        let xml = r#"<testsuites><testsuite name="pytest">
+  <testcase classname="tests.test_math" name="test_square[1]" time="0.1" />
+  <testcase classname="tests.test_math" name="test_square[2]" time="0.2"><failure message="assert 4 == 5" /></testcase>
+  <testcase classname="tests.test_math.TestMath" name="test_add" time="0.1" />
+</testsuite></testsuites>"#;
+        let results = runner.map_results(&tests, crate::formats::parse_junit_xml(xml).unwrap());
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].test_id, square);
+        assert_eq!(results[0].outcome, TestOutcome::Failed);
+        assert_eq!(results[0].duration, Duration::from_millis(300));
+        assert_eq!(results[1].test_id, add);
+        assert_eq!(results[1].outcome, TestOutcome::Passed);
+    }
+
+    #[tokio::test]
+    async fn test_cargo_targets() {
+        let dir = std::env::temp_dir().join(format!("foxkit-cargo-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("src")).unwrap();
+        std::fs::create_dir_all(dir.join("tests")).unwrap();
+        let dir = std::fs::canonicalize(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"targets\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n").unwrap();
+        let module = "#[cfg(test)]\nmod tests {\n    #[test]\n    fn adds() {}\n}\n";
+        std::fs::write(dir.join("src/lib.rs"), format!("mod parser;\n\n{module}")).unwrap();
+        std::fs::write(dir.join("src/parser.rs"), module).unwrap();
+        std::fs::write(dir.join("tests/api.rs"), module).unwrap();
+
+        let discovery = crate::TestDiscovery::new();
+        let mut discovered: Vec<TestId> = discovery.discover(&dir).await.unwrap()
+            .iter()
+            .flat_map(crate::TestItem::runnable_ids)
+            .collect();
+        discovered.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let runner = CargoTestRunner::new(dir.clone());
+        let config = TestRunConfig::default();
+        let all = runner.run(&[], &config).await;
+        let lib = TestId::new(format!("{}::tests::adds", dir.join("src/lib.rs").display()));
+        let selected = runner.run(std::slice::from_ref(&lib), &config).await;
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let mut ids: Vec<TestId> = all.unwrap().into_iter().map(|r| r.test_id).collect();
+        ids.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(discovered.len(), 3);
+        assert_eq!(ids, discovered);
+
+        let selected = selected.unwrap();
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].test_id, lib);
+        assert_eq!(selected[0].outcome, TestOutcome::Passed);
+    }
+
+    #[test]
+    fn test_plain_output() {
+        let output = "running 2 tests\ntest tests::a ... ok\ntest tests::b ... FAILED\n";
+        let results = parse_cargo_test_output(output);
+        assert_eq!(results[1].test_id, TestId::new("tests::b"));
+        assert_eq!(results[1].outcome, TestOutcome::Failed);
+    }
 }
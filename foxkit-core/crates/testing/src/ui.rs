@@ -1,7 +1,11 @@
 //! Test UI components
 
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Serialize};
-use crate::{TestId, TestItem, TestResult, TestOutcome, TestRunResult};
+use crate::coverage::CoverageSummary;
+use crate::history::{Flakiness, TestHistory};
+use crate::{TestEvent, TestId, TestItem, TestResult, TestOutcome, TestRunResult};
 
 /// Test explorer view model
 #[derive(Debug, Clone, Default)]
@@ -16,6 +20,12 @@ pub struct TestExplorerViewModel {
     pub show_failed_only: bool,
     /// Expanded items
     pub expanded: Vec<TestId>,
+    /// Tests currently running
+    pub running: HashSet<TestId>,
+    /// Watch mode is on
+    pub watching: bool,
+    /// Coverage summary of the latest coverage run
+    pub coverage: Option<CoverageSummary>,
 }
 
 impl TestExplorerViewModel {
@@ -44,6 +54,78 @@ impl TestExplorerViewModel {
         update_item(&mut self.items, result);
     }
 
+    /// Replace the items of one file, keeping known results
+    pub fn update_file(&mut self, file: &Path, items: Vec<TestItem>) {
+        let mut previous = Vec::new();
+        self.items.retain(|item| {
+            if item.file.as_deref() == Some(file) {
+                previous.push(item.clone());
+                false
+            } else {
+                true
+            }
+        });
+
+        let mut updated: Vec<TestTreeItem> = items.into_iter().map(TestTreeItem::from).collect();
+        fn restore(items: &mut [TestTreeItem], previous: &[TestTreeItem]) {
+            for item in items {
+                if let Some(old) = find_item(previous, &item.id) {
+                    item.result = old.result;
+                    item.duration = old.duration;
+                    item.flakiness = old.flakiness;
+                }
+                restore(&mut item.children, previous);
+            }
+        }
+        restore(&mut updated, &previous);
+        self.items.extend(updated);
+    }
+
+    /// Apply a service event, so results stream in while tests run
+    pub fn apply_event(&mut self, event: &TestEvent) {
+        match event {
+            TestEvent::TestsChanged { file, items } => self.update_file(file, items.clone()),
+            TestEvent::TestStarted { test_id, .. } => {
+                self.running.insert(test_id.clone());
+            }
+            TestEvent::TestCompleted { result, .. } => {
+                self.running.remove(&result.test_id);
+                self.update_result(result);
+            }
+            TestEvent::RunCompleted { .. } | TestEvent::RunCancelled => self.running.clear(),
+            TestEvent::WatchStarted => self.watching = true,
+            TestEvent::WatchStopped => self.watching = false,
+            TestEvent::CoverageUpdated { summary } => self.coverage = Some(summary.clone()),
+            TestEvent::CoverageCleared => self.coverage = None,
+            TestEvent::FlakyTestsDetected { tests } => {
+                for (id, flakiness) in tests {
+                    if let Some(item) = find_item_mut(&mut self.items, id) {
+                        item.flakiness = Some(*flakiness);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Refresh flakiness badges from the run history
+    pub fn update_flakiness(&mut self, history: &TestHistory) {
+        fn update(items: &mut [TestTreeItem], history: &TestHistory) {
+            for item in items {
+                let flakiness = history.flakiness(&item.id);
+                item.flakiness = flakiness.is_flaky().then_some(flakiness);
+                update(&mut item.children, history);
+            }
+        }
+
+        update(&mut self.items, history);
+    }
+
+    /// Is a test running?
+    pub fn is_running(&self, id: &TestId) -> bool {
+        self.running.contains(id)
+    }
+
     /// Toggle item expansion
     pub fn toggle_expanded(&mut self, id: &TestId) {
         if let Some(pos) = self.expanded.iter().position(|i| i == id) {
@@ -85,10 +167,20 @@ impl TestExplorerViewModel {
 pub struct TestTreeItem {
     pub id: TestId,
     pub label: String,
+    pub file: Option<PathBuf>,
     pub kind: TestTreeItemKind,
     pub children: Vec<TestTreeItem>,
     pub result: Option<TestOutcome>,
     pub duration: Option<std::time::Duration>,
+    /// Set when the test has flipped between pass and fail on one commit
+    pub flakiness: Option<Flakiness>,
+}
+
+impl TestTreeItem {
+    /// Flakiness badge text
+    pub fn badge(&self) -> Option<String> {
+        self.flakiness.and_then(|f| f.badge())
+    }
 }
 
 impl From<TestItem> for TestTreeItem {
@@ -96,6 +188,7 @@ impl From<TestItem> for TestTreeItem {
         Self {
             id: item.id,
             label: item.name,
+            file: Some(item.file),
             kind: match item.kind {
                 crate::discovery::TestItemKind::Test => TestTreeItemKind::Test,
                 crate::discovery::TestItemKind::Suite => TestTreeItemKind::Suite,
@@ -105,8 +198,31 @@ impl From<TestItem> for TestTreeItem {
             children: item.children.into_iter().map(TestTreeItem::from).collect(),
             result: None,
             duration: None,
+            flakiness: None,
+        }
+    }
+}
+
+fn find_item<'a>(items: &'a [TestTreeItem], id: &TestId) -> Option<&'a TestTreeItem> {
+    items.iter().find_map(|item| {
+        if &item.id == id {
+            Some(item)
+        } else {
+            find_item(&item.children, id)
+        }
+    })
+}
+
+fn find_item_mut<'a>(items: &'a mut [TestTreeItem], id: &TestId) -> Option<&'a mut TestTreeItem> {
+    for item in items {
+        if &item.id == id {
+            return Some(item);
+        }
+        if let Some(found) = find_item_mut(&mut item.children, id) {
+            return Some(found);
         }
     }
+    None
 }
 
 /// Test tree item kind
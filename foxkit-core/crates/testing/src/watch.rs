@@ -0,0 +1,461 @@
+//! Continuous test mode
+//!
+//! Maps file changes to the tests they can affect: either every test in the
+//! changed packages and their dependents, or only the tests whose source or
+//! recorded coverage touches the changed lines.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+use file_watcher::{FileChange, FileChangeDebouncer, FileChangeKind};
+use monorepo::{DependencyGraph, Package};
+
+use crate::coverage::CoverageReport;
+use crate::discovery::{TestDiscovery, TestItem};
+use crate::{TestId, TestRunProfile};
+
+/// How changes select tests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TestWatchMode {
+    /// All tests of changed packages and the packages depending on them
+    #[default]
+    AffectedPackages,
+    /// Tests whose source or coverage touches the changed lines; files
+    /// without either fall back to their affected packages
+    ChangedLines,
+}
+
+/// Watch mode configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestWatchConfig {
+    /// Test selection
+    pub mode: TestWatchMode,
+    /// Quiet period before a batch of changes triggers a run
+    pub debounce: Duration,
+    /// Profile used for triggered runs
+    pub profile: TestRunProfile,
+}
+
+impl Default for TestWatchConfig {
+    fn default() -> Self {
+        Self {
+            mode: TestWatchMode::default(),
+            debounce: Duration::from_millis(300),
+            profile: TestRunProfile::Run,
+        }
+    }
+}
+
+/// Lines changed between two versions of a file (zero-based)
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedLines {
+    /// Changed or removed lines in the old version
+    pub old: BTreeSet<u32>,
+    /// Changed or added lines in the new version
+    pub new: BTreeSet<u32>,
+}
+
+impl ChangedLines {
+    /// Compare two versions of a file
+    pub fn between(old: &str, new: &str) -> Self {
+        let mut changed = Self::default();
+        let (mut old_line, mut new_line) = (0u32, 0u32);
+        let mut replacing = false;
+
+        for op in diff::diff(old, new).ops {
+            match op {
+                diff::DiffOp::Equal(_) => {
+                    old_line += 1;
+                    new_line += 1;
+                    replacing = false;
+                }
+                diff::DiffOp::Delete(_) => {
+                    changed.old.insert(old_line);
+                    old_line += 1;
+                    replacing = true;
+                }
+                diff::DiffOp::Insert(_) => {
+                    // A pure insertion touches the old line it lands on
+                    if !replacing {
+                        changed.old.insert(old_line);
+                    }
+                    changed.new.insert(new_line);
+                    new_line += 1;
+                }
+            }
+        }
+
+        changed
+    }
+
+    /// Nothing changed?
+    pub fn is_empty(&self) -> bool {
+        self.old.is_empty() && self.new.is_empty()
+    }
+}
+
+/// Per-test coverage, mapping lines back to the tests that execute them
+#[derive(Debug, Default)]
+pub struct TestImpactIndex {
+    /// File -> line (one-based, as in coverage reports) -> tests
+    files: HashMap<PathBuf, HashMap<u32, HashSet<TestId>>>,
+}
+
+impl TestImpactIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the coverage of a single test run, replacing earlier data
+    pub fn record(&mut self, test_id: &TestId, report: &CoverageReport) {
+        self.remove(test_id);
+        for (path, coverage) in &report.files {
+            let lines = self.files.entry(path.clone()).or_default();
+            for (&line, &hits) in &coverage.lines {
+                if hits > 0 {
+                    lines.entry(line).or_default().insert(test_id.clone());
+                }
+            }
+        }
+    }
+
+    /// Forget a test's coverage
+    pub fn remove(&mut self, test_id: &TestId) {
+        for lines in self.files.values_mut() {
+            lines.retain(|_, tests| {
+                tests.remove(test_id);
+                !tests.is_empty()
+            });
+        }
+        self.files.retain(|_, lines| !lines.is_empty());
+    }
+
+    /// Is any coverage known for a file?
+    pub fn covers(&self, path: &Path) -> bool {
+        self.files.contains_key(path)
+    }
+
+    /// Tests covering any of the given zero-based lines
+    pub fn tests_touching(&self, path: &Path, lines: &BTreeSet<u32>) -> HashSet<TestId> {
+        let Some(covered) = self.files.get(path) else {
+            return HashSet::new();
+        };
+        lines.iter()
+            .filter_map(|line| covered.get(&(line + 1)))
+            .flatten()
+            .cloned()
+            .collect()
+    }
+}
+
+/// Packages and their dependency graph
+pub struct PackageMap {
+    packages: Vec<Package>,
+    graph: DependencyGraph,
+}
+
+impl PackageMap {
+    pub fn new(packages: Vec<Package>) -> anyhow::Result<Self> {
+        let graph = DependencyGraph::build(&packages)?;
+        Ok(Self { packages, graph })
+    }
+
+    /// Innermost package containing a path
+    pub fn package_for(&self, path: &Path) -> Option<&Package> {
+        self.packages.iter()
+            .filter(|p| path.starts_with(&p.path))
+            .max_by_key(|p| p.path.components().count())
+    }
+
+    /// Packages owning the paths, plus everything depending on them
+    pub fn affected(&self, paths: &[PathBuf]) -> anyhow::Result<Vec<&Package>> {
+        let mut names: Vec<String> = paths.iter()
+            .filter_map(|path| self.package_for(path))
+            .map(|p| p.name.clone())
+            .collect();
+        names.sort();
+        names.dedup();
+
+        let dependents = self.graph.dependents(&names)?;
+        Ok(self.packages.iter()
+            .filter(|p| names.contains(&p.name) || dependents.contains(&p.name))
+            .collect())
+    }
+}
+
+/// Turns file changes into the set of tests to re-run
+pub struct TestWatcher {
+    /// Configuration
+    config: RwLock<TestWatchConfig>,
+    /// Workspace packages
+    packages: RwLock<Option<Arc<PackageMap>>>,
+    /// Per-test coverage
+    impact: RwLock<TestImpactIndex>,
+    /// Last seen contents, to work out which lines changed
+    snapshots: RwLock<HashMap<PathBuf, String>>,
+    /// Pending changes
+    debouncer: RwLock<FileChangeDebouncer>,
+}
+
+impl TestWatcher {
+    pub fn new(config: TestWatchConfig) -> Self {
+        Self {
+            debouncer: RwLock::new(FileChangeDebouncer::new(config.debounce)),
+            config: RwLock::new(config),
+            packages: RwLock::new(None),
+            impact: RwLock::new(TestImpactIndex::new()),
+            snapshots: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Get configuration
+    pub fn config(&self) -> TestWatchConfig {
+        self.config.read().clone()
+    }
+
+    /// Configure; pending changes are discarded
+    pub fn configure(&self, config: TestWatchConfig) {
+        *self.debouncer.write() = FileChangeDebouncer::new(config.debounce);
+        *self.config.write() = config;
+    }
+
+    /// Set workspace packages
+    pub fn set_packages(&self, packages: Vec<Package>) -> anyhow::Result<()> {
+        *self.packages.write() = Some(Arc::new(PackageMap::new(packages)?));
+        Ok(())
+    }
+
+    /// Record coverage collected while running a single test
+    pub fn record_coverage(&self, test_id: &TestId, report: &CoverageReport) {
+        self.impact.write().record(test_id, report);
+    }
+
+    /// Remember file contents as the baseline for the next change
+    pub fn snapshot(&self, path: impl Into<PathBuf>, content: impl Into<String>) {
+        self.snapshots.write().insert(path.into(), content.into());
+    }
+
+    /// Lines changed since the last snapshot; the new content becomes the
+    /// snapshot. `None` without a previous snapshot.
+    pub fn changed_lines(&self, path: &Path, content: &str) -> Option<ChangedLines> {
+        let previous = self.snapshots.write().insert(path.to_path_buf(), content.to_string())?;
+        Some(ChangedLines::between(&previous, content))
+    }
+
+    /// Queue a change
+    pub fn add(&self, change: FileChange) {
+        self.debouncer.read().add(change);
+    }
+
+    /// Changes that have been quiet for the debounce period
+    pub fn flush(&self) -> Vec<FileChange> {
+        self.debouncer.read().flush()
+    }
+
+    /// Work out which tests to run for a batch of changes
+    pub async fn plan(&self, changes: &[FileChange], discovery: &TestDiscovery) -> anyhow::Result<Vec<TestId>> {
+        let mode = self.config.read().mode;
+        let mut selected = Selection::default();
+        let mut fallback = Vec::new();
+
+        for change in changes {
+            let path = &change.path;
+            if let FileChangeKind::Renamed { from } = &change.kind {
+                self.snapshots.write().remove(from);
+                discovery.close_buffer(from);
+            }
+
+            let content = match change.kind {
+                FileChangeKind::Deleted => None,
+                _ => tokio::fs::read_to_string(path).await.ok(),
+            };
+            let Some(content) = content else {
+                self.snapshots.write().remove(path);
+                fallback.push(path.clone());
+                continue;
+            };
+
+            let previous_tests = discovery.get_tests(path);
+            let tests = discovery.discover_source(path, &content);
+            let changed = self.changed_lines(path, &content);
+
+            if mode == TestWatchMode::AffectedPackages {
+                fallback.push(path.clone());
+                continue;
+            }
+
+            let Some(changed) = changed else {
+                // First sighting: run the file's tests, or its packages
+                if tests.is_empty() {
+                    fallback.push(path.clone());
+                } else {
+                    selected.extend(tests.iter().map(|t| t.id.clone()));
+                }
+                continue;
+            };
+
+            let covered = self.impact.read().covers(path);
+            if tests.is_empty() && previous_tests.is_none() && !covered {
+                fallback.push(path.clone());
+                continue;
+            }
+
+            touched_tests(&tests, &changed.new, &mut selected);
+            selected.extend(self.impact.read().tests_touching(path, &changed.old));
+        }
+
+        if !fallback.is_empty() {
+            let packages = self.packages.read().clone();
+            match packages {
+                Some(packages) => {
+                    for package in packages.affected(&fallback)? {
+                        selected.extend(discovery.tests_under(&package.path).into_iter().map(|t| t.id));
+                    }
+                }
+                // Without package information any test could be affected
+                None => selected.extend(discovery.all_tests().into_iter().map(|t| t.id)),
+            }
+        }
+
+        Ok(discovery.expand(&selected.tests))
+    }
+}
+
+impl Default for TestWatcher {
+    fn default() -> Self {
+        Self::new(TestWatchConfig::default())
+    }
+}
+
+/// Selected tests in first-seen order
+#[derive(Default)]
+struct Selection {
+    seen: HashSet<TestId>,
+    tests: Vec<TestId>,
+}
+
+impl Selection {
+    fn extend(&mut self, ids: impl IntoIterator<Item = TestId>) {
+        for id in ids {
+            if self.seen.insert(id.clone()) {
+                self.tests.push(id);
+            }
+        }
+    }
+}
+
+/// Innermost items touching changed lines; a change inside a suite but
+/// outside its tests (setup, fixtures) selects the whole suite
+fn touched_tests(items: &[TestItem], lines: &BTreeSet<u32>, selected: &mut Selection) {
+    for item in items {
+        let range = item.range;
+        if lines.range(range.start_line..=range.end_line).next().is_none() {
+            continue;
+        }
+
+        let outside_children = lines.range(range.start_line..=range.end_line)
+            .any(|line| !item.children.iter().any(|child| child.range.contains_line(*line)));
+
+        if outside_children {
+            selected.extend([item.id.clone()]);
+        } else {
+            touched_tests(&item.children, lines, selected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::coverage::FileCoverage;
+    use monorepo::PackageKind;
+
+    fn package(name: &str, path: &str, dependencies: &[&str]) -> Package {
+        Package {
+            name: name.to_string(),
+            version: None,
+            path: PathBuf::from(path),
+            kind: PackageKind::Library,
+            package_manager: None,
+            build_system: None,
+            dependencies: dependencies.iter().map(|d| d.to_string()).collect(),
+            dev_dependencies: vec![],
+            peer_dependencies: vec![],
+            source_files: vec![],
+            entry_points: vec![],
+        }
+    }
+
+    #[test]
+    fn test_changed_lines() {
+        let changed = ChangedLines::between("a\nb\nc\nd", "a\nB\nc\nd\ne");
+        assert_eq!(changed.old, BTreeSet::from([1, 4]));
+        assert_eq!(changed.new, BTreeSet::from([1, 4]));
+    }
+
+    #[test]
+    fn test_affected_packages() {
+        let map = PackageMap::new(vec![
+            package("core", "/repo/core", &[]),
+            package("api", "/repo/api", &["core"]),
+            package("web", "/repo/web", &["api"]),
+            package("docs", "/repo/docs", &[]),
+        ]).unwrap();
+
+        let mut affected: Vec<_> = map.affected(&[PathBuf::from("/repo/api/src/lib.rs")])
+            .unwrap()
+            .into_iter()
+            .map(|p| p.name.as_str())
+            .collect();
+        affected.sort();
+        assert_eq!(affected, vec!["api", "web"]);
+    }
+
+    #[tokio::test]
+    async fn test_plan_changed_lines() {
+        let dir = std::env::temp_dir().join(format!("foxkit-watch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lib.rs");
+        let before = "#[test]\nfn a() {\n}\n\n#[test]\nfn b() {\n}\n";
+        std::fs::write(&file, before).unwrap();
+
+        let discovery = TestDiscovery::with_query_loader(treesitter::QueryLoader::new());
+        let watcher = TestWatcher::new(TestWatchConfig {
+            mode: TestWatchMode::ChangedLines,
+            ..Default::default()
+        });
+        discovery.discover_source(&file, before);
+        watcher.snapshot(&file, before);
+
+        std::fs::write(&file, before.replace("fn b() {\n", "fn b() {\n    assert!(true);\n")).unwrap();
+        let tests = watcher.plan(&[FileChange::modified(file.clone())], &discovery).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(tests, vec![TestId::new(format!("{}::b", file.display()))]);
+    }
+
+    #[test]
+    fn test_impact_index() {
+        let mut report = CoverageReport::default();
+        report.files.insert(PathBuf::from("src/math.rs"), FileCoverage {
+            path: PathBuf::from("src/math.rs"),
+            lines: HashMap::from([(3, 1), (4, 0)]),
+            branches: vec![],
+            functions: vec![],
+        });
+
+        let mut index = TestImpactIndex::new();
+        index.record(&TestId::new("adds"), &report);
+
+        let path = Path::new("src/math.rs");
+        assert!(index.tests_touching(path, &BTreeSet::from([2])).contains(&TestId::new("adds")));
+        assert!(index.tests_touching(path, &BTreeSet::from([3])).is_empty());
+
+        index.remove(&TestId::new("adds"));
+        assert!(!index.covers(path));
+    }
+}
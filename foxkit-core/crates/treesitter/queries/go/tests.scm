@@ -0,0 +1,24 @@
+; Test discovery
+;
+; @test.*, @suite.* and @bench.* pairs mark items (`.definition` is the
+; range, `.name` the label); @parametrized flags the match's item.
+
+((function_declaration
+  name: (identifier) @test.name) @test.definition
+  (#match? @test.name "^(Test|Example|Fuzz)")
+  (#not-eq? @test.name "TestMain"))
+
+((function_declaration
+  name: (identifier) @bench.name) @bench.definition
+  (#match? @bench.name "^Benchmark"))
+
+; Subtests: t.Run("name", func(t *testing.T) { ... })
+((call_expression
+  function: (selector_expression
+    field: (field_identifier) @_run)
+  arguments: (argument_list
+    .
+    [(interpreted_string_literal) (raw_string_literal)] @test.name
+    .
+    (func_literal))) @test.definition
+  (#eq? @_run "Run"))
@@ -0,0 +1,48 @@
+; Test discovery
+;
+; @test.*, @suite.* and @bench.* pairs mark items (`.definition` is the
+; range, `.name` the label); @parametrized flags the match's item.
+
+; describe("name", fn), describe.only("name", fn), ...
+((call_expression
+  function: [
+    (identifier) @_fn
+    (member_expression object: (identifier) @_fn)
+  ]
+  arguments: (arguments . [(string) (template_string)] @suite.name)) @suite.definition
+  (#match? @_fn "^(describe|suite|context)$"))
+
+; describe.each(table)("name %s", fn)
+((call_expression
+  function: (call_expression
+    function: (member_expression
+      object: (identifier) @_fn
+      property: (property_identifier) @_each))
+  arguments: (arguments . [(string) (template_string)] @suite.name)) @suite.definition @parametrized
+  (#match? @_fn "^(describe|suite|context)$")
+  (#eq? @_each "each"))
+
+; it("name", fn), test.skip("name", fn), ...
+((call_expression
+  function: [
+    (identifier) @_fn
+    (member_expression object: (identifier) @_fn)
+  ]
+  arguments: (arguments . [(string) (template_string)] @test.name)) @test.definition
+  (#match? @_fn "^(it|test)$"))
+
+; test.each(table)("name %s", fn)
+((call_expression
+  function: (call_expression
+    function: (member_expression
+      object: (identifier) @_fn
+      property: (property_identifier) @_each))
+  arguments: (arguments . [(string) (template_string)] @test.name)) @test.definition @parametrized
+  (#match? @_fn "^(it|test)$")
+  (#eq? @_each "each"))
+
+; Vitest benchmarks
+((call_expression
+  function: (identifier) @_fn
+  arguments: (arguments . [(string) (template_string)] @bench.name)) @bench.definition
+  (#eq? @_fn "bench"))
@@ -0,0 +1,27 @@
+; Test discovery
+;
+; @test.*, @suite.* and @bench.* pairs mark items (`.definition` is the
+; range, `.name` the label); @parametrized flags the match's item.
+
+((function_definition
+  name: (identifier) @test.name) @test.definition
+  (#match? @test.name "^test"))
+
+; @pytest.mark.parametrize(...)
+((decorated_definition
+  (decorator) @_decorator
+  definition: (function_definition
+    name: (identifier) @test.name)) @test.definition @parametrized
+  (#match? @test.name "^test")
+  (#match? @_decorator "parametrize"))
+
+; pytest classes
+((class_definition
+  name: (identifier) @suite.name) @suite.definition
+  (#match? @suite.name "^Test"))
+
+; unittest.TestCase subclasses
+((class_definition
+  name: (identifier) @suite.name
+  superclasses: (argument_list (_) @_base)) @suite.definition
+  (#match? @_base "TestCase$"))
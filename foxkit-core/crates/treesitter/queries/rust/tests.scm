@@ -0,0 +1,48 @@
+; Test discovery
+;
+; @test.*, @suite.* and @bench.* pairs mark items (`.definition` is the
+; range, `.name` the label); @parametrized flags the match's item;
+; @test.case is one parameter set of the function named by @test.case.of.
+
+; #[test], #[tokio::test], #[rstest], #[test_case(..)], ...
+(
+  (attribute_item) @_attr
+  .
+  [(attribute_item) (line_comment) (block_comment)]*
+  .
+  (function_item name: (identifier) @test.name) @test.definition
+  (#match? @_attr "^#\\[(test|rstest|test_case|[a-z_]+::test)\\b")
+)
+
+(
+  (attribute_item) @_attr
+  .
+  [(attribute_item) (line_comment) (block_comment)]*
+  .
+  (function_item name: (identifier) @test.name) @test.definition @parametrized
+  (#match? @_attr "^#\\[(rstest|test_case)\\b")
+)
+
+; rstest cases
+(
+  (attribute_item) @test.case
+  .
+  [(attribute_item) (line_comment) (block_comment)]*
+  .
+  (function_item name: (identifier) @test.case.of)
+  (#match? @test.case "^#\\[case\\b")
+)
+
+(
+  (attribute_item) @_attr
+  .
+  [(attribute_item) (line_comment) (block_comment)]*
+  .
+  (function_item name: (identifier) @bench.name) @bench.definition
+  (#match? @_attr "^#\\[bench\\]")
+)
+
+; Modules become suites when they contain tests
+(mod_item
+  name: (identifier) @suite.name
+  body: (declaration_list)) @suite.definition
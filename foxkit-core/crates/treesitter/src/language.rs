@@ -45,11 +45,27 @@ impl Language {
             #[cfg(feature = "json")]
             Language::Json => tree_sitter_json::language().into(),
             
+            #[cfg(feature = "go")]
+            Language::Go => tree_sitter_go::language().into(),
+            
             // Fallback for disabled features or unsupported languages
             _ => panic!("Language not available: {:?}", self),
         }
     }
 
+    /// Is a grammar compiled in for this language?
+    pub fn is_available(&self) -> bool {
+        match self {
+            Language::Rust => cfg!(feature = "rust"),
+            Language::JavaScript => cfg!(feature = "javascript"),
+            Language::TypeScript | Language::Tsx => cfg!(feature = "typescript"),
+            Language::Python => cfg!(feature = "python"),
+            Language::Json => cfg!(feature = "json"),
+            Language::Go => cfg!(feature = "go"),
+            _ => false,
+        }
+    }
+
     /// Get language from string ID
     pub fn from_id(id: &str) -> Option<Self> {
         match id {
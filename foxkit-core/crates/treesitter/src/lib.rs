@@ -33,7 +33,7 @@ use std::sync::Arc;
 use parking_lot::RwLock;
 
 pub use language::Language;
-pub use parser::{Parser, Tree, Node};
+pub use parser::{Parser, Tree, Node, InputEdit, Position};
 pub use query::{Query, QueryCapture, QueryMatch};
 pub use query_loader::{QueryLoader, QueryType, LoadedQuery};
 pub use highlight::{Highlighter, HighlightEvent};
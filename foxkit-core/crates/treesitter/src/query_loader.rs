@@ -29,6 +29,8 @@ pub enum QueryType {
     TextObjects,
     /// Runnable detection (tests, main, etc.)
     Runnables,
+    /// Test discovery (test functions, suites, parametrized cases)
+    Tests,
     /// Debugger breakpoints
     Debugger,
     /// Import statements
@@ -48,6 +50,7 @@ impl QueryType {
             QueryType::Outline => "outline.scm",
             QueryType::TextObjects => "textobjects.scm",
             QueryType::Runnables => "runnables.scm",
+            QueryType::Tests => "tests.scm",
             QueryType::Debugger => "debugger.scm",
             QueryType::Imports => "imports.scm",
             QueryType::Overrides => "overrides.scm",
@@ -64,6 +67,7 @@ impl QueryType {
             QueryType::Outline,
             QueryType::TextObjects,
             QueryType::Runnables,
+            QueryType::Tests,
             QueryType::Debugger,
             QueryType::Imports,
             QueryType::Overrides,
@@ -207,18 +211,24 @@ impl QueryLoader {
 
     /// Get fallback/embedded query
     fn get_fallback(&self, language: &str, query_type: QueryType) -> Option<Arc<LoadedQuery>> {
-        // Only provide fallbacks for highlights (the most common need)
-        if query_type != QueryType::Highlights {
-            return None;
-        }
-
-        let source = match language {
-            "rust" => Some(RUST_HIGHLIGHTS_FALLBACK),
-            "javascript" | "typescript" | "tsx" | "jsx" => Some(JS_HIGHLIGHTS_FALLBACK),
-            "python" => Some(PYTHON_HIGHLIGHTS_FALLBACK),
-            "json" => Some(JSON_HIGHLIGHTS_FALLBACK),
-            "toml" => Some(TOML_HIGHLIGHTS_FALLBACK),
-            "markdown" | "md" => Some(MARKDOWN_HIGHLIGHTS_FALLBACK),
+        let source = match query_type {
+            QueryType::Highlights => match language {
+                "rust" => Some(RUST_HIGHLIGHTS_FALLBACK),
+                "javascript" | "typescript" | "tsx" | "jsx" => Some(JS_HIGHLIGHTS_FALLBACK),
+                "python" => Some(PYTHON_HIGHLIGHTS_FALLBACK),
+                "json" => Some(JSON_HIGHLIGHTS_FALLBACK),
+                "toml" => Some(TOML_HIGHLIGHTS_FALLBACK),
+                "markdown" | "md" => Some(MARKDOWN_HIGHLIGHTS_FALLBACK),
+                _ => None,
+            },
+            QueryType::Tests => match language {
+                "rust" => Some(RUST_TESTS_FALLBACK),
+                "javascript" | "jsx" => Some(JS_TESTS_FALLBACK),
+                "typescript" | "typescriptreact" | "tsx" => Some(TS_TESTS_FALLBACK),
+                "python" => Some(PYTHON_TESTS_FALLBACK),
+                "go" => Some(GO_TESTS_FALLBACK),
+                _ => None,
+            },
             _ => None,
         }?;
 
@@ -409,6 +419,12 @@ const MARKDOWN_HIGHLIGHTS_FALLBACK: &str = r#"
 (list_marker_dot) @punctuation
 "#;
 
+const RUST_TESTS_FALLBACK: &str = include_str!("../queries/rust/tests.scm");
+const JS_TESTS_FALLBACK: &str = include_str!("../queries/javascript/tests.scm");
+const TS_TESTS_FALLBACK: &str = include_str!("../queries/typescript/tests.scm");
+const PYTHON_TESTS_FALLBACK: &str = include_str!("../queries/python/tests.scm");
+const GO_TESTS_FALLBACK: &str = include_str!("../queries/go/tests.scm");
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -429,6 +445,15 @@ mod tests {
         assert!(query.unwrap().is_fallback);
     }
 
+    #[test]
+    fn test_tests_fallback() {
+        let loader = QueryLoader::new();
+
+        assert!(loader.load("go", QueryType::Tests).unwrap().is_fallback);
+        assert!(loader.load("tsx", QueryType::Tests).is_some());
+        assert!(loader.load("json", QueryType::Tests).is_none());
+    }
+
     #[test]
     fn test_cache() {
         let loader = QueryLoader::new();
@@ -3,9 +3,10 @@
 //! Workspace security and trust management.
 
 use std::collections::{HashMap, HashSet};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use tokio::sync::broadcast;
 
 /// Workspace trust service
@@ -20,6 +21,19 @@ pub struct WorkspaceTrustService {
     events: broadcast::Sender<WorkspaceTrustEvent>,
     /// Configuration
     config: RwLock<WorkspaceTrustConfig>,
+    /// Decisions on environment files, keyed by path
+    env_files: RwLock<HashMap<PathBuf, EnvFileDecision>>,
+    /// File the environment file decisions are saved to
+    env_files_store: RwLock<Option<PathBuf>>,
+}
+
+/// A decision on an environment file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum EnvFileDecision {
+    /// Allowed with this SHA-256 content hash
+    Allowed { sha256: String },
+    Denied,
 }
 
 impl WorkspaceTrustService {
@@ -32,6 +46,8 @@ impl WorkspaceTrustService {
             state: RwLock::new(WorkspaceTrustState::Unknown),
             events,
             config: RwLock::new(WorkspaceTrustConfig::default()),
+            env_files: RwLock::new(HashMap::new()),
+            env_files_store: RwLock::new(None),
         }
     }
 
@@ -146,6 +162,61 @@ impl WorkspaceTrustService {
         self.untrusted.read().iter().cloned().collect()
     }
 
+    /// Check whether an environment file (`.env`, `.envrc`) may be loaded.
+    /// Like `direnv allow`, approval covers the content it was given for;
+    /// editing the file needs a new approval
+    pub fn env_file_approval(&self, path: &Path, content: &[u8]) -> EnvFileApproval {
+        match self.env_files.read().get(path) {
+            Some(EnvFileDecision::Denied) => EnvFileApproval::Denied,
+            Some(EnvFileDecision::Allowed { sha256 }) if *sha256 == content_hash(content) => EnvFileApproval::Allowed,
+            _ => EnvFileApproval::Pending,
+        }
+    }
+
+    /// Ask the user to allow an environment file
+    pub fn request_env_file_approval(&self, path: PathBuf) {
+        let _ = self.events.send(WorkspaceTrustEvent::EnvFileApprovalRequested { path });
+    }
+
+    /// Allow an environment file with its current content
+    pub fn allow_env_file(&self, path: PathBuf, content: &[u8]) {
+        self.env_files.write().insert(path.clone(), EnvFileDecision::Allowed { sha256: content_hash(content) });
+        self.save_env_files();
+        let _ = self.events.send(WorkspaceTrustEvent::EnvFileAllowed { path });
+    }
+
+    /// Deny an environment file until it is allowed again
+    pub fn deny_env_file(&self, path: PathBuf) {
+        self.env_files.write().insert(path.clone(), EnvFileDecision::Denied);
+        self.save_env_files();
+        let _ = self.events.send(WorkspaceTrustEvent::EnvFileDenied { path });
+    }
+
+    /// Load environment file decisions saved in `path`, if it exists, and
+    /// save later decisions there
+    pub fn load_env_files(&self, path: PathBuf) -> anyhow::Result<()> {
+        if path.exists() {
+            let decisions: HashMap<PathBuf, EnvFileDecision> = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+            self.env_files.write().extend(decisions);
+        }
+        *self.env_files_store.write() = Some(path);
+        Ok(())
+    }
+
+    fn save_env_files(&self) {
+        let Some(path) = self.env_files_store.read().clone() else { return };
+        let result = (|| -> anyhow::Result<()> {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(&path, serde_json::to_string_pretty(&*self.env_files.read())?)?;
+            Ok(())
+        })();
+        if let Err(e) = result {
+            tracing::warn!("Failed to save environment file decisions to {}: {}", path.display(), e);
+        }
+    }
+
     /// Check if feature requires trust
     pub fn requires_trust(&self, feature: &str) -> bool {
         let config = self.config.read();
@@ -168,6 +239,21 @@ impl Default for WorkspaceTrustService {
     }
 }
 
+fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Whether an environment file may be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EnvFileApproval {
+    /// Allowed with its current content
+    Allowed,
+    /// Explicitly denied
+    Denied,
+    /// New or changed since it was allowed; needs a decision
+    Pending,
+}
+
 /// Workspace trust state
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum WorkspaceTrustState {
@@ -253,6 +339,9 @@ pub enum WorkspaceTrustEvent {
     StateChanged { state: WorkspaceTrustState },
     TrustGranted { folder: PathBuf },
     TrustRevoked { folder: PathBuf },
+    EnvFileApprovalRequested { path: PathBuf },
+    EnvFileAllowed { path: PathBuf },
+    EnvFileDenied { path: PathBuf },
 }
 
 /// Trust request
@@ -366,3 +455,33 @@ pub fn builtin_restricted_features() -> Vec<RestrictedFeature> {
             .with_description("Automatic git fetch operations"),
     ]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_file_decisions_persist() {
+        let dir = std::env::temp_dir().join(format!("foxkit-trust-{}", std::process::id()));
+        let store = dir.join("env-files.json");
+        let envrc = dir.join(".envrc");
+
+        let trust = WorkspaceTrustService::new();
+        trust.load_env_files(store.clone()).unwrap();
+        trust.allow_env_file(envrc.clone(), b"export A=1\n");
+        trust.deny_env_file(dir.join(".env"));
+
+        // The content hash is SHA-256, so it survives toolchain upgrades
+        let saved = std::fs::read_to_string(&store).unwrap();
+        assert!(saved.contains(&content_hash(b"export A=1\n")));
+        assert_eq!(content_hash(b"abc"), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+
+        let restarted = WorkspaceTrustService::new();
+        restarted.load_env_files(store).unwrap();
+        assert_eq!(restarted.env_file_approval(&envrc, b"export A=1\n"), EnvFileApproval::Allowed);
+        assert_eq!(restarted.env_file_approval(&envrc, b"export A=2\n"), EnvFileApproval::Pending);
+        assert_eq!(restarted.env_file_approval(&dir.join(".env"), b""), EnvFileApproval::Denied);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}